@@ -0,0 +1 @@
+DROP TABLE build_hosts;
\ No newline at end of file
@@ -0,0 +1,5 @@
+CREATE TABLE build_hosts (
+    artifact_ident TEXT NOT NULL,
+    host TEXT NOT NULL,
+    PRIMARY KEY (artifact_ident)
+);
\ No newline at end of file
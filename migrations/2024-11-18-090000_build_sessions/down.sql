@@ -0,0 +1 @@
+DROP TABLE build_session_artifacts;
\ No newline at end of file
@@ -0,0 +1,6 @@
+CREATE TABLE build_session_artifacts (
+    session_id TEXT NOT NULL,
+    artifact TEXT NOT NULL,
+    error_count INTEGER NOT NULL,
+    PRIMARY KEY (session_id, artifact)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE build_session_artifacts DROP COLUMN artifact_path;
\ No newline at end of file
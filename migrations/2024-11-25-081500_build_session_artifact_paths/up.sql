@@ -0,0 +1 @@
+ALTER TABLE build_session_artifacts ADD COLUMN artifact_path TEXT;
\ No newline at end of file
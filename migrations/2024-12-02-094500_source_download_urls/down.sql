@@ -0,0 +1 @@
+DROP TABLE source_download_urls;
\ No newline at end of file
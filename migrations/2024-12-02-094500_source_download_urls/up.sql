@@ -0,0 +1,5 @@
+CREATE TABLE source_download_urls (
+    hash TEXT NOT NULL,
+    url TEXT NOT NULL,
+    PRIMARY KEY (hash)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE build_claims;
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE build_claims (
+    build_id TEXT NOT NULL,
+    plan TEXT NOT NULL,
+    worker TEXT NOT NULL,
+    status TEXT NOT NULL,
+    artifact TEXT,
+    lease_expires_at TEXT NOT NULL,
+    PRIMARY KEY (build_id, plan)
+);
\ No newline at end of file
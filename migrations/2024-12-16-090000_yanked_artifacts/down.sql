@@ -0,0 +1 @@
+DROP TABLE yanked_artifacts;
\ No newline at end of file
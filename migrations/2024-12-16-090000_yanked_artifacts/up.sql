@@ -0,0 +1,5 @@
+CREATE TABLE yanked_artifacts (
+    artifact TEXT NOT NULL PRIMARY KEY,
+    reason TEXT,
+    yanked_at TEXT NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE build_step_states;
\ No newline at end of file
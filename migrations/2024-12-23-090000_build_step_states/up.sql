@@ -0,0 +1,7 @@
+CREATE TABLE build_step_states (
+    session_id TEXT NOT NULL,
+    plan TEXT NOT NULL,
+    status TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (session_id, plan)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE plan_context_hashes;
\ No newline at end of file
@@ -0,0 +1,5 @@
+CREATE TABLE plan_context_hashes (
+    artifact_ident TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    PRIMARY KEY (artifact_ident)
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+DROP TABLE build_sessions;
+ALTER TABLE build_session_artifacts DROP COLUMN warning_count;
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE build_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    session_id TEXT NOT NULL UNIQUE,
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    planned INTEGER NOT NULL,
+    attempted INTEGER NOT NULL DEFAULT 0,
+    succeeded INTEGER NOT NULL DEFAULT 0,
+    failed INTEGER NOT NULL DEFAULT 0,
+    error_count INTEGER NOT NULL DEFAULT 0,
+    warning_count INTEGER NOT NULL DEFAULT 0
+);
+ALTER TABLE build_session_artifacts ADD COLUMN warning_count INTEGER NOT NULL DEFAULT 0;
\ No newline at end of file
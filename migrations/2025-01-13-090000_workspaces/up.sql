@@ -0,0 +1,69 @@
+CREATE TABLE file_modifications_new (
+    workspace TEXT NOT NULL DEFAULT '',
+    plan_context_path TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    real_modified_at TEXT NOT NULL,
+    alternate_modified_at TEXT NOT NULL,
+    PRIMARY KEY (workspace, plan_context_path, file_path)
+);
+INSERT INTO file_modifications_new (plan_context_path, file_path, real_modified_at, alternate_modified_at)
+    SELECT plan_context_path, file_path, real_modified_at, alternate_modified_at FROM file_modifications;
+DROP TABLE file_modifications;
+ALTER TABLE file_modifications_new RENAME TO file_modifications;
+
+CREATE TABLE build_times_new (
+    workspace TEXT NOT NULL DEFAULT '',
+    build_ident TEXT NOT NULL,
+    duration_in_secs INTEGER NOT NULL,
+    PRIMARY KEY (workspace, build_ident)
+);
+INSERT INTO build_times_new (build_ident, duration_in_secs)
+    SELECT build_ident, duration_in_secs FROM build_times;
+DROP TABLE build_times;
+ALTER TABLE build_times_new RENAME TO build_times;
+
+CREATE TABLE build_sessions_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    workspace TEXT NOT NULL DEFAULT '',
+    session_id TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    planned INTEGER NOT NULL,
+    attempted INTEGER NOT NULL DEFAULT 0,
+    succeeded INTEGER NOT NULL DEFAULT 0,
+    failed INTEGER NOT NULL DEFAULT 0,
+    error_count INTEGER NOT NULL DEFAULT 0,
+    warning_count INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (workspace, session_id)
+);
+INSERT INTO build_sessions_new (id, session_id, started_at, ended_at, planned, attempted, succeeded, failed, error_count, warning_count)
+    SELECT id, session_id, started_at, ended_at, planned, attempted, succeeded, failed, error_count, warning_count FROM build_sessions;
+DROP TABLE build_sessions;
+ALTER TABLE build_sessions_new RENAME TO build_sessions;
+
+CREATE TABLE build_session_artifacts_new (
+    workspace TEXT NOT NULL DEFAULT '',
+    session_id TEXT NOT NULL,
+    artifact TEXT NOT NULL,
+    error_count INTEGER NOT NULL,
+    artifact_path TEXT,
+    warning_count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (workspace, session_id, artifact)
+);
+INSERT INTO build_session_artifacts_new (session_id, artifact, error_count, artifact_path, warning_count)
+    SELECT session_id, artifact, error_count, artifact_path, warning_count FROM build_session_artifacts;
+DROP TABLE build_session_artifacts;
+ALTER TABLE build_session_artifacts_new RENAME TO build_session_artifacts;
+
+CREATE TABLE build_step_states_new (
+    workspace TEXT NOT NULL DEFAULT '',
+    session_id TEXT NOT NULL,
+    plan TEXT NOT NULL,
+    status TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (workspace, session_id, plan)
+);
+INSERT INTO build_step_states_new (session_id, plan, status, updated_at)
+    SELECT session_id, plan, status, updated_at FROM build_step_states;
+DROP TABLE build_step_states;
+ALTER TABLE build_step_states_new RENAME TO build_step_states;
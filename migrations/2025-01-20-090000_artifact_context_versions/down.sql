@@ -0,0 +1 @@
+ALTER TABLE artifact_contexts DROP COLUMN version;
@@ -0,0 +1 @@
+ALTER TABLE artifact_contexts ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
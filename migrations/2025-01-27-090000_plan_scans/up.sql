@@ -0,0 +1,5 @@
+CREATE TABLE plan_scans (
+    plan_path TEXT NOT NULL PRIMARY KEY,
+    hash TEXT NOT NULL,
+    data TEXT NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE file_modifications DROP COLUMN synced_from_modified_at;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE file_modifications ADD COLUMN synced_from_modified_at TEXT;
\ No newline at end of file
@@ -0,0 +1,5 @@
+CREATE TABLE vulnerability_queries (
+    query TEXT NOT NULL PRIMARY KEY,
+    vulnerabilities TEXT NOT NULL,
+    fetched_at TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Files of the artifacts indexed before this migration are recorded the next
+-- time they are requested, by reading them from the artifacts
+CREATE TABLE artifact_files (
+    hash TEXT NOT NULL PRIMARY KEY,
+    files TEXT NOT NULL
+);
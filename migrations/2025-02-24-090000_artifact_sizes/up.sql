@@ -0,0 +1,5 @@
+CREATE TABLE artifact_sizes (
+    artifact TEXT NOT NULL PRIMARY KEY,
+    installed_size BIGINT NOT NULL,
+    closure_size BIGINT NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE build_disk_usages (
+    workspace TEXT NOT NULL,
+    package TEXT NOT NULL,
+    disk_usage BIGINT NOT NULL,
+    PRIMARY KEY (workspace, package)
+);
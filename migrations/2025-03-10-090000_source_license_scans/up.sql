@@ -0,0 +1,6 @@
+CREATE TABLE source_license_scans (
+    hash TEXT NOT NULL,
+    scanner TEXT NOT NULL,
+    licenses TEXT NOT NULL,
+    PRIMARY KEY (hash, scanner)
+);
@@ -0,0 +1 @@
+ALTER TABLE build_claims DROP COLUMN released_at;
@@ -0,0 +1 @@
+ALTER TABLE build_claims ADD COLUMN released_at TEXT;
@@ -1,4 +1,8 @@
-use std::{collections::HashSet, fmt::Display, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    path::PathBuf,
+};
 
 use owo_colors::OwoColorize;
 use path_absolutize::Absolutize;
@@ -10,7 +14,10 @@ use crate::{
         ArtifactCheck, ArtifactCheckViolation, ArtifactRuleOptions, CheckerContext,
         LeveledArtifactCheckViolation, PlanContextConfig, ViolationLevel,
     },
-    core::{ArtifactCache, ArtifactContext, ElfType, GlobSetExpression, PackageIdent, PackagePath},
+    core::{
+        ArtifactCache, ArtifactContext, ElfMetadata, ElfType, GlobSetExpression, PackageIdent,
+        PackagePath,
+    },
     store::Store,
 };
 
@@ -43,6 +50,8 @@ pub(crate) enum ElfRule {
     MissingELFInterpreterDependency(MissingELFInterpreterDependency),
     #[serde(rename = "unexpected-elf-interpreter")]
     UnexpectedELFInterpreter(UnexpectedELFInterpreter),
+    #[serde(rename = "missing-symbol-version")]
+    MissingSymbolVersion(MissingSymbolVersion),
 }
 
 impl Display for ElfRule {
@@ -61,6 +70,7 @@ impl Display for ElfRule {
             ElfRule::ELFInterpreterNotFound(rule) => write!(f, "{}", rule),
             ElfRule::MissingELFInterpreterDependency(rule) => write!(f, "{}", rule),
             ElfRule::UnexpectedELFInterpreter(rule) => write!(f, "{}", rule),
+            ElfRule::MissingSymbolVersion(rule) => write!(f, "{}", rule),
         }
     }
 }
@@ -94,6 +104,8 @@ pub(crate) enum ElfRuleOptions {
     MissingELFInterpreterDependency(MissingELFInterpreterDependencyOptions),
     #[serde(rename = "unexpected-elf-interpreter")]
     UnexpectedELFInterpreter(UnexpectedELFInterpreterOptions),
+    #[serde(rename = "missing-symbol-version")]
+    MissingSymbolVersion(MissingSymbolVersionOptions),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -442,6 +454,7 @@ impl Default for BadLibraryDependencyOptions {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct MissingELFInterpreter {
     pub source: PathBuf,
@@ -461,6 +474,7 @@ impl Display for MissingELFInterpreter {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct MissingELFInterpreterOptions {
     #[serde(default = "MissingELFInterpreterOptions::level")]
@@ -470,6 +484,7 @@ pub(crate) struct MissingELFInterpreterOptions {
 }
 
 impl MissingELFInterpreterOptions {
+    #[allow(dead_code)]
     fn level() -> ViolationLevel {
         ViolationLevel::Error
     }
@@ -689,6 +704,54 @@ impl Default for UnexpectedELFInterpreterOptions {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MissingSymbolVersion {
+    pub source: PathBuf,
+    pub library: String,
+    pub library_path: PathBuf,
+    pub version: String,
+}
+
+impl Display for MissingSymbolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The symbol version {} required from the library {} is not provided by {}, the binary may have been linked against a library from the host",
+            self.source
+                .relative_package_path()
+                .unwrap()
+                .display()
+                .white(),
+            self.version.yellow(),
+            self.library.yellow(),
+            self.library_path.display().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MissingSymbolVersionOptions {
+    #[serde(default = "MissingSymbolVersionOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl MissingSymbolVersionOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for MissingSymbolVersionOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct ElfCheck {}
 
@@ -721,7 +784,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
         let bad_rpath_entry_options = rules
             .artifact_rules
@@ -735,7 +798,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let unused_rpath_entry_options = rules
@@ -750,7 +813,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let missing_runpath_entry_dependency_options = rules
@@ -766,7 +829,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let bad_runpath_entry_options = rules
@@ -781,7 +844,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let unused_runpath_entry_options = rules
@@ -796,7 +859,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let library_dependency_not_found_options = rules
@@ -812,7 +875,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let bad_library_dependency_options = rules
@@ -827,7 +890,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let bad_elf_interpreter_options = rules
@@ -842,7 +905,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let host_elf_interpreter_options = rules
@@ -857,7 +920,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let elf_interpreter_not_found_options = rules
@@ -872,7 +935,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let missing_elf_interpreter_dependency_options = rules
@@ -888,7 +951,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let unexpected_elf_interpreter_options = rules
@@ -903,7 +966,22 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
+            .expect("Default rule missing");
+
+        let missing_symbol_version_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Elf(ElfRuleOptions::MissingSymbolVersion(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
             .expect("Default rule missing");
 
         for (path, metadata) in artifact_context.elfs.iter() {
@@ -1060,6 +1138,7 @@ impl ArtifactCheck for ElfCheck {
                     None
                 }
             });
+            let mut resolved_libraries: HashMap<&String, (PathBuf, &ElfMetadata)> = HashMap::new();
             for library in metadata.required_libraries.iter() {
                 let mut found = false;
                 // If the library is the interpreter skip it
@@ -1106,6 +1185,8 @@ impl ArtifactCheck for ElfCheck {
                                             search_path.display()
                                         );
                                         unused_rpath_entries.remove(&search_path);
+                                        resolved_libraries
+                                            .insert(library, (library_path.clone(), metadata));
                                         used_deps.insert(artifact.id.clone());
                                         break;
                                     }
@@ -1215,6 +1296,8 @@ impl ArtifactCheck for ElfCheck {
                                             search_path.display()
                                         );
                                             unused_runpath_entries.remove(&search_path);
+                                            resolved_libraries
+                                                .insert(library, (library_path.clone(), metadata));
                                             used_deps.insert(artifact.id.clone());
                                             break;
                                         }
@@ -1325,6 +1408,8 @@ impl ArtifactCheck for ElfCheck {
                                             path.display(),
                                             search_path.display()
                                         );
+                                            resolved_libraries
+                                                .insert(library, (library_path.clone(), metadata));
                                             used_deps.insert(artifact.id.clone());
                                         }
                                         ElfType::Executable
@@ -1380,6 +1465,35 @@ impl ArtifactCheck for ElfCheck {
                     });
                 }
             }
+            if !missing_symbol_version_options
+                .ignored_files
+                .is_match(path.relative_package_path().unwrap())
+            {
+                for (library, versions) in metadata.required_symbol_versions.iter() {
+                    let Some((library_path, library_metadata)) = resolved_libraries.get(library)
+                    else {
+                        continue;
+                    };
+                    // Libraries without any version definitions, or indexed by an older
+                    // version of hab-auto-build, cannot be checked
+                    if library_metadata.provided_symbol_versions.is_empty() {
+                        continue;
+                    }
+                    for version in versions.difference(&library_metadata.provided_symbol_versions) {
+                        violations.push(LeveledArtifactCheckViolation {
+                            level: missing_symbol_version_options.level,
+                            violation: ArtifactCheckViolation::Elf(ElfRule::MissingSymbolVersion(
+                                MissingSymbolVersion {
+                                    source: path.clone(),
+                                    library: library.clone(),
+                                    library_path: library_path.clone(),
+                                    version: version.clone(),
+                                },
+                            )),
+                        });
+                    }
+                }
+            }
             if !unused_rpath_entries.is_empty()
                 && !unused_rpath_entry_options
                     .ignored_files
@@ -1,25 +1,37 @@
-use std::{collections::BTreeSet, fmt::Display, path::PathBuf};
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
 #[cfg(not(target_os = "windows"))]
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, BTreeMap, HashMap},
     ffi::OsString,
 };
 
 use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
+use tracing::error;
 
 use crate::{
     check::{
         ArtifactCheck, CheckerContext, LeveledArtifactCheckViolation, PlanContextConfig,
         ViolationLevel,
     },
-    core::{ArtifactCache, ArtifactContext, PackageDepGlob, PackageIdent, PackagePath},
+    core::{
+        ArtifactCache, ArtifactContext, GlobSetExpression, PackageDepGlob, PackageIdent,
+        PackagePath, PackageResolvedDepIdent, PackageTarget, PackageType, PlanContext, SETGID_MODE,
+        SETUID_MODE, WORLD_WRITABLE_MODE,
+    },
     store::Store,
 };
 
 #[cfg(not(target_os = "windows"))]
-use crate::check::{ArtifactCheckViolation, ArtifactRuleOptions};
+use crate::{
+    check::{ArtifactCheckViolation, ArtifactRuleOptions},
+    core::ElfType,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "rule", content = "metadata")]
@@ -40,6 +52,34 @@ pub(crate) enum PackageRule {
     UnusedDependency(UnusedDependency),
     #[serde(rename = "duplicate-runtime-binary")]
     DuplicateRuntimeBinary(DuplicateRuntimeBinary),
+    #[serde(rename = "conflicting-runtime-file")]
+    ConflictingRuntimeFile(ConflictingRuntimeFile),
+    #[serde(rename = "runtime-path-ordering")]
+    RuntimePathOrdering(RuntimePathOrdering),
+    #[serde(rename = "bad-pkg-config-path-entry")]
+    BadPkgConfigPathEntry(BadPkgConfigPathEntry),
+    #[serde(rename = "empty-pkg-config-path-entry")]
+    EmptyPkgConfigPathEntry(EmptyPkgConfigPathEntry),
+    #[serde(rename = "missing-pkg-config-path-entry")]
+    MissingPkgConfigPathEntry(MissingPkgConfigPathEntry),
+    #[serde(rename = "removed-dependency-still-used")]
+    RemovedDependencyStillUsed(RemovedDependencyStillUsed),
+    #[serde(rename = "plan-source-mismatch")]
+    PlanSourceMismatch(PlanSourceMismatch),
+    #[serde(rename = "unsafe-permissions")]
+    UnsafePermissions(UnsafePermissions),
+    #[serde(rename = "host-path-reference")]
+    HostPathReference(HostPathReference),
+    #[serde(rename = "bad-cmake-config-path")]
+    BadCMakeConfigPath(BadCMakeConfigPath),
+    #[serde(rename = "static-library")]
+    StaticLibrary(StaticLibrary),
+    #[serde(rename = "unstripped-binary")]
+    UnstrippedBinary(UnstrippedBinary),
+    #[serde(rename = "max-artifact-size-exceeded")]
+    MaxArtifactSizeExceeded(MaxArtifactSizeExceeded),
+    #[serde(rename = "target-metafile-mismatch")]
+    TargetMetafileMismatch(TargetMetafileMismatch),
 }
 
 impl Display for PackageRule {
@@ -53,6 +93,20 @@ impl Display for PackageRule {
             PackageRule::BrokenLink(rule) => write!(f, "{}", rule),
             PackageRule::UnusedDependency(rule) => write!(f, "{}", rule),
             PackageRule::DuplicateRuntimeBinary(rule) => write!(f, "{}", rule),
+            PackageRule::ConflictingRuntimeFile(rule) => write!(f, "{}", rule),
+            PackageRule::RuntimePathOrdering(rule) => write!(f, "{}", rule),
+            PackageRule::BadPkgConfigPathEntry(rule) => write!(f, "{}", rule),
+            PackageRule::EmptyPkgConfigPathEntry(rule) => write!(f, "{}", rule),
+            PackageRule::MissingPkgConfigPathEntry(rule) => write!(f, "{}", rule),
+            PackageRule::RemovedDependencyStillUsed(rule) => write!(f, "{}", rule),
+            PackageRule::PlanSourceMismatch(rule) => write!(f, "{}", rule),
+            PackageRule::UnsafePermissions(rule) => write!(f, "{}", rule),
+            PackageRule::HostPathReference(rule) => write!(f, "{}", rule),
+            PackageRule::BadCMakeConfigPath(rule) => write!(f, "{}", rule),
+            PackageRule::StaticLibrary(rule) => write!(f, "{}", rule),
+            PackageRule::UnstrippedBinary(rule) => write!(f, "{}", rule),
+            PackageRule::MaxArtifactSizeExceeded(rule) => write!(f, "{}", rule),
+            PackageRule::TargetMetafileMismatch(rule) => write!(f, "{}", rule),
         }
     }
 }
@@ -76,6 +130,34 @@ pub(crate) enum PackageRuleOptions {
     UnusedDependency(UnusedDependencyOptions),
     #[serde(rename = "duplicate-runtime-binary")]
     DuplicateRuntimeBinary(DuplicateRuntimeBinaryOptions),
+    #[serde(rename = "conflicting-runtime-file")]
+    ConflictingRuntimeFile(ConflictingRuntimeFileOptions),
+    #[serde(rename = "runtime-path-ordering")]
+    RuntimePathOrdering(RuntimePathOrderingOptions),
+    #[serde(rename = "bad-pkg-config-path-entry")]
+    BadPkgConfigPathEntry(BadPkgConfigPathEntryOptions),
+    #[serde(rename = "empty-pkg-config-path-entry")]
+    EmptyPkgConfigPathEntry(EmptyPkgConfigPathEntryOptions),
+    #[serde(rename = "missing-pkg-config-path-entry")]
+    MissingPkgConfigPathEntry(MissingPkgConfigPathEntryOptions),
+    #[serde(rename = "removed-dependency-still-used")]
+    RemovedDependencyStillUsed(RemovedDependencyStillUsedOptions),
+    #[serde(rename = "plan-source-mismatch")]
+    PlanSourceMismatch(PlanSourceMismatchOptions),
+    #[serde(rename = "unsafe-permissions")]
+    UnsafePermissions(UnsafePermissionsOptions),
+    #[serde(rename = "host-path-reference")]
+    HostPathReference(HostPathReferenceOptions),
+    #[serde(rename = "bad-cmake-config-path")]
+    BadCMakeConfigPath(BadCMakeConfigPathOptions),
+    #[serde(rename = "static-library")]
+    StaticLibrary(StaticLibraryOptions),
+    #[serde(rename = "unstripped-binary")]
+    UnstrippedBinary(UnstrippedBinaryOptions),
+    #[serde(rename = "max-artifact-size-exceeded")]
+    MaxArtifactSizeExceeded(MaxArtifactSizeExceededOptions),
+    #[serde(rename = "target-metafile-mismatch")]
+    TargetMetafileMismatch(TargetMetafileMismatchOptions),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -333,6 +415,997 @@ impl Default for DuplicateRuntimeBinaryOptions {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ConflictingFileKind {
+    /// Shared library required by a binary of the runtime closure
+    #[serde(rename = "library")]
+    Library,
+    /// File inside a directory on the runtime path
+    #[serde(rename = "file")]
+    File,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ConflictingRuntimeFile {
+    pub kind: ConflictingFileKind,
+    /// Library soname, or path of the file relative to the package directory
+    pub file: PathBuf,
+    /// Packages shipping the file, in the order of the runtime path
+    pub providers: Vec<PackageIdent>,
+    /// Package whose file is used, if it can be determined from the runtime path
+    pub winner: Option<PackageIdent>,
+}
+
+impl Display for ConflictingRuntimeFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            ConflictingFileKind::Library => "library",
+            ConflictingFileKind::File => "file",
+        };
+        let providers = self
+            .providers
+            .iter()
+            .map(|provider| provider.blue().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &self.winner {
+            Some(winner) => write!(
+                f,
+                "The {} {} is provided by {}, {} comes first in the runtime path",
+                kind,
+                self.file.display().yellow(),
+                providers,
+                winner.green()
+            ),
+            None => write!(
+                f,
+                "The {} {} is provided by {}, none of which are in the runtime path so the one used depends on the RUNPATH of each binary",
+                kind,
+                self.file.display().yellow(),
+                providers
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct ConflictingRuntimeFileOptions {
+    #[serde(default = "ConflictingRuntimeFileOptions::level")]
+    pub level: ViolationLevel,
+    /// Packages whose files are allowed to shadow the same files of other packages
+    #[serde(default)]
+    pub primary_packages: BTreeSet<PackageDepGlob>,
+    /// Library sonames and file paths relative to the package directory to skip
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl ConflictingRuntimeFileOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for ConflictingRuntimeFileOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            primary_packages: BTreeSet::default(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RuntimePathOrdering {
+    /// Entries without any executables
+    pub empty_entries: Vec<PathBuf>,
+    /// Entries whose executables are all found in earlier entries first
+    pub shadowed_entries: Vec<PathBuf>,
+    /// Runtime path without the empty and shadowed entries, which resolves every
+    /// executable to the same file as the current runtime path
+    pub suggested_runtime_path: Vec<PathBuf>,
+}
+
+impl Display for RuntimePathOrdering {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The runtime path can be reduced to {} entries",
+            self.suggested_runtime_path.len().yellow()
+        )?;
+        if !self.empty_entries.is_empty() {
+            write!(
+                f,
+                ", {} do not contain any executables",
+                self.empty_entries
+                    .iter()
+                    .map(|entry| entry.display().blue().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        if !self.shadowed_entries.is_empty() {
+            write!(
+                f,
+                ", the executables of {} are all shadowed by earlier entries",
+                self.shadowed_entries
+                    .iter()
+                    .map(|entry| entry.display().blue().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        write!(
+            f,
+            ", suggested runtime path: {}",
+            self.suggested_runtime_path
+                .iter()
+                .map(|entry| entry.display().to_string())
+                .collect::<Vec<_>>()
+                .join(":")
+                .green()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct RuntimePathOrderingOptions {
+    #[serde(default = "RuntimePathOrderingOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl RuntimePathOrderingOptions {
+    /// The rule is opt-in, a plan enables it by setting the level to 'suggest'
+    fn level() -> ViolationLevel {
+        ViolationLevel::Off
+    }
+}
+
+impl Default for RuntimePathOrderingOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BadPkgConfigPathEntry {
+    pub entry: PathBuf,
+}
+
+impl Display for BadPkgConfigPathEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The pkg-config path entry {} does not belong to this package",
+            self.entry.display().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct BadPkgConfigPathEntryOptions {
+    pub level: ViolationLevel,
+}
+
+impl Default for BadPkgConfigPathEntryOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Warn,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct EmptyPkgConfigPathEntry {
+    pub entry: PathBuf,
+}
+
+impl Display for EmptyPkgConfigPathEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The pkg-config path entry {} does not exist or does not contain any .pc files",
+            self.entry.display().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct EmptyPkgConfigPathEntryOptions {
+    pub level: ViolationLevel,
+}
+
+impl Default for EmptyPkgConfigPathEntryOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Warn,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MissingPkgConfigPathEntry {
+    pub entry: PathBuf,
+}
+
+impl Display for MissingPkgConfigPathEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The directory {} contains .pc files but is not declared in the pkg-config path, add it to 'pkg_pconfig_dirs'",
+            self.entry.display().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MissingPkgConfigPathEntryOptions {
+    pub level: ViolationLevel,
+}
+
+impl Default for MissingPkgConfigPathEntryOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Warn,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RemovedDependencyStillUsed {
+    pub source: PathBuf,
+    pub entry: PathBuf,
+    pub dep_ident: PackageIdent,
+}
+
+impl Display for RemovedDependencyStillUsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The dependency {} was removed from the plan but {} still uses {} from it in the current artifact",
+            self.dep_ident.yellow(),
+            self.source.display().white(),
+            self.entry.display().blue()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct RemovedDependencyStillUsedOptions {
+    pub level: ViolationLevel,
+}
+
+impl Default for RemovedDependencyStillUsedOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PlanSourceMismatch {
+    pub plan: PathBuf,
+}
+
+impl Display for PlanSourceMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The artifact was built from a plan source that differs from {}, it may have been built from another branch or uncommitted changes",
+            self.plan.display().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct PlanSourceMismatchOptions {
+    pub level: ViolationLevel,
+}
+
+impl Default for PlanSourceMismatchOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Warn,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnsafePermissions {
+    pub entry: PathBuf,
+    pub mode: u32,
+}
+
+impl Display for UnsafePermissions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let permissions = [
+            (SETUID_MODE, "setuid"),
+            (SETGID_MODE, "setgid"),
+            (WORLD_WRITABLE_MODE, "world-writable"),
+        ]
+        .into_iter()
+        .filter(|(bits, _)| self.mode & bits != 0)
+        .map(|(_, permission)| permission)
+        .collect::<Vec<_>>();
+        write!(
+            f,
+            "{}: The file has {} permissions (mode {:o})",
+            self.entry
+                .relative_package_path()
+                .unwrap()
+                .display()
+                .white(),
+            permissions.join(" and ").yellow(),
+            self.mode & 0o7777
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnsafePermissionsOptions {
+    #[serde(default = "UnsafePermissionsOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl UnsafePermissionsOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for UnsafePermissionsOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct HostPathReference {
+    pub source: PathBuf,
+    pub entry: PathBuf,
+}
+
+impl Display for HostPathReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The file refers to the host path {} which does not belong to a habitat package",
+            self.source
+                .relative_package_path()
+                .unwrap()
+                .display()
+                .white(),
+            self.entry.display().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct HostPathReferenceOptions {
+    #[serde(default = "HostPathReferenceOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+    #[serde(default)]
+    pub ignored_entries: GlobSetExpression,
+}
+
+impl HostPathReferenceOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for HostPathReferenceOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+            ignored_entries: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BadCMakeConfigPath {
+    pub source: PathBuf,
+    pub entry: PathBuf,
+    /// Package the path belongs to, if it belongs to a package that is not a dependency
+    pub dep_ident: Option<PackageIdent>,
+}
+
+impl Display for BadCMakeConfigPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.dep_ident {
+            Some(dep_ident) => write!(
+                f,
+                "{}: The CMake configuration refers to {} which belongs to {}, it is not a runtime dependency of this package",
+                self.source
+                    .relative_package_path()
+                    .unwrap()
+                    .display()
+                    .white(),
+                self.entry.display().yellow(),
+                dep_ident.yellow()
+            ),
+            None => write!(
+                f,
+                "{}: The CMake configuration refers to {} which does not belong to a habitat package",
+                self.source
+                    .relative_package_path()
+                    .unwrap()
+                    .display()
+                    .white(),
+                self.entry.display().yellow()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct BadCMakeConfigPathOptions {
+    #[serde(default = "BadCMakeConfigPathOptions::level")]
+    pub level: ViolationLevel,
+    /// Level of the violations in native packages
+    #[serde(default = "BadCMakeConfigPathOptions::native_level")]
+    pub native_level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+    #[serde(default)]
+    pub ignored_entries: GlobSetExpression,
+}
+
+impl BadCMakeConfigPathOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+    fn native_level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for BadCMakeConfigPathOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            native_level: Self::native_level(),
+            ignored_files: GlobSetExpression::default(),
+            ignored_entries: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct StaticLibrary {
+    pub entry: PathBuf,
+}
+
+impl Display for StaticLibrary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: Static libraries and object files are not needed at runtime",
+            self.entry
+                .relative_package_path()
+                .unwrap()
+                .display()
+                .white(),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct StaticLibraryOptions {
+    #[serde(default = "StaticLibraryOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl StaticLibraryOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for StaticLibraryOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnstrippedBinary {
+    pub entry: PathBuf,
+    pub debug_size: u64,
+}
+
+impl Display for UnstrippedBinary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The binary contains {} KiB of debug information, it should be stripped",
+            self.entry
+                .relative_package_path()
+                .unwrap()
+                .display()
+                .white(),
+            (self.debug_size / 1024).yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnstrippedBinaryOptions {
+    #[serde(default = "UnstrippedBinaryOptions::level")]
+    pub level: ViolationLevel,
+    /// Size in kilobytes of the debug sections above which a binary is reported
+    #[serde(default = "UnstrippedBinaryOptions::max_debug_size_kb")]
+    pub max_debug_size_kb: u64,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl UnstrippedBinaryOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+    fn max_debug_size_kb() -> u64 {
+        1024
+    }
+}
+
+impl Default for UnstrippedBinaryOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            max_debug_size_kb: Self::max_debug_size_kb(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MaxArtifactSizeExceeded {
+    pub size: u64,
+    pub max_size_mb: u64,
+}
+
+impl Display for MaxArtifactSizeExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The artifact contains {} MiB of files, more than the budget of {} MiB set by 'max-artifact-size-mb'",
+            (self.size / (1024 * 1024)).yellow(),
+            self.max_size_mb
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MaxArtifactSizeExceededOptions {
+    pub level: ViolationLevel,
+}
+
+impl Default for MaxArtifactSizeExceededOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TargetMetafileMismatch {
+    pub target: PackageTarget,
+    pub metafile_target: PackageTarget,
+}
+
+impl Display for TargetMetafileMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The TARGET metafile of the artifact declares {} but its MANIFEST declares {}, the artifact may have been renamed or hand-rolled",
+            self.metafile_target.yellow(),
+            self.target.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct TargetMetafileMismatchOptions {
+    pub level: ViolationLevel,
+}
+
+impl Default for TargetMetafileMismatchOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Error,
+        }
+    }
+}
+
+/// Compares the plan source embedded in the artifact of a plan with the plan file
+/// in the repo. Artifacts stored without their plan source are not checked.
+pub(crate) fn plan_source_check(
+    rules: &PlanContextConfig,
+    plan_ctx: &PlanContext,
+    artifact_context: &ArtifactContext,
+) -> Vec<LeveledArtifactCheckViolation> {
+    let plan_source_mismatch_options = rules
+        .artifact_rules
+        .iter()
+        .filter_map(|rule| {
+            if let crate::check::ArtifactRuleOptions::Package(
+                PackageRuleOptions::PlanSourceMismatch(options),
+            ) = &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+    let Some(plan_source) = artifact_context.plan_source.as_ref() else {
+        return vec![];
+    };
+    match plan_ctx.plan_source_matches(plan_source) {
+        Ok(true) => vec![],
+        Ok(false) => vec![LeveledArtifactCheckViolation {
+            level: plan_source_mismatch_options.level,
+            violation: crate::check::ArtifactCheckViolation::Package(
+                PackageRule::PlanSourceMismatch(PlanSourceMismatch {
+                    plan: plan_ctx.plan_path.as_ref().to_path_buf(),
+                }),
+            ),
+        }],
+        Err(err) => {
+            error!(
+                "Failed to compare plan source of {}: {:#}",
+                plan_ctx.id, err
+            );
+            vec![]
+        }
+    }
+}
+
+/// Predicts the violations a rebuild of a plan will cause by comparing the
+/// runtime dependencies of the plan with the paths the current artifact uses.
+/// Any path that belongs to a dependency that is no longer declared by the plan
+/// will most likely break once the plan is rebuilt.
+pub(crate) fn removed_dependency_check(
+    rules: &PlanContextConfig,
+    artifact_context: &ArtifactContext,
+    plan_deps: &[PackageResolvedDepIdent],
+) -> Vec<LeveledArtifactCheckViolation> {
+    let removed_dependency_still_used_options = rules
+        .artifact_rules
+        .iter()
+        .filter_map(|rule| {
+            if let crate::check::ArtifactRuleOptions::Package(
+                PackageRuleOptions::RemovedDependencyStillUsed(options),
+            ) = &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+
+    let mut used_paths: Vec<(&Path, &Path)> = vec![];
+    for entry in artifact_context.runtime_path.iter() {
+        used_paths.push((Path::new("RUNTIME_PATH"), entry.as_path()));
+    }
+    for (path, metadata) in artifact_context.elfs.iter() {
+        for entry in metadata.rpath.iter().chain(metadata.runpath.iter()) {
+            used_paths.push((path.as_path(), entry.as_path()));
+        }
+        if let Some(interpreter) = metadata.interpreter.as_ref() {
+            used_paths.push((path.as_path(), interpreter.as_path()));
+        }
+    }
+    for (path, metadata) in artifact_context.machos.iter() {
+        for arch in metadata.archs.iter() {
+            for entry in arch.rpath.iter() {
+                used_paths.push((path.as_path(), entry.as_path()));
+            }
+            for library in arch.required_libraries.iter() {
+                used_paths.push((path.as_path(), Path::new(library.as_str())));
+            }
+        }
+    }
+    for (path, metadata) in artifact_context.scripts.iter() {
+        used_paths.push((path.as_path(), metadata.interpreter.command.as_path()));
+    }
+    used_paths.sort();
+
+    let mut reported = HashSet::new();
+    let mut violations = vec![];
+    for (source, entry) in used_paths {
+        let Some(dep_ident) = entry.package_ident(artifact_context.target) else {
+            continue;
+        };
+        if dep_ident == artifact_context.id || !artifact_context.deps.contains(&dep_ident) {
+            continue;
+        }
+        if plan_deps
+            .iter()
+            .any(|dep| dep.origin == dep_ident.origin && dep.name == dep_ident.name)
+        {
+            continue;
+        }
+        if !reported.insert((source, dep_ident.clone())) {
+            continue;
+        }
+        violations.push(LeveledArtifactCheckViolation {
+            level: removed_dependency_still_used_options.level,
+            violation: crate::check::ArtifactCheckViolation::Package(
+                PackageRule::RemovedDependencyStillUsed(RemovedDependencyStillUsed {
+                    source: source.to_path_buf(),
+                    entry: entry.to_path_buf(),
+                    dep_ident,
+                }),
+            ),
+        });
+    }
+    violations
+}
+
+/// Lists the files and links of an artifact relative to its package directory.
+/// Artifact contexts that were not read from the artifact itself use the files
+/// recorded in the store, if the files were never recorded only the binaries,
+/// scripts and links of the artifact are known.
+#[cfg(not(target_os = "windows"))]
+fn artifact_relative_files(store: &Store, artifact_context: &ArtifactContext) -> Vec<PathBuf> {
+    if !artifact_context.files.is_empty() {
+        return artifact_context
+            .files
+            .iter()
+            .map(|file| file.path.clone())
+            .collect();
+    }
+    let recorded_files = store.get_connection().and_then(|mut connection| {
+        crate::store::artifact_files_get(&mut connection, &artifact_context.hash)
+    });
+    match recorded_files {
+        Ok(Some(files)) => return files.into_iter().map(|file| file.path).collect(),
+        Ok(None) => {}
+        Err(err) => error!(
+            "Failed to read the recorded files of artifact {}: {:#}",
+            artifact_context.id, err
+        ),
+    }
+    artifact_context
+        .elfs
+        .keys()
+        .chain(artifact_context.scripts.keys())
+        .chain(artifact_context.links.keys())
+        .filter_map(|path| path.relative_package_path())
+        .collect()
+}
+
+/// Finds the shared libraries and files that are shipped by more than one package
+/// of the runtime closure of an artifact. Libraries are matched by soname and only
+/// reported if a binary of the closure requires them, files are matched by their
+/// path relative to the package directory and only reported if they are in a
+/// directory on the runtime path. Executables in runtime path directories are
+/// reported by the `duplicate-runtime-binary` rule instead.
+#[cfg(not(target_os = "windows"))]
+fn conflicting_runtime_file_check(
+    store: &Store,
+    options: &ConflictingRuntimeFileOptions,
+    artifact_context: &ArtifactContext,
+    tdep_artifacts: &HashMap<PackageIdent, ArtifactContext>,
+) -> Vec<LeveledArtifactCheckViolation> {
+    let mut runtime_order: Vec<PackageIdent> = vec![];
+    let mut runtime_dirs: Vec<(PackageIdent, PathBuf)> = vec![];
+    for search_path in artifact_context.runtime_path.iter() {
+        let (Some(dep_ident), Some(dir)) = (
+            search_path.package_ident(artifact_context.target),
+            search_path.relative_package_path(),
+        ) else {
+            continue;
+        };
+        if !tdep_artifacts.contains_key(&dep_ident) {
+            continue;
+        }
+        if !runtime_order.contains(&dep_ident) {
+            runtime_order.push(dep_ident.clone());
+        }
+        runtime_dirs.push((dep_ident, dir));
+    }
+
+    let required_libraries = tdep_artifacts
+        .values()
+        .flat_map(|artifact| artifact.elfs.values())
+        .flat_map(|metadata| metadata.required_libraries.iter())
+        .map(OsString::from)
+        .collect::<HashSet<_>>();
+    let mut conflicts: BTreeMap<(PathBuf, ConflictingFileKind), BTreeSet<PackageIdent>> =
+        BTreeMap::new();
+    for (dep_ident, artifact) in tdep_artifacts.iter() {
+        let shared_libraries = artifact
+            .elfs
+            .iter()
+            .filter(|(_, metadata)| metadata.elf_type == ElfType::SharedLibrary)
+            .map(|(path, _)| path)
+            .chain(artifact.links.keys());
+        for path in shared_libraries {
+            if let Some(soname) = path
+                .file_name()
+                .filter(|file_name| required_libraries.contains(*file_name))
+            {
+                conflicts
+                    .entry((PathBuf::from(soname), ConflictingFileKind::Library))
+                    .or_default()
+                    .insert(dep_ident.clone());
+            }
+        }
+    }
+    let mut artifact_files: HashMap<&PackageIdent, Vec<PathBuf>> = HashMap::new();
+    for (dep_ident, dir) in runtime_dirs.iter() {
+        let artifact = &tdep_artifacts[dep_ident];
+        let executables = artifact
+            .elfs
+            .iter()
+            .filter(|(_, metadata)| metadata.is_executable)
+            .map(|(path, _)| path)
+            .chain(
+                artifact
+                    .scripts
+                    .iter()
+                    .filter(|(_, metadata)| metadata.is_executable)
+                    .map(|(path, _)| path),
+            )
+            .filter_map(|path| path.relative_package_path())
+            .collect::<HashSet<_>>();
+        let files = artifact_files
+            .entry(dep_ident)
+            .or_insert_with(|| artifact_relative_files(store, artifact));
+        for file in files.iter() {
+            if file.parent() == Some(dir.as_path()) && !executables.contains(file) {
+                conflicts
+                    .entry((file.clone(), ConflictingFileKind::File))
+                    .or_default()
+                    .insert(dep_ident.clone());
+            }
+        }
+    }
+
+    let mut violations = vec![];
+    for ((file, kind), providers) in conflicts {
+        if providers.len() < 2 || options.ignored_files.is_match(&file) {
+            continue;
+        }
+        let mut providers = providers.into_iter().collect::<Vec<_>>();
+        providers.sort_by_key(|provider| {
+            runtime_order
+                .iter()
+                .position(|ident| ident == provider)
+                .unwrap_or(usize::MAX)
+        });
+        let winner = providers
+            .first()
+            .filter(|provider| runtime_order.contains(provider))
+            .cloned();
+        // Conflicts resolved in favor of a primary package are expected
+        if winner.as_ref().is_some_and(|winner| {
+            options
+                .primary_packages
+                .iter()
+                .any(|dep_ident| dep_ident.matcher().matches_package_ident(winner))
+        }) {
+            continue;
+        }
+        violations.push(LeveledArtifactCheckViolation {
+            level: options.level,
+            violation: ArtifactCheckViolation::Package(PackageRule::ConflictingRuntimeFile(
+                ConflictingRuntimeFile {
+                    kind,
+                    file,
+                    providers,
+                    winner,
+                },
+            )),
+        });
+    }
+    violations
+}
+
+/// Finds the runtime path entries of an artifact that do not change which file
+/// an executable resolves to, either because they do not contain executables or
+/// because all of their executables are found in earlier entries first. Entries
+/// that do not belong to a runtime dependency are reported by other rules and
+/// are kept as they are.
+#[cfg(not(target_os = "windows"))]
+fn runtime_path_ordering_check(
+    options: &RuntimePathOrderingOptions,
+    artifact_context: &ArtifactContext,
+    tdep_artifacts: &HashMap<PackageIdent, ArtifactContext>,
+) -> Vec<LeveledArtifactCheckViolation> {
+    if options.level == ViolationLevel::Off {
+        return vec![];
+    }
+    let mut executables: HashSet<OsString> = HashSet::new();
+    let mut empty_entries = vec![];
+    let mut shadowed_entries = vec![];
+    let mut suggested_runtime_path = vec![];
+    for search_path in artifact_context.runtime_path.iter() {
+        let Some(artifact) = search_path
+            .package_ident(artifact_context.target)
+            .and_then(|dep_ident| tdep_artifacts.get(&dep_ident))
+        else {
+            suggested_runtime_path.push(search_path.clone());
+            continue;
+        };
+        let entry_executables = artifact
+            .elfs
+            .iter()
+            .filter(|(_, metadata)| metadata.is_executable)
+            .map(|(path, _)| path)
+            .chain(
+                artifact
+                    .scripts
+                    .iter()
+                    .filter(|(_, metadata)| metadata.is_executable)
+                    .map(|(path, _)| path),
+            )
+            .chain(artifact.machos.keys())
+            .chain(
+                artifact
+                    .links
+                    .keys()
+                    .filter(|path| !artifact.broken_links.contains_key(*path)),
+            )
+            .filter(|path| path.parent() == Some(search_path.as_path()))
+            .filter_map(|path| path.file_name())
+            .collect::<HashSet<_>>();
+        if entry_executables.is_empty() {
+            empty_entries.push(search_path.clone());
+        } else if entry_executables
+            .iter()
+            .all(|executable| executables.contains(*executable))
+        {
+            shadowed_entries.push(search_path.clone());
+        } else {
+            executables.extend(
+                entry_executables
+                    .into_iter()
+                    .map(|executable| executable.to_os_string()),
+            );
+            suggested_runtime_path.push(search_path.clone());
+        }
+    }
+    if empty_entries.is_empty() && shadowed_entries.is_empty() {
+        return vec![];
+    }
+    vec![LeveledArtifactCheckViolation {
+        level: options.level,
+        violation: ArtifactCheckViolation::Package(PackageRule::RuntimePathOrdering(
+            RuntimePathOrdering {
+                empty_entries,
+                shadowed_entries,
+                suggested_runtime_path,
+            },
+        )),
+    }]
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct PackageBeforeCheck {}
 
@@ -353,7 +1426,7 @@ impl ArtifactCheck for PackageBeforeCheck {
     #[cfg(not(target_os = "windows"))]
     fn artifact_context_check(
         &self,
-        _store: &Store,
+        store: &Store,
         rules: &PlanContextConfig,
         checker_context: &mut CheckerContext,
         artifact_cache: &mut ArtifactCache,
@@ -373,7 +1446,7 @@ impl ArtifactCheck for PackageBeforeCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let missing_runtime_path_entry_dependency_options = rules
@@ -389,7 +1462,7 @@ impl ArtifactCheck for PackageBeforeCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let missing_dependency_artifact_options = rules
@@ -405,7 +1478,7 @@ impl ArtifactCheck for PackageBeforeCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let duplicate_dependency_options = rules
@@ -421,7 +1494,7 @@ impl ArtifactCheck for PackageBeforeCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let empty_top_level_directory_options = rules
@@ -437,7 +1510,7 @@ impl ArtifactCheck for PackageBeforeCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let broken_link_options = rules
@@ -452,7 +1525,7 @@ impl ArtifactCheck for PackageBeforeCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let duplicate_runtime_binary_options = rules
@@ -468,9 +1541,213 @@ impl ArtifactCheck for PackageBeforeCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
+            .expect("Default rule missing");
+
+        let conflicting_runtime_file_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::ConflictingRuntimeFile(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let runtime_path_ordering_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::RuntimePathOrdering(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let bad_pkg_config_path_entry_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::BadPkgConfigPathEntry(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let empty_pkg_config_path_entry_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::EmptyPkgConfigPathEntry(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let missing_pkg_config_path_entry_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(
+                    PackageRuleOptions::MissingPkgConfigPathEntry(options),
+                ) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let unsafe_permissions_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::UnsafePermissions(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let host_path_reference_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::HostPathReference(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let bad_cmake_config_path_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::BadCMakeConfigPath(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let static_library_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::StaticLibrary(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let unstripped_binary_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::UnstrippedBinary(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let max_artifact_size_exceeded_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::MaxArtifactSizeExceeded(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let target_metafile_mismatch_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Package(PackageRuleOptions::TargetMetafileMismatch(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
             .expect("Default rule missing");
 
+        if let Some(metafile_target) = artifact_context.metafile_target {
+            if metafile_target != artifact_context.target {
+                violations.push(LeveledArtifactCheckViolation {
+                    level: target_metafile_mismatch_options.level,
+                    violation: ArtifactCheckViolation::Package(
+                        PackageRule::TargetMetafileMismatch(TargetMetafileMismatch {
+                            target: artifact_context.target,
+                            metafile_target,
+                        }),
+                    ),
+                });
+            }
+        }
+
         let duplicate_deps = artifact_context
             .deps
             .intersection(&artifact_context.build_deps);
@@ -511,6 +1788,194 @@ impl ArtifactCheck for PackageBeforeCheck {
             }
         }
 
+        let mut unsafe_permissions = artifact_context
+            .unsafe_permissions
+            .iter()
+            .filter(|(entry, _)| {
+                !unsafe_permissions_options
+                    .ignored_files
+                    .is_match(entry.relative_package_path().unwrap())
+            })
+            .collect::<Vec<_>>();
+        unsafe_permissions.sort();
+        for (entry, mode) in unsafe_permissions {
+            violations.push(LeveledArtifactCheckViolation {
+                level: unsafe_permissions_options.level,
+                violation: ArtifactCheckViolation::Package(PackageRule::UnsafePermissions(
+                    UnsafePermissions {
+                        entry: entry.clone(),
+                        mode: *mode,
+                    },
+                )),
+            });
+        }
+
+        let mut host_paths = artifact_context
+            .host_paths
+            .iter()
+            .filter(|(source, _)| {
+                !host_path_reference_options
+                    .ignored_files
+                    .is_match(source.relative_package_path().unwrap())
+            })
+            .flat_map(|(source, entries)| entries.iter().map(move |entry| (source, entry)))
+            .filter(|(_, entry)| !host_path_reference_options.ignored_entries.is_match(entry))
+            .collect::<Vec<_>>();
+        host_paths.sort();
+        for (source, entry) in host_paths {
+            violations.push(LeveledArtifactCheckViolation {
+                level: host_path_reference_options.level,
+                violation: ArtifactCheckViolation::Package(PackageRule::HostPathReference(
+                    HostPathReference {
+                        source: source.clone(),
+                        entry: entry.clone(),
+                    },
+                )),
+            });
+        }
+
+        let bad_cmake_config_path_level = match artifact_context.package_type {
+            PackageType::Native => bad_cmake_config_path_options.native_level,
+            PackageType::Standard => bad_cmake_config_path_options.level,
+        };
+        let mut cmake_config_paths = artifact_context
+            .cmake_config_paths
+            .iter()
+            .filter(|(source, _)| {
+                !bad_cmake_config_path_options
+                    .ignored_files
+                    .is_match(source.relative_package_path().unwrap())
+            })
+            .flat_map(|(source, entries)| entries.iter().map(move |entry| (source, entry)))
+            .filter(|(_, entry)| {
+                !bad_cmake_config_path_options
+                    .ignored_entries
+                    .is_match(entry)
+            })
+            .collect::<Vec<_>>();
+        cmake_config_paths.sort();
+        for (source, entry) in cmake_config_paths {
+            let dep_ident = match entry.package_ident(artifact_context.target) {
+                Some(dep_ident)
+                    if dep_ident == artifact_context.id
+                        || artifact_context.tdeps.contains(&dep_ident) =>
+                {
+                    continue;
+                }
+                dep_ident => dep_ident,
+            };
+            violations.push(LeveledArtifactCheckViolation {
+                level: bad_cmake_config_path_level,
+                violation: ArtifactCheckViolation::Package(PackageRule::BadCMakeConfigPath(
+                    BadCMakeConfigPath {
+                        source: source.clone(),
+                        entry: entry.clone(),
+                        dep_ident,
+                    },
+                )),
+            });
+        }
+
+        let mut static_libraries = artifact_context
+            .static_libraries
+            .iter()
+            .filter(|entry| {
+                !static_library_options
+                    .ignored_files
+                    .is_match(entry.relative_package_path().unwrap())
+            })
+            .collect::<Vec<_>>();
+        static_libraries.sort();
+        for entry in static_libraries {
+            violations.push(LeveledArtifactCheckViolation {
+                level: static_library_options.level,
+                violation: ArtifactCheckViolation::Package(PackageRule::StaticLibrary(
+                    StaticLibrary {
+                        entry: entry.clone(),
+                    },
+                )),
+            });
+        }
+        let mut unstripped_binaries = artifact_context
+            .elfs
+            .iter()
+            .filter(|(entry, metadata)| {
+                metadata.debug_size > unstripped_binary_options.max_debug_size_kb * 1024
+                    && !artifact_context.static_libraries.contains(*entry)
+                    && !unstripped_binary_options
+                        .ignored_files
+                        .is_match(entry.relative_package_path().unwrap())
+            })
+            .collect::<Vec<_>>();
+        unstripped_binaries.sort_by_key(|(entry, _)| *entry);
+        for (entry, metadata) in unstripped_binaries {
+            violations.push(LeveledArtifactCheckViolation {
+                level: unstripped_binary_options.level,
+                violation: ArtifactCheckViolation::Package(PackageRule::UnstrippedBinary(
+                    UnstrippedBinary {
+                        entry: entry.clone(),
+                        debug_size: metadata.debug_size,
+                    },
+                )),
+            });
+        }
+        if let Some(max_size_mb) = rules.max_artifact_size_mb {
+            if artifact_context.installed_size > max_size_mb * 1024 * 1024 {
+                violations.push(LeveledArtifactCheckViolation {
+                    level: max_artifact_size_exceeded_options.level,
+                    violation: ArtifactCheckViolation::Package(
+                        PackageRule::MaxArtifactSizeExceeded(MaxArtifactSizeExceeded {
+                            size: artifact_context.installed_size,
+                            max_size_mb,
+                        }),
+                    ),
+                });
+            }
+        }
+
+        let pkg_config_dirs = artifact_context
+            .pkg_config_files
+            .iter()
+            .filter_map(|pkg_config_file| pkg_config_file.parent())
+            .collect::<BTreeSet<_>>();
+        for entry in artifact_context.pkg_config_path.iter() {
+            if entry.package_ident(artifact_context.target).as_ref() != Some(&artifact_context.id) {
+                violations.push(LeveledArtifactCheckViolation {
+                    level: bad_pkg_config_path_entry_options.level,
+                    violation: ArtifactCheckViolation::Package(PackageRule::BadPkgConfigPathEntry(
+                        BadPkgConfigPathEntry {
+                            entry: entry.clone(),
+                        },
+                    )),
+                });
+            } else if !pkg_config_dirs.contains(entry.as_path()) {
+                violations.push(LeveledArtifactCheckViolation {
+                    level: empty_pkg_config_path_entry_options.level,
+                    violation: ArtifactCheckViolation::Package(
+                        PackageRule::EmptyPkgConfigPathEntry(EmptyPkgConfigPathEntry {
+                            entry: entry.clone(),
+                        }),
+                    ),
+                });
+            }
+        }
+        for pkg_config_dir in pkg_config_dirs {
+            if !artifact_context
+                .pkg_config_path
+                .iter()
+                .any(|entry| entry == pkg_config_dir)
+            {
+                violations.push(LeveledArtifactCheckViolation {
+                    level: missing_pkg_config_path_entry_options.level,
+                    violation: ArtifactCheckViolation::Package(
+                        PackageRule::MissingPkgConfigPathEntry(MissingPkgConfigPathEntry {
+                            entry: pkg_config_dir.to_path_buf(),
+                        }),
+                    ),
+                });
+            }
+        }
+
         let tdep_artifacts = artifact_context
             .tdeps
             .iter()
@@ -564,7 +2029,7 @@ impl ArtifactCheck for PackageBeforeCheck {
                                                         entry
                                                             .get()
                                                             .package_ident(artifact_ctx.target)
-                                                            .map_or(false, |ident| {
+                                                            .is_some_and(|ident| {
                                                                 dep_ident
                                                                     .matcher()
                                                                     .matches_package_ident(&ident)
@@ -611,7 +2076,7 @@ impl ArtifactCheck for PackageBeforeCheck {
                                                         entry
                                                             .get()
                                                             .package_ident(artifact_ctx.target)
-                                                            .map_or(false, |ident| {
+                                                            .is_some_and(|ident| {
                                                                 dep_ident
                                                                     .matcher()
                                                                     .matches_package_ident(&ident)
@@ -669,6 +2134,17 @@ impl ArtifactCheck for PackageBeforeCheck {
                 }
             })
             .collect();
+        violations.extend(conflicting_runtime_file_check(
+            store,
+            conflicting_runtime_file_options,
+            artifact_context,
+            &tdep_artifacts,
+        ));
+        violations.extend(runtime_path_ordering_check(
+            runtime_path_ordering_options,
+            artifact_context,
+            &tdep_artifacts,
+        ));
         checker_context.tdeps = Some(tdep_artifacts);
         checker_context.runtime_artifacts = Some(runtime_path);
         checker_context.unused_deps = Some(artifact_context.deps.clone());
@@ -715,7 +2191,7 @@ impl ArtifactCheck for PackageAfterCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
         let unused_deps = checker_context.unused_deps.as_ref().unwrap();
         if !unused_deps.is_empty() {
@@ -740,3 +2216,95 @@ impl ArtifactCheck for PackageAfterCheck {
         violations.into_iter().collect()
     }
 }
+
+#[cfg(all(test, not(target_os = "windows")))]
+mod tests {
+    use super::*;
+    use crate::core::PackageDepIdent;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    const ARTIFACT_PREFIX: &str = "hab/pkgs/test/hello/1.0.0/20260101000000";
+
+    /// Writes a minimal artifact of test/hello depending on test/zlib, with the
+    /// library directory of test/zlib in its runtime path
+    fn artifact_write(dir: &Path) -> PathBuf {
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        {
+            let mut builder = tar::Builder::new(&mut encoder);
+            for (name, data) in [
+                ("IDENT", "test/hello/1.0.0/20260101000000\n"),
+                ("TARGET", "x86_64-linux\n"),
+                ("DEPS", "test/zlib/1.0.0/20250101000000\n"),
+                ("TDEPS", "test/zlib/1.0.0/20250101000000\n"),
+                (
+                    "RUNTIME_PATH",
+                    "/hab/pkgs/test/hello/1.0.0/20260101000000/bin:/hab/pkgs/test/zlib/1.0.0/20250101000000/lib\n",
+                ),
+                (
+                    "MANIFEST",
+                    "# test/hello\n\n* __Target__: x86_64-linux\n\n## Plan Source\n\n```bash\npkg_name=hello\npkg_license=('MIT')\n```\n",
+                ),
+            ] {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder
+                    .append_data(
+                        &mut header,
+                        format!("{}/{}", ARTIFACT_PREFIX, name),
+                        data.as_bytes(),
+                    )
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+        let artifact_path = dir.join("test-hello-1.0.0-20260101000000-x86_64-linux.hart");
+        let mut artifact = std::fs::File::create(&artifact_path).unwrap();
+        artifact
+            .write_all(b"HART-1\ntest-20260101000000\nBLAKE2b\nsignature\n\n")
+            .unwrap();
+        artifact.write_all(&encoder.finish().unwrap()).unwrap();
+        artifact_path
+    }
+
+    fn plan_deps(idents: &[&str]) -> Vec<PackageResolvedDepIdent> {
+        let target = PackageTarget::parse("x86_64-linux").unwrap();
+        idents
+            .iter()
+            .map(|ident| {
+                PackageDepIdent::parse(ident)
+                    .unwrap()
+                    .to_resolved_dep_ident(target)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn removed_dependency_still_used() {
+        let dir = TempDir::new("hab-auto-build-test").unwrap();
+        let artifact = ArtifactContext::read_from_disk(artifact_write(dir.path()), None).unwrap();
+        let rules = PlanContextConfig::default();
+
+        let violations = removed_dependency_check(&rules, &artifact, &plan_deps(&[]));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].level, ViolationLevel::Error);
+        let ArtifactCheckViolation::Package(PackageRule::RemovedDependencyStillUsed(violation)) =
+            &violations[0].violation
+        else {
+            panic!("Unexpected violation: {}", violations[0]);
+        };
+        assert_eq!(violation.source, Path::new("RUNTIME_PATH"));
+        assert_eq!(
+            violation.entry,
+            Path::new("/hab/pkgs/test/zlib/1.0.0/20250101000000/lib")
+        );
+        assert_eq!(
+            violation.dep_ident.to_string(),
+            "test/zlib/1.0.0/20250101000000 (x86_64-linux)"
+        );
+
+        assert!(removed_dependency_check(&rules, &artifact, &plan_deps(&["test/zlib"])).is_empty());
+    }
+}
@@ -23,7 +23,10 @@ use crate::{
 };
 
 #[cfg(not(target_os = "windows"))]
-use crate::check::{ArtifactCheckViolation, ArtifactRuleOptions};
+use crate::{
+    check::{ArtifactCheckViolation, ArtifactRuleOptions},
+    core::{Hook, HookInterpreter},
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "rule", content = "metadata")]
@@ -40,6 +43,18 @@ pub(crate) enum ScriptRule {
     UnlistedScriptInterpreter(UnlistedScriptInterpreter),
     #[serde(rename = "missing-script-interpreter-dependency")]
     MissingScriptInterpreterDependency(MissingScriptInterpreterDependency),
+    #[serde(rename = "unlisted-interpreter")]
+    UnlistedInterpreter(UnlistedInterpreter),
+    #[serde(rename = "unused-interpreter-entry")]
+    UnusedInterpreterEntry(UnusedInterpreterEntry),
+    #[serde(rename = "missing-hook-dependency")]
+    MissingHookDependency(MissingHookDependency),
+    #[serde(rename = "hook-interpreter-not-found")]
+    HookInterpreterNotFound(HookInterpreterNotFound),
+    #[serde(rename = "unlisted-hook-interpreter")]
+    UnlistedHookInterpreter(UnlistedHookInterpreter),
+    #[serde(rename = "hook-command-not-found")]
+    HookCommandNotFound(HookCommandNotFound),
 }
 
 impl Display for ScriptRule {
@@ -51,6 +66,12 @@ impl Display for ScriptRule {
             ScriptRule::ScriptInterpreterNotFound(rule) => write!(f, "{}", rule),
             ScriptRule::UnlistedScriptInterpreter(rule) => write!(f, "{}", rule),
             ScriptRule::MissingScriptInterpreterDependency(rule) => write!(f, "{}", rule),
+            ScriptRule::UnlistedInterpreter(rule) => write!(f, "{}", rule),
+            ScriptRule::UnusedInterpreterEntry(rule) => write!(f, "{}", rule),
+            ScriptRule::MissingHookDependency(rule) => write!(f, "{}", rule),
+            ScriptRule::HookInterpreterNotFound(rule) => write!(f, "{}", rule),
+            ScriptRule::UnlistedHookInterpreter(rule) => write!(f, "{}", rule),
+            ScriptRule::HookCommandNotFound(rule) => write!(f, "{}", rule),
         }
     }
 }
@@ -70,6 +91,18 @@ pub(crate) enum ScriptRuleOptions {
     UnlistedScriptInterpreter(UnlistedScriptInterpreterOptions),
     #[serde(rename = "missing-script-interpreter-dependency")]
     MissingScriptInterpreterDependency(MissingScriptInterpreterDependencyOptions),
+    #[serde(rename = "unlisted-interpreter")]
+    UnlistedInterpreter(UnlistedInterpreterOptions),
+    #[serde(rename = "unused-interpreter-entry")]
+    UnusedInterpreterEntry(UnusedInterpreterEntryOptions),
+    #[serde(rename = "missing-hook-dependency")]
+    MissingHookDependency(MissingHookDependencyOptions),
+    #[serde(rename = "hook-interpreter-not-found")]
+    HookInterpreterNotFound(HookInterpreterNotFoundOptions),
+    #[serde(rename = "unlisted-hook-interpreter")]
+    UnlistedHookInterpreter(UnlistedHookInterpreterOptions),
+    #[serde(rename = "hook-command-not-found")]
+    HookCommandNotFound(HookCommandNotFoundOptions),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -348,6 +381,285 @@ impl Default for UnlistedScriptInterpreterOptions {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnlistedInterpreter {
+    pub source: PathBuf,
+    pub interpreter: PathBuf,
+}
+
+impl Display for UnlistedInterpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The interpreter {} is shipped by this package but is not listed in its INTERPRETERS metafile, add it to 'pkg_interpreters'",
+            self.source
+                .relative_package_path()
+                .unwrap()
+                .display()
+                .white(),
+            self.interpreter.display().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnlistedInterpreterOptions {
+    #[serde(default = "UnlistedInterpreterOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl UnlistedInterpreterOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for UnlistedInterpreterOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnusedInterpreterEntry {
+    pub entry: PathBuf,
+}
+
+impl Display for UnusedInterpreterEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The INTERPRETERS metafile lists {} which is not shipped by this package and not used by any of its scripts or binaries",
+            self.entry.display().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnusedInterpreterEntryOptions {
+    #[serde(default = "UnusedInterpreterEntryOptions::level")]
+    pub level: ViolationLevel,
+    /// Entries to skip, relative to the package directory
+    #[serde(default)]
+    pub ignored_entries: GlobSetExpression,
+}
+
+impl UnusedInterpreterEntryOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for UnusedInterpreterEntryOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_entries: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MissingHookDependency {
+    pub hook: PathBuf,
+    pub dependency: String,
+}
+
+impl Display for MissingHookDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The hook uses '{}' which is not a runtime dependency of this package",
+            self.hook.display().white(),
+            self.dependency.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MissingHookDependencyOptions {
+    #[serde(default = "MissingHookDependencyOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl MissingHookDependencyOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for MissingHookDependencyOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct HookInterpreterNotFound {
+    pub hook: PathBuf,
+    pub interpreter: PathBuf,
+    pub interpreter_dependency: Option<PackageIdent>,
+}
+
+impl Display for HookInterpreterNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(interpreter_dependency) = self.interpreter_dependency.as_ref() {
+            write!(
+                f,
+                "{}: The interpreter '{}' could not be found in {}",
+                self.hook.display().white(),
+                self.interpreter.display().yellow(),
+                interpreter_dependency.yellow()
+            )
+        } else if self.interpreter.is_absolute() {
+            write!(
+                f,
+                "{}: The interpreter {} does not belong to a habitat package",
+                self.hook.display().white(),
+                self.interpreter.display().yellow()
+            )
+        } else {
+            write!(
+                f,
+                "{}: The interpreter command '{}' could not be found in the runtime environment",
+                self.hook.display().white(),
+                self.interpreter.display().yellow()
+            )
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct HookInterpreterNotFoundOptions {
+    #[serde(default = "HookInterpreterNotFoundOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl HookInterpreterNotFoundOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for HookInterpreterNotFoundOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnlistedHookInterpreter {
+    pub hook: PathBuf,
+    pub interpreter: PathBuf,
+    pub interpreter_dependency: PackageIdent,
+    pub listed_interpreters: Vec<PathBuf>,
+}
+
+impl Display for UnlistedHookInterpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.listed_interpreters.is_empty() {
+            write!(
+                f,
+                "{}: The interpreter command '{}' is not listed as an interpreter in {}",
+                self.hook.display().white(),
+                self.interpreter.display().yellow(),
+                self.interpreter_dependency.yellow()
+            )
+        } else {
+            write!(
+                f,
+                "{}: The interpreter command '{}' is not listed as an interpreter in {}, available interpreters are: {:?}",
+                self.hook.display().white(),
+                self.interpreter.display().yellow(),
+                self.interpreter_dependency.yellow(),
+                self.listed_interpreters.blue()
+            )
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnlistedHookInterpreterOptions {
+    #[serde(default = "UnlistedHookInterpreterOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+}
+
+impl UnlistedHookInterpreterOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for UnlistedHookInterpreterOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct HookCommandNotFound {
+    pub hook: PathBuf,
+    pub command: String,
+}
+
+impl Display for HookCommandNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The command '{}' could not be found in the runtime environment",
+            self.hook.display().white(),
+            self.command.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct HookCommandNotFoundOptions {
+    #[serde(default = "HookCommandNotFoundOptions::level")]
+    pub level: ViolationLevel,
+    #[serde(default)]
+    pub ignored_files: GlobSetExpression,
+    /// Commands that are expected to be available on the host, such as 'sudo'
+    #[serde(default)]
+    pub ignored_commands: Vec<String>,
+}
+
+impl HookCommandNotFoundOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for HookCommandNotFoundOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_files: GlobSetExpression::default(),
+            ignored_commands: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct ScriptCheck {
     #[allow(dead_code)]
@@ -380,6 +692,307 @@ impl Default for ScriptCheck {
     }
 }
 
+impl ScriptCheck {
+    /// Checks the interpreters and the commands of the hooks of the package
+    #[cfg(not(target_os = "windows"))]
+    fn hooks_check(
+        &self,
+        rules: &PlanContextConfig,
+        checker_context: &CheckerContext,
+        artifact_context: &ArtifactContext,
+        used_deps: &mut HashSet<PackageIdent>,
+        used_interpreters: &mut HashSet<PathBuf>,
+    ) -> Vec<LeveledArtifactCheckViolation> {
+        let mut violations = vec![];
+        let missing_hook_dependency_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Script(ScriptRuleOptions::MissingHookDependency(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+        let hook_interpreter_not_found_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Script(ScriptRuleOptions::HookInterpreterNotFound(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+        let unlisted_hook_interpreter_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Script(ScriptRuleOptions::UnlistedHookInterpreter(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+        let hook_command_not_found_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Script(ScriptRuleOptions::HookCommandNotFound(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let tdep_artifacts = checker_context
+            .tdeps
+            .as_ref()
+            .expect("Check context missing transitive dep artifacts");
+        let runtime_artifacts = checker_context
+            .runtime_artifacts
+            .as_ref()
+            .expect("Check context missing runtime artifacts");
+        let tdep_ident = |origin: &str, name: &str| {
+            tdep_artifacts
+                .keys()
+                .find(|ident| ident.origin.to_string() == origin && ident.name.to_string() == name)
+        };
+
+        for (name, content) in artifact_context.hooks.iter() {
+            let hook = Hook {
+                name: name.clone(),
+                content: content.clone(),
+            };
+            let hook_path = hook.path();
+
+            for (origin, name) in hook.pkg_path_for_references() {
+                if tdep_ident(&origin, &name).is_none()
+                    && !missing_hook_dependency_options
+                        .ignored_files
+                        .is_match(hook_path.as_path())
+                {
+                    violations.push(LeveledArtifactCheckViolation {
+                        level: missing_hook_dependency_options.level,
+                        violation: ArtifactCheckViolation::Script(
+                            ScriptRule::MissingHookDependency(MissingHookDependency {
+                                hook: hook_path.clone(),
+                                dependency: format!("{}/{}", origin, name),
+                            }),
+                        ),
+                    });
+                }
+            }
+
+            let interpreter = hook.interpreter();
+            // Absolute path of the interpreter, if it is expected to be inside a package
+            let interpreter_path = match interpreter.as_ref() {
+                Some(HookInterpreter::Dependency { origin, name, path }) => {
+                    // Missing dependencies are reported by the missing-hook-dependency rule
+                    tdep_ident(origin, name).map(|ident| package_install_path(ident).join(path))
+                }
+                Some(HookInterpreter::Package(path)) => {
+                    Some(package_install_path(&artifact_context.id).join(path))
+                }
+                Some(HookInterpreter::Path(path)) => {
+                    if path.package_ident(artifact_context.target).is_some() {
+                        Some(path.clone())
+                    } else {
+                        if !self.platform_interpreter_paths.contains(path)
+                            && !hook_interpreter_not_found_options
+                                .ignored_files
+                                .is_match(hook_path.as_path())
+                        {
+                            violations.push(LeveledArtifactCheckViolation {
+                                level: hook_interpreter_not_found_options.level,
+                                violation: ArtifactCheckViolation::Script(
+                                    ScriptRule::HookInterpreterNotFound(HookInterpreterNotFound {
+                                        hook: hook_path.clone(),
+                                        interpreter: path.clone(),
+                                        interpreter_dependency: None,
+                                    }),
+                                ),
+                            });
+                        }
+                        None
+                    }
+                }
+                Some(HookInterpreter::Env(command)) => {
+                    let mut found = false;
+                    for runtime_artifact_ctx in runtime_artifacts.iter() {
+                        if let Some(metadata) =
+                            runtime_artifact_ctx.search_runtime_executable(tdep_artifacts, command)
+                        {
+                            found = metadata.is_executable();
+                            used_deps.insert(runtime_artifact_ctx.id.clone());
+                            break;
+                        }
+                    }
+                    if !found
+                        && !hook_interpreter_not_found_options
+                            .ignored_files
+                            .is_match(hook_path.as_path())
+                    {
+                        violations.push(LeveledArtifactCheckViolation {
+                            level: hook_interpreter_not_found_options.level,
+                            violation: ArtifactCheckViolation::Script(
+                                ScriptRule::HookInterpreterNotFound(HookInterpreterNotFound {
+                                    hook: hook_path.clone(),
+                                    interpreter: PathBuf::from(command),
+                                    interpreter_dependency: None,
+                                }),
+                            ),
+                        });
+                    }
+                    None
+                }
+                None => None,
+            };
+
+            if let Some(interpreter_path) = interpreter_path {
+                let (command, intermediates) = artifact_context
+                    .resolve_path_and_intermediates(tdep_artifacts, interpreter_path.as_path());
+                used_interpreters.extend(intermediates.iter().cloned());
+                used_interpreters.insert(command.clone());
+                let interpreter_dep = command
+                    .as_path()
+                    .package_ident(artifact_context.target)
+                    .or_else(|| interpreter_path.package_ident(artifact_context.target));
+                let interpreter_artifact_ctx = interpreter_dep
+                    .as_ref()
+                    .and_then(|interpreter_dep| tdep_artifacts.get(interpreter_dep));
+                match interpreter_artifact_ctx {
+                    Some(interpreter_artifact_ctx)
+                        if interpreter_artifact_ctx
+                            .elfs
+                            .contains_key(command.as_path())
+                            || interpreter_artifact_ctx
+                                .scripts
+                                .contains_key(command.as_path())
+                            || interpreter_artifact_ctx
+                                .links
+                                .contains_key(command.as_path())
+                            || interpreter_artifact_ctx
+                                .machos
+                                .contains_key(command.as_path()) =>
+                    {
+                        used_deps.insert(interpreter_artifact_ctx.id.clone());
+                        let interpreter_listed = intermediates.iter().any(|intermediate| {
+                            interpreter_artifact_ctx.interpreters.contains(intermediate)
+                        });
+                        if !interpreter_listed
+                            && !unlisted_hook_interpreter_options
+                                .ignored_files
+                                .is_match(hook_path.as_path())
+                        {
+                            violations.push(LeveledArtifactCheckViolation {
+                                level: unlisted_hook_interpreter_options.level,
+                                violation: ArtifactCheckViolation::Script(
+                                    ScriptRule::UnlistedHookInterpreter(UnlistedHookInterpreter {
+                                        hook: hook_path.clone(),
+                                        interpreter: command,
+                                        interpreter_dependency: interpreter_artifact_ctx.id.clone(),
+                                        listed_interpreters: interpreter_artifact_ctx
+                                            .interpreters
+                                            .clone(),
+                                    }),
+                                ),
+                            });
+                        }
+                    }
+                    _ => {
+                        if !hook_interpreter_not_found_options
+                            .ignored_files
+                            .is_match(hook_path.as_path())
+                        {
+                            violations.push(LeveledArtifactCheckViolation {
+                                level: hook_interpreter_not_found_options.level,
+                                violation: ArtifactCheckViolation::Script(
+                                    ScriptRule::HookInterpreterNotFound(HookInterpreterNotFound {
+                                        hook: hook_path.clone(),
+                                        interpreter: command,
+                                        interpreter_dependency: interpreter_dep,
+                                    }),
+                                ),
+                            });
+                        }
+                    }
+                }
+            }
+
+            // Commands can only be detected reliably in shell hooks
+            if !interpreter
+                .as_ref()
+                .is_some_and(|interpreter| interpreter.is_shell())
+                || hook_command_not_found_options
+                    .ignored_files
+                    .is_match(hook_path.as_path())
+            {
+                continue;
+            }
+            for command in hook.commands() {
+                if hook_command_not_found_options
+                    .ignored_commands
+                    .contains(&command)
+                {
+                    continue;
+                }
+                let mut found = false;
+                for runtime_artifact_ctx in runtime_artifacts.iter() {
+                    if let Some(metadata) =
+                        runtime_artifact_ctx.search_runtime_executable(tdep_artifacts, &command)
+                    {
+                        found = metadata.is_executable();
+                        used_deps.insert(runtime_artifact_ctx.id.clone());
+                        break;
+                    }
+                }
+                if !found {
+                    violations.push(LeveledArtifactCheckViolation {
+                        level: hook_command_not_found_options.level,
+                        violation: ArtifactCheckViolation::Script(ScriptRule::HookCommandNotFound(
+                            HookCommandNotFound {
+                                hook: hook_path.clone(),
+                                command,
+                            },
+                        )),
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Folder in which a package is installed
+#[cfg(not(target_os = "windows"))]
+fn package_install_path(ident: &PackageIdent) -> PathBuf {
+    PathBuf::from(format!(
+        "/hab/pkgs/{}/{}/{}/{}",
+        ident.origin, ident.name, ident.version, ident.release
+    ))
+}
+
 impl ArtifactCheck for ScriptCheck {
     #[cfg(target_os = "windows")]
     fn artifact_context_check(
@@ -405,6 +1018,7 @@ impl ArtifactCheck for ScriptCheck {
     ) -> Vec<LeveledArtifactCheckViolation> {
         let mut violations = vec![];
         let mut used_deps = HashSet::new();
+        let mut used_interpreters = HashSet::new();
         let host_script_interpreter_options = rules
             .artifact_rules
             .iter()
@@ -418,7 +1032,7 @@ impl ArtifactCheck for ScriptCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let missing_env_script_interpreter_options = rules
@@ -434,7 +1048,7 @@ impl ArtifactCheck for ScriptCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let env_script_interpreter_not_found_options = rules
@@ -450,7 +1064,7 @@ impl ArtifactCheck for ScriptCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let script_interpreter_not_found_options = rules
@@ -466,7 +1080,7 @@ impl ArtifactCheck for ScriptCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let unlisted_script_interpreter_options = rules
@@ -482,7 +1096,7 @@ impl ArtifactCheck for ScriptCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
         let missing_script_interpreter_dependency_options = rules
@@ -498,7 +1112,39 @@ impl ArtifactCheck for ScriptCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
+            .expect("Default rule missing");
+
+        let unlisted_interpreter_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Script(ScriptRuleOptions::UnlistedInterpreter(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let unused_interpreter_entry_options = rules
+            .artifact_rules
+            .iter()
+            .filter_map(|rule| {
+                if let ArtifactRuleOptions::Script(ScriptRuleOptions::UnusedInterpreterEntry(
+                    options,
+                )) = &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
             .expect("Default rule missing");
 
         let tdep_artifacts = checker_context
@@ -531,6 +1177,8 @@ impl ArtifactCheck for ScriptCheck {
             );
             let (command, intermediates) =
                 artifact_context.resolve_path_and_intermediates(tdep_artifacts, command);
+            used_interpreters.extend(intermediates.iter().cloned());
+            used_interpreters.insert(command.clone());
 
             if let Some(interpreter_dep) = command.as_path().package_ident(artifact_context.target)
             {
@@ -611,10 +1259,29 @@ impl ArtifactCheck for ScriptCheck {
                                 interpreter_listed = true;
                             }
                         }
-                        if !interpreter_listed
-                            && !unlisted_script_interpreter_options
+                        if interpreter_listed {
+                            continue;
+                        }
+                        // Interpreters of the package itself are checked against its
+                        // own INTERPRETERS metafile by the unlisted-interpreter rule
+                        if interpreter_dep == artifact_context.id {
+                            if !unlisted_interpreter_options
                                 .ignored_files
                                 .is_match(path.relative_package_path().unwrap())
+                            {
+                                violations.push(LeveledArtifactCheckViolation {
+                                    level: unlisted_interpreter_options.level,
+                                    violation: ArtifactCheckViolation::Script(
+                                        ScriptRule::UnlistedInterpreter(UnlistedInterpreter {
+                                            source: path.clone(),
+                                            interpreter: command,
+                                        }),
+                                    ),
+                                });
+                            }
+                        } else if !unlisted_script_interpreter_options
+                            .ignored_files
+                            .is_match(path.relative_package_path().unwrap())
                         {
                             violations.push(LeveledArtifactCheckViolation {
                                 level: unlisted_script_interpreter_options.level,
@@ -680,6 +1347,48 @@ impl ArtifactCheck for ScriptCheck {
                 });
             }
         }
+        for metadata in artifact_context.elfs.values() {
+            if let Some(interpreter) = metadata.interpreter.as_ref() {
+                let (interpreter, intermediates) =
+                    artifact_context.resolve_path_and_intermediates(tdep_artifacts, interpreter);
+                used_interpreters.extend(intermediates);
+                used_interpreters.insert(interpreter);
+            }
+        }
+        violations.extend(self.hooks_check(
+            rules,
+            checker_context,
+            artifact_context,
+            &mut used_deps,
+            &mut used_interpreters,
+        ));
+        // Entries that point to an executable of the package may be used by other
+        // packages, only entries that are neither shipped nor used are stale
+        for entry in artifact_context.interpreters.iter() {
+            if entry.as_os_str().is_empty()
+                || used_interpreters.contains(entry)
+                || artifact_context.elfs.contains_key(entry)
+                || artifact_context.scripts.contains_key(entry)
+                || artifact_context.links.contains_key(entry)
+                || artifact_context.machos.contains_key(entry)
+            {
+                continue;
+            }
+            if unused_interpreter_entry_options
+                .ignored_entries
+                .is_match(entry.relative_package_path().unwrap_or(entry.clone()))
+            {
+                continue;
+            }
+            violations.push(LeveledArtifactCheckViolation {
+                level: unused_interpreter_entry_options.level,
+                violation: ArtifactCheckViolation::Script(ScriptRule::UnusedInterpreterEntry(
+                    UnusedInterpreterEntry {
+                        entry: entry.clone(),
+                    },
+                )),
+            });
+        }
         for used_dep in used_deps {
             checker_context.mark_used(&used_dep);
         }
@@ -0,0 +1,390 @@
+use std::{fmt::Display, ops::Range, path::PathBuf};
+
+use owo_colors::OwoColorize;
+
+use crate::core::{PackageDepIdent, PackageIdent, PackagePath};
+
+use super::{
+    artifact::package::PackageRule, source::license::LicenseRule, ArtifactCheckViolation,
+    LeveledArtifactCheckViolation, LeveledSourceCheckViolation, SourceCheckViolation,
+    ViolationLevel,
+};
+
+/// Number of unchanged lines shown around the changed lines of a diff
+const DIFF_CONTEXT_LINES: usize = 2;
+
+/// Change to a plan file that fixes a check violation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PlanFix {
+    /// Removes a dependency that is not used at runtime from 'pkg_deps'
+    DependencyRemove(PackageIdent),
+    /// Removes a dependency that is also a runtime dependency from 'pkg_build_deps'
+    BuildDependencyRemove(PackageIdent),
+    /// Adds a license detected in the sources to 'pkg_license'
+    LicenseAdd(String),
+    /// Empty directories must be removed by the build, this is only a hint as the
+    /// right place to remove them depends on the plan
+    EmptyDirectoryRemoveHint(PathBuf),
+}
+
+impl Display for PlanFix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanFix::DependencyRemove(dep_ident) => {
+                write!(f, "Remove {} from 'pkg_deps'", dep_ident.yellow())
+            }
+            PlanFix::BuildDependencyRemove(dep_ident) => {
+                write!(f, "Remove {} from 'pkg_build_deps'", dep_ident.yellow())
+            }
+            PlanFix::LicenseAdd(license) => {
+                write!(f, "Add {} to 'pkg_license'", license.yellow())
+            }
+            PlanFix::EmptyDirectoryRemoveHint(directory) => write!(
+                f,
+                "Add {} at the end of 'do_install' to remove the empty directory",
+                format!("rm -rf \"${{pkg_prefix}}/{}\"", directory.display()).yellow()
+            ),
+        }
+    }
+}
+
+impl PlanFix {
+    /// Finds the fixes for the violations that can be fixed mechanically
+    pub fn from_violations(
+        source_violations: &[LeveledSourceCheckViolation],
+        artifact_violations: &[LeveledArtifactCheckViolation],
+    ) -> Vec<PlanFix> {
+        let mut fixes = Vec::new();
+        for violation in source_violations {
+            if violation.level == ViolationLevel::Off {
+                continue;
+            }
+            match &violation.violation {
+                SourceCheckViolation::License(LicenseRule::MissingLicense(rule)) => {
+                    fixes.push(PlanFix::LicenseAdd(rule.license.clone()))
+                }
+                SourceCheckViolation::License(_)
+                | SourceCheckViolation::Hook(_)
+                | SourceCheckViolation::Config(_) => {}
+            }
+        }
+        for violation in artifact_violations {
+            if violation.level == ViolationLevel::Off {
+                continue;
+            }
+            match &violation.violation {
+                ArtifactCheckViolation::Package(PackageRule::UnusedDependency(rule)) => {
+                    fixes.push(PlanFix::DependencyRemove(rule.dep_ident.clone()))
+                }
+                ArtifactCheckViolation::Package(PackageRule::DuplicateDependency(rule)) => {
+                    fixes.push(PlanFix::BuildDependencyRemove(rule.dep_ident.clone()))
+                }
+                ArtifactCheckViolation::Package(PackageRule::EmptyTopLevelDirectory(rule)) => fixes
+                    .push(PlanFix::EmptyDirectoryRemoveHint(
+                        rule.directory
+                            .relative_package_path()
+                            .unwrap_or_else(|| rule.directory.clone()),
+                    )),
+                _ => {}
+            }
+        }
+        fixes
+    }
+
+    /// Applies the fix to the source of a plan file, returns `None` if the fix
+    /// is only a hint or the plan could not be changed safely.
+    pub fn apply(&self, plan_source: &str) -> Option<String> {
+        match self {
+            PlanFix::DependencyRemove(dep_ident) => {
+                array_item_remove(plan_source, "pkg_deps", dep_ident)
+            }
+            PlanFix::BuildDependencyRemove(dep_ident) => {
+                array_item_remove(plan_source, "pkg_build_deps", dep_ident)
+            }
+            PlanFix::LicenseAdd(license) => array_item_add(plan_source, "pkg_license", license),
+            PlanFix::EmptyDirectoryRemoveHint(_) => None,
+        }
+    }
+}
+
+/// Finds the range of the contents of the bash array assigned to the variable,
+/// between the opening and closing parentheses.
+fn array_range(plan_source: &str, variable: &str) -> Option<Range<usize>> {
+    let assignment = format!("{}=(", variable);
+    let mut offset = 0;
+    for line in plan_source.split_inclusive('\n') {
+        let indent = line.len() - line.trim_start().len();
+        if line.trim_start().starts_with(&assignment) {
+            let start = offset + indent + assignment.len();
+            let mut quote = None;
+            let mut comment = false;
+            for (index, char) in plan_source[start..].char_indices() {
+                match (char, quote, comment) {
+                    ('\n', _, true) => comment = false,
+                    (_, _, true) => {}
+                    ('\'' | '"', None, false) => quote = Some(char),
+                    (char, Some(current), false) if char == current => quote = None,
+                    ('#', None, false) => comment = true,
+                    (')', None, false) => return Some(start..start + index),
+                    _ => {}
+                }
+            }
+            return None;
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Splits the contents of a bash array into the ranges of its items, skipping
+/// comments.
+fn array_items(plan_source: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let mut items = Vec::new();
+    let mut item_start = None;
+    let mut quote = None;
+    let mut comment = false;
+    for (index, char) in plan_source[range.clone()].char_indices() {
+        let index = range.start + index;
+        match (char, quote, comment) {
+            ('\n', _, true) => comment = false,
+            (_, _, true) => {}
+            ('\'' | '"', None, false) => {
+                quote = Some(char);
+                item_start.get_or_insert(index);
+            }
+            (char, Some(current), false) if char == current => quote = None,
+            (_, Some(_), false) => {}
+            ('#', None, false) if item_start.is_none() => comment = true,
+            (char, None, false) if char.is_whitespace() => {
+                if let Some(start) = item_start.take() {
+                    items.push(start..index);
+                }
+            }
+            _ => {
+                item_start.get_or_insert(index);
+            }
+        }
+    }
+    if let Some(start) = item_start {
+        items.push(start..range.end);
+    }
+    items
+}
+
+fn array_item_remove(
+    plan_source: &str,
+    variable: &str,
+    dep_ident: &PackageIdent,
+) -> Option<String> {
+    let range = array_range(plan_source, variable)?;
+    let item = array_items(plan_source, range.clone())
+        .into_iter()
+        .find(|item| {
+            plan_source[item.clone()]
+                .trim_matches(['\'', '"'])
+                .parse::<PackageDepIdent>()
+                .is_ok_and(|item_dep_ident| dep_ident.satisfies_dependency(&item_dep_ident))
+        })?;
+    let line_start = plan_source[..item.start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let line_end = plan_source[item.end..]
+        .find('\n')
+        .map_or(plan_source.len(), |index| item.end + index + 1);
+    let removed = if plan_source[line_start..item.start].trim().is_empty()
+        && (plan_source[item.end..line_end].trim().is_empty()
+            || plan_source[item.end..line_end].trim().starts_with('#'))
+    {
+        // The item is alone on its line, remove the whole line along with its comment
+        line_start..line_end
+    } else {
+        let whitespace_end = item.end + plan_source[item.end..range.end].len()
+            - plan_source[item.end..range.end].trim_start().len();
+        if whitespace_end < range.end && !plan_source[item.end..whitespace_end].contains('\n') {
+            item.start..whitespace_end
+        } else {
+            let whitespace_start = plan_source[range.start..item.start].trim_end().len();
+            range.start + whitespace_start..item.end
+        }
+    };
+    Some(format!(
+        "{}{}",
+        &plan_source[..removed.start],
+        &plan_source[removed.end..]
+    ))
+}
+
+fn array_item_add(plan_source: &str, variable: &str, value: &str) -> Option<String> {
+    let range = array_range(plan_source, variable)?;
+    let items = array_items(plan_source, range.clone());
+    if items
+        .iter()
+        .any(|item| plan_source[item.clone()].trim_matches(['\'', '"']) == value)
+    {
+        return None;
+    }
+    let quote = items
+        .first()
+        .and_then(|item| plan_source[item.clone()].chars().next())
+        .filter(|char| *char == '"')
+        .unwrap_or('\'');
+    let quoted_value = format!("{}{}{}", quote, value, quote);
+    let close_line_start = plan_source[..range.end]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let (position, insertion) = match items.last() {
+        Some(last_item) if close_line_start > range.start => {
+            if plan_source[close_line_start..range.end].trim().is_empty() {
+                // The closing parenthesis is on its own line, add a line with the
+                // same indentation as the last item
+                let last_line_start = plan_source[..last_item.start]
+                    .rfind('\n')
+                    .map_or(0, |index| index + 1);
+                let indent = &plan_source[last_line_start..last_item.start];
+                let indent = &indent[..indent.len() - indent.trim_start().len()];
+                (close_line_start, format!("{}{}\n", indent, quoted_value))
+            } else {
+                (range.end, format!(" {}", quoted_value))
+            }
+        }
+        Some(last_item) => (last_item.end, format!(" {}", quoted_value)),
+        None => (range.start, quoted_value),
+    };
+    Some(format!(
+        "{}{}{}",
+        &plan_source[..position],
+        insertion,
+        &plan_source[position..]
+    ))
+}
+
+/// Finds the range of the value of the last top level assignment of the variable,
+/// if the value fits on a single line.
+fn variable_value_range(plan_source: &str, variable: &str) -> Option<Range<usize>> {
+    let assignment = format!("{}=", variable);
+    let mut offset = 0;
+    let mut value_range = None;
+    for line in plan_source.split_inclusive('\n') {
+        if line.starts_with(&assignment) && !line.starts_with(&format!("{}(", assignment)) {
+            let start = offset + assignment.len();
+            let mut quote = None;
+            let mut end = None;
+            for (index, char) in plan_source[start..].char_indices() {
+                match (char, quote) {
+                    ('\n', Some(_)) => return None,
+                    ('\'' | '"', None) => quote = Some(char),
+                    (char, Some(current)) if char == current => quote = None,
+                    (char, None) if char.is_whitespace() || char == ';' => {
+                        end = Some(start + index);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            value_range = Some(start..end.unwrap_or(plan_source.len()));
+        }
+        offset += line.len();
+    }
+    value_range
+}
+
+/// Sets the value of a variable assigned at the top level of a plan, keeping
+/// the quotes around the previous value.
+pub(crate) fn variable_set(plan_source: &str, variable: &str, value: &str) -> Option<String> {
+    let range = variable_value_range(plan_source, variable)?;
+    let quote = plan_source[range.clone()]
+        .chars()
+        .next()
+        .filter(|char| *char == '\'')
+        .unwrap_or('"');
+    Some(format!(
+        "{}{}{}{}{}",
+        &plan_source[..range.start],
+        quote,
+        value,
+        quote,
+        &plan_source[range.end..]
+    ))
+}
+
+/// Replaces a text in the value of a variable assigned at the top level of a plan,
+/// returns `None` if the value does not contain the text.
+pub(crate) fn variable_replace(
+    plan_source: &str,
+    variable: &str,
+    from: &str,
+    to: &str,
+) -> Option<String> {
+    let range = variable_value_range(plan_source, variable)?;
+    if !plan_source[range.clone()].contains(from) {
+        return None;
+    }
+    Some(format!(
+        "{}{}{}",
+        &plan_source[..range.start],
+        plan_source[range.clone()].replace(from, to),
+        &plan_source[range.end..]
+    ))
+}
+
+/// Formats the changed lines between two versions of a file, with a few unchanged
+/// lines around them.
+pub(crate) fn line_diff(old: &str, new: &str) -> String {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    // Length of the longest common subsequence of the remaining lines
+    let mut lcs = vec![vec![0usize; new_lines.len() + 1]; old_lines.len() + 1];
+    for i in (0..old_lines.len()).rev() {
+        for j in (0..new_lines.len()).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_lines.len() || j < new_lines.len() {
+        if i < old_lines.len() && j < new_lines.len() && old_lines[i] == new_lines[j] {
+            changes.push((' ', i, old_lines[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_lines.len() && (j == new_lines.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(('-', i, old_lines[i]));
+            i += 1;
+        } else {
+            changes.push(('+', i, new_lines[j]));
+            j += 1;
+        }
+    }
+    let changed = changes
+        .iter()
+        .enumerate()
+        .filter(|(_, (kind, _, _))| *kind != ' ')
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let mut output = String::new();
+    let mut last_shown = None;
+    for (index, (kind, old_index, line)) in changes.iter().enumerate() {
+        let is_shown = changed.iter().any(|changed| {
+            index + DIFF_CONTEXT_LINES >= *changed && index <= changed + DIFF_CONTEXT_LINES
+        });
+        if !is_shown {
+            continue;
+        }
+        if last_shown.is_none_or(|last_shown| last_shown + 1 != index) {
+            output.push_str(&format!(
+                "{}\n",
+                format!("@@ line {} @@", old_index + 1).blue()
+            ));
+        }
+        last_shown = Some(index);
+        match kind {
+            '+' => output.push_str(&format!("{}\n", format!("+{}", line).green())),
+            '-' => output.push_str(&format!("{}\n", format!("-{}", line).red())),
+            _ => output.push_str(&format!(" {}\n", line)),
+        }
+    }
+    output
+}
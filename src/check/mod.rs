@@ -1,18 +1,23 @@
 mod artifact;
+mod fix;
+mod plan;
 mod source;
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
+    path::PathBuf,
 };
 
 use crate::{
-    core::{ArtifactCache, ArtifactContext, PackageIdent, PlanContext, SourceContext},
+    core::{
+        ArtifactCache, ArtifactContext, AuditConfig, PackageDepIdent, PackageIdent, PlanContext,
+        SourceContext, UpdateCheckConfig,
+    },
     store::Store,
 };
 
-#[cfg(not(target_os = "windows"))]
-use crate::core::PackageTarget;
+use crate::core::{habitat::PlanBuilder, PackageOS, PackageTarget};
 
 #[cfg(not(target_os = "windows"))]
 use color_eyre::{
@@ -21,13 +26,19 @@ use color_eyre::{
 };
 
 use owo_colors::OwoColorize;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[cfg(not(target_os = "windows"))]
 use toml_edit::{Array, DocumentMut, Formatted, InlineTable, Value};
 
 use tracing::debug;
 
+pub(crate) use self::artifact::package::{plan_source_check, removed_dependency_check};
+pub(crate) use self::fix::{line_diff, variable_replace, variable_set, PlanFix};
+pub(crate) use self::plan::lint::plan_lint;
+use self::plan::lint::PlanRuleOptions;
+pub(crate) use self::plan::parser::{AssignmentValue, PlanFile, Word};
+
 #[cfg(target_os = "linux")]
 use self::artifact::elf::{ElfCheck, ElfRule, ElfRuleOptions};
 
@@ -40,11 +51,20 @@ use self::{
         package::{PackageAfterCheck, PackageRuleOptions},
         script::{ScriptCheck, ScriptRule, ScriptRuleOptions},
     },
-    source::license::{LicenseCheck, LicenseRule, LicenseRuleOptions},
+    source::{
+        config::{ConfigCheck, ConfigRule, ConfigRuleOptions},
+        hook::{HookCheck, HookRule, HookRuleOptions},
+        license::{LicenseCheck, LicenseRule, LicenseRuleOptions},
+    },
 };
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub(crate) use self::source::license::LicensePolicy;
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum ViolationLevel {
+    /// Improvements that never fail a build, only used by opt-in rules
+    #[serde(rename = "suggest")]
+    Suggest,
     #[serde(rename = "warn")]
     Warn,
     #[serde(rename = "error")]
@@ -53,6 +73,99 @@ pub(crate) enum ViolationLevel {
     Off,
 }
 
+/// Version of the rule set, incremented every time new rules are added
+pub(crate) const RULES_VERSION: u32 = 13;
+
+/// Rules added after the first version of the rule set, with the version of the
+/// rule set they were added in
+const RULES_ADDED_IN: &[(&str, u32)] = &[
+    ("bad-pkg-config-path-entry", 2),
+    ("empty-pkg-config-path-entry", 2),
+    ("missing-pkg-config-path-entry", 2),
+    ("removed-dependency-still-used", 3),
+    ("plan-source-mismatch", 3),
+    ("unsafe-permissions", 4),
+    ("host-path-reference", 4),
+    ("bad-cmake-config-path", 5),
+    ("static-library", 6),
+    ("unstripped-binary", 6),
+    ("max-artifact-size-exceeded", 6),
+    ("missing-symbol-version", 7),
+    ("denied-license", 8),
+    ("unknown-license", 8),
+    ("conflicting-runtime-file", 9),
+    ("unlisted-interpreter", 10),
+    ("unused-interpreter-entry", 10),
+    ("unknown-hook", 11),
+    ("invalid-hook-interpreter", 11),
+    ("undeclared-hook-dependency", 11),
+    ("hook-shellcheck", 11),
+    ("missing-hook-dependency", 11),
+    ("hook-interpreter-not-found", 11),
+    ("unlisted-hook-interpreter", 11),
+    ("hook-command-not-found", 11),
+    ("invalid-default-config", 12),
+    ("undefined-config-value", 12),
+    ("unused-config-value", 12),
+    ("target-metafile-mismatch", 13),
+];
+
+/// Level of the rules added after the version of the rule set a repo was last
+/// checked against, so that upgrading hab-auto-build does not immediately fail
+/// builds because of new rules.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct NewRulesPolicy {
+    pub rules_version: u32,
+    pub level: ViolationLevel,
+}
+
+impl NewRulesPolicy {
+    pub fn is_new_rule(&self, rule_id: &str) -> bool {
+        RULES_ADDED_IN
+            .iter()
+            .any(|(id, version)| *id == rule_id && *version > self.rules_version)
+    }
+}
+
+/// Named set of changes to the levels of the rules, selected with `check --profile`.
+/// Profiles apply after the plan configuration, so they also override the levels
+/// configured in the plan's `.hab-plan-config.toml`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RuleProfile {
+    /// Changes the level of every rule with the given level
+    #[serde(default)]
+    pub levels: BTreeMap<ViolationLevel, ViolationLevel>,
+    /// Sets the level of the rules with the given ids, takes precedence over 'levels'
+    #[serde(default)]
+    pub rules: BTreeMap<String, ViolationLevel>,
+}
+
+impl RuleProfile {
+    /// Names of the profiles available without any configuration
+    pub const BUILT_IN: [&'static str; 3] = ["strict", "default", "permissive"];
+
+    pub fn built_in(name: &str) -> Option<RuleProfile> {
+        let levels = match name {
+            "strict" => BTreeMap::from([(ViolationLevel::Warn, ViolationLevel::Error)]),
+            "default" => BTreeMap::new(),
+            "permissive" => BTreeMap::from([(ViolationLevel::Error, ViolationLevel::Warn)]),
+            _ => return None,
+        };
+        Some(RuleProfile {
+            levels,
+            rules: BTreeMap::new(),
+        })
+    }
+
+    fn level(&self, rule_id: &str, level: ViolationLevel) -> ViolationLevel {
+        self.rules
+            .get(rule_id)
+            .or_else(|| self.levels.get(&level))
+            .copied()
+            .unwrap_or(level)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct PlanConfig {
     #[serde(default)]
@@ -70,17 +183,147 @@ pub(crate) enum RuleConfig {
 pub(crate) struct PlanContextConfig {
     #[serde(default, rename = "docker-image")]
     pub docker_image: Option<String>,
+    /// Where the plan is built if it is a standard plan, overriding the builder
+    /// configured for all plans
+    #[serde(default)]
+    pub builder: Option<PlanBuilder>,
+    /// Whether native macOS builds of the plan run in a sandbox, defaults to true
     pub sandbox: Option<bool>,
+    /// Absolute paths outside the sandbox that the native macOS build of the plan
+    /// may access
+    #[serde(default, rename = "sandbox-allow-paths")]
+    pub sandbox_allow_paths: Vec<PathBuf>,
+    /// Whether the sandboxed native macOS build of the plan may access the network
+    #[serde(default, rename = "allow-networking")]
+    pub allow_networking: Option<bool>,
     #[serde(default)]
     pub source_rules: Vec<SourceRule>,
     #[serde(default)]
     pub artifact_rules: Vec<ArtifactRule>,
+    /// Glob patterns of generated paths in the plan context, relative to the
+    /// plan context, that are ignored when detecting changes
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Glob patterns of files in the plan context, relative to the plan context,
+    /// whose changes do not cause the plan to be rebuilt
+    #[serde(default, rename = "change-ignore")]
+    pub change_ignore: Vec<String>,
+    /// Number of seconds the build of the plan may run before it is killed
+    #[serde(default, rename = "timeout-secs")]
+    pub timeout_secs: Option<u64>,
+    /// Number of times the build of the plan is retried after it fails or times out
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Maximum size in megabytes of the files in the artifact of the plan
+    #[serde(default, rename = "max-artifact-size-mb")]
+    pub max_artifact_size_mb: Option<u64>,
+    /// Where to look for newer upstream versions of the plan's source
+    #[serde(default)]
+    pub update: Option<UpdateCheckConfig>,
+    /// Where to look up the known vulnerabilities of the plan's version
+    #[serde(default)]
+    pub audit: Option<AuditConfig>,
+    /// Studio package the plan is built with, pinning the version of the studio
+    /// configured for its repo
+    #[serde(default)]
+    pub studio: Option<PackageDepIdent>,
+    /// Runtime dependencies added to the plan when it is built for the target
+    #[serde(default, rename = "extra-deps")]
+    pub extra_deps: Vec<PackageDepIdent>,
+    /// Build dependencies added to the plan when it is built for the target
+    #[serde(default, rename = "extra-build-deps")]
+    pub extra_build_deps: Vec<PackageDepIdent>,
+    /// Runtime dependencies of the plan that are dropped when it is built for
+    /// the target
+    #[serde(default, rename = "remove-deps")]
+    pub removed_deps: Vec<PackageDepIdent>,
+    /// Build dependencies of the plan that are dropped when it is built for the
+    /// target
+    #[serde(default, rename = "remove-build-deps")]
+    pub removed_build_deps: Vec<PackageDepIdent>,
+    /// License policy of the plan's repo, set from the configuration of the
+    /// workspace rather than the plan
+    #[serde(skip)]
+    pub license_policy: Option<LicensePolicy>,
 }
 
 impl PlanContextConfig {
     pub fn merge(mut self, other: &PlanContextConfig) -> PlanContextConfig {
         self.source_rules.extend_from_slice(&other.source_rules);
         self.artifact_rules.extend_from_slice(&other.artifact_rules);
+        self.exclude.extend_from_slice(&other.exclude);
+        self.change_ignore.extend_from_slice(&other.change_ignore);
+        self.max_artifact_size_mb = other.max_artifact_size_mb.or(self.max_artifact_size_mb);
+        self.update = other.update.clone().or(self.update);
+        self.audit = other.audit.clone().or(self.audit);
+        self.extra_deps.extend_from_slice(&other.extra_deps);
+        self.extra_build_deps
+            .extend_from_slice(&other.extra_build_deps);
+        self.removed_deps.extend_from_slice(&other.removed_deps);
+        self.removed_build_deps
+            .extend_from_slice(&other.removed_build_deps);
+        self
+    }
+
+    /// Sets the level of the new rules according to the policy. Rules configured
+    /// by a plan are merged after these, so their configured level still applies.
+    pub fn with_new_rules_policy(self, policy: &NewRulesPolicy) -> PlanContextConfig {
+        self.with_levels(|rule_id, option, level| {
+            if option == "level" && policy.is_new_rule(rule_id) {
+                policy.level
+            } else {
+                level
+            }
+        })
+    }
+
+    /// Changes the levels of all the rules according to the profile, including
+    /// the rules configured by the plan.
+    pub fn with_rule_profile(self, profile: &RuleProfile) -> PlanContextConfig {
+        self.with_levels(|rule_id, option, level| {
+            if option == "level" {
+                profile.level(rule_id, level)
+            } else {
+                // Secondary levels like 'native_level' are only remapped by level
+                profile.levels.get(&level).copied().unwrap_or(level)
+            }
+        })
+    }
+
+    /// Updates every level option of the rules, such as 'level' or 'native_level',
+    /// with the level returned for the rule id, option name and current level.
+    fn with_levels(
+        mut self,
+        level_update: impl Fn(&str, &str, ViolationLevel) -> ViolationLevel,
+    ) -> PlanContextConfig {
+        fn levels_set<T: Serialize + DeserializeOwned>(
+            rule: &mut T,
+            level_update: &impl Fn(&str, &str, ViolationLevel) -> ViolationLevel,
+        ) {
+            let mut value = serde_json::to_value(&*rule).unwrap();
+            let Some(rule_id) = value["id"].as_str().map(String::from) else {
+                return;
+            };
+            let Some(options) = value["options"].as_object_mut() else {
+                return;
+            };
+            for (option, option_value) in options.iter_mut() {
+                if option != "level" && !option.ends_with("_level") {
+                    continue;
+                }
+                if let Ok(level) = serde_json::from_value(option_value.clone()) {
+                    *option_value =
+                        serde_json::to_value(level_update(&rule_id, option, level)).unwrap();
+                }
+            }
+            *rule = serde_json::from_value(value).expect("Invalid rule options");
+        }
+        for rule in self.source_rules.iter_mut() {
+            levels_set(rule, &level_update);
+        }
+        for rule in self.artifact_rules.iter_mut() {
+            levels_set(rule, &level_update);
+        }
         self
     }
 
@@ -141,8 +384,70 @@ impl PlanContextConfig {
                     .to_string()
                     .header("Restructured Rules:")
             })?;
+        // Dependency changes only apply to the target they are declared for
+        let target_deps = |key: &str| -> Result<Vec<PackageDepIdent>> {
+            if document.get(key).is_some() {
+                return Err(eyre!(
+                    "Invalid dependency changes, '{}' must be declared under a target such as '[{}]'",
+                    key,
+                    target
+                ));
+            }
+            document
+                .get(target.to_string().as_str())
+                .and_then(|value| value.get(key))
+                .map(|value| {
+                    value
+                        .as_array()
+                        .ok_or(eyre!(
+                            "Invalid dependency changes, '{}' must be an array",
+                            key
+                        ))?
+                        .iter()
+                        .map(|dep| {
+                            dep.as_str()
+                                .ok_or(eyre!(
+                                    "Invalid dependency in '{}', it must be a package identifier",
+                                    key
+                                ))
+                                .and_then(PackageDepIdent::parse)
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+                .transpose()
+                .map(Option::unwrap_or_default)
+        };
         let mut context_rules = PlanContextConfig {
             sandbox: document.get("sandbox").and_then(|value| value.as_bool()),
+            sandbox_allow_paths: document
+                .get("sandbox-allow-paths")
+                .map(|value| {
+                    value
+                        .as_array()
+                        .ok_or(eyre!(
+                            "Invalid sandbox allowed paths, 'sandbox-allow-paths' must be an array"
+                        ))?
+                        .iter()
+                        .map(|path| {
+                            path.as_str()
+                                .map(PathBuf::from)
+                                .filter(|path| path.is_absolute())
+                                .ok_or(eyre!(
+                                    "Invalid sandbox allowed path, it must be an absolute path"
+                                ))
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            allow_networking: document
+                .get("allow-networking")
+                .map(|value| {
+                    value.as_bool().ok_or(eyre!(
+                        "Invalid sandbox networking, 'allow-networking' must be a boolean"
+                    ))
+                })
+                .transpose()?,
             docker_image: document
                 .get("docker-image")
                 .map(|value| {
@@ -152,8 +457,176 @@ impl PlanContextConfig {
                         .map(String::from)
                 })
                 .transpose()?,
+            builder: document
+                .get("builder")
+                .map(|value| match value.as_str() {
+                    Some("studio") => Ok(PlanBuilder::Studio),
+                    Some("docker") => Ok(PlanBuilder::Docker),
+                    _ => Err(eyre!(
+                        "Invalid builder, 'builder' must be either 'studio' or 'docker'"
+                    )),
+                })
+                .transpose()?,
             source_rules: vec![],
             artifact_rules: vec![],
+            exclude: document
+                .get("exclude")
+                .map(|value| {
+                    value
+                        .as_array()
+                        .ok_or(eyre!(
+                            "Invalid exclude patterns, 'exclude' must be an array"
+                        ))?
+                        .iter()
+                        .map(|pattern| {
+                            let pattern = pattern
+                                .as_str()
+                                .ok_or(eyre!("Invalid exclude pattern, it must be a string"))?;
+                            globset::Glob::new(pattern).map_err(|err| {
+                                eyre!("Invalid exclude pattern '{}': {}", pattern, err)
+                            })?;
+                            Ok(pattern.to_string())
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            change_ignore: document
+                .get("change-ignore")
+                .map(|value| {
+                    value
+                        .as_array()
+                        .ok_or(eyre!(
+                            "Invalid change ignore patterns, 'change-ignore' must be an array"
+                        ))?
+                        .iter()
+                        .map(|pattern| {
+                            let pattern = pattern.as_str().ok_or(eyre!(
+                                "Invalid change ignore pattern, it must be a string"
+                            ))?;
+                            globset::Glob::new(pattern).map_err(|err| {
+                                eyre!("Invalid change ignore pattern '{}': {}", pattern, err)
+                            })?;
+                            Ok(pattern.to_string())
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            timeout_secs: document
+                .get("timeout-secs")
+                .map(|value| {
+                    value
+                        .as_integer()
+                        .and_then(|value| u64::try_from(value).ok())
+                        .filter(|value| *value > 0)
+                        .ok_or(eyre!(
+                            "Invalid build timeout, 'timeout-secs' must be a positive integer"
+                        ))
+                })
+                .transpose()?,
+            retries: document
+                .get("retries")
+                .map(|value| {
+                    value
+                        .as_integer()
+                        .and_then(|value| u32::try_from(value).ok())
+                        .ok_or(eyre!(
+                            "Invalid build retries, 'retries' must be a non-negative integer"
+                        ))
+                })
+                .transpose()?,
+            max_artifact_size_mb: document
+                .get("max-artifact-size-mb")
+                .map(|value| {
+                    value
+                        .as_integer()
+                        .and_then(|value| u64::try_from(value).ok())
+                        .filter(|value| *value > 0)
+                        .ok_or(eyre!(
+                            "Invalid artifact size budget, 'max-artifact-size-mb' must be a positive integer"
+                        ))
+                })
+                .transpose()?,
+            update: document
+                .get("update")
+                .map(|value| -> Result<UpdateCheckConfig> {
+                    let value = value.as_table_like().ok_or(eyre!(
+                        "Invalid update check configuration, 'update' must be a table"
+                    ))?;
+                    let option = |key: &str| {
+                        value
+                            .get(key)
+                            .map(|value| {
+                                value.as_str().map(String::from).ok_or(eyre!(
+                                    "Invalid update check configuration, 'update.{}' must be a string",
+                                    key
+                                ))
+                            })
+                            .transpose()
+                    };
+                    Ok(UpdateCheckConfig {
+                        url: option("url")?,
+                        regex: option("regex")?,
+                    })
+                })
+                .transpose()?,
+            audit: document
+                .get("audit")
+                .map(|value| -> Result<AuditConfig> {
+                    let value = value.as_table_like().ok_or(eyre!(
+                        "Invalid audit configuration, 'audit' must be a table"
+                    ))?;
+                    let option = |key: &str| {
+                        value
+                            .get(key)
+                            .map(|value| {
+                                value.as_str().map(String::from).ok_or(eyre!(
+                                    "Invalid audit configuration, 'audit.{}' must be a string",
+                                    key
+                                ))
+                            })
+                            .transpose()
+                    };
+                    Ok(AuditConfig {
+                        ecosystem: option("ecosystem")?,
+                        package: option("package")?,
+                        ignore: value
+                            .get("ignore")
+                            .map(|value| {
+                                value
+                                    .as_array()
+                                    .and_then(|ignore| {
+                                        ignore
+                                            .iter()
+                                            .map(|id| id.as_str().map(String::from))
+                                            .collect::<Option<Vec<_>>>()
+                                    })
+                                    .ok_or(eyre!(
+                                        "Invalid audit configuration, 'audit.ignore' must be a list of vulnerability ids"
+                                    ))
+                            })
+                            .transpose()?
+                            .unwrap_or_default(),
+                    })
+                })
+                .transpose()?,
+            studio: document
+                .get("studio")
+                .map(|value| {
+                    value
+                        .as_str()
+                        .ok_or(eyre!(
+                            "Invalid studio package, 'studio' must be a package identifier"
+                        ))
+                        .and_then(PackageDepIdent::parse)
+                })
+                .transpose()?,
+            extra_deps: target_deps("extra-deps")?,
+            extra_build_deps: target_deps("extra-build-deps")?,
+            removed_deps: target_deps("remove-deps")?,
+            removed_build_deps: target_deps("remove-build-deps")?,
+            license_policy: None,
         };
         for rule in plan_config.rules {
             match rule {
@@ -185,6 +658,53 @@ impl Default for PlanContextConfig {
                     Default::default(),
                 )),
             },
+            SourceRule {
+                options: SourceRuleOptions::License(LicenseRuleOptions::DeniedLicense(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::License(LicenseRuleOptions::UnknownLicense(
+                    Default::default(),
+                )),
+            },
+        ];
+        let mut hook_rules = vec![
+            SourceRule {
+                options: SourceRuleOptions::Hook(HookRuleOptions::UnknownHook(Default::default())),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Hook(HookRuleOptions::InvalidHookInterpreter(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Hook(HookRuleOptions::UndeclaredHookDependency(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Hook(HookRuleOptions::HookShellcheck(
+                    Default::default(),
+                )),
+            },
+        ];
+        let mut config_rules = vec![
+            SourceRule {
+                options: SourceRuleOptions::Config(ConfigRuleOptions::InvalidDefaultConfig(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Config(ConfigRuleOptions::UndefinedConfigValue(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Config(ConfigRuleOptions::UnusedConfigValue(
+                    Default::default(),
+                )),
+            },
         ];
         #[cfg(target_os = "linux")]
         let mut elf_rules = vec![
@@ -253,6 +773,11 @@ impl Default for PlanContextConfig {
                     Default::default(),
                 )),
             },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Elf(ElfRuleOptions::MissingSymbolVersion(
+                    Default::default(),
+                )),
+            },
         ];
         #[cfg(target_os = "macos")]
         let mut macho_rules = vec![
@@ -328,6 +853,76 @@ impl Default for PlanContextConfig {
                     Default::default(),
                 )),
             },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::ConflictingRuntimeFile(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::RuntimePathOrdering(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::BadPkgConfigPathEntry(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::EmptyPkgConfigPathEntry(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(
+                    PackageRuleOptions::MissingPkgConfigPathEntry(Default::default()),
+                ),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(
+                    PackageRuleOptions::RemovedDependencyStillUsed(Default::default()),
+                ),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::PlanSourceMismatch(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::UnsafePermissions(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::HostPathReference(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::BadCMakeConfigPath(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::StaticLibrary(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::UnstrippedBinary(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::MaxArtifactSizeExceeded(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Package(PackageRuleOptions::TargetMetafileMismatch(
+                    Default::default(),
+                )),
+            },
         ];
         let mut script_rules = vec![
             ArtifactRule {
@@ -360,14 +955,90 @@ impl Default for PlanContextConfig {
                     ScriptRuleOptions::MissingScriptInterpreterDependency(Default::default()),
                 ),
             },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Script(ScriptRuleOptions::UnlistedInterpreter(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Script(ScriptRuleOptions::UnusedInterpreterEntry(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Script(ScriptRuleOptions::MissingHookDependency(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Script(ScriptRuleOptions::HookInterpreterNotFound(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Script(ScriptRuleOptions::UnlistedHookInterpreter(
+                    Default::default(),
+                )),
+            },
+            ArtifactRule {
+                options: ArtifactRuleOptions::Script(ScriptRuleOptions::HookCommandNotFound(
+                    Default::default(),
+                )),
+            },
+        ];
+        let mut plan_rules = vec![
+            SourceRule {
+                options: SourceRuleOptions::Plan(PlanRuleOptions::InvalidPlanSyntax(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Plan(PlanRuleOptions::MissingPlanVariable(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Plan(PlanRuleOptions::DeprecatedCallback(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Plan(PlanRuleOptions::UnquotedSourceVariable(
+                    Default::default(),
+                )),
+            },
+            SourceRule {
+                options: SourceRuleOptions::Plan(PlanRuleOptions::PlanNameMismatch(
+                    Default::default(),
+                )),
+            },
         ];
         let mut config = Self {
             sandbox: None,
+            sandbox_allow_paths: vec![],
+            allow_networking: None,
             docker_image: None,
+            builder: None,
             source_rules: vec![],
             artifact_rules: vec![],
+            exclude: vec![],
+            change_ignore: vec![],
+            timeout_secs: None,
+            retries: None,
+            max_artifact_size_mb: None,
+            update: None,
+            audit: None,
+            studio: None,
+            extra_deps: vec![],
+            extra_build_deps: vec![],
+            removed_deps: vec![],
+            removed_build_deps: vec![],
+            license_policy: None,
         };
         config.source_rules.append(&mut license_rules);
+        config.source_rules.append(&mut hook_rules);
+        config.source_rules.append(&mut config_rules);
+        config.source_rules.append(&mut plan_rules);
         config.artifact_rules.append(&mut package_rules);
         config.artifact_rules.append(&mut script_rules);
         #[cfg(target_os = "linux")]
@@ -388,6 +1059,9 @@ pub(crate) struct SourceRule {
 #[serde(untagged)]
 pub(crate) enum SourceRuleOptions {
     License(LicenseRuleOptions),
+    Hook(HookRuleOptions),
+    Config(ConfigRuleOptions),
+    Plan(PlanRuleOptions),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -415,6 +1089,19 @@ pub(crate) struct LeveledSourceCheckViolation {
 impl Display for LeveledSourceCheckViolation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.level {
+            ViolationLevel::Suggest => write!(
+                f,
+                "{}{} {}",
+                "suggest: ".cyan().bold(),
+                format!(
+                    "[{}]",
+                    serde_json::to_value(&self.violation).unwrap()["rule"]
+                        .as_str()
+                        .unwrap()
+                )
+                .bright_black(),
+                self.violation,
+            ),
             ViolationLevel::Warn => write!(
                 f,
                 "{}{} {}",
@@ -451,12 +1138,18 @@ impl Display for LeveledSourceCheckViolation {
 pub(crate) enum SourceCheckViolation {
     #[serde(rename = "license")]
     License(LicenseRule),
+    #[serde(rename = "hook")]
+    Hook(HookRule),
+    #[serde(rename = "config")]
+    Config(ConfigRule),
 }
 
 impl Display for SourceCheckViolation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SourceCheckViolation::License(rule) => write!(f, "{}", rule),
+            SourceCheckViolation::Hook(rule) => write!(f, "{}", rule),
+            SourceCheckViolation::Config(rule) => write!(f, "{}", rule),
         }
     }
 }
@@ -470,6 +1163,19 @@ pub(crate) struct LeveledArtifactCheckViolation {
 impl Display for LeveledArtifactCheckViolation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.level {
+            ViolationLevel::Suggest => write!(
+                f,
+                "{}{} {}",
+                "suggest: ".cyan().bold(),
+                format!(
+                    "[{}]",
+                    serde_json::to_value(&self.violation).unwrap()["rule"]
+                        .as_str()
+                        .unwrap()
+                )
+                .bright_black(),
+                self.violation,
+            ),
             ViolationLevel::Warn => write!(
                 f,
                 "{}{} {}",
@@ -581,43 +1287,65 @@ pub(crate) struct Checker {
 }
 
 impl Checker {
+    /// Creates a checker for artifacts of the given target. Binary checks are only
+    /// available for targets with the same operating system as the host, artifacts
+    /// of other targets only get package and script checks.
     #[cfg(target_os = "macos")]
-    pub fn new() -> Checker {
+    pub fn new(target: PackageTarget) -> Checker {
         use self::artifact::macho::MachOCheck;
 
+        let mut artifact_checks: Vec<Box<dyn ArtifactCheck>> =
+            vec![Box::<PackageBeforeCheck>::default()];
+        if target.os == PackageOS::Darwin {
+            artifact_checks.push(Box::<MachOCheck>::default());
+        }
+        artifact_checks.push(Box::<ScriptCheck>::default());
+        artifact_checks.push(Box::<PackageAfterCheck>::default());
         Checker {
-            source_checks: vec![Box::<LicenseCheck>::default()],
-            artifact_checks: vec![
-                Box::<PackageBeforeCheck>::default(),
-                Box::<MachOCheck>::default(),
-                Box::<ScriptCheck>::default(),
-                Box::<PackageAfterCheck>::default(),
+            source_checks: vec![
+                Box::<LicenseCheck>::default(),
+                Box::<HookCheck>::default(),
+                Box::<ConfigCheck>::default(),
             ],
+            artifact_checks,
         }
     }
     #[cfg(target_os = "linux")]
-    pub fn new() -> Checker {
+    pub fn new(target: PackageTarget) -> Checker {
+        let mut artifact_checks: Vec<Box<dyn ArtifactCheck>> =
+            vec![Box::<PackageBeforeCheck>::default()];
+        if target.os == PackageOS::Linux {
+            artifact_checks.push(Box::<ElfCheck>::default());
+        }
+        artifact_checks.push(Box::<ScriptCheck>::default());
+        artifact_checks.push(Box::<PackageAfterCheck>::default());
         Checker {
-            source_checks: vec![Box::<LicenseCheck>::default()],
-            artifact_checks: vec![
-                Box::<PackageBeforeCheck>::default(),
-                Box::<ElfCheck>::default(),
-                Box::<ScriptCheck>::default(),
-                Box::<PackageAfterCheck>::default(),
+            source_checks: vec![
+                Box::<LicenseCheck>::default(),
+                Box::<HookCheck>::default(),
+                Box::<ConfigCheck>::default(),
             ],
+            artifact_checks,
         }
     }
     #[cfg(target_os = "windows")]
-    pub fn new() -> Checker {
+    pub fn new(target: PackageTarget) -> Checker {
         use self::artifact::win::PeCheck;
+
+        let mut artifact_checks: Vec<Box<dyn ArtifactCheck>> =
+            vec![Box::<PackageBeforeCheck>::default()];
+        if target.os == PackageOS::Windows {
+            artifact_checks.push(Box::<PeCheck>::default());
+        }
+        artifact_checks.push(Box::<ScriptCheck>::default());
+        artifact_checks.push(Box::<PackageAfterCheck>::default());
         Checker {
-            source_checks: vec![Box::<LicenseCheck>::default()],
-            artifact_checks: vec![
-                Box::<PackageBeforeCheck>::default(),
-                Box::<PeCheck>::default(),
-                Box::<ScriptCheck>::default(),
-                Box::<PackageAfterCheck>::default(),
+            source_checks: vec![
+                Box::<LicenseCheck>::default(),
+                Box::<HookCheck>::default(),
+                Box::<ConfigCheck>::default(),
             ],
+            artifact_checks,
         }
     }
 }
@@ -686,3 +1414,87 @@ impl ArtifactCheck for Checker {
         violations
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deps(idents: &[&str]) -> Vec<PackageDepIdent> {
+        idents
+            .iter()
+            .map(|ident| PackageDepIdent::parse(ident).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn plan_config_target_dep_changes_parsing() {
+        let config = r#"
+[aarch64-linux]
+extra-deps = ["core/libatomic"]
+extra-build-deps = ["core/gcc/12.2.0"]
+remove-deps = ["core/glibc"]
+remove-build-deps = ["core/nasm", "core/yasm/1.3.0"]
+
+[x86_64-linux]
+extra-deps = ["core/zlib"]
+"#;
+        let plan_config =
+            PlanContextConfig::from_str(config, PackageTarget::parse("aarch64-linux").unwrap())
+                .unwrap();
+        assert_eq!(plan_config.extra_deps, deps(&["core/libatomic"]));
+        assert_eq!(plan_config.extra_build_deps, deps(&["core/gcc/12.2.0"]));
+        assert_eq!(plan_config.removed_deps, deps(&["core/glibc"]));
+        assert_eq!(
+            plan_config.removed_build_deps,
+            deps(&["core/nasm", "core/yasm/1.3.0"])
+        );
+
+        let plan_config =
+            PlanContextConfig::from_str(config, PackageTarget::parse("x86_64-linux").unwrap())
+                .unwrap();
+        assert_eq!(plan_config.extra_deps, deps(&["core/zlib"]));
+        assert!(plan_config.extra_build_deps.is_empty());
+        assert!(plan_config.removed_deps.is_empty());
+        assert!(plan_config.removed_build_deps.is_empty());
+
+        // Plans without changes for the target keep their dependencies
+        let plan_config =
+            PlanContextConfig::from_str(config, PackageTarget::parse("x86_64-windows").unwrap())
+                .unwrap();
+        assert!(plan_config.extra_deps.is_empty());
+    }
+
+    #[test]
+    fn plan_config_invalid_dep_changes() {
+        let target = PackageTarget::parse("x86_64-linux").unwrap();
+        for config in [
+            // Changes must be declared under a target
+            "extra-deps = [\"core/zlib\"]\n",
+            "[x86_64-linux]\nremove-deps = \"core/zlib\"\n",
+            "[x86_64-linux]\nextra-build-deps = [1]\n",
+            "[x86_64-linux]\nextra-deps = [\"zlib\"]\n",
+        ] {
+            assert!(
+                PlanContextConfig::from_str(config, target).is_err(),
+                "{}",
+                config
+            );
+        }
+    }
+
+    #[test]
+    fn plan_config_dep_changes_merging() {
+        let target = PackageTarget::parse("x86_64-linux").unwrap();
+        let repo_config =
+            PlanContextConfig::from_str("[x86_64-linux]\nextra-deps = [\"core/zlib\"]\n", target)
+                .unwrap();
+        let plan_config = PlanContextConfig::from_str(
+            "[x86_64-linux]\nextra-deps = [\"core/xz\"]\nremove-deps = [\"core/bzip2\"]\n",
+            target,
+        )
+        .unwrap();
+        let merged = repo_config.merge(&plan_config);
+        assert_eq!(merged.extra_deps, deps(&["core/zlib", "core/xz"]));
+        assert_eq!(merged.removed_deps, deps(&["core/bzip2"]));
+    }
+}
@@ -0,0 +1,523 @@
+use std::fmt::Display;
+
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+use crate::check::{PlanContextConfig, SourceRuleOptions, ViolationLevel};
+
+use super::parser::{AssignmentValue, PlanFile, Quoting};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "rule", content = "metadata")]
+pub(crate) enum PlanRule {
+    #[serde(rename = "invalid-plan-syntax")]
+    InvalidPlanSyntax(InvalidPlanSyntax),
+    #[serde(rename = "missing-plan-variable")]
+    MissingPlanVariable(MissingPlanVariable),
+    #[serde(rename = "deprecated-callback")]
+    DeprecatedCallback(DeprecatedCallback),
+    #[serde(rename = "unquoted-source-variable")]
+    UnquotedSourceVariable(UnquotedSourceVariable),
+    #[serde(rename = "plan-name-mismatch")]
+    PlanNameMismatch(PlanNameMismatch),
+}
+
+impl Display for PlanRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanRule::InvalidPlanSyntax(rule) => write!(f, "{}", rule),
+            PlanRule::MissingPlanVariable(rule) => write!(f, "{}", rule),
+            PlanRule::DeprecatedCallback(rule) => write!(f, "{}", rule),
+            PlanRule::UnquotedSourceVariable(rule) => write!(f, "{}", rule),
+            PlanRule::PlanNameMismatch(rule) => write!(f, "{}", rule),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "id", content = "options")]
+pub(crate) enum PlanRuleOptions {
+    #[serde(rename = "invalid-plan-syntax")]
+    InvalidPlanSyntax(InvalidPlanSyntaxOptions),
+    #[serde(rename = "missing-plan-variable")]
+    MissingPlanVariable(MissingPlanVariableOptions),
+    #[serde(rename = "deprecated-callback")]
+    DeprecatedCallback(DeprecatedCallbackOptions),
+    #[serde(rename = "unquoted-source-variable")]
+    UnquotedSourceVariable(UnquotedSourceVariableOptions),
+    #[serde(rename = "plan-name-mismatch")]
+    PlanNameMismatch(PlanNameMismatchOptions),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct InvalidPlanSyntax {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for InvalidPlanSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The plan could not be parsed, {}",
+            format!("line {}", self.line).white(),
+            self.message
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct InvalidPlanSyntaxOptions {
+    #[serde(default = "InvalidPlanSyntaxOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl InvalidPlanSyntaxOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for InvalidPlanSyntaxOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MissingPlanVariable {
+    pub variable: String,
+}
+
+impl Display for MissingPlanVariable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.variable == "pkg_version" {
+            write!(
+                f,
+                "The plan must set {} or define a {} callback that calls {}",
+                self.variable.yellow(),
+                "pkg_version()".yellow(),
+                "update_pkg_version".yellow()
+            )
+        } else {
+            write!(f, "The plan must set {}", self.variable.yellow())
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MissingPlanVariableOptions {
+    #[serde(default = "MissingPlanVariableOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl MissingPlanVariableOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for MissingPlanVariableOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct DeprecatedCallback {
+    pub line: usize,
+    pub callback: String,
+    pub replacement: String,
+}
+
+impl Display for DeprecatedCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The callback {} should not be overridden by plans, define {} instead",
+            format!("line {}", self.line).white(),
+            self.callback.yellow(),
+            self.replacement.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct DeprecatedCallbackOptions {
+    #[serde(default = "DeprecatedCallbackOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl DeprecatedCallbackOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for DeprecatedCallbackOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnquotedSourceVariable {
+    pub line: usize,
+    pub value: String,
+}
+
+impl Display for UnquotedSourceVariable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The variables in {} should be double quoted: {}",
+            format!("line {}", self.line).white(),
+            "pkg_source".yellow(),
+            self.value.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnquotedSourceVariableOptions {
+    #[serde(default = "UnquotedSourceVariableOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl UnquotedSourceVariableOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for UnquotedSourceVariableOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PlanNameMismatch {
+    pub line: usize,
+    pub pkg_name: String,
+    pub plan_dir_name: String,
+}
+
+impl Display for PlanNameMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The package name {} does not match the name of the plan directory {}",
+            format!("line {}", self.line).white(),
+            self.pkg_name.yellow(),
+            self.plan_dir_name.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct PlanNameMismatchOptions {
+    #[serde(default = "PlanNameMismatchOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl PlanNameMismatchOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for PlanNameMismatchOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct LeveledPlanCheckViolation {
+    pub level: ViolationLevel,
+    pub violation: PlanRule,
+}
+
+impl Display for LeveledPlanCheckViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rule = serde_json::to_value(&self.violation).unwrap()["rule"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        match self.level {
+            ViolationLevel::Suggest => write!(
+                f,
+                "{}{} {}",
+                "suggest: ".cyan().bold(),
+                format!("[{}]", rule).bright_black(),
+                self.violation,
+            ),
+            ViolationLevel::Warn => write!(
+                f,
+                "{}{} {}",
+                "warning: ".yellow().bold(),
+                format!("[{}]", rule).bright_black(),
+                self.violation,
+            ),
+            ViolationLevel::Error => write!(
+                f,
+                "{}{} {}",
+                "  error: ".red().bold(),
+                format!("[{}]", rule).bright_black(),
+                self.violation,
+            ),
+            ViolationLevel::Off => write!(f, ""),
+        }
+    }
+}
+
+/// Variables every plan must set
+const REQUIRED_PLAN_VARIABLES: &[&str] = &["pkg_name", "pkg_origin", "pkg_version"];
+
+/// Prefix of the internal implementations of the build phase callbacks, plans
+/// override the callbacks without the prefix instead
+const DEFAULT_CALLBACK_PREFIX: &str = "do_default_";
+
+/// Checks the source of a bash plan file for common mistakes, without running it.
+pub(crate) fn plan_lint(
+    rules: &PlanContextConfig,
+    plan_source: &str,
+    plan_dir_name: Option<&str>,
+) -> Vec<LeveledPlanCheckViolation> {
+    let invalid_plan_syntax_options = rules
+        .source_rules
+        .iter()
+        .filter_map(|rule| {
+            if let SourceRuleOptions::Plan(PlanRuleOptions::InvalidPlanSyntax(options)) =
+                &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+    let missing_plan_variable_options = rules
+        .source_rules
+        .iter()
+        .filter_map(|rule| {
+            if let SourceRuleOptions::Plan(PlanRuleOptions::MissingPlanVariable(options)) =
+                &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+    let deprecated_callback_options = rules
+        .source_rules
+        .iter()
+        .filter_map(|rule| {
+            if let SourceRuleOptions::Plan(PlanRuleOptions::DeprecatedCallback(options)) =
+                &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+    let unquoted_source_variable_options = rules
+        .source_rules
+        .iter()
+        .filter_map(|rule| {
+            if let SourceRuleOptions::Plan(PlanRuleOptions::UnquotedSourceVariable(options)) =
+                &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+    let plan_name_mismatch_options = rules
+        .source_rules
+        .iter()
+        .filter_map(|rule| {
+            if let SourceRuleOptions::Plan(PlanRuleOptions::PlanNameMismatch(options)) =
+                &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+
+    let mut violations = Vec::new();
+    let plan_file = match PlanFile::parse(plan_source) {
+        Ok(plan_file) => plan_file,
+        Err(err) => {
+            violations.push(LeveledPlanCheckViolation {
+                level: invalid_plan_syntax_options.level,
+                violation: PlanRule::InvalidPlanSyntax(InvalidPlanSyntax {
+                    line: err.line,
+                    message: err.message,
+                }),
+            });
+            return violations;
+        }
+    };
+
+    for variable in REQUIRED_PLAN_VARIABLES {
+        let is_set = plan_file.assignment(variable).is_some()
+            || (*variable == "pkg_version" && plan_file.function("pkg_version").is_some());
+        if !is_set {
+            violations.push(LeveledPlanCheckViolation {
+                level: missing_plan_variable_options.level,
+                violation: PlanRule::MissingPlanVariable(MissingPlanVariable {
+                    variable: variable.to_string(),
+                }),
+            });
+        }
+    }
+
+    for function in plan_file.functions.iter() {
+        if let Some(phase) = function.name.strip_prefix(DEFAULT_CALLBACK_PREFIX) {
+            violations.push(LeveledPlanCheckViolation {
+                level: deprecated_callback_options.level,
+                violation: PlanRule::DeprecatedCallback(DeprecatedCallback {
+                    line: function.line,
+                    callback: function.name.clone(),
+                    replacement: format!("do_{}", phase),
+                }),
+            });
+        }
+    }
+
+    if let Some(assignment) = plan_file.assignment("pkg_source") {
+        if let AssignmentValue::Scalar(value) = &assignment.value {
+            if value
+                .parts
+                .iter()
+                .any(|part| part.quoting == Quoting::None && part.has_expansion())
+            {
+                violations.push(LeveledPlanCheckViolation {
+                    level: unquoted_source_variable_options.level,
+                    violation: PlanRule::UnquotedSourceVariable(UnquotedSourceVariable {
+                        line: assignment.line,
+                        value: value.parts.iter().map(|part| part.text.as_str()).collect(),
+                    }),
+                });
+            }
+        }
+    }
+
+    if let (Some(plan_dir_name), Some(assignment)) =
+        (plan_dir_name, plan_file.assignment("pkg_name"))
+    {
+        if let AssignmentValue::Scalar(value) = &assignment.value {
+            if let Some(pkg_name) = value.literal() {
+                if pkg_name != plan_dir_name {
+                    violations.push(LeveledPlanCheckViolation {
+                        level: plan_name_mismatch_options.level,
+                        violation: PlanRule::PlanNameMismatch(PlanNameMismatch {
+                            line: assignment.line,
+                            pkg_name,
+                            plan_dir_name: plan_dir_name.to_string(),
+                        }),
+                    });
+                }
+            }
+        }
+    }
+
+    violations.retain(|violation| violation.level != ViolationLevel::Off);
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_levels(violations: &[LeveledPlanCheckViolation]) -> Vec<(String, ViolationLevel)> {
+        violations
+            .iter()
+            .map(|violation| {
+                (
+                    serde_json::to_value(&violation.violation).unwrap()["rule"]
+                        .as_str()
+                        .unwrap()
+                        .to_string(),
+                    violation.level,
+                )
+            })
+            .collect()
+    }
+
+    const PLAN_SOURCE: &str = r#"pkg_name=hello
+pkg_origin=core
+pkg_source=https://example.com/${pkg_name}.tar.gz
+do_default_build() {
+  make
+}
+"#;
+
+    #[test]
+    fn plan_lint_default_levels() {
+        let violations = plan_lint(&PlanContextConfig::default(), PLAN_SOURCE, Some("world"));
+        assert_eq!(
+            rule_levels(&violations),
+            vec![
+                ("missing-plan-variable".to_string(), ViolationLevel::Error),
+                ("deprecated-callback".to_string(), ViolationLevel::Warn),
+                ("unquoted-source-variable".to_string(), ViolationLevel::Warn),
+                ("plan-name-mismatch".to_string(), ViolationLevel::Warn),
+            ]
+        );
+        let violations = plan_lint(&PlanContextConfig::default(), "do_build() {\n", None);
+        assert_eq!(
+            rule_levels(&violations),
+            vec![("invalid-plan-syntax".to_string(), ViolationLevel::Error)]
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn plan_lint_configured_levels() {
+        use crate::core::PackageTarget;
+
+        let plan_config = PlanContextConfig::from_str(
+            r#"
+[rules]
+missing-plan-variable = "warn"
+deprecated-callback = { level = "error" }
+plan-name-mismatch = "off"
+"#,
+            PackageTarget::default(),
+        )
+        .unwrap();
+        let rules = PlanContextConfig::default().merge(&plan_config);
+        let violations = plan_lint(&rules, PLAN_SOURCE, Some("world"));
+        assert_eq!(
+            rule_levels(&violations),
+            vec![
+                ("missing-plan-variable".to_string(), ViolationLevel::Warn),
+                ("deprecated-callback".to_string(), ViolationLevel::Error),
+                ("unquoted-source-variable".to_string(), ViolationLevel::Warn),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1296 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("{message} at line {line}")]
+pub(crate) struct PlanParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Quoting of a part of a word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Quoting {
+    None,
+    Single,
+    Double,
+}
+
+/// Part of a word with a single kind of quoting, the text excludes the quotes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WordPart {
+    pub text: String,
+    pub quoting: Quoting,
+}
+
+impl WordPart {
+    /// Determines whether the part contains a parameter or command expansion
+    pub fn has_expansion(&self) -> bool {
+        self.quoting != Quoting::Single && self.text.contains(['$', '`'])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Word {
+    pub parts: Vec<WordPart>,
+    pub line: usize,
+}
+
+impl Word {
+    /// Value of the word if it does not contain any expansions
+    pub fn literal(&self) -> Option<String> {
+        if self.parts.iter().any(WordPart::has_expansion) {
+            None
+        } else {
+            Some(self.parts.iter().map(|part| part.text.as_str()).collect())
+        }
+    }
+
+    /// Value of the word with the given variables substituted, if it does not
+    /// contain any other expansions or unquoted glob characters
+    pub fn expand(&self, variables: &[(&str, &str)]) -> Option<String> {
+        let mut value = String::new();
+        for part in self.parts.iter() {
+            match part.quoting {
+                Quoting::Single => {
+                    value.push_str(&part.text);
+                    continue;
+                }
+                Quoting::None if part.text.contains(['*', '?', '[', '~']) => return None,
+                Quoting::None | Quoting::Double => {}
+            }
+            let mut rest = part.text.as_str();
+            while let Some(index) = rest.find(['$', '`']) {
+                value.push_str(&rest[..index]);
+                rest = &rest[index..];
+                let (name, length) = if rest.starts_with('`') {
+                    return None;
+                } else if let Some(braced) = rest.strip_prefix("${") {
+                    let end = braced.find('}')?;
+                    (&braced[..end], end + 3)
+                } else {
+                    let end = rest[1..]
+                        .find(|char: char| !char.is_ascii_alphanumeric() && char != '_')
+                        .unwrap_or(rest.len() - 1);
+                    (&rest[1..end + 1], end + 1)
+                };
+                let (_, variable_value) =
+                    variables.iter().find(|(variable, _)| *variable == name)?;
+                value.push_str(variable_value);
+                rest = &rest[length..];
+            }
+            value.push_str(rest);
+        }
+        Some(value)
+    }
+
+    /// Bodies of the command substitutions in the word, like `date` in `$(date)`
+    pub fn command_substitutions(&self) -> Vec<String> {
+        let mut substitutions = Vec::new();
+        for part in self.parts.iter() {
+            if part.quoting == Quoting::Single {
+                continue;
+            }
+            let chars = part.text.chars().collect::<Vec<_>>();
+            let mut index = 0;
+            while index < chars.len() {
+                match chars[index] {
+                    '\\' => index += 1,
+                    '`' => {
+                        let mut end = index + 1;
+                        while end < chars.len() && chars[end] != '`' {
+                            end += if chars[end] == '\\' { 2 } else { 1 };
+                        }
+                        let end = end.min(chars.len());
+                        substitutions.push(chars[index + 1..end].iter().collect());
+                        index = end;
+                    }
+                    // Arithmetic expansions like $((1 + 2)) run no commands
+                    '$' if chars.get(index + 1) == Some(&'(')
+                        && chars.get(index + 2) != Some(&'(') =>
+                    {
+                        let mut depth = 1;
+                        let mut end = index + 2;
+                        while end < chars.len() {
+                            match chars[end] {
+                                '\\' => end += 1,
+                                quote @ ('\'' | '"') => {
+                                    end += 1;
+                                    while end < chars.len() && chars[end] != quote {
+                                        end += 1;
+                                    }
+                                }
+                                '(' => depth += 1,
+                                ')' => {
+                                    depth -= 1;
+                                    if depth == 0 {
+                                        break;
+                                    }
+                                }
+                                _ => {}
+                            }
+                            end += 1;
+                        }
+                        let end = end.min(chars.len());
+                        substitutions.push(chars[index + 2..end].iter().collect());
+                        index = end;
+                    }
+                    _ => {}
+                }
+                index += 1;
+            }
+        }
+        substitutions
+    }
+
+    fn text(&self) -> String {
+        self.parts.iter().map(|part| part.text.as_str()).collect()
+    }
+
+    /// Whether the word is the given unquoted keyword
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.parts.len() == 1
+            && self.parts[0].quoting == Quoting::None
+            && self.parts[0].text == keyword
+    }
+
+    fn push(&mut self, char: char, quoting: Quoting) {
+        match self.parts.last_mut() {
+            Some(part) if part.quoting == quoting => part.text.push(char),
+            _ => self.parts.push(WordPart {
+                text: char.to_string(),
+                quoting,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AssignmentValue {
+    Scalar(Word),
+    Array(Vec<Word>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Assignment {
+    pub name: String,
+    pub value: AssignmentValue,
+    pub line: usize,
+    /// Whether the assignment is made inside a function instead of at the top
+    /// level of the plan
+    pub in_function: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FunctionDefinition {
+    pub name: String,
+    pub line: usize,
+}
+
+/// A simple command with its arguments, the assignments and redirections around
+/// it are left out. The `for`, `select` and `case` statements are recorded as commands
+/// with the words up to their body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SimpleCommand {
+    pub words: Vec<Word>,
+    pub line: usize,
+    /// Name of the innermost function the command is run in
+    pub function: Option<String>,
+}
+
+impl SimpleCommand {
+    /// Name of the command if it does not contain any expansions
+    pub fn name(&self) -> Option<String> {
+        self.words.first().and_then(Word::literal)
+    }
+}
+
+/// Variable assignments, function definitions and commands of a plan file
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct PlanFile {
+    pub assignments: Vec<Assignment>,
+    pub functions: Vec<FunctionDefinition>,
+    pub commands: Vec<SimpleCommand>,
+}
+
+impl PlanFile {
+    /// Parses the source of a bash plan file. Commands are not interpreted, only
+    /// the structure needed to find the assignments, functions and commands is parsed.
+    pub fn parse(source: &str) -> Result<PlanFile, PlanParseError> {
+        let tokens = Lexer::new(source).tokens()?;
+        let mut plan_file = PlanFile::default();
+        // Open braces, with the name of the function whose body they start
+        let mut braces: Vec<(Option<String>, usize)> = Vec::new();
+        let mut function_pending = None;
+        let mut command_start = true;
+        // Whether the words read are arguments of the last command
+        let mut command_open = false;
+        // Number of open case statements, and whether the patterns of one are read
+        let mut case_depth: usize = 0;
+        let mut case_patterns = false;
+        let mut index = 0;
+        while index < tokens.len() {
+            // The patterns of a case statement are read up to their closing parenthesis
+            if case_patterns {
+                match &tokens[index] {
+                    Token::Word(word) if word.is_keyword("esac") => {
+                        case_depth -= 1;
+                        case_patterns = false;
+                    }
+                    Token::Operator(")") => {
+                        case_patterns = false;
+                        command_start = true;
+                    }
+                    _ => {}
+                }
+                index += 1;
+                continue;
+            }
+            let function = braces
+                .iter()
+                .rev()
+                .find_map(|(function, _)| function.clone());
+            let in_function = function.is_some();
+            match &tokens[index] {
+                Token::Word(word) if command_start => {
+                    let text = word.text();
+                    let unquoted = word.parts.len() == 1 && word.parts[0].quoting == Quoting::None;
+                    if unquoted && text == "{" {
+                        braces.push((function_pending.take(), word.line));
+                    } else if unquoted && text == "}" {
+                        if braces.pop().is_none() {
+                            return Err(PlanParseError {
+                                line: word.line,
+                                message: "Unexpected '}'".to_string(),
+                            });
+                        }
+                        command_start = false;
+                    } else if unquoted && text == "function" {
+                        if let Some(Token::Word(name)) = tokens.get(index + 1) {
+                            plan_file.functions.push(FunctionDefinition {
+                                name: name.text(),
+                                line: word.line,
+                            });
+                            function_pending = Some(name.text());
+                            index += 1;
+                            // The parentheses after the name are optional
+                            if let (Some(Token::Operator("(")), Some(Token::Operator(")"))) =
+                                (tokens.get(index + 1), tokens.get(index + 2))
+                            {
+                                index += 2;
+                            }
+                        }
+                    } else if let (true, Some(Token::Operator("(")), Some(Token::Operator(")"))) = (
+                        unquoted && is_name(&text),
+                        tokens.get(index + 1),
+                        tokens.get(index + 2),
+                    ) {
+                        plan_file.functions.push(FunctionDefinition {
+                            name: text.clone(),
+                            line: word.line,
+                        });
+                        function_pending = Some(text);
+                        index += 2;
+                    } else if unquoted && DECLARATION_BUILTINS.contains(&text.as_str()) {
+                        // The assignments made by declarations are recorded as well
+                        while let Some(Token::Word(option)) = tokens.get(index + 1) {
+                            if !option.text().starts_with('-') {
+                                break;
+                            }
+                            index += 1;
+                        }
+                    } else if unquoted && (text == "for" || text == "select") {
+                        // The variable and the words of the loop are not commands
+                        let mut words = vec![word.clone()];
+                        while let Some(Token::Word(word)) = tokens.get(index + 1) {
+                            words.push(word.clone());
+                            index += 1;
+                        }
+                        plan_file.commands.push(SimpleCommand {
+                            words,
+                            line: word.line,
+                            function,
+                        });
+                    } else if unquoted && text == "case" {
+                        let mut words = vec![word.clone()];
+                        while let Some(Token::Word(word)) = tokens.get(index + 1) {
+                            words.push(word.clone());
+                            index += 1;
+                            if word.is_keyword("in") {
+                                break;
+                            }
+                        }
+                        plan_file.commands.push(SimpleCommand {
+                            words,
+                            line: word.line,
+                            function,
+                        });
+                        case_depth += 1;
+                        case_patterns = true;
+                    } else if unquoted && text == "esac" {
+                        case_depth = case_depth.saturating_sub(1);
+                    } else if unquoted && text == "[[" {
+                        // Conditional expressions may contain operators up to their ']]'
+                        let mut words = vec![word.clone()];
+                        while let Some(token) = tokens.get(index + 1) {
+                            index += 1;
+                            if let Token::Word(word) = token {
+                                words.push(word.clone());
+                                if word.is_keyword("]]") {
+                                    break;
+                                }
+                            }
+                        }
+                        plan_file.commands.push(SimpleCommand {
+                            words,
+                            line: word.line,
+                            function,
+                        });
+                        command_start = false;
+                        command_open = true;
+                    } else if let Some((name, value)) = assignment_split(word) {
+                        plan_file.assignments.push(Assignment {
+                            name,
+                            value: AssignmentValue::Scalar(value),
+                            line: word.line,
+                            in_function,
+                        });
+                    } else if !RESERVED_WORDS.contains(&text.as_str()) || !unquoted {
+                        plan_file.commands.push(SimpleCommand {
+                            words: vec![word.clone()],
+                            line: word.line,
+                            function,
+                        });
+                        command_start = false;
+                        command_open = true;
+                    }
+                }
+                Token::Word(word) => {
+                    if let (true, Some(command)) = (command_open, plan_file.commands.last_mut()) {
+                        command.words.push(word.clone());
+                    }
+                }
+                // The target of the redirection is not an argument
+                Token::Redirection => {
+                    if let Some(Token::Word(_)) = tokens.get(index + 1) {
+                        index += 1;
+                    }
+                }
+                Token::ArrayStart(name, line) => {
+                    let mut items = Vec::new();
+                    index += 1;
+                    loop {
+                        match tokens.get(index) {
+                            Some(Token::Word(word)) => items.push(word.clone()),
+                            Some(Token::Newline) => {}
+                            Some(Token::Operator(")")) => break,
+                            _ => {
+                                return Err(PlanParseError {
+                                    line: *line,
+                                    message: format!("Unterminated array '{}'", name),
+                                })
+                            }
+                        }
+                        index += 1;
+                    }
+                    plan_file.assignments.push(Assignment {
+                        name: name.clone(),
+                        value: AssignmentValue::Array(items),
+                        line: *line,
+                        in_function,
+                    });
+                }
+                Token::Operator(operator) => {
+                    command_start = true;
+                    command_open = false;
+                    if *operator == ";;" && case_depth > 0 {
+                        case_patterns = true;
+                    }
+                }
+                Token::Newline => {
+                    command_start = true;
+                    command_open = false;
+                }
+            }
+            index += 1;
+        }
+        if let Some((_, line)) = braces.pop() {
+            return Err(PlanParseError {
+                line,
+                message: "Unclosed '{'".to_string(),
+            });
+        }
+        Ok(plan_file)
+    }
+
+    /// Finds the last top level assignment of a variable
+    pub fn assignment(&self, name: &str) -> Option<&Assignment> {
+        self.assignments
+            .iter()
+            .rev()
+            .find(|assignment| !assignment.in_function && assignment.name == name)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&FunctionDefinition> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+/// Words after which a new command starts
+const RESERVED_WORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "do", "done", "while", "until", "for", "in", "case",
+    "esac", "!", "time",
+];
+
+/// Builtins whose arguments can be assignments
+const DECLARATION_BUILTINS: &[&str] = &["declare", "export", "local", "readonly", "typeset"];
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|char| char.is_ascii_alphabetic() || char == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+/// Splits an assignment word like `name=value` into the name and the value
+fn assignment_split(word: &Word) -> Option<(String, Word)> {
+    let first = word.parts.first()?;
+    if first.quoting != Quoting::None {
+        return None;
+    }
+    let (name, rest) = first.text.split_once('=')?;
+    let name = name.strip_suffix('+').unwrap_or(name);
+    if !is_name(name) {
+        return None;
+    }
+    let mut value = Word {
+        parts: Vec::new(),
+        line: word.line,
+    };
+    if !rest.is_empty() {
+        value.parts.push(WordPart {
+            text: rest.to_string(),
+            quoting: Quoting::None,
+        });
+    }
+    value.parts.extend(word.parts[1..].iter().cloned());
+    Some((name.to_string(), value))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(Word),
+    /// Start of an array assignment like `name=(`, with the name and line
+    ArrayStart(String, usize),
+    Operator(&'static str),
+    /// Redirection operator like `>` or `<<<`, here-documents are read by the lexer
+    Redirection,
+    Newline,
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    /// Delimiters of the here-documents whose bodies start on the next line,
+    /// with whether leading tabs are stripped
+    heredocs: Vec<(String, bool)>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Lexer<'a> {
+        Lexer {
+            chars: source.chars().peekable(),
+            line: 1,
+            heredocs: Vec::new(),
+        }
+    }
+
+    fn error(&self, message: &str) -> PlanParseError {
+        PlanParseError {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.chars.next();
+        if char == Some('\n') {
+            self.line += 1;
+        }
+        char
+    }
+
+    fn tokens(mut self) -> Result<Vec<Token>, PlanParseError> {
+        let mut tokens = Vec::new();
+        while let Some(&char) = self.chars.peek() {
+            match char {
+                ' ' | '\t' | '\r' => {
+                    self.next();
+                }
+                '\\' => {
+                    self.next();
+                    // Line continuation
+                    if self.chars.peek() == Some(&'\n') {
+                        self.next();
+                    } else {
+                        let mut word = Word {
+                            parts: Vec::new(),
+                            line: self.line,
+                        };
+                        if let Some(char) = self.next() {
+                            word.push(char, Quoting::Single);
+                        }
+                        tokens.push(self.word_read(word)?);
+                    }
+                }
+                '#' => {
+                    while self.chars.peek().is_some_and(|char| *char != '\n') {
+                        self.next();
+                    }
+                }
+                '\n' => {
+                    self.next();
+                    tokens.push(Token::Newline);
+                    self.heredocs_skip()?;
+                }
+                // Arithmetic commands like (( i++ )) are read as a single word
+                '(' if self.chars.clone().nth(1) == Some('(') => {
+                    let mut word = Word {
+                        parts: Vec::new(),
+                        line: self.line,
+                    };
+                    self.next();
+                    word.push('(', Quoting::None);
+                    self.balanced_read(&mut word, '(', ')', Quoting::None)?;
+                    tokens.push(Token::Word(word));
+                }
+                ';' | '&' | '|' | '(' | ')' => {
+                    self.next();
+                    let operator = match (char, self.chars.peek()) {
+                        (';', Some(';')) => {
+                            self.next();
+                            ";;"
+                        }
+                        ('&', Some('&')) => {
+                            self.next();
+                            "&&"
+                        }
+                        ('|', Some('|')) => {
+                            self.next();
+                            "||"
+                        }
+                        (';', _) => ";",
+                        ('&', _) => "&",
+                        ('|', _) => "|",
+                        ('(', _) => "(",
+                        _ => ")",
+                    };
+                    tokens.push(Token::Operator(operator));
+                }
+                '<' | '>' => {
+                    self.next();
+                    if char == '<' && self.chars.peek() == Some(&'<') {
+                        self.next();
+                        if self.chars.peek() == Some(&'<') {
+                            // Here-string, the word after it is read normally
+                            self.next();
+                            tokens.push(Token::Redirection);
+                        } else {
+                            let strip_tabs = self.chars.peek() == Some(&'-');
+                            if strip_tabs {
+                                self.next();
+                            }
+                            while self.chars.peek().is_some_and(|char| *char == ' ') {
+                                self.next();
+                            }
+                            let line = self.line;
+                            let Token::Word(delimiter) = self.word_read(Word {
+                                parts: Vec::new(),
+                                line,
+                            })?
+                            else {
+                                return Err(self.error("Missing here-document delimiter"));
+                            };
+                            if delimiter.parts.is_empty() {
+                                return Err(self.error("Missing here-document delimiter"));
+                            }
+                            self.heredocs.push((delimiter.text(), strip_tabs));
+                        }
+                    } else {
+                        if matches!(self.chars.peek(), Some('>') | Some('&')) {
+                            self.next();
+                        }
+                        tokens.push(Token::Redirection);
+                    }
+                }
+                _ => {
+                    let word = Word {
+                        parts: Vec::new(),
+                        line: self.line,
+                    };
+                    let token = self.word_read(word)?;
+                    // File descriptor numbers like the 2 in `2>` belong to the redirection
+                    if let Token::Word(word) = &token {
+                        if matches!(self.chars.peek(), Some('<') | Some('>'))
+                            && word.parts.len() == 1
+                            && word.parts[0].quoting == Quoting::None
+                            && word.parts[0].text.chars().all(|char| char.is_ascii_digit())
+                        {
+                            continue;
+                        }
+                    }
+                    tokens.push(token);
+                }
+            }
+        }
+        if !self.heredocs.is_empty() {
+            return Err(self.error("Unterminated here-document"));
+        }
+        Ok(tokens)
+    }
+
+    /// Skips the bodies of the here-documents started on the previous line
+    fn heredocs_skip(&mut self) -> Result<(), PlanParseError> {
+        for (delimiter, strip_tabs) in std::mem::take(&mut self.heredocs) {
+            let start_line = self.line;
+            loop {
+                if self.chars.peek().is_none() {
+                    return Err(PlanParseError {
+                        line: start_line,
+                        message: format!("Unterminated here-document '{}'", delimiter),
+                    });
+                }
+                let mut line = String::new();
+                while let Some(char) = self.next() {
+                    if char == '\n' {
+                        break;
+                    }
+                    line.push(char);
+                }
+                let line = if strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    line.as_str()
+                };
+                if line == delimiter {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the rest of a word, the word may already contain characters
+    fn word_read(&mut self, mut word: Word) -> Result<Token, PlanParseError> {
+        while let Some(&char) = self.chars.peek() {
+            match char {
+                ' ' | '\t' | '\r' | '\n' | ';' | '&' | '|' | '<' | '>' | ')' => break,
+                '(' => {
+                    let text = word.text();
+                    let name = text
+                        .strip_suffix("+=")
+                        .or_else(|| text.strip_suffix('='))
+                        .filter(|name| is_name(name));
+                    match name {
+                        Some(name) if word.parts.len() == 1 => {
+                            self.next();
+                            return Ok(Token::ArrayStart(name.to_string(), word.line));
+                        }
+                        // Extended globs like @(a|b) are part of the word
+                        _ if text.ends_with(['@', '?', '*', '+', '!']) => {
+                            self.next();
+                            word.push('(', Quoting::None);
+                            self.balanced_read(&mut word, '(', ')', Quoting::None)?;
+                        }
+                        _ => break,
+                    }
+                }
+                '\\' => {
+                    self.next();
+                    match self.next() {
+                        Some('\n') => {}
+                        Some(char) => word.push(char, Quoting::Single),
+                        None => {}
+                    }
+                }
+                '\'' => {
+                    self.next();
+                    word.parts.push(WordPart {
+                        text: String::new(),
+                        quoting: Quoting::Single,
+                    });
+                    loop {
+                        match self.next() {
+                            Some('\'') => break,
+                            Some(char) => word.push(char, Quoting::Single),
+                            None => return Err(self.error("Unterminated single quote")),
+                        }
+                    }
+                }
+                '"' => {
+                    self.next();
+                    self.double_quoted_read(&mut word)?;
+                }
+                // ANSI-C quoting like $'a\tb', the escapes are decoded into a literal
+                '$' if self.chars.clone().nth(1) == Some('\'') => {
+                    self.next();
+                    self.next();
+                    let text = self.ansi_c_quoted_read()?;
+                    word.parts.push(WordPart {
+                        text,
+                        quoting: Quoting::Single,
+                    });
+                }
+                // Locale specific translation like $"text", read as a double quoted string
+                '$' if self.chars.clone().nth(1) == Some('"') => {
+                    self.next();
+                    self.next();
+                    self.double_quoted_read(&mut word)?;
+                }
+                '$' | '`' => self.expansion_read(&mut word, Quoting::None)?,
+                _ => {
+                    self.next();
+                    word.push(char, Quoting::None);
+                }
+            }
+        }
+        Ok(Token::Word(word))
+    }
+
+    /// Reads the rest of an ANSI-C quoted string after the opening `$'`, returning
+    /// its text with the backslash escapes decoded
+    fn ansi_c_quoted_read(&mut self) -> Result<String, PlanParseError> {
+        let line = self.line;
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(text),
+                Some('\\') => {
+                    let Some(char) = self.next() else {
+                        break;
+                    };
+                    match char {
+                        'n' => text.push('\n'),
+                        't' => text.push('\t'),
+                        'r' => text.push('\r'),
+                        'a' => text.push('\x07'),
+                        'b' => text.push('\x08'),
+                        'e' | 'E' => text.push('\x1b'),
+                        'f' => text.push('\x0c'),
+                        'v' => text.push('\x0b'),
+                        '\\' | '\'' | '"' | '?' => text.push(char),
+                        'x' => text.extend(self.escaped_char_read(16, 2)),
+                        'u' => text.extend(self.escaped_char_read(16, 4)),
+                        'U' => text.extend(self.escaped_char_read(16, 8)),
+                        '0'..='7' => {
+                            let mut value = char.to_digit(8).unwrap();
+                            for _ in 0..2 {
+                                match self.chars.peek().and_then(|char| char.to_digit(8)) {
+                                    Some(digit) => {
+                                        self.next();
+                                        value = value * 8 + digit;
+                                    }
+                                    None => break,
+                                }
+                            }
+                            text.extend(char::from_u32(value & 0xff));
+                        }
+                        'c' => {
+                            if let Some(char) = self.next() {
+                                text.extend(char::from_u32(
+                                    char.to_ascii_uppercase() as u32 & 0x1f,
+                                ));
+                            }
+                        }
+                        char => {
+                            text.push('\\');
+                            text.push(char);
+                        }
+                    }
+                }
+                Some(char) => text.push(char),
+                None => break,
+            }
+        }
+        Err(PlanParseError {
+            line,
+            message: "Unterminated ANSI-C quote".to_string(),
+        })
+    }
+
+    /// Reads up to the given number of digits of a numeric escape, returns the
+    /// escaped character, or nothing if the escape has no digits
+    fn escaped_char_read(&mut self, radix: u32, max_digits: usize) -> Option<char> {
+        let mut value = None;
+        for _ in 0..max_digits {
+            match self.chars.peek().and_then(|char| char.to_digit(radix)) {
+                Some(digit) => {
+                    self.next();
+                    value = Some(value.unwrap_or(0) * radix + digit);
+                }
+                None => break,
+            }
+        }
+        value.and_then(char::from_u32)
+    }
+
+    fn double_quoted_read(&mut self, word: &mut Word) -> Result<(), PlanParseError> {
+        let line = self.line;
+        word.parts.push(WordPart {
+            text: String::new(),
+            quoting: Quoting::Double,
+        });
+        loop {
+            match self.chars.peek() {
+                Some('"') => {
+                    self.next();
+                    return Ok(());
+                }
+                Some('\\') => {
+                    self.next();
+                    match self.next() {
+                        Some('\n') => {}
+                        Some(char) if matches!(char, '$' | '`' | '"' | '\\') => {
+                            word.push(char, Quoting::Double)
+                        }
+                        Some(char) => {
+                            word.push('\\', Quoting::Double);
+                            word.push(char, Quoting::Double);
+                        }
+                        None => break,
+                    }
+                }
+                Some('$') | Some('`') => self.expansion_read(word, Quoting::Double)?,
+                Some(_) => {
+                    let char = self.next().unwrap();
+                    word.push(char, Quoting::Double);
+                }
+                None => break,
+            }
+        }
+        Err(PlanParseError {
+            line,
+            message: "Unterminated double quote".to_string(),
+        })
+    }
+
+    /// Reads a parameter, command or arithmetic expansion starting with `$` or a
+    /// backtick into the word
+    fn expansion_read(&mut self, word: &mut Word, quoting: Quoting) -> Result<(), PlanParseError> {
+        let char = self.next().unwrap();
+        word.push(char, quoting);
+        if char == '`' {
+            loop {
+                match self.next() {
+                    Some('`') => {
+                        word.push('`', quoting);
+                        return Ok(());
+                    }
+                    Some('\\') => {
+                        word.push('\\', quoting);
+                        if let Some(char) = self.next() {
+                            word.push(char, quoting);
+                        }
+                    }
+                    Some(char) => word.push(char, quoting),
+                    None => return Err(self.error("Unterminated command substitution")),
+                }
+            }
+        }
+        match self.chars.peek() {
+            Some('{') => {
+                self.next();
+                word.push('{', quoting);
+                self.balanced_read(word, '{', '}', quoting)
+            }
+            Some('(') => {
+                self.next();
+                word.push('(', quoting);
+                self.balanced_read(word, '(', ')', quoting)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads the contents of an expansion up to its closing character, skipping
+    /// nested quotes and expansions
+    fn balanced_read(
+        &mut self,
+        word: &mut Word,
+        open: char,
+        close: char,
+        quoting: Quoting,
+    ) -> Result<(), PlanParseError> {
+        let line = self.line;
+        let mut depth = 1;
+        while let Some(char) = self.next() {
+            word.push(char, quoting);
+            match char {
+                '\\' => {
+                    if let Some(char) = self.next() {
+                        word.push(char, quoting);
+                    }
+                }
+                '\'' if open == '(' => {
+                    while let Some(char) = self.next() {
+                        word.push(char, quoting);
+                        if char == '\'' {
+                            break;
+                        }
+                    }
+                }
+                // ANSI-C quotes are kept as they are, they may contain escaped quotes
+                '$' if self.chars.peek() == Some(&'\'') => {
+                    word.push(self.next().unwrap(), quoting);
+                    while let Some(char) = self.next() {
+                        word.push(char, quoting);
+                        match char {
+                            '\\' => {
+                                if let Some(char) = self.next() {
+                                    word.push(char, quoting);
+                                }
+                            }
+                            '\'' => break,
+                            _ => {}
+                        }
+                    }
+                }
+                '"' => {
+                    // Nested double quotes are kept as part of the expansion
+                    let mut nested = Word::default();
+                    self.double_quoted_read(&mut nested)?;
+                    for char in nested.text().chars() {
+                        word.push(char, quoting);
+                    }
+                    word.push('"', quoting);
+                }
+                char if char == open => depth += 1,
+                char if char == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(PlanParseError {
+            line,
+            message: format!("Unterminated expansion, missing '{}'", close),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(plan_file: &PlanFile, name: &str) -> Word {
+        match &plan_file.assignment(name).unwrap().value {
+            AssignmentValue::Scalar(word) => word.clone(),
+            AssignmentValue::Array(_) => panic!("{} is an array", name),
+        }
+    }
+
+    #[test]
+    fn plan_file_assignments_parsing() {
+        let plan_file = PlanFile::parse(
+            r#"pkg_name=hello
+pkg_origin="core"
+pkg_version='1.0'
+pkg_source="https://example.com/${pkg_name}-${pkg_version}.tar.gz"
+pkg_deps=(
+  core/glibc # libc
+  "core/gcc-libs"
+)
+export HAB_ENV_VAR=1
+pkg_version=2.0
+"#,
+        )
+        .unwrap();
+        assert_eq!(scalar(&plan_file, "pkg_name").literal().unwrap(), "hello");
+        assert_eq!(scalar(&plan_file, "pkg_origin").literal().unwrap(), "core");
+        assert_eq!(scalar(&plan_file, "pkg_version").literal().unwrap(), "2.0");
+        assert_eq!(plan_file.assignment("pkg_version").unwrap().line, 10);
+        assert_eq!(scalar(&plan_file, "pkg_source").literal(), None);
+        assert_eq!(scalar(&plan_file, "HAB_ENV_VAR").literal().unwrap(), "1");
+        let AssignmentValue::Array(deps) = &plan_file.assignment("pkg_deps").unwrap().value else {
+            panic!("pkg_deps is not an array");
+        };
+        assert_eq!(
+            deps.iter()
+                .map(|dep| dep.literal().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["core/glibc", "core/gcc-libs"]
+        );
+    }
+
+    #[test]
+    fn plan_file_functions_parsing() {
+        let plan_file = PlanFile::parse(
+            r#"pkg_name=hello
+do_build() {
+  local pkg_name=inner
+  make
+}
+function do_install {
+  if [ -f x ]; then
+    pkg_version=inner
+  fi
+}
+function do_check() { make check; }
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            plan_file
+                .functions
+                .iter()
+                .map(|function| (function.name.as_str(), function.line))
+                .collect::<Vec<_>>(),
+            vec![("do_build", 2), ("do_install", 6), ("do_check", 11)]
+        );
+        // Assignments inside functions are not top level assignments
+        assert_eq!(scalar(&plan_file, "pkg_name").literal().unwrap(), "hello");
+        assert!(plan_file.assignment("pkg_version").is_none());
+        assert!(plan_file
+            .assignments
+            .iter()
+            .any(|assignment| assignment.name == "pkg_version" && assignment.in_function));
+    }
+
+    #[test]
+    fn plan_file_quoting_parsing() {
+        let plan_file = PlanFile::parse(
+            r#"a='single $quoted'
+b="double \"quoted\" \$value"
+c=unquoted\ value
+d="$(echo "nested ) quotes")"
+e=`echo backtick`
+f=mixed'single'"double"
+"#,
+        )
+        .unwrap();
+        assert_eq!(scalar(&plan_file, "a").literal().unwrap(), "single $quoted");
+        assert_eq!(scalar(&plan_file, "b").parts[0].quoting, Quoting::Double);
+        assert_eq!(
+            scalar(&plan_file, "b").parts[0].text,
+            r#"double "quoted" $value"#
+        );
+        assert_eq!(scalar(&plan_file, "c").literal().unwrap(), "unquoted value");
+        assert_eq!(scalar(&plan_file, "d").literal(), None);
+        assert_eq!(scalar(&plan_file, "e").literal(), None);
+        assert_eq!(
+            scalar(&plan_file, "f")
+                .parts
+                .iter()
+                .map(|part| part.quoting)
+                .collect::<Vec<_>>(),
+            vec![Quoting::None, Quoting::Single, Quoting::Double]
+        );
+        assert_eq!(
+            scalar(&plan_file, "f").literal().unwrap(),
+            "mixedsingledouble"
+        );
+    }
+
+    #[test]
+    fn plan_file_ansi_c_quoting_parsing() {
+        let plan_file = PlanFile::parse(
+            r#"a=$'a\'b'
+b=$'tab\there\nline'
+c=$'\x41\101é\\'
+d=prefix$'\'quoted\''suffix
+e=$''
+f=$"translated $value"
+g="$(printf $'%s\'\n' "x)")"
+h=after
+"#,
+        )
+        .unwrap();
+        assert_eq!(scalar(&plan_file, "a").literal().unwrap(), "a'b");
+        assert_eq!(
+            scalar(&plan_file, "b").literal().unwrap(),
+            "tab\there\nline"
+        );
+        assert_eq!(scalar(&plan_file, "c").literal().unwrap(), "AAé\\");
+        assert_eq!(
+            scalar(&plan_file, "d").literal().unwrap(),
+            "prefix'quoted'suffix"
+        );
+        assert_eq!(scalar(&plan_file, "e").literal().unwrap(), "");
+        // ANSI-C quoted strings never contain expansions
+        assert!(!scalar(&plan_file, "a").parts[0].has_expansion());
+        assert_eq!(scalar(&plan_file, "f").literal(), None);
+        assert_eq!(scalar(&plan_file, "f").parts[0].quoting, Quoting::Double);
+        assert_eq!(scalar(&plan_file, "g").literal(), None);
+        assert_eq!(plan_file.assignment("h").unwrap().line, 8);
+
+        assert_eq!(
+            PlanFile::parse("a=$'unterminated\\'\n").unwrap_err(),
+            PlanParseError {
+                line: 1,
+                message: "Unterminated ANSI-C quote".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn plan_file_commands_parsing() {
+        let plan_file = PlanFile::parse(
+            r#"source "$PLAN_CONTEXT/../plan.sh"
+pkg_name=hello
+CFLAGS=-O2 make -j 2> build.log && echo done
+do_build() {
+  . ./helpers.sh; helper_run
+}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            plan_file
+                .commands
+                .iter()
+                .map(|command| (
+                    command.name().unwrap(),
+                    command.words.len(),
+                    command.line,
+                    command.function.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("source".to_string(), 2, 1, None),
+                ("make".to_string(), 2, 3, None),
+                ("echo".to_string(), 2, 3, None),
+                (".".to_string(), 2, 5, Some("do_build")),
+                ("helper_run".to_string(), 1, 5, Some("do_build")),
+            ]
+        );
+        assert_eq!(plan_file.commands[0].words[1].literal(), None);
+    }
+
+    #[test]
+    fn word_expansion() {
+        let variables = [("PLAN_CONTEXT", "/src/hello"), ("SRC_PATH", "/src")];
+        let word = |source: &str| {
+            let plan_file = PlanFile::parse(&format!("source {}\n", source)).unwrap();
+            plan_file.commands[0].words[1].clone()
+        };
+        assert_eq!(
+            word("\"$PLAN_CONTEXT/../plan.sh\"")
+                .expand(&variables)
+                .unwrap(),
+            "/src/hello/../plan.sh"
+        );
+        assert_eq!(
+            word("${SRC_PATH}/lib/'$x'.sh").expand(&variables).unwrap(),
+            "/src/lib/$x.sh"
+        );
+        assert_eq!(word("./plan.sh").expand(&variables).unwrap(), "./plan.sh");
+        assert_eq!(word("\"$HOME/plan.sh\"").expand(&variables), None);
+        assert_eq!(
+            word("\"${PLAN_CONTEXT:-.}/plan.sh\"").expand(&variables),
+            None
+        );
+        assert_eq!(word("\"$(dirname $0)/plan.sh\"").expand(&variables), None);
+        assert_eq!(word("`pwd`/plan.sh").expand(&variables), None);
+        assert_eq!(word("./*.sh").expand(&variables), None);
+        assert_eq!(word("\"./*.sh\"").expand(&variables).unwrap(), "./*.sh");
+    }
+
+    #[test]
+    fn plan_file_heredocs_parsing() {
+        let plan_file = PlanFile::parse(
+            "do_install() {\n  cat > config <<EOF\npkg_name=heredoc\n}\n'unbalanced\nEOF\n  cat <<-'EOF' >> config\n\t{ \"quoted\": $'x }\n\tEOF\n}\npkg_name=hello\n",
+        )
+        .unwrap();
+        assert_eq!(scalar(&plan_file, "pkg_name").literal().unwrap(), "hello");
+        assert_eq!(plan_file.assignment("pkg_name").unwrap().line, 11);
+        assert_eq!(plan_file.assignments.len(), 1);
+
+        // Here-strings have no body
+        let plan_file = PlanFile::parse("read -r a <<< \"$value\"\npkg_name=hello\n").unwrap();
+        assert_eq!(scalar(&plan_file, "pkg_name").literal().unwrap(), "hello");
+
+        assert_eq!(
+            PlanFile::parse("cat <<EOF\nbody\n").unwrap_err(),
+            PlanParseError {
+                line: 2,
+                message: "Unterminated here-document 'EOF'".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn plan_file_multi_line_strings_parsing() {
+        let plan_file = PlanFile::parse(
+            r#"pkg_description="A description
+spanning { several
+lines"
+pkg_upstream_url='https://example.com
+'
+pkg_license=(
+  'Apache-2.0'
+  "MIT"
+)
+pkg_name=hello
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            scalar(&plan_file, "pkg_description").literal().unwrap(),
+            "A description\nspanning { several\nlines"
+        );
+        assert_eq!(plan_file.assignment("pkg_description").unwrap().line, 1);
+        assert_eq!(plan_file.assignment("pkg_upstream_url").unwrap().line, 4);
+        assert_eq!(plan_file.assignment("pkg_license").unwrap().line, 6);
+        assert_eq!(plan_file.assignment("pkg_name").unwrap().line, 10);
+    }
+
+    #[test]
+    fn plan_file_syntax_errors() {
+        let error = |source: &str| PlanFile::parse(source).unwrap_err();
+        assert_eq!(
+            error("do_build() {\n  make\n"),
+            PlanParseError {
+                line: 1,
+                message: "Unclosed '{'".to_string()
+            }
+        );
+        assert_eq!(
+            error("make\n}\n"),
+            PlanParseError {
+                line: 2,
+                message: "Unexpected '}'".to_string()
+            }
+        );
+        assert_eq!(
+            error("a='unterminated\n"),
+            PlanParseError {
+                line: 2,
+                message: "Unterminated single quote".to_string()
+            }
+        );
+        assert_eq!(
+            error("a=\"unterminated\n"),
+            PlanParseError {
+                line: 1,
+                message: "Unterminated double quote".to_string()
+            }
+        );
+        assert_eq!(
+            error("a=${unterminated\n"),
+            PlanParseError {
+                line: 1,
+                message: "Unterminated expansion, missing '}'".to_string()
+            }
+        );
+        assert_eq!(
+            error("pkg_deps=(\n  core/glibc\n"),
+            PlanParseError {
+                line: 1,
+                message: "Unterminated array 'pkg_deps'".to_string()
+            }
+        );
+    }
+}
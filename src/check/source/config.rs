@@ -0,0 +1,396 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::PathBuf,
+};
+
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    check::{
+        LeveledSourceCheckViolation, PlanContextConfig, SourceCheck, SourceCheckViolation,
+        SourceRuleOptions, ViolationLevel,
+    },
+    core::{
+        config_value_references, ArtifactContext, ConfigTemplate, DefaultConfig, Hook, PlanContext,
+        SourceContext, DEFAULT_CONFIG_FILE,
+    },
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "rule", content = "metadata")]
+pub(crate) enum ConfigRule {
+    #[serde(rename = "invalid-default-config")]
+    InvalidDefaultConfig(InvalidDefaultConfig),
+    #[serde(rename = "undefined-config-value")]
+    UndefinedConfigValue(UndefinedConfigValue),
+    #[serde(rename = "unused-config-value")]
+    UnusedConfigValue(UnusedConfigValue),
+}
+
+impl Display for ConfigRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigRule::InvalidDefaultConfig(rule) => write!(f, "{}", rule),
+            ConfigRule::UndefinedConfigValue(rule) => write!(f, "{}", rule),
+            ConfigRule::UnusedConfigValue(rule) => write!(f, "{}", rule),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "id", content = "options")]
+pub(crate) enum ConfigRuleOptions {
+    #[serde(rename = "invalid-default-config")]
+    InvalidDefaultConfig(InvalidDefaultConfigOptions),
+    #[serde(rename = "undefined-config-value")]
+    UndefinedConfigValue(UndefinedConfigValueOptions),
+    #[serde(rename = "unused-config-value")]
+    UnusedConfigValue(UnusedConfigValueOptions),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct InvalidDefaultConfig {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+impl Display for InvalidDefaultConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The default configuration is not valid TOML: {}",
+            self.path.display().white(),
+            self.error.trim().yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct InvalidDefaultConfigOptions {
+    #[serde(default = "InvalidDefaultConfigOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl InvalidDefaultConfigOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for InvalidDefaultConfigOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UndefinedConfigValue {
+    pub template: PathBuf,
+    pub value: String,
+}
+
+impl Display for UndefinedConfigValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The configuration value '{}' has no default in {}",
+            self.template.display().white(),
+            format!("cfg.{}", self.value).yellow(),
+            DEFAULT_CONFIG_FILE.blue()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UndefinedConfigValueOptions {
+    #[serde(default = "UndefinedConfigValueOptions::level")]
+    pub level: ViolationLevel,
+    /// Values that are expected to be provided at runtime, such as 'tls.cert'.
+    /// Values nested under them are also ignored.
+    #[serde(default)]
+    pub ignored_values: Vec<String>,
+}
+
+impl UndefinedConfigValueOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for UndefinedConfigValueOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_values: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnusedConfigValue {
+    pub value: String,
+}
+
+impl Display for UnusedConfigValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The configuration value '{}' is not used by any template, hook or export",
+            DEFAULT_CONFIG_FILE.white(),
+            self.value.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnusedConfigValueOptions {
+    #[serde(default = "UnusedConfigValueOptions::level")]
+    pub level: ViolationLevel,
+    /// Values that are used by other means, such as the service reading its
+    /// configuration from 'pkg_svc_config_path'. Values nested under them are
+    /// also ignored.
+    #[serde(default)]
+    pub ignored_values: Vec<String>,
+}
+
+impl UnusedConfigValueOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for UnusedConfigValueOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            ignored_values: Vec::new(),
+        }
+    }
+}
+
+/// Whether the value is one of the given values or nested under one of them
+fn is_ignored_value(ignored_values: &[String], value: &str) -> bool {
+    ignored_values.iter().any(|ignored_value| {
+        value == ignored_value
+            || value
+                .strip_prefix(ignored_value.as_str())
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Whether a reference to the `reference` value uses the `value` value, either
+/// because it is the same value, a parent of it or a value nested under it
+fn is_used_by(reference: &str, value: &str) -> bool {
+    reference.is_empty()
+        || reference == value
+        || value
+            .strip_prefix(reference)
+            .is_some_and(|rest| rest.starts_with('.'))
+        || reference
+            .strip_prefix(value)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ConfigCheck {}
+
+impl ConfigCheck {
+    fn plan_config_check(
+        &self,
+        rules: &PlanContextConfig,
+        plan_context: &PlanContext,
+    ) -> Vec<LeveledSourceCheckViolation> {
+        let mut violations = Vec::new();
+        let invalid_default_config_options = rules
+            .source_rules
+            .iter()
+            .filter_map(|rule| {
+                if let SourceRuleOptions::Config(ConfigRuleOptions::InvalidDefaultConfig(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+        let undefined_config_value_options = rules
+            .source_rules
+            .iter()
+            .filter_map(|rule| {
+                if let SourceRuleOptions::Config(ConfigRuleOptions::UndefinedConfigValue(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+        let unused_config_value_options = rules
+            .source_rules
+            .iter()
+            .filter_map(|rule| {
+                if let SourceRuleOptions::Config(ConfigRuleOptions::UnusedConfigValue(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let plan_dir = plan_context
+            .plan_path
+            .as_ref()
+            .parent()
+            .expect("Plan file must be inside a folder");
+        let plan_file = PathBuf::from(
+            plan_context
+                .plan_path
+                .as_ref()
+                .file_name()
+                .expect("Plan file must have a name"),
+        );
+        let default_config = match DefaultConfig::read(plan_dir) {
+            Ok(default_config) => default_config,
+            Err(err) => {
+                violations.push(LeveledSourceCheckViolation {
+                    level: invalid_default_config_options.level,
+                    violation: SourceCheckViolation::Config(ConfigRule::InvalidDefaultConfig(
+                        InvalidDefaultConfig {
+                            path: PathBuf::from(DEFAULT_CONFIG_FILE),
+                            // Leaves out the snippet of the file pointing to the error
+                            error: err
+                                .root_cause()
+                                .to_string()
+                                .lines()
+                                .filter(|line| !line.contains('|') && !line.trim().is_empty())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        },
+                    )),
+                });
+                // The other rules cannot be checked without the default values
+                return violations;
+            }
+        };
+        let templates = match ConfigTemplate::read_all(plan_dir) {
+            Ok(templates) => templates,
+            Err(err) => {
+                debug!(
+                    "Skipping config checks for {}, failed to read templates: {:?}",
+                    plan_context.id, err
+                );
+                return violations;
+            }
+        };
+        let hooks = match Hook::read_all(plan_dir) {
+            Ok(hooks) => hooks,
+            Err(err) => {
+                debug!(
+                    "Skipping config checks for {}, failed to read hooks: {:?}",
+                    plan_context.id, err
+                );
+                return violations;
+            }
+        };
+
+        // Values referenced by each template, hook and the plan's exports
+        let mut references: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
+        for template in templates.iter() {
+            references.insert(
+                template.path.clone(),
+                config_value_references(&template.content),
+            );
+        }
+        for hook in hooks.iter() {
+            references.insert(hook.path(), config_value_references(&hook.content));
+        }
+        if !plan_context.exports.is_empty() {
+            references.insert(plan_file, plan_context.exports.values().cloned().collect());
+        }
+        if default_config.is_none() && references.values().all(|values| values.is_empty()) {
+            return violations;
+        }
+        let default_config = default_config.unwrap_or_default();
+
+        for (template, values) in references.iter() {
+            for value in values.iter() {
+                if default_config.is_defined(value)
+                    || is_ignored_value(&undefined_config_value_options.ignored_values, value)
+                {
+                    continue;
+                }
+                violations.push(LeveledSourceCheckViolation {
+                    level: undefined_config_value_options.level,
+                    violation: SourceCheckViolation::Config(ConfigRule::UndefinedConfigValue(
+                        UndefinedConfigValue {
+                            template: template.clone(),
+                            value: value.clone(),
+                        },
+                    )),
+                });
+            }
+        }
+
+        // Only the outermost unused value is reported, the values nested under
+        // it are unused as well
+        let mut unused_values: Vec<String> = Vec::new();
+        for value in default_config.keys() {
+            if references
+                .values()
+                .flatten()
+                .any(|reference| is_used_by(reference, &value))
+                || is_ignored_value(&unused_config_value_options.ignored_values, &value)
+                || unused_values.iter().any(|unused_value| {
+                    value
+                        .strip_prefix(unused_value.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+                })
+            {
+                continue;
+            }
+            unused_values.push(value);
+        }
+        for value in unused_values {
+            violations.push(LeveledSourceCheckViolation {
+                level: unused_config_value_options.level,
+                violation: SourceCheckViolation::Config(ConfigRule::UnusedConfigValue(
+                    UnusedConfigValue { value },
+                )),
+            });
+        }
+        violations
+    }
+}
+
+impl SourceCheck for ConfigCheck {
+    fn source_context_check_with_plan(
+        &self,
+        rules: &PlanContextConfig,
+        plan_context: &PlanContext,
+        _source_context: &SourceContext,
+    ) -> Vec<LeveledSourceCheckViolation> {
+        self.plan_config_check(rules, plan_context)
+    }
+
+    fn source_context_check_with_artifact(
+        &self,
+        _rules: &PlanContextConfig,
+        _artifact_context: &ArtifactContext,
+        _source_context: &SourceContext,
+    ) -> Vec<LeveledSourceCheckViolation> {
+        // The configuration of artifacts is checked with their plan before the build
+        vec![]
+    }
+}
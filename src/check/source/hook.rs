@@ -0,0 +1,512 @@
+use std::{fmt::Display, path::PathBuf};
+
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    check::{LeveledSourceCheckViolation, PlanContextConfig, SourceCheck, ViolationLevel},
+    core::{ArtifactContext, PlanContext, SourceContext},
+};
+
+#[cfg(not(target_os = "windows"))]
+use std::{
+    collections::BTreeSet,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+#[cfg(not(target_os = "windows"))]
+use lazy_static::lazy_static;
+
+#[cfg(not(target_os = "windows"))]
+use regex::Regex;
+
+#[cfg(not(target_os = "windows"))]
+use crate::{
+    check::{SourceCheckViolation, SourceRuleOptions},
+    core::{Hook, HookInterpreter},
+};
+
+use tracing::debug;
+
+#[cfg(not(target_os = "windows"))]
+lazy_static! {
+    static ref SHELLCHECK_GCC_LINE: Regex =
+        Regex::new(r"^-:(\d+):(\d+):\s*(\w+):\s*(.*?)\s*\[(SC\d+)\]$").unwrap();
+}
+
+/// Shells understood by shellcheck
+#[cfg(not(target_os = "windows"))]
+const SHELLCHECK_SHELLS: &[&str] = &["sh", "bash", "dash", "ksh"];
+
+/// Interpreters available on every host that hooks may use without a dependency
+#[cfg(not(target_os = "windows"))]
+const HOST_HOOK_INTERPRETERS: &[&str] = &["/bin/sh", "/bin/false"];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "rule", content = "metadata")]
+pub(crate) enum HookRule {
+    #[serde(rename = "unknown-hook")]
+    UnknownHook(UnknownHook),
+    #[serde(rename = "invalid-hook-interpreter")]
+    InvalidHookInterpreter(InvalidHookInterpreter),
+    #[serde(rename = "undeclared-hook-dependency")]
+    UndeclaredHookDependency(UndeclaredHookDependency),
+    #[serde(rename = "hook-shellcheck")]
+    HookShellcheck(HookShellcheck),
+}
+
+impl Display for HookRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookRule::UnknownHook(rule) => write!(f, "{}", rule),
+            HookRule::InvalidHookInterpreter(rule) => write!(f, "{}", rule),
+            HookRule::UndeclaredHookDependency(rule) => write!(f, "{}", rule),
+            HookRule::HookShellcheck(rule) => write!(f, "{}", rule),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "id", content = "options")]
+pub(crate) enum HookRuleOptions {
+    #[serde(rename = "unknown-hook")]
+    UnknownHook(UnknownHookOptions),
+    #[serde(rename = "invalid-hook-interpreter")]
+    InvalidHookInterpreter(InvalidHookInterpreterOptions),
+    #[serde(rename = "undeclared-hook-dependency")]
+    UndeclaredHookDependency(UndeclaredHookDependencyOptions),
+    #[serde(rename = "hook-shellcheck")]
+    HookShellcheck(HookShellcheckOptions),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnknownHook {
+    pub hook: PathBuf,
+}
+
+impl Display for UnknownHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The hook is not a lifecycle hook run by the Supervisor",
+            self.hook.display().white(),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnknownHookOptions {
+    #[serde(default = "UnknownHookOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl UnknownHookOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+}
+
+impl Default for UnknownHookOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct InvalidHookInterpreter {
+    pub hook: PathBuf,
+    pub interpreter: Option<String>,
+}
+
+impl Display for InvalidHookInterpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(interpreter) = self.interpreter.as_ref() {
+            write!(
+                f,
+                "{}: The interpreter '{}' is not provided by a dependency, use '{}' instead",
+                self.hook.display().white(),
+                interpreter.yellow(),
+                "#!{{pkgPathFor \"<origin>/<name>\"}}/bin/<interpreter>".blue()
+            )
+        } else {
+            write!(
+                f,
+                "{}: The hook does not start with an interpreter line",
+                self.hook.display().white(),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct InvalidHookInterpreterOptions {
+    #[serde(default = "InvalidHookInterpreterOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl InvalidHookInterpreterOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for InvalidHookInterpreterOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UndeclaredHookDependency {
+    pub hook: PathBuf,
+    pub dependency: String,
+}
+
+impl Display for UndeclaredHookDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: The hook uses '{}' which is not a runtime dependency of the plan, add it to 'pkg_deps'",
+            self.hook.display().white(),
+            self.dependency.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UndeclaredHookDependencyOptions {
+    #[serde(default = "UndeclaredHookDependencyOptions::level")]
+    pub level: ViolationLevel,
+}
+
+impl UndeclaredHookDependencyOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Error
+    }
+}
+
+impl Default for UndeclaredHookDependencyOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct HookShellcheck {
+    pub hook: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub severity: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl Display for HookShellcheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {} {}",
+            self.hook.display().white(),
+            self.line,
+            self.column,
+            format!("[{} {}]", self.code, self.severity).blue(),
+            self.message.yellow()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct HookShellcheckOptions {
+    #[serde(default = "HookShellcheckOptions::level")]
+    pub level: ViolationLevel,
+    /// Static analysis tool to run on the shell hooks, it must accept the
+    /// '--shell', '--format=gcc' options and read the script from stdin
+    #[serde(default = "HookShellcheckOptions::command")]
+    pub command: String,
+    /// Additional arguments given to the command, such as '--exclude=SC2034'
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl HookShellcheckOptions {
+    fn level() -> ViolationLevel {
+        ViolationLevel::Warn
+    }
+    fn command() -> String {
+        String::from("shellcheck")
+    }
+}
+
+impl Default for HookShellcheckOptions {
+    fn default() -> Self {
+        Self {
+            level: Self::level(),
+            command: Self::command(),
+            args: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct HookCheck {}
+
+impl HookCheck {
+    #[cfg(target_os = "windows")]
+    fn plan_hooks_check(
+        &self,
+        _rules: &PlanContextConfig,
+        _plan_context: &PlanContext,
+    ) -> Vec<LeveledSourceCheckViolation> {
+        // Windows hooks are powershell scripts which we do not check yet.
+        debug!("Skipping plan hook checks");
+        vec![]
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn plan_hooks_check(
+        &self,
+        rules: &PlanContextConfig,
+        plan_context: &PlanContext,
+    ) -> Vec<LeveledSourceCheckViolation> {
+        let mut violations = Vec::new();
+        let unknown_hook_options = rules
+            .source_rules
+            .iter()
+            .filter_map(|rule| {
+                if let SourceRuleOptions::Hook(HookRuleOptions::UnknownHook(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+        let invalid_hook_interpreter_options = rules
+            .source_rules
+            .iter()
+            .filter_map(|rule| {
+                if let SourceRuleOptions::Hook(HookRuleOptions::InvalidHookInterpreter(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+        let undeclared_hook_dependency_options = rules
+            .source_rules
+            .iter()
+            .filter_map(|rule| {
+                if let SourceRuleOptions::Hook(HookRuleOptions::UndeclaredHookDependency(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+        let hook_shellcheck_options = rules
+            .source_rules
+            .iter()
+            .filter_map(|rule| {
+                if let SourceRuleOptions::Hook(HookRuleOptions::HookShellcheck(options)) =
+                    &rule.options
+                {
+                    Some(options)
+                } else {
+                    None
+                }
+            })
+            .next_back()
+            .expect("Default rule missing");
+
+        let plan_dir = plan_context
+            .plan_path
+            .as_ref()
+            .parent()
+            .expect("Plan file must be inside a folder");
+        let hooks = match Hook::read_all(plan_dir) {
+            Ok(hooks) => hooks,
+            Err(err) => {
+                debug!("Skipping hook checks for {}: {:?}", plan_context.id, err);
+                return violations;
+            }
+        };
+        let plan_ident = plan_context.id.as_ref();
+        let declared_deps = plan_context
+            .deps
+            .iter()
+            .map(|dep| (dep.origin.to_string(), dep.name.to_string()))
+            .chain(std::iter::once((
+                plan_ident.origin.to_string(),
+                plan_ident.name.to_string(),
+            )))
+            .collect::<BTreeSet<_>>();
+
+        for hook in hooks.iter() {
+            if !hook.is_known() {
+                violations.push(LeveledSourceCheckViolation {
+                    level: unknown_hook_options.level,
+                    violation: SourceCheckViolation::Hook(HookRule::UnknownHook(UnknownHook {
+                        hook: hook.path(),
+                    })),
+                });
+            }
+
+            let interpreter = hook.interpreter();
+            let is_valid_interpreter = match interpreter.as_ref() {
+                Some(HookInterpreter::Dependency { .. }) | Some(HookInterpreter::Package(_)) => {
+                    true
+                }
+                Some(HookInterpreter::Path(path)) => HOST_HOOK_INTERPRETERS
+                    .iter()
+                    .any(|host_interpreter| path.as_path() == *host_interpreter),
+                Some(HookInterpreter::Env(_)) | None => false,
+            };
+            if !is_valid_interpreter {
+                violations.push(LeveledSourceCheckViolation {
+                    level: invalid_hook_interpreter_options.level,
+                    violation: SourceCheckViolation::Hook(HookRule::InvalidHookInterpreter(
+                        InvalidHookInterpreter {
+                            hook: hook.path(),
+                            interpreter: hook.interpreter_line().map(String::from),
+                        },
+                    )),
+                });
+            }
+
+            for (origin, name) in hook.pkg_path_for_references() {
+                if !declared_deps.contains(&(origin.clone(), name.clone())) {
+                    violations.push(LeveledSourceCheckViolation {
+                        level: undeclared_hook_dependency_options.level,
+                        violation: SourceCheckViolation::Hook(HookRule::UndeclaredHookDependency(
+                            UndeclaredHookDependency {
+                                hook: hook.path(),
+                                dependency: format!("{}/{}", origin, name),
+                            },
+                        )),
+                    });
+                }
+            }
+
+            if hook_shellcheck_options.level == ViolationLevel::Off {
+                continue;
+            }
+            let Some(shell) = interpreter
+                .as_ref()
+                .and_then(|interpreter| interpreter.command_name())
+                .filter(|command_name| SHELLCHECK_SHELLS.contains(command_name))
+            else {
+                debug!(
+                    "Skipping static analysis of hook {}, it is not a shell script",
+                    hook.path().display()
+                );
+                continue;
+            };
+            violations.extend(
+                hook_shellcheck(hook_shellcheck_options, hook, shell)
+                    .into_iter()
+                    .map(|rule| LeveledSourceCheckViolation {
+                        level: hook_shellcheck_options.level,
+                        violation: SourceCheckViolation::Hook(HookRule::HookShellcheck(rule)),
+                    }),
+            );
+        }
+        violations
+    }
+}
+
+/// Runs the configured static analysis tool on a shell hook, the hook is skipped
+/// if the tool is not installed
+#[cfg(not(target_os = "windows"))]
+fn hook_shellcheck(
+    options: &HookShellcheckOptions,
+    hook: &Hook,
+    shell: &str,
+) -> Vec<HookShellcheck> {
+    let command = match which::which(&options.command) {
+        Ok(command) => command,
+        Err(_) => {
+            debug!(
+                "Skipping static analysis of hook {}, '{}' is not installed",
+                hook.path().display(),
+                options.command
+            );
+            return vec![];
+        }
+    };
+    let child = Command::new(command)
+        .args(options.args.iter())
+        .arg(format!("--shell={}", shell))
+        .arg("--format=gcc")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            debug!("Failed to run '{}': {:?}", options.command, err);
+            return vec![];
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(err) = stdin.write_all(hook.shell_source(shell).as_bytes()) {
+            debug!("Failed to write hook to '{}': {:?}", options.command, err);
+        }
+    }
+    let output = match child.wait_with_output() {
+        Ok(output) => output,
+        Err(err) => {
+            debug!("Failed to run '{}': {:?}", options.command, err);
+            return vec![];
+        }
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| SHELLCHECK_GCC_LINE.captures(line))
+        .map(|captures| HookShellcheck {
+            hook: hook.path(),
+            line: captures[1].parse().unwrap_or_default(),
+            column: captures[2].parse().unwrap_or_default(),
+            severity: captures[3].to_string(),
+            code: captures[5].to_string(),
+            message: captures[4].to_string(),
+        })
+        .collect()
+}
+
+impl SourceCheck for HookCheck {
+    fn source_context_check_with_plan(
+        &self,
+        rules: &PlanContextConfig,
+        plan_context: &PlanContext,
+        _source_context: &SourceContext,
+    ) -> Vec<LeveledSourceCheckViolation> {
+        self.plan_hooks_check(rules, plan_context)
+    }
+
+    fn source_context_check_with_artifact(
+        &self,
+        _rules: &PlanContextConfig,
+        _artifact_context: &ArtifactContext,
+        _source_context: &SourceContext,
+    ) -> Vec<LeveledSourceCheckViolation> {
+        // The hooks of artifacts are checked by the script artifact rules
+        vec![]
+    }
+}
@@ -26,6 +26,10 @@ pub(crate) enum LicenseRule {
     LicenseNotFound(LicenseNotFound),
     #[serde(rename = "invalid-license-expression")]
     InvalidLicenseExpression(InvalidLicenseExpression),
+    #[serde(rename = "denied-license")]
+    DeniedLicense(DeniedLicense),
+    #[serde(rename = "unknown-license")]
+    UnknownLicense(UnknownLicense),
 }
 
 impl Display for LicenseRule {
@@ -34,6 +38,8 @@ impl Display for LicenseRule {
             LicenseRule::MissingLicense(rule) => write!(f, "{}", rule),
             LicenseRule::LicenseNotFound(rule) => write!(f, "{}", rule),
             LicenseRule::InvalidLicenseExpression(rule) => write!(f, "{}", rule),
+            LicenseRule::DeniedLicense(rule) => write!(f, "{}", rule),
+            LicenseRule::UnknownLicense(rule) => write!(f, "{}", rule),
         }
     }
 }
@@ -47,6 +53,29 @@ pub(crate) enum LicenseRuleOptions {
     LicenseNotFound(LicenseNotFoundOptions),
     #[serde(rename = "invalid-license-expression")]
     InvalidLicenseExpression(InvalidLicenseExpressionOptions),
+    #[serde(rename = "denied-license")]
+    DeniedLicense(DeniedLicenseOptions),
+    #[serde(rename = "unknown-license")]
+    UnknownLicense(UnknownLicenseOptions),
+}
+
+/// Licenses the packages are allowed to have, enforced by the 'denied-license'
+/// and 'unknown-license' rules
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub(crate) struct LicensePolicy {
+    /// SPDX identifiers of the licenses packages may not have, such as 'AGPL-3.0-only'
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Whether packages may have no license or a license that is not an SPDX license,
+    /// defaults to true
+    #[serde(default = "LicensePolicy::default_allow_unknown")]
+    pub allow_unknown: bool,
+}
+
+impl LicensePolicy {
+    fn default_allow_unknown() -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -172,6 +201,89 @@ impl Default for InvalidLicenseExpressionOptions {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct DeniedLicense {
+    pub license: String,
+    /// Source files the license was found in, empty if it is specified in the 'pkg_licenses'
+    pub sources: BTreeSet<PathBuf>,
+}
+
+impl Display for DeniedLicense {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.sources.is_empty() {
+            write!(
+                f,
+                "License '{}' specified in the 'pkg_licenses' is denied by the license policy",
+                self.license.yellow(),
+            )
+        } else {
+            write!(
+                f,
+                "License '{}' denied by the license policy found in files:\n{}",
+                self.license.yellow(),
+                self.sources
+                    .iter()
+                    .map(|p| format!("                  - {}", p.display().blue()))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct DeniedLicenseOptions {
+    pub level: ViolationLevel,
+    /// Denied licenses the plan is allowed to have
+    #[serde(default)]
+    pub ignored_licenses: Vec<String>,
+}
+
+impl Default for DeniedLicenseOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Error,
+            ignored_licenses: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UnknownLicense {
+    /// License that is not an SPDX license, none if no license is specified
+    pub license: Option<String>,
+}
+
+impl Display for UnknownLicense {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(license) = &self.license {
+            write!(
+                f,
+                "License '{}' specified in the 'pkg_licenses' is not an SPDX license, the license policy does not allow unknown licenses",
+                license.yellow(),
+            )
+        } else {
+            write!(
+                f,
+                "No license specified in the 'pkg_licenses', the license policy does not allow unknown licenses",
+            )
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct UnknownLicenseOptions {
+    pub level: ViolationLevel,
+}
+
+impl Default for UnknownLicenseOptions {
+    fn default() -> Self {
+        Self {
+            level: ViolationLevel::Error,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct LicenseCheck {}
 
@@ -199,6 +311,7 @@ impl LicenseCheck {
         let mut violations = Vec::new();
         let mut specified_licenses = BTreeSet::new();
         let mut detected_licenses = BTreeSet::default();
+        let mut licenses_not_found = BTreeSet::new();
         let mut license_sources: HashMap<String, BTreeSet<PathBuf>> = HashMap::new();
 
         let missing_license_options = rules
@@ -213,7 +326,7 @@ impl LicenseCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
         let license_not_found_options = rules
             .source_rules
@@ -227,7 +340,7 @@ impl LicenseCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
         let invalid_license_expression_options = rules
             .source_rules
@@ -242,46 +355,51 @@ impl LicenseCheck {
                     None
                 }
             })
-            .last()
+            .next_back()
             .expect("Default rule missing");
 
+        for license_ctx in source_context.licenses.iter() {
+            for detected_license in license_ctx.detected_licenses.iter() {
+                license_sources
+                    .entry(detected_license.clone())
+                    .or_default()
+                    .insert(license_ctx.path.clone());
+            }
+            detected_licenses.extend(license_ctx.detected_licenses.clone());
+        }
+
+        let mut expressions = Vec::new();
         for license_expression in license_expressions {
             match spdx::Expression::parse(license_expression) {
                 Ok(expression) => {
                     for req_expression in expression.requirements() {
-                        // Transform license id into correct string form
-                        let license_id = match &req_expression.req.license {
-                            spdx::LicenseItem::Spdx {
-                                id,
-                                or_later: false,
-                            } => {
-                                if id.is_gnu() {
-                                    format!("{}-only", id.name)
-                                } else {
-                                    id.name.to_string()
-                                }
+                        specified_licenses.insert(license_req_id(&req_expression.req));
+                        if let Some(ref exception) = req_expression.req.exception {
+                            specified_licenses.insert(exception.name.to_string());
+                        }
+                    }
+                    // Only the alternatives of an 'OR' expression that could not be
+                    // satisfied are reported, if any one of them is satisfied the
+                    // expression is considered to be satisfied
+                    if let Err(failed_req_expressions) = expression.evaluate_with_failures(|req| {
+                        detected_licenses.contains(&license_req_id(req))
+                            && req
+                                .exception
+                                .is_none_or(|exception| detected_licenses.contains(exception.name))
+                    }) {
+                        for req_expression in failed_req_expressions {
+                            let license_id = license_req_id(&req_expression.req);
+                            if !detected_licenses.contains(&license_id) {
+                                licenses_not_found.insert(license_id);
                             }
-                            spdx::LicenseItem::Spdx { id, or_later: true } => {
-                                if id.is_gnu() {
-                                    format!("{}-or-later", id.name)
-                                } else {
-                                    format!("{}+", id.name)
+                            if let Some(exception) = req_expression.req.exception {
+                                if !detected_licenses.contains(exception.name) {
+                                    licenses_not_found.insert(exception.name.to_string());
                                 }
                             }
-                            spdx::LicenseItem::Other {
-                                doc_ref: _,
-                                lic_ref,
-                            } => lic_ref.to_string(),
-                        };
-                        if !specified_licenses.contains(&license_id) {
-                            specified_licenses.insert(license_id);
-                        }
-                        if let Some(ref exception) = req_expression.req.exception {
-                            if !specified_licenses.contains(exception.name) {
-                                specified_licenses.insert(exception.name.to_string());
-                            }
                         }
                     }
+                    expressions.push(expression);
                 }
                 Err(err) => {
                     violations.push(LeveledSourceCheckViolation {
@@ -297,14 +415,15 @@ impl LicenseCheck {
             }
         }
 
-        for license_ctx in source_context.licenses.iter() {
-            for detected_license in license_ctx.detected_licenses.iter() {
-                license_sources
-                    .entry(detected_license.clone())
-                    .or_default()
-                    .insert(license_ctx.path.clone());
-            }
-            detected_licenses.extend(license_ctx.detected_licenses.clone().into_iter());
+        if let Some(license_policy) = rules.license_policy.as_ref() {
+            violations.extend(license_policy_check(
+                rules,
+                license_policy,
+                license_expressions,
+                &expressions,
+                &specified_licenses,
+                &license_sources,
+            ));
         }
 
         let missing_licenses = detected_licenses.difference(&specified_licenses);
@@ -332,7 +451,6 @@ impl LicenseCheck {
             });
         }
 
-        let licenses_not_found = specified_licenses.difference(&detected_licenses);
         for license_not_found in licenses_not_found {
             violations.push(LeveledSourceCheckViolation {
                 level: match (
@@ -349,7 +467,7 @@ impl LicenseCheck {
                 },
                 violation: SourceCheckViolation::License(LicenseRule::LicenseNotFound(
                     LicenseNotFound {
-                        license: license_not_found.clone(),
+                        license: license_not_found,
                         source_shasum: source_context.source_shasum.clone(),
                     },
                 )),
@@ -360,6 +478,143 @@ impl LicenseCheck {
     }
 }
 
+/// Checks the specified and detected licenses against the license policy. A license
+/// expression only violates the policy if it cannot be satisfied without a denied
+/// license, and detected licenses only if they are not specified in the 'pkg_licenses'.
+#[cfg(not(target_os = "windows"))]
+fn license_policy_check(
+    rules: &PlanContextConfig,
+    license_policy: &LicensePolicy,
+    license_expressions: &[String],
+    expressions: &[spdx::Expression],
+    specified_licenses: &BTreeSet<String>,
+    license_sources: &HashMap<String, BTreeSet<PathBuf>>,
+) -> Vec<LeveledSourceCheckViolation> {
+    let mut violations = Vec::new();
+    let denied_license_options = rules
+        .source_rules
+        .iter()
+        .filter_map(|rule| {
+            if let SourceRuleOptions::License(LicenseRuleOptions::DeniedLicense(options)) =
+                &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+    let unknown_license_options = rules
+        .source_rules
+        .iter()
+        .filter_map(|rule| {
+            if let SourceRuleOptions::License(LicenseRuleOptions::UnknownLicense(options)) =
+                &rule.options
+            {
+                Some(options)
+            } else {
+                None
+            }
+        })
+        .next_back()
+        .expect("Default rule missing");
+    let is_denied = |license: &str| {
+        license_policy.deny.iter().any(|denied| denied == license)
+            && !denied_license_options
+                .ignored_licenses
+                .iter()
+                .any(|ignored| ignored == license)
+    };
+
+    let mut denied_licenses = BTreeSet::new();
+    for expression in expressions {
+        if !expression.evaluate(|req| !is_denied(&license_req_id(req))) {
+            for req_expression in expression.requirements() {
+                let license_id = license_req_id(&req_expression.req);
+                if is_denied(&license_id) {
+                    denied_licenses.insert(license_id);
+                }
+            }
+        }
+    }
+    for denied_license in denied_licenses {
+        violations.push(LeveledSourceCheckViolation {
+            level: denied_license_options.level,
+            violation: SourceCheckViolation::License(LicenseRule::DeniedLicense(DeniedLicense {
+                license: denied_license,
+                sources: BTreeSet::new(),
+            })),
+        });
+    }
+    for (detected_license, sources) in license_sources.iter() {
+        if is_denied(detected_license) && !specified_licenses.contains(detected_license) {
+            violations.push(LeveledSourceCheckViolation {
+                level: denied_license_options.level,
+                violation: SourceCheckViolation::License(LicenseRule::DeniedLicense(
+                    DeniedLicense {
+                        license: detected_license.clone(),
+                        sources: sources.clone(),
+                    },
+                )),
+            });
+        }
+    }
+
+    if !license_policy.allow_unknown {
+        let mut unknown_licenses = BTreeSet::new();
+        if license_expressions.is_empty() {
+            unknown_licenses.insert(None);
+        }
+        for expression in expressions {
+            for req_expression in expression.requirements() {
+                if let spdx::LicenseItem::Other { .. } = req_expression.req.license {
+                    unknown_licenses.insert(Some(license_req_id(&req_expression.req)));
+                }
+            }
+        }
+        for unknown_license in unknown_licenses {
+            violations.push(LeveledSourceCheckViolation {
+                level: unknown_license_options.level,
+                violation: SourceCheckViolation::License(LicenseRule::UnknownLicense(
+                    UnknownLicense {
+                        license: unknown_license,
+                    },
+                )),
+            });
+        }
+    }
+    violations
+}
+
+/// Transforms the license of a requirement into the form used by the license scanner
+#[cfg(not(target_os = "windows"))]
+fn license_req_id(req: &spdx::LicenseReq) -> String {
+    match &req.license {
+        spdx::LicenseItem::Spdx {
+            id,
+            or_later: false,
+        } => {
+            if id.is_gnu() {
+                format!("{}-only", id.name)
+            } else {
+                id.name.to_string()
+            }
+        }
+        spdx::LicenseItem::Spdx { id, or_later: true } => {
+            if id.is_gnu() {
+                format!("{}-or-later", id.name)
+            } else {
+                format!("{}+", id.name)
+            }
+        }
+        spdx::LicenseItem::Other {
+            doc_ref: _,
+            lic_ref,
+        } => lic_ref.to_string(),
+    }
+}
+
 impl SourceCheck for LicenseCheck {
     fn source_context_check_with_plan(
         &self,
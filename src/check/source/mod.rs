@@ -1 +1,3 @@
+pub mod config;
+pub mod hook;
 pub mod license;
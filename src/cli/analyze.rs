@@ -1,20 +1,53 @@
-use color_eyre::eyre::{eyre, Context, Result};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
 use owo_colors::OwoColorize;
+use petgraph::stable_graph::NodeIndex;
 use serde_json::json;
-use std::{collections::HashSet, env, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fmt::Write,
+    path::PathBuf,
+};
 use tera::Tera;
 use tracing::{error, info};
 
-use clap::Args;
+use clap::{Args, ValueEnum};
+
+use super::clean::format_size;
 
 use crate::{
-    cli::output::OutputFormat,
+    cli::output::{
+        self, AnalysisOutput, BuildHostOutput, CycleBreakOutput, CycleDependencyOutput,
+        CycleOutput, DependencySizeOutput, DuplicateSourceKind, DuplicateSourceOutput,
+        GraphEdgeOutput, GraphNodeOutput, GraphOutput, JsonOutputKind, LayerOutput,
+        PackageSizeOutput, PlanOutput, PlanSourceOutput, RebuildPathOutput, RebuildPathsOutput,
+        ReportFormat, ServiceBindOutput, ServiceCompositionOutput, UnconsumedExportsOutput,
+    },
     core::{
-        AnalysisType, AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, Dependency,
-        DependencyAnalysis, PackageDepGlob, PackageTarget,
+        AnalysisType, ArtifactSize, AutoBuildConfig, AutoBuildContext, BuildOrder,
+        ChangeDetectionMode, Dependency, DependencyAnalysis, DependencyChangeCause,
+        DependencyCycle, DependencyType, DuplicateSource, PackageDepGlob, PackageTarget,
+        PlanContext, RebuildPath, ServiceComposition,
     },
 };
 
+/// Number of transitive runtime dependencies listed as the heaviest contributors
+/// to the closure size of a package
+const HEAVIEST_DEPENDENCIES_COUNT: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum GraphFormat {
+    /// Graphviz DOT graph
+    Dot,
+    /// Mermaid flowchart, for embedding in markdown documents
+    Mermaid,
+    /// JSON list of nodes and edges
+    Json,
+}
+
 #[derive(Debug, Args)]
 pub(crate) struct Params {
     /// Path to hab auto build configuration
@@ -24,8 +57,8 @@ pub(crate) struct Params {
     #[arg(short = 's', long, default_value_t = false)]
     strict_build_order: bool,
     /// Output format
-    #[arg(value_enum, short = 'f', long, default_value_t = OutputFormat::Plain)]
-    format: OutputFormat,
+    #[arg(value_enum, short = 'f', long, default_value_t = ReportFormat::Plain)]
+    format: ReportFormat,
     /// Detect runtime dependencies
     #[arg(long, default_value_t = false)]
     deps: bool,
@@ -47,8 +80,47 @@ pub(crate) struct Params {
     /// Detect reverse build dependencies
     #[arg(long, default_value_t = false)]
     build_rdeps: bool,
+    /// Detect the hosts on which the package and its transitive runtime dependencies were built
+    #[arg(long, default_value_t = false)]
+    build_hosts: bool,
+    /// Group the packages and their transitive dependencies into layers of plans that only depend on plans in lower layers
+    #[arg(long, default_value_t = false)]
+    layers: bool,
+    /// List the dependency cycles between plans and the build dependencies to remove to break them
+    #[arg(long, default_value_t = false, conflicts_with = "layers")]
+    cycles: bool,
+    /// Explain why the matching plans are rebuilt, listing every path from a changed plan to them
+    #[arg(long, conflicts_with_all = ["layers", "cycles"])]
+    why: Option<PackageDepGlob>,
+    /// List plans that share a source suspiciously, like different packages built from the same archive
+    #[arg(long, default_value_t = false, conflicts_with_all = ["layers", "cycles", "why"])]
+    duplicate_sources: bool,
+    /// Report the installed size of the latest artifacts of the matching packages, of their transitive runtime dependencies and the dependencies contributing the most to it
+    #[arg(long, conflicts_with_all = ["layers", "cycles", "why", "duplicate_sources"])]
+    size: Option<PackageDepGlob>,
+    /// Check the binds and exports of the services of all repos, listing the binds no plan can satisfy and the exports no bind needs
+    #[arg(long, default_value_t = false, conflicts_with_all = ["layers", "cycles", "why", "duplicate_sources", "size"])]
+    binds: bool,
+    /// Flag packages whose closure size grew by more than this percentage since the previous release
+    #[arg(long, default_value_t = 10.0, requires = "size")]
+    max_closure_growth: f64,
+    /// Build ordering to use with respect to the build's studio when explaining rebuilds
+    #[arg(value_enum, short = 'b', long, default_value_t = BuildOrder::Strict)]
+    build_order: BuildOrder,
+    /// Method to use to detect changes to packages when explaining rebuilds
+    #[arg(value_enum, short = 'm', long, default_value_t = ChangeDetectionMode::Disk)]
+    change_detection_mode: ChangeDetectionMode,
+    /// Skip scanning the artifact cache, speeding up analyses that only need the dependency graph
+    #[arg(long, default_value_t = false, conflicts_with_all = ["why", "build_hosts", "size"])]
+    no_artifact_cache: bool,
     #[arg(long)]
     template: Option<String>,
+    /// Export the packages and their transitive dependencies as a graph, the dependency types included are selected with '--deps', '--build-deps' and '--studio-dep'
+    #[arg(value_enum, long, conflicts_with_all = ["format", "layers", "cycles", "why", "duplicate_sources", "size", "binds", "build_hosts", "template"])]
+    emit: Option<GraphFormat>,
+    /// Package target of the plans to analyze, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
     /// List of packages to include
     packages: Vec<PackageDepGlob>,
 }
@@ -60,9 +132,14 @@ pub(crate) fn execute(args: Params) -> Result<()> {
             .join("hab-auto-build.json"),
     );
     let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
 
-    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
-        .with_context(|| eyre!("Failed to initialize run"))?;
+    let run_context = if args.no_artifact_cache {
+        AutoBuildContext::new_without_artifact_cache(&config, &config_path)
+    } else {
+        AutoBuildContext::new(&config, &config_path, args.change_detection_mode)
+    }
+    .with_context(|| eyre!("Failed to initialize run"))?;
 
     let mut analysis_types = HashSet::new();
     if args.studio_dep {
@@ -86,8 +163,209 @@ pub(crate) fn execute(args: Params) -> Result<()> {
     if args.build_rdeps {
         analysis_types.insert(AnalysisType::ReverseBuildDependencies);
     }
+    if args.build_hosts {
+        analysis_types.insert(AnalysisType::BuildHosts);
+    }
 
-    let package_indices = run_context.glob_deps(&args.packages, PackageTarget::default())?;
+    if args.layers {
+        // Layers are computed across all plans unless specific packages are selected
+        let packages = if args.packages.is_empty() {
+            vec![PackageDepGlob::parse("*/*").unwrap()]
+        } else {
+            args.packages.clone()
+        };
+        let package_indices = run_context.glob_deps(&packages, target)?;
+        let layers = run_context.plan_layers(&package_indices);
+        return match args.format {
+            ReportFormat::Plain => output_layers_plain(layers),
+            ReportFormat::Json => output_layers_json(layers),
+            ReportFormat::Markdown => output_layers_markdown(layers),
+        };
+    }
+
+    if let Some(package) = args.why.as_ref() {
+        let package_indices = run_context
+            .glob_deps(std::slice::from_ref(package), target)?
+            .into_iter()
+            .filter(|package_index| run_context.dep(*package_index).plan_ctx().is_some())
+            .collect::<Vec<_>>();
+        if package_indices.is_empty() {
+            error!(target: "user-log", "No plans found matching pattern: {}", package);
+            return Ok(());
+        }
+        let rebuild_paths = package_indices
+            .into_iter()
+            .map(|package_index| {
+                let (paths, truncated) = run_context.rebuild_paths(
+                    package_index,
+                    args.change_detection_mode,
+                    args.build_order,
+                    target,
+                );
+                (package_index, paths, truncated)
+            })
+            .collect::<Vec<_>>();
+        return match args.format {
+            ReportFormat::Plain => output_rebuild_paths_plain(&run_context, rebuild_paths),
+            ReportFormat::Json => output_rebuild_paths_json(&run_context, rebuild_paths),
+            ReportFormat::Markdown => Err(eyre!(
+                "Markdown output is only supported for layers and cycles"
+            ))
+            .with_suggestion(|| "Use '--format plain' to explain why plans are rebuilt"),
+        };
+    }
+
+    if args.duplicate_sources {
+        // Only the duplicates involving the selected packages are listed, if any are selected
+        let package_indices = run_context.glob_deps(&args.packages, target)?;
+        let selected_plans = package_indices
+            .iter()
+            .filter_map(|package_index| run_context.dep(*package_index).plan_ctx())
+            .map(|plan_ctx| &plan_ctx.id)
+            .collect::<HashSet<_>>();
+        let duplicate_sources = run_context
+            .duplicate_sources(target)
+            .into_iter()
+            .filter(|duplicate_source| {
+                args.packages.is_empty()
+                    || duplicate_source
+                        .plans()
+                        .iter()
+                        .any(|plan_ctx| selected_plans.contains(&plan_ctx.id))
+            })
+            .collect::<Vec<_>>();
+        return match args.format {
+            ReportFormat::Plain => output_duplicate_sources_plain(duplicate_sources),
+            ReportFormat::Json => output_duplicate_sources_json(duplicate_sources),
+            ReportFormat::Markdown => output_duplicate_sources_markdown(duplicate_sources),
+        };
+    }
+
+    if args.binds {
+        // Only the binds and exports involving the selected packages are listed, if any are selected
+        let package_indices = run_context.glob_deps(&args.packages, target)?;
+        let selected_plans = package_indices
+            .iter()
+            .filter_map(|package_index| run_context.dep(*package_index).plan_ctx())
+            .map(|plan_ctx| &plan_ctx.id)
+            .collect::<HashSet<_>>();
+        let mut service_composition = run_context.service_composition(target);
+        if !args.packages.is_empty() {
+            service_composition.binds.retain(|bind| {
+                selected_plans.contains(&bind.plan.id)
+                    || bind
+                        .providers
+                        .iter()
+                        .any(|provider| selected_plans.contains(&provider.id))
+            });
+            service_composition
+                .unconsumed_exports
+                .retain(|unconsumed_exports| selected_plans.contains(&unconsumed_exports.plan.id));
+        }
+        return match args.format {
+            ReportFormat::Plain => output_service_composition_plain(service_composition),
+            ReportFormat::Json => output_service_composition_json(service_composition),
+            ReportFormat::Markdown => output_service_composition_markdown(service_composition),
+        };
+    }
+
+    if let Some(package) = args.size.as_ref() {
+        let package_indices = run_context.glob_deps(std::slice::from_ref(package), target)?;
+        if package_indices.is_empty() {
+            error!(target: "user-log", "No packages found matching pattern: {}", package);
+            return Ok(());
+        }
+        let mut artifacts = Vec::new();
+        for package_index in package_indices {
+            match run_context.package_artifact(package_index)? {
+                Some(artifact) => artifacts.push(artifact),
+                None => {
+                    info!(target: "user-ui", "{}: {:?}: No artifact found, skipping", "warning".bold().yellow(), run_context.dep(package_index).red())
+                }
+            }
+        }
+        let mut artifact_sizes = run_context.artifact_sizes(&artifacts)?;
+        artifact_sizes.sort_by(|a, b| {
+            b.closure_size
+                .cmp(&a.closure_size)
+                .then_with(|| a.artifact.cmp(&b.artifact))
+        });
+        return match args.format {
+            ReportFormat::Plain => output_sizes_plain(artifact_sizes, args.max_closure_growth),
+            ReportFormat::Json => output_sizes_json(artifact_sizes, args.max_closure_growth),
+            ReportFormat::Markdown => {
+                output_sizes_markdown(artifact_sizes, args.max_closure_growth)
+            }
+        };
+    }
+
+    if args.cycles {
+        // Only the cycles involving the selected packages are listed, if any are selected
+        let package_indices = run_context.glob_deps(&args.packages, target)?;
+        let cycles = run_context
+            .dep_cycles()
+            .iter()
+            .filter(|cycle| {
+                args.packages.is_empty()
+                    || cycle
+                        .nodes
+                        .iter()
+                        .any(|node_index| package_indices.contains(node_index))
+            })
+            .collect::<Vec<_>>();
+        return match args.format {
+            ReportFormat::Plain => output_cycles_plain(&run_context, cycles),
+            ReportFormat::Json => output_cycles_json(&run_context, cycles),
+            ReportFormat::Markdown => output_cycles_markdown(&run_context, cycles),
+        };
+    }
+
+    if let Some(graph_format) = args.emit {
+        // The graph includes all plans unless specific packages are selected
+        let packages = if args.packages.is_empty() {
+            vec![PackageDepGlob::parse("*/*").unwrap()]
+        } else {
+            args.packages.clone()
+        };
+        let package_indices = run_context.glob_deps(&packages, target)?;
+        let mut dep_types = HashSet::new();
+        if args.deps || args.tdeps {
+            dep_types.insert(DependencyType::Runtime);
+        }
+        if args.build_deps || args.build_tdeps {
+            dep_types.insert(DependencyType::Build);
+        }
+        if args.studio_dep {
+            dep_types.insert(DependencyType::Studio);
+        }
+        if dep_types.is_empty() {
+            dep_types.extend([DependencyType::Runtime, DependencyType::Build]);
+        }
+        let (node_indices, edges) = run_context.dep_subgraph(&package_indices, dep_types);
+        let mut nodes = node_indices
+            .into_iter()
+            .map(|node_index| run_context.dep(node_index))
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|dep| dep_id(dep));
+        let mut edges = edges
+            .into_iter()
+            .map(|(source, target, dep_type)| {
+                (
+                    dep_id(run_context.dep(source)),
+                    dep_id(run_context.dep(target)),
+                    dep_type,
+                )
+            })
+            .collect::<Vec<_>>();
+        edges.sort();
+        return match graph_format {
+            GraphFormat::Dot => output_graph_dot(nodes, edges),
+            GraphFormat::Mermaid => output_graph_mermaid(nodes, edges),
+            GraphFormat::Json => output_graph_json(nodes, edges),
+        };
+    }
+
+    let package_indices = run_context.glob_deps(&args.packages, target)?;
     if package_indices.is_empty() && !run_context.is_empty() && !args.packages.is_empty() {
         error!(target: "user-log",
             "No packages found matching patterns: {}",
@@ -103,8 +381,14 @@ pub(crate) fn execute(args: Params) -> Result<()> {
         .collect::<Vec<_>>();
 
     match args.format {
-        OutputFormat::Plain => output_plain(plan_analysis_list)?,
-        OutputFormat::Json => output_json(plan_analysis_list, args.template)?,
+        ReportFormat::Plain => output_plain(plan_analysis_list)?,
+        ReportFormat::Json => output_json(plan_analysis_list, args.template)?,
+        ReportFormat::Markdown => {
+            return Err(eyre!(
+                "Markdown output is only supported for layers and cycles"
+            ))
+            .with_suggestion(|| "Use '--layers' to output the plan layers as markdown tables")
+        }
     }
 
     Ok(())
@@ -176,6 +460,22 @@ fn output_plain(dep_analysis_list: Vec<DependencyAnalysis>) -> Result<()> {
                 }
             }
         }
+        if let Some(build_hosts) = dep_analysis.build_hosts.as_ref() {
+            info!(target: "user-ui", "{}", format!("{}:", AnalysisType::BuildHosts).white().bold());
+            if !build_hosts.is_empty() {
+                for build_host in build_hosts {
+                    match build_host.host.as_ref() {
+                        Some(host) => info!(target: "user-ui", "{}: {}", build_host.artifact, host),
+                        None => {
+                            info!(target: "user-ui", "{}: {}", build_host.artifact, "UNKNOWN".dimmed())
+                        }
+                    }
+                }
+                info!(target: "user-ui", "");
+            } else {
+                info!(target: "user-ui", "NO ARTIFACT\n");
+            }
+        }
     }
     Ok(())
 }
@@ -190,16 +490,730 @@ fn output_json(
         let result = Tera::one_off(&template, &context, false)?;
         info!(target: "user-ui", "{}", result);
     } else {
-        info!(
-            target: "user-ui",
-            "{}",
-            serde_json::to_string_pretty(&plan_analysis_list)
-                .context("Failed to serialize plan analysis into JSON")?
-        );
+        let dep_ids = |deps: &Option<Vec<&Dependency>>| {
+            deps.as_ref()
+                .map(|deps| deps.iter().map(|dep| dep_id(dep)).collect())
+        };
+        let plan_analysis_list = plan_analysis_list
+            .iter()
+            .map(|dep_analysis| AnalysisOutput {
+                package: dep_id(dep_analysis.dep_ctx),
+                repo: dep_analysis
+                    .repo_ctx
+                    .map(|repo_ctx| repo_ctx.id.to_string()),
+                plan: dep_analysis
+                    .plan_ctx
+                    .map(|plan_ctx| plan_ctx.plan_path.as_ref().to_path_buf()),
+                studio_dep: dep_analysis
+                    .studio_dep
+                    .map(|studio_dep| studio_dep.map(dep_id)),
+                deps: dep_ids(&dep_analysis.deps),
+                build_deps: dep_ids(&dep_analysis.build_deps),
+                tdeps: dep_ids(&dep_analysis.tdeps),
+                build_tdeps: dep_ids(&dep_analysis.build_tdeps),
+                rdeps: dep_ids(&dep_analysis.rdeps),
+                build_rdeps: dep_ids(&dep_analysis.build_rdeps),
+                build_hosts: dep_analysis.build_hosts.as_ref().map(|build_hosts| {
+                    build_hosts
+                        .iter()
+                        .map(|build_host| BuildHostOutput {
+                            artifact: build_host.artifact.to_string(),
+                            host: build_host.host.clone(),
+                        })
+                        .collect()
+                }),
+            })
+            .collect::<Vec<_>>();
+        output::output_json(JsonOutputKind::Analysis, plan_analysis_list)?;
+    }
+    Ok(())
+}
+
+fn output_layers_plain(layers: Vec<Vec<&PlanContext>>) -> Result<()> {
+    for (index, layer) in layers.iter().enumerate() {
+        info!(target: "user-ui", "{}", format!("Layer {}:", index).white().bold());
+        for plan_ctx in layer {
+            info!(target: "user-ui", "{}", plan_ctx.id);
+        }
+        info!(target: "user-ui", "");
+    }
+    info!(target: "user-log", "Found {} plans across {} layers", layers.iter().map(|layer| layer.len()).sum::<usize>(), layers.len());
+    Ok(())
+}
+
+fn output_layers_json(layers: Vec<Vec<&PlanContext>>) -> Result<()> {
+    let layers = layers
+        .iter()
+        .enumerate()
+        .map(|(index, layer)| LayerOutput {
+            layer: index,
+            plans: layer
+                .iter()
+                .map(|plan_ctx| PlanOutput::from(*plan_ctx))
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    output::output_json(JsonOutputKind::Layers, layers)
+}
+
+fn output_layers_markdown(layers: Vec<Vec<&PlanContext>>) -> Result<()> {
+    let mut output = String::new();
+    for (index, layer) in layers.iter().enumerate() {
+        writeln!(output, "## Layer {}\n", index)?;
+        writeln!(output, "| Package | Repo |")?;
+        writeln!(output, "| --- | --- |")?;
+        for plan_ctx in layer {
+            writeln!(output, "| {} | {} |", plan_ctx.id, plan_ctx.repo_id)?;
+        }
+        writeln!(output)?;
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
+
+fn output_cycles_plain(
+    run_context: &AutoBuildContext,
+    cycles: Vec<&DependencyCycle>,
+) -> Result<()> {
+    for (index, cycle) in cycles.iter().enumerate() {
+        info!(target: "user-ui", "{}", format!("Cycle {}:", index).white().bold());
+        for node_index in cycle.nodes.iter() {
+            info!(target: "user-ui", "{:?}", run_context.dep(*node_index));
+        }
+        info!(target: "user-ui", "{}", "Dependencies:".white().bold());
+        for (source, target, dep_type) in cycle.edges.iter() {
+            info!(target: "user-ui", "{:?} -> {:?} ({})", run_context.dep(*source), run_context.dep(*target), dep_type);
+        }
+        if cycle.suggested_breaks.is_empty() {
+            info!(target: "user-ui", "{}: The cycle is only made of runtime dependencies and cannot be broken by removing build dependencies", "warning".bold().yellow());
+        } else {
+            info!(target: "user-ui", "{}", "Build dependencies to remove:".white().bold());
+            for (source, target) in cycle.suggested_breaks.iter() {
+                info!(target: "user-ui", "{:?} -> {:?}", run_context.dep(*source).green(), run_context.dep(*target).green());
+            }
+        }
+        info!(target: "user-ui", "");
+    }
+    if cycles.is_empty() {
+        info!(target: "user-log", "No dependency cycles found");
+    } else {
+        info!(target: "user-log", "Found {} dependency cycles across {} plans", cycles.len(), cycles.iter().map(|cycle| cycle.nodes.len()).sum::<usize>());
+    }
+    Ok(())
+}
+
+fn output_cycles_json(run_context: &AutoBuildContext, cycles: Vec<&DependencyCycle>) -> Result<()> {
+    let cycles = cycles
+        .iter()
+        .enumerate()
+        .map(|(index, cycle)| CycleOutput {
+            cycle: index,
+            plans: cycle
+                .nodes
+                .iter()
+                .filter_map(|node_index| run_context.dep(*node_index).plan_ctx())
+                .map(PlanOutput::from)
+                .collect(),
+            dependencies: cycle
+                .edges
+                .iter()
+                .map(|(source, target, dep_type)| CycleDependencyOutput {
+                    source: dep_id(run_context.dep(*source)),
+                    target: dep_id(run_context.dep(*target)),
+                    dep_type: *dep_type,
+                })
+                .collect(),
+            suggested_breaks: cycle
+                .suggested_breaks
+                .iter()
+                .map(|(source, target)| CycleBreakOutput {
+                    source: dep_id(run_context.dep(*source)),
+                    target: dep_id(run_context.dep(*target)),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    output::output_json(JsonOutputKind::Cycles, cycles)
+}
+
+fn output_duplicate_sources_plain(duplicate_sources: Vec<DuplicateSource>) -> Result<()> {
+    for duplicate_source in duplicate_sources.iter() {
+        match duplicate_source {
+            DuplicateSource::SameArchive { shasum, plans } => {
+                info!(target: "user-ui", "{} {}", "Same source archive in different packages:".white().bold(), shasum);
+                for plan_ctx in plans {
+                    info!(target: "user-ui", "{} ({})", plan_ctx.id, plan_ctx.source.as_ref().unwrap().url);
+                }
+            }
+            DuplicateSource::ConflictingShasums { url, plans } => {
+                info!(target: "user-ui", "{} {}", "Different shasums for the same source url:".white().bold(), url);
+                for plan_ctx in plans {
+                    info!(target: "user-ui", "{} ({})", plan_ctx.id, plan_ctx.source.as_ref().unwrap().shasum);
+                }
+            }
+        }
+        info!(target: "user-ui", "");
+    }
+    if duplicate_sources.is_empty() {
+        info!(target: "user-log", "No duplicate sources found");
+    } else {
+        info!(target: "user-log", "Found {} duplicate sources", duplicate_sources.len());
+    }
+    Ok(())
+}
+
+fn output_duplicate_sources_json(duplicate_sources: Vec<DuplicateSource>) -> Result<()> {
+    let duplicate_sources = duplicate_sources
+        .iter()
+        .map(|duplicate_source| {
+            let (kind, source) = match duplicate_source {
+                DuplicateSource::SameArchive { shasum, .. } => {
+                    (DuplicateSourceKind::SameArchive, shasum.to_string())
+                }
+                DuplicateSource::ConflictingShasums { url, .. } => {
+                    (DuplicateSourceKind::ConflictingShasums, url.to_string())
+                }
+            };
+            DuplicateSourceOutput {
+                kind,
+                source,
+                plans: duplicate_source
+                    .plans()
+                    .iter()
+                    .map(|plan_ctx| {
+                        let plan_source = plan_ctx.source.as_ref().unwrap();
+                        PlanSourceOutput {
+                            plan: PlanOutput::from(*plan_ctx),
+                            url: plan_source.url.to_string(),
+                            shasum: plan_source.shasum.to_string(),
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    output::output_json(JsonOutputKind::DuplicateSources, duplicate_sources)
+}
+
+fn output_duplicate_sources_markdown(duplicate_sources: Vec<DuplicateSource>) -> Result<()> {
+    let mut output = String::new();
+    for duplicate_source in duplicate_sources.iter() {
+        match duplicate_source {
+            DuplicateSource::SameArchive { shasum, .. } => {
+                writeln!(output, "## Same source archive `{}`\n", shasum)?;
+            }
+            DuplicateSource::ConflictingShasums { url, .. } => {
+                writeln!(output, "## Different shasums for `{}`\n", url)?;
+            }
+        }
+        writeln!(output, "| Plan | Source | Shasum |")?;
+        writeln!(output, "| --- | --- | --- |")?;
+        for plan_ctx in duplicate_source.plans() {
+            let plan_source = plan_ctx.source.as_ref().unwrap();
+            writeln!(
+                output,
+                "| {} | {} | {} |",
+                plan_ctx.id, plan_source.url, plan_source.shasum
+            )?;
+        }
+        writeln!(output)?;
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
+
+fn output_service_composition_plain(service_composition: ServiceComposition) -> Result<()> {
+    if !service_composition.binds.is_empty() {
+        info!(target: "user-ui", "{}", "Binds:".white().bold());
+    }
+    for bind in service_composition.binds.iter() {
+        let optional = if bind.optional { " (optional)" } else { "" };
+        if bind.providers.is_empty() {
+            info!(target: "user-ui", "{} {}{}: {} -> {}", bind.plan.id, bind.name.yellow(), optional, bind.exports.join(" "), "unsatisfied".red().bold());
+        } else {
+            info!(target: "user-ui", "{} {}{}: {} -> {}", bind.plan.id, bind.name.yellow(), optional, bind.exports.join(" "), bind.providers.iter().map(|provider| provider.id.to_string()).collect::<Vec<_>>().join(", "));
+        }
+    }
+    if !service_composition.unconsumed_exports.is_empty() {
+        if !service_composition.binds.is_empty() {
+            info!(target: "user-ui", "");
+        }
+        info!(target: "user-ui", "{}", "Exports not needed by any bind:".white().bold());
+    }
+    for unconsumed_exports in service_composition.unconsumed_exports.iter() {
+        info!(target: "user-ui", "{}: {}", unconsumed_exports.plan.id, unconsumed_exports.exports.join(" ").yellow());
+    }
+    let unsatisfied_binds = service_composition
+        .binds
+        .iter()
+        .filter(|bind| bind.providers.is_empty())
+        .collect::<Vec<_>>();
+    if unsatisfied_binds.is_empty() && service_composition.unconsumed_exports.is_empty() {
+        info!(target: "user-log", "All binds are satisfied and all exports are needed");
+    } else {
+        info!(target: "user-log", "Found {} unsatisfied binds ({} optional) and {} plans with exports not needed by any bind", unsatisfied_binds.len(), unsatisfied_binds.iter().filter(|bind| bind.optional).count(), service_composition.unconsumed_exports.len());
+    }
+    Ok(())
+}
+
+fn output_service_composition_json(service_composition: ServiceComposition) -> Result<()> {
+    let service_composition = ServiceCompositionOutput {
+        binds: service_composition
+            .binds
+            .iter()
+            .map(|bind| ServiceBindOutput {
+                plan: PlanOutput::from(bind.plan),
+                bind: bind.name.to_string(),
+                exports: bind.exports.to_vec(),
+                optional: bind.optional,
+                providers: bind
+                    .providers
+                    .iter()
+                    .map(|provider| PlanOutput::from(*provider))
+                    .collect(),
+            })
+            .collect(),
+        unconsumed_exports: service_composition
+            .unconsumed_exports
+            .iter()
+            .map(|unconsumed_exports| UnconsumedExportsOutput {
+                plan: PlanOutput::from(unconsumed_exports.plan),
+                exports: unconsumed_exports
+                    .exports
+                    .iter()
+                    .map(|export| export.to_string())
+                    .collect(),
+            })
+            .collect(),
+    };
+    output::output_json(JsonOutputKind::ServiceComposition, service_composition)
+}
+
+fn output_service_composition_markdown(service_composition: ServiceComposition) -> Result<()> {
+    let mut output = String::new();
+    if !service_composition.binds.is_empty() {
+        writeln!(output, "## Binds\n")?;
+        writeln!(output, "| Plan | Bind | Exports | Optional | Providers |")?;
+        writeln!(output, "| --- | --- | --- | --- | --- |")?;
+        for bind in service_composition.binds.iter() {
+            writeln!(
+                output,
+                "| {} | {} | {} | {} | {} |",
+                bind.plan.id,
+                bind.name,
+                bind.exports.join(" "),
+                if bind.optional { "yes" } else { "no" },
+                if bind.providers.is_empty() {
+                    String::from("**unsatisfied**")
+                } else {
+                    bind.providers
+                        .iter()
+                        .map(|provider| provider.id.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }
+            )?;
+        }
+        writeln!(output)?;
+    }
+    if !service_composition.unconsumed_exports.is_empty() {
+        writeln!(output, "## Exports not needed by any bind\n")?;
+        writeln!(output, "| Plan | Exports |")?;
+        writeln!(output, "| --- | --- |")?;
+        for unconsumed_exports in service_composition.unconsumed_exports.iter() {
+            writeln!(
+                output,
+                "| {} | {} |",
+                unconsumed_exports.plan.id,
+                unconsumed_exports.exports.join(" ")
+            )?;
+        }
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
+
+/// Growth of the closure of an artifact since the previous release of its package, in percent
+fn closure_growth(artifact_size: &ArtifactSize) -> Option<f64> {
+    artifact_size
+        .previous
+        .as_ref()
+        .filter(|previous| previous.closure_size > 0)
+        .map(|previous| {
+            (artifact_size.closure_size as f64 - previous.closure_size as f64) * 100.0
+                / previous.closure_size as f64
+        })
+}
+
+fn output_sizes_plain(artifact_sizes: Vec<ArtifactSize>, max_closure_growth: f64) -> Result<()> {
+    let mut exceeding_count = 0;
+    for artifact_size in artifact_sizes.iter() {
+        info!(target: "user-ui", "{}", artifact_size.artifact.white().bold());
+        info!(target: "user-ui", "  Installed size: {}", format_size(artifact_size.installed_size));
+        match (
+            artifact_size.previous.as_ref(),
+            closure_growth(artifact_size),
+        ) {
+            (Some(previous), Some(growth)) if growth > max_closure_growth => {
+                exceeding_count += 1;
+                info!(target: "user-ui", "  Closure size: {} ({} since {})", format_size(artifact_size.closure_size), format!("{:+.1}%", growth).red().bold(), previous.artifact);
+            }
+            (Some(previous), Some(growth)) => {
+                info!(target: "user-ui", "  Closure size: {} ({:+.1}% since {})", format_size(artifact_size.closure_size), growth, previous.artifact);
+            }
+            _ => {
+                info!(target: "user-ui", "  Closure size: {}", format_size(artifact_size.closure_size));
+            }
+        }
+        if !artifact_size.tdeps.is_empty() {
+            info!(target: "user-ui", "  Heaviest dependencies:");
+            for (tdep, size) in artifact_size.tdeps.iter().take(HEAVIEST_DEPENDENCIES_COUNT) {
+                info!(target: "user-ui", "    {} {}", tdep, format_size(*size).bright_black());
+            }
+        }
+        for tdep in artifact_size.missing_tdeps.iter() {
+            info!(target: "user-ui", "  {}: {} has no artifact and is not counted", "warning".bold().yellow(), tdep);
+        }
+        info!(target: "user-ui", "");
+    }
+    if exceeding_count > 0 {
+        info!(target: "user-log", "{} packages have a closure that grew by more than {}% since their previous release", exceeding_count.red(), max_closure_growth);
+    }
+    Ok(())
+}
+
+fn output_sizes_json(artifact_sizes: Vec<ArtifactSize>, max_closure_growth: f64) -> Result<()> {
+    output::output_json(
+        JsonOutputKind::PackageSizes,
+        artifact_sizes
+            .iter()
+            .map(|artifact_size| {
+                let closure_growth = closure_growth(artifact_size);
+                PackageSizeOutput {
+                    artifact: artifact_size.artifact.to_string(),
+                    installed_size: artifact_size.installed_size,
+                    closure_size: artifact_size.closure_size,
+                    heaviest_dependencies: artifact_size
+                        .tdeps
+                        .iter()
+                        .take(HEAVIEST_DEPENDENCIES_COUNT)
+                        .map(|(tdep, size)| DependencySizeOutput {
+                            artifact: tdep.to_string(),
+                            installed_size: *size,
+                        })
+                        .collect(),
+                    missing_dependencies: artifact_size
+                        .missing_tdeps
+                        .iter()
+                        .map(|tdep| tdep.to_string())
+                        .collect(),
+                    previous_artifact: artifact_size
+                        .previous
+                        .as_ref()
+                        .map(|previous| previous.artifact.to_string()),
+                    previous_closure_size: artifact_size
+                        .previous
+                        .as_ref()
+                        .map(|previous| previous.closure_size),
+                    closure_growth,
+                    exceeds_max_growth: closure_growth
+                        .is_some_and(|growth| growth > max_closure_growth),
+                }
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn output_sizes_markdown(artifact_sizes: Vec<ArtifactSize>, max_closure_growth: f64) -> Result<()> {
+    let mut output = String::new();
+    writeln!(
+        output,
+        "| Package | Installed Size | Closure Size | Growth | Heaviest Dependencies |"
+    )?;
+    writeln!(output, "| --- | --- | --- | --- | --- |")?;
+    for artifact_size in artifact_sizes.iter() {
+        let growth = match closure_growth(artifact_size) {
+            Some(growth) if growth > max_closure_growth => format!("**{:+.1}%**", growth),
+            Some(growth) => format!("{:+.1}%", growth),
+            None => String::new(),
+        };
+        writeln!(
+            output,
+            "| {} | {} | {} | {} | {} |",
+            artifact_size.artifact,
+            format_size(artifact_size.installed_size),
+            format_size(artifact_size.closure_size),
+            growth,
+            artifact_size
+                .tdeps
+                .iter()
+                .take(HEAVIEST_DEPENDENCIES_COUNT)
+                .map(|(tdep, size)| format!("{} ({})", tdep, format_size(*size)))
+                .collect::<Vec<_>>()
+                .join("<br>")
+        )?;
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
+
+fn dep_id(dep: &Dependency) -> String {
+    match dep {
+        Dependency::ResolvedDep(ident) => ident.to_string(),
+        Dependency::RemoteDep(ident) => ident.to_string(),
+        Dependency::LocalPlan(plan_ctx) => plan_ctx.id.to_string(),
+    }
+}
+
+fn output_cycles_markdown(
+    run_context: &AutoBuildContext,
+    cycles: Vec<&DependencyCycle>,
+) -> Result<()> {
+    let mut output = String::new();
+    for (index, cycle) in cycles.iter().enumerate() {
+        writeln!(output, "## Cycle {}\n", index)?;
+        writeln!(output, "| Package | Depends On | Type | Remove |")?;
+        writeln!(output, "| --- | --- | --- | --- |")?;
+        for (source, target, dep_type) in cycle.edges.iter() {
+            let remove = *dep_type == DependencyType::Build
+                && cycle.suggested_breaks.contains(&(*source, *target));
+            writeln!(
+                output,
+                "| {:?} | {:?} | {} | {} |",
+                run_context.dep(*source),
+                run_context.dep(*target),
+                dep_type,
+                if remove { "yes" } else { "" }
+            )?;
+        }
+        writeln!(output)?;
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
+
+fn rebuild_cause_summary(cause: &DependencyChangeCause) -> String {
+    match cause {
+        DependencyChangeCause::PlanContextChanged {
+            files_changed_on_disk,
+            files_changed_on_git,
+            ..
+        } => {
+            if files_changed_on_git.is_empty() {
+                format!(
+                    "{} plan files modified on disk since the latest artifact was built",
+                    files_changed_on_disk.len()
+                )
+            } else {
+                format!(
+                    "{} plan files modified on git since the latest artifact was built",
+                    files_changed_on_git.len()
+                )
+            }
+        }
+        DependencyChangeCause::DependencyArtifactsUpdated {
+            updated_dep_artifacts,
+            ..
+        } => format!(
+            "dependencies re-built since the latest artifact was built: {}",
+            updated_dep_artifacts
+                .iter()
+                .map(|artifact| artifact.ident.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        DependencyChangeCause::NoBuiltArtifact => "plan not built yet".to_string(),
+        DependencyChangeCause::DependencyStudioNeedRebuild { plan } => {
+            format!("plan's studio {} has been modified", plan)
+        }
+        DependencyChangeCause::DependencyPlansNeedRebuild { plans } => format!(
+            "plan dependencies will be re-built: {}",
+            plans
+                .iter()
+                .map(|(_, plan_ctx_id, _)| plan_ctx_id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn output_rebuild_paths_plain(
+    run_context: &AutoBuildContext,
+    rebuild_paths: Vec<(NodeIndex, Vec<RebuildPath>, bool)>,
+) -> Result<()> {
+    for (package_index, paths, truncated) in rebuild_paths {
+        let package = run_context.dep(package_index);
+        if paths.is_empty() {
+            info!(target: "user-ui", "{}: {}\n", format!("{:?}", package).white().bold(), "Not rebuilt".green());
+            continue;
+        }
+        info!(target: "user-ui", "{}: Rebuilt due to changes along {} paths", format!("{:?}", package).white().bold(), paths.len());
+        // Paths starting at the same changed plan are listed together
+        let mut root_node_indices = Vec::new();
+        for path in paths.iter() {
+            if !root_node_indices.contains(&path.nodes[0]) {
+                root_node_indices.push(path.nodes[0]);
+            }
+        }
+        for root_node_index in root_node_indices {
+            let root_paths = paths
+                .iter()
+                .filter(|path| path.nodes[0] == root_node_index)
+                .collect::<Vec<_>>();
+            info!(target: "user-ui", "  {}", format!("{:?}", run_context.dep(root_node_index)).magenta());
+            for cause in root_paths[0].causes.iter() {
+                info!(target: "user-ui", "    {} {}", cause.to_emoji(), rebuild_cause_summary(cause));
+            }
+            for path in root_paths {
+                let mut output = format!("{:?}", run_context.dep(path.nodes[0]));
+                for (node_index, dep_type) in path.nodes[1..].iter().zip(path.dep_types.iter()) {
+                    write!(
+                        output,
+                        " {} {:?}",
+                        format!("-[{}]->", dep_type).cyan(),
+                        run_context.dep(*node_index)
+                    )?;
+                }
+                info!(target: "user-ui", "    - {}", output);
+            }
+        }
+        if truncated {
+            info!(target: "user-ui", "{}: Only the first {} paths are listed", "warning".bold().yellow(), paths.len());
+        }
+        info!(target: "user-ui", "");
     }
     Ok(())
 }
 
+fn output_rebuild_paths_json(
+    run_context: &AutoBuildContext,
+    rebuild_paths: Vec<(NodeIndex, Vec<RebuildPath>, bool)>,
+) -> Result<()> {
+    let rebuild_paths = rebuild_paths
+        .iter()
+        .map(|(package_index, paths, truncated)| RebuildPathsOutput {
+            plan: dep_id(run_context.dep(*package_index)),
+            rebuilt: !paths.is_empty(),
+            truncated: *truncated,
+            paths: paths
+                .iter()
+                .map(|path| RebuildPathOutput {
+                    plans: path
+                        .nodes
+                        .iter()
+                        .map(|node_index| dep_id(run_context.dep(*node_index)))
+                        .collect(),
+                    dependency_types: path.dep_types.clone(),
+                    causes: path.causes.iter().map(rebuild_cause_summary).collect(),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    output::output_json(JsonOutputKind::RebuildPaths, rebuild_paths)
+}
+
+fn output_graph_dot(
+    nodes: Vec<&Dependency>,
+    edges: Vec<(String, String, DependencyType)>,
+) -> Result<()> {
+    let mut output = String::new();
+    writeln!(output, "digraph dependencies {{")?;
+    writeln!(output, "  rankdir=LR;")?;
+    for dep in nodes {
+        let shape = match dep {
+            Dependency::LocalPlan(_) => "box",
+            Dependency::ResolvedDep(_) | Dependency::RemoteDep(_) => "ellipse",
+        };
+        writeln!(output, "  {} [shape={}];", dot_id(&dep_id(dep)), shape)?;
+    }
+    for (source, target, dep_type) in edges {
+        let style = match dep_type {
+            DependencyType::Runtime => "solid",
+            DependencyType::Build => "dashed",
+            DependencyType::Studio => "dotted",
+        };
+        writeln!(
+            output,
+            "  {} -> {} [label={}, style={}];",
+            dot_id(&source),
+            dot_id(&target),
+            dot_id(&dep_type.to_string()),
+            style
+        )?;
+    }
+    writeln!(output, "}}")?;
+    info!(target: "user-ui", "{}", output);
+    Ok(())
+}
+
+fn dot_id(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn output_graph_mermaid(
+    nodes: Vec<&Dependency>,
+    edges: Vec<(String, String, DependencyType)>,
+) -> Result<()> {
+    // Mermaid node ids cannot contain most punctuation, nodes are numbered instead
+    let mut node_ids = HashMap::new();
+    let mut output = String::new();
+    writeln!(output, "flowchart LR")?;
+    for (index, dep) in nodes.into_iter().enumerate() {
+        let id = dep_id(dep);
+        let label = id.replace('"', "#quot;");
+        match dep {
+            Dependency::LocalPlan(_) => writeln!(output, "    n{}[\"{}\"]", index, label)?,
+            Dependency::ResolvedDep(_) | Dependency::RemoteDep(_) => {
+                writeln!(output, "    n{}([\"{}\"])", index, label)?
+            }
+        }
+        node_ids.insert(id, index);
+    }
+    for (source, target, dep_type) in edges {
+        let arrow = match dep_type {
+            DependencyType::Runtime => "-->",
+            DependencyType::Build | DependencyType::Studio => "-.->",
+        };
+        writeln!(
+            output,
+            "    n{} {}|{}| n{}",
+            node_ids[&source], arrow, dep_type, node_ids[&target]
+        )?;
+    }
+    info!(target: "user-ui", "{}", output);
+    Ok(())
+}
+
+fn output_graph_json(
+    nodes: Vec<&Dependency>,
+    edges: Vec<(String, String, DependencyType)>,
+) -> Result<()> {
+    let graph = GraphOutput {
+        nodes: nodes
+            .into_iter()
+            .map(|dep| GraphNodeOutput {
+                id: dep_id(dep),
+                node_type: match dep {
+                    Dependency::ResolvedDep(_) => "resolved_dependency",
+                    Dependency::RemoteDep(_) => "remote_dependency",
+                    Dependency::LocalPlan(_) => "local_plan",
+                }
+                .to_string(),
+                plan: dep.plan_ctx().map(PlanOutput::from),
+            })
+            .collect(),
+        edges: edges
+            .into_iter()
+            .map(|(source, target, dep_type)| GraphEdgeOutput {
+                source,
+                target,
+                dep_type,
+            })
+            .collect(),
+    };
+    output::output_json(JsonOutputKind::Graph, graph)
+}
+
 #[allow(dead_code)]
 fn output_pretty(_deps: Vec<&Dependency>) {
     todo!()
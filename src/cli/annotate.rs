@@ -0,0 +1,253 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    check::{LeveledArtifactCheckViolation, LeveledSourceCheckViolation, PlanFile, ViolationLevel},
+    core::PlanContext,
+};
+
+/// Default path of the GitLab Code Quality report, relative to the current directory
+pub(crate) const GITLAB_REPORT_DEFAULT_PATH: &str = "gl-code-quality-report.json";
+
+lazy_static! {
+    pub(crate) static ref ANSI_ESCAPE_SEQUENCE: Regex =
+        Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum AnnotationFormat {
+    /// GitHub Actions workflow commands, printed along with the other output
+    Github,
+    /// GitLab Code Quality report, written to a file
+    Gitlab,
+}
+
+/// Check violation of a package, located in its plan file when the package is
+/// built from a local plan.
+#[derive(Debug)]
+pub(crate) struct Annotation {
+    pub package: String,
+    pub rule: String,
+    pub level: ViolationLevel,
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+}
+
+impl Annotation {
+    /// Creates the annotations for the violations of a package, violations that
+    /// are turned off are left out.
+    pub fn from_violations(
+        package: &str,
+        plan_ctx: Option<&PlanContext>,
+        source_violations: &[LeveledSourceCheckViolation],
+        artifact_violations: &[LeveledArtifactCheckViolation],
+    ) -> Vec<Annotation> {
+        let plan_file = plan_ctx.and_then(|plan_ctx| {
+            std::fs::read_to_string(plan_ctx.plan_path.as_ref())
+                .ok()
+                .and_then(|plan_source| PlanFile::parse(&plan_source).ok())
+        });
+        let file = plan_ctx.map(|plan_ctx| annotation_path(plan_ctx.plan_path.as_ref()));
+        let violations = source_violations
+            .iter()
+            .map(|violation| {
+                (
+                    violation.level,
+                    serde_json::to_value(&violation.violation).unwrap(),
+                    violation.violation.to_string(),
+                )
+            })
+            .chain(artifact_violations.iter().map(|violation| {
+                (
+                    violation.level,
+                    serde_json::to_value(&violation.violation).unwrap(),
+                    violation.violation.to_string(),
+                )
+            }));
+        let mut annotations = Vec::new();
+        for (level, value, message) in violations {
+            if level == ViolationLevel::Off {
+                continue;
+            }
+            let rule = value["rule"].as_str().unwrap_or_default().to_string();
+            let line = rule_plan_variable(&rule)
+                .and_then(|variable| plan_file.as_ref()?.assignment(variable))
+                .map(|assignment| assignment.line);
+            annotations.push(Annotation {
+                package: package.to_string(),
+                rule,
+                level,
+                message: ANSI_ESCAPE_SEQUENCE
+                    .replace_all(&message, "")
+                    .lines()
+                    .map(|line| line.trim())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                file: file.clone(),
+                line,
+            });
+        }
+        annotations
+    }
+
+    /// Formats the annotation as a GitHub Actions workflow command
+    pub fn github_command(&self) -> String {
+        let command = match self.level {
+            ViolationLevel::Error => "error",
+            ViolationLevel::Suggest => "notice",
+            _ => "warning",
+        };
+        let mut properties = Vec::new();
+        if let Some(file) = self.file.as_ref() {
+            properties.push(format!(
+                "file={}",
+                github_property_escape(&file.display().to_string())
+            ));
+        }
+        if let Some(line) = self.line {
+            properties.push(format!("line={}", line));
+        }
+        properties.push(format!(
+            "title={}",
+            github_property_escape(&format!("[{}] {}", self.rule, self.package))
+        ));
+        format!(
+            "::{} {}::{}",
+            command,
+            properties.join(","),
+            github_message_escape(&self.message)
+        )
+    }
+}
+
+/// Issue of a GitLab Code Quality report
+/// https://docs.gitlab.com/ee/ci/testing/code_quality.html#implement-a-custom-tool
+#[derive(Debug, Serialize)]
+struct GitlabCodeQualityIssue {
+    description: String,
+    check_name: String,
+    fingerprint: String,
+    severity: &'static str,
+    location: GitlabCodeQualityLocation,
+}
+
+#[derive(Debug, Serialize)]
+struct GitlabCodeQualityLocation {
+    path: PathBuf,
+    lines: GitlabCodeQualityLines,
+}
+
+#[derive(Debug, Serialize)]
+struct GitlabCodeQualityLines {
+    begin: usize,
+}
+
+/// Prints the annotations as GitHub Actions workflow commands
+pub(crate) fn output_github(annotations: &[Annotation]) {
+    for annotation in annotations {
+        info!(target: "user-ui", "{}", annotation.github_command());
+    }
+}
+
+/// Writes the annotations as a GitLab Code Quality report. Code Quality issues
+/// must be located in a file, so violations of packages without a local plan
+/// are left out.
+pub(crate) fn gitlab_report_write(annotations: &[Annotation], report_path: &Path) -> Result<()> {
+    let issues = annotations
+        .iter()
+        .filter_map(|annotation| {
+            let path = annotation.file.clone()?;
+            let description = format!("{}: {}", annotation.package, annotation.message);
+            let mut hasher = blake3::Hasher::new();
+            for part in [
+                annotation.rule.as_str(),
+                annotation.package.as_str(),
+                &path.display().to_string(),
+                annotation.message.as_str(),
+            ] {
+                hasher.update(part.as_bytes());
+                hasher.update(b"\n");
+            }
+            Some(GitlabCodeQualityIssue {
+                description,
+                check_name: annotation.rule.clone(),
+                fingerprint: hasher.finalize().to_hex().to_string(),
+                severity: match annotation.level {
+                    ViolationLevel::Error => "major",
+                    ViolationLevel::Suggest => "info",
+                    _ => "minor",
+                },
+                location: GitlabCodeQualityLocation {
+                    path,
+                    lines: GitlabCodeQualityLines {
+                        begin: annotation.line.unwrap_or(1),
+                    },
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+    std::fs::write(
+        report_path,
+        serde_json::to_string_pretty(&issues)
+            .context("Failed to serialize GitLab Code Quality report into JSON")?,
+    )
+    .with_context(|| {
+        format!(
+            "Failed to write GitLab Code Quality report to '{}'",
+            report_path.display()
+        )
+    })?;
+    info!(target: "user-log", "Wrote {} issues to the GitLab Code Quality report at {}", issues.len(), report_path.display());
+    Ok(())
+}
+
+/// Plan variable that must be changed to fix violations of the rule
+fn rule_plan_variable(rule: &str) -> Option<&'static str> {
+    match rule {
+        "missing-license"
+        | "license-not-found"
+        | "invalid-license-expression"
+        | "denied-license"
+        | "unknown-license" => Some("pkg_license"),
+        "unused-dependency"
+        | "missing-dependency-artifact"
+        | "removed-dependency-still-used"
+        | "undeclared-hook-dependency"
+        | "missing-hook-dependency" => Some("pkg_deps"),
+        "duplicate-dependency" => Some("pkg_build_deps"),
+        _ => None,
+    }
+}
+
+/// CI systems expect paths relative to the checkout, which is usually the
+/// current directory
+fn annotation_path(path: &Path) -> PathBuf {
+    env::current_dir()
+        .ok()
+        .and_then(|current_dir| path.strip_prefix(current_dir).ok())
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+fn github_message_escape(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn github_property_escape(value: &str) -> String {
+    github_message_escape(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
@@ -0,0 +1,321 @@
+use std::{env, fmt::Write, path::PathBuf};
+
+use chrono::{Duration, Utc};
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::OwoColorize;
+use rayon::prelude::*;
+use tracing::{error, info};
+
+use crate::{
+    cli::output::{self, AuditOutput, JsonOutputKind, PlanOutput, ReportFormat},
+    core::{
+        AuditQuery, AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageBuildVersion,
+        PackageDepGlob, PackageTarget, PlanContext, Vulnerability, VulnerabilityChecker,
+        VulnerabilitySeverity,
+    },
+    store,
+};
+
+/// Number of hours the vulnerabilities found by a query are reused before the
+/// vulnerability database is queried again
+const VULNERABILITY_CACHE_HOURS: i64 = 24;
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Output format
+    #[arg(value_enum, short = 'f', long, default_value_t = ReportFormat::Plain)]
+    format: ReportFormat,
+    /// Package target of the plans to check, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// Number of plans checked concurrently in OSV, plans are checked in the NVD one at a time
+    #[arg(short = 'j', long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: u16,
+    /// Fail if a vulnerability of this severity or higher is found, or if a plan could not be checked
+    #[arg(value_enum, long)]
+    fail_on: Option<VulnerabilitySeverity>,
+    /// Query the vulnerability databases again instead of using the results cached in the store
+    #[arg(long)]
+    refresh: bool,
+    /// List of packages to check for vulnerabilities, all plans are checked if none are specified
+    packages: Vec<PackageDepGlob>,
+}
+
+/// Result of looking up the known vulnerabilities of a plan's version
+struct PlanAudit<'a> {
+    plan_ctx: &'a PlanContext,
+    version: String,
+    query: AuditQuery,
+    result: Result<Vec<Vulnerability>>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let packages = if args.packages.is_empty() {
+        vec![PackageDepGlob::parse("*/*").unwrap()]
+    } else {
+        args.packages.clone()
+    };
+    let package_indices = run_context.glob_deps(&packages, target)?;
+    if package_indices.is_empty() && !run_context.is_empty() && !args.packages.is_empty() {
+        error!(target: "user-log",
+            "No packages found matching patterns: {}",
+            serde_json::to_string(&args.packages).unwrap()
+        );
+        return Ok(());
+    }
+
+    // Only plans with a static version can be checked
+    let plans = package_indices
+        .iter()
+        .filter_map(|package_index| run_context.dep(*package_index).plan_ctx())
+        .filter_map(|plan_ctx| match &plan_ctx.id.as_ref().version {
+            PackageBuildVersion::Static(version) => Some((plan_ctx, version.to_string())),
+            _ => None,
+        })
+        .map(|(plan_ctx, version)| {
+            let query = AuditQuery::new(
+                &plan_ctx.id.as_ref().name.to_string(),
+                &version,
+                plan_ctx.source.as_ref().map(|source| &source.url),
+                plan_ctx
+                    .plan_config
+                    .as_ref()
+                    .and_then(|plan_config| plan_config.audit.as_ref()),
+            );
+            (plan_ctx, version, query)
+        })
+        .collect::<Vec<_>>();
+    // The NVD only accepts a few requests at a time, the plans looked up in it are
+    // checked one at a time alongside the plans looked up in OSV
+    let (nvd_plans, osv_plans): (Vec<_>, Vec<_>) = plans
+        .into_iter()
+        .partition(|(_, _, query)| matches!(query, AuditQuery::Nvd { .. }));
+
+    let checker = VulnerabilityChecker::new()?;
+    let check_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.concurrency as usize)
+        .build()
+        .context("Failed to create vulnerability check thread pool")?;
+    let (mut audits, nvd_audits) = check_pool.install(|| {
+        rayon::join(
+            || {
+                osv_plans
+                    .into_par_iter()
+                    .map(|plan| plan_audit(&run_context, &checker, plan, args.refresh))
+                    .collect::<Vec<_>>()
+            },
+            || {
+                nvd_plans
+                    .into_iter()
+                    .map(|plan| plan_audit(&run_context, &checker, plan, args.refresh))
+                    .collect::<Vec<_>>()
+            },
+        )
+    });
+    audits.extend(nvd_audits);
+    audits.sort_by_key(|audit| audit.plan_ctx.id.to_string());
+
+    match args.format {
+        ReportFormat::Plain => output_plain(&audits)?,
+        ReportFormat::Json => output::output_json(
+            JsonOutputKind::Audit,
+            audits
+                .iter()
+                .map(|audit| AuditOutput {
+                    plan: PlanOutput::from(audit.plan_ctx),
+                    version: audit.version.clone(),
+                    checked: audit.query.to_string(),
+                    vulnerabilities: audit.result.as_ref().cloned().unwrap_or_default(),
+                    error: audit.result.as_ref().err().map(|err| format!("{:#}", err)),
+                })
+                .collect::<Vec<_>>(),
+        )?,
+        ReportFormat::Markdown => output_markdown(&audits)?,
+    }
+
+    if let Some(fail_on) = args.fail_on {
+        let vulnerability_count = audits
+            .iter()
+            .filter_map(|audit| audit.result.as_ref().ok())
+            .flatten()
+            .filter(|vulnerability| vulnerability.severity >= fail_on)
+            .count();
+        if vulnerability_count > 0 {
+            return Err(eyre!(
+                "Found {} vulnerabilities with a severity of {} or higher",
+                vulnerability_count,
+                fail_on
+            ));
+        }
+        let error_count = audits.iter().filter(|audit| audit.result.is_err()).count();
+        if error_count > 0 {
+            return Err(eyre!(
+                "Failed to check {} plans for vulnerabilities",
+                error_count
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Looks up the vulnerabilities of a plan, leaving out the ones ignored by its config
+fn plan_audit<'a>(
+    run_context: &AutoBuildContext,
+    checker: &VulnerabilityChecker,
+    (plan_ctx, version, query): (&'a PlanContext, String, AuditQuery),
+    refresh: bool,
+) -> PlanAudit<'a> {
+    let result =
+        plan_vulnerabilities(run_context, checker, &query, refresh).map(|vulnerabilities| {
+            let ignored = plan_ctx
+                .plan_config
+                .as_ref()
+                .and_then(|plan_config| plan_config.audit.as_ref())
+                .map(|audit_config| audit_config.ignore.as_slice())
+                .unwrap_or_default();
+            let mut vulnerabilities = vulnerabilities
+                .into_iter()
+                .filter(|vulnerability| !vulnerability.is_any_of(ignored))
+                .collect::<Vec<_>>();
+            vulnerabilities
+                .sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.id.cmp(&b.id)));
+            vulnerabilities
+        });
+    PlanAudit {
+        plan_ctx,
+        version,
+        query,
+        result,
+    }
+}
+
+/// Looks up the vulnerabilities found by a query, reusing the results cached in
+/// the store to avoid the rate limits of the vulnerability databases
+fn plan_vulnerabilities(
+    run_context: &AutoBuildContext,
+    checker: &VulnerabilityChecker,
+    query: &AuditQuery,
+    refresh: bool,
+) -> Result<Vec<Vulnerability>> {
+    let mut connection = run_context.get_connection()?;
+    let query_key = query.to_string();
+    if !refresh {
+        if let Some(vulnerabilities) = store::vulnerability_query_get(
+            &mut connection,
+            &query_key,
+            Utc::now() - Duration::hours(VULNERABILITY_CACHE_HOURS),
+        )? {
+            return serde_json::from_str(&vulnerabilities)
+                .context("Failed to read cached vulnerabilities");
+        }
+    }
+    let vulnerabilities = checker.vulnerabilities(query)?;
+    store::vulnerability_query_put(
+        &mut connection,
+        &query_key,
+        &serde_json::to_string(&vulnerabilities)?,
+    )?;
+    Ok(vulnerabilities)
+}
+
+fn output_plain(audits: &[PlanAudit]) -> Result<()> {
+    let mut vulnerable_count = 0;
+    let mut error_count = 0;
+    for audit in audits.iter() {
+        match &audit.result {
+            Ok(vulnerabilities) if vulnerabilities.is_empty() => {}
+            Ok(vulnerabilities) => {
+                vulnerable_count += 1;
+                info!(
+                    target: "user-ui",
+                    "{}: {} known vulnerabilities in {} ({})",
+                    audit.plan_ctx.id,
+                    vulnerabilities.len().red(),
+                    audit.version.yellow(),
+                    audit.query.to_string().bright_black()
+                );
+                for vulnerability in vulnerabilities.iter() {
+                    let severity = match vulnerability.score {
+                        Some(score) => format!("{} {:.1}", vulnerability.severity, score),
+                        None => vulnerability.severity.to_string(),
+                    };
+                    info!(
+                        target: "user-ui",
+                        "  {} [{}] {} {}",
+                        vulnerability.id.bold(),
+                        severity,
+                        vulnerability.summary.as_deref().unwrap_or_default(),
+                        vulnerability.url.bright_black()
+                    );
+                }
+            }
+            Err(err) => {
+                error_count += 1;
+                info!(target: "user-ui", "{}: Failed to check {} for vulnerabilities: {:#}", "error".bold().red(), audit.plan_ctx.id, err);
+            }
+        }
+    }
+    info!(
+        target: "user-log",
+        "Checked {} plans for vulnerabilities, {} have known vulnerabilities, {} could not be checked",
+        audits.len().blue(),
+        vulnerable_count.blue(),
+        error_count.blue()
+    );
+    Ok(())
+}
+
+fn output_markdown(audits: &[PlanAudit]) -> Result<()> {
+    let mut output = String::new();
+    writeln!(
+        output,
+        "| Plan | Version | Vulnerability | Severity | Summary |"
+    )?;
+    writeln!(output, "| --- | --- | --- | --- | --- |")?;
+    for audit in audits.iter() {
+        for vulnerability in audit.result.iter().flatten() {
+            writeln!(
+                output,
+                "| {} | {} | [{}]({}) | {} | {} |",
+                audit.plan_ctx.id,
+                audit.version,
+                vulnerability.id,
+                vulnerability.url,
+                vulnerability.severity,
+                vulnerability
+                    .summary
+                    .as_deref()
+                    .unwrap_or_default()
+                    .replace('|', "\\|")
+                    .replace('\n', " ")
+            )?;
+        }
+    }
+    let failed_audits = audits
+        .iter()
+        .filter_map(|audit| audit.result.as_ref().err().map(|err| (audit, err)))
+        .collect::<Vec<_>>();
+    if !failed_audits.is_empty() {
+        writeln!(output, "\n## Plans that could not be checked\n")?;
+        for (audit, err) in failed_audits {
+            writeln!(output, "- {}: {:#}", audit.plan_ctx.id, err)?;
+        }
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
@@ -1,24 +1,48 @@
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use chrono_humanize::{Accuracy, HumanTime, Tense};
 use clap::{Args, ValueEnum};
-use color_eyre::eyre::{eyre, Context, Result};
+use color_eyre::{
+    eyre::{eyre, Context, Report, Result},
+    Help,
+};
 use owo_colors::OwoColorize;
-use std::{env, path::PathBuf};
+use petgraph::stable_graph::NodeIndex;
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, RecvTimeoutError},
+};
 use tracing::{error, info};
 
 use crate::{
     check::ViolationLevel,
     cli::{
         check::{self, output_violations},
-        output::OutputFormat,
+        clean::format_size,
+        lock::output_difference,
+        output::{self, BuildEventOutput, JsonOutputKind, OutputFormat},
+        tui,
     },
     core::{
-        habitat::BuildError, AutoBuildConfig, AutoBuildContext, BuildOrder, BuildPlan, BuildStep,
-        BuildStepError, ChangeDetectionMode, Dependency, DownloadStatus, PackageDepGlob,
-        PackageTarget, PlanCheckStatus,
+        artifact_content_diff, build_failure_analyze, dependency_lock_path,
+        habitat::{self, BuildError, BuildExecutor},
+        host_name, ArtifactsDirMode, AutoBuildConfig, AutoBuildContext, BuildArgsConfig,
+        BuildOrder, BuildPlan, BuildPlanScope, BuildSessionSummary, BuildStep, BuildStepError,
+        BuilderClient, ChangeDetectionMode, Dependency, DependencyLock, DiskSpaceCheckPolicy,
+        DownloadStatus, NotifyConfig, OfflineMissingItem, PackageDepGlob, PackageDepIdent,
+        PackageRelease, PackageTarget, PackageVersion, PlanCheckStatus, SkipStep,
     },
+    store::{BuildClaim, BuildClaimStatus, BuildStepStatus, StoreLock},
 };
 
+/// Interval at which a cooperative build worker checks for new steps to claim
+const COOPERATIVE_POLL_INTERVAL_SECS: u64 = 5;
+
+/// Disk space in MB kept free on top of the estimated needs of a build, unless configured
+#[cfg(not(target_os = "windows"))]
+const DEFAULT_DISK_SPACE_HEADROOM_MB: u64 = 2048;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub(crate) enum CheckLevel {
     AllowAll,
@@ -31,8 +55,8 @@ pub(crate) struct Params {
     /// Path to hab auto build configuration
     #[arg(short, long)]
     config_path: Option<PathBuf>,
-    /// Output format
-    #[arg(value_enum, short = 'f', long, default_value_t = OutputFormat::Plain, requires = "dry_run")]
+    /// Output format, JSON output of cooperative builds is a stream of one event per line
+    #[arg(value_enum, short = 'f', long, default_value_t = OutputFormat::Plain)]
     format: OutputFormat,
     /// Do a dry run of the build, does not actually build anything
     #[arg(short = 'd', long)]
@@ -46,11 +70,74 @@ pub(crate) struct Params {
     /// Allow use of packages from a remote habitat builder instance specified by HAB_BLDR_URL
     #[arg(short = 'r', long)]
     allow_remote: bool,
+    /// Fail if a remote dependency cannot be resolved from the Builder channel specified by HAB_BLDR_CHANNEL
+    #[arg(long, requires = "allow_remote")]
+    strict_remote: bool,
+    /// Verify that all dependency artifacts and sources are available locally before building, and never download anything
+    #[arg(long, conflicts_with = "allow_remote")]
+    offline: bool,
     /// Level of checks to perform
     #[arg(value_enum, short = 'l', long, default_value_t = CheckLevel::Strict)]
     check_level: CheckLevel,
+    /// Folder in which to place successfully built artifacts, overrides the configured artifacts folder
+    #[arg(long)]
+    artifacts_dir: Option<PathBuf>,
+    /// How to place built artifacts in the artifacts folder, overrides the configured mode
+    #[arg(value_enum, long, requires = "artifacts_dir")]
+    artifacts_dir_mode: Option<ArtifactsDirMode>,
+    /// Where to build bootstrap and standard plans, either 'local' or 'ssh://[user@]host[:port][/path]', overrides the configured executor
+    #[arg(long)]
+    executor: Option<BuildExecutor>,
+    /// Package target to build for, only dry runs are supported for targets other than the host, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// Share the build with other workers using the same store, each worker claims and builds steps until the build is done
+    #[arg(long, conflicts_with = "dry_run")]
+    cooperative: bool,
+    /// Identifier of the cooperative build, defaults to an identifier derived from the build plan
+    #[arg(long, requires = "cooperative")]
+    build_id: Option<String>,
+    /// Identifier of this worker in the cooperative build, defaults to the host name and process id
+    #[arg(long, requires = "cooperative")]
+    worker_id: Option<String>,
+    /// Number of seconds after which a step claimed by an unresponsive worker can be claimed by another worker
+    #[arg(long, default_value_t = 600, requires = "cooperative")]
+    lease_timeout: i64,
+    /// What to do when the build is estimated to need more disk space than available, overrides the configured policy
+    #[arg(value_enum, long)]
+    disk_space_check: Option<DiskSpaceCheckPolicy>,
+    /// Wait for other builds using the same store to finish instead of failing
+    #[arg(long)]
+    wait: bool,
+    /// Fail if any remote dependency resolves to a different artifact than the one pinned by the lockfile
+    #[arg(long)]
+    locked: bool,
+    /// Verify that the plans matching these patterns build reproducibly, each build is compared with the previous artifact of the plan or with a second build
+    #[arg(long, value_name = "PACKAGES", conflicts_with_all = ["dry_run", "cooperative"])]
+    verify_reproducible: Vec<PackageDepGlob>,
+    /// Only build the selected packages, not the changed dependencies they would be built with
+    #[arg(long, requires = "packages")]
+    skip_deps: bool,
+    /// Only build the plans that changed, not the plans that would be rebuilt because their dependencies are rebuilt
+    #[arg(long)]
+    only_changed: bool,
+    /// Stop the rebuild at the packages matching this pattern, they are built but the plans depending on them are not
+    #[arg(long, value_name = "PACKAGE")]
+    through: Option<PackageDepGlob>,
+    /// Resume the last build session, building the packages it failed to build or did not get to
+    #[arg(long, conflicts_with_all = ["packages", "skip_deps", "only_changed", "through", "cooperative"])]
+    resume: bool,
+    /// Keep building the packages that do not depend on a failed package instead of stopping at the first failure
+    #[arg(long, conflicts_with_all = ["dry_run", "cooperative"])]
+    keep_going: bool,
+    /// Follow the build in an interactive dashboard showing the status and build log of every package
+    #[arg(long, conflicts_with_all = ["dry_run", "cooperative", "format"])]
+    tui: bool,
     /// List of packages to build
     packages: Vec<PackageDepGlob>,
+    /// Extra arguments passed to the build command of every plan, after the configured ones
+    #[arg(last = true)]
+    build_args: Vec<String>,
 }
 
 pub(crate) fn execute(args: Params) -> Result<()> {
@@ -59,12 +146,79 @@ pub(crate) fn execute(args: Params) -> Result<()> {
             .context("Failed to determine current working directory")?
             .join("hab-auto-build.json"),
     );
-    let config = AutoBuildConfig::new(&config_path)?;
+    if args.format != OutputFormat::Plain && !args.dry_run && !args.cooperative {
+        return Err(eyre!(
+            "The {:?} output format is only supported for dry runs and cooperative builds",
+            args.format
+        ));
+    }
+    if args.tui {
+        tui::dashboard_verify()?;
+    }
+    let mut config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
+    if target != PackageTarget::default() && !args.dry_run {
+        return Err(eyre!(
+            "Building {} packages on a {} host is not supported",
+            target,
+            PackageTarget::default()
+        ))
+        .with_suggestion(|| "Use '--dry-run' to preview the build plan for the target");
+    }
+    if let Some(artifacts_dir) = args.artifacts_dir {
+        config.artifacts_dir = Some(
+            env::current_dir()
+                .context("Failed to determine current working directory")?
+                .join(artifacts_dir),
+        );
+    }
+    if let Some(artifacts_dir_mode) = args.artifacts_dir_mode {
+        config.artifacts_dir_mode = artifacts_dir_mode;
+    }
+    if let Some(executor) = args.executor {
+        config.build_executor = executor;
+    }
+    if let Some(disk_space_check) = args.disk_space_check {
+        config.disk_space_check = disk_space_check;
+    }
+    if !args.build_args.is_empty() {
+        habitat::build_args_validate(&args.build_args)?;
+        config.build_args.push(BuildArgsConfig {
+            package: PackageDepGlob::parse("*/*")?,
+            args: args.build_args,
+        });
+    }
+    if args.cooperative && config.artifacts_dir_mode == ArtifactsDirMode::Move {
+        return Err(eyre!(
+            "Cooperative builds share artifacts through the hab-auto-build store, they cannot be moved out of it"
+        ))
+        .with_suggestion(|| "Use the 'copy' artifacts folder mode for cooperative builds");
+    }
 
-    let run_context = AutoBuildContext::new(&config, &config_path, args.change_detection_mode)
+    // Cooperative workers share the store on purpose and coordinate through build
+    // claims instead. The lock is held for the whole build, and acquired before the
+    // plans are scanned so that changes made by a build we waited for are seen.
+    let _store_lock = if args.dry_run || args.cooperative {
+        None
+    } else {
+        Some(StoreLock::acquire(
+            config.store_path(&config_path)?,
+            "build",
+            args.wait,
+        )?)
+    };
+
+    let mut run_context = AutoBuildContext::new(&config, &config_path, args.change_detection_mode)
         .with_context(|| eyre!("Failed to initialize run"))?;
+    run_context.broken_plans_verify()?;
+    if args.locked {
+        dependency_lock_verify(&run_context, &config_path)?;
+    }
+    if args.allow_remote {
+        remote_deps_resolve(&mut run_context, target, args.strict_remote)?;
+    }
 
-    let package_indices = run_context.glob_deps(&args.packages, PackageTarget::default())?;
+    let package_indices = run_context.glob_deps(&args.packages, target)?;
     if package_indices.is_empty() && !run_context.is_empty() && !args.packages.is_empty() {
         error!(target: "user-log",
             "No packages found matching patterns: {}",
@@ -72,19 +226,53 @@ pub(crate) fn execute(args: Params) -> Result<()> {
         );
         return Ok(());
     }
+    let through = match args.through.as_ref() {
+        Some(through) => {
+            let through_indices = run_context.glob_deps(std::slice::from_ref(through), target)?;
+            if through_indices.is_empty() {
+                return Err(eyre!("No packages found matching pattern: {}", through));
+            }
+            through_indices
+        }
+        None => Vec::new(),
+    };
+    let resume = if args.resume {
+        let Some(plans) = build_session_resume_plans(&run_context)? else {
+            return Ok(());
+        };
+        Some(plans)
+    } else {
+        None
+    };
     let build_plan = run_context.build_plan_generate(
         package_indices,
         args.change_detection_mode,
         args.build_order,
-        PackageTarget::default(),
+        target,
         args.allow_remote,
+        &BuildPlanScope {
+            skip_deps: args.skip_deps,
+            only_changed: args.only_changed,
+            through,
+            resume,
+        },
     )?;
+    if args.offline {
+        offline_items_verify(&run_context, &build_plan)?;
+    }
     if args.dry_run {
         match args.format {
             OutputFormat::Plain => output_plain(build_plan)?,
             OutputFormat::Json => output_json(build_plan)?,
         }
     } else {
+        run_context.build_sandbox_verify(&build_plan.build_steps)?;
+        run_context.docker_images_verify(&build_plan.build_steps)?;
+        #[cfg(not(target_os = "windows"))]
+        disk_space_verify(&run_context, &config, &build_plan.build_steps)?;
+        for mismatch in run_context.build_host_mismatches(&build_plan)? {
+            info!(target: "user-ui", "{}: {} was built on a different host ({}): {}", "warning".bold().yellow(), mismatch.artifact, mismatch.host, mismatch.differences.join(", "));
+        }
         let mut all_checks_passed = true;
         for step in build_plan.check_steps {
             let mut step_check_passed = true;
@@ -99,7 +287,7 @@ pub(crate) fn execute(args: Params) -> Result<()> {
                     info!(target: "user-ui", "{} [plan] {}", "     Checking".green().bold(), plan_ctx.id);
                 }
             }
-            match run_context.package_check(step.index) {
+            match run_context.package_check(step.index, None) {
                 Ok(check_status) => match check_status {
                     PlanCheckStatus::CheckSucceeded(
                         plan_config_path,
@@ -188,110 +376,840 @@ pub(crate) fn execute(args: Params) -> Result<()> {
             info!(target: "user-ui", "{}: Found issues with dependency packages, you should fix them before building more packages", "error".bold().red());
             return Ok(());
         }
-        for step in build_plan.build_steps {
-            info!(target: "user-ui", "{} [{}] {}", "     Building".green().bold(), step.studio, step.plan_ctx.id);
-            match run_context.download_plan_source(step.plan_ctx, true)? {
-                DownloadStatus::Downloaded(_source_ctx, _, _, _, source_violations)
-                | DownloadStatus::AlreadyDownloaded(_source_ctx, _, _, source_violations) => {
-                    let source_warnings = source_violations
-                        .iter()
-                        .filter(|v| v.level == ViolationLevel::Warn)
-                        .count();
-                    let source_errors = source_violations
-                        .iter()
-                        .filter(|v| v.level == ViolationLevel::Error)
-                        .count();
-                    match args.check_level {
-                        CheckLevel::AllowWarnings if source_errors > 0 => all_checks_passed = false,
-                        CheckLevel::Strict if source_errors + source_warnings > 0 => {
-                            all_checks_passed = false
-                        }
-                        _ => {}
-                    };
-                    output_violations(
-                        if !all_checks_passed {
-                            Some(step.plan_ctx.plan_path.plan_config_path())
-                        } else {
-                            None
-                        },
-                        &source_violations,
-                        &[],
-                        &step.plan_ctx.id.to_string(),
-                        false,
-                        false,
-                    )?;
-                    if !all_checks_passed {
-                        info!(target: "user-ui", "{} [{}] {}", "Build Failure".red().bold(), step.studio, step.plan_ctx.id);
-                        info!(target: "user-ui", "{}: Found issues with the package {}, you should fix the plan at {} before re-attempting the build.", "error".bold().red(), step.plan_ctx.id.yellow(), step.plan_ctx.plan_path.as_ref().display().blue());
-                        return Ok(());
+        for step in build_plan.skip_steps.iter() {
+            output_skip_step(step);
+        }
+        run_context.build_steps_pending_put(&build_plan.build_steps)?;
+        run_context.build_session_start(&build_plan.build_steps)?;
+        let session_result = if args.cooperative {
+            let build_id = match args.build_id {
+                Some(build_id) => build_id,
+                None => run_context.build_plan_id(&build_plan.build_steps)?,
+            };
+            let worker_id = args.worker_id.unwrap_or_else(|| {
+                format!(
+                    "{}-{}",
+                    host_name().unwrap_or_else(|| "localhost".to_string()),
+                    std::process::id()
+                )
+            });
+            cooperative_build_steps_execute(
+                &run_context,
+                &build_plan.build_steps,
+                args.check_level,
+                &CooperativeWorker {
+                    build_id,
+                    worker_id,
+                    lease_timeout: Duration::seconds(args.lease_timeout),
+                    format: args.format,
+                },
+            )
+        } else if args.tui {
+            tui::build_dashboard_run(&run_context, &build_plan.build_steps, || {
+                build_steps_execute(
+                    &run_context,
+                    &build_plan.build_steps,
+                    args.check_level,
+                    &args.verify_reproducible,
+                    args.keep_going,
+                )
+            })
+        } else {
+            build_steps_execute(
+                &run_context,
+                &build_plan.build_steps,
+                args.check_level,
+                &args.verify_reproducible,
+                args.keep_going,
+            )
+        };
+        if let Err(err) = run_context.build_session_end() {
+            info!(target: "user-ui", "{}: Failed to record the end of the build session: {:#}", "warning".bold().yellow(), err);
+        }
+        let session_succeeded = matches!(session_result, Ok(true));
+        if run_context
+            .studio_cleanup_policy()
+            .should_cleanup(session_succeeded)
+        {
+            match run_context.session_studios_cleanup() {
+                Ok(studio_roots) => {
+                    for studio_root in studio_roots {
+                        info!(target: "user-ui", "{} {}", "      Removed".green().bold(), studio_root.display());
                     }
                 }
-                DownloadStatus::MissingSource(_) => {}
-                DownloadStatus::NoSource => {
-                    unreachable!()
+                Err(err) => {
+                    info!(target: "user-ui", "{}: Failed to cleanup build studios: {:#}", "warning".bold().yellow(), err);
                 }
-                DownloadStatus::InvalidArchive(_, source, actual_shasum, _) => {
-                    return Err(eyre!(
-                        "Failed to download package source, package shasum mismatch. Expected shasum {}, found shasum {}", source.shasum, actual_shasum
-                    ));
+            }
+        }
+        if let Some(notify) = config.notify.as_ref() {
+            build_session_notify(
+                &run_context,
+                notify,
+                &config,
+                &config_path,
+                session_succeeded,
+            );
+        }
+        session_result?;
+    }
+    Ok(())
+}
+
+/// Finds the plans left to build by the last build session, returns nothing if
+/// there is nothing to resume.
+fn build_session_resume_plans(run_context: &AutoBuildContext) -> Result<Option<Vec<NodeIndex>>> {
+    let Some((plans, missing_plans)) = run_context.build_session_resume_plans()? else {
+        return Err(eyre!("No build session found to resume"))
+            .with_suggestion(|| "Run 'hab-auto-build build' without '--resume' to start one");
+    };
+    for plan in missing_plans {
+        info!(target: "user-ui", "{}: Not resuming the build of {}, the plan is no longer part of the workspace", "warning".bold().yellow(), plan);
+    }
+    if plans.is_empty() {
+        info!(target: "user-log", "The last build session built all its packages, nothing to resume");
+        return Ok(None);
+    }
+    info!(target: "user-log", "Resuming the last build session, {} packages left to build", plans.len());
+    Ok(Some(plans))
+}
+
+/// Sends the notifications configured for the end of the build session, failing
+/// to send them does not fail the build.
+fn build_session_notify(
+    run_context: &AutoBuildContext,
+    notify: &NotifyConfig,
+    config: &AutoBuildConfig,
+    config_path: &Path,
+    session_succeeded: bool,
+) {
+    let errors = match build_session_summary(run_context, config, config_path, session_succeeded) {
+        Ok(summary) => notify.notify(&summary),
+        Err(err) => vec![err],
+    };
+    for err in errors {
+        info!(target: "user-ui", "{}: Failed to send build notification: {:#}", "warning".bold().yellow(), err);
+    }
+}
+
+fn build_session_summary(
+    run_context: &AutoBuildContext,
+    config: &AutoBuildConfig,
+    config_path: &Path,
+    session_succeeded: bool,
+) -> Result<BuildSessionSummary> {
+    let build_session = run_context
+        .build_sessions(Some(1))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre!("No build session recorded"))?;
+    let failed_plans = run_context
+        .build_session_step_states(&build_session)?
+        .into_iter()
+        .filter(|step_state| step_state.status == BuildStepStatus::Failed)
+        .map(|step_state| step_state.plan)
+        .collect();
+    Ok(BuildSessionSummary {
+        workspace: config.workspace_id(config_path)?,
+        succeeded: session_succeeded,
+        planned: build_session.planned,
+        built: build_session.succeeded,
+        failed_plans,
+        duration: build_session
+            .ended_at
+            .map(|ended_at| ended_at - build_session.started_at),
+    })
+}
+
+/// Fails if any artifact or source needed by the build plan is missing, listing
+/// everything that must be made available before building offline.
+fn offline_items_verify(run_context: &AutoBuildContext, build_plan: &BuildPlan) -> Result<()> {
+    let missing_items = run_context.offline_missing_items(build_plan);
+    if missing_items.is_empty() {
+        return Ok(());
+    }
+    for missing_item in missing_items.iter() {
+        match missing_item {
+            OfflineMissingItem::Artifact { plan, dependency } => {
+                info!(target: "user-ui", "{} [artifact] {} required by {}", "      Missing".red().bold(), dependency, plan);
+            }
+            OfflineMissingItem::Source { plan, source } => {
+                info!(target: "user-ui", "{} [source] {} ({}) required by {}", "      Missing".red().bold(), source.url, source.shasum, plan);
+            }
+        }
+    }
+    Err(eyre!(
+        "Found {} missing artifacts and sources required to build offline",
+        missing_items.len()
+    ))
+    .with_suggestion(|| {
+        "Download the missing sources with 'hab-auto-build download' and install the missing artifacts into the artifact cache"
+    })
+}
+
+/// Compares the disk space the build steps are estimated to need with the space
+/// available on the file systems they write to.
+#[cfg(not(target_os = "windows"))]
+fn disk_space_verify(
+    run_context: &AutoBuildContext,
+    config: &AutoBuildConfig,
+    build_steps: &[BuildStep],
+) -> Result<()> {
+    if config.disk_space_check == DiskSpaceCheckPolicy::Ignore || build_steps.is_empty() {
+        return Ok(());
+    }
+    let headroom = config
+        .disk_space_headroom_mb
+        .unwrap_or(DEFAULT_DISK_SPACE_HEADROOM_MB)
+        * 1024
+        * 1024;
+    let estimate = run_context.disk_space_estimate(build_steps)?;
+    let mut short_file_systems = 0;
+    for requirement in estimate.requirements.iter() {
+        if requirement.required + headroom <= requirement.available {
+            continue;
+        }
+        short_file_systems += 1;
+        let paths = requirement
+            .paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        info!(target: "user-ui", "{}: the build needs an estimated {} (+{} headroom) on the file system of {}, only {} is available", "warning".bold().yellow(), format_size(requirement.required), format_size(headroom), paths, format_size(requirement.available));
+    }
+    if short_file_systems > 0 && estimate.unestimated_steps > 0 {
+        info!(target: "user-ui", "{}: {} plans were never built before and are not part of the estimate", "warning".bold().yellow(), estimate.unestimated_steps);
+    }
+    if short_file_systems > 0 && config.disk_space_check == DiskSpaceCheckPolicy::Abort {
+        return Err(eyre!(
+            "The build needs more disk space than available on {} file systems",
+            short_file_systems
+        ))
+        .with_suggestion(|| {
+            "Free up space with 'hab-auto-build clean', or set 'disk_space_check' to 'warn' to build anyway"
+        });
+    }
+    Ok(())
+}
+
+/// Resolves the remote dependencies of the build graph from Builder, dependencies
+/// that cannot be resolved are reported and fail the build in strict mode.
+fn remote_deps_resolve(
+    run_context: &mut AutoBuildContext,
+    target: PackageTarget,
+    strict: bool,
+) -> Result<()> {
+    let builder = BuilderClient::new()?;
+    let unresolved = run_context.remote_deps_resolve(&builder, target);
+    if unresolved.is_empty() {
+        return Ok(());
+    }
+    for (dependency, reason) in unresolved.iter() {
+        if strict {
+            info!(target: "user-ui", "{} {}: {}", "   Unresolved".red().bold(), dependency, reason);
+        } else {
+            info!(target: "user-ui", "{}: Failed to resolve remote dependency {}: {}", "warning".bold().yellow(), dependency, reason);
+        }
+    }
+    if strict {
+        return Err(eyre!(
+            "Failed to resolve {} remote dependencies from Builder",
+            unresolved.len()
+        ))
+        .with_suggestion(|| {
+            "Publish the missing packages to the Builder channel, or select another channel with HAB_BLDR_CHANNEL"
+        });
+    }
+    Ok(())
+}
+
+/// Fails if the remote dependencies of the build graph do not resolve to the
+/// artifacts pinned by the lockfile.
+fn dependency_lock_verify(run_context: &AutoBuildContext, config_path: &Path) -> Result<()> {
+    let lock_path = dependency_lock_path(config_path);
+    if !lock_path.is_file() {
+        return Err(eyre!("No lockfile found at '{}'", lock_path.display()))
+            .with_suggestion(|| "Create the lockfile with 'hab-auto-build lock'");
+    }
+    let dependency_lock = DependencyLock::read(&lock_path)?;
+    let (current_lock, unresolved) = run_context.dependency_lock();
+    let differences = dependency_lock.differences(&current_lock, &unresolved);
+    if differences.is_empty() {
+        return Ok(());
+    }
+    for difference in differences.iter() {
+        output_difference(difference);
+    }
+    Err(eyre!(
+        "Found {} dependencies that do not resolve as pinned by the lockfile '{}'",
+        differences.len(),
+        lock_path.display()
+    ))
+    .with_suggestion(|| {
+        "Restore the pinned artifacts in the artifact cache, or update the lockfile with 'hab-auto-build lock'"
+    })
+}
+
+/// Executes the build steps of a session, returns whether all the packages were
+/// successfully built.
+fn build_steps_execute(
+    run_context: &AutoBuildContext,
+    build_steps: &[BuildStep],
+    check_level: CheckLevel,
+    verify_reproducible: &[PackageDepGlob],
+    keep_going: bool,
+) -> Result<bool> {
+    let verify_reproducible = verify_reproducible
+        .iter()
+        .map(|glob| glob.matcher())
+        .collect::<Vec<_>>();
+    let step_dependencies = if keep_going {
+        run_context.build_step_dependencies(build_steps)
+    } else {
+        Vec::new()
+    };
+    let mut failures: Vec<(usize, BuildStepFailure)> = Vec::new();
+    for (position, step) in build_steps.iter().enumerate() {
+        if keep_going {
+            let failed_dependency = failures
+                .iter()
+                .map(|(failed_position, _)| *failed_position)
+                .find(|failed_position| step_dependencies[position].contains(failed_position));
+            if let Some(failed_dependency) = failed_dependency {
+                let dependency = build_steps[failed_dependency].plan_ctx.id.to_string();
+                info!(target: "user-ui", "{} [{}] {}: depends on failed package {}", "     Skipping".yellow().bold(), step.studio, step.plan_ctx.id, dependency);
+                failures.push((position, BuildStepFailure::Skipped { dependency }));
+                continue;
+            }
+        }
+        let started_at = Utc::now();
+        run_context.build_step_status_put(step, BuildStepStatus::Building)?;
+        let verify_step_reproducible = verify_reproducible
+            .iter()
+            .any(|matcher| matcher.matches_package_build_ident(step.plan_ctx.id.as_ref()));
+        let step_result = build_step_run(run_context, step, check_level, verify_step_reproducible);
+        run_context.build_step_status_put(
+            step,
+            if matches!(step_result, Ok(true)) {
+                BuildStepStatus::Built
+            } else {
+                BuildStepStatus::Failed
+            },
+        )?;
+        match step_result {
+            Ok(true) => {}
+            Ok(false) if keep_going => failures.push((
+                position,
+                BuildStepFailure::Failed {
+                    build_log: step_build_log(run_context, step, started_at),
+                },
+            )),
+            Err(err) if keep_going => {
+                info!(target: "user-ui", "{}: Failed to build package {}: {:#}", "error".bold().red(), step.plan_ctx.id.yellow(), err);
+                failures.push((position, BuildStepFailure::Errored(err)));
+            }
+            Ok(false) => return Ok(false),
+            Err(err) => return Err(err),
+        }
+    }
+    if failures.is_empty() {
+        return Ok(true);
+    }
+    output_build_failures(build_steps, &failures);
+    Ok(false)
+}
+
+/// Reason a build step was not built when building with '--keep-going'
+enum BuildStepFailure {
+    /// The build failed or the package did not pass its checks
+    Failed { build_log: Option<PathBuf> },
+    /// The build could not be run
+    Errored(Report),
+    /// The step depends on a package that was not built
+    Skipped { dependency: String },
+}
+
+/// Finds the build log written by a step since it started, there is none if the
+/// step failed before the package was built.
+fn step_build_log(
+    run_context: &AutoBuildContext,
+    step: &BuildStep,
+    started_at: DateTime<Utc>,
+) -> Option<PathBuf> {
+    let package = PackageDepIdent {
+        version: PackageVersion::Unresolved,
+        release: PackageRelease::Unresolved,
+        ..PackageDepIdent::from(step.plan_ctx.id.as_ref())
+    };
+    run_context
+        .build_logs(&package, step.plan_ctx.id.as_ref().target)
+        .ok()?
+        .into_iter()
+        .find(|build_log| build_log.modified_at >= started_at)
+        .map(|build_log| build_log.path)
+}
+
+fn output_build_failures(build_steps: &[BuildStep], failures: &[(usize, BuildStepFailure)]) {
+    let skipped = failures
+        .iter()
+        .filter(|(_, failure)| matches!(failure, BuildStepFailure::Skipped { .. }))
+        .count();
+    info!(target: "user-ui", "");
+    info!(target: "user-ui", "{}: {} packages failed to build, {} packages were skipped because they depend on them", "error".bold().red(), failures.len() - skipped, skipped);
+    for (position, failure) in failures {
+        let step = &build_steps[*position];
+        match failure {
+            BuildStepFailure::Failed {
+                build_log: Some(build_log),
+            } => {
+                info!(target: "user-ui", "{} [{}] {}: {}", "       Failed".red().bold(), step.studio, step.plan_ctx.id, build_log.display().blue());
+            }
+            BuildStepFailure::Failed { build_log: None } => {
+                info!(target: "user-ui", "{} [{}] {}: found issues with the package sources", "       Failed".red().bold(), step.studio, step.plan_ctx.id);
+            }
+            BuildStepFailure::Errored(err) => {
+                info!(target: "user-ui", "{} [{}] {}: {:#}", "       Failed".red().bold(), step.studio, step.plan_ctx.id, err);
+            }
+            BuildStepFailure::Skipped { dependency } => {
+                info!(target: "user-ui", "{} [{}] {}: depends on {}", "      Skipped".yellow().bold(), step.studio, step.plan_ctx.id, dependency);
+            }
+        }
+    }
+}
+
+/// Downloads the sources of a build step and builds it, returns whether the
+/// package was successfully built and passed its checks. When asked to verify
+/// that the package is reproducible, differences between builds are reported
+/// but do not fail the step.
+fn build_step_run(
+    run_context: &AutoBuildContext,
+    step: &BuildStep,
+    check_level: CheckLevel,
+    verify_reproducible: bool,
+) -> Result<bool> {
+    let mut all_checks_passed = true;
+    info!(target: "user-ui", "{} [{}] {}", "     Building".green().bold(), step.studio, step.plan_ctx.id);
+    match run_context.download_plan_source(step.plan_ctx, true)? {
+        DownloadStatus::Downloaded(_source_ctx, _, _, _, _, source_violations)
+        | DownloadStatus::AlreadyDownloaded(_source_ctx, _, _, _, source_violations) => {
+            let source_warnings = source_violations
+                .iter()
+                .filter(|v| v.level == ViolationLevel::Warn)
+                .count();
+            let source_errors = source_violations
+                .iter()
+                .filter(|v| v.level == ViolationLevel::Error)
+                .count();
+            match check_level {
+                CheckLevel::AllowWarnings if source_errors > 0 => all_checks_passed = false,
+                CheckLevel::Strict if source_errors + source_warnings > 0 => {
+                    all_checks_passed = false
                 }
+                _ => {}
+            };
+            output_violations(
+                if !all_checks_passed {
+                    Some(step.plan_ctx.plan_path.plan_config_path())
+                } else {
+                    None
+                },
+                &source_violations,
+                &[],
+                &step.plan_ctx.id.to_string(),
+                false,
+                false,
+            )?;
+            if !all_checks_passed {
+                info!(target: "user-ui", "{} [{}] {}", "Build Failure".red().bold(), step.studio, step.plan_ctx.id);
+                info!(target: "user-ui", "{}: Found issues with the package {}, you should fix the plan at {} before re-attempting the build.", "error".bold().red(), step.plan_ctx.id.yellow(), step.plan_ctx.plan_path.as_ref().display().blue());
+                return Ok(false);
             }
-            match run_context.build_step_execute(&step) {
-                Ok(build_result) => {
-                    output_violations(
-                        Some(step.plan_ctx.plan_path.plan_config_path()),
-                        &[],
-                        &build_result.artifact_violations,
-                        &step.plan_ctx.id.to_string(),
-                        false,
-                        false,
-                    )?;
-
-                    let artifact_warnings = build_result
-                        .artifact_violations
-                        .iter()
-                        .filter(|v| v.level == ViolationLevel::Warn)
-                        .count();
-                    let artifact_errors = build_result
-                        .artifact_violations
-                        .iter()
-                        .filter(|v| v.level == ViolationLevel::Error)
-                        .count();
-                    match args.check_level {
-                        CheckLevel::AllowWarnings if artifact_errors > 0 => {
-                            all_checks_passed = false
-                        }
-                        CheckLevel::Strict if artifact_errors + artifact_warnings > 0 => {
-                            all_checks_passed = false
-                        }
-                        _ => {}
-                    };
+        }
+        DownloadStatus::MissingSource(_) => {}
+        DownloadStatus::NoSource => {
+            unreachable!()
+        }
+        DownloadStatus::InvalidArchive(_, source, actual_shasum, _) => {
+            return Err(eyre!(
+                "Failed to download package source, package shasum mismatch. Expected shasum {}, found shasum {}", source.shasum, actual_shasum
+            ));
+        }
+    }
+    // The previous artifact must be found before the build replaces it as the latest one
+    let previous_artifact_path = if verify_reproducible {
+        run_context.latest_plan_artifact_path(step.plan_ctx)
+    } else {
+        None
+    };
+    match run_context.build_step_execute(step) {
+        Ok(build_result) => {
+            output_violations(
+                Some(step.plan_ctx.plan_path.plan_config_path()),
+                &[],
+                &build_result.artifact_violations,
+                &step.plan_ctx.id.to_string(),
+                false,
+                false,
+            )?;
 
-                    if !all_checks_passed {
-                        info!(target: "user-ui", "{} [{}] {}", "Build Failure".red().bold(), step.studio, build_result.artifact_ident.artifact_name());
-                        info!(target: "user-ui", "{}: Found issues with the package {}, you should fix the plan at {} before re-attempting the build. You can find the build log at {}", "error".bold().red(), step.plan_ctx.id.yellow(), step.plan_ctx.plan_path.as_ref().display().blue(), build_result.build_log.display().blue());
-                        return Ok(());
-                    } else {
-                        info!(target: "user-ui", "{} [{}] {}", "Build Success".green().bold(), step.studio, build_result.artifact_ident.artifact_name());
-                    }
+            let artifact_warnings = build_result
+                .artifact_violations
+                .iter()
+                .filter(|v| v.level == ViolationLevel::Warn)
+                .count();
+            let artifact_errors = build_result
+                .artifact_violations
+                .iter()
+                .filter(|v| v.level == ViolationLevel::Error)
+                .count();
+            match check_level {
+                CheckLevel::AllowWarnings if artifact_errors > 0 => all_checks_passed = false,
+                CheckLevel::Strict if artifact_errors + artifact_warnings > 0 => {
+                    all_checks_passed = false
                 }
+                _ => {}
+            };
+
+            if !all_checks_passed {
+                info!(target: "user-ui", "{} [{}] {}", "Build Failure".red().bold(), step.studio, build_result.artifact_ident.artifact_name());
+                info!(target: "user-ui", "{}: Found issues with the package {}, you should fix the plan at {} before re-attempting the build. You can find the build log at {}", "error".bold().red(), step.plan_ctx.id.yellow(), step.plan_ctx.plan_path.as_ref().display().blue(), build_result.build_log.display().blue());
+                return Ok(false);
+            } else {
+                info!(target: "user-ui", "{} [{}] {}", "Build Success".green().bold(), step.studio, build_result.artifact_path.display());
+            }
+            if verify_reproducible {
+                return reproducibility_verify(
+                    run_context,
+                    step,
+                    previous_artifact_path,
+                    &build_result.artifact_path,
+                );
+            }
+        }
+        Err(BuildStepError::Build(
+            BuildError::Native(_, build_log)
+            | BuildError::Bootstrap(_, build_log)
+            | BuildError::Standard(_, build_log),
+        )) => {
+            info!(target: "user-ui", "{} [{}] {}", "Build Failure".red().bold(), step.studio, step.plan_ctx.id);
+            info!(target: "user-ui", "{}: Failed to complete build of package {}, you should fix the plan at {} before re-attempting the build. You can find the build log at {}", "error".bold().red(), step.plan_ctx.id.yellow(), step.plan_ctx.plan_path.as_ref().display().blue(), build_log.display().blue());
+            output_build_failure_hints(step, &build_log);
+            return Ok(false);
+        }
+        Err(err) => return Err(err.into()),
+    }
+    Ok(true)
+}
+
+/// Prints the likely causes of a failed build recognized from its build log
+fn output_build_failure_hints(step: &BuildStep, build_log: &Path) {
+    match build_failure_analyze(step.plan_ctx, build_log) {
+        Ok(hints) => {
+            for hint in hints {
+                info!(target: "user-ui", "{} {}", "         Hint".cyan().bold(), hint.hint);
+                info!(target: "user-ui", "              {}", format!("{}:{}: {}", build_log.display(), hint.line_number, hint.line).dimmed());
+            }
+        }
+        Err(err) => {
+            info!(target: "user-ui", "{}: Failed to analyze the build log: {:#}", "warning".bold().yellow(), err);
+        }
+    }
+}
+
+/// Compares a build with the previous artifact of its plan, or with a second
+/// build when the plan was never built before, and reports the files that differ.
+/// Returns whether the second build succeeded, if one was needed.
+fn reproducibility_verify(
+    run_context: &AutoBuildContext,
+    step: &BuildStep,
+    previous_artifact_path: Option<PathBuf>,
+    artifact_path: &Path,
+) -> Result<bool> {
+    let (source_path, target_path) = match previous_artifact_path {
+        Some(previous_artifact_path) => (previous_artifact_path, artifact_path.to_path_buf()),
+        None => {
+            info!(target: "user-ui", "{} [{}] {}", "   Rebuilding".green().bold(), step.studio, step.plan_ctx.id);
+            match run_context.build_step_execute(step) {
+                Ok(build_result) => (artifact_path.to_path_buf(), build_result.artifact_path),
                 Err(BuildStepError::Build(
                     BuildError::Native(_, build_log)
                     | BuildError::Bootstrap(_, build_log)
                     | BuildError::Standard(_, build_log),
                 )) => {
                     info!(target: "user-ui", "{} [{}] {}", "Build Failure".red().bold(), step.studio, step.plan_ctx.id);
-                    info!(target: "user-ui", "{}: Failed to complete build of package {}, you should fix the plan at {} before re-attempting the build. You can find the build log at {}", "error".bold().red(), step.plan_ctx.id.yellow(), step.plan_ctx.plan_path.as_ref().display().blue(), build_log.display().blue());
-                    return Ok(());
+                    info!(target: "user-ui", "{}: Failed to rebuild package {} to verify that it is reproducible. You can find the build log at {}", "error".bold().red(), step.plan_ctx.id.yellow(), build_log.display().blue());
+                    output_build_failure_hints(step, &build_log);
+                    return Ok(false);
                 }
                 Err(err) => return Err(err.into()),
             }
         }
+    };
+    let diff = artifact_content_diff(&source_path, &target_path)?;
+    if diff.is_empty() {
+        info!(target: "user-ui", "{} [{}] {}", " Reproducible".green().bold(), step.studio, step.plan_ctx.id);
+        return Ok(true);
+    }
+    info!(target: "user-ui", "{} [{}] {}", "Not Reproducible".yellow().bold(), step.studio, step.plan_ctx.id);
+    info!(target: "user-ui", "{}: Found {} files that differ between {} and {}", "warning".bold().yellow(), diff.added_files.len() + diff.removed_files.len() + diff.changed_files.len(), source_path.display().blue(), target_path.display().blue());
+    for file in diff.changed_files.iter() {
+        info!(target: "user-ui", "  {}", format!("~ {}", file.display()).yellow());
+    }
+    for file in diff.added_files.iter() {
+        info!(target: "user-ui", "  {}", format!("+ {}", file.display()).green());
+    }
+    for file in diff.removed_files.iter() {
+        info!(target: "user-ui", "  {}", format!("- {}", file.display()).red());
+    }
+    Ok(true)
+}
+
+struct CooperativeWorker {
+    build_id: String,
+    worker_id: String,
+    lease_timeout: Duration,
+    format: OutputFormat,
+}
+
+impl CooperativeWorker {
+    fn output_event(&self, event: &str, step: Option<&BuildStep>, detail: Option<String>) {
+        match self.format {
+            OutputFormat::Json => {
+                let event = BuildEventOutput {
+                    event: event.to_string(),
+                    build_id: self.build_id.clone(),
+                    worker: self.worker_id.clone(),
+                    plan: step.map(|step| step.plan_ctx.id.to_string()),
+                    detail,
+                };
+                if let Err(err) = output::output_json_line(JsonOutputKind::BuildEvent, event) {
+                    error!(target: "user-log", "Failed to output build event: {:#}", err);
+                }
+            }
+            _ => {
+                info!(target: "user-ui", "{} [{}] {}{}",
+                    format!("{:>13}", event).blue().bold(),
+                    self.worker_id,
+                    step.map(|step| step.plan_ctx.id.to_string()).unwrap_or_else(|| self.build_id.clone()),
+                    detail.map(|detail| format!(": {}", detail)).unwrap_or_default()
+                );
+            }
+        }
+    }
+}
+
+/// Executes the build steps of a session in cooperation with other workers
+/// sharing the same store. Steps are claimed one at a time once all the steps
+/// they depend on are completed, returns whether all the packages were
+/// successfully built.
+fn cooperative_build_steps_execute(
+    run_context: &AutoBuildContext,
+    build_steps: &[BuildStep],
+    check_level: CheckLevel,
+    worker: &CooperativeWorker,
+) -> Result<bool> {
+    let step_dependencies = run_context.build_step_dependencies(build_steps);
+    let step_key = |step: &BuildStep| step.plan_ctx.id.to_string();
+    worker.output_event("Joined", None, Some(format!("{} steps", build_steps.len())));
+    let failed_claims = run_context.build_claims_join(&worker.build_id)?;
+    if failed_claims > 0 {
+        worker.output_event(
+            "Retrying",
+            None,
+            Some(format!("{} steps that failed previously", failed_claims)),
+        );
+    }
+    let mut is_waiting = false;
+    loop {
+        let claims: HashMap<String, BuildClaim> = run_context.build_claims(&worker.build_id)?;
+        let now = chrono::Utc::now();
+        if let Some(failed_claim) = claims
+            .values()
+            .find(|claim| claim.status == BuildClaimStatus::Failed)
+        {
+            worker.output_event(
+                "Aborted",
+                None,
+                Some(format!(
+                    "{} failed to build on worker {}",
+                    failed_claim.plan, failed_claim.worker
+                )),
+            );
+            return Ok(false);
+        }
+        let is_completed = |position: usize| {
+            matches!(
+                claims
+                    .get(&step_key(&build_steps[position]))
+                    .map(|claim| &claim.status),
+                Some(BuildClaimStatus::Completed(_))
+            )
+        };
+        let pending_steps = (0..build_steps.len())
+            .filter(|position| !is_completed(*position))
+            .collect::<Vec<_>>();
+        if pending_steps.is_empty() {
+            worker.output_event("Done", None, None);
+            return Ok(true);
+        }
+        let mut claimed_step = None;
+        for position in pending_steps {
+            let step = &build_steps[position];
+            if claims
+                .get(&step_key(step))
+                .is_some_and(|claim| !claim.is_expired(now))
+            {
+                continue;
+            }
+            if !step_dependencies[position]
+                .iter()
+                .all(|dependency| is_completed(*dependency))
+            {
+                continue;
+            }
+            if run_context.build_step_claim(
+                &worker.build_id,
+                &worker.worker_id,
+                step,
+                worker.lease_timeout,
+            )? {
+                claimed_step = Some(step);
+                break;
+            }
+        }
+        let Some(step) = claimed_step else {
+            if !is_waiting {
+                worker.output_event(
+                    "Waiting",
+                    None,
+                    Some("for steps claimed by other workers".to_string()),
+                );
+                is_waiting = true;
+            }
+            std::thread::sleep(std::time::Duration::from_secs(
+                COOPERATIVE_POLL_INTERVAL_SECS,
+            ));
+            continue;
+        };
+        is_waiting = false;
+        worker.output_event("Claimed", Some(step), None);
+
+        // Make the artifacts of dependencies built by other workers available locally
+        let dependency_artifacts = claims
+            .values()
+            .filter_map(|claim| match &claim.status {
+                BuildClaimStatus::Completed(artifact_ident) => Some(artifact_ident.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for artifact_ident in run_context.artifacts_import(&dependency_artifacts)? {
+            worker.output_event("Imported", Some(step), Some(artifact_ident.to_string()));
+        }
+
+        // Keep renewing the lease on the step while it is being built, the build
+        // is stopped if another worker takes over the claim
+        step.cancellation.reset();
+        let step_succeeded = std::thread::scope(|scope| {
+            let (sender, receiver) = channel::<()>();
+            let lease_renewal_interval = (worker.lease_timeout / 3)
+                .to_std()
+                .unwrap_or(std::time::Duration::from_secs(1));
+            scope.spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) =
+                    receiver.recv_timeout(lease_renewal_interval)
+                {
+                    match run_context.build_step_claim_renew(
+                        &worker.build_id,
+                        &worker.worker_id,
+                        step,
+                        worker.lease_timeout,
+                    ) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            info!(target: "user-ui", "{}: The claim on {} was taken over by another worker, stopping its build", "warning".bold().yellow(), step.plan_ctx.id);
+                            step.cancellation.cancel();
+                            break;
+                        }
+                        Err(err) => {
+                            info!(target: "user-ui", "{}: Failed to renew the claim on {}: {:#}", "warning".bold().yellow(), step.plan_ctx.id, err);
+                        }
+                    }
+                }
+            });
+            let result = build_steps_execute(
+                run_context,
+                std::slice::from_ref(step),
+                check_level,
+                &[],
+                false,
+            );
+            drop(sender);
+            result
+        });
+        if step.cancellation.is_cancelled() {
+            worker.output_event(
+                "Lost",
+                Some(step),
+                Some("claim taken over by another worker".to_string()),
+            );
+            continue;
+        }
+        let step_succeeded = match step_succeeded {
+            Ok(step_succeeded) => step_succeeded,
+            Err(err) => {
+                run_context.build_step_claim_release(
+                    &worker.build_id,
+                    &worker.worker_id,
+                    step,
+                    &BuildClaimStatus::Failed,
+                )?;
+                worker.output_event("Failed", Some(step), Some(format!("{:#}", err)));
+                return Err(err);
+            }
+        };
+        if step_succeeded {
+            let artifact_ident = run_context
+                .package_artifact(step.index)?
+                .ok_or(eyre!(
+                    "No artifact found for {} after building it",
+                    step.plan_ctx.id
+                ))?
+                .id
+                .clone();
+            run_context.build_step_claim_release(
+                &worker.build_id,
+                &worker.worker_id,
+                step,
+                &BuildClaimStatus::Completed(artifact_ident.clone()),
+            )?;
+            worker.output_event("Completed", Some(step), Some(artifact_ident.to_string()));
+        } else {
+            run_context.build_step_claim_release(
+                &worker.build_id,
+                &worker.worker_id,
+                step,
+                &BuildClaimStatus::Failed,
+            )?;
+            worker.output_event("Failed", Some(step), None);
+            return Ok(false);
+        }
+    }
+}
+
+fn output_skip_step(step: &SkipStep) {
+    let SkipStep { plan_ctx, skip } = step;
+    if &plan_ctx.id == skip.broken_plan {
+        info!(target: "user-ui", "{} [plan] {}: {} (owner: {}, expires on {})", "     Skipping".yellow().bold(), plan_ctx.id, skip.config.reason, skip.config.owner, skip.config.expires_on);
+    } else {
+        info!(target: "user-ui", "{} [plan] {}: depends on broken plan {}: {} (owner: {}, expires on {})", "     Skipping".yellow().bold(), plan_ctx.id, skip.broken_plan, skip.config.reason, skip.config.owner, skip.config.expires_on);
     }
-    Ok(())
 }
 
 fn output_plain(build_plan: BuildPlan) -> Result<()> {
+    for step in build_plan.skip_steps.iter() {
+        output_skip_step(step);
+    }
     if build_plan.build_steps.is_empty() {
         info!(target: "user-log", "{}", "All plans built");
         return Ok(());
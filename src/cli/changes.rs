@@ -1,16 +1,17 @@
-use std::{env, path::PathBuf};
+use std::{collections::BTreeSet, env, fmt::Write, path::PathBuf};
 
 use chrono_humanize::{Accuracy, HumanTime};
-use clap::{arg, Args};
+use clap::Args;
 use color_eyre::eyre::{eyre, Context, Result};
 use owo_colors::OwoColorize;
+use petgraph::stable_graph::NodeIndex;
 use tracing::{error, info};
 
 use crate::{
     cli::output::OutputFormat,
     core::{
-        AutoBuildConfig, AutoBuildContext, BuildOrder, ChangeDetectionMode, DependencyChangeCause,
-        PackageDepGlob, PackageTarget, RepoChanges,
+        AutoBuildConfig, AutoBuildContext, BuildOrder, ChangeDetectionMode, DependencyChange,
+        DependencyChangeCause, PackageBuildVersion, PackageDepGlob, PackageTarget, RepoChanges,
     },
 };
 
@@ -31,6 +32,15 @@ pub(crate) struct Params {
     /// Display reasons for changes
     #[arg(short = 'e', long, default_value_t = false)]
     explain: bool,
+    /// Flag plans whose latest artifact was built from a plan source that differs from the repo, this requires reading every artifact
+    #[arg(long, default_value_t = false)]
+    plan_source: bool,
+    /// List the plans whose files differ from the branch of the git remote their repo is tracking, including local commits and commits missing locally
+    #[arg(long, default_value_t = false)]
+    upstream: bool,
+    /// Output the changed plans as a markdown changelog fragment grouped by repo, for use in release notes
+    #[arg(long, default_value_t = false, conflicts_with_all = ["format", "explain", "plan_source", "upstream"])]
+    changelog: bool,
     /// List of packages to check for changes
     packages: Option<Vec<PackageDepGlob>>,
 }
@@ -45,6 +55,7 @@ pub(crate) fn execute(args: Params) -> Result<()> {
 
     let run_context = AutoBuildContext::new(&config, &config_path, args.change_detection_mode)
         .with_context(|| eyre!("Failed to initialize run"))?;
+    run_context.broken_plans_verify()?;
 
     let packages = &args
         .packages
@@ -65,9 +76,20 @@ pub(crate) fn execute(args: Params) -> Result<()> {
         PackageTarget::default(),
     );
 
+    if args.changelog {
+        return output_changelog(&changes);
+    }
     match args.format {
-        OutputFormat::Plain => output_plain(changes, args.explain)?,
-        OutputFormat::Json => todo!(),
+        OutputFormat::Plain => {
+            output_plain(changes, args.explain)?;
+            if args.plan_source {
+                output_plan_source_divergences(&run_context, &package_indices)?;
+            }
+            if args.upstream {
+                output_upstream_changes(&run_context, &package_indices)?;
+            }
+        }
+        OutputFormat::Json => return Err(eyre!("JSON output is not supported for changes")),
     }
     Ok(())
 }
@@ -93,6 +115,13 @@ fn output_plain(repo_statuses: Vec<RepoChanges<'_>>, explain: bool) -> Result<()
                         .bold(),
                     change.plan_ctx.plan_path.as_ref().display()
                 );
+                if let Some(skip) = change.skipped {
+                    if &change.plan_ctx.id == skip.broken_plan {
+                        info!(target: "user-ui", "    {} {} (owner: {}, expires on {})", "Skipped:".yellow().bold(), skip.config.reason, skip.config.owner, skip.config.expires_on);
+                    } else {
+                        info!(target: "user-ui", "    {} depends on broken plan {}: {} (owner: {}, expires on {})", "Skipped:".yellow().bold(), skip.broken_plan.magenta(), skip.config.reason, skip.config.owner, skip.config.expires_on);
+                    }
+                }
                 if explain {
                     if let Some(latest_artifact) = change.plan_ctx.latest_artifact.as_ref() {
                         info!( target: "user-ui",
@@ -217,3 +246,192 @@ fn output_plain(repo_statuses: Vec<RepoChanges<'_>>, explain: bool) -> Result<()
 
     Ok(())
 }
+
+/// Outputs a markdown changelog fragment with a section for each repo with
+/// changes, listing the version change and the causes of each changed plan.
+fn output_changelog(repo_statuses: &[RepoChanges<'_>]) -> Result<()> {
+    let mut output = String::new();
+    for repo_status in repo_statuses {
+        if repo_status.changes.is_empty() {
+            continue;
+        }
+        writeln!(output, "## {}\n", repo_status.repo.id)?;
+        let mut changes = repo_status.changes.iter().collect::<Vec<_>>();
+        changes.sort_by_key(|change| change.plan_ctx.id.to_string());
+        for change in changes {
+            writeln!(
+                output,
+                "- **{}/{}** {}: {}",
+                change.plan_ctx.id.as_ref().origin,
+                change.plan_ctx.id.as_ref().name,
+                changelog_version_change(change),
+                changelog_causes(change).join("; ")
+            )?;
+        }
+        writeln!(output)?;
+    }
+    if output.is_empty() {
+        info!(target: "user-log", "No changes detected in any repo");
+        return Ok(());
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
+
+/// Describes the version of the latest artifact of a changed plan and the version
+/// it will be rebuilt with
+fn changelog_version_change(change: &DependencyChange<'_>) -> String {
+    let new_version = match &change.plan_ctx.id.as_ref().version {
+        PackageBuildVersion::Static(version) => version.to_string(),
+        PackageBuildVersion::Dynamic => "dynamic version".to_string(),
+    };
+    match change.plan_ctx.latest_artifact.as_ref() {
+        Some(latest_artifact) if latest_artifact.ident.version.to_string() == new_version => {
+            new_version
+        }
+        Some(latest_artifact) => format!("{} → {}", latest_artifact.ident.version, new_version),
+        None => format!("new → {}", new_version),
+    }
+}
+
+/// Summarizes each cause of a plan change in a few words
+fn changelog_causes(change: &DependencyChange<'_>) -> Vec<String> {
+    let mut causes = Vec::new();
+    if let Some(skip) = change.skipped {
+        causes.push(format!("build skipped, {}", skip.config.reason));
+    }
+    for cause in change.causes.iter() {
+        causes.push(match cause {
+            DependencyChangeCause::PlanContextChanged {
+                files_changed_on_disk,
+                files_changed_on_git,
+                ..
+            } => {
+                let files = files_changed_on_disk
+                    .iter()
+                    .map(|file| file.path.as_ref())
+                    .chain(files_changed_on_git.iter().map(|file| file.path.as_ref()))
+                    .map(|path| {
+                        path.strip_prefix(change.plan_ctx.context_path.as_ref())
+                            .unwrap_or(path)
+                            .display()
+                            .to_string()
+                    })
+                    .collect::<BTreeSet<_>>();
+                format!(
+                    "plan files changed ({})",
+                    files.into_iter().collect::<Vec<_>>().join(", ")
+                )
+            }
+            DependencyChangeCause::DependencyArtifactsUpdated {
+                updated_dep_artifacts,
+                ..
+            } => format!(
+                "dependencies rebuilt ({})",
+                updated_dep_artifacts
+                    .iter()
+                    .map(|artifact| artifact.ident.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            DependencyChangeCause::DependencyPlansNeedRebuild { plans } => format!(
+                "dependencies will be rebuilt ({})",
+                plans
+                    .iter()
+                    .map(|(_, plan_ctx_id, _)| {
+                        format!(
+                            "{}/{}",
+                            plan_ctx_id.as_ref().origin,
+                            plan_ctx_id.as_ref().name
+                        )
+                    })
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            DependencyChangeCause::DependencyStudioNeedRebuild { plan } => format!(
+                "studio {}/{} will be rebuilt",
+                plan.as_ref().origin,
+                plan.as_ref().name
+            ),
+            DependencyChangeCause::NoBuiltArtifact => "new plan".to_string(),
+        });
+    }
+    causes
+}
+
+fn output_plan_source_divergences(
+    run_context: &AutoBuildContext,
+    package_indices: &[NodeIndex],
+) -> Result<()> {
+    let divergences = run_context.plan_source_divergences(package_indices)?;
+    if divergences.is_empty() {
+        info!(target: "user-log", "All artifacts were built from the current plan sources");
+        return Ok(());
+    }
+    info!(target: "user-ui",
+        "{} {} plans have artifacts built from a different plan source",
+        "Diverged:".yellow().bold(),
+        divergences.len().magenta()
+    );
+    for (plan_ctx, artifact_ident) in divergences {
+        info!(target: "user-ui",
+            "  {} {} (latest artifact {})",
+            format!("{}:", plan_ctx.id).green().bold(),
+            plan_ctx.plan_path.as_ref().display(),
+            artifact_ident.magenta()
+        );
+    }
+    Ok(())
+}
+
+fn output_upstream_changes(
+    run_context: &AutoBuildContext,
+    package_indices: &[NodeIndex],
+) -> Result<()> {
+    let repo_changes = run_context.upstream_changes(package_indices)?;
+    if repo_changes.is_empty() {
+        info!(target: "user-log", "No repos are configured with a git remote");
+        return Ok(());
+    }
+    for repo_changes in repo_changes {
+        let Some(git) = repo_changes.repo.git.as_ref() else {
+            continue;
+        };
+        if repo_changes.plans.is_empty() && repo_changes.other_files.is_empty() {
+            info!(target: "user-ui",
+                "{} No differences with branch '{}' of {}",
+                format!("{}:", repo_changes.repo.id).cyan().bold(),
+                git.branch,
+                git.url
+            );
+            continue;
+        }
+        info!(target: "user-ui",
+            "{} {} plans differ from branch '{}' of {}",
+            format!("{}:", repo_changes.repo.id).cyan().bold(),
+            repo_changes.plans.len().magenta(),
+            git.branch,
+            git.url
+        );
+        for (plan_ctx, files) in repo_changes.plans {
+            info!(target: "user-ui",
+                "  {} {}",
+                format!("{}:", plan_ctx.id.as_ref()).green().bold(),
+                plan_ctx.plan_path.as_ref().display()
+            );
+            for file in files {
+                info!(target: "user-ui", "    - {}", file.display());
+            }
+        }
+        if !repo_changes.other_files.is_empty() {
+            info!(target: "user-ui",
+                "  {} {} files outside of plans",
+                "Other:".yellow().bold(),
+                repo_changes.other_files.len().magenta()
+            );
+        }
+    }
+    Ok(())
+}
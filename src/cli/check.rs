@@ -5,11 +5,14 @@ use std::{env, fmt::Write, path::PathBuf, time::Instant};
 use tracing::{error, info};
 
 use crate::{
-    check::{LeveledArtifactCheckViolation, LeveledSourceCheckViolation, ViolationLevel},
-    cli::output::OutputFormat,
+    check::{
+        line_diff, LeveledArtifactCheckViolation, LeveledSourceCheckViolation, PlanFix,
+        ViolationLevel,
+    },
+    cli::annotate::{self, Annotation, AnnotationFormat},
     core::{
-        AutoBuildConfig, AutoBuildContext, BuildPlan, ChangeDetectionMode, PackageDepGlob,
-        PackageTarget, PlanCheckStatus,
+        AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepGlob, PackageTarget,
+        PlanCheckStatus, PlanContext,
     },
 };
 
@@ -18,12 +21,27 @@ pub(crate) struct Params {
     /// Path to hab auto build configuration
     #[arg(short, long)]
     config_path: Option<PathBuf>,
-    /// Output format
-    #[arg(value_enum, short = 'f', long, default_value_t = OutputFormat::Plain, requires = "dry_run")]
-    format: OutputFormat,
     /// Only diplay the number of issues with each package
     #[arg(short, long)]
     summary: bool,
+    /// Check plans before building them, predicting the artifact violations caused by the plan's current dependencies from its latest artifact
+    #[arg(long)]
+    pre_build: bool,
+    /// Rule profile changing the levels of the rules, such as 'strict', 'default', 'permissive' or a profile from the configuration
+    #[arg(long)]
+    profile: Option<String>,
+    /// Fix the violations that can be fixed mechanically by rewriting the plan files, and print the changes for review
+    #[arg(long)]
+    fix: bool,
+    /// Annotate the violations for a CI system, so that they are shown inline in pull and merge requests
+    #[arg(value_enum, long)]
+    annotate: Option<AnnotationFormat>,
+    /// File to write the GitLab Code Quality report to, defaults to 'gl-code-quality-report.json' in the current directory
+    #[arg(long)]
+    annotate_output: Option<PathBuf>,
+    /// Package target of the artifacts to check, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
     /// List of packages to check
     packages: Vec<PackageDepGlob>,
 }
@@ -35,11 +53,22 @@ pub(crate) fn execute(args: Params) -> Result<()> {
             .join("hab-auto-build.json"),
     );
     let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
 
     let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
         .with_context(|| eyre!("Failed to initialize run"))?;
 
-    let package_indices = run_context.glob_deps(&args.packages, PackageTarget::default())?;
+    let rule_profile = args
+        .profile
+        .as_deref()
+        .map(|profile| config.rule_profile(profile))
+        .transpose()?;
+
+    let mut package_indices = run_context.glob_deps(&args.packages, target)?;
+    if args.pre_build {
+        package_indices
+            .retain(|package_index| run_context.dep(*package_index).plan_ctx().is_some());
+    }
     if package_indices.is_empty() && !run_context.is_empty() && !args.packages.is_empty() {
         error!(target: "user-log",
             "No packages found matching patterns: {}",
@@ -47,10 +76,16 @@ pub(crate) fn execute(args: Params) -> Result<()> {
         );
         return Ok(());
     }
+    let mut annotations = Vec::new();
     let start = Instant::now();
     for package_index in package_indices.iter() {
         let package = run_context.dep(*package_index);
-        match run_context.package_check(*package_index) {
+        let check_status = if args.pre_build {
+            run_context.package_pre_build_check(*package_index, rule_profile.as_ref())
+        } else {
+            run_context.package_check(*package_index, rule_profile.as_ref())
+        };
+        match check_status {
             Ok(check_status) => match check_status {
                 PlanCheckStatus::CheckSucceeded(
                     plan_config_path,
@@ -65,6 +100,19 @@ pub(crate) fn execute(args: Params) -> Result<()> {
                         true,
                         args.summary,
                     )?;
+                    if args.annotate.is_some() {
+                        annotations.extend(Annotation::from_violations(
+                            &format!("{:?}", package),
+                            package.plan_ctx(),
+                            &source_violations,
+                            &artifact_violations,
+                        ));
+                    }
+                    if args.fix {
+                        if let Some(plan_ctx) = package.plan_ctx() {
+                            plan_fix(plan_ctx, &source_violations, &artifact_violations)?;
+                        }
+                    }
                 }
                 PlanCheckStatus::ArtifactNotFound => {
                     info!(target: "user-ui", "{}: {:?}: No artifact found","warning".bold().yellow(), package.red())
@@ -76,9 +124,76 @@ pub(crate) fn execute(args: Params) -> Result<()> {
         };
     }
     info!(target: "user-log", "Checked {} packages in {}s", package_indices.len().blue(), start.elapsed().as_secs_f32().blue());
+    output_license_scan_summary(&run_context);
+    match args.annotate {
+        Some(AnnotationFormat::Github) => annotate::output_github(&annotations),
+        Some(AnnotationFormat::Gitlab) => annotate::gitlab_report_write(
+            &annotations,
+            &args
+                .annotate_output
+                .unwrap_or_else(|| PathBuf::from(annotate::GITLAB_REPORT_DEFAULT_PATH)),
+        )?,
+        None => {}
+    }
     Ok(())
 }
 
+/// Applies the mechanical fixes for the violations to the plan file and prints
+/// the changes, along with the fixes that must be made by hand.
+fn plan_fix(
+    plan_ctx: &PlanContext,
+    source_violations: &[LeveledSourceCheckViolation],
+    artifact_violations: &[LeveledArtifactCheckViolation],
+) -> Result<()> {
+    let fixes = PlanFix::from_violations(source_violations, artifact_violations);
+    if fixes.is_empty() {
+        return Ok(());
+    }
+    let plan_path = plan_ctx.plan_path.as_ref();
+    let plan_source = std::fs::read_to_string(plan_path)
+        .with_context(|| eyre!("Failed to read plan file '{}'", plan_path.display()))?;
+    let mut fixed_plan_source = plan_source.clone();
+    for fix in fixes.iter() {
+        if let Some(source) = fix.apply(&fixed_plan_source) {
+            fixed_plan_source = source;
+        } else {
+            info!(target: "user-ui", "       {}: {}", "fix manually".bold().yellow(), fix);
+        }
+    }
+    if fixed_plan_source == plan_source {
+        return Ok(());
+    }
+    std::fs::write(plan_path, &fixed_plan_source)
+        .with_context(|| eyre!("Failed to write plan file '{}'", plan_path.display()))?;
+    info!(target: "user-ui", "       {} {}:", "Fixed".bold().green(), plan_path.display().blue());
+    for line in line_diff(&plan_source, &fixed_plan_source).lines() {
+        info!(target: "user-ui", "         {}", line);
+    }
+    Ok(())
+}
+
+/// Number of slowest license scans listed in the license scan summary
+const SLOWEST_LICENSE_SCAN_COUNT: usize = 3;
+
+pub(crate) fn output_license_scan_summary(run_context: &AutoBuildContext) {
+    let mut license_scans = run_context.license_scans();
+    if license_scans.is_empty() {
+        return;
+    }
+    license_scans.sort_by_key(|license_scan| std::cmp::Reverse(license_scan.duration));
+    let total_duration = license_scans
+        .iter()
+        .map(|license_scan| license_scan.duration)
+        .sum::<std::time::Duration>();
+    info!(target: "user-log", "Scanned {} source archives for licenses in {}s", license_scans.len().blue(), total_duration.as_secs_f32().blue());
+    if license_scans.len() > 1 {
+        info!(target: "user-ui", "Slowest license scans:");
+        for license_scan in license_scans.iter().take(SLOWEST_LICENSE_SCAN_COUNT) {
+            info!(target: "user-ui", "  {}: {} license files in {:.3}s", license_scan.plan_ctx_id, license_scan.license_count, license_scan.duration.as_secs_f32());
+        }
+    }
+}
+
 pub(crate) fn output_violations(
     plan_config_path: Option<PathBuf>,
     source_violations: &[LeveledSourceCheckViolation],
@@ -103,6 +218,12 @@ pub(crate) fn output_violations(
         .iter()
         .filter(|v| v.level == ViolationLevel::Warn)
         .count();
+    let suggestion_count = source_violations
+        .iter()
+        .map(|v| v.level)
+        .chain(artifact_violations.iter().map(|v| v.level))
+        .filter(|level| *level == ViolationLevel::Suggest)
+        .count();
     if header {
         let mut header = String::new();
         write!(header, "{}:", package.white())?;
@@ -124,6 +245,13 @@ pub(crate) fn output_violations(
                     .bold()
             )?;
         }
+        if suggestion_count != 0 {
+            write!(
+                &mut header,
+                " {}",
+                format!("{} suggestions", suggestion_count).cyan().bold()
+            )?;
+        }
         if artifact_error_count + artifact_warning_count + source_error_count + source_warning_count
             != 0
         {
@@ -160,13 +288,3 @@ pub(crate) fn output_violations(
     }
     Ok(())
 }
-
-#[allow(dead_code)]
-fn output_plain(_dry_run: BuildPlan) -> Result<()> {
-    todo!()
-}
-
-#[allow(dead_code)]
-fn output_json(_dry_run: BuildPlan) -> Result<()> {
-    todo!()
-}
@@ -0,0 +1,143 @@
+use std::{env, path::PathBuf};
+
+use chrono::Duration;
+use chrono_humanize::HumanTime;
+use clap::Args;
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::{
+    core::{AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, StoreCleanOptions},
+    store::StoreLock,
+};
+use color_eyre::eyre::{eyre, Context, Result};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Remove downloaded and invalid source archives
+    #[arg(long)]
+    sources: bool,
+    /// Remove the bare clones of the repositories of git sources
+    #[arg(long)]
+    git_repos: bool,
+    /// Remove build success and failure logs
+    #[arg(long)]
+    logs: bool,
+    /// Remove temporary files and folders
+    #[arg(long)]
+    tmp: bool,
+    /// Remove build studios left over from previous sessions, requires sudo
+    #[arg(long)]
+    studios: bool,
+    /// Only remove entries last modified before the given duration (eg: 12h, 30d, 4w)
+    #[arg(long, value_parser = parse_duration)]
+    older_than: Option<Duration>,
+    /// Only remove entries that are no longer referenced by any plan or artifact
+    #[arg(short, long)]
+    unreferenced: bool,
+    /// Do a dry run of the clean and output the entries that would be removed
+    #[arg(short = 'd', long)]
+    dry_run: bool,
+    /// Wait for builds using the same store to finish instead of failing
+    #[arg(long)]
+    wait: bool,
+}
+
+fn parse_duration(value: &str) -> Result<Duration> {
+    let unit_index = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| eyre!("Duration '{}' is missing a unit (s, m, h, d, w)", value))?;
+    let (amount, unit) = value.split_at(unit_index);
+    let amount = amount
+        .parse::<i64>()
+        .with_context(|| format!("Invalid duration '{}'", value))?;
+    match unit {
+        "s" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        _ => Err(eyre!(
+            "Invalid duration unit '{}', expected one of s, m, h, d, w",
+            unit
+        )),
+    }
+}
+
+pub(super) fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    // Temporary files and studios of a running build must not be removed under it
+    let _store_lock = if args.dry_run {
+        None
+    } else {
+        Some(StoreLock::acquire(
+            config.store_path(&config_path)?,
+            "clean",
+            args.wait,
+        )?)
+    };
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    // If no specific category is selected we clean all of them, studios
+    // live outside the store and must be explicitly requested
+    let clean_all = !args.sources && !args.git_repos && !args.logs && !args.tmp && !args.studios;
+    let options = StoreCleanOptions {
+        sources: args.sources || clean_all,
+        git_repos: args.git_repos || clean_all,
+        logs: args.logs || clean_all,
+        tmp: args.tmp || clean_all,
+        studios: args.studios,
+        older_than: args.older_than,
+        unreferenced: args.unreferenced,
+    };
+
+    run_context
+        .get_connection()?
+        .exclusive_transaction(|connection| {
+            match run_context.store_clean(connection, &options, args.dry_run) {
+                Ok(statuses) => {
+                    let mut total_size = 0;
+                    for status in statuses.iter() {
+                        total_size += status.size;
+                        info!(target: "user-ui", "{} {}: {} ({}, last modified {})",
+                            if args.dry_run { "Would remove" } else { "Removed" },
+                            status.category,
+                            status.path.display().white(),
+                            format_size(status.size).yellow(),
+                            HumanTime::from(status.modified_at)
+                        );
+                    }
+                    if statuses.is_empty() {
+                        info!(target: "user-log", "Nothing to clean in the hab-auto-build store");
+                    } else if args.dry_run {
+                        info!(target: "user-log", "{} entries totalling {} would be removed from the hab-auto-build store", statuses.len(), format_size(total_size));
+                    } else {
+                        info!(target: "user-log", "Removed {} entries totalling {} from the hab-auto-build store", statuses.len(), format_size(total_size));
+                    }
+                }
+                Err(err) => return Err(eyre!(err)),
+            }
+            Ok(())
+        })
+}
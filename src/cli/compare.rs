@@ -1,34 +1,94 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeSet, HashMap},
+    env,
+    fmt::Write,
+    path::PathBuf,
+};
 
-use clap::{arg, Args};
-use color_eyre::eyre::{eyre, Context, Result};
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
 use owo_colors::OwoColorize;
 use tracing::info;
 
 use crate::{
-    cli::output::OutputFormat,
+    cli::output::{
+        output_json, GraphDiffEdgeOutput, GraphDiffOutput, GraphDiffPackageOutput,
+        GraphDiffVersionChangeOutput, JsonOutputKind, PlanDiffOutput, ReportFormat,
+    },
     core::{
-        AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDiff, PackageName,
-        PackageOrigin, PackageTarget,
+        artifact_diff, ArtifactCachePath, ArtifactDiff, AutoBuildConfig, AutoBuildContext,
+        AutoBuildContextPath, ChangeDetectionMode, GraphDiff, GraphEdge, GraphPackage,
+        PackageBuildVersion, PackageDepIdent, PackageDiff, PackageName, PackageOrigin,
+        PackageTarget, RepoWorktree,
     },
+    store::Store,
 };
 
+use super::{clean::format_size, yank::artifact_ident};
+
 #[derive(Debug, Args)]
 pub(crate) struct Params {
     /// Path to hab auto build configuration for source repos
-    #[arg(short = 's', long)]
-    source_config_path: PathBuf,
+    #[arg(short = 's', long, required_unless_present_any = ["artifacts", "from"])]
+    source_config_path: Option<PathBuf>,
     /// Path to hab auto build configuration for target repos
-    #[arg(short = 't', long)]
-    target_config_path: PathBuf,
+    #[arg(short = 't', long, required_unless_present_any = ["artifacts", "from"])]
+    target_config_path: Option<PathBuf>,
+    /// Git ref of the repo whose dependency graph is compared with the one of '--to'
+    #[arg(
+        long,
+        requires = "to",
+        conflicts_with_all = ["source_config_path", "target_config_path", "artifacts"]
+    )]
+    from: Option<String>,
+    /// Git ref of the repo whose dependency graph is compared with the one of '--from'
+    #[arg(long, requires = "from")]
+    to: Option<String>,
+    /// Path to hab auto build configuration, used to compare git refs
+    #[arg(short, long, requires = "from")]
+    config_path: Option<PathBuf>,
+    /// Repo whose git refs are compared, required if there are multiple repos
+    #[arg(long, requires = "from")]
+    repo: Option<String>,
+    /// Package target of the artifacts given as package identifiers
+    #[arg(long)]
+    package_target: Option<PackageTarget>,
     /// Output format
-    #[arg(value_enum, short = 'f', long, default_value_t = OutputFormat::Plain)]
-    format: OutputFormat,
+    #[arg(value_enum, short = 'f', long, default_value_t = ReportFormat::Plain)]
+    format: ReportFormat,
+    /// Two artifacts to compare instead of plans, each given as the path to a .hart file or
+    /// as a fully qualified package identifier of an artifact in the artifact cache
+    #[arg(
+        num_args = 2,
+        value_names = ["SOURCE", "TARGET"],
+        conflicts_with_all = ["source_config_path", "target_config_path"]
+    )]
+    artifacts: Vec<String>,
 }
 
 pub(crate) fn execute(args: Params) -> Result<()> {
-    let source_config_path = args.source_config_path;
-    let target_config_path = args.target_config_path;
+    if let [source, target] = args.artifacts.as_slice() {
+        return artifacts_execute(source, target, args.package_target, args.format);
+    }
+    if let (Some(from), Some(to)) = (args.from.as_ref(), args.to.as_ref()) {
+        return refs_execute(
+            from,
+            to,
+            args.config_path,
+            args.repo.as_deref(),
+            args.format,
+        );
+    }
+    let (Some(source_config_path), Some(target_config_path)) =
+        (args.source_config_path, args.target_config_path)
+    else {
+        return Err(eyre!(
+            "Either two artifacts or the source and target configurations must be given"
+        ));
+    };
     let source_config = AutoBuildConfig::new(&source_config_path)?;
     let target_config = AutoBuildConfig::new(&target_config_path)?;
 
@@ -48,8 +108,11 @@ pub(crate) fn execute(args: Params) -> Result<()> {
     let diffs = target_run_context.compare(&source_run_context);
 
     match args.format {
-        OutputFormat::Plain => output_plain(diffs)?,
-        OutputFormat::Json => todo!(),
+        ReportFormat::Plain => output_plain(diffs)?,
+        ReportFormat::Json => output_json(JsonOutputKind::PlanDiff, plan_diff_output(&diffs))?,
+        ReportFormat::Markdown => {
+            return Err(eyre!("Markdown output is not supported for comparisons"))
+        }
     }
     Ok(())
 }
@@ -79,3 +142,367 @@ fn output_plain(
     }
     Ok(())
 }
+
+fn plan_diff_output(
+    package_diffs: &HashMap<(PackageTarget, PackageOrigin, PackageName), PackageDiff>,
+) -> Vec<PlanDiffOutput> {
+    let mut plan_diffs = package_diffs
+        .iter()
+        .map(|((target, origin, name), package_diff)| PlanDiffOutput {
+            package: GraphDiffPackageOutput {
+                package: format!("{}/{}", origin, name),
+                target: target.to_string(),
+            },
+            source_versions: package_diff.source.iter().map(|v| v.to_string()).collect(),
+            target_versions: package_diff.target.iter().map(|v| v.to_string()).collect(),
+            changed: package_diff.source != package_diff.target,
+        })
+        .collect::<Vec<_>>();
+    plan_diffs.sort_by(|a, b| {
+        (&a.package.package, &a.package.target).cmp(&(&b.package.package, &b.package.target))
+    });
+    plan_diffs
+}
+
+fn refs_execute(
+    from: &str,
+    to: &str,
+    config_path: Option<PathBuf>,
+    repo: Option<&str>,
+    format: ReportFormat,
+) -> Result<()> {
+    let config_path = config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let repo_config = match repo {
+        Some(repo_id) => config.repos.iter().find(|repo| repo.id == repo_id),
+        None if config.repos.len() == 1 => config.repos.first(),
+        None => None,
+    };
+    let Some(repo_config) = repo_config else {
+        let repo_ids = config
+            .repos
+            .iter()
+            .map(|repo| repo.id.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(match repo {
+            Some(repo_id) => eyre!("No repo with the id '{}' is configured", repo_id),
+            None => eyre!("The repo whose git refs are compared must be specified"),
+        })
+        .with_suggestion(|| format!("Use '--repo' with one of: {}", repo_ids));
+    };
+    let auto_build_ctx_path = AutoBuildContextPath::from(
+        config_path
+            .parent()
+            .ok_or(eyre!(
+                "Failed to determine parent folder of hab-auto-build configuration file"
+            ))?
+            .to_path_buf(),
+    );
+    let store_path = config.store_path(&config_path)?;
+    let store =
+        Store::new(&store_path, &config.workspace_id(&config_path)?).with_context(|| {
+            format!(
+                "Failed to initialize hab-auto-build store at {}",
+                store_path.display()
+            )
+        })?;
+
+    // The worktrees are removed when they are dropped at the end of the comparison
+    let from_worktree = repo_config.git_worktree_add(&auto_build_ctx_path, &store, "from", from)?;
+    let to_worktree = repo_config.git_worktree_add(&auto_build_ctx_path, &store, "to", to)?;
+    let worktree_context = |worktree: &RepoWorktree, git_ref: &str| {
+        let mut config = config.clone();
+        for repo in config.repos.iter_mut() {
+            if repo.id == repo_config.id {
+                repo.source = Some(worktree.source.clone());
+            }
+        }
+        info!(target: "user-log",
+            "Building the dependency graph of repo '{}' at {} ({})",
+            repo_config.id,
+            git_ref,
+            &worktree.commit[..worktree.commit.len().min(12)]
+        );
+        AutoBuildContext::new_without_artifact_cache(&config, &config_path).with_context(|| {
+            eyre!(
+                "Failed to build the dependency graph of repo '{}' at '{}'",
+                repo_config.id,
+                git_ref
+            )
+        })
+    };
+    let from_run_context = worktree_context(&from_worktree, from)?;
+    let to_run_context = worktree_context(&to_worktree, to)?;
+    let diff = to_run_context.graph_diff(&from_run_context);
+
+    match format {
+        ReportFormat::Plain => output_graph_diff_plain(&diff),
+        ReportFormat::Json => output_json(
+            JsonOutputKind::GraphDiff,
+            graph_diff_output(&diff, &from_worktree.commit, &to_worktree.commit),
+        )?,
+        ReportFormat::Markdown => output_graph_diff_markdown(&diff)?,
+    }
+    Ok(())
+}
+
+fn graph_package_output(package: &GraphPackage) -> GraphDiffPackageOutput {
+    GraphDiffPackageOutput {
+        package: format!("{}/{}", package.origin, package.name),
+        target: package.target.to_string(),
+    }
+}
+
+fn graph_edge_output(edge: &GraphEdge) -> GraphDiffEdgeOutput {
+    GraphDiffEdgeOutput {
+        package: graph_package_output(&edge.package),
+        dep_type: edge.dep_type,
+        dependency: edge.dep.to_string(),
+    }
+}
+
+fn graph_diff_output(diff: &GraphDiff, from: &str, to: &str) -> GraphDiffOutput {
+    GraphDiffOutput {
+        from: from.to_string(),
+        to: to.to_string(),
+        added_plans: diff.added_plans.iter().map(graph_package_output).collect(),
+        removed_plans: diff
+            .removed_plans
+            .iter()
+            .map(graph_package_output)
+            .collect(),
+        version_changes: diff
+            .version_changes
+            .iter()
+            .map(|change| GraphDiffVersionChangeOutput {
+                package: graph_package_output(&change.package),
+                from: change.from.iter().map(|v| v.to_string()).collect(),
+                to: change.to.iter().map(|v| v.to_string()).collect(),
+            })
+            .collect(),
+        added_edges: diff.added_edges.iter().map(graph_edge_output).collect(),
+        removed_edges: diff.removed_edges.iter().map(graph_edge_output).collect(),
+    }
+}
+
+fn versions_list(versions: &BTreeSet<PackageBuildVersion>) -> String {
+    versions
+        .iter()
+        .map(|version| version.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn output_graph_diff_plain(diff: &GraphDiff) {
+    if diff.is_empty() {
+        info!(target: "user-ui", "{}", "No changes to the plans or their dependencies".green());
+        return;
+    }
+    if !diff.added_plans.is_empty() || !diff.removed_plans.is_empty() {
+        info!(target: "user-ui", "{}", "Changed Plans".white().bold());
+        for package in diff.added_plans.iter() {
+            info!(target: "user-ui", "  {}", format!("+ {}", package).green());
+        }
+        for package in diff.removed_plans.iter() {
+            info!(target: "user-ui", "  {}", format!("- {}", package).red());
+        }
+    }
+    if !diff.version_changes.is_empty() {
+        info!(target: "user-ui", "{}", "Changed Versions".white().bold());
+        for change in diff.version_changes.iter() {
+            info!(target: "user-ui", "  {}: {} -> {}",
+                change.package.yellow(),
+                versions_list(&change.from).white(),
+                versions_list(&change.to).blue()
+            );
+        }
+    }
+    if !diff.added_edges.is_empty() || !diff.removed_edges.is_empty() {
+        info!(target: "user-ui", "{}", "Changed Dependencies".white().bold());
+        for edge in diff.added_edges.iter() {
+            info!(target: "user-ui", "  {}", format!("+ {} -> {} ({})", edge.package, edge.dep, edge.dep_type).green());
+        }
+        for edge in diff.removed_edges.iter() {
+            info!(target: "user-ui", "  {}", format!("- {} -> {} ({})", edge.package, edge.dep, edge.dep_type).red());
+        }
+    }
+}
+
+fn output_graph_diff_markdown(diff: &GraphDiff) -> Result<()> {
+    let mut output = String::new();
+    if diff.is_empty() {
+        writeln!(output, "No changes to the plans or their dependencies")?;
+    }
+    if !diff.added_plans.is_empty() || !diff.removed_plans.is_empty() {
+        writeln!(output, "## Changed Plans\n")?;
+        writeln!(output, "| Change | Package | Target |")?;
+        writeln!(output, "| --- | --- | --- |")?;
+        for (change, packages) in [
+            ("added", &diff.added_plans),
+            ("removed", &diff.removed_plans),
+        ] {
+            for package in packages.iter() {
+                writeln!(
+                    output,
+                    "| {} | {}/{} | {} |",
+                    change, package.origin, package.name, package.target
+                )?;
+            }
+        }
+        writeln!(output)?;
+    }
+    if !diff.version_changes.is_empty() {
+        writeln!(output, "## Changed Versions\n")?;
+        writeln!(output, "| Package | Target | From | To |")?;
+        writeln!(output, "| --- | --- | --- | --- |")?;
+        for change in diff.version_changes.iter() {
+            writeln!(
+                output,
+                "| {}/{} | {} | {} | {} |",
+                change.package.origin,
+                change.package.name,
+                change.package.target,
+                versions_list(&change.from),
+                versions_list(&change.to)
+            )?;
+        }
+        writeln!(output)?;
+    }
+    if !diff.added_edges.is_empty() || !diff.removed_edges.is_empty() {
+        writeln!(output, "## Changed Dependencies\n")?;
+        writeln!(output, "| Change | Package | Target | Dependency | Type |")?;
+        writeln!(output, "| --- | --- | --- | --- | --- |")?;
+        for (change, edges) in [
+            ("added", &diff.added_edges),
+            ("removed", &diff.removed_edges),
+        ] {
+            for edge in edges.iter() {
+                writeln!(
+                    output,
+                    "| {} | {}/{} | {} | {} | {} |",
+                    change,
+                    edge.package.origin,
+                    edge.package.name,
+                    edge.package.target,
+                    edge.dep,
+                    edge.dep_type
+                )?;
+            }
+        }
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
+
+fn artifacts_execute(
+    source: &str,
+    target: &str,
+    package_target: Option<PackageTarget>,
+    format: ReportFormat,
+) -> Result<()> {
+    let source_path = artifact_path_resolve(source, package_target)?;
+    let target_path = artifact_path_resolve(target, package_target)?;
+    let diff = artifact_diff(&source_path, &target_path)?;
+    match format {
+        ReportFormat::Plain => output_artifact_diff_plain(&diff),
+        ReportFormat::Json => output_json(JsonOutputKind::ArtifactDiff, &diff)?,
+        ReportFormat::Markdown => {
+            return Err(eyre!("Markdown output is not supported for comparisons"))
+        }
+    }
+    Ok(())
+}
+
+/// Resolves an artifact given as the path to a .hart file or as a fully qualified
+/// package identifier of an artifact in the artifact cache
+fn artifact_path_resolve(artifact: &str, package_target: Option<PackageTarget>) -> Result<PathBuf> {
+    let path = PathBuf::from(artifact);
+    if path.is_file() {
+        return Ok(path);
+    }
+    if artifact.ends_with(".hart") {
+        return Err(eyre!("The artifact '{}' does not exist", path.display()));
+    }
+    let ident = artifact_ident(&PackageDepIdent::parse(artifact)?, package_target)?;
+    let path = ArtifactCachePath::default()
+        .as_ref()
+        .join(ident.artifact_name());
+    if !path.is_file() {
+        return Err(eyre!(
+            "No artifact found for {} in the artifact cache",
+            ident
+        ))
+        .with_suggestion(|| "Specify the path to the .hart file of the artifact instead");
+    }
+    Ok(path)
+}
+
+fn size_delta(source: u64, target: u64) -> String {
+    if target >= source {
+        format!("+{}", format_size(target - source))
+    } else {
+        format!("-{}", format_size(source - target))
+    }
+}
+
+fn output_artifact_diff_plain(diff: &ArtifactDiff) {
+    info!(target: "user-ui", "{} -> {}", diff.source.white(), diff.target.blue());
+    info!(target: "user-ui", "Installed size: {} -> {} ({})",
+        format_size(diff.source_installed_size),
+        format_size(diff.target_installed_size),
+        size_delta(diff.source_installed_size, diff.target_installed_size)
+    );
+    info!(target: "user-ui", "Artifact size: {} -> {} ({})",
+        format_size(diff.source_artifact_size),
+        format_size(diff.target_artifact_size),
+        size_delta(diff.source_artifact_size, diff.target_artifact_size)
+    );
+    if diff.is_empty() {
+        info!(target: "user-ui", "{}", "No changes to files, ELF dependencies, licenses or runtime path".green());
+        return;
+    }
+    if !diff.added_files.is_empty() || !diff.removed_files.is_empty() {
+        info!(target: "user-ui", "{}", "Changed Files".white().bold());
+        for file in diff.added_files.iter() {
+            info!(target: "user-ui", "  {}", format!("+ {}", file.display()).green());
+        }
+        for file in diff.removed_files.iter() {
+            info!(target: "user-ui", "  {}", format!("- {}", file.display()).red());
+        }
+    }
+    if !diff.elf_dependencies.is_empty() {
+        info!(target: "user-ui", "{}", "Changed ELF Dependencies".white().bold());
+        for elf in diff.elf_dependencies.iter() {
+            info!(target: "user-ui", "  {}", elf.file.display().yellow());
+            for library in elf.added.iter() {
+                info!(target: "user-ui", "    {}", format!("+ {}", library).green());
+            }
+            for library in elf.removed.iter() {
+                info!(target: "user-ui", "    {}", format!("- {}", library).red());
+            }
+        }
+    }
+    if !diff.added_licenses.is_empty() || !diff.removed_licenses.is_empty() {
+        info!(target: "user-ui", "{}", "Changed Licenses".white().bold());
+        for license in diff.added_licenses.iter() {
+            info!(target: "user-ui", "  {}", format!("+ {}", license).green());
+        }
+        for license in diff.removed_licenses.iter() {
+            info!(target: "user-ui", "  {}", format!("- {}", license).red());
+        }
+    }
+    if !diff.added_runtime_path.is_empty() || !diff.removed_runtime_path.is_empty() {
+        info!(target: "user-ui", "{}", "Changed Runtime Path".white().bold());
+        for entry in diff.added_runtime_path.iter() {
+            info!(target: "user-ui", "  {}", format!("+ {}", entry.display()).green());
+        }
+        for entry in diff.removed_runtime_path.iter() {
+            info!(target: "user-ui", "  {}", format!("- {}", entry.display()).red());
+        }
+    }
+}
@@ -1,26 +1,48 @@
 use color_eyre::eyre::{eyre, Context, Result};
 
-use std::{env, path::PathBuf};
+use owo_colors::OwoColorize;
+use petgraph::stable_graph::NodeIndex;
+use rayon::prelude::*;
+use std::{
+    env,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, RecvTimeoutError},
+    },
+    time::Duration,
+};
 use tracing::{error, info};
 
 use clap::Args;
 
 use crate::{
-    cli::check::output_violations,
+    cli::check::{output_license_scan_summary, output_violations},
     core::{
-        AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, DownloadStatus, PackageDepGlob,
-        PackageTarget,
+        AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, DownloadStatus, DownloadTracker,
+        PackageDepGlob, PackageTarget,
     },
 };
 
+/// Interval at which the progress of the downloads is reported
+const PROGRESS_INTERVAL_SECS: u64 = 2;
+/// Width of the progress bars, in characters
+const PROGRESS_BAR_WIDTH: usize = 30;
+
 #[derive(Debug, Args)]
 pub(crate) struct Params {
     /// Path to hab auto build configuration
     #[arg(short, long)]
     config_path: Option<PathBuf>,
     /// Check the source archive against the plan for issues
-    #[arg(short, long, default_value_t = false)]
+    #[arg(long, default_value_t = false)]
     check_source: bool,
+    /// Number of source archives to download concurrently
+    #[arg(short = 'j', long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: u16,
+    /// Maximum combined download rate in bytes per second, accepts K, M and G suffixes like '500K' or '2M'
+    #[arg(long, value_parser = parse_rate)]
+    limit_rate: Option<u64>,
     /// List of packages for which to download source archives
     packages: Vec<PackageDepGlob>,
 }
@@ -33,7 +55,7 @@ pub(crate) fn execute(args: Params) -> Result<()> {
     );
     let config = AutoBuildConfig::new(&config_path)?;
 
-    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+    let mut run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
         .with_context(|| eyre!("Failed to initialize run"))?;
 
     let package_indices = run_context.glob_deps(&args.packages, PackageTarget::default())?;
@@ -44,61 +66,174 @@ pub(crate) fn execute(args: Params) -> Result<()> {
         );
         return Ok(());
     }
-    for package_index in package_indices {
-        let dep = run_context.dep(package_index);
-        info!(target: "user-log", "Downloading source for {:?}", dep);
-        match run_context.download_dep_source(package_index, args.check_source) {
-            Ok(status) => match status {
-                DownloadStatus::Downloaded(
-                    _source_ctx,
-                    plan_ctx,
-                    source,
-                    download_duration,
-                    source_violations,
-                ) => {
-                    info!(target: "user-log", "Downloaded sources for {} from {} in {:.3}s", plan_ctx.id, source.url, download_duration.num_milliseconds() as f32 / 1000.0f32);
-                    if args.check_source {
-                        output_violations(
-                            Some(plan_ctx.plan_path.plan_config_path()),
-                            &source_violations,
-                            &[],
-                            "",
-                            false,
-                            false,
-                        )?;
-                    }
-                }
-                DownloadStatus::AlreadyDownloaded(
-                    _source_ctx,
-                    plan_ctx,
-                    source,
-                    source_violations,
-                ) => {
-                    info!(target: "user-log", "Found existing sources for {} from {}", plan_ctx.id, source.url);
-                    if args.check_source {
-                        output_violations(
-                            Some(plan_ctx.plan_path.plan_config_path()),
-                            &source_violations,
-                            &[],
-                            "",
-                            false,
-                            false,
-                        )?;
-                    }
-                }
-                DownloadStatus::MissingSource(plan_ctx) => {
-                    info!(target: "user-log", "Plan {} has no 'pkg_source' attribute specified", plan_ctx.id);
-                }
-                DownloadStatus::InvalidArchive(plan_ctx, source, actual_shasum, archive_path) => {
-                    error!(target: "user-log", "Downloaded source shasum for {} from {} does not match, expected '{}', found '{}'. You can inspect the downloaded file at {}", plan_ctx.id, source.url, source.shasum, actual_shasum, archive_path.as_ref().display());
+    let download_tracker = DownloadTracker::new(args.limit_rate);
+    run_context.download_tracker_set(download_tracker.clone());
+    let download_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.concurrency as usize)
+        .build()
+        .context("Failed to create download thread pool")?;
+    let completed = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        let (done_sender, done_receiver) = channel::<()>();
+        let (download_tracker, completed, package_indices) =
+            (&download_tracker, &completed, &package_indices);
+        scope.spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) =
+                done_receiver.recv_timeout(Duration::from_secs(PROGRESS_INTERVAL_SECS))
+            {
+                output_progress(
+                    download_tracker,
+                    completed.load(Ordering::Relaxed),
+                    package_indices.len(),
+                );
+            }
+        });
+        let result = download_pool.install(|| {
+            package_indices.par_iter().try_for_each(|package_index| {
+                let result = download_source(&run_context, *package_index, args.check_source);
+                completed.fetch_add(1, Ordering::Relaxed);
+                result
+            })
+        });
+        drop(done_sender);
+        result
+    })?;
+    output_license_scan_summary(&run_context);
+
+    Ok(())
+}
+
+/// Downloads the source archive of a package, if it is not already in the store
+fn download_source(
+    run_context: &AutoBuildContext,
+    package_index: NodeIndex,
+    check_source: bool,
+) -> Result<()> {
+    let dep = run_context.dep(package_index);
+    info!(target: "user-log", "Downloading source for {:?}", dep);
+    match run_context.download_dep_source(package_index, check_source) {
+        Ok(status) => match status {
+            DownloadStatus::Downloaded(
+                _source_ctx,
+                plan_ctx,
+                _source,
+                download_url,
+                download_duration,
+                source_violations,
+            ) => {
+                info!(target: "user-log", "Downloaded sources for {} from {} in {:.3}s", plan_ctx.id, download_url, download_duration.num_milliseconds() as f32 / 1000.0f32);
+                if check_source {
+                    output_violations(
+                        Some(plan_ctx.plan_path.plan_config_path()),
+                        &source_violations,
+                        &[],
+                        "",
+                        false,
+                        false,
+                    )?;
                 }
-                DownloadStatus::NoSource => {
-                    info!(target: "user-log", "Dependency {:?} cannot be downloaded", dep);
+            }
+            DownloadStatus::AlreadyDownloaded(
+                _source_ctx,
+                plan_ctx,
+                _source,
+                download_url,
+                source_violations,
+            ) => {
+                info!(target: "user-log", "Found existing sources for {} from {}", plan_ctx.id, download_url);
+                if check_source {
+                    output_violations(
+                        Some(plan_ctx.plan_path.plan_config_path()),
+                        &source_violations,
+                        &[],
+                        "",
+                        false,
+                        false,
+                    )?;
                 }
-            },
-            Err(err) => return Err(eyre!(err)),
+            }
+            DownloadStatus::MissingSource(plan_ctx) => {
+                info!(target: "user-log", "Plan {} has no 'pkg_source' attribute specified", plan_ctx.id);
+            }
+            DownloadStatus::InvalidArchive(plan_ctx, source, actual_shasum, archive_path) => {
+                error!(target: "user-log", "Downloaded source shasum for {} from {} does not match, expected '{}', found '{}'. You can inspect the downloaded file at {}", plan_ctx.id, source.url, source.shasum, actual_shasum, archive_path.as_ref().display());
+            }
+            DownloadStatus::NoSource => {
+                info!(target: "user-log", "Dependency {:?} cannot be downloaded", dep);
+            }
+        },
+        Err(err) => return Err(eyre!(err)),
+    }
+    Ok(())
+}
+
+/// Prints a progress bar for each download in progress and one for all the downloads
+fn output_progress(download_tracker: &DownloadTracker, completed: usize, total: usize) {
+    let downloads = download_tracker.downloads();
+    if downloads.is_empty() {
+        return;
+    }
+    for download in downloads {
+        let size = match download.total {
+            Some(total) => format!(
+                "{}/{}",
+                format_bytes(download.downloaded),
+                format_bytes(total)
+            ),
+            None => format_bytes(download.downloaded),
+        };
+        info!(target: "user-ui", "{} {} {} {}", "  Downloading".green().bold(), progress_bar(download.downloaded, download.total), size, download.name);
+    }
+    info!(target: "user-ui", "{} {} {}/{} sources, {} downloaded", "     Progress".green().bold(), progress_bar(completed as u64, Some(total as u64)), completed, total, format_bytes(download_tracker.downloaded()));
+}
+
+fn progress_bar(done: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) if total > 0 => {
+            let ratio = (done as f64 / total as f64).min(1.0);
+            let filled = (ratio * PROGRESS_BAR_WIDTH as f64) as usize;
+            format!(
+                "[{}{}] {:>3}%",
+                "#".repeat(filled),
+                " ".repeat(PROGRESS_BAR_WIDTH - filled),
+                (ratio * 100.0) as u64
+            )
         }
+        _ => format!("[{}]    ", "?".repeat(PROGRESS_BAR_WIDTH)),
     }
+}
 
-    Ok(())
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next_unit in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{:.1} {}", value, unit)
+}
+
+/// Parses a download rate in bytes per second, with an optional K, M or G suffix
+fn parse_rate(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&value[..index], 1024),
+        Some((index, 'm' | 'M')) => (&value[..index], 1024 * 1024),
+        Some((index, 'g' | 'G')) => (&value[..index], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    match number.trim().parse::<u64>() {
+        Ok(rate) if rate > 0 => Ok(rate * multiplier),
+        _ => Err(format!(
+            "invalid rate '{}', expected a positive number of bytes with an optional K, M or G suffix",
+            value
+        )),
+    }
 }
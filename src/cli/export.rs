@@ -0,0 +1,98 @@
+use std::{env, path::PathBuf};
+
+use clap::{Args, Subcommand};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::core::{
+    AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepGlob, PackageTarget,
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Export a built package and its runtime dependencies as an OCI container image archive
+    Docker(DockerParams),
+}
+
+#[derive(Debug, Args)]
+struct DockerParams {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Name and tag of the image, defaults to <ORIGIN>/<NAME>:<VERSION>-<RELEASE>
+    #[arg(short, long)]
+    tag: Option<String>,
+    /// Path of the image archive, defaults to <ORIGIN>-<NAME>-<VERSION>-<RELEASE>-<TARGET>.oci.tar in the current folder
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Package to export, the latest artifact built from its plan is used for local plans
+    package: PackageDepGlob,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    match args.command {
+        Command::Docker(args) => docker_execute(args),
+    }
+}
+
+fn docker_execute(args: DockerParams) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let package_indices = run_context.glob_deps(
+        std::slice::from_ref(&args.package),
+        PackageTarget::default(),
+    )?;
+    let package_index = match package_indices.as_slice() {
+        [package_index] => *package_index,
+        [] => return Err(eyre!("No package found matching '{}'", args.package)),
+        _ => {
+            return Err(eyre!(
+                "Found {} packages matching '{}', only one package can be exported at a time",
+                package_indices.len(),
+                args.package
+            ))
+        }
+    };
+    let artifact = run_context
+        .package_artifact(package_index)?
+        .ok_or_else(|| eyre!("No artifact found for '{}'", args.package))
+        .with_suggestion(|| "Build the package with 'hab-auto-build build' before exporting it")?;
+
+    let id = &artifact.id;
+    let tag = args
+        .tag
+        .unwrap_or_else(|| format!("{}/{}:{}-{}", id.origin, id.name, id.version, id.release));
+    let output_path = match args.output {
+        Some(output) => output,
+        None => env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join(format!(
+                "{}-{}-{}-{}-{}.oci.tar",
+                id.origin, id.name, id.version, id.release, id.target
+            )),
+    };
+    let image = run_context.oci_image_export(&artifact, &tag, &output_path)?;
+    if !image.has_entrypoint {
+        info!(target: "user-ui", "{}: {} has no run hook, the image has no entrypoint", "warning".bold().yellow(), id);
+    }
+    info!(target: "user-log", "Exported {} with {} packages as {} ({}) to {}", id, image.package_count, image.tag.blue(), image.digest, output_path.display().blue());
+    Ok(())
+}
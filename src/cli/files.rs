@@ -0,0 +1,146 @@
+use std::{env, fmt::Write, path::PathBuf};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::{error, info};
+
+use crate::{
+    cli::output::{self, ArtifactFilesOutput, JsonOutputKind, ReportFormat},
+    core::{
+        ArtifactContext, ArtifactFile, AutoBuildConfig, AutoBuildContext, ChangeDetectionMode,
+        PackageDepGlob, PackageTarget,
+    },
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Output format
+    #[arg(value_enum, short = 'f', long, default_value_t = ReportFormat::Plain)]
+    format: ReportFormat,
+    /// Package target of the artifacts to list, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// List of packages whose latest artifact's files are listed
+    #[arg(required = true)]
+    packages: Vec<PackageDepGlob>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let package_indices = run_context.glob_deps(&args.packages, target)?;
+    if package_indices.is_empty() {
+        error!(target: "user-log",
+            "No packages found matching patterns: {}",
+            serde_json::to_string(&args.packages).unwrap()
+        );
+        return Ok(());
+    }
+
+    let mut artifacts = Vec::new();
+    for package_index in package_indices.iter() {
+        let package = run_context.dep(*package_index);
+        match run_context.package_artifact(*package_index)? {
+            Some(artifact) => {
+                let files = run_context.artifact_files(&artifact)?;
+                artifacts.push((artifact, files));
+            }
+            None => {
+                info!(target: "user-ui", "{}: {:?}: No artifact found, skipping", "warning".bold().yellow(), package.red())
+            }
+        }
+    }
+    if artifacts.is_empty() {
+        return Err(eyre!("No artifacts found for any of the selected packages"))
+            .with_suggestion(|| "Build the packages before listing their files");
+    }
+
+    match args.format {
+        ReportFormat::Plain => output_plain(&artifacts),
+        ReportFormat::Json => output::output_json(
+            JsonOutputKind::ArtifactFiles,
+            artifacts
+                .iter()
+                .map(|(artifact, files)| ArtifactFilesOutput {
+                    artifact: artifact.id.to_string(),
+                    files: files.clone(),
+                })
+                .collect::<Vec<_>>(),
+        ),
+        ReportFormat::Markdown => output_markdown(&artifacts),
+    }
+}
+
+fn output_plain(artifacts: &[(ArtifactContext, Vec<ArtifactFile>)]) -> Result<()> {
+    for (artifact, files) in artifacts.iter() {
+        info!(target: "user-ui", "{}:", artifact.id.blue());
+        for file in files.iter() {
+            match &file.link {
+                Some(link) => info!(
+                    target: "user-ui",
+                    "  {:o} {:>12} {} -> {}",
+                    file.mode,
+                    file.size,
+                    file.path.display(),
+                    link.display().bright_black()
+                ),
+                None => info!(
+                    target: "user-ui",
+                    "  {:o} {:>12} {}",
+                    file.mode,
+                    file.size,
+                    file.path.display()
+                ),
+            }
+        }
+        info!(
+            target: "user-log",
+            "{} contains {} files, {} bytes in total",
+            artifact.id,
+            files.len().blue(),
+            files.iter().map(|file| file.size).sum::<u64>().blue()
+        );
+    }
+    Ok(())
+}
+
+fn output_markdown(artifacts: &[(ArtifactContext, Vec<ArtifactFile>)]) -> Result<()> {
+    let mut output = String::new();
+    for (artifact, files) in artifacts.iter() {
+        writeln!(output, "## {}\n", artifact.id)?;
+        writeln!(output, "| Path | Size | Mode | Link |")?;
+        writeln!(output, "| --- | --- | --- | --- |")?;
+        for file in files.iter() {
+            writeln!(
+                output,
+                "| {} | {} | {:o} | {} |",
+                file.path.display(),
+                file.size,
+                file.mode,
+                file.link
+                    .as_ref()
+                    .map(|link| link.display().to_string())
+                    .unwrap_or_default()
+            )?;
+        }
+        writeln!(output)?;
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
@@ -18,6 +18,9 @@ pub(crate) struct Params {
     /// Do a dry run of the sync and output the potential changes
     #[arg(short = 'd', long)]
     dry_run: bool,
+    /// Restore the modification times that files had before they were synced with git
+    #[arg(short = 'r', long, conflicts_with = "dry_run")]
+    revert: bool,
     /// List of packages to add to the change list
     packages: Option<Vec<PackageDepGlob>>,
 }
@@ -47,28 +50,75 @@ pub(crate) fn execute(args: Params) -> Result<()> {
     }
 
     run_context.get_connection()?.exclusive_transaction(|connection| {
-        match run_context.sync_plans_with_git(connection, &package_indices, args.dry_run) {
-            Ok(repo_statuses) => {
-                for (repo_id, plan_statuses ) in repo_statuses {
-                    info!(target: "user-ui", "{}:", repo_id.to_string().blue());
-                for plan_status in plan_statuses {
-                    if !plan_status.file_statuses.is_empty() {
-                        info!(target: "user-ui", "  {}:", plan_status.id.to_string().blue());
-                        for file_status in plan_status.file_statuses {
-                            match file_status {
-                                PlanContextPathGitSyncStatus::Synced(path, disk_modified_at, git_modified_at) => {
-                                    info!(target: "user-ui", "    {}: synced from {} to {}", path.display().white(), disk_modified_at.green(), git_modified_at.green());
-                                },
-                                PlanContextPathGitSyncStatus::LocallyModified(path, disk_modified_at) => {
-                                    info!(target: "user-ui", "    {}: local modification at {}", path.display().white(), disk_modified_at.yellow());
-                                },
-                            }
+        let repo_statuses = if args.revert {
+            run_context.revert_plans_git_sync(connection, &package_indices)
+        } else {
+            run_context.sync_plans_with_git(connection, &package_indices, args.dry_run)
+        }
+        .map_err(|err| eyre!(err))?;
+        let mut file_count = 0;
+        for (repo_id, plan_statuses) in repo_statuses {
+            info!(target: "user-ui", "{}:", repo_id.to_string().blue());
+            for plan_status in plan_statuses {
+                if plan_status.file_statuses.is_empty() {
+                    continue;
+                }
+                info!(target: "user-ui", "  {}:", plan_status.id.to_string().blue());
+                for file_status in plan_status.file_statuses {
+                    match file_status {
+                        PlanContextPathGitSyncStatus::Synced(
+                            path,
+                            disk_modified_at,
+                            git_modified_at,
+                            commit,
+                        ) => {
+                            file_count += 1;
+                            info!(target: "user-ui",
+                                "    {}: {} from {} to {} (commit {})",
+                                path.display().white(),
+                                if args.dry_run { "would be synced" } else { "synced" },
+                                disk_modified_at.green(),
+                                git_modified_at.green(),
+                                commit.cyan()
+                            );
+                        }
+                        PlanContextPathGitSyncStatus::LocallyModified(path, disk_modified_at) => {
+                            info!(target: "user-ui",
+                                "    {}: local modification at {}",
+                                path.display().white(),
+                                disk_modified_at.yellow()
+                            );
+                        }
+                        PlanContextPathGitSyncStatus::Reverted(
+                            path,
+                            synced_modified_at,
+                            original_modified_at,
+                        ) => {
+                            file_count += 1;
+                            info!(target: "user-ui",
+                                "    {}: reverted from {} to {}",
+                                path.display().white(),
+                                synced_modified_at.green(),
+                                original_modified_at.green()
+                            );
+                        }
+                        PlanContextPathGitSyncStatus::ModifiedSinceSync(path, disk_modified_at) => {
+                            info!(target: "user-ui",
+                                "    {}: modified at {} since the sync, not reverted",
+                                path.display().white(),
+                                disk_modified_at.yellow()
+                            );
                         }
                     }
                 }
             }
-            }
-            Err(err) => return Err(eyre!(err)),
+        }
+        if args.revert {
+            info!(target: "user-log", "Reverted the modification time of {} files", file_count);
+        } else if args.dry_run {
+            info!(target: "user-log", "The modification time of {} files would be synced with git", file_count);
+        } else {
+            info!(target: "user-log", "Synced the modification time of {} files with git", file_count);
         }
         Ok(())
     })
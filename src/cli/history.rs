@@ -0,0 +1,322 @@
+use std::{env, fmt::Write, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use chrono_humanize::{Accuracy, HumanTime, Tense};
+use clap::{Args, Subcommand};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::{
+    cli::output::{
+        self, BuildSessionArtifactOutput, BuildSessionDetailOutput, BuildSessionOutput,
+        BuildSessionStepOutput, JsonOutputKind, ReportFormat,
+    },
+    core::{AutoBuildConfig, AutoBuildContext, ChangeDetectionMode},
+    store::{BuildSession, BuildSessionArtifact, BuildStepState, BuildStepStatus},
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long, global = true)]
+    config_path: Option<PathBuf>,
+    /// Output format
+    #[arg(value_enum, short = 'f', long, global = true, default_value_t = ReportFormat::Plain)]
+    format: ReportFormat,
+    /// Maximum number of sessions to list, starting from the most recent
+    #[arg(short = 'n', long, default_value_t = 20)]
+    limit: usize,
+    /// List all recorded sessions
+    #[arg(short, long, conflicts_with = "limit")]
+    all: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Show the steps and built artifacts of a build session
+    Show {
+        /// Id of the build session, as listed by 'hab-auto-build history'
+        id: i32,
+    },
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    match args.command {
+        Some(Command::Show { id }) => {
+            let build_session = run_context
+                .build_session(id)?
+                .ok_or_else(|| eyre!("No build session found with id {}", id))
+                .with_suggestion(|| "Use 'hab-auto-build history' to list the build sessions")?;
+            let step_states = run_context.build_session_step_states(&build_session)?;
+            let artifacts = run_context.build_session_artifacts(&build_session)?;
+            match args.format {
+                ReportFormat::Plain => {
+                    output_session_plain(&build_session, &step_states, &artifacts)
+                }
+                ReportFormat::Json => output::output_json(
+                    JsonOutputKind::HistorySession,
+                    BuildSessionDetailOutput {
+                        session: session_output(&build_session),
+                        steps: step_states
+                            .iter()
+                            .map(|step_state| BuildSessionStepOutput {
+                                plan: step_state.plan.clone(),
+                                status: step_state.status.as_str().to_string(),
+                                updated_at: step_state.updated_at.to_rfc3339(),
+                            })
+                            .collect(),
+                        artifacts: artifacts
+                            .iter()
+                            .map(|artifact| BuildSessionArtifactOutput {
+                                artifact: artifact.artifact.to_string(),
+                                error_count: artifact.error_count,
+                                warning_count: artifact.warning_count,
+                            })
+                            .collect(),
+                    },
+                ),
+                ReportFormat::Markdown => {
+                    output_session_markdown(&build_session, &step_states, &artifacts)
+                }
+            }
+        }
+        None => {
+            let build_sessions =
+                run_context.build_sessions(if args.all { None } else { Some(args.limit) })?;
+            match args.format {
+                ReportFormat::Plain => output_sessions_plain(&build_sessions),
+                ReportFormat::Json => output::output_json(
+                    JsonOutputKind::History,
+                    build_sessions
+                        .iter()
+                        .map(session_output)
+                        .collect::<Vec<_>>(),
+                ),
+                ReportFormat::Markdown => output_sessions_markdown(&build_sessions),
+            }
+        }
+    }
+}
+
+fn session_output(build_session: &BuildSession) -> BuildSessionOutput {
+    BuildSessionOutput {
+        id: build_session.id,
+        started_at: build_session.started_at.to_rfc3339(),
+        ended_at: build_session.ended_at.map(|ended_at| ended_at.to_rfc3339()),
+        planned: build_session.planned,
+        attempted: build_session.attempted,
+        succeeded: build_session.succeeded,
+        failed: build_session.failed,
+        error_count: build_session.error_count,
+        warning_count: build_session.warning_count,
+    }
+}
+
+fn timestamp(value: &DateTime<Utc>) -> String {
+    value.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+fn duration(build_session: &BuildSession) -> String {
+    match build_session.ended_at {
+        Some(ended_at) => HumanTime::from(ended_at - build_session.started_at)
+            .to_text_en(Accuracy::Rough, Tense::Present),
+        None => "did not finish".to_string(),
+    }
+}
+
+fn output_sessions_plain(build_sessions: &[BuildSession]) -> Result<()> {
+    for build_session in build_sessions.iter() {
+        info!(
+            target: "user-ui",
+            "{} {} ({}): {} planned, {} attempted, {} succeeded, {} failed, {} errors, {} warnings",
+            format!("{:>6}", format!("#{}", build_session.id)).bold(),
+            timestamp(&build_session.started_at),
+            duration(build_session),
+            build_session.planned,
+            build_session.attempted,
+            build_session.succeeded.green(),
+            if build_session.failed > 0 {
+                build_session.failed.red().to_string()
+            } else {
+                build_session.failed.to_string()
+            },
+            build_session.error_count,
+            build_session.warning_count
+        );
+    }
+    info!(target: "user-log", "Found {} build sessions", build_sessions.len());
+    Ok(())
+}
+
+fn output_sessions_markdown(build_sessions: &[BuildSession]) -> Result<()> {
+    let mut output = String::new();
+    writeln!(
+        output,
+        "| Id | Started | Duration | Planned | Attempted | Succeeded | Failed | Errors | Warnings |"
+    )?;
+    writeln!(
+        output,
+        "| --- | --- | --- | --- | --- | --- | --- | --- | --- |"
+    )?;
+    for build_session in build_sessions.iter() {
+        writeln!(
+            output,
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} |",
+            build_session.id,
+            timestamp(&build_session.started_at),
+            duration(build_session),
+            build_session.planned,
+            build_session.attempted,
+            build_session.succeeded,
+            build_session.failed,
+            build_session.error_count,
+            build_session.warning_count
+        )?;
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
+
+fn output_session_plain(
+    build_session: &BuildSession,
+    step_states: &[BuildStepState],
+    artifacts: &[BuildSessionArtifact],
+) -> Result<()> {
+    info!(target: "user-ui", "{} {}", "Build session".bold(), format!("#{}", build_session.id).bold());
+    info!(target: "user-ui", "     Started: {}", timestamp(&build_session.started_at));
+    match build_session.ended_at.as_ref() {
+        Some(ended_at) => {
+            info!(target: "user-ui", "       Ended: {} ({})", timestamp(ended_at), duration(build_session))
+        }
+        None => info!(target: "user-ui", "       Ended: {}", "did not finish".yellow()),
+    }
+    info!(
+        target: "user-ui",
+        "    Packages: {} planned, {} attempted, {} succeeded, {} failed",
+        build_session.planned,
+        build_session.attempted,
+        build_session.succeeded,
+        build_session.failed
+    );
+    info!(
+        target: "user-ui",
+        "  Violations: {} errors, {} warnings",
+        build_session.error_count,
+        build_session.warning_count
+    );
+    if !step_states.is_empty() {
+        info!(target: "user-ui", "\n{}", "Steps".bold());
+        let mut step_states = step_states.iter().collect::<Vec<_>>();
+        step_states.sort_by_key(|step_state| step_state.updated_at);
+        for step_state in step_states {
+            let status = format!("{:>9}", step_state.status.as_str());
+            info!(
+                target: "user-ui",
+                "{} {} at {}",
+                match step_state.status {
+                    BuildStepStatus::Built => status.green().bold().to_string(),
+                    BuildStepStatus::Failed => status.red().bold().to_string(),
+                    BuildStepStatus::Building => status.blue().bold().to_string(),
+                    BuildStepStatus::Pending => status.bright_black().to_string(),
+                },
+                step_state.plan,
+                timestamp(&step_state.updated_at)
+            );
+        }
+    }
+    if !artifacts.is_empty() {
+        info!(target: "user-ui", "\n{}", "Artifacts".bold());
+        for artifact in artifacts.iter() {
+            info!(
+                target: "user-ui",
+                "  {}: {} errors, {} warnings",
+                artifact.artifact,
+                artifact.error_count,
+                artifact.warning_count
+            );
+        }
+    }
+    Ok(())
+}
+
+fn output_session_markdown(
+    build_session: &BuildSession,
+    step_states: &[BuildStepState],
+    artifacts: &[BuildSessionArtifact],
+) -> Result<()> {
+    let mut output = String::new();
+    writeln!(output, "## Build session #{}\n", build_session.id)?;
+    writeln!(
+        output,
+        "- Started: {}",
+        timestamp(&build_session.started_at)
+    )?;
+    writeln!(
+        output,
+        "- Ended: {}",
+        build_session
+            .ended_at
+            .as_ref()
+            .map(|ended_at| format!("{} ({})", timestamp(ended_at), duration(build_session)))
+            .unwrap_or_else(|| "did not finish".to_string())
+    )?;
+    writeln!(
+        output,
+        "- Packages: {} planned, {} attempted, {} succeeded, {} failed",
+        build_session.planned,
+        build_session.attempted,
+        build_session.succeeded,
+        build_session.failed
+    )?;
+    writeln!(
+        output,
+        "- Violations: {} errors, {} warnings",
+        build_session.error_count, build_session.warning_count
+    )?;
+    if !step_states.is_empty() {
+        writeln!(output, "\n### Steps\n")?;
+        writeln!(output, "| Plan | Status | Updated |")?;
+        writeln!(output, "| --- | --- | --- |")?;
+        let mut step_states = step_states.iter().collect::<Vec<_>>();
+        step_states.sort_by_key(|step_state| step_state.updated_at);
+        for step_state in step_states {
+            writeln!(
+                output,
+                "| {} | {} | {} |",
+                step_state.plan,
+                step_state.status.as_str(),
+                timestamp(&step_state.updated_at)
+            )?;
+        }
+    }
+    if !artifacts.is_empty() {
+        writeln!(output, "\n### Artifacts\n")?;
+        writeln!(output, "| Artifact | Errors | Warnings |")?;
+        writeln!(output, "| --- | --- | --- |")?;
+        for artifact in artifacts.iter() {
+            writeln!(
+                output,
+                "| {} | {} | {} |",
+                artifact.artifact, artifact.error_count, artifact.warning_count
+            )?;
+        }
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
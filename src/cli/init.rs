@@ -0,0 +1,289 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{stdin, stdout, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use ignore::WalkBuilder;
+use owo_colors::OwoColorize;
+use serde_json::json;
+use tracing::info;
+
+use crate::core::{
+    AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepIdent, PackageTarget,
+    BOOTSTRAP_BUILD_STUDIO_PACKAGE, DEFAULT_STORE_PATH, STANDARD_BUILD_STUDIO_PACKAGE,
+};
+
+/// Maximum folder depth at which plans are searched for when detecting repos
+const REPO_DETECTION_DEPTH: usize = 6;
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path of the hab auto build configuration to create
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Repo to include in the configuration, specified as <ID>=<PATH>. Disables repo detection
+    #[arg(short, long = "repo", value_parser = parse_repo)]
+    repos: Vec<(String, PathBuf)>,
+    /// Package to use as the standard build studio
+    #[arg(long)]
+    standard_studio: Option<PackageDepIdent>,
+    /// Package to use as the bootstrap build studio
+    #[arg(long)]
+    bootstrap_studio: Option<PackageDepIdent>,
+    /// Path to the hab-auto-build store
+    #[arg(short, long)]
+    store: Option<PathBuf>,
+    /// Package target of the plans in the workspace, also used to validate the configuration with a trial plan scan
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// Accept the detected and default values without prompting
+    #[arg(short, long)]
+    yes: bool,
+    /// Overwrite an existing configuration
+    #[arg(short, long)]
+    force: bool,
+}
+
+fn parse_repo(value: &str) -> Result<(String, PathBuf)> {
+    let (id, path) = value
+        .split_once('=')
+        .ok_or_else(|| eyre!("Invalid repo '{}', expected <ID>=<PATH>", value))?;
+    if id.is_empty() || path.is_empty() {
+        return Err(eyre!("Invalid repo '{}', expected <ID>=<PATH>", value));
+    }
+    Ok((id.to_string(), PathBuf::from(path)))
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let current_dir =
+        env::current_dir().context("Failed to determine current working directory")?;
+    let config_path = args
+        .config_path
+        .unwrap_or(current_dir.join("hab-auto-build.json"));
+    if config_path.exists() && !args.force {
+        return Err(eyre!(
+            "A hab-auto-build configuration already exists at '{}'",
+            config_path.display()
+        ))
+        .with_suggestion(|| "Use '--force' to overwrite the existing configuration");
+    }
+    let config_dir = match config_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => current_dir,
+    };
+    // The configuration is validated before it is written, so its path is resolved
+    // the same way it will be once the file exists
+    let config_dir = config_dir.canonicalize().with_context(|| {
+        format!(
+            "Failed to find the folder '{}' to create the configuration in",
+            config_dir.display()
+        )
+    })?;
+    let config_path = config_dir.join(
+        config_path
+            .file_name()
+            .ok_or_else(|| eyre!("Invalid configuration path '{}'", config_path.display()))?,
+    );
+    let interactive = !args.yes && stdin().is_terminal();
+
+    let repos = if args.repos.is_empty() {
+        let mut repos = Vec::new();
+        for (id, path, plan_count) in repos_detect(&config_dir) {
+            if !interactive
+                || prompt_confirm(&format!(
+                    "Include repo '{}' at '{}' with {} plans?",
+                    id,
+                    path.display(),
+                    plan_count
+                ))?
+            {
+                repos.push((id, path));
+            }
+        }
+        if interactive {
+            loop {
+                let path = prompt("Path to an additional repo (leave empty to continue)", None)?;
+                if path.is_empty() {
+                    break;
+                }
+                let default_id = Path::new(&path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string());
+                let id = prompt("Repo id", default_id.as_deref())?;
+                repos.push((id, PathBuf::from(path)));
+            }
+        }
+        repos
+    } else {
+        args.repos
+    };
+    if repos.is_empty() {
+        return Err(eyre!("No repos were detected or specified")).with_suggestion(|| {
+            "Run the command in a folder containing habitat plans, or specify repos with '--repo <ID>=<PATH>'"
+        });
+    }
+
+    let standard_studio = match args.standard_studio {
+        Some(standard_studio) => standard_studio,
+        None if interactive => prompt(
+            "Standard build studio package",
+            Some(STANDARD_BUILD_STUDIO_PACKAGE.to_string().as_str()),
+        )?
+        .parse()?,
+        None => STANDARD_BUILD_STUDIO_PACKAGE.clone(),
+    };
+    let bootstrap_studio = match args.bootstrap_studio {
+        Some(bootstrap_studio) => bootstrap_studio,
+        None if interactive => prompt(
+            "Bootstrap build studio package",
+            Some(BOOTSTRAP_BUILD_STUDIO_PACKAGE.to_string().as_str()),
+        )?
+        .parse()?,
+        None => BOOTSTRAP_BUILD_STUDIO_PACKAGE.clone(),
+    };
+    let store = match args.store {
+        Some(store) => store,
+        None if interactive => PathBuf::from(prompt(
+            "Store location",
+            Some(DEFAULT_STORE_PATH.display().to_string().as_str()),
+        )?),
+        None => DEFAULT_STORE_PATH.clone(),
+    };
+    let target = match args.target {
+        Some(target) => target,
+        None if interactive => PackageTarget::parse(prompt(
+            "Package target",
+            Some(PackageTarget::default().to_string().as_str()),
+        )?)?,
+        None => PackageTarget::default(),
+    };
+
+    let mut config = json!({
+        "repos": repos
+            .iter()
+            .map(|(id, source)| json!({ "id": id, "source": source }))
+            .collect::<Vec<_>>(),
+    });
+    if standard_studio != *STANDARD_BUILD_STUDIO_PACKAGE
+        || bootstrap_studio != *BOOTSTRAP_BUILD_STUDIO_PACKAGE
+    {
+        config["studios"] = json!({
+            "standard": standard_studio,
+            "bootstrap": bootstrap_studio,
+        });
+    }
+    if store != *DEFAULT_STORE_PATH {
+        config["store"] = json!(store);
+    }
+    config["target"] = json!(target.to_string());
+
+    // Do a trial plan scan to make sure the configuration is valid before writing it
+    let config_json = serde_json::to_string_pretty(&config)
+        .context("Failed to serialize hab-auto-build configuration into JSON")?;
+    let config: AutoBuildConfig = serde_json::from_value(config)
+        .context("Failed to read the new hab-auto-build configuration")?;
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to scan plans with the new configuration"))
+        .with_suggestion(|| "Check the repos, studios and store given to 'hab-auto-build init'")?;
+    let plan_counts = run_context.repo_plan_counts(target);
+
+    std::fs::write(&config_path, config_json).with_context(|| {
+        format!(
+            "Failed to write hab-auto-build configuration to '{}'",
+            config_path.display()
+        )
+    })?;
+    info!(target: "user-log", "Created hab-auto-build configuration at {}", config_path.display());
+    for (repo_id, plan_count) in plan_counts.iter() {
+        if *plan_count == 0 {
+            info!(target: "user-ui", "{}: No {} plans found in repo {}", "warning".bold().yellow(), target, repo_id.blue());
+        } else {
+            info!(target: "user-ui", "{} {} plans found in repo {}", plan_count.magenta(), target, repo_id.blue());
+        }
+    }
+    info!(target: "user-log", "Found {} {} plans across {} repos", plan_counts.values().sum::<usize>(), target, plan_counts.len());
+    info!(target: "user-ui", "Run '{}' for an overview of the workspace", "hab-auto-build summary".blue());
+    Ok(())
+}
+
+/// Detects folders containing habitat plans. Each top level folder containing
+/// plans is considered a repo, if plans are found directly in the given folder
+/// it is considered a repo itself.
+fn repos_detect(path: &Path) -> Vec<(String, PathBuf, usize)> {
+    let mut repos: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    for entry in WalkBuilder::new(path)
+        .max_depth(Some(REPO_DETECTION_DEPTH))
+        .follow_links(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+    {
+        let file_name = entry.file_name();
+        if file_name != "plan.sh" && file_name != "plan.ps1" {
+            continue;
+        }
+        let Ok(relative_path) = entry.path().strip_prefix(path) else {
+            continue;
+        };
+        // Plans in the folder itself, either as 'plan.sh' or 'habitat/plan.sh'
+        let repo_path = if relative_path.parent() == Some(Path::new(""))
+            || relative_path.parent() == Some(Path::new("habitat"))
+        {
+            PathBuf::from(".")
+        } else if let Some(component) = relative_path.components().next() {
+            PathBuf::from(".").join(component)
+        } else {
+            continue;
+        };
+        *repos.entry(repo_path).or_default() += 1;
+    }
+    repos
+        .into_iter()
+        .map(|(repo_path, plan_count)| {
+            let id = if repo_path == Path::new(".") {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "default".to_string())
+            } else {
+                repo_path.file_name().unwrap().to_string_lossy().to_string()
+            };
+            (id, repo_path, plan_count)
+        })
+        .collect()
+}
+
+fn prompt(question: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => print!("{} [{}]: ", question.bold(), default.blue()),
+        None => print!("{}: ", question.bold()),
+    }
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin()
+        .read_line(&mut answer)
+        .context("Failed to read answer from standard input")?;
+    let answer = answer.trim();
+    Ok(match default {
+        Some(default) if answer.is_empty() => default.to_string(),
+        _ => answer.to_string(),
+    })
+}
+
+fn prompt_confirm(question: &str) -> Result<bool> {
+    loop {
+        match prompt(&format!("{} [Y/n]", question), None)?
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => continue,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::{env, path::PathBuf};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::OwoColorize;
+use tracing::{error, info};
+
+use crate::{
+    check::{plan_lint, ViolationLevel},
+    core::{AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepGlob, PackageTarget},
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Package target of the plans to lint, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// List of packages to lint, all plans are linted if none are specified
+    packages: Vec<PackageDepGlob>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let packages = if args.packages.is_empty() {
+        vec![PackageDepGlob::parse("*/*").unwrap()]
+    } else {
+        args.packages.clone()
+    };
+    let package_indices = run_context.glob_deps(&packages, target)?;
+    if package_indices.is_empty() && !run_context.is_empty() && !args.packages.is_empty() {
+        error!(target: "user-log",
+            "No packages found matching patterns: {}",
+            serde_json::to_string(&args.packages).unwrap()
+        );
+        return Ok(());
+    }
+
+    let mut plan_count = 0;
+    let mut plans_with_errors = 0;
+    for package_index in package_indices {
+        let Some(plan_ctx) = run_context.dep(package_index).plan_ctx() else {
+            continue;
+        };
+        let plan_path = plan_ctx.plan_path.as_ref();
+        // Only bash plans are linted
+        if plan_path
+            .extension()
+            .is_some_and(|extension| extension != "sh")
+        {
+            continue;
+        }
+        let plan_source = std::fs::read_to_string(plan_path)
+            .with_context(|| eyre!("Failed to read plan file '{}'", plan_path.display()))?;
+        let plan_dir_name = plan_ctx
+            .context_path
+            .as_ref()
+            .file_name()
+            .and_then(|name| name.to_str());
+        let violations = plan_lint(&plan_ctx.config(), &plan_source, plan_dir_name);
+        plan_count += 1;
+
+        let error_count = violations
+            .iter()
+            .filter(|violation| violation.level == ViolationLevel::Error)
+            .count();
+        let warning_count = violations
+            .iter()
+            .filter(|violation| violation.level == ViolationLevel::Warn)
+            .count();
+        if error_count != 0 {
+            plans_with_errors += 1;
+        }
+        if violations.is_empty() {
+            continue;
+        }
+        info!(target: "user-ui", "{}: {} errors, {} warnings {}", plan_ctx.id.to_string().white(), error_count.red().bold(), warning_count.yellow().bold(), plan_path.display().bright_black());
+        for violation in violations.iter() {
+            info!(target: "user-ui", "     {}", violation);
+        }
+    }
+    info!(target: "user-log", "Linted {} plans, {} have errors", plan_count.blue(), plans_with_errors.blue());
+    if plans_with_errors != 0 {
+        return Err(eyre!("Found errors in {} plans", plans_with_errors));
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::{env, path::PathBuf};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::core::{
+    dependency_lock_path, AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, DependencyLock,
+    DependencyLockDifference,
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let (dependency_lock, unresolved) = run_context.dependency_lock();
+    if !unresolved.is_empty() {
+        for dependency in unresolved.iter() {
+            info!(target: "user-ui", "{} {}", "      Missing".red().bold(), dependency);
+        }
+        return Err(eyre!(
+            "Found {} dependencies without an artifact in the artifact cache",
+            unresolved.len()
+        ))
+        .with_suggestion(|| {
+            "Install the missing dependencies into the artifact cache with 'hab pkg install'"
+        });
+    }
+
+    let lock_path = dependency_lock_path(&config_path);
+    let previous_lock = if lock_path.is_file() {
+        DependencyLock::read(&lock_path)?
+    } else {
+        DependencyLock::default()
+    };
+    for difference in previous_lock.differences(&dependency_lock, &[]) {
+        output_difference(&difference);
+    }
+    dependency_lock.write(&lock_path)?;
+    info!(target: "user-log", "Locked {} dependencies in {}", dependency_lock.dependencies.len(), lock_path.display());
+    Ok(())
+}
+
+pub(crate) fn output_difference(difference: &DependencyLockDifference) {
+    match difference {
+        DependencyLockDifference::Changed {
+            dependency,
+            locked,
+            current,
+        } => {
+            info!(target: "user-ui", "{} {}: {} -> {}", "      Changed".yellow().bold(), dependency, locked, current);
+        }
+        DependencyLockDifference::Added {
+            dependency,
+            current: Some(current),
+        } => {
+            info!(target: "user-ui", "{} {}: {}", "        Added".green().bold(), dependency, current);
+        }
+        DependencyLockDifference::Added {
+            dependency,
+            current: None,
+        } => {
+            info!(target: "user-ui", "{} {}", "        Added".green().bold(), dependency);
+        }
+        DependencyLockDifference::Removed { dependency, locked } => {
+            info!(target: "user-ui", "{} {}: {}", "      Removed".red().bold(), dependency, locked);
+        }
+        DependencyLockDifference::Unresolved { dependency, locked } => {
+            info!(target: "user-ui", "{} {}: {}", "      Missing".red().bold(), dependency, locked);
+        }
+    }
+}
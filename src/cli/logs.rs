@@ -0,0 +1,114 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::core::{
+    AutoBuildConfig, AutoBuildContext, BuildLogKind, ChangeDetectionMode, PackageDepIdent,
+    PackageTarget,
+};
+
+/// Interval at which the log of a running build is checked for new output
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Package target of the build log, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// Keep printing the log of a build in progress until the build completes
+    #[arg(short, long, conflicts_with = "failed")]
+    follow: bool,
+    /// Show the latest failed build log instead of the latest build log
+    #[arg(long)]
+    failed: bool,
+    /// Package whose build log is shown, as <ORIGIN>/<NAME>[/<VERSION>[/<RELEASE>]]
+    package: PackageDepIdent,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let build_logs = run_context.build_logs(&args.package, target)?;
+    let Some(build_log) = build_logs
+        .iter()
+        .find(|build_log| !args.failed || build_log.kind == BuildLogKind::Failure)
+    else {
+        return Err(eyre!(
+            "No {}build logs found for '{}' with target '{}'",
+            if args.failed { "failed " } else { "" },
+            args.package,
+            target
+        ))
+        .with_suggestion(|| "Build the package with 'hab-auto-build build' to create a build log");
+    };
+
+    info!(target: "user-log", "Showing {} log of {} from {} at {}", build_log.kind, args.package, build_log.path.display().blue(), build_log.modified_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    if args.follow && build_log.kind == BuildLogKind::Running {
+        log_follow(&build_log.path)?;
+        // The log is moved out of the build folder once the build completes
+        if let Some(final_build_log) = run_context
+            .build_logs(&args.package, target)?
+            .iter()
+            .find(|build_log| build_log.kind != BuildLogKind::Running)
+        {
+            info!(target: "user-log", "Build completed, {} log saved to {}", final_build_log.kind, final_build_log.path.display().blue());
+        }
+    } else {
+        let mut log_file = File::open(&build_log.path)
+            .with_context(|| eyre!("Failed to open build log '{}'", build_log.path.display()))?;
+        io::copy(&mut log_file, &mut io::stdout().lock())
+            .with_context(|| eyre!("Failed to read build log '{}'", build_log.path.display()))?;
+        if args.follow {
+            info!(target: "user-log", "No build of {} is in progress", args.package);
+        }
+    }
+    Ok(())
+}
+
+/// Prints the log of a running build as it is written, until the build folder is
+/// removed. The open file remains readable after the log is moved, so the last
+/// output of the build is not lost.
+fn log_follow(build_log_path: &Path) -> Result<()> {
+    let mut log_file = File::open(build_log_path)
+        .with_context(|| eyre!("Failed to open build log '{}'", build_log_path.display()))?;
+    let mut stdout = io::stdout().lock();
+    let mut buffer = Vec::new();
+    loop {
+        let is_running = build_log_path.is_file();
+        buffer.clear();
+        log_file
+            .read_to_end(&mut buffer)
+            .with_context(|| eyre!("Failed to read build log '{}'", build_log_path.display()))?;
+        stdout.write_all(&buffer)?;
+        stdout.flush()?;
+        if !is_running {
+            return Ok(());
+        }
+        thread::sleep(FOLLOW_POLL_INTERVAL);
+    }
+}
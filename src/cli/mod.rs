@@ -1,22 +1,60 @@
 mod add;
 mod analyze;
+mod annotate;
+mod audit;
 mod build;
 mod changes;
 mod check;
+mod clean;
 mod compare;
 mod download;
+mod export;
+mod files;
 mod git_sync;
+mod history;
+mod init;
+mod lint;
+mod lock;
+mod logs;
+mod new_plan;
+mod outdated;
 mod output;
+mod plan;
+mod promote;
 mod remove;
+mod sbom;
+mod schema;
 mod server;
+mod store;
+mod summary;
+mod timestamps;
+mod tui;
+mod unyank;
+mod update_repos;
+mod verify_sources;
+mod yank;
 
-use clap::{command, Parser, Subcommand};
-use color_eyre::eyre::Result;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use color_eyre::eyre::{Context, Result};
+
+use crate::core::{AutoBuildConfig, AutoBuildConfigOverrides};
+
+pub(crate) use tui::UserOutput;
 
 // Habitat Auto Build allows you to automatically build multiple packages
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
+    /// Path to the hab-auto-build store, overriding the one in the configuration.
+    /// A store can be safely shared by multiple workspaces
+    #[arg(long, value_name = "STORE_PATH")]
+    store: Option<PathBuf>,
+    /// Name of the workspace used to namespace records in the store, defaults to
+    /// one derived from the configuration path
+    #[arg(long)]
+    workspace: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -25,40 +63,129 @@ pub struct Cli {
 enum Commands {
     /// Analyze various types of dependencies of a set of packages
     Analyze(analyze::Params),
+    /// Check the versions of a set of plans for known vulnerabilities
+    Audit(audit::Params),
     /// Build a set of packages
     Build(build::Params),
     /// Check a set of packages
     Check(check::Params),
     /// Check the current list of changes across all repos
     Changes(changes::Params),
-    /// Compare plans across two sets of repos
+    /// Remove unused data from the hab-auto-build store
+    Clean(clean::Params),
+    /// Compare plans across two sets of repos, or the contents of two artifacts
     Compare(compare::Params),
     /// Download source archives for specified plans
     Download(download::Params),
+    /// Export built packages to other formats
+    Export(export::Params),
+    /// List the files of the latest artifacts of a set of packages, with their sizes and modes
+    Files(files::Params),
+    /// List past build sessions or show the details of one of them
+    History(history::Params),
+    /// Create a hab-auto-build configuration for the current folder
+    Init(init::Params),
+    /// Lint the plan files of a set of packages without building them
+    Lint(lint::Params),
+    /// Pin the remote dependencies of the build graph to the artifacts they currently resolve to
+    Lock(lock::Params),
+    /// Print or follow the latest build log of a plan
+    Logs(logs::Params),
+    /// Scaffold a new plan in one of the repos from a template
+    NewPlan(new_plan::Params),
+    /// Check the sources of a set of plans for newer upstream versions
+    Outdated(outdated::Params),
+    /// Output the build order of a set of packages as stages of plans that can be built in parallel
+    Plan(plan::Params),
     /// Add a plan from the list of changed plans
     Add(add::Params),
+    /// Promote packages built in the last build session to a Builder channel
+    Promote(promote::Params),
     /// Remove a plan from the list of changed plans
     Remove(remove::Params),
+    /// Generate software bills of materials for a set of packages
+    Sbom(sbom::Params),
+    /// Print the JSON schema of a machine-readable output
+    Schema(schema::Params),
     /// Sync plan file timestamps with git commit timestamps
     GitSync(git_sync::Params),
     /// Start a server to visualize the package build graph
     Server(server::Params),
+    /// Export, import or repair the hab-auto-build store
+    Store(store::Params),
+    /// Summarize the repos and plans of the workspace
+    Summary(summary::Params),
+    /// Inspect or clear the file modification times recorded for plans by the 'add' and 'remove' commands
+    Timestamps(timestamps::Params),
+    /// Verify the integrity of the source archives in the hab-auto-build store
+    VerifySources(verify_sources::Params),
+    /// Exclude a broken artifact from dependency resolution without deleting it, or list yanked artifacts
+    Yank(yank::Params),
+    /// Make a yanked artifact available for dependency resolution again
+    Unyank(unyank::Params),
+    /// Clone or update the repos that are configured with a git remote
+    UpdateRepos(update_repos::Params),
 }
 
 impl Cli {
     pub fn run() -> Result<()> {
         let cli = Cli::parse();
+        let store = match cli.store {
+            Some(store) if store.is_relative() => Some(
+                std::env::current_dir()
+                    .context("Failed to determine current working directory")?
+                    .join(store),
+            ),
+            store => store,
+        };
+        AutoBuildConfig::overrides_set(AutoBuildConfigOverrides {
+            store,
+            workspace: cli.workspace,
+        });
         match cli.command {
             Commands::Add(args) => add::execute(args),
             Commands::Changes(args) => changes::execute(args),
             Commands::Check(args) => check::execute(args),
+            Commands::Clean(args) => clean::execute(args),
             Commands::Compare(args) => compare::execute(args),
             Commands::Download(args) => download::execute(args),
+            Commands::Export(args) => export::execute(args),
+            Commands::Files(args) => files::execute(args),
             Commands::GitSync(args) => git_sync::execute(args),
+            Commands::History(args) => history::execute(args),
+            Commands::Init(args) => init::execute(args),
+            Commands::Lint(args) => lint::execute(args),
+            Commands::Lock(args) => lock::execute(args),
+            Commands::Logs(args) => logs::execute(args),
+            Commands::NewPlan(args) => new_plan::execute(args),
+            Commands::Outdated(args) => outdated::execute(args),
+            Commands::Plan(args) => plan::execute(args),
+            Commands::Promote(args) => promote::execute(args),
             Commands::Remove(args) => remove::execute(args),
+            Commands::Sbom(args) => sbom::execute(args),
+            Commands::Schema(args) => schema::execute(args),
             Commands::Build(args) => build::execute(args),
             Commands::Analyze(args) => analyze::execute(args),
+            Commands::Audit(args) => audit::execute(args),
             Commands::Server(args) => server::execute(args),
+            Commands::Store(args) => store::execute(args),
+            Commands::Summary(args) => summary::execute(args),
+            Commands::Timestamps(args) => timestamps::execute(args),
+            Commands::VerifySources(args) => verify_sources::execute(args),
+            Commands::Yank(args) => yank::execute(args),
+            Commands::Unyank(args) => unyank::execute(args),
+            Commands::UpdateRepos(args) => update_repos::execute(args),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition() {
+        Cli::command().debug_assert();
+    }
+}
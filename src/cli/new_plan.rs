@@ -0,0 +1,427 @@
+use std::{
+    env,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, ValueEnum};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::{info, warn};
+
+use crate::core::{
+    AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepIdent, PackageName,
+    PackageOrigin, PackageRelease, PackageVersion, RepoContext,
+};
+
+/// Version given to the new plan when none is specified
+const DEFAULT_PLAN_VERSION: &str = "0.1.0";
+
+/// Origin used for dependencies that have no plan in the workspace
+const DEFAULT_DEPENDENCY_ORIGIN: &str = "core";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum PlanTemplate {
+    /// Software built with './configure && make'
+    Autotools,
+    /// Software built with CMake
+    Cmake,
+    /// Rust software built with Cargo
+    Cargo,
+    /// Go software built with 'go build'
+    Go,
+    /// Prebuilt binaries patched to use the workspace's glibc
+    Binary,
+}
+
+impl PlanTemplate {
+    fn deps(&self) -> &'static [&'static str] {
+        match self {
+            PlanTemplate::Autotools | PlanTemplate::Go | PlanTemplate::Binary => &["glibc"],
+            PlanTemplate::Cmake | PlanTemplate::Cargo => &["glibc", "gcc-libs"],
+        }
+    }
+
+    fn build_deps(&self) -> &'static [&'static str] {
+        match self {
+            PlanTemplate::Autotools => &["gcc", "make"],
+            PlanTemplate::Cmake => &["cmake", "gcc", "make"],
+            PlanTemplate::Cargo => &["rust"],
+            PlanTemplate::Go => &["go"],
+            PlanTemplate::Binary => &["patchelf"],
+        }
+    }
+
+    fn dirs(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            PlanTemplate::Autotools | PlanTemplate::Cmake => &[
+                ("pkg_bin_dirs", "bin"),
+                ("pkg_include_dirs", "include"),
+                ("pkg_lib_dirs", "lib"),
+            ],
+            PlanTemplate::Cargo | PlanTemplate::Go | PlanTemplate::Binary => {
+                &[("pkg_bin_dirs", "bin")]
+            }
+        }
+    }
+
+    fn callbacks(&self) -> &'static str {
+        match self {
+            PlanTemplate::Autotools => {
+                r#"
+do_check() {
+	make check
+}
+"#
+            }
+            PlanTemplate::Cmake => {
+                r#"
+do_build() {
+	cmake \
+		-S . \
+		-B build \
+		-DCMAKE_BUILD_TYPE=Release \
+		-DCMAKE_INSTALL_PREFIX="${pkg_prefix}"
+	cmake --build build --parallel "$(nproc)"
+}
+
+do_install() {
+	cmake --install build
+}
+"#
+            }
+            PlanTemplate::Cargo => {
+                r#"
+do_build() {
+	cargo build --release --locked
+}
+
+do_install() {
+	install -Dm755 "target/release/${pkg_name}" "${pkg_prefix}/bin/${pkg_name}"
+}
+"#
+            }
+            PlanTemplate::Go => {
+                r#"
+do_build() {
+	go build -o "bin/${pkg_name}" .
+}
+
+do_install() {
+	install -Dm755 "bin/${pkg_name}" "${pkg_prefix}/bin/${pkg_name}"
+}
+"#
+            }
+            PlanTemplate::Binary => {
+                r#"
+do_build() {
+	return 0
+}
+
+do_install() {
+	install -Dm755 "${pkg_name}" "${pkg_prefix}/bin/${pkg_name}"
+	patchelf \
+		--set-interpreter "$(pkg_path_for glibc)/lib/ld-linux-x86-64.so.2" \
+		--set-rpath "${LD_RUN_PATH}" \
+		"${pkg_prefix}/bin/${pkg_name}"
+}
+"#
+            }
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Template used for the build callbacks and dependencies of the plan
+    #[arg(value_enum, short, long)]
+    template: PlanTemplate,
+    /// Repo in which the plan is created, required if there are multiple repos
+    #[arg(short, long)]
+    repo: Option<String>,
+    /// Folder of the plan relative to the repo, defaults to the package name
+    #[arg(short, long)]
+    path: Option<PathBuf>,
+    /// Also create a default.toml and a run hook for a package that runs as a service
+    #[arg(long)]
+    service: bool,
+    /// Overwrite the files of an existing plan
+    #[arg(short, long)]
+    force: bool,
+    /// Package of the new plan, specified as <ORIGIN>/<NAME> or <ORIGIN>/<NAME>/<VERSION>
+    package: PackageDepIdent,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    if let PackageRelease::Resolved(_) = args.package.release {
+        return Err(eyre!(
+            "Invalid package '{}', a new plan cannot have a release",
+            args.package
+        ))
+        .with_suggestion(|| "Specify the package as <ORIGIN>/<NAME>/<VERSION>");
+    }
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let repos = run_context.repos();
+    let repo_ctx = match args.repo.as_ref() {
+        Some(repo_id) => repos
+            .iter()
+            .find(|repo_ctx| repo_ctx.id.to_string() == *repo_id)
+            .copied(),
+        None if repos.len() == 1 => repos.first().copied(),
+        None => None,
+    };
+    let Some(repo_ctx) = repo_ctx else {
+        let repo_ids = repos
+            .iter()
+            .map(|repo_ctx| repo_ctx.id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(match args.repo.as_ref() {
+            Some(repo_id) => eyre!("No repo with the id '{}' is configured", repo_id),
+            None => eyre!("The repo in which to create the plan must be specified"),
+        })
+        .with_suggestion(|| format!("Use '--repo' with one of: {}", repo_ids));
+    };
+
+    let relative_plan_path = args
+        .path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(args.package.name.to_string()));
+    if relative_plan_path.is_absolute() {
+        return Err(eyre!(
+            "Invalid plan folder '{}', it must be relative to the repo",
+            relative_plan_path.display()
+        ));
+    }
+    let plan_path = repo_ctx.path.as_ref().join(&relative_plan_path);
+    if plan_path.join("plan.sh").exists() && !args.force {
+        return Err(eyre!(
+            "A plan already exists at '{}'",
+            plan_path.join("plan.sh").display()
+        ))
+        .with_suggestion(|| "Use '--force' to overwrite the existing plan");
+    }
+
+    let (deps, unresolved_deps) = resolve_deps(&run_context, &args.package, args.template);
+    let is_native = repo_ctx.native_globset.is_match(&relative_plan_path);
+
+    let mut files = vec![
+        (
+            PathBuf::from("plan.sh"),
+            plan_file(&args.package, args.template, &deps, args.service),
+        ),
+        (
+            PathBuf::from(".hab-plan-config.toml"),
+            plan_config_file(&config, repo_ctx, is_native),
+        ),
+    ];
+    if args.service {
+        files.push((PathBuf::from("default.toml"), default_config_file()));
+        files.push((PathBuf::from("hooks").join("run"), run_hook_file()));
+    }
+    for (file_path, contents) in files.iter() {
+        write_plan_file(
+            &plan_path.join(file_path),
+            contents,
+            file_path.starts_with("hooks"),
+        )?;
+        info!(target: "user-ui", "{} {}", "Created".green().bold(), plan_path.join(file_path).display());
+    }
+    for dep_name in unresolved_deps.iter() {
+        warn!(target: "user-log", "No plan for the dependency '{}' was found in the workspace, using '{}/{}'", dep_name, DEFAULT_DEPENDENCY_ORIGIN, dep_name);
+    }
+    info!(
+        target: "user-log",
+        "Created the {} plan {} in repo {}, it will be built {}",
+        args.template.to_possible_value().unwrap().get_name(),
+        format!("{}/{}", args.package.origin, args.package.name).white().bold(),
+        repo_ctx.id,
+        if is_native {
+            "natively".to_string()
+        } else {
+            format!("in the standard studio {}", config.studios.standard)
+        }
+    );
+    Ok(())
+}
+
+/// Picks the origin of each dependency of the template from the plans of the
+/// workspace, preferring the origin of the new plan and then the default origin.
+/// Also returns the dependencies that have no plan in the workspace.
+fn resolve_deps(
+    run_context: &AutoBuildContext,
+    package: &PackageDepIdent,
+    template: PlanTemplate,
+) -> (Vec<(bool, PackageDepIdent)>, Vec<&'static str>) {
+    let default_origin = PackageOrigin::parse(DEFAULT_DEPENDENCY_ORIGIN).unwrap();
+    let mut deps = Vec::new();
+    let mut unresolved_deps = Vec::new();
+    for (is_build_dep, dep_name) in template
+        .deps()
+        .iter()
+        .map(|dep_name| (false, *dep_name))
+        .chain(
+            template
+                .build_deps()
+                .iter()
+                .map(|dep_name| (true, *dep_name)),
+        )
+    {
+        let name = PackageName::parse(dep_name).unwrap();
+        let origins = run_context.plan_origins(&name);
+        let origin = if origins.contains(&package.origin) {
+            package.origin.clone()
+        } else if origins.contains(&default_origin) {
+            default_origin.clone()
+        } else if let Some(origin) = origins.first() {
+            (*origin).clone()
+        } else {
+            if !unresolved_deps.contains(&dep_name) {
+                unresolved_deps.push(dep_name);
+            }
+            default_origin.clone()
+        };
+        deps.push((
+            is_build_dep,
+            PackageDepIdent {
+                origin,
+                name,
+                version: PackageVersion::Unresolved,
+                release: PackageRelease::Unresolved,
+            },
+        ));
+    }
+    (deps, unresolved_deps)
+}
+
+fn plan_file(
+    package: &PackageDepIdent,
+    template: PlanTemplate,
+    deps: &[(bool, PackageDepIdent)],
+    service: bool,
+) -> String {
+    let version = match &package.version {
+        PackageVersion::Resolved(version) => version.to_string(),
+        PackageVersion::Unresolved => DEFAULT_PLAN_VERSION.to_string(),
+    };
+    let mut plan = String::new();
+    writeln!(plan, "pkg_name=\"{}\"", package.name).unwrap();
+    writeln!(plan, "pkg_origin=\"{}\"", package.origin).unwrap();
+    writeln!(plan, "pkg_version=\"{}\"", version).unwrap();
+    writeln!(
+        plan,
+        "pkg_maintainer=\"The Habitat Maintainers <humans@habitat.sh>\""
+    )
+    .unwrap();
+    writeln!(plan, "pkg_description=\"TODO: Describe the package\"").unwrap();
+    writeln!(
+        plan,
+        "pkg_upstream_url=\"TODO: Set the project's homepage\""
+    )
+    .unwrap();
+    writeln!(plan, "pkg_license=()").unwrap();
+    writeln!(
+        plan,
+        "# TODO: Set the url of the source archive and its sha256 checksum"
+    )
+    .unwrap();
+    writeln!(
+        plan,
+        "pkg_source=\"https://example.com/${{pkg_name}}-${{pkg_version}}.tar.gz\""
+    )
+    .unwrap();
+    writeln!(plan, "pkg_shasum=\"\"").unwrap();
+    for (variable, is_build_dep) in [("pkg_deps", false), ("pkg_build_deps", true)] {
+        writeln!(plan, "{}=(", variable).unwrap();
+        for (_, dep_ident) in deps.iter().filter(|(dep, _)| *dep == is_build_dep) {
+            writeln!(plan, "\t{}", dep_ident).unwrap();
+        }
+        writeln!(plan, ")").unwrap();
+    }
+    for (variable, dir) in template.dirs() {
+        writeln!(plan, "{}=({})", variable, dir).unwrap();
+    }
+    if service {
+        writeln!(plan, "pkg_svc_user=\"hab\"").unwrap();
+        writeln!(plan, "pkg_svc_group=\"${{pkg_svc_user}}\"").unwrap();
+    }
+    plan.push_str(template.callbacks());
+    plan
+}
+
+fn plan_config_file(config: &AutoBuildConfig, repo_ctx: &RepoContext, is_native: bool) -> String {
+    let mut plan_config = String::new();
+    if is_native {
+        writeln!(
+            plan_config,
+            "# This plan matches the native packages of the '{}' repo and is built without a studio",
+            repo_ctx.id
+        )
+        .unwrap();
+        writeln!(
+            plan_config,
+            "# Build the plan in a Docker container instead of on the host"
+        )
+        .unwrap();
+        writeln!(plan_config, "# docker-image = \"ubuntu:22.04\"").unwrap();
+    } else {
+        writeln!(
+            plan_config,
+            "# This plan is built in the standard studio {}",
+            config.studios.standard
+        )
+        .unwrap();
+    }
+    writeln!(
+        plan_config,
+        "# Time limit of the build and number of retries"
+    )
+    .unwrap();
+    writeln!(plan_config, "# timeout-secs = 3600").unwrap();
+    writeln!(plan_config, "# retries = 1").unwrap();
+    writeln!(plan_config).unwrap();
+    writeln!(plan_config, "[rules]").unwrap();
+    plan_config
+}
+
+fn default_config_file() -> String {
+    "# Default configuration of the service, available to hooks as {{cfg.<key>}}\n".to_string()
+}
+
+fn run_hook_file() -> String {
+    "#!/bin/sh\n\nexec 2>&1\nexec {{pkg.name}}\n".to_string()
+}
+
+fn write_plan_file(path: &Path, contents: &str, is_executable: bool) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| eyre!("Failed to create folder '{}'", parent.display()))?;
+    }
+    std::fs::write(path, contents)
+        .with_context(|| eyre!("Failed to write plan file '{}'", path.display()))?;
+    #[cfg(unix)]
+    if is_executable {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| eyre!("Failed to make '{}' executable", path.display()))?;
+    }
+    #[cfg(not(unix))]
+    let _ = is_executable;
+    Ok(())
+}
@@ -0,0 +1,280 @@
+use std::{env, fmt::Write, path::PathBuf};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::OwoColorize;
+use rayon::prelude::*;
+use tracing::{error, info};
+
+use crate::{
+    check::{line_diff, variable_replace, variable_set},
+    cli::output::{self, JsonOutputKind, OutdatedOutput, PlanOutput, ReportFormat},
+    core::{
+        latest_version, AutoBuildConfig, AutoBuildContext, ChangeDetectionMode,
+        PackageBuildVersion, PackageDepGlob, PackageSourceURL, PackageTarget, PlanContext,
+        UpdateCheckMethod, UpdateChecker,
+    },
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Output format
+    #[arg(value_enum, short = 'f', long, default_value_t = ReportFormat::Plain)]
+    format: ReportFormat,
+    /// Package target of the plans to check, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// Number of plans checked concurrently
+    #[arg(short = 'j', long, default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: u16,
+    /// Update the version and shasum of outdated plans, downloading their new sources
+    #[arg(long)]
+    bump: bool,
+    /// List of packages to check for updates, all plans are checked if none are specified
+    packages: Vec<PackageDepGlob>,
+}
+
+/// Result of looking for a newer upstream version of a plan's source
+struct PlanUpdateCheck<'a> {
+    plan_ctx: &'a PlanContext,
+    version: String,
+    method: Option<UpdateCheckMethod>,
+    result: Result<Option<String>>,
+    bump: Option<Result<()>>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let packages = if args.packages.is_empty() {
+        vec![PackageDepGlob::parse("*/*").unwrap()]
+    } else {
+        args.packages.clone()
+    };
+    let package_indices = run_context.glob_deps(&packages, target)?;
+    if package_indices.is_empty() && !run_context.is_empty() && !args.packages.is_empty() {
+        error!(target: "user-log",
+            "No packages found matching patterns: {}",
+            serde_json::to_string(&args.packages).unwrap()
+        );
+        return Ok(());
+    }
+
+    // Only plans with a source and a static version can be checked
+    let plans = package_indices
+        .iter()
+        .filter_map(|package_index| run_context.dep(*package_index).plan_ctx())
+        .filter_map(
+            |plan_ctx| match (&plan_ctx.source, &plan_ctx.id.as_ref().version) {
+                (Some(source), PackageBuildVersion::Static(version)) => {
+                    Some((plan_ctx, &source.url, version.to_string()))
+                }
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let checker = UpdateChecker::new()?;
+    let check_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.concurrency as usize)
+        .build()
+        .context("Failed to create update check thread pool")?;
+    let mut checks = check_pool.install(|| {
+        plans
+            .par_iter()
+            .map(|(plan_ctx, source_url, version)| {
+                let method = UpdateCheckMethod::new(
+                    source_url,
+                    version,
+                    plan_ctx
+                        .plan_config
+                        .as_ref()
+                        .and_then(|plan_config| plan_config.update.as_ref()),
+                );
+                let (method, result) = match method {
+                    Ok(method) => {
+                        let result = checker
+                            .versions(&method)
+                            .map(|versions| latest_version(version, versions.iter()).cloned());
+                        (Some(method), result)
+                    }
+                    Err(err) => (None, Err(err)),
+                };
+                PlanUpdateCheck {
+                    plan_ctx,
+                    version: version.clone(),
+                    method,
+                    result,
+                    bump: None,
+                }
+            })
+            .collect::<Vec<_>>()
+    });
+    checks.sort_by_key(|check| check.plan_ctx.id.to_string());
+
+    if args.bump {
+        for check in checks.iter_mut() {
+            if let (Some(source), Ok(Some(latest_version))) =
+                (check.plan_ctx.source.as_ref(), check.result.as_ref())
+            {
+                check.bump = Some(plan_bump(
+                    &run_context,
+                    check.plan_ctx,
+                    &source.url,
+                    &check.version,
+                    latest_version,
+                    args.format == ReportFormat::Plain,
+                ));
+            }
+        }
+    }
+
+    match args.format {
+        ReportFormat::Plain => output_plain(&checks),
+        ReportFormat::Json => output::output_json(
+            JsonOutputKind::Outdated,
+            checks
+                .iter()
+                .map(|check| OutdatedOutput {
+                    plan: PlanOutput::from(check.plan_ctx),
+                    version: check.version.clone(),
+                    latest_version: check.result.as_ref().ok().cloned().flatten(),
+                    checked: check.method.as_ref().map(|method| method.to_string()),
+                    error: match (&check.result, &check.bump) {
+                        (Err(err), _) | (_, Some(Err(err))) => Some(format!("{:#}", err)),
+                        _ => None,
+                    },
+                    bumped: matches!(check.bump, Some(Ok(_))),
+                })
+                .collect::<Vec<_>>(),
+        ),
+        ReportFormat::Markdown => output_markdown(&checks),
+    }
+}
+
+/// Updates the version and shasum of the plan to the latest version, the source
+/// of the latest version is downloaded into the store to compute its shasum.
+fn plan_bump(
+    run_context: &AutoBuildContext,
+    plan_ctx: &PlanContext,
+    source_url: &PackageSourceURL,
+    version: &str,
+    latest_version: &str,
+    show_diff: bool,
+) -> Result<()> {
+    let latest_source_url = source_url.to_string().replace(version, latest_version);
+    if latest_source_url == source_url.to_string() {
+        return Err(eyre!(
+            "The source url '{}' does not contain the version '{}'",
+            source_url,
+            version
+        ));
+    }
+    let latest_source_url = PackageSourceURL::parse(latest_source_url)?;
+    let shasum = run_context.source_archive_add(&latest_source_url)?;
+
+    let plan_path = plan_ctx.plan_path.as_ref();
+    let plan_source = std::fs::read_to_string(plan_path)
+        .with_context(|| eyre!("Failed to read plan file '{}'", plan_path.display()))?;
+    let mut bumped_plan_source = variable_set(&plan_source, "pkg_version", latest_version)
+        .ok_or_else(|| eyre!("Failed to find 'pkg_version' in the plan, update it manually"))?;
+    // Sources that do not use '${pkg_version}' contain the version itself
+    if let Some(source) =
+        variable_replace(&bumped_plan_source, "pkg_source", version, latest_version)
+    {
+        bumped_plan_source = source;
+    }
+    bumped_plan_source = variable_set(&bumped_plan_source, "pkg_shasum", &shasum.to_string())
+        .ok_or_else(|| eyre!("Failed to find 'pkg_shasum' in the plan, update it manually"))?;
+    std::fs::write(plan_path, &bumped_plan_source)
+        .with_context(|| eyre!("Failed to write plan file '{}'", plan_path.display()))?;
+    if show_diff {
+        info!(target: "user-ui", "{} {}:", "Bumped".bold().green(), plan_path.display().blue());
+        for line in line_diff(&plan_source, &bumped_plan_source).lines() {
+            info!(target: "user-ui", "  {}", line);
+        }
+    }
+    Ok(())
+}
+
+fn output_plain(checks: &[PlanUpdateCheck]) -> Result<()> {
+    let mut update_count = 0;
+    let mut error_count = 0;
+    for check in checks.iter() {
+        match &check.result {
+            Ok(Some(latest_version)) => {
+                update_count += 1;
+                info!(
+                    target: "user-ui",
+                    "{}: {} -> {} ({})",
+                    check.plan_ctx.id,
+                    check.version.yellow(),
+                    latest_version.green(),
+                    check.method.as_ref().map(|method| method.to_string()).unwrap_or_default().bright_black()
+                );
+            }
+            Ok(None) => {}
+            Err(err) => {
+                error_count += 1;
+                info!(target: "user-ui", "{}: Failed to check {} for updates: {:#}", "error".bold().red(), check.plan_ctx.id, err);
+            }
+        }
+        if let Some(Err(err)) = &check.bump {
+            info!(target: "user-ui", "{}: Failed to bump {}: {:#}", "error".bold().red(), check.plan_ctx.id, err);
+        }
+    }
+    info!(
+        target: "user-log",
+        "Checked {} plans for updates, {} have updates available, {} could not be checked",
+        checks.len().blue(),
+        update_count.blue(),
+        error_count.blue()
+    );
+    Ok(())
+}
+
+fn output_markdown(checks: &[PlanUpdateCheck]) -> Result<()> {
+    let mut output = String::new();
+    writeln!(output, "| Plan | Version | Latest Version | Checked |")?;
+    writeln!(output, "| --- | --- | --- | --- |")?;
+    for check in checks.iter() {
+        if let Ok(Some(latest_version)) = &check.result {
+            writeln!(
+                output,
+                "| {} | {} | {} | {} |",
+                check.plan_ctx.id,
+                check.version,
+                latest_version,
+                check
+                    .method
+                    .as_ref()
+                    .map(|method| method.to_string())
+                    .unwrap_or_default()
+            )?;
+        }
+    }
+    let failed_checks = checks
+        .iter()
+        .filter_map(|check| check.result.as_ref().err().map(|err| (check, err)))
+        .collect::<Vec<_>>();
+    if !failed_checks.is_empty() {
+        writeln!(output, "\n## Plans that could not be checked\n")?;
+        for (check, err) in failed_checks {
+            writeln!(output, "- {}: {:#}", check.plan_ctx.id, err)?;
+        }
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
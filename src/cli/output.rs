@@ -1,7 +1,542 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use clap::ValueEnum;
+use color_eyre::eyre::{Context, Result};
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::Serialize;
+use tracing::info;
+
+use crate::core::{
+    ArtifactDiff, ArtifactFile, BuildHostMetadata, DependencyType, PlanContext, Vulnerability,
+};
+
+/// Version of the JSON outputs, it must be incremented whenever a field of an
+/// output is removed, renamed or changes meaning. Adding fields does not require
+/// a new version.
+pub(crate) const JSON_SCHEMA_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub(crate) enum OutputFormat {
     Plain,
     Json,
 }
+
+/// Output formats of the commands producing reports, which can also be rendered
+/// as Markdown to be posted on pull requests or wikis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ReportFormat {
+    Plain,
+    Json,
+    Markdown,
+}
+
+/// Kinds of machine-readable outputs, each output is wrapped in a `JsonOutput`
+/// envelope that identifies its kind and the version of its schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum JsonOutputKind {
+    /// Dependency analysis of packages, output by 'analyze -f json'
+    Analysis,
+    /// Plans grouped into build layers, output by 'analyze --layers -f json'
+    Layers,
+    /// Dependency cycles between plans, output by 'analyze --cycles -f json'
+    Cycles,
+    /// Paths causing plans to be rebuilt, output by 'analyze --why <package> -f json'
+    RebuildPaths,
+    /// Progress of a cooperative build, output one per line by 'build --cooperative -f json'
+    BuildEvent,
+    /// Plans sharing a source suspiciously, output by 'analyze --duplicate-sources -f json'
+    DuplicateSources,
+    /// Binds and exports of the services of plans, output by 'analyze --binds -f json'
+    ServiceComposition,
+    /// Installed sizes of packages and their closures, output by 'analyze --size <package> -f json'
+    PackageSizes,
+    /// Overview of the repos and plans of the workspace, output by 'summary -f json'
+    Summary,
+    /// Newer upstream versions of the sources of plans, output by 'outdated -f json'
+    Outdated,
+    /// Known vulnerabilities of the versions of plans, output by 'audit -f json'
+    Audit,
+    /// Past build sessions, output by 'history -f json'
+    History,
+    /// Details of a past build session, output by 'history show <id> -f json'
+    HistorySession,
+    /// Differences between two artifacts, output by 'compare <SOURCE> <TARGET> -f json'
+    ArtifactDiff,
+    /// Differences between the dependency graphs of two commits, output by
+    /// 'compare --from <REF> --to <REF> -f json'
+    GraphDiff,
+    /// Versions of the plans of two configurations, output by
+    /// 'compare -s <CONFIG> -t <CONFIG> -f json'
+    PlanDiff,
+    /// Files of artifacts, output by 'files -f json'
+    ArtifactFiles,
+    /// Dependency graph of packages, output by 'analyze --emit json'
+    Graph,
+    /// Build order grouped into stages of parallel builds, output by 'plan -f json'
+    BuildStages,
+}
+
+impl JsonOutputKind {
+    /// JSON schema of the output, including its envelope
+    pub fn schema(&self) -> RootSchema {
+        match self {
+            JsonOutputKind::Analysis => schema_for!(JsonOutput<Vec<AnalysisOutput>>),
+            JsonOutputKind::Layers => schema_for!(JsonOutput<Vec<LayerOutput>>),
+            JsonOutputKind::Cycles => schema_for!(JsonOutput<Vec<CycleOutput>>),
+            JsonOutputKind::RebuildPaths => schema_for!(JsonOutput<Vec<RebuildPathsOutput>>),
+            JsonOutputKind::BuildEvent => schema_for!(JsonOutput<BuildEventOutput>),
+            JsonOutputKind::DuplicateSources => {
+                schema_for!(JsonOutput<Vec<DuplicateSourceOutput>>)
+            }
+            JsonOutputKind::ServiceComposition => {
+                schema_for!(JsonOutput<ServiceCompositionOutput>)
+            }
+            JsonOutputKind::PackageSizes => schema_for!(JsonOutput<Vec<PackageSizeOutput>>),
+            JsonOutputKind::Summary => schema_for!(JsonOutput<SummaryOutput>),
+            JsonOutputKind::Outdated => schema_for!(JsonOutput<Vec<OutdatedOutput>>),
+            JsonOutputKind::Audit => schema_for!(JsonOutput<Vec<AuditOutput>>),
+            JsonOutputKind::History => schema_for!(JsonOutput<Vec<BuildSessionOutput>>),
+            JsonOutputKind::HistorySession => schema_for!(JsonOutput<BuildSessionDetailOutput>),
+            JsonOutputKind::ArtifactDiff => schema_for!(JsonOutput<ArtifactDiff>),
+            JsonOutputKind::GraphDiff => schema_for!(JsonOutput<GraphDiffOutput>),
+            JsonOutputKind::PlanDiff => schema_for!(JsonOutput<Vec<PlanDiffOutput>>),
+            JsonOutputKind::ArtifactFiles => schema_for!(JsonOutput<Vec<ArtifactFilesOutput>>),
+            JsonOutputKind::Graph => schema_for!(JsonOutput<GraphOutput>),
+            JsonOutputKind::BuildStages => schema_for!(JsonOutput<Vec<BuildStageOutput>>),
+        }
+    }
+}
+
+/// Envelope wrapping every JSON output
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct JsonOutput<T> {
+    /// Version of the schema of the output
+    pub schema_version: u32,
+    /// Kind of the output
+    pub kind: JsonOutputKind,
+    pub data: T,
+}
+
+impl<T: Serialize> JsonOutput<T> {
+    pub fn new(kind: JsonOutputKind, data: T) -> JsonOutput<T> {
+        JsonOutput {
+            schema_version: JSON_SCHEMA_VERSION,
+            kind,
+            data,
+        }
+    }
+}
+
+/// Prints the data as pretty JSON wrapped in the output envelope
+pub(crate) fn output_json(kind: JsonOutputKind, data: impl Serialize) -> Result<()> {
+    info!(target: "user-ui", "{}", json_string(kind, data)?);
+    Ok(())
+}
+
+/// Serializes the data as pretty JSON wrapped in the output envelope
+pub(crate) fn json_string(kind: JsonOutputKind, data: impl Serialize) -> Result<String> {
+    serde_json::to_string_pretty(&JsonOutput::new(kind, data))
+        .with_context(|| format!("Failed to serialize {:?} output into JSON", kind))
+}
+
+/// Prints the data as a single line of JSON wrapped in the output envelope,
+/// for outputs that are streamed as they are produced.
+pub(crate) fn output_json_line(kind: JsonOutputKind, data: impl Serialize) -> Result<()> {
+    info!(
+        target: "user-ui",
+        "{}",
+        serde_json::to_string(&JsonOutput::new(kind, data))
+            .with_context(|| format!("Failed to serialize {:?} output into JSON", kind))?
+    );
+    Ok(())
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct AnalysisOutput {
+    /// Ident of the package, or the plan id for local plans
+    pub package: String,
+    /// Repo containing the plan, only set for local plans
+    pub repo: Option<String>,
+    /// Path to the plan file, only set for local plans
+    pub plan: Option<PathBuf>,
+    /// Studio used to build the plan, null for native plans
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub studio_dep: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_deps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tdeps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_tdeps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rdeps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_rdeps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_hosts: Option<Vec<BuildHostOutput>>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct BuildHostOutput {
+    pub artifact: String,
+    /// Host on which the artifact was built, null if it was not built by hab-auto-build
+    pub host: Option<BuildHostMetadata>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct PlanOutput {
+    pub id: String,
+    pub repo: String,
+    pub plan: PathBuf,
+}
+
+impl From<&PlanContext> for PlanOutput {
+    fn from(plan_ctx: &PlanContext) -> Self {
+        PlanOutput {
+            id: plan_ctx.id.to_string(),
+            repo: plan_ctx.repo_id.to_string(),
+            plan: plan_ctx.plan_path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct LayerOutput {
+    pub layer: usize,
+    pub plans: Vec<PlanOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct CycleOutput {
+    pub cycle: usize,
+    pub plans: Vec<PlanOutput>,
+    pub dependencies: Vec<CycleDependencyOutput>,
+    /// Build dependencies to remove to break the cycle
+    pub suggested_breaks: Vec<CycleBreakOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct CycleDependencyOutput {
+    pub source: String,
+    pub target: String,
+    #[serde(rename = "type")]
+    pub dep_type: DependencyType,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct GraphOutput {
+    pub nodes: Vec<GraphNodeOutput>,
+    pub edges: Vec<GraphEdgeOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct GraphNodeOutput {
+    /// Ident of the package, or the plan id for local plans
+    pub id: String,
+    /// One of 'local_plan', 'remote_dependency' or 'resolved_dependency'
+    #[serde(rename = "type")]
+    pub node_type: String,
+    /// Repo and plan file, only set for local plans
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<PlanOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct GraphEdgeOutput {
+    pub source: String,
+    pub target: String,
+    #[serde(rename = "type")]
+    pub dep_type: DependencyType,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct CycleBreakOutput {
+    pub source: String,
+    pub target: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct RebuildPathsOutput {
+    pub plan: String,
+    pub rebuilt: bool,
+    /// Set if there were too many paths to list them all
+    pub truncated: bool,
+    pub paths: Vec<RebuildPathOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct RebuildPathOutput {
+    /// Plans from the changed plan to the rebuilt plan
+    pub plans: Vec<String>,
+    /// Type of the dependency each plan has on the previous one
+    pub dependency_types: Vec<DependencyType>,
+    /// Changes to the first plan that cause it to be rebuilt
+    pub causes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct BuildStageOutput {
+    pub stage: usize,
+    /// Plans of the stage, which can be built in parallel once the previous stages are built
+    pub plans: Vec<BuildStageStepOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct BuildStageStepOutput {
+    pub id: String,
+    pub repo: String,
+    pub plan: PathBuf,
+    /// One of 'native', 'bootstrap' or 'standard'
+    pub studio: String,
+    /// Studio package used to build the plan, null for native plans
+    pub studio_package: Option<String>,
+    /// Plans of earlier stages that must be built first, dependencies implied by
+    /// other listed plans are left out
+    pub depends_on: Vec<String>,
+    /// Locally built artifacts installed in the studio before building
+    pub deps_to_install: Vec<String>,
+    /// Dependencies that are not built from a local plan
+    pub remote_deps: Vec<String>,
+    /// Seconds the last build of the plan took, if it was built before
+    pub build_duration_secs: Option<i64>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct BuildEventOutput {
+    pub event: String,
+    pub build_id: String,
+    pub worker: String,
+    pub plan: Option<String>,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum DuplicateSourceKind {
+    /// Plans of different packages using the same source archive
+    SameArchive,
+    /// Plans using the same source url but pinning different shasums
+    ConflictingShasums,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct DuplicateSourceOutput {
+    pub kind: DuplicateSourceKind,
+    /// Shared shasum for 'same-archive' duplicates, shared url for 'conflicting-shasums' duplicates
+    pub source: String,
+    pub plans: Vec<PlanSourceOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct PlanSourceOutput {
+    #[serde(flatten)]
+    pub plan: PlanOutput,
+    pub url: String,
+    pub shasum: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ServiceCompositionOutput {
+    pub binds: Vec<ServiceBindOutput>,
+    /// Plans with exports that no bind of another plan needs
+    pub unconsumed_exports: Vec<UnconsumedExportsOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ServiceBindOutput {
+    /// Plan declaring the bind
+    pub plan: PlanOutput,
+    pub bind: String,
+    /// Exports needed from the bound service
+    pub exports: Vec<String>,
+    /// Whether the bind was declared with 'pkg_binds_optional'
+    pub optional: bool,
+    /// Plans exporting everything the bind needs, empty if the bind cannot be satisfied
+    pub providers: Vec<PlanOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct UnconsumedExportsOutput {
+    pub plan: PlanOutput,
+    pub exports: Vec<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct GraphDiffOutput {
+    /// Commit of the '--from' ref
+    pub from: String,
+    /// Commit of the '--to' ref
+    pub to: String,
+    /// Packages with plans only in the '--to' commit, as 'origin/name'
+    pub added_plans: Vec<GraphDiffPackageOutput>,
+    /// Packages with plans only in the '--from' commit, as 'origin/name'
+    pub removed_plans: Vec<GraphDiffPackageOutput>,
+    pub version_changes: Vec<GraphDiffVersionChangeOutput>,
+    /// Dependencies only in the '--to' commit, between packages with plans in both commits
+    pub added_edges: Vec<GraphDiffEdgeOutput>,
+    /// Dependencies only in the '--from' commit, between packages with plans in both commits
+    pub removed_edges: Vec<GraphDiffEdgeOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct GraphDiffPackageOutput {
+    pub package: String,
+    pub target: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct GraphDiffVersionChangeOutput {
+    #[serde(flatten)]
+    pub package: GraphDiffPackageOutput,
+    /// Versions of the plans of the package in the '--from' commit
+    pub from: Vec<String>,
+    /// Versions of the plans of the package in the '--to' commit
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct GraphDiffEdgeOutput {
+    #[serde(flatten)]
+    pub package: GraphDiffPackageOutput,
+    #[serde(rename = "type")]
+    pub dep_type: DependencyType,
+    /// Dependency of the package, as 'origin/name'
+    pub dependency: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct PlanDiffOutput {
+    #[serde(flatten)]
+    pub package: GraphDiffPackageOutput,
+    /// Versions of the plans of the package in the source configuration
+    pub source_versions: Vec<String>,
+    /// Versions of the plans of the package in the target configuration
+    pub target_versions: Vec<String>,
+    /// Whether the versions differ between the configurations
+    pub changed: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct PackageSizeOutput {
+    pub artifact: String,
+    /// Installed size in bytes of the artifact
+    pub installed_size: u64,
+    /// Installed size in bytes of the artifact and its transitive runtime dependencies
+    pub closure_size: u64,
+    /// Transitive runtime dependencies contributing the most to the closure size, largest first
+    pub heaviest_dependencies: Vec<DependencySizeOutput>,
+    /// Transitive runtime dependencies without an artifact, not counted in the closure size
+    pub missing_dependencies: Vec<String>,
+    /// Previous release of the package whose sizes are recorded in the store
+    pub previous_artifact: Option<String>,
+    pub previous_closure_size: Option<u64>,
+    /// Growth of the closure size since the previous release, in percent
+    pub closure_growth: Option<f64>,
+    /// Whether the closure grew by more than the allowed percentage since the previous release
+    pub exceeds_max_growth: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct DependencySizeOutput {
+    pub artifact: String,
+    /// Installed size in bytes of the artifact
+    pub installed_size: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct SummaryOutput {
+    pub repos: Vec<RepoSummaryOutput>,
+    /// Total number of plans across all repos
+    pub plans: usize,
+    /// Number of plans built with each kind of studio
+    pub plans_by_studio: BTreeMap<String, usize>,
+    pub plans_by_origin: BTreeMap<String, usize>,
+    /// Plans without any artifact in the artifact cache
+    pub plans_without_artifact: Vec<PlanOutput>,
+    /// Plans whose source archive has not been downloaded
+    pub plans_missing_source: Vec<PlanOutput>,
+    /// Plans with a '.hab-plan-config.toml' file
+    pub plans_with_config: Vec<PlanOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct RepoSummaryOutput {
+    pub id: String,
+    pub path: PathBuf,
+    pub plans: usize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct OutdatedOutput {
+    pub plan: PlanOutput,
+    pub version: String,
+    /// Newest upstream version, null if the plan is up to date or could not be checked
+    pub latest_version: Option<String>,
+    /// Where the upstream versions were looked for
+    pub checked: Option<String>,
+    /// Reason the plan could not be checked or bumped
+    pub error: Option<String>,
+    /// Whether the plan was updated to the latest version
+    pub bumped: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct AuditOutput {
+    pub plan: PlanOutput,
+    pub version: String,
+    /// Where the known vulnerabilities were looked up
+    pub checked: String,
+    /// Known vulnerabilities affecting the version, excluding the ones ignored by the plan
+    pub vulnerabilities: Vec<Vulnerability>,
+    /// Reason the plan could not be checked
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ArtifactFilesOutput {
+    pub artifact: String,
+    /// Files and links of the artifact, relative to its package folder
+    pub files: Vec<ArtifactFile>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct BuildSessionOutput {
+    pub id: i32,
+    pub started_at: String,
+    /// Null if the session is still running or was interrupted
+    pub ended_at: Option<String>,
+    /// Number of packages the session planned to build
+    pub planned: usize,
+    pub attempted: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Number of error-level violations found in the artifacts built by the session
+    pub error_count: usize,
+    /// Number of warning-level violations found in the artifacts built by the session
+    pub warning_count: usize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct BuildSessionDetailOutput {
+    pub session: BuildSessionOutput,
+    pub steps: Vec<BuildSessionStepOutput>,
+    pub artifacts: Vec<BuildSessionArtifactOutput>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct BuildSessionStepOutput {
+    pub plan: String,
+    /// One of 'pending', 'building', 'failed' or 'built'
+    pub status: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct BuildSessionArtifactOutput {
+    pub artifact: String,
+    pub error_count: usize,
+    pub warning_count: usize,
+}
@@ -0,0 +1,175 @@
+use std::{env, fmt::Write, path::PathBuf};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::OwoColorize;
+use tracing::{error, info};
+
+use crate::{
+    cli::output::{self, BuildStageOutput, BuildStageStepOutput, JsonOutputKind, ReportFormat},
+    core::{
+        AutoBuildConfig, AutoBuildContext, BuildOrder, BuildPlanScope, BuildStep,
+        ChangeDetectionMode, Dependency, PackageDepGlob, PackageTarget,
+    },
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Output format
+    #[arg(value_enum, short = 'f', long, visible_alias = "output", default_value_t = ReportFormat::Plain)]
+    format: ReportFormat,
+    /// Build ordering to use with respect to the build's studio
+    #[arg(value_enum, short = 'b', long, default_value_t = BuildOrder::Strict)]
+    build_order: BuildOrder,
+    /// Method to use to detect changes to packages
+    #[arg(value_enum, short = 'm', long, default_value_t = ChangeDetectionMode::Disk)]
+    change_detection_mode: ChangeDetectionMode,
+    /// Allow use of packages from a remote habitat builder instance specified by HAB_BLDR_URL
+    #[arg(short = 'r', long)]
+    allow_remote: bool,
+    /// Package target to plan the builds for, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// List of packages to build
+    packages: Vec<PackageDepGlob>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
+    let run_context = AutoBuildContext::new(&config, &config_path, args.change_detection_mode)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+    run_context.broken_plans_verify()?;
+
+    let package_indices = run_context.glob_deps(&args.packages, target)?;
+    if package_indices.is_empty() && !run_context.is_empty() && !args.packages.is_empty() {
+        error!(target: "user-log",
+            "No packages found matching patterns: {}",
+            serde_json::to_string(&args.packages).unwrap()
+        );
+        return Ok(());
+    }
+    let build_plan = run_context.build_plan_generate(
+        package_indices,
+        args.change_detection_mode,
+        args.build_order,
+        target,
+        args.allow_remote,
+        &BuildPlanScope::default(),
+    )?;
+    let stages = build_stages(&run_context, &build_plan.build_steps);
+    match args.format {
+        ReportFormat::Plain => output_plain(stages),
+        ReportFormat::Json => output::output_json(JsonOutputKind::BuildStages, stages),
+        ReportFormat::Markdown => output_markdown(stages),
+    }
+}
+
+fn build_stages(
+    run_context: &AutoBuildContext,
+    build_steps: &[BuildStep],
+) -> Vec<BuildStageOutput> {
+    let step_dependencies = run_context.build_step_dependencies(build_steps);
+    run_context
+        .build_step_stages(build_steps)
+        .into_iter()
+        .enumerate()
+        .map(|(stage, positions)| {
+            let mut plans = positions
+                .into_iter()
+                .map(|position| {
+                    let step = &build_steps[position];
+                    let dependencies = step_dependencies[position]
+                        .iter()
+                        .filter(|dependency| **dependency < position)
+                        .collect::<Vec<_>>();
+                    // Dependencies that are transitive dependencies of another listed
+                    // dependency are implied by it
+                    let mut depends_on = dependencies
+                        .iter()
+                        .filter(|dependency| {
+                            !dependencies.iter().any(|other| {
+                                other != *dependency
+                                    && step_dependencies[**other].contains(dependency)
+                            })
+                        })
+                        .map(|dependency| build_steps[**dependency].plan_ctx.id.to_string())
+                        .collect::<Vec<_>>();
+                    depends_on.sort();
+                    BuildStageStepOutput {
+                        id: step.plan_ctx.id.to_string(),
+                        repo: step.repo_ctx.id.to_string(),
+                        plan: step.plan_ctx.plan_path.as_ref().to_path_buf(),
+                        studio: step.studio.to_string(),
+                        studio_package: step.studio_package.map(|package| package.to_string()),
+                        depends_on,
+                        deps_to_install: step
+                            .deps_to_install
+                            .iter()
+                            .map(|dep| dep.to_string())
+                            .collect(),
+                        remote_deps: step
+                            .remote_deps
+                            .iter()
+                            .filter_map(|dep| match dep {
+                                Dependency::ResolvedDep(ident) => Some(ident.to_string()),
+                                Dependency::RemoteDep(ident) => Some(ident.to_string()),
+                                Dependency::LocalPlan(_) => None,
+                            })
+                            .collect(),
+                        build_duration_secs: step
+                            .build_duration
+                            .map(|build_duration| build_duration.num_seconds()),
+                    }
+                })
+                .collect::<Vec<_>>();
+            // The order of plans within a stage does not matter, keep it stable across runs
+            plans.sort_by(|a, b| a.id.cmp(&b.id));
+            BuildStageOutput { stage, plans }
+        })
+        .collect()
+}
+
+fn output_plain(stages: Vec<BuildStageOutput>) -> Result<()> {
+    if stages.is_empty() {
+        info!(target: "user-log", "{}", "All plans built");
+        return Ok(());
+    }
+    for stage in stages {
+        info!(target: "user-ui", "{}", format!("Stage {}:", stage.stage).white().bold());
+        for plan in stage.plans {
+            info!(target: "user-ui", "   - [{}] {}", plan.studio, plan.id);
+        }
+        info!(target: "user-ui", "");
+    }
+    Ok(())
+}
+
+fn output_markdown(stages: Vec<BuildStageOutput>) -> Result<()> {
+    let mut output = String::new();
+    for stage in stages {
+        writeln!(output, "## Stage {}\n", stage.stage)?;
+        writeln!(output, "| Plan | Studio | Depends On |")?;
+        writeln!(output, "| ---- | ------ | ---------- |")?;
+        for plan in stage.plans {
+            writeln!(
+                output,
+                "| {} | {} | {} |",
+                plan.id,
+                plan.studio,
+                plan.depends_on.join(", ")
+            )?;
+        }
+        writeln!(output)?;
+    }
+    info!(target: "user-ui", "{}", output);
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use std::{
+    env,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use rayon::prelude::*;
+use tracing::info;
+
+use super::clean::format_size;
+use crate::core::{
+    habitat, ArtifactUploadStatus, AutoBuildConfig, AutoBuildContext, BuilderClient,
+    ChangeDetectionMode, PackageDepGlob, PackageIdent,
+};
+
+/// Percentage of an artifact uploaded between two progress reports
+const UPLOAD_PROGRESS_STEP: u64 = 25;
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Builder channel to promote the packages to
+    #[arg(long)]
+    channel: String,
+    /// Refuse to promote packages whose artifact checks found error-level violations
+    #[arg(long)]
+    after_checks_pass: bool,
+    /// Upload the artifacts to Builder before promoting them, artifacts Builder already has are skipped
+    #[arg(long)]
+    upload: bool,
+    /// Number of artifacts uploaded at the same time
+    #[arg(long, default_value_t = 4, requires = "upload")]
+    upload_concurrency: u16,
+    /// Number of times a failed upload is retried before giving up on the artifact
+    #[arg(long, default_value_t = 3, requires = "upload")]
+    upload_retries: u32,
+    /// Do a dry run of the promotion and output the packages that would be promoted
+    #[arg(short = 'd', long)]
+    dry_run: bool,
+    /// List of packages built in the last build session to promote, all of them are promoted if none are specified
+    packages: Vec<PackageDepGlob>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let last_session = run_context.last_build_session()?;
+    if last_session.is_empty() {
+        return Err(eyre!("No packages were built in the last build session"))
+            .with_suggestion(|| "Build the packages with 'hab-auto-build build' first");
+    }
+    let matchers = args
+        .packages
+        .iter()
+        .map(|package| package.matcher())
+        .collect::<Vec<_>>();
+    let artifacts = last_session
+        .into_iter()
+        .filter(|(artifact_ident, _)| {
+            matchers.is_empty()
+                || matchers
+                    .iter()
+                    .any(|matcher| matcher.matches_package_ident(artifact_ident))
+        })
+        .collect::<Vec<_>>();
+    if artifacts.is_empty() {
+        return Err(eyre!(
+            "No packages built in the last build session match patterns: {}",
+            serde_json::to_string(&args.packages).unwrap()
+        ));
+    }
+
+    let mut refused_count = 0;
+    let mut artifacts_to_promote = Vec::new();
+    for (artifact_ident, error_count) in artifacts {
+        if args.after_checks_pass && error_count > 0 {
+            info!(target: "user-ui", "{} {}: {} error-level violations found when checking the artifact", "      Refused".red().bold(), artifact_ident, error_count);
+            refused_count += 1;
+            continue;
+        }
+        artifacts_to_promote.push(artifact_ident);
+    }
+
+    let mut failed_upload_count = 0;
+    if args.upload {
+        if args.dry_run {
+            for artifact_ident in artifacts_to_promote.iter() {
+                info!(target: "user-ui", "{} {}", "Would upload".green().bold(), artifact_ident);
+            }
+        } else {
+            let upload_results = artifacts_upload(
+                &run_context,
+                &artifacts_to_promote,
+                args.upload_concurrency,
+                args.upload_retries,
+            )?;
+            artifacts_to_promote = artifacts_to_promote
+                .into_iter()
+                .zip(upload_results)
+                .filter_map(|(artifact_ident, result)| match result {
+                    Ok(ArtifactUploadStatus::Uploaded) => {
+                        info!(target: "user-ui", "{} {}", "     Uploaded".green().bold(), artifact_ident);
+                        Some(artifact_ident)
+                    }
+                    Ok(ArtifactUploadStatus::AlreadyUploaded) => {
+                        info!(target: "user-ui", "{} {}: already on Builder", "     Uploaded".green().bold(), artifact_ident);
+                        Some(artifact_ident)
+                    }
+                    Err(err) => {
+                        info!(target: "user-ui", "{} {}: {:#}", "       Failed".red().bold(), artifact_ident, err);
+                        failed_upload_count += 1;
+                        None
+                    }
+                })
+                .collect();
+        }
+    }
+
+    let mut promoted_count = 0;
+    for artifact_ident in artifacts_to_promote {
+        if !args.dry_run {
+            habitat::artifact_promote(&artifact_ident, &args.channel)?;
+        }
+        info!(target: "user-ui", "{} {} to {}", if args.dry_run { "Would promote" } else { "     Promoted" }.green().bold(), artifact_ident, args.channel.blue());
+        promoted_count += 1;
+    }
+    if args.dry_run {
+        info!(target: "user-log", "{} packages would be promoted to channel {}", promoted_count, args.channel);
+    } else {
+        info!(target: "user-log", "Promoted {} packages to channel {}", promoted_count, args.channel);
+    }
+    if failed_upload_count > 0 {
+        return Err(eyre!(
+            "Failed to upload {} packages to Builder, they were not promoted",
+            failed_upload_count
+        ))
+        .with_suggestion(|| "Run the command again to upload the remaining packages, packages already on Builder are skipped");
+    }
+    if refused_count > 0 {
+        return Err(eyre!(
+            "Refused to promote {} packages with failing artifact checks",
+            refused_count
+        ))
+        .with_suggestion(|| "Fix the plans of the refused packages and rebuild them");
+    }
+    Ok(())
+}
+
+/// Uploads artifacts to Builder in parallel, returns the outcome of the upload of
+/// each artifact in the same order.
+fn artifacts_upload(
+    run_context: &AutoBuildContext,
+    artifact_idents: &[PackageIdent],
+    concurrency: u16,
+    retries: u32,
+) -> Result<Vec<Result<ArtifactUploadStatus>>> {
+    let builder = BuilderClient::new()?;
+    let upload_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency.max(1) as usize)
+        .build()
+        .context("Failed to create upload thread pool")?;
+    Ok(upload_pool.install(|| {
+        artifact_idents
+            .par_iter()
+            .map(|artifact_ident| {
+                let artifact_path = run_context.artifact_path(artifact_ident).ok_or_else(|| {
+                    eyre!("No .hart file found for {}", artifact_ident)
+                })?;
+                let reported_percent = Arc::new(AtomicU64::new(0));
+                let progress_ident = artifact_ident.clone();
+                builder.artifact_upload(
+                    artifact_ident,
+                    &artifact_path,
+                    retries,
+                    move |sent, size| {
+                        let percent = (sent * 100)
+                            .checked_div(size)
+                            .unwrap_or(100)
+                            .min(100)
+                            / UPLOAD_PROGRESS_STEP
+                            * UPLOAD_PROGRESS_STEP;
+                        if reported_percent.fetch_max(percent, Ordering::Relaxed) < percent {
+                            info!(target: "user-ui", "{} {}: {}% of {}", "    Uploading".blue().bold(), progress_ident, percent, format_size(size));
+                        }
+                    },
+                )
+            })
+            .collect()
+    }))
+}
@@ -0,0 +1,146 @@
+use std::{env, path::PathBuf};
+
+use clap::{Args, ValueEnum};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::{error, info};
+
+use crate::core::{
+    sbom_generate, AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepGlob,
+    PackageTarget, SbomFormat,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Format {
+    Spdx,
+    #[value(name = "cyclonedx")]
+    CycloneDx,
+}
+
+impl From<Format> for SbomFormat {
+    fn from(value: Format) -> Self {
+        match value {
+            Format::Spdx => SbomFormat::Spdx,
+            Format::CycloneDx => SbomFormat::CycloneDx,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Format of the generated software bill of materials
+    #[arg(value_enum, short = 'f', long, default_value_t = Format::Spdx)]
+    format: Format,
+    /// Generate a single document with the given name describing all the packages, instead of one per package
+    #[arg(short, long)]
+    merge: Option<String>,
+    /// Folder in which to write the documents, required when generating multiple documents
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+    /// List of packages to generate a software bill of materials for
+    #[arg(required = true)]
+    packages: Vec<PackageDepGlob>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let package_indices = run_context.glob_deps(&args.packages, PackageTarget::default())?;
+    if package_indices.is_empty() {
+        error!(target: "user-log",
+            "No packages found matching patterns: {}",
+            serde_json::to_string(&args.packages).unwrap()
+        );
+        return Ok(());
+    }
+
+    let mut artifacts = Vec::new();
+    for package_index in package_indices.iter() {
+        let package = run_context.dep(*package_index);
+        match run_context.package_artifact(*package_index)? {
+            Some(artifact) => artifacts.push(artifact),
+            None => {
+                info!(target: "user-ui", "{}: {:?}: No artifact found, skipping", "warning".bold().yellow(), package.red())
+            }
+        }
+    }
+    if artifacts.is_empty() {
+        return Err(eyre!("No artifacts found for any of the selected packages")).with_suggestion(
+            || "Build the packages before generating a software bill of materials",
+        );
+    }
+
+    let format = SbomFormat::from(args.format);
+    let documents = if let Some(name) = args.merge {
+        let sbom_packages = run_context.sbom_packages(&artifacts)?;
+        vec![(name.clone(), sbom_generate(format, &name, &sbom_packages))]
+    } else {
+        artifacts
+            .iter()
+            .map(|artifact| {
+                let name = format!(
+                    "{}-{}-{}-{}",
+                    artifact.id.origin, artifact.id.name, artifact.id.version, artifact.id.release
+                );
+                let sbom_packages = run_context.sbom_packages(std::slice::from_ref(artifact))?;
+                Ok((name.clone(), sbom_generate(format, &name, &sbom_packages)))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    match args.output_dir {
+        Some(output_dir) => {
+            std::fs::create_dir_all(&output_dir).with_context(|| {
+                format!(
+                    "Failed to create output directory '{}'",
+                    output_dir.display()
+                )
+            })?;
+            for (name, document) in documents {
+                let document_path = output_dir.join(format!("{}.{}", name, format.extension()));
+                std::fs::write(
+                    &document_path,
+                    serde_json::to_string_pretty(&document)
+                        .context("Failed to serialize software bill of materials into JSON")?,
+                )
+                .with_context(|| {
+                    format!(
+                        "Failed to write software bill of materials to '{}'",
+                        document_path.display()
+                    )
+                })?;
+                info!(target: "user-log", "Wrote software bill of materials for {} to {}", name.blue(), document_path.display());
+            }
+        }
+        None => {
+            if documents.len() > 1 {
+                return Err(eyre!(
+                    "Cannot write {} software bills of materials to the terminal",
+                    documents.len()
+                ))
+                .with_suggestion(|| {
+                    "Use '--output-dir' to write each document to a file, or '--merge' to generate a single document"
+                });
+            }
+            for (_, document) in documents {
+                info!(target: "user-ui", "{}", serde_json::to_string_pretty(&document)
+                    .context("Failed to serialize software bill of materials into JSON")?);
+            }
+        }
+    }
+    Ok(())
+}
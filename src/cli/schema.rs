@@ -0,0 +1,22 @@
+use clap::Args;
+use color_eyre::eyre::{Context, Result};
+use tracing::info;
+
+use crate::cli::output::JsonOutputKind;
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Kind of JSON output to describe
+    #[arg(value_enum)]
+    kind: JsonOutputKind,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    info!(
+        target: "user-ui",
+        "{}",
+        serde_json::to_string_pretty(&args.kind.schema())
+            .with_context(|| format!("Failed to serialize JSON schema of {:?} output", args.kind))?
+    );
+    Ok(())
+}
@@ -1,18 +1,60 @@
-use crate::core::{AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, DepGraphData};
+use crate::{
+    core::{
+        AnalysisType, AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepGlob,
+        PackageTarget,
+    },
+    store::{
+        BuildSession, BuildStepStatus, ContextCacheStats, ARTIFACT_CONTEXT_CACHE_STATS,
+        SOURCE_CONTEXT_CACHE_STATS,
+    },
+};
 
 use axum::{
-    extract::State,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
     handler::HandlerWithoutStateExt,
     http::{header, StatusCode, Uri},
-    response::{Html, IntoResponse, Response},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
     routing::get,
     Json, Router,
 };
+use chrono::{DateTime, Utc};
 use clap::Args;
 use color_eyre::eyre::{eyre, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
 use rust_embed::RustEmbed;
-use serde_json::Value;
-use std::{env, net::SocketAddr, path::PathBuf, sync::Arc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashSet},
+    convert::Infallible,
+    env,
+    fmt::Write,
+    net::SocketAddr,
+    path::PathBuf,
+    process::Stdio,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    sync::{mpsc, watch},
+};
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Interval at which the store is polled for the progress of build sessions
+const BUILD_STATE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    static ref ANSI_ESCAPE_SEQUENCE: Regex = Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap();
+}
 
 #[derive(Debug, Args)]
 pub(crate) struct Params {
@@ -35,20 +77,104 @@ pub(crate) fn execute(args: Params) -> Result<()> {
     let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
         .with_context(|| eyre!("Failed to initialize run"))?;
 
+    let state = Arc::new(ServerState {
+        config_path,
+        run_context: RwLock::new(Arc::new(run_context)),
+        builds: Mutex::new(Vec::new()),
+        graph_states: watch::channel(GraphStates::default()).0,
+    });
     let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(start(run_context.dep_graph_data(), args.port));
+    rt.block_on(start(state, args.port));
     Ok(())
 }
 
-async fn start(graph: DepGraphData, port: u16) {
-    let graph = Arc::new(graph);
+struct ServerState {
+    config_path: PathBuf,
+    /// Context of the plans, refreshed after every build
+    run_context: RwLock<Arc<AutoBuildContext>>,
+    builds: Mutex<Vec<ServerBuild>>,
+    /// States of the graph nodes in the most recent build session
+    graph_states: watch::Sender<GraphStates>,
+}
+
+impl ServerState {
+    fn run_context(&self) -> Arc<AutoBuildContext> {
+        self.run_context.read().unwrap().clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ServerBuildStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// Build triggered through the server, executed by a `hab-auto-build build` process
+#[derive(Debug, Serialize)]
+struct ServerBuild {
+    id: usize,
+    packages: Vec<PackageDepGlob>,
+    status: ServerBuildStatus,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    log: Vec<String>,
+    /// Notifies log streams of new output and status changes
+    #[serde(skip)]
+    updates: watch::Sender<()>,
+}
+
+/// States of the plans built in a build session, indexed by the ids of their
+/// nodes in the graph data
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct GraphStates {
+    session_id: Option<String>,
+    nodes: BTreeMap<u32, BuildStepStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildRequest {
+    #[serde(default)]
+    packages: Vec<PackageDepGlob>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogQuery {
+    /// Number of log lines to skip
+    #[serde(default)]
+    offset: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnalysisQuery {
+    /// Comma separated list of package patterns
+    packages: String,
+    /// Comma separated list of analysis types, named like the 'analyze' command options
+    #[serde(default)]
+    types: Option<String>,
+    target: Option<PackageTarget>,
+}
+
+type ApiError = (StatusCode, String);
+
+async fn start(state: Arc<ServerState>, port: u16) {
     // build our application with a route
     let app = Router::new()
         .route("/", get(index_handler))
         .route("/index.html", get(index_handler))
         .route_service("/static/*file", static_handler.into_service())
         .route("/data", get(data))
-        .with_state(graph);
+        .route("/api/builds", get(builds_list).post(build_start))
+        .route("/api/builds/:id", get(build_status))
+        .route("/api/builds/:id/logs", get(build_logs))
+        .route("/api/analysis", get(analysis))
+        .route("/api/graph/live", get(graph_live))
+        .route("/metrics", get(metrics))
+        .with_state(state.clone());
+
+    tokio::spawn(graph_states_poll(state));
 
     // run our app with hyper
     // `axum::Server` is a re-export of `hyper::Server`
@@ -59,8 +185,537 @@ async fn start(graph: DepGraphData, port: u16) {
 }
 
 // basic handler that responds with a static string
-async fn data(State(graph): State<Arc<DepGraphData>>) -> Json<Value> {
-    Json(serde_json::to_value(&*graph).unwrap())
+async fn data(State(state): State<Arc<ServerState>>) -> Json<Value> {
+    Json(serde_json::to_value(state.run_context().dep_graph_data()).unwrap())
+}
+
+async fn builds_list(State(state): State<Arc<ServerState>>) -> Json<Value> {
+    Json(serde_json::to_value(&*state.builds.lock().unwrap()).unwrap())
+}
+
+async fn build_status(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<usize>,
+) -> Result<Json<Value>, ApiError> {
+    let builds = state.builds.lock().unwrap();
+    let build = builds
+        .get(id)
+        .ok_or((StatusCode::NOT_FOUND, format!("No build with id {}", id)))?;
+    Ok(Json(serde_json::to_value(build).unwrap()))
+}
+
+/// Starts a build of the given packages, only one build can run at a time
+async fn build_start(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<BuildRequest>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let executable = env::current_exe().map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to determine the hab-auto-build executable: {}", err),
+        )
+    })?;
+    let mut command = Command::new(executable);
+    command
+        .arg("build")
+        .arg("--config-path")
+        .arg(&state.config_path)
+        .args(request.packages.iter().map(|package| package.to_string()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let id = {
+        let mut builds = state.builds.lock().unwrap();
+        if let Some(running_build) = builds
+            .iter()
+            .find(|build| build.status == ServerBuildStatus::Running)
+        {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("Build {} is already running", running_build.id),
+            ));
+        }
+        let mut child = command.spawn().map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to start build: {}", err),
+            )
+        })?;
+        let id = builds.len();
+        builds.push(ServerBuild {
+            id,
+            packages: request.packages,
+            status: ServerBuildStatus::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            log: Vec::new(),
+            updates: watch::channel(()).0,
+        });
+        let stdout_task = tokio::spawn(build_output_capture(
+            state.clone(),
+            id,
+            child.stdout.take().unwrap(),
+        ));
+        let stderr_task = tokio::spawn(build_output_capture(
+            state.clone(),
+            id,
+            child.stderr.take().unwrap(),
+        ));
+        let state = state.clone();
+        tokio::spawn(async move {
+            let exit_status = child.wait().await;
+            let _ = tokio::join!(stdout_task, stderr_task);
+            // Pick up the artifacts and changes resulting from the build
+            let config_path = state.config_path.clone();
+            let run_context = tokio::task::spawn_blocking(move || {
+                let config = AutoBuildConfig::new(&config_path)?;
+                AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+            })
+            .await;
+            let mut builds = state.builds.lock().unwrap();
+            let build = &mut builds[id];
+            match run_context {
+                Ok(Ok(run_context)) => {
+                    *state.run_context.write().unwrap() = Arc::new(run_context);
+                }
+                Ok(Err(err)) => build
+                    .log
+                    .push(format!("Failed to refresh plans after build: {:#}", err)),
+                Err(err) => build
+                    .log
+                    .push(format!("Failed to refresh plans after build: {}", err)),
+            }
+            build.status = match exit_status {
+                Ok(exit_status) if exit_status.success() => ServerBuildStatus::Succeeded,
+                Ok(exit_status) => {
+                    build.log.push(format!("Build exited with {}", exit_status));
+                    ServerBuildStatus::Failed
+                }
+                Err(err) => {
+                    build.log.push(format!("Failed to wait for build: {}", err));
+                    ServerBuildStatus::Failed
+                }
+            };
+            build.finished_at = Some(Utc::now());
+            build.updates.send_replace(());
+        });
+        id
+    };
+    let builds = state.builds.lock().unwrap();
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::to_value(&builds[id]).unwrap()),
+    ))
+}
+
+/// Appends the output of a build process to the build log
+async fn build_output_capture(state: Arc<ServerState>, id: usize, output: impl AsyncRead + Unpin) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let mut builds = state.builds.lock().unwrap();
+        let build = &mut builds[id];
+        build
+            .log
+            .push(ANSI_ESCAPE_SEQUENCE.replace_all(&line, "").to_string());
+        build.updates.send_replace(());
+    }
+}
+
+/// Streams the log of a build as server-sent events, starting at the given
+/// line offset. The stream ends with a 'finished' event once the build is done.
+async fn build_logs(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<usize>,
+    Query(query): Query<LogQuery>,
+) -> Result<Sse<ReceiverStream<Result<Event, Infallible>>>, ApiError> {
+    let mut updates = state
+        .builds
+        .lock()
+        .unwrap()
+        .get(id)
+        .ok_or((StatusCode::NOT_FOUND, format!("No build with id {}", id)))?
+        .updates
+        .subscribe();
+    let (sender, receiver) = mpsc::channel(16);
+    tokio::spawn(async move {
+        let mut offset = query.offset;
+        loop {
+            let (lines, status) = {
+                let builds = state.builds.lock().unwrap();
+                let build = &builds[id];
+                (
+                    build.log.iter().skip(offset).cloned().collect::<Vec<_>>(),
+                    build.status,
+                )
+            };
+            offset += lines.len();
+            for line in lines {
+                if sender.send(Ok(Event::default().data(line))).await.is_err() {
+                    return;
+                }
+            }
+            if status != ServerBuildStatus::Running {
+                let _ = sender
+                    .send(Ok(Event::default()
+                        .event("finished")
+                        .data(serde_json::to_string(&status).unwrap())))
+                    .await;
+                return;
+            }
+            if updates.changed().await.is_err() {
+                return;
+            }
+        }
+    });
+    Ok(Sse::new(ReceiverStream::new(receiver)).keep_alive(KeepAlive::default()))
+}
+
+/// Follows the progress of build sessions recorded in the store, this includes
+/// builds started outside of the server that share its store.
+async fn graph_states_poll(state: Arc<ServerState>) {
+    let mut interval = tokio::time::interval(BUILD_STATE_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let run_context = state.run_context();
+        let graph_states = tokio::task::spawn_blocking(move || -> Result<GraphStates> {
+            let Some((session_id, step_states)) = run_context.last_build_session_step_states()?
+            else {
+                return Ok(GraphStates::default());
+            };
+            let plan_node_ids = run_context.dep_graph_plan_node_ids();
+            Ok(GraphStates {
+                session_id: Some(session_id),
+                nodes: step_states
+                    .into_iter()
+                    .filter_map(|step_state| {
+                        plan_node_ids
+                            .get(&step_state.plan)
+                            .map(|node_id| (*node_id, step_state.status))
+                    })
+                    .collect(),
+            })
+        })
+        .await;
+        match graph_states {
+            Ok(Ok(graph_states)) => {
+                state.graph_states.send_if_modified(|current_graph_states| {
+                    if *current_graph_states != graph_states {
+                        *current_graph_states = graph_states;
+                        true
+                    } else {
+                        false
+                    }
+                });
+            }
+            Ok(Err(err)) => tracing::error!("Failed to read build session progress: {:#}", err),
+            Err(err) => tracing::error!("Failed to read build session progress: {}", err),
+        }
+    }
+}
+
+/// Streams the states of the graph nodes over a WebSocket. The first message
+/// holds the state of every node in the current build session, later messages
+/// only hold the nodes whose state changed. Messages with 'reset' set replace
+/// all previously received states, which happens when a new session starts.
+async fn graph_live(
+    State(state): State<Arc<ServerState>>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| graph_live_stream(state, socket))
+}
+
+async fn graph_live_stream(state: Arc<ServerState>, mut socket: WebSocket) {
+    let mut updates = state.graph_states.subscribe();
+    let mut sent_graph_states: Option<GraphStates> = None;
+    loop {
+        let graph_states = updates.borrow_and_update().clone();
+        let message = match &sent_graph_states {
+            Some(sent_graph_states) if sent_graph_states.session_id == graph_states.session_id => {
+                let changed_nodes = graph_states
+                    .nodes
+                    .iter()
+                    .filter(|(node_id, status)| {
+                        sent_graph_states.nodes.get(node_id) != Some(status)
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                if changed_nodes.is_empty() {
+                    None
+                } else {
+                    Some(json!({
+                        "session_id": graph_states.session_id,
+                        "reset": false,
+                        "nodes": changed_nodes,
+                    }))
+                }
+            }
+            _ => Some(json!({
+                "session_id": graph_states.session_id,
+                "reset": true,
+                "nodes": graph_states.nodes,
+            })),
+        };
+        if let Some(message) = message {
+            if socket
+                .send(Message::Text(message.to_string()))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+        sent_graph_states = Some(graph_states);
+        tokio::select! {
+            changed = updates.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => {
+                // Clients are not expected to send anything, stop once they disconnect
+                if !matches!(message, Some(Ok(_))) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Health of the build farm in the Prometheus text format. Package and session
+/// counts come from the build sessions recorded in the store, so they include
+/// builds started outside of the server. Cache lookups are only counted for the
+/// server process, which loads artifacts when it starts and after every build.
+async fn metrics(State(state): State<Arc<ServerState>>) -> Result<Response, ApiError> {
+    let (builds_succeeded, builds_failed, builds_running) = {
+        let builds = state.builds.lock().unwrap();
+        let count = |status: ServerBuildStatus| {
+            builds.iter().filter(|build| build.status == status).count() as f64
+        };
+        (
+            count(ServerBuildStatus::Succeeded),
+            count(ServerBuildStatus::Failed),
+            count(ServerBuildStatus::Running),
+        )
+    };
+    let run_context = state.run_context();
+    let (build_sessions, build_times) = tokio::task::spawn_blocking(move || -> Result<_> {
+        Ok((
+            run_context.build_sessions(None)?,
+            run_context.build_times()?,
+        ))
+    })
+    .await
+    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", err)))?;
+
+    let mut output = String::new();
+    metric_write(
+        &mut output,
+        "hab_auto_build_server_builds_total",
+        "counter",
+        "Builds started through the server that have finished",
+        &[
+            ("{status=\"succeeded\"}".to_string(), builds_succeeded),
+            ("{status=\"failed\"}".to_string(), builds_failed),
+        ],
+    );
+    metric_write(
+        &mut output,
+        "hab_auto_build_server_builds_running",
+        "gauge",
+        "Builds started through the server that are running",
+        &[(String::new(), builds_running)],
+    );
+    metric_write(
+        &mut output,
+        "hab_auto_build_build_sessions_total",
+        "counter",
+        "Build sessions recorded in the store",
+        &[(String::new(), build_sessions.len() as f64)],
+    );
+    let session_sum =
+        |count: fn(&BuildSession) -> usize| build_sessions.iter().map(count).sum::<usize>() as f64;
+    metric_write(
+        &mut output,
+        "hab_auto_build_packages_built_total",
+        "counter",
+        "Packages successfully built in all build sessions",
+        &[(String::new(), session_sum(|session| session.succeeded))],
+    );
+    metric_write(
+        &mut output,
+        "hab_auto_build_packages_failed_total",
+        "counter",
+        "Packages that failed to build in all build sessions",
+        &[(String::new(), session_sum(|session| session.failed))],
+    );
+    metric_write(
+        &mut output,
+        "hab_auto_build_violations_total",
+        "counter",
+        "Violations found in the artifacts built in all build sessions",
+        &[
+            (
+                "{level=\"error\"}".to_string(),
+                session_sum(|session| session.error_count),
+            ),
+            (
+                "{level=\"warning\"}".to_string(),
+                session_sum(|session| session.warning_count),
+            ),
+        ],
+    );
+    metric_write(
+        &mut output,
+        "hab_auto_build_package_build_duration_seconds",
+        "gauge",
+        "Duration of the most recent build of each plan",
+        &build_times
+            .iter()
+            .map(|(plan, duration)| {
+                (
+                    format!("{{plan=\"{}\"}}", label_value_escape(plan)),
+                    *duration as f64,
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    if !build_times.is_empty() {
+        metric_write(
+            &mut output,
+            "hab_auto_build_package_build_duration_seconds_average",
+            "gauge",
+            "Average duration of the most recent build of each plan",
+            &[(
+                String::new(),
+                build_times
+                    .iter()
+                    .map(|(_, duration)| *duration as f64)
+                    .sum::<f64>()
+                    / build_times.len() as f64,
+            )],
+        );
+    }
+    let caches: [(&str, &ContextCacheStats); 2] = [
+        ("artifact", &ARTIFACT_CONTEXT_CACHE_STATS),
+        ("source", &SOURCE_CONTEXT_CACHE_STATS),
+    ];
+    metric_write(
+        &mut output,
+        "hab_auto_build_context_cache_lookups_total",
+        "counter",
+        "Lookups of artifact and source contexts cached in the store",
+        &caches
+            .iter()
+            .flat_map(|(context, stats)| {
+                [
+                    (
+                        format!("{{context=\"{}\",result=\"hit\"}}", context),
+                        stats.hits() as f64,
+                    ),
+                    (
+                        format!("{{context=\"{}\",result=\"miss\"}}", context),
+                        stats.misses() as f64,
+                    ),
+                ]
+            })
+            .collect::<Vec<_>>(),
+    );
+    metric_write(
+        &mut output,
+        "hab_auto_build_context_cache_hit_ratio",
+        "gauge",
+        "Ratio of lookups of artifact and source contexts found in the store",
+        &caches
+            .iter()
+            .filter(|(_, stats)| stats.hits() + stats.misses() > 0)
+            .map(|(context, stats)| {
+                (
+                    format!("{{context=\"{}\"}}", context),
+                    stats.hits() as f64 / (stats.hits() + stats.misses()) as f64,
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        output,
+    )
+        .into_response())
+}
+
+/// Writes a metric with its samples in the Prometheus text format, each sample
+/// is made of its labels and its value
+fn metric_write(
+    output: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: &[(String, f64)],
+) {
+    writeln!(output, "# HELP {} {}", name, help).unwrap();
+    writeln!(output, "# TYPE {} {}", name, kind).unwrap();
+    for (labels, value) in samples {
+        writeln!(output, "{}{} {}", name, labels, value).unwrap();
+    }
+}
+
+fn label_value_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Dependency analysis of a set of packages, equivalent to the JSON output of the 'analyze' command
+async fn analysis(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<AnalysisQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let packages = query
+        .packages
+        .split(',')
+        .map(PackageDepGlob::parse)
+        .collect::<Result<Vec<_>>>()
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("{:#}", err)))?;
+    let mut analysis_types = HashSet::new();
+    for analysis_type in query.types.iter().flat_map(|types| types.split(',')) {
+        analysis_types.insert(match analysis_type {
+            "studio-dep" => AnalysisType::StudioDependency,
+            "deps" => AnalysisType::Dependencies,
+            "build-deps" => AnalysisType::BuildDependencies,
+            "tdeps" => AnalysisType::TransitiveDependencies,
+            "build-tdeps" => AnalysisType::TransitiveBuildDependencies,
+            "rdeps" => AnalysisType::ReverseDependencies,
+            "build-rdeps" => AnalysisType::ReverseBuildDependencies,
+            "build-hosts" => AnalysisType::BuildHosts,
+            _ => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Unknown analysis type '{}'", analysis_type),
+                ))
+            }
+        });
+    }
+    let run_context = state.run_context();
+    tokio::task::spawn_blocking(move || {
+        let package_indices = run_context.glob_deps(&packages, query.target.unwrap_or_default())?;
+        let plan_analysis_list = package_indices
+            .into_iter()
+            .map(|package_index| run_context.dep_analysis(package_index, &analysis_types))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(serde_json::to_value(plan_analysis_list)?)
+    })
+    .await
+    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .map(Json)
+    .map_err(|err: color_eyre::eyre::Error| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", err))
+    })
 }
 
 // We use static route matchers ("/" and "/index.html") to serve our home
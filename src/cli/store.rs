@@ -0,0 +1,156 @@
+use std::{env, path::PathBuf};
+
+use clap::{Args, Subcommand};
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::core::{
+    store_archive_import, store_repair, AutoBuildConfig, AutoBuildContext, ChangeDetectionMode,
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Bundle the store database, source archives and optionally artifacts into a tar archive
+    Export(ExportParams),
+    /// Prime the store of the workspace from a tar archive created with 'store export'
+    Import(ImportParams),
+    /// Rebuild a corrupted store database from the artifact cache and the downloaded sources
+    Repair(RepairParams),
+}
+
+#[derive(Debug, Args)]
+struct ExportParams {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Do not include the source archives referenced by the plans
+    #[arg(long)]
+    no_sources: bool,
+    /// Include the latest artifacts built from the plans
+    #[arg(long)]
+    artifacts: bool,
+    /// Path of the tar archive to create
+    output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct ImportParams {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Replace the database of a store that is already initialized
+    #[arg(short, long)]
+    force: bool,
+    /// Path of the tar archive to import
+    archive: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct RepairParams {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Rebuild the database even if it is not corrupted
+    #[arg(short, long)]
+    force: bool,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    match args.command {
+        Command::Export(args) => export_execute(args),
+        Command::Import(args) => import_execute(args),
+        Command::Repair(args) => repair_execute(args),
+    }
+}
+
+fn export_execute(args: ExportParams) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let summary = run_context.store_export(!args.no_sources, args.artifacts, &args.output)?;
+    info!(target: "user-log",
+        "Exported hab-auto-build store with {} source archives and {} artifacts to {}",
+        summary.sources,
+        summary.artifacts,
+        args.output.display().white()
+    );
+    Ok(())
+}
+
+fn import_execute(args: ImportParams) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let store_path = config.store_path(&config_path)?;
+    let workspace = config.workspace_id(&config_path)?;
+
+    let summary = store_archive_import(&store_path, &workspace, &args.archive, args.force)?;
+    info!(target: "user-log",
+        "Imported hab-auto-build store with {} source archives and {} artifacts into {}",
+        summary.sources,
+        summary.artifacts,
+        store_path.display().white()
+    );
+    if summary.artifacts_skipped > 0 {
+        info!(target: "user-log",
+            "Skipped {} artifacts that were already present in the artifact cache",
+            summary.artifacts_skipped
+        );
+    }
+    Ok(())
+}
+
+fn repair_execute(args: RepairParams) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let store_path = config.store_path(&config_path)?;
+    let workspace = config.workspace_id(&config_path)?;
+
+    let Some(summary) = store_repair(&store_path, &workspace, args.force)? else {
+        info!(target: "user-log",
+            "The hab-auto-build database at {} is healthy, use --force to rebuild it anyway",
+            store_path.display().white()
+        );
+        return Ok(());
+    };
+    if let Some(backup_path) = summary.backup_path.as_ref() {
+        info!(target: "user-log",
+            "Moved the previous database to {}",
+            backup_path.display().white()
+        );
+    }
+    info!(target: "user-log",
+        "Rebuilt the hab-auto-build database with {} source archives and {} artifacts",
+        summary.sources,
+        summary.artifacts
+    );
+    if summary.failures > 0 {
+        info!(target: "user-log",
+            "{}: {} source archives and artifacts could not be read, they were left out of the database",
+            "warning".bold().yellow(),
+            summary.failures
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use std::{env, fmt::Write, path::PathBuf};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::{
+    cli::output::{
+        self, JsonOutputKind, PlanOutput, RepoSummaryOutput, ReportFormat, SummaryOutput,
+    },
+    core::{AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PlanContext, WorkspaceSummary},
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Output format
+    #[arg(value_enum, short = 'f', long, default_value_t = ReportFormat::Plain)]
+    format: ReportFormat,
+    /// Also write the summary as JSON to a file, to keep snapshots of the workspace health
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let summary = run_context.workspace_summary();
+    if let Some(output_path) = args.output.as_ref() {
+        std::fs::write(
+            output_path,
+            output::json_string(JsonOutputKind::Summary, summary_output(&summary))?,
+        )
+        .with_context(|| {
+            eyre!(
+                "Failed to write workspace summary to '{}'",
+                output_path.display()
+            )
+        })?;
+    }
+    match args.format {
+        ReportFormat::Plain => output_plain(&summary),
+        ReportFormat::Json => {
+            output::output_json(JsonOutputKind::Summary, summary_output(&summary))
+        }
+        ReportFormat::Markdown => output_markdown(&summary),
+    }
+}
+
+fn summary_output(summary: &WorkspaceSummary) -> SummaryOutput {
+    let plans_output = |plans: &[&PlanContext]| {
+        plans
+            .iter()
+            .map(|plan_ctx| PlanOutput::from(*plan_ctx))
+            .collect()
+    };
+    SummaryOutput {
+        repos: summary
+            .repos
+            .iter()
+            .map(|(repo_ctx, plan_count)| RepoSummaryOutput {
+                id: repo_ctx.id.to_string(),
+                path: repo_ctx.path.as_ref().to_path_buf(),
+                plans: *plan_count,
+            })
+            .collect(),
+        plans: summary.plan_count(),
+        plans_by_studio: summary
+            .plans_by_studio
+            .iter()
+            .map(|(studio, plan_count)| (studio.to_string(), *plan_count))
+            .collect(),
+        plans_by_origin: summary
+            .plans_by_origin
+            .iter()
+            .map(|(origin, plan_count)| (origin.to_string(), *plan_count))
+            .collect(),
+        plans_without_artifact: plans_output(&summary.plans_without_artifact),
+        plans_missing_source: plans_output(&summary.plans_missing_source),
+        plans_with_config: plans_output(&summary.plans_with_config),
+    }
+}
+
+fn output_plain(summary: &WorkspaceSummary) -> Result<()> {
+    let plan_count = summary.plan_count();
+    info!(target: "user-ui", "{}", "Repos:".white().bold());
+    for (repo_ctx, plan_count) in summary.repos.iter() {
+        info!(target: "user-ui", "{} ({} plans) {}", repo_ctx.id, plan_count, repo_ctx.path.as_ref().display().bright_black());
+    }
+    info!(target: "user-ui", "");
+    info!(target: "user-ui", "{}", "Plans by studio:".white().bold());
+    for (studio, plan_count) in summary.plans_by_studio.iter() {
+        info!(target: "user-ui", "{}: {}", studio, plan_count);
+    }
+    info!(target: "user-ui", "");
+    info!(target: "user-ui", "{}", "Plans by origin:".white().bold());
+    for (origin, plan_count) in summary.plans_by_origin.iter() {
+        info!(target: "user-ui", "{}: {}", origin, plan_count);
+    }
+    for (header, plans) in [
+        (
+            "Plans without an artifact in the cache:",
+            &summary.plans_without_artifact,
+        ),
+        (
+            "Plans with sources not downloaded:",
+            &summary.plans_missing_source,
+        ),
+        (
+            "Plans with a .hab-plan-config.toml:",
+            &summary.plans_with_config,
+        ),
+    ] {
+        if plans.is_empty() {
+            continue;
+        }
+        info!(target: "user-ui", "");
+        info!(target: "user-ui", "{}", header.white().bold());
+        for plan_ctx in plans.iter() {
+            info!(target: "user-ui", "{}", plan_ctx.id);
+        }
+    }
+    info!(target: "user-ui", "");
+    info!(
+        target: "user-log",
+        "Found {} plans in {} repos, {} have an artifact in the cache, {} have sources to download",
+        plan_count,
+        summary.repos.len(),
+        plan_count - summary.plans_without_artifact.len(),
+        summary.plans_missing_source.len()
+    );
+    Ok(())
+}
+
+fn output_markdown(summary: &WorkspaceSummary) -> Result<()> {
+    let mut output = String::new();
+    let plan_count = summary.plan_count();
+    writeln!(output, "## Repos\n")?;
+    writeln!(output, "| Repo | Path | Plans |")?;
+    writeln!(output, "| --- | --- | --- |")?;
+    for (repo_ctx, plan_count) in summary.repos.iter() {
+        writeln!(
+            output,
+            "| {} | {} | {} |",
+            repo_ctx.id,
+            repo_ctx.path.as_ref().display(),
+            plan_count
+        )?;
+    }
+    writeln!(output, "\n## Plans\n")?;
+    writeln!(output, "| Plans | Count |")?;
+    writeln!(output, "| --- | --- |")?;
+    writeln!(output, "| Total | {} |", plan_count)?;
+    for (studio, plan_count) in summary.plans_by_studio.iter() {
+        writeln!(output, "| Studio {} | {} |", studio, plan_count)?;
+    }
+    for (origin, plan_count) in summary.plans_by_origin.iter() {
+        writeln!(output, "| Origin {} | {} |", origin, plan_count)?;
+    }
+    writeln!(
+        output,
+        "| With an artifact in the cache | {} |",
+        plan_count - summary.plans_without_artifact.len()
+    )?;
+    writeln!(
+        output,
+        "| With sources not downloaded | {} |",
+        summary.plans_missing_source.len()
+    )?;
+    writeln!(
+        output,
+        "| With a .hab-plan-config.toml | {} |",
+        summary.plans_with_config.len()
+    )?;
+    for (header, plans) in [
+        (
+            "Plans without an artifact in the cache",
+            &summary.plans_without_artifact,
+        ),
+        (
+            "Plans with sources not downloaded",
+            &summary.plans_missing_source,
+        ),
+        (
+            "Plans with a .hab-plan-config.toml",
+            &summary.plans_with_config,
+        ),
+    ] {
+        if plans.is_empty() {
+            continue;
+        }
+        writeln!(output, "\n## {}\n", header)?;
+        for plan_ctx in plans.iter() {
+            writeln!(output, "- {}", plan_ctx.id)?;
+        }
+    }
+    info!(target: "user-ui", "{}", output.trim_end());
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::{env, path::PathBuf};
+
+use clap::{Args, Subcommand};
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::OwoColorize;
+use tracing::{error, info};
+
+use crate::core::{
+    AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepGlob, PackageTarget,
+    PlanContext, PlanFileTimestamp,
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Show the file modification times recorded for plans and their effect on change detection
+    Show(TimestampsParams),
+    /// Clear the file modification times recorded for plans, so changes are detected using the real modification times
+    Reset(TimestampsParams),
+}
+
+#[derive(Debug, Args)]
+struct TimestampsParams {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Package target of the plans, defaults to the configured target
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// List of plans
+    #[arg(required = true)]
+    packages: Vec<PackageDepGlob>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let (args, is_reset) = match args.command {
+        Command::Show(args) => (args, false),
+        Command::Reset(args) => (args, true),
+    };
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+    let target = args.target.unwrap_or(config.target());
+
+    let mut run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let package_indices = run_context
+        .glob_deps(&args.packages, target)?
+        .into_iter()
+        .filter(|package_index| run_context.dep(*package_index).plan_ctx().is_some())
+        .collect::<Vec<_>>();
+    if package_indices.is_empty() {
+        error!(target: "user-log",
+            "No plans found matching patterns: {}",
+            serde_json::to_string(&args.packages).unwrap()
+        );
+        return Ok(());
+    }
+
+    let mut connection = run_context.get_connection()?;
+    for package_index in package_indices {
+        if is_reset {
+            let timestamps = connection.exclusive_transaction(|connection| {
+                run_context.plan_file_timestamps_reset(connection, package_index)
+            })?;
+            let plan_ctx = run_context.dep(package_index).plan_ctx().unwrap();
+            if timestamps.is_empty() {
+                info!(target: "user-ui", "{}: No timestamps recorded", plan_ctx.id.white().bold());
+            } else {
+                info!(target: "user-ui", "{}: Cleared {} recorded timestamps", plan_ctx.id.white().bold(), timestamps.len());
+            }
+            output_change_detection(plan_ctx);
+        } else {
+            let timestamps = run_context.plan_file_timestamps(&mut connection, package_index)?;
+            let plan_ctx = run_context.dep(package_index).plan_ctx().unwrap();
+            info!(target: "user-ui", "{}:", plan_ctx.id.white().bold());
+            output_timestamps(plan_ctx, &timestamps);
+            output_change_detection(plan_ctx);
+        }
+        info!(target: "user-ui", "");
+    }
+    Ok(())
+}
+
+fn output_timestamps(plan_ctx: &PlanContext, timestamps: &[PlanFileTimestamp]) {
+    if timestamps.is_empty() {
+        info!(target: "user-ui", "No timestamps recorded, changes are detected using the real modification times of the files");
+        return;
+    }
+    info!(target: "user-ui", "Recorded timestamps:");
+    for timestamp in timestamps {
+        let path = timestamp
+            .path
+            .strip_prefix(plan_ctx.context_path.as_ref())
+            .unwrap_or(timestamp.path.as_path());
+        let effect = match (timestamp.real_modified_at, plan_ctx.latest_artifact.as_ref()) {
+            (None, _) => "stale, the file no longer exists".dimmed().to_string(),
+            (Some(_), _) if !timestamp.is_active() => {
+                "stale, the file was modified after the timestamp was recorded so its real modification time is used"
+                    .dimmed()
+                    .to_string()
+            }
+            (Some(_), Some(latest_artifact))
+                if timestamp.alternate_modified_at > latest_artifact.created_at =>
+            {
+                "active, newer than the latest artifact so the plan is rebuilt"
+                    .yellow()
+                    .to_string()
+            }
+            (Some(_), Some(_)) => "active, older than the latest artifact so changes to the file are ignored"
+                .green()
+                .to_string(),
+            (Some(_), None) => "active, no artifact was built for the plan yet so it has no effect"
+                .dimmed()
+                .to_string(),
+        };
+        info!(target: "user-ui", "  {}: recorded for modification time {}, replaced by {} ({})",
+            path.display().blue(),
+            timestamp.recorded_modified_at,
+            timestamp.alternate_modified_at,
+            effect
+        );
+    }
+}
+
+fn output_change_detection(plan_ctx: &PlanContext) {
+    let Some(latest_artifact) = plan_ctx.latest_artifact.as_ref() else {
+        info!(target: "user-ui", "Change detection: {}", "no artifact built for the plan, it will be built".yellow());
+        return;
+    };
+    info!(target: "user-ui", "Latest artifact: {} built at {}", latest_artifact.ident, latest_artifact.created_at);
+    if plan_ctx.files_changed_on_disk.is_empty() {
+        info!(target: "user-ui", "Change detection: {}", "no files modified after the latest artifact was built".green());
+    } else {
+        info!(target: "user-ui", "Change detection: {}", format!("{} files modified after the latest artifact was built, the plan will be rebuilt", plan_ctx.files_changed_on_disk.len()).yellow());
+        for file_change in plan_ctx.files_changed_on_disk.iter() {
+            let path = file_change
+                .path
+                .as_ref()
+                .strip_prefix(plan_ctx.context_path.as_ref())
+                .unwrap_or(file_change.path.as_ref());
+            if file_change.last_modified_at != file_change.real_last_modified_at {
+                info!(target: "user-ui", "  {}: modified at {} (recorded timestamp, really modified at {})", path.display().blue(), file_change.last_modified_at, file_change.real_last_modified_at);
+            } else {
+                info!(target: "user-ui", "  {}: modified at {}", path.display().blue(), file_change.last_modified_at);
+            }
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, IsTerminal, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::{
+    eyre::{eyre, Result},
+    Help,
+};
+use lazy_static::lazy_static;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::{
+    cli::annotate::ANSI_ESCAPE_SEQUENCE,
+    core::{AutoBuildContext, BuildStep, PackageDepIdent, PackageRelease, PackageVersion},
+    store::BuildStepStatus,
+};
+
+/// Interval at which the dashboard refreshes the state of the build
+const DASHBOARD_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+/// Amount of data read from the end of a build log for the log pane
+const DASHBOARD_LOG_TAIL_BYTES: u64 = 256 * 1024;
+/// Number of lines of captured output shown below the packages
+const DASHBOARD_MESSAGE_LINES: u16 = 6;
+
+lazy_static! {
+    /// Output of the 'user-ui' and 'user-log' targets, captured while the dashboard
+    /// is shown so that it does not garble the screen
+    static ref CAPTURED_OUTPUT: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+}
+
+/// Writer for the user facing output, the output is captured instead of being
+/// printed while the build dashboard is shown.
+pub(crate) struct UserOutput;
+
+impl<'a> MakeWriter<'a> for UserOutput {
+    type Writer = UserOutputWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        UserOutputWriter
+    }
+}
+
+pub(crate) struct UserOutputWriter;
+
+impl Write for UserOutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(captured_output) = CAPTURED_OUTPUT.lock().unwrap().as_mut() {
+            captured_output.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+        io::stdout().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// Build step as shown in the dashboard
+struct DashboardStep {
+    plan: String,
+    studio: String,
+    package: PackageDepIdent,
+    estimate: Option<Duration>,
+    status: BuildStepStatus,
+    started_at: Option<Instant>,
+    duration: Option<Duration>,
+}
+
+struct Dashboard {
+    steps: Vec<DashboardStep>,
+    table_state: TableState,
+    /// Line of the log shown at the top of the log pane, the log pane follows the
+    /// end of the log when not set
+    log_scroll: Option<usize>,
+    log_path: Option<PathBuf>,
+    log_lines: Vec<String>,
+    started_at: Instant,
+    finished: bool,
+}
+
+/// Verifies that the build dashboard can be shown
+pub(crate) fn dashboard_verify() -> Result<()> {
+    if !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        return Err(eyre!("The build dashboard can only be shown in a terminal"))
+            .with_suggestion(|| "Remove '--tui' to print the build progress instead");
+    }
+    Ok(())
+}
+
+/// Runs the build while showing a dashboard of the build steps, the build runs
+/// in a separate thread and its output is printed once the dashboard is closed.
+pub(crate) fn build_dashboard_run(
+    run_context: &AutoBuildContext,
+    build_steps: &[BuildStep],
+    build: impl FnOnce() -> Result<bool> + Send,
+) -> Result<bool> {
+    let mut dashboard = Dashboard {
+        steps: build_steps
+            .iter()
+            .map(|step| DashboardStep {
+                plan: step.plan_ctx.id.to_string(),
+                studio: step.studio.to_string(),
+                package: PackageDepIdent {
+                    version: PackageVersion::Unresolved,
+                    release: PackageRelease::Unresolved,
+                    ..PackageDepIdent::from(step.plan_ctx.id.as_ref())
+                },
+                estimate: step
+                    .build_duration
+                    .and_then(|build_duration| build_duration.to_std().ok()),
+                status: BuildStepStatus::Pending,
+                started_at: None,
+                duration: None,
+            })
+            .collect(),
+        table_state: TableState::default().with_selected(Some(0)),
+        log_scroll: None,
+        log_path: None,
+        log_lines: Vec::new(),
+        started_at: Instant::now(),
+        finished: false,
+    };
+    let target = build_steps
+        .first()
+        .map(|step| step.plan_ctx.id.as_ref().target)
+        .unwrap_or_default();
+
+    *CAPTURED_OUTPUT.lock().unwrap() = Some(Vec::new());
+    let mut terminal = ratatui::init();
+    let result = thread::scope(|scope| {
+        let build_thread = scope.spawn(build);
+        let mut follow_selection = true;
+        loop {
+            if !dashboard.finished && build_thread.is_finished() {
+                dashboard.finished = true;
+            }
+            dashboard_state_update(&mut dashboard, run_context, follow_selection);
+            dashboard_log_update(&mut dashboard, run_context, target);
+            if let Err(err) = dashboard_draw(&mut terminal, &mut dashboard) {
+                break Some(Err(err));
+            }
+            match dashboard_event_handle(&mut dashboard) {
+                Ok(DashboardAction::None) => {}
+                Ok(DashboardAction::Select) => follow_selection = false,
+                Ok(DashboardAction::Quit) => break None,
+                Ok(DashboardAction::Interrupt) => {
+                    dashboard_close();
+                    build_interrupt();
+                }
+                Err(err) => break Some(Err(err)),
+            }
+        }
+        .unwrap_or_else(|| {
+            build_thread
+                .join()
+                .unwrap_or_else(|_| Err(eyre!("The build panicked")))
+        })
+    });
+    dashboard_close();
+    result
+}
+
+enum DashboardAction {
+    None,
+    Select,
+    Quit,
+    Interrupt,
+}
+
+/// Restores the terminal and prints the output captured while the dashboard was shown
+fn dashboard_close() {
+    ratatui::restore();
+    if let Some(captured_output) = CAPTURED_OUTPUT.lock().unwrap().take() {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(&captured_output);
+        let _ = stdout.flush();
+    }
+}
+
+/// Interrupts the build like Ctrl-C does when the dashboard is not shown, the
+/// terminal does not send the signal to the running builds in raw mode
+fn build_interrupt() -> ! {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(0, libc::SIGINT);
+    }
+    std::process::exit(130)
+}
+
+fn dashboard_state_update(
+    dashboard: &mut Dashboard,
+    run_context: &AutoBuildContext,
+    follow_selection: bool,
+) {
+    let Ok(Some((_, step_states))) = run_context.last_build_session_step_states() else {
+        return;
+    };
+    let statuses = step_states
+        .into_iter()
+        .map(|step_state| (step_state.plan, step_state.status))
+        .collect::<HashMap<_, _>>();
+    for (position, step) in dashboard.steps.iter_mut().enumerate() {
+        let Some(status) = statuses.get(&step.plan) else {
+            continue;
+        };
+        if *status == step.status {
+            continue;
+        }
+        match status {
+            BuildStepStatus::Building => {
+                step.started_at = Some(Instant::now());
+                if follow_selection {
+                    dashboard.table_state.select(Some(position));
+                    dashboard.log_scroll = None;
+                }
+            }
+            BuildStepStatus::Built | BuildStepStatus::Failed => {
+                step.duration = step.started_at.map(|started_at| started_at.elapsed());
+            }
+            BuildStepStatus::Pending => {}
+        }
+        step.status = *status;
+    }
+}
+
+fn dashboard_log_update(
+    dashboard: &mut Dashboard,
+    run_context: &AutoBuildContext,
+    target: crate::core::PackageTarget,
+) {
+    let Some(step) = dashboard
+        .table_state
+        .selected()
+        .and_then(|position| dashboard.steps.get(position))
+    else {
+        return;
+    };
+    // Logs of earlier builds of the package are not relevant until it is built
+    if step.status == BuildStepStatus::Pending {
+        dashboard.log_path = None;
+        dashboard.log_lines.clear();
+        return;
+    }
+    dashboard.log_path = run_context
+        .build_logs(&step.package, target)
+        .ok()
+        .and_then(|build_logs| build_logs.into_iter().next())
+        .map(|build_log| build_log.path);
+    dashboard.log_lines = dashboard
+        .log_path
+        .as_ref()
+        .and_then(|log_path| log_tail_read(log_path).ok())
+        .map(|log| {
+            log.lines()
+                .map(|line| ANSI_ESCAPE_SEQUENCE.replace_all(line, "").to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+}
+
+fn log_tail_read(log_path: &PathBuf) -> Result<String> {
+    let mut file = File::open(log_path)?;
+    let length = file.metadata()?.len();
+    let start = length.saturating_sub(DASHBOARD_LOG_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    let log = String::from_utf8_lossy(&data).to_string();
+    // The first line is likely partial when the log is read from the middle
+    if start > 0 {
+        return Ok(log
+            .split_once('\n')
+            .map(|(_, log)| log.to_string())
+            .unwrap_or_default());
+    }
+    Ok(log)
+}
+
+fn dashboard_event_handle(dashboard: &mut Dashboard) -> Result<DashboardAction> {
+    if !event::poll(DASHBOARD_REFRESH_INTERVAL)? {
+        return Ok(DashboardAction::None);
+    }
+    let Event::Key(key) = event::read()? else {
+        return Ok(DashboardAction::None);
+    };
+    if key.kind != KeyEventKind::Press {
+        return Ok(DashboardAction::None);
+    }
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            return Ok(DashboardAction::Interrupt);
+        }
+        KeyCode::Char('q') | KeyCode::Esc if dashboard.finished => {
+            return Ok(DashboardAction::Quit);
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            dashboard.table_state.select_previous();
+            dashboard.log_scroll = None;
+            return Ok(DashboardAction::Select);
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            dashboard.table_state.select_next();
+            dashboard.log_scroll = None;
+            return Ok(DashboardAction::Select);
+        }
+        KeyCode::PageUp => {
+            let log_scroll = dashboard.log_scroll.unwrap_or(dashboard.log_lines.len());
+            dashboard.log_scroll = Some(log_scroll.saturating_sub(20));
+        }
+        KeyCode::PageDown => {
+            if let Some(log_scroll) = dashboard.log_scroll {
+                dashboard.log_scroll = Some(log_scroll + 20);
+            }
+        }
+        KeyCode::Home => dashboard.log_scroll = Some(0),
+        KeyCode::End => dashboard.log_scroll = None,
+        _ => {}
+    }
+    Ok(DashboardAction::None)
+}
+
+fn dashboard_draw(terminal: &mut DefaultTerminal, dashboard: &mut Dashboard) -> Result<()> {
+    terminal.draw(|frame| dashboard_render(frame, dashboard))?;
+    Ok(())
+}
+
+fn dashboard_render(frame: &mut Frame, dashboard: &mut Dashboard) {
+    let [header_area, body_area, messages_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(DASHBOARD_MESSAGE_LINES + 2),
+    ])
+    .areas(frame.area());
+    let [steps_area, log_area] =
+        Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .areas(body_area);
+
+    let done = dashboard
+        .steps
+        .iter()
+        .filter(|step| {
+            matches!(
+                step.status,
+                BuildStepStatus::Built | BuildStepStatus::Failed
+            )
+        })
+        .count();
+    let estimate = dashboard
+        .steps
+        .iter()
+        .filter_map(|step| step.estimate)
+        .sum::<Duration>();
+    let header = format!(
+        " Built {}/{} plans | elapsed {} | estimated {} | {}",
+        done,
+        dashboard.steps.len(),
+        duration_format(dashboard.started_at.elapsed()),
+        duration_format(estimate),
+        if dashboard.finished {
+            "build finished, q: quit"
+        } else {
+            "↑↓: select, PgUp/PgDn/Home/End: scroll log, Ctrl-C: interrupt"
+        }
+    );
+    frame.render_widget(Paragraph::new(header).bold(), header_area);
+
+    let rows = dashboard.steps.iter().map(|step| {
+        let (status, color) = match step.status {
+            BuildStepStatus::Pending => ("queued", Color::DarkGray),
+            BuildStepStatus::Building => ("building", Color::Yellow),
+            BuildStepStatus::Built => ("succeeded", Color::Green),
+            BuildStepStatus::Failed => ("failed", Color::Red),
+        };
+        let elapsed = step
+            .duration
+            .or_else(|| step.started_at.map(|started_at| started_at.elapsed()));
+        let time = match (elapsed, step.estimate) {
+            (Some(elapsed), Some(estimate)) => format!(
+                "{} / {}",
+                duration_format(elapsed),
+                duration_format(estimate)
+            ),
+            (Some(elapsed), None) => duration_format(elapsed),
+            (None, Some(estimate)) => format!("~{}", duration_format(estimate)),
+            (None, None) => String::new(),
+        };
+        Row::new(vec![
+            status.to_string(),
+            step.studio.clone(),
+            step.plan.clone(),
+            time,
+        ])
+        .style(Style::default().fg(color))
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Min(20),
+            Constraint::Length(17),
+        ],
+    )
+    .header(Row::new(vec!["Status", "Studio", "Plan", "Elapsed / Est."]).bold())
+    .block(Block::bordered().title(" Packages "))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, steps_area, &mut dashboard.table_state);
+
+    let log_height = log_area.height.saturating_sub(2) as usize;
+    let max_scroll = dashboard.log_lines.len().saturating_sub(log_height);
+    let log_scroll = dashboard
+        .log_scroll
+        .map(|log_scroll| log_scroll.min(max_scroll))
+        .unwrap_or(max_scroll);
+    if dashboard.log_scroll.is_some() {
+        dashboard.log_scroll = Some(log_scroll);
+    }
+    let log_title = match dashboard.log_path.as_ref() {
+        Some(log_path) => format!(" {} ", log_path.display()),
+        None => " No build log ".to_string(),
+    };
+    let log = Text::from(
+        dashboard.log_lines[log_scroll..]
+            .iter()
+            .take(log_height)
+            .map(|line| Line::raw(line.as_str()))
+            .collect::<Vec<_>>(),
+    );
+    frame.render_widget(
+        Paragraph::new(log).block(Block::bordered().title(log_title)),
+        log_area,
+    );
+
+    let messages = CAPTURED_OUTPUT
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|captured_output| {
+            let captured_output = String::from_utf8_lossy(captured_output);
+            let lines = captured_output
+                .lines()
+                .map(|line| ANSI_ESCAPE_SEQUENCE.replace_all(line, "").to_string())
+                .collect::<Vec<_>>();
+            lines[lines.len().saturating_sub(DASHBOARD_MESSAGE_LINES as usize)..].to_vec()
+        })
+        .unwrap_or_default();
+    frame.render_widget(
+        Paragraph::new(Text::from(
+            messages.into_iter().map(Line::raw).collect::<Vec<_>>(),
+        ))
+        .block(Block::bordered().title(" Messages ")),
+        messages_area,
+    );
+}
+
+fn duration_format(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
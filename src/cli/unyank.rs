@@ -0,0 +1,47 @@
+use std::{env, path::PathBuf};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::core::{
+    AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepIdent, PackageTarget,
+};
+
+use super::yank::artifact_ident;
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Package target of the artifact to restore
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// Fully qualified identifier of the yanked artifact, as <ORIGIN>/<NAME>/<VERSION>/<RELEASE>
+    package: PackageDepIdent,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let artifact_ident = artifact_ident(&args.package, args.target)?;
+    if !run_context.artifact_unyank(&artifact_ident)? {
+        return Err(eyre!("The artifact {} is not yanked", artifact_ident))
+            .with_suggestion(|| "Use 'hab-auto-build yank --list' to view the yanked artifacts");
+    }
+    info!(target: "user-ui", "{} {}", "     Restored".green().bold(), artifact_ident);
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::{env, path::PathBuf};
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::core::{AutoBuildConfig, AutoBuildContext, RepoGitUpdate};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    // Repos that were not cloned yet are cloned while initializing the context
+    let run_context = AutoBuildContext::new_without_artifact_cache(&config, &config_path)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let updates = run_context.repos_git_update();
+    if updates.is_empty() {
+        info!(target: "user-ui", "No repos are cloned from a git remote");
+        return Ok(());
+    }
+    let mut failed = 0;
+    for (repo_ctx, update) in updates {
+        let branch = repo_ctx
+            .git
+            .as_ref()
+            .map(|git| git.branch.as_str())
+            .unwrap_or_default();
+        match update {
+            Ok(RepoGitUpdate::Updated { from, to }) => {
+                info!(target: "user-ui",
+                    "{} {} to {} on branch '{}'",
+                    format!("{}:", repo_ctx.id).cyan().bold(),
+                    "Updated".green(),
+                    format!("{}..{}", short_commit(&from), short_commit(&to)).magenta(),
+                    branch
+                );
+            }
+            Ok(RepoGitUpdate::UpToDate) | Ok(RepoGitUpdate::Cloned) => {
+                info!(target: "user-ui",
+                    "{} Up to date with branch '{}'",
+                    format!("{}:", repo_ctx.id).cyan().bold(),
+                    branch
+                );
+            }
+            Err(err) => {
+                failed += 1;
+                info!(target: "user-ui",
+                    "{} {} {:?}",
+                    format!("{}:", repo_ctx.id).cyan().bold(),
+                    "error:".bold().red(),
+                    err
+                );
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(eyre!("Failed to update {} repos", failed));
+    }
+    Ok(())
+}
+
+fn short_commit(commit: &str) -> &str {
+    &commit[..commit.len().min(10)]
+}
@@ -0,0 +1,82 @@
+use std::{env, path::PathBuf};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::core::{AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, SourceVerifyStatus};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Move corrupted source archives to the invalid sources folder of the store
+    #[arg(short, long)]
+    quarantine: bool,
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    let results = run_context
+        .get_connection()?
+        .exclusive_transaction(|connection| {
+            run_context
+                .sources_verify(connection, args.quarantine)
+                .map_err(|err| eyre!(err))
+        })?;
+
+    let mut invalid_count = 0;
+    for result in results.iter() {
+        let plans = if result.plans.is_empty() {
+            "no plans".to_string()
+        } else {
+            result
+                .plans
+                .iter()
+                .map(|plan| plan.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match &result.status {
+            SourceVerifyStatus::Valid => {
+                info!(target: "user-ui", "{} {} ({})", "     Verified".green().bold(), result.shasum, plans);
+                continue;
+            }
+            SourceVerifyStatus::Mismatch(actual) => {
+                info!(target: "user-ui", "{} {} ({}): archive has shasum {}", "     Mismatch".red().bold(), result.shasum, plans, actual.red());
+            }
+            SourceVerifyStatus::Missing => {
+                info!(target: "user-ui", "{} {} ({}): archive not found in {}", "      Missing".red().bold(), result.shasum, plans, result.path.display());
+            }
+        }
+        invalid_count += 1;
+        if result.quarantined {
+            info!(target: "user-ui", "{} {}", "  Quarantined".yellow().bold(), result.path.display());
+        }
+    }
+    info!(target: "user-log", "Verified {} source archives, {} are invalid", results.len(), invalid_count);
+    if invalid_count > 0 && !args.quarantine {
+        return Err(eyre!(
+            "Found {} invalid source archives in the hab-auto-build store",
+            invalid_count
+        ))
+        .with_suggestion(|| {
+            "Use '--quarantine' to move them out of the way, they will be downloaded again on the next build"
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::{env, path::PathBuf};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use tracing::info;
+
+use crate::core::{
+    AutoBuildConfig, AutoBuildContext, ChangeDetectionMode, PackageDepIdent, PackageIdent,
+    PackageRelease, PackageTarget, PackageVersion,
+};
+
+#[derive(Debug, Args)]
+pub(crate) struct Params {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// List the yanked artifacts instead of yanking one
+    #[arg(short, long, conflicts_with_all = ["reason", "package"])]
+    list: bool,
+    /// Reason for yanking the artifact, shown when listing yanked artifacts
+    #[arg(short, long)]
+    reason: Option<String>,
+    /// Package target of the artifact to yank
+    #[arg(short, long)]
+    target: Option<PackageTarget>,
+    /// Fully qualified identifier of the artifact to yank, as <ORIGIN>/<NAME>/<VERSION>/<RELEASE>
+    #[arg(required_unless_present = "list")]
+    package: Option<PackageDepIdent>,
+}
+
+/// Converts a fully qualified package identifier into the identifier of an artifact
+pub(super) fn artifact_ident(
+    package: &PackageDepIdent,
+    target: Option<PackageTarget>,
+) -> Result<PackageIdent> {
+    match (&package.version, &package.release) {
+        (PackageVersion::Resolved(version), PackageRelease::Resolved(release)) => {
+            Ok(PackageIdent {
+                origin: package.origin.clone(),
+                name: package.name.clone(),
+                version: version.clone(),
+                release: release.clone(),
+                target: target.unwrap_or_default(),
+            })
+        }
+        _ => Err(eyre!("The package '{}' is not fully qualified", package))
+            .with_suggestion(|| "Specify the artifact as <ORIGIN>/<NAME>/<VERSION>/<RELEASE>"),
+    }
+}
+
+pub(crate) fn execute(args: Params) -> Result<()> {
+    let config_path = args.config_path.unwrap_or(
+        env::current_dir()
+            .context("Failed to determine current working directory")?
+            .join("hab-auto-build.json"),
+    );
+    let config = AutoBuildConfig::new(&config_path)?;
+
+    let run_context = AutoBuildContext::new(&config, &config_path, ChangeDetectionMode::Disk)
+        .with_context(|| eyre!("Failed to initialize run"))?;
+
+    if args.list {
+        let yanked_artifacts = run_context.yanked_artifacts()?;
+        for yanked_artifact in yanked_artifacts.iter() {
+            info!(target: "user-ui", "{} {} at {}{}", "       Yanked".yellow().bold(), yanked_artifact.artifact, yanked_artifact.yanked_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                yanked_artifact.reason.as_ref().map(|reason| format!(": {}", reason)).unwrap_or_default());
+        }
+        info!(target: "user-log", "Found {} yanked artifacts", yanked_artifacts.len());
+        return Ok(());
+    }
+
+    let artifact_ident = artifact_ident(args.package.as_ref().unwrap(), args.target)?;
+    run_context
+        .artifact_yank(&artifact_ident, args.reason.as_deref())
+        .with_suggestion(|| "Use 'hab-auto-build analyze' to view the artifacts used by plans")?;
+    info!(target: "user-ui", "{} {}, it will no longer be used to resolve dependencies", "       Yanked".yellow().bold(), artifact_ident);
+    Ok(())
+}
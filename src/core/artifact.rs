@@ -7,11 +7,20 @@ use color_eyre::{Help, SectionExt};
 use diesel::Connection;
 use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use goblin::{
+    container::Ctx,
+    elf::{
+        dynamic::{DT_RPATH, DT_RUNPATH},
+        program_header::{PT_DYNAMIC, PT_INTERP, PT_LOAD},
+        section_header::{SHN_XINDEX, SHT_GNU_VERDEF, SHT_GNU_VERNEED, SHT_NOBITS},
+        symver::VER_FLG_BASE,
+        Dynamic, Elf, ProgramHeader, SectionHeader, VerdefSection, VerneedSection,
+    },
     elf64::{
         dynamic::DF_1_PIE,
-        header::{ET_DYN, ET_EXEC},
+        header::{ET_DYN, ET_EXEC, SIZEOF_EHDR},
     },
     mach::{Mach, SingleArch},
+    strtab::Strtab,
     Object,
 };
 use ignore::{ParallelVisitor, ParallelVisitorBuilder, WalkBuilder, WalkState};
@@ -19,27 +28,27 @@ use lazy_static::lazy_static;
 use path_absolutize::Absolutize;
 use rayon::prelude::*;
 use reqwest::Url;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+#[cfg(not(target_os = "windows"))]
+use std::process::{Command, Stdio};
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     ffi::OsStr,
     fmt::Display,
-    io::{BufRead, BufReader, Read},
-    ops::Deref,
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write},
+    ops::{Deref, Range},
     path::{Path, PathBuf},
     sync::{
-        mpsc::{channel, Sender},
+        mpsc::{channel, sync_channel, Sender},
         Arc, RwLock, RwLockWriteGuard,
     },
     time::Instant,
 };
-#[cfg(not(target_os = "windows"))]
-use std::{
-    io::Write,
-    process::{Command, Stdio},
-};
 use tar::Archive;
-use tracing::{debug, error, info, trace};
+use tempdir::TempDir;
+use tracing::{debug, error, info, trace, warn};
 use xz2::bufread::XzDecoder;
 
 use crate::{
@@ -48,10 +57,12 @@ use crate::{
 };
 
 use super::{
-    Blake3, FSRootPath, FileKind, HabitatRootPath, PackageBuildVersion, PackageDepIdent,
-    PackageIdent, PackageName, PackageOrigin, PackagePath, PackageRelease, PackageResolvedDepIdent,
+    artifact_archive_open, artifact_signature_verify, Blake3, FSRootPath, FileKind,
+    HabitatKeyCachePath, HabitatRootPath, PackageBuildVersion, PackageDepIdent, PackageIdent,
+    PackageName, PackageOrigin, PackagePath, PackageRelease, PackageResolvedDepIdent,
     PackageResolvedRelease, PackageResolvedVersion, PackageSha256Sum, PackageSource,
-    PackageSourceURL, PackageTarget, PackageType, PackageVersion, PlanContextID,
+    PackageSourceURL, PackageTarget, PackageType, PackageVersion, PlanContextID, HOOKS_DIRECTORY,
+    HOOK_MAX_SIZE,
 };
 
 lazy_static! {
@@ -78,8 +89,80 @@ lazy_static! {
         }
         globset_builder.build().unwrap()
     };
+    static ref CMAKE_CONFIG_GLOBSET: GlobSet = {
+        let mut globset_builder = GlobSetBuilder::new();
+        for pattern in [
+            "hab/pkgs/*/*/*/*/**/*Config.cmake",
+            "hab/pkgs/*/*/*/*/**/*-config.cmake",
+            "hab/pkgs/*/*/*/*/**/*Targets.cmake",
+            "hab/pkgs/*/*/*/*/**/*Targets-*.cmake",
+            "hab/pkgs/*/*/*/*/**/*-targets.cmake",
+            "hab/pkgs/*/*/*/*/**/*-targets-*.cmake",
+        ] {
+            globset_builder.add(
+                GlobBuilder::new(pattern)
+                    .literal_separator(true)
+                    .build()
+                    .unwrap(),
+            );
+        }
+        globset_builder.build().unwrap()
+    };
+    static ref TEXT_FILE_GLOBSET: GlobSet = {
+        let mut globset_builder = GlobSetBuilder::new();
+        for pattern in [
+            "hab/pkgs/*/*/*/*/etc/**",
+            "hab/pkgs/*/*/*/*/**/*.pc",
+            "hab/pkgs/*/*/*/*/**/*.la",
+            "hab/pkgs/*/*/*/*/**/*.conf",
+            "hab/pkgs/*/*/*/*/**/*.cnf",
+            "hab/pkgs/*/*/*/*/**/*.cfg",
+            "hab/pkgs/*/*/*/*/**/*.ini",
+            "hab/pkgs/*/*/*/*/**/*.json",
+            "hab/pkgs/*/*/*/*/**/*.toml",
+            "hab/pkgs/*/*/*/*/**/*.yaml",
+            "hab/pkgs/*/*/*/*/**/*.yml",
+            "hab/pkgs/*/*/*/*/**/*.xml",
+            "hab/pkgs/*/*/*/*/**/*.properties",
+        ] {
+            globset_builder.add(
+                GlobBuilder::new(pattern)
+                    .literal_separator(true)
+                    .build()
+                    .unwrap(),
+            );
+        }
+        globset_builder.build().unwrap()
+    };
 }
 
+/// Host directories that text files of a package should not refer to
+const HOST_PATH_PREFIXES: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/opt", "/home", "/root",
+];
+
+/// Text files larger than this are not scanned for host paths
+const TEXT_FILE_MAX_SIZE: u64 = 1024 * 1024;
+
+/// ELF and Mach-O binaries larger than this are spooled to a temporary file
+/// instead of being held in memory until they are parsed
+const RESOURCE_FILE_MAX_MEMORY_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Largest prefix of an ELF binary that is searched for its program headers
+/// when it is spooled to a temporary file
+const ELF_HEADERS_MAX_SIZE: u64 = 64 * 1024;
+
+/// Scripts are only read up to the end of their interpreter line, or this many bytes
+const SCRIPT_INTERPRETER_MAX_SIZE: usize = 4096;
+
+/// Number of artifact files per thread that can be read ahead of the threads parsing them
+const RAW_ARTIFACT_ITEM_QUEUE_SIZE_PER_THREAD: usize = 2;
+
+/// Permission bits of artifact files that are checked by the 'unsafe-permissions' rule
+pub(crate) const SETUID_MODE: u32 = 0o4000;
+pub(crate) const SETGID_MODE: u32 = 0o2000;
+pub(crate) const WORLD_WRITABLE_MODE: u32 = 0o0002;
+
 #[cfg(not(target_os = "windows"))]
 const ARTIFACT_DATA_EXTRACT_SCRIPT: &[u8] = include_bytes!("../scripts/artifact_data_extract.sh");
 
@@ -154,28 +237,39 @@ impl LazyArtifactContext {
 pub(crate) struct ArtifactCache {
     pub path: ArtifactCachePath,
     known_artifacts: Arc<RwLock<ArtifactList>>,
+    unverified_artifacts: HashSet<PackageIdent>,
+    yanked_artifacts: HashSet<PackageIdent>,
     store: Store,
 }
 
 impl ArtifactCache {
-    pub fn new(artifact_cache_path: ArtifactCachePath, store: &Store) -> Result<ArtifactCache> {
+    pub fn new(
+        artifact_cache_path: ArtifactCachePath,
+        store: &Store,
+        verify_artifacts: bool,
+    ) -> Result<ArtifactCache> {
         let start = Instant::now();
-        let artifact_cache = ArtifactCache {
-            path: artifact_cache_path,
-            known_artifacts: Arc::new(RwLock::new(ArtifactList::default())),
-            store: store.clone(),
+        let mut artifact_cache = ArtifactCache::empty(artifact_cache_path, store)?;
+        let key_cache_path = if verify_artifacts {
+            Some(HabitatRootPath::default().key_cache())
+        } else {
+            None
         };
         let artifact_cache_walker = WalkBuilder::new(artifact_cache.path.as_ref()).build_parallel();
         std::thread::scope(|scope| {
             let (sender, receiver) = channel();
-            let mut artifact_indexer_builder = ArtifactIndexerBuilder::new(store, sender);
+            let mut artifact_indexer_builder =
+                ArtifactIndexerBuilder::new(store, key_cache_path.as_ref(), sender);
             let artifact_indexer_thread =
                 scope.spawn(move || artifact_cache_walker.visit(&mut artifact_indexer_builder));
             let mut known_artifact_count = 0;
 
-            while let Ok(artifact_ctx) = receiver.recv() {
+            while let Ok((artifact_ctx, is_verified)) = receiver.recv() {
                 known_artifact_count += 1;
-                artifact_cache.artifact_add(store, artifact_ctx)?;
+                let artifact_ident = artifact_cache.artifact_add(store, artifact_ctx)?;
+                if !is_verified {
+                    artifact_cache.unverified_artifacts.insert(artifact_ident);
+                }
             }
             artifact_indexer_thread
                 .join()
@@ -186,10 +280,77 @@ impl ArtifactCache {
                 artifact_cache.path.as_ref().display(),
                 start.elapsed().as_secs_f32()
             );
+            if !artifact_cache.unverified_artifacts.is_empty() {
+                warn!(
+                    "Skipping {} artifacts at {} with signatures that could not be verified",
+                    artifact_cache.unverified_artifacts.len(),
+                    artifact_cache.path.as_ref().display(),
+                );
+            }
             Ok(artifact_cache)
         })
     }
 
+    /// Creates an artifact cache without scanning the artifacts on disk, for
+    /// operations that only need the dependency graph of the plans.
+    pub fn empty(artifact_cache_path: ArtifactCachePath, store: &Store) -> Result<ArtifactCache> {
+        Ok(ArtifactCache {
+            path: artifact_cache_path,
+            known_artifacts: Arc::new(RwLock::new(ArtifactList::default())),
+            unverified_artifacts: HashSet::new(),
+            yanked_artifacts: store
+                .get_connection()?
+                .transaction(|connection| store::yanked_artifacts_get(connection))?
+                .into_iter()
+                .map(|yanked_artifact| yanked_artifact.artifact)
+                .collect(),
+            store: store.clone(),
+        })
+    }
+
+    fn is_unverified(&self, artifact_ident: &PackageIdent) -> bool {
+        self.unverified_artifacts.contains(artifact_ident)
+    }
+
+    pub fn is_yanked(&self, artifact_ident: &PackageIdent) -> bool {
+        self.yanked_artifacts.contains(artifact_ident)
+    }
+
+    fn is_excluded(&self, artifact_ident: &PackageIdent) -> bool {
+        self.is_unverified(artifact_ident) || self.is_yanked(artifact_ident)
+    }
+
+    /// Excludes an artifact from dependency resolution, the artifact remains in the cache
+    pub fn artifact_yank(&mut self, artifact_ident: &PackageIdent) {
+        self.yanked_artifacts.insert(artifact_ident.clone());
+    }
+
+    pub fn artifact_unyank(&mut self, artifact_ident: &PackageIdent) {
+        self.yanked_artifacts.remove(artifact_ident);
+    }
+
+    /// Finds the latest artifact with the given version and release, skipping
+    /// over artifacts whose signatures could not be verified and yanked artifacts.
+    fn latest_verified_artifact<'b>(
+        &self,
+        versions: &'b BTreeMap<
+            PackageResolvedVersion,
+            BTreeMap<PackageResolvedRelease, LazyArtifactContext>,
+        >,
+        version: Option<&PackageResolvedVersion>,
+        release: Option<&PackageResolvedRelease>,
+    ) -> Option<&'b LazyArtifactContext> {
+        let latest_release =
+            |releases: &'b BTreeMap<PackageResolvedRelease, LazyArtifactContext>| match release {
+                Some(release) => releases.get(release).filter(|a| !self.is_excluded(a.id())),
+                None => releases.values().rev().find(|a| !self.is_excluded(a.id())),
+            };
+        match version {
+            Some(version) => versions.get(version).and_then(latest_release),
+            None => versions.values().rev().find_map(latest_release),
+        }
+    }
+
     pub fn artifact_add(
         &self,
         _store: &Store,
@@ -215,7 +376,19 @@ impl ArtifactCache {
             .get_connection()?
             .immediate_transaction(|connection| {
                 store::artifact_context_put(connection, &artifact_ctx.hash, artifact_ctx)
-                    .with_context(|| format!("Failed to add artifact {} to store", artifact_ctx.id))
+                    .with_context(|| {
+                        format!("Failed to add artifact {} to store", artifact_ctx.id)
+                    })?;
+                if !artifact_ctx.files.is_empty() {
+                    store::artifact_files_put(connection, &artifact_ctx.hash, &artifact_ctx.files)
+                        .with_context(|| {
+                            format!(
+                                "Failed to add files of artifact {} to store",
+                                artifact_ctx.id
+                            )
+                        })?;
+                }
+                Ok::<_, color_eyre::eyre::Error>(())
             })?;
         trace!("Added artifact {} to store", artifact_ctx.id);
         Ok(())
@@ -252,11 +425,13 @@ impl ArtifactCache {
             .get(&build_ident.origin)
             .and_then(|a| a.get(&build_ident.name))
             .and_then(|a| a.get(&build_ident.target))
-            .and_then(|a| match &build_ident.version {
-                PackageBuildVersion::Static(version) => a.get(version),
-                PackageBuildVersion::Dynamic => a.values().next_back(),
+            .and_then(|a| {
+                let version = match &build_ident.version {
+                    PackageBuildVersion::Static(version) => Some(version),
+                    PackageBuildVersion::Dynamic => None,
+                };
+                self.latest_verified_artifact(a, version, None)
             })
-            .and_then(|a| a.values().next_back())
             .map(|a| match a {
                 LazyArtifactContext::NotLoaded(a) => a.clone(),
                 LazyArtifactContext::Loaded(a) => MinimalArtifactContext::from(a),
@@ -275,16 +450,17 @@ impl ArtifactCache {
             .get(&build_ident.origin)
             .and_then(|a| a.get(&build_ident.name))
             .and_then(|a| a.get(&build_ident.target))
-            .and_then(|a| match &build_ident.version {
-                PackageBuildVersion::Static(version) => a.get(version),
-                PackageBuildVersion::Dynamic => a.values().next_back(),
+            .and_then(|a| {
+                let version = match &build_ident.version {
+                    PackageBuildVersion::Static(version) => Some(version),
+                    PackageBuildVersion::Dynamic => None,
+                };
+                self.latest_verified_artifact(a, version, None)
             })
-            .and_then(|a| a.values().next_back())
             .cloned();
         self.load_lazy_artifact(lazy_artifact)
     }
 
-    #[cfg(not(target_os = "windows"))]
     pub fn latest_minimal_artifact(
         &self,
         dep_ident: &PackageResolvedDepIdent,
@@ -295,13 +471,16 @@ impl ArtifactCache {
             .get(&dep_ident.origin)
             .and_then(|a| a.get(&dep_ident.name))
             .and_then(|a| a.get(&dep_ident.target))
-            .and_then(|a| match &dep_ident.version {
-                PackageVersion::Resolved(version) => a.get(version),
-                PackageVersion::Unresolved => a.values().next_back(),
-            })
-            .and_then(|a| match &dep_ident.release {
-                PackageRelease::Resolved(release) => a.get(release),
-                PackageRelease::Unresolved => a.values().next_back(),
+            .and_then(|a| {
+                let version = match &dep_ident.version {
+                    PackageVersion::Resolved(version) => Some(version),
+                    PackageVersion::Unresolved => None,
+                };
+                let release = match &dep_ident.release {
+                    PackageRelease::Resolved(release) => Some(release),
+                    PackageRelease::Unresolved => None,
+                };
+                self.latest_verified_artifact(a, version, release)
             })
             .map(|a| match a {
                 LazyArtifactContext::NotLoaded(a) => a.clone(),
@@ -320,19 +499,47 @@ impl ArtifactCache {
             .get(&dep_ident.origin)
             .and_then(|a| a.get(&dep_ident.name))
             .and_then(|a| a.get(&dep_ident.target))
-            .and_then(|a| match &dep_ident.version {
-                PackageVersion::Resolved(version) => a.get(version),
-                PackageVersion::Unresolved => a.values().next_back(),
-            })
-            .and_then(|a| match &dep_ident.release {
-                PackageRelease::Resolved(release) => a.get(release),
-                PackageRelease::Unresolved => a.values().next_back(),
+            .and_then(|a| {
+                let version = match &dep_ident.version {
+                    PackageVersion::Resolved(version) => Some(version),
+                    PackageVersion::Unresolved => None,
+                };
+                let release = match &dep_ident.release {
+                    PackageRelease::Resolved(release) => Some(release),
+                    PackageRelease::Unresolved => None,
+                };
+                self.latest_verified_artifact(a, version, release)
             })
             .cloned();
         self.load_lazy_artifact(lazy_artifact)
     }
 
-    #[allow(dead_code)]
+    /// Finds the artifact of the same package with the latest release before the
+    /// given artifact's release, whatever its version
+    pub fn previous_artifact(
+        &self,
+        artifact_ident: &PackageIdent,
+    ) -> Result<Option<ArtifactContext>> {
+        let lazy_artifact = self
+            .known_artifacts
+            .read()
+            .unwrap()
+            .get(&artifact_ident.origin)
+            .and_then(|a| a.get(&artifact_ident.name))
+            .and_then(|a| a.get(&artifact_ident.target))
+            .and_then(|versions| {
+                versions
+                    .values()
+                    .flat_map(|releases| releases.iter())
+                    .filter(|(release, a)| {
+                        **release < artifact_ident.release && !self.is_excluded(a.id())
+                    })
+                    .max_by(|(a, _), (b, _)| a.cmp(b))
+                    .map(|(_, a)| a.clone())
+            });
+        self.load_lazy_artifact(lazy_artifact)
+    }
+
     pub fn minimal_artifact(&self, dep_ident: &PackageIdent) -> Option<MinimalArtifactContext> {
         self.known_artifacts
             .read()
@@ -407,6 +614,16 @@ pub(crate) struct ElfMetadata {
     pub interpreter: Option<PathBuf>,
     pub elf_type: ElfType,
     pub is_executable: bool,
+    /// Total size in bytes of the debug sections
+    #[serde(default)]
+    pub debug_size: u64,
+    /// Symbol versions required from each shared library, such as `GLIBC_2.34`
+    /// from `libc.so.6`
+    #[serde(default)]
+    pub required_symbol_versions: BTreeMap<String, BTreeSet<String>>,
+    /// Symbol versions defined by a shared library
+    #[serde(default)]
+    pub provided_symbol_versions: BTreeSet<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
@@ -507,6 +724,7 @@ impl From<u32> for MachOType {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 pub(crate) enum PeType {
     #[serde(rename = "executable")]
@@ -565,6 +783,45 @@ impl From<InnerArtifactContext> for ArtifactContext {
     }
 }
 
+/// Reader that writes all the data read from an artifact into another file
+/// and hashes it at the same time.
+struct ArtifactTeeReader<R: Read, W: Write> {
+    reader: R,
+    writer: W,
+    hasher: blake3::Hasher,
+}
+
+impl<R: Read, W: Write> ArtifactTeeReader<R, W> {
+    fn new(reader: R, writer: W) -> Self {
+        ArtifactTeeReader {
+            reader,
+            writer,
+            hasher: blake3::Hasher::new(),
+        }
+    }
+
+    fn finish(mut self) -> Result<Blake3> {
+        self.writer.flush()?;
+        Ok(Blake3::from(self.hasher.finalize().to_string()))
+    }
+}
+
+impl<R: Read, W: Write> Read for ArtifactTeeReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.writer.write_all(&buf[..n])?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Version of the `InnerArtifactContext` schema recorded alongside artifact contexts
+/// in the store. It must be incremented whenever a change to the struct would leave
+/// previously stored contexts unreadable or incomplete, so that they are regenerated
+/// from the artifacts instead. Fields added to the struct are missing from artifact
+/// contexts stored by older versions of hab-auto-build.
+pub(crate) const ARTIFACT_CONTEXT_VERSION: i32 = 4;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct InnerArtifactContext {
     pub id: PackageIdent,
@@ -572,14 +829,25 @@ pub(crate) struct InnerArtifactContext {
     #[serde(default, skip)]
     pub is_dirty: bool,
     pub target: PackageTarget,
+    /// Target recorded in the TARGET metafile, it only differs from the target in
+    /// the MANIFEST metafile for renamed or hand-rolled artifacts
+    #[serde(default)]
+    pub metafile_target: Option<PackageTarget>,
     pub package_type: PackageType,
     pub deps: HashSet<PackageIdent>,
     pub tdeps: HashSet<PackageIdent>,
     pub build_deps: HashSet<PackageIdent>,
     pub runtime_path: Vec<PathBuf>,
+    #[serde(default)]
+    pub pkg_config_path: Vec<PathBuf>,
+    #[serde(default)]
+    pub pkg_config_files: HashSet<PathBuf>,
     pub interpreters: Vec<PathBuf>,
     pub source: Option<PackageSource>,
     pub licenses: Vec<String>,
+    /// Plan file the artifact was built from, as embedded in its MANIFEST
+    #[serde(default)]
+    pub plan_source: Option<String>,
     pub elfs: HashMap<PathBuf, ElfMetadata>,
     pub machos: HashMap<PathBuf, MachOMetadata>,
     pub empty_top_level_dirs: HashSet<PathBuf>,
@@ -587,9 +855,76 @@ pub(crate) struct InnerArtifactContext {
     pub broken_links: HashMap<PathBuf, PathBuf>,
     pub empty_links: HashSet<PathBuf>,
     pub scripts: HashMap<PathBuf, ScriptMetadata>,
+    /// Modes of the files with setuid, setgid or world-writable permissions
+    #[serde(default)]
+    pub unsafe_permissions: HashMap<PathBuf, u32>,
+    /// Host paths referenced by the text and config files
+    #[serde(default)]
+    pub host_paths: HashMap<PathBuf, BTreeSet<PathBuf>>,
+    /// Absolute paths referenced by the CMake package configuration files
+    #[serde(default)]
+    pub cmake_config_paths: HashMap<PathBuf, BTreeSet<PathBuf>>,
+    /// Static libraries and object files
+    #[serde(default)]
+    pub static_libraries: HashSet<PathBuf>,
+    /// Total size in bytes of the files in the artifact once installed
+    #[serde(default)]
+    pub installed_size: u64,
+    /// Contents of the hooks of the package by hook name
+    #[serde(default)]
+    pub hooks: BTreeMap<String, String>,
+    /// Files and links of the artifact, only set on contexts read from the artifact.
+    /// They are recorded separately in the store, see `AutoBuildContext::artifact_files`.
+    #[serde(default, skip)]
+    pub files: Vec<ArtifactFile>,
     pub created_at: DateTime<Utc>,
 }
 
+/// File or link of an artifact, as listed in its archive
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+pub(crate) struct ArtifactFile {
+    /// Path of the file relative to the package folder
+    pub path: PathBuf,
+    /// Size of the file in bytes, 0 for links
+    pub size: u64,
+    /// Permissions of the file
+    pub mode: u32,
+    /// Target of the link, as recorded in the archive
+    pub link: Option<PathBuf>,
+}
+
+impl ArtifactFile {
+    /// Lists the files and links of the artifact at the given path, without
+    /// reading their contents
+    pub fn read_all(artifact_path: impl AsRef<Path>) -> Result<Vec<ArtifactFile>> {
+        let mut archive = artifact_archive_open(artifact_path.as_ref())?;
+        let mut files = Vec::new();
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            if !(entry_type.is_file() || entry_type.is_hard_link() || entry_type.is_symlink()) {
+                continue;
+            }
+            let entry_install_path = FSRootPath::default().as_ref().join(entry.path()?);
+            if let Some(file_path) = entry_install_path.relative_package_path() {
+                let is_link = !entry_type.is_file();
+                files.push(ArtifactFile {
+                    path: file_path,
+                    size: if is_link { 0 } else { header.size()? },
+                    mode: header.mode()?,
+                    link: if is_link {
+                        header.link_name()?.map(|link_path| link_path.to_path_buf())
+                    } else {
+                        None
+                    },
+                });
+            }
+        }
+        Ok(files)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct MinimalArtifactContext(Arc<InnerMinimalArtifactContext>);
 
@@ -628,24 +963,43 @@ impl From<&ArtifactContext> for MinimalArtifactContext {
 #[derive(Debug)]
 enum RawArtifactItem {
     MetaFile(String, String),
-    Resource(PathBuf, u32, FileKind, Vec<u8>),
+    Resource(PathBuf, u32, FileKind, RawResourceData),
+    TextFile(PathBuf, Vec<u8>),
+    CMakeConfig(PathBuf, Vec<u8>),
+    Hook(String, Vec<u8>),
+}
+
+/// Contents of an artifact file that is parsed as a resource
+#[derive(Debug)]
+enum RawResourceData {
+    /// Contents of a binary, or the interpreter line of a script
+    Memory(Vec<u8>),
+    /// Path to a temporary copy of a large binary, along with the ranges of
+    /// the binary that were left out of the copy
+    Spooled(PathBuf, Vec<Range<u64>>),
 }
 
 #[derive(Debug)]
 enum IndexedArtifactItem {
     PackageIdent(PackageDepIdent),
     PackageTarget(PackageTarget),
+    MetafileTarget(PackageTarget),
     PackageType(PackageType),
     PackageSource(PackageSource),
     Licenses(Vec<String>),
+    PlanSource(String),
     PackageDeps(HashSet<PackageDepIdent>),
     PackageTDeps(HashSet<PackageDepIdent>),
     PackageBuildDeps(HashSet<PackageDepIdent>),
     RuntimePath(Vec<PathBuf>),
+    PkgConfigPath(Vec<PathBuf>),
     Interpreters(Vec<PathBuf>),
     Script((PathBuf, ScriptMetadata)),
     Elf((PathBuf, ElfMetadata)),
     MachO((PathBuf, MachOMetadata)),
+    HostPaths((PathBuf, BTreeSet<PathBuf>)),
+    CMakeConfigPaths((PathBuf, BTreeSet<PathBuf>)),
+    Hook((String, String)),
 }
 
 impl ArtifactContext {
@@ -736,10 +1090,74 @@ impl ArtifactContext {
         artifact_path: impl AsRef<Path>,
         hash: Option<&Blake3>,
     ) -> Result<ArtifactContext> {
-        let start = Instant::now();
-
         let f = std::fs::File::open(artifact_path.as_ref())?;
-        let mut reader = std::io::BufReader::new(f);
+        ArtifactContext::read_from_reader(BufReader::new(f), artifact_path.as_ref(), |_| {
+            if let Some(hash) = hash {
+                Ok(hash.clone())
+            } else {
+                Blake3::from_path(artifact_path.as_ref()).with_context(|| {
+                    format!(
+                        "Failed to generate hash for artifact {}",
+                        artifact_path.as_ref().display(),
+                    )
+                })
+            }
+        })
+    }
+
+    /// Moves an artifact to a new location while indexing it. The artifact is renamed
+    /// into place and then read only once, being hashed as the archive is parsed. When
+    /// the destination is on another file system the artifact is instead copied and
+    /// hashed in the same pass that indexes it.
+    pub fn move_and_read(
+        artifact_path: impl AsRef<Path>,
+        final_artifact_path: impl AsRef<Path>,
+    ) -> Result<ArtifactContext> {
+        let artifact_path = artifact_path.as_ref();
+        let final_artifact_path = final_artifact_path.as_ref();
+        match std::fs::rename(artifact_path, final_artifact_path) {
+            Ok(()) => {
+                let source = std::fs::File::open(final_artifact_path)?;
+                let reader = BufReader::new(ArtifactTeeReader::new(source, std::io::sink()));
+                ArtifactContext::read_from_reader(reader, final_artifact_path, |mut reader| {
+                    // Hash any trailing data that was not consumed while indexing the archive
+                    std::io::copy(&mut reader, &mut std::io::sink())?;
+                    reader.into_inner().finish()
+                })
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+                ArtifactContext::copy_and_read(artifact_path, final_artifact_path)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn copy_and_read(artifact_path: &Path, final_artifact_path: &Path) -> Result<ArtifactContext> {
+        let source = std::fs::File::open(artifact_path)?;
+        let destination = std::fs::File::create(final_artifact_path)?;
+        let reader = BufReader::new(ArtifactTeeReader::new(source, destination));
+        match ArtifactContext::read_from_reader(reader, final_artifact_path, |mut reader| {
+            // Copy any trailing data that was not consumed while indexing the archive
+            std::io::copy(&mut reader, &mut std::io::sink())?;
+            reader.into_inner().finish()
+        }) {
+            Ok(artifact) => {
+                std::fs::remove_file(artifact_path)?;
+                Ok(artifact)
+            }
+            Err(err) => {
+                let _ = std::fs::remove_file(final_artifact_path);
+                Err(err)
+            }
+        }
+    }
+
+    fn read_from_reader<R: BufRead>(
+        mut reader: R,
+        artifact_path: &Path,
+        hash: impl FnOnce(R) -> Result<Blake3>,
+    ) -> Result<ArtifactContext> {
+        let start = Instant::now();
 
         // We skip the first 5 lines
         let mut line = String::new();
@@ -749,7 +1167,7 @@ impl ArtifactContext {
                 Ok(0) => {
                     return Err(eyre!(
                         "The file '{}' is not a valid .hart file",
-                        artifact_path.as_ref().display()
+                        artifact_path.display()
                     ));
                 }
                 Ok(_) => {
@@ -763,278 +1181,256 @@ impl ArtifactContext {
                 Err(err) => {
                     return Err(eyre!(
                         "The file '{}' is not a valid .hart file: {:?}",
-                        artifact_path.as_ref().display(),
+                        artifact_path.display(),
                         err
                     ));
                 }
             }
         }
-        let decoder = XzDecoder::new(reader);
+        let decoder = XzDecoder::new(&mut reader);
         let mut tar = Archive::new(decoder);
 
         let mut id = None;
         let mut target = None;
+        let mut metafile_target = None;
         let mut package_type = PackageType::Standard;
         let mut source = None;
         let mut licenses = Vec::new();
+        let mut plan_source = None;
         let mut deps = HashSet::new();
         let mut tdeps = HashSet::new();
         let mut build_deps = HashSet::new();
         let mut runtime_path = Vec::new();
+        let mut pkg_config_path = Vec::new();
+        let mut pkg_config_files = HashSet::new();
         let mut interpreters = Vec::new();
         let mut empty_top_level_dirs = HashSet::new();
         let mut broken_links = HashMap::new();
         let mut empty_links = HashSet::new();
         let mut links = BTreeMap::new();
         let mut scripts = HashMap::new();
+        let mut unsafe_permissions = HashMap::new();
+        let mut host_paths = HashMap::new();
+        let mut cmake_config_paths = HashMap::new();
+        let mut static_libraries = HashSet::new();
+        let mut hooks = BTreeMap::new();
+        let mut installed_size = 0;
+        let mut files = Vec::new();
         let mut elfs = HashMap::new();
         let mut machos = HashMap::new();
 
         // We need to skip 5 entries to retrieve the path with the full identifier.
         let entries_to_skip = if cfg!(target_os = "windows") { 5 } else { 0 };
-        let indexed_item_batches = tar
-            .entries()?
-            .skip(entries_to_skip)
-            .filter_map(|entry| entry.ok())
-            .map(|mut entry| {
-                let header = entry.header();
-                let entry_type = header.entry_type();
-                let path = entry.path()?.to_path_buf();
-                let entry_install_path = FSRootPath::default().as_ref().join(&path);
-                if entry_type.is_dir() {
-                    let is_top_level_dir = entry_install_path.components().count() == 8;
-                    if is_top_level_dir {
-                        empty_top_level_dirs
-                            .insert(entry_install_path.components().take(8).collect::<PathBuf>());
+
+        // Files are parsed by the thread pool while the archive is still being read, the
+        // queue in between is bounded so only a few of them are held in memory at a time
+        let mut spool_dir: Option<TempDir> = None;
+        let mut spooled_files = 0;
+        let indexed_item_batches = std::thread::scope(|scope| {
+            let (raw_item_sender, raw_item_receiver) = sync_channel(
+                rayon::current_num_threads() * RAW_ARTIFACT_ITEM_QUEUE_SIZE_PER_THREAD,
+            );
+            let indexer = scope.spawn(move || {
+                raw_item_receiver
+                    .into_iter()
+                    .par_bridge()
+                    .map(ArtifactContext::raw_item_index)
+                    .collect::<Vec<_>>()
+            });
+            let raw_items = tar
+                .entries()?
+                .skip(entries_to_skip)
+                .filter_map(|entry| entry.ok())
+                .map(|mut entry| {
+                    let header = entry.header();
+                    let entry_type = header.entry_type();
+                    let path = entry.path()?.to_path_buf();
+                    let entry_install_path = FSRootPath::default().as_ref().join(&path);
+                    if entry_type.is_dir() {
+                        let is_top_level_dir = entry_install_path.components().count() == 8;
+                        if is_top_level_dir {
+                            empty_top_level_dirs.insert(
+                                entry_install_path.components().take(8).collect::<PathBuf>(),
+                            );
+                        }
+                        return Ok::<_, color_eyre::eyre::Error>(None);
                     }
-                    return Ok::<_, color_eyre::eyre::Error>(None);
-                }
 
-                let top_level_dir = entry_install_path.components().take(8).collect::<PathBuf>();
-                empty_top_level_dirs.remove(&top_level_dir);
+                    let top_level_dir =
+                        entry_install_path.components().take(8).collect::<PathBuf>();
+                    empty_top_level_dirs.remove(&top_level_dir);
+
+                    if entry_install_path.extension() == Some(OsStr::new("pc")) {
+                        pkg_config_files.insert(entry_install_path.clone());
+                    }
 
-                if entry_type.is_hard_link() || entry_type.is_symlink() {
-                    if let Ok(Some(link_path)) = header.link_name() {
-                        let canonical_link_path = if link_path.is_relative() {
-                            if entry_type.is_hard_link() {
-                                FSRootPath::default().as_ref().join(link_path)
+                    if entry_type.is_hard_link() || entry_type.is_symlink() {
+                        if let Some(file_path) = entry_install_path.relative_package_path() {
+                            files.push(ArtifactFile {
+                                path: file_path,
+                                size: 0,
+                                mode: header.mode()?,
+                                link: header
+                                    .link_name()
+                                    .ok()
+                                    .flatten()
+                                    .map(|link_path| link_path.to_path_buf()),
+                            });
+                        }
+                        if let Ok(Some(link_path)) = header.link_name() {
+                            let canonical_link_path = if link_path.is_relative() {
+                                if entry_type.is_hard_link() {
+                                    FSRootPath::default().as_ref().join(link_path)
+                                } else {
+                                    entry_install_path
+                                        .parent()
+                                        .unwrap()
+                                        .join(link_path)
+                                        .absolutize()
+                                        .unwrap()
+                                        .to_path_buf()
+                                }
                             } else {
-                                entry_install_path
-                                    .parent()
-                                    .unwrap()
-                                    .join(link_path)
-                                    .absolutize()
-                                    .unwrap()
-                                    .to_path_buf()
+                                link_path.absolutize().unwrap().to_path_buf()
+                            };
+                            if !canonical_link_path.is_package_path() {
+                                broken_links.insert(entry_install_path, canonical_link_path);
+                            } else {
+                                links.insert(entry_install_path, canonical_link_path);
                             }
                         } else {
-                            link_path.absolutize().unwrap().to_path_buf()
-                        };
-                        if !canonical_link_path.is_package_path() {
-                            broken_links.insert(entry_install_path, canonical_link_path);
-                        } else {
-                            links.insert(entry_install_path, canonical_link_path);
+                            empty_links.insert(entry_install_path);
                         }
-                    } else {
-                        empty_links.insert(entry_install_path);
+                        return Ok::<_, color_eyre::eyre::Error>(None);
+                    } else if !entry_type.is_file() {
+                        return Ok::<_, color_eyre::eyre::Error>(None);
                     }
-                    return Ok::<_, color_eyre::eyre::Error>(None);
-                } else if !entry_type.is_file() {
-                    return Ok::<_, color_eyre::eyre::Error>(None);
-                }
 
-                let file_name = path.file_name().unwrap().to_str().unwrap();
-                let file_mode = header.mode()?;
-                let matches = METADATA_GLOBSET.matches(&path);
-                // Check if the file is executable
-                // https://stackoverflow.com/questions/37062143/how-to-check-if-file-is-executable-using-bitwise-operations-in-rust
-                if !matches.is_empty() {
-                    let mut data = String::new();
-                    entry.read_to_string(&mut data)?;
-                    Ok::<_, color_eyre::eyre::Error>(Some(RawArtifactItem::MetaFile(
-                        file_name.to_string(),
-                        data,
-                    )))
-                } else if let Some((kind, data)) = FileKind::maybe_read_file(
-                    entry,
-                    &[FileKind::Elf, FileKind::Script, FileKind::MachBinary],
-                ) {
-                    Ok::<_, color_eyre::eyre::Error>(Some(RawArtifactItem::Resource(
-                        entry_install_path,
-                        file_mode,
-                        kind,
-                        data,
-                    )))
-                } else {
-                    Ok::<_, color_eyre::eyre::Error>(None)
-                }
-            })
-            .collect::<Vec<_>>()
-            .into_par_iter()
-            .map(|raw_item| {
-                if let Some(raw_item) = raw_item? {
-                    match raw_item {
-                        RawArtifactItem::MetaFile(file_name, data) => {
-                            Ok::<_, color_eyre::eyre::Error>(match file_name.as_str() {
-                                "IDENT" => {
-                                    vec![IndexedArtifactItem::PackageIdent(PackageDepIdent::parse(
-                                        data.trim(),
-                                    )?)]
-                                }
-                                "PACKAGE_TYPE" => {
-                                    vec![IndexedArtifactItem::PackageType(PackageType::parse(
-                                        data.trim(),
-                                    )?)]
-                                }
-                                "DEPS" => {
-                                    vec![IndexedArtifactItem::PackageDeps(
-                                        data.lines()
-                                            .map(PackageDepIdent::parse)
-                                            .collect::<Result<HashSet<_>>>()?,
-                                    )]
-                                }
-                                "TDEPS" => {
-                                    vec![IndexedArtifactItem::PackageTDeps(
-                                        data.lines()
-                                            .map(PackageDepIdent::parse)
-                                            .collect::<Result<HashSet<_>>>()?,
-                                    )]
-                                }
-                                "BUILD_DEPS" => {
-                                    vec![IndexedArtifactItem::PackageBuildDeps(
-                                        data.lines()
-                                            .map(PackageDepIdent::parse)
-                                            .collect::<Result<HashSet<_>>>()?,
-                                    )]
-                                }
-                                "RUNTIME_PATH" => {
-                                    vec![IndexedArtifactItem::RuntimePath(
-                                        data.split(':')
-                                            .map(str::trim)
-                                            .map(PathBuf::from)
-                                            .collect::<Vec<_>>(),
-                                    )]
-                                }
-                                "INTERPRETERS" => {
-                                    vec![IndexedArtifactItem::Interpreters(
-                                        data.lines().map(PathBuf::from).collect::<Vec<_>>(),
-                                    )]
-                                }
-                                "MANIFEST" => {
-                                    let mut result = Vec::new();
-                                    let mut pkg_source = None;
-                                    let mut pkg_shasum = None;
-                                    let mut plan_source = String::new();
-                                    let mut plan_source_header_read = false;
-                                    for line in data.lines() {
-                                        if let Some(value) = line.strip_prefix("* __Target__:") {
-                                            let patterns: &[_] = &[' ', '`', '\n'];
-                                            if let Ok(target) =
-                                                PackageTarget::parse(value.trim_matches(patterns))
-                                            {
-                                                result.push(IndexedArtifactItem::PackageTarget(
-                                                    target,
-                                                ));
-                                            }
-                                        }
-                                        if let Some(value) = line.strip_prefix("* __Source__:") {
-                                            let src = value
-                                                .trim()
-                                                .split_terminator(&['[', ']'])
-                                                .collect::<Vec<_>>();
-                                            if let Some(url) = src.get(1) {
-                                                if !url.is_empty() {
-                                                    pkg_source = Some(Url::parse(url)?);
-                                                }
-                                            }
-                                        }
-                                        if let Some(value) = line.strip_prefix("* __SHA__:") {
-                                            let patterns: &[_] = &[' ', '`', '\n'];
-                                            pkg_shasum =
-                                                Some(value.trim_matches(patterns).to_owned());
-                                        }
-                                        if plan_source_header_read {
-                                            plan_source.push_str(line);
-                                            plan_source.push('\n');
-                                        }
-                                        if line.starts_with("## Plan Source") {
-                                            plan_source_header_read = true;
-                                        }
-                                    }
-                                    if let (Some(url), Some(shasum)) = (pkg_source, pkg_shasum) {
-                                        result.push(IndexedArtifactItem::PackageSource(
-                                            PackageSource {
-                                                url: PackageSourceURL::from(url),
-                                                shasum: PackageSha256Sum::from(shasum),
-                                            },
-                                        ));
-                                    }
-                                    let shell_type = if cfg!(target_os = "windows") {
-                                        "ps1"
-                                    } else {
-                                        "bash"
-                                    };
-
-                                    // Split the source based on the delimiter
-                                    let split_str = format!("```{}", shell_type);
-                                    plan_source = plan_source
-                                        .split_once(&split_str)
-                                        .unwrap()
-                                        .1
-                                        .rsplit_once("```")
-                                        .unwrap()
-                                        .0
-                                        .to_string();
-                                    result.push(IndexedArtifactItem::Licenses(
-                                        ArtifactContext::extract_licenses_from_plan_source(
-                                            &plan_source,
-                                        )?,
-                                    ));
-                                    result
-                                }
-                                _ => {
-                                    vec![]
+                    let file_name = path.file_name().unwrap().to_str().unwrap();
+                    let file_mode = header.mode()?;
+                    if file_mode & (SETUID_MODE | SETGID_MODE | WORLD_WRITABLE_MODE) != 0 {
+                        unsafe_permissions.insert(entry_install_path.clone(), file_mode);
+                    }
+                    let entry_size = header.size()?;
+                    installed_size += entry_size;
+                    if let Some(file_path) = entry_install_path.relative_package_path() {
+                        files.push(ArtifactFile {
+                            path: file_path,
+                            size: entry_size,
+                            mode: file_mode,
+                            link: None,
+                        });
+                    }
+                    if matches!(
+                        entry_install_path.extension().and_then(OsStr::to_str),
+                        Some("a" | "o")
+                    ) {
+                        static_libraries.insert(entry_install_path.clone());
+                    }
+                    let hook_name = entry_install_path
+                        .relative_package_path()
+                        .filter(|file_path| file_path.parent() == Some(Path::new(HOOKS_DIRECTORY)))
+                        .and_then(|file_path| {
+                            file_path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                        });
+                    let matches = METADATA_GLOBSET.matches(&path);
+                    // Check if the file is executable
+                    // https://stackoverflow.com/questions/37062143/how-to-check-if-file-is-executable-using-bitwise-operations-in-rust
+                    if let Some(hook_name) = hook_name.filter(|_| entry_size <= HOOK_MAX_SIZE) {
+                        let mut data = Vec::new();
+                        entry.read_to_end(&mut data)?;
+                        Ok::<_, color_eyre::eyre::Error>(Some(RawArtifactItem::Hook(
+                            hook_name, data,
+                        )))
+                    } else if !matches.is_empty() {
+                        let mut data = String::new();
+                        entry.read_to_string(&mut data)?;
+                        Ok::<_, color_eyre::eyre::Error>(Some(RawArtifactItem::MetaFile(
+                            file_name.to_string(),
+                            data,
+                        )))
+                    } else if CMAKE_CONFIG_GLOBSET.is_match(&path)
+                        && entry_size <= TEXT_FILE_MAX_SIZE
+                    {
+                        let mut data = Vec::new();
+                        entry.read_to_end(&mut data)?;
+                        Ok::<_, color_eyre::eyre::Error>(Some(RawArtifactItem::CMakeConfig(
+                            entry_install_path,
+                            data,
+                        )))
+                    } else if TEXT_FILE_GLOBSET.is_match(&path) && entry_size <= TEXT_FILE_MAX_SIZE
+                    {
+                        let mut data = Vec::new();
+                        entry.read_to_end(&mut data)?;
+                        Ok::<_, color_eyre::eyre::Error>(Some(RawArtifactItem::TextFile(
+                            entry_install_path,
+                            data,
+                        )))
+                    } else if let Some((kind, mut data)) = FileKind::maybe_detect_from_reader(
+                        &mut entry,
+                        &[FileKind::Elf, FileKind::Script, FileKind::MachBinary],
+                    ) {
+                        let data = if kind == FileKind::Script {
+                            // Only the interpreter line of a script is parsed
+                            let mut buffer = [0u8; 1024];
+                            while !data.contains(&b'\n') && data.len() < SCRIPT_INTERPRETER_MAX_SIZE
+                            {
+                                let n = entry.read(&mut buffer)?;
+                                if n == 0 {
+                                    break;
                                 }
-                            })
-                        }
-                        RawArtifactItem::Resource(path, file_mode, kind, data) => {
-                            Ok(if cfg!(target_os = "windows") {
-                                debug!("Skipping raw artifact resource check for issues");
-                                vec![] // Skip processing on Windows
+                                data.extend_from_slice(&buffer[..n]);
+                            }
+                            if let Some(index) = data.iter().position(|value| *value == b'\n') {
+                                data.truncate(index + 1);
+                            }
+                            RawResourceData::Memory(data)
+                        } else if entry_size > RESOURCE_FILE_MAX_MEMORY_SIZE {
+                            let spool_dir = match spool_dir.as_ref() {
+                                Some(spool_dir) => spool_dir,
+                                None => spool_dir.insert(TempDir::new("hab-auto-build-artifact")?),
+                            };
+                            let spool_path = spool_dir.path().join(spooled_files.to_string());
+                            spooled_files += 1;
+                            let mut spool_file = File::create(&spool_path)?;
+                            let skipped_ranges = if kind == FileKind::Elf {
+                                elf_spool(&mut entry, data, entry_size, &mut spool_file)?
                             } else {
-                                match Resource::from_data(&path, file_mode, kind, data) {
-                                    Err(err) => {
-                                        error!(
-                                            "Failed to read {} detected as {:?} resource: {:?}",
-                                            path.display(),
-                                            kind,
-                                            err
-                                        );
-                                        vec![]
-                                    }
-                                    Ok(resource) => match resource {
-                                        Resource::Elf(metadata) => {
-                                            vec![IndexedArtifactItem::Elf((path, metadata))]
-                                        }
-                                        Resource::Script(metadata) => {
-                                            vec![IndexedArtifactItem::Script((path, metadata))]
-                                        }
-                                        Resource::MachO(metadata) => {
-                                            vec![IndexedArtifactItem::MachO((path, metadata))]
-                                        }
-                                        _ => {
-                                            vec![]
-                                        }
-                                    },
-                                }
-                            })
-                        }
+                                spool_file.write_all(&data)?;
+                                std::io::copy(&mut entry, &mut spool_file)?;
+                                Vec::new()
+                            };
+                            RawResourceData::Spooled(spool_path, skipped_ranges)
+                        } else {
+                            entry.read_to_end(&mut data)?;
+                            RawResourceData::Memory(data)
+                        };
+                        Ok::<_, color_eyre::eyre::Error>(Some(RawArtifactItem::Resource(
+                            entry_install_path,
+                            file_mode,
+                            kind,
+                            data,
+                        )))
+                    } else {
+                        Ok::<_, color_eyre::eyre::Error>(None)
                     }
-                } else {
-                    Ok(vec![])
+                });
+            for raw_item in raw_items {
+                if raw_item_sender.send(raw_item).is_err() {
+                    break;
                 }
-            })
-            .collect::<Vec<_>>();
+            }
+            drop(raw_item_sender);
+            Ok::<_, color_eyre::eyre::Error>(
+                indexer
+                    .join()
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err)),
+            )
+        })?;
+        drop(tar);
+        drop(spool_dir);
 
         for indexed_item_batch in indexed_item_batches {
             let indexed_item_batch = indexed_item_batch?;
@@ -1046,6 +1442,9 @@ impl ArtifactContext {
                     IndexedArtifactItem::PackageTarget(value) => {
                         target = Some(value);
                     }
+                    IndexedArtifactItem::MetafileTarget(value) => {
+                        metafile_target = Some(value);
+                    }
                     IndexedArtifactItem::PackageType(value) => {
                         package_type = value;
                     }
@@ -1055,6 +1454,9 @@ impl ArtifactContext {
                     IndexedArtifactItem::Licenses(value) => {
                         licenses = value;
                     }
+                    IndexedArtifactItem::PlanSource(value) => {
+                        plan_source = Some(value);
+                    }
                     IndexedArtifactItem::PackageDeps(value) => {
                         deps = value;
                     }
@@ -1067,6 +1469,9 @@ impl ArtifactContext {
                     IndexedArtifactItem::RuntimePath(value) => {
                         runtime_path = value;
                     }
+                    IndexedArtifactItem::PkgConfigPath(value) => {
+                        pkg_config_path = value;
+                    }
                     IndexedArtifactItem::Interpreters(value) => {
                         interpreters = value;
                     }
@@ -1079,6 +1484,15 @@ impl ArtifactContext {
                     IndexedArtifactItem::MachO((path, metadata)) => {
                         machos.insert(path, metadata);
                     }
+                    IndexedArtifactItem::HostPaths((path, paths)) => {
+                        host_paths.insert(path, paths);
+                    }
+                    IndexedArtifactItem::CMakeConfigPaths((path, paths)) => {
+                        cmake_config_paths.insert(path, paths);
+                    }
+                    IndexedArtifactItem::Hook((name, content)) => {
+                        hooks.insert(name, content);
+                    }
                 }
             }
         }
@@ -1102,20 +1516,11 @@ impl ArtifactContext {
             .into_iter()
             .map(|d| d.to_resolved_dep_ident(target).to_ident().unwrap())
             .collect();
-        let hash = if let Some(hash) = hash {
-            hash.clone()
-        } else {
-            Blake3::from_path(artifact_path.as_ref()).with_context(|| {
-                format!(
-                    "Failed to generate hash for artifact {}",
-                    artifact_path.as_ref().display(),
-                )
-            })?
-        };
+        let hash = hash(reader)?;
         debug!(
             "Artifact {} data loaded from {} in {}s",
             id,
-            artifact_path.as_ref().display(),
+            artifact_path.display(),
             start.elapsed().as_secs_f32()
         );
         Ok(InnerArtifactContext {
@@ -1126,27 +1531,249 @@ impl ArtifactContext {
             ),
             id,
             target,
+            metafile_target,
             package_type,
             source,
             deps,
             tdeps,
             build_deps,
             licenses,
+            plan_source,
             runtime_path,
+            pkg_config_path,
+            pkg_config_files,
             interpreters,
             empty_top_level_dirs,
             broken_links,
             empty_links,
             links,
             scripts,
+            unsafe_permissions,
+            host_paths,
+            cmake_config_paths,
+            static_libraries,
+            hooks,
+            installed_size,
+            files,
             elfs,
             machos,
-            hash: hash.clone(),
+            hash,
             is_dirty: true,
         }
         .into())
     }
 
+    /// Parses the contents of an artifact file read from the archive
+    fn raw_item_index(
+        raw_item: Result<Option<RawArtifactItem>>,
+    ) -> Result<Vec<IndexedArtifactItem>> {
+        if let Some(raw_item) = raw_item? {
+            match raw_item {
+                RawArtifactItem::MetaFile(file_name, data) => {
+                    Ok::<_, color_eyre::eyre::Error>(match file_name.as_str() {
+                        "IDENT" => {
+                            vec![IndexedArtifactItem::PackageIdent(PackageDepIdent::parse(
+                                data.trim(),
+                            )?)]
+                        }
+                        "TARGET" => {
+                            vec![IndexedArtifactItem::MetafileTarget(PackageTarget::parse(
+                                data.trim(),
+                            )?)]
+                        }
+                        "PACKAGE_TYPE" => {
+                            vec![IndexedArtifactItem::PackageType(PackageType::parse(
+                                data.trim(),
+                            )?)]
+                        }
+                        "DEPS" => {
+                            vec![IndexedArtifactItem::PackageDeps(
+                                data.lines()
+                                    .map(PackageDepIdent::parse)
+                                    .collect::<Result<HashSet<_>>>()?,
+                            )]
+                        }
+                        "TDEPS" => {
+                            vec![IndexedArtifactItem::PackageTDeps(
+                                data.lines()
+                                    .map(PackageDepIdent::parse)
+                                    .collect::<Result<HashSet<_>>>()?,
+                            )]
+                        }
+                        "BUILD_DEPS" => {
+                            vec![IndexedArtifactItem::PackageBuildDeps(
+                                data.lines()
+                                    .map(PackageDepIdent::parse)
+                                    .collect::<Result<HashSet<_>>>()?,
+                            )]
+                        }
+                        "RUNTIME_PATH" => {
+                            vec![IndexedArtifactItem::RuntimePath(
+                                data.split(':')
+                                    .map(str::trim)
+                                    .map(PathBuf::from)
+                                    .collect::<Vec<_>>(),
+                            )]
+                        }
+                        "PKG_CONFIG_PATH" => {
+                            vec![IndexedArtifactItem::PkgConfigPath(
+                                data.split(':')
+                                    .map(str::trim)
+                                    .filter(|entry| !entry.is_empty())
+                                    .map(PathBuf::from)
+                                    .collect::<Vec<_>>(),
+                            )]
+                        }
+                        "INTERPRETERS" => {
+                            vec![IndexedArtifactItem::Interpreters(
+                                data.lines().map(PathBuf::from).collect::<Vec<_>>(),
+                            )]
+                        }
+                        "MANIFEST" => {
+                            let mut result = Vec::new();
+                            let mut pkg_source = None;
+                            let mut pkg_shasum = None;
+                            let mut plan_source = String::new();
+                            let mut plan_source_header_read = false;
+                            for line in data.lines() {
+                                if let Some(value) = line.strip_prefix("* __Target__:") {
+                                    let patterns: &[_] = &[' ', '`', '\n'];
+                                    if let Ok(target) =
+                                        PackageTarget::parse(value.trim_matches(patterns))
+                                    {
+                                        result.push(IndexedArtifactItem::PackageTarget(target));
+                                    }
+                                }
+                                if let Some(value) = line.strip_prefix("* __Source__:") {
+                                    let src = value
+                                        .trim()
+                                        .split_terminator(&['[', ']'])
+                                        .collect::<Vec<_>>();
+                                    if let Some(url) = src.get(1) {
+                                        if !url.is_empty() {
+                                            pkg_source = Some(Url::parse(url)?);
+                                        }
+                                    }
+                                }
+                                if let Some(value) = line.strip_prefix("* __SHA__:") {
+                                    let patterns: &[_] = &[' ', '`', '\n'];
+                                    pkg_shasum = Some(value.trim_matches(patterns).to_owned());
+                                }
+                                if plan_source_header_read {
+                                    plan_source.push_str(line);
+                                    plan_source.push('\n');
+                                }
+                                if line.starts_with("## Plan Source") {
+                                    plan_source_header_read = true;
+                                }
+                            }
+                            if let (Some(url), Some(shasum)) = (pkg_source, pkg_shasum) {
+                                result.push(IndexedArtifactItem::PackageSource(PackageSource {
+                                    url: PackageSourceURL::from(url),
+                                    shasum: PackageSha256Sum::from(shasum),
+                                }));
+                            }
+                            let shell_type = if cfg!(target_os = "windows") {
+                                "ps1"
+                            } else {
+                                "bash"
+                            };
+
+                            // Split the source based on the delimiter
+                            let split_str = format!("```{}", shell_type);
+                            plan_source = plan_source
+                                .split_once(&split_str)
+                                .unwrap()
+                                .1
+                                .rsplit_once("```")
+                                .unwrap()
+                                .0
+                                .to_string();
+                            result.push(IndexedArtifactItem::Licenses(
+                                ArtifactContext::extract_licenses_from_plan_source(&plan_source)?,
+                            ));
+                            result.push(IndexedArtifactItem::PlanSource(plan_source));
+                            result
+                        }
+                        _ => {
+                            vec![]
+                        }
+                    })
+                }
+                RawArtifactItem::Resource(path, file_mode, kind, data) => {
+                    Ok(if cfg!(target_os = "windows") {
+                        debug!("Skipping raw artifact resource check for issues");
+                        vec![] // Skip processing on Windows
+                    } else {
+                        let resource = match data {
+                            RawResourceData::Memory(data) => {
+                                Resource::from_data(&path, file_mode, kind, data)
+                            }
+                            RawResourceData::Spooled(spool_path, skipped_ranges) => {
+                                let resource = Resource::from_spooled_file(
+                                    &path,
+                                    file_mode,
+                                    kind,
+                                    &spool_path,
+                                    skipped_ranges,
+                                );
+                                let _ = std::fs::remove_file(&spool_path);
+                                resource
+                            }
+                        };
+                        match resource {
+                            Err(err) => {
+                                error!(
+                                    "Failed to read {} detected as {:?} resource: {:?}",
+                                    path.display(),
+                                    kind,
+                                    err
+                                );
+                                vec![]
+                            }
+                            Ok(resource) => match resource {
+                                Resource::Elf(metadata) => {
+                                    vec![IndexedArtifactItem::Elf((path, metadata))]
+                                }
+                                Resource::Script(metadata) => {
+                                    vec![IndexedArtifactItem::Script((path, metadata))]
+                                }
+                                Resource::MachO(metadata) => {
+                                    vec![IndexedArtifactItem::MachO((path, metadata))]
+                                }
+                                _ => {
+                                    vec![]
+                                }
+                            },
+                        }
+                    })
+                }
+                RawArtifactItem::TextFile(path, data) => {
+                    let host_paths = host_paths_find(&data);
+                    Ok(if cfg!(target_os = "windows") || host_paths.is_empty() {
+                        vec![]
+                    } else {
+                        vec![IndexedArtifactItem::HostPaths((path, host_paths))]
+                    })
+                }
+                RawArtifactItem::CMakeConfig(path, data) => {
+                    let paths = absolute_paths_find(&data);
+                    Ok(if cfg!(target_os = "windows") || paths.is_empty() {
+                        vec![]
+                    } else {
+                        vec![IndexedArtifactItem::CMakeConfigPaths((path, paths))]
+                    })
+                }
+                RawArtifactItem::Hook(name, data) => Ok(vec![IndexedArtifactItem::Hook((
+                    name,
+                    String::from_utf8_lossy(&data).to_string(),
+                ))]),
+            }
+        } else {
+            Ok(vec![])
+        }
+    }
+
     #[cfg(not(target_os = "windows"))]
     fn extract_licenses_from_plan_source(plan_source: &str) -> Result<Vec<String>> {
         let mut child =  Command::new("bash")
@@ -1424,9 +2051,29 @@ impl<'a> ExecutableMetadata<'a> {
     }
 }
 
+/// Checks that the file name of an artifact agrees with the ident and target
+/// recorded in its metadata. Renamed or hand-rolled artifacts can otherwise be
+/// indexed under an ident that differs from their contents.
+fn artifact_consistency_check(artifact_path: &Path, id: &PackageIdent) -> Result<()> {
+    let file_name = artifact_path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .ok_or(eyre!("Invalid artifact name"))?;
+    let expected_file_name = id.artifact_name();
+    if file_name != expected_file_name {
+        return Err(eyre!(
+            "File name does not match the artifact metadata, expected '{}' for {}",
+            expected_file_name,
+            id
+        ));
+    }
+    Ok(())
+}
+
 pub(crate) struct ArtifactIndexer<'a> {
     store: &'a Store,
-    sender: Sender<LazyArtifactContext>,
+    key_cache_path: Option<&'a HabitatKeyCachePath>,
+    sender: Sender<(LazyArtifactContext, bool)>,
 }
 
 impl<'a> ParallelVisitor for ArtifactIndexer<'a> {
@@ -1442,7 +2089,23 @@ impl<'a> ParallelVisitor for ArtifactIndexer<'a> {
                         entry.path().display()
                     )
                 });
-                if let Some(artifact_ctx) = self
+                let is_verified = match self.key_cache_path {
+                    Some(key_cache_path) => {
+                        match artifact_signature_verify(entry.path(), key_cache_path) {
+                            Ok(()) => true,
+                            Err(err) => {
+                                warn!(
+                                    "Failed to verify signature of package artifact '{}': {}",
+                                    entry.path().display(),
+                                    err
+                                );
+                                false
+                            }
+                        }
+                    }
+                    None => true,
+                };
+                let artifact_ctx = if let Some(artifact_ctx) = self
                     .store
                     .get_connection()
                     .expect("Failed to open connection to hab-auto-build sqlite database")
@@ -1450,25 +2113,31 @@ impl<'a> ParallelVisitor for ArtifactIndexer<'a> {
                     .expect("Failed to read artifact context from hab-auto-build sqlite database")
                 {
                     debug!("Artifact {} loaded from cache", artifact_ctx.id);
-                    self.sender
-                        .send(LazyArtifactContext::Loaded(artifact_ctx))
-                        .expect("Failed to send artifact context to parent thread");
+                    LazyArtifactContext::Loaded(artifact_ctx)
                 } else {
                     match ArtifactContext::lazy_read_from_disk(entry.path(), Some(&hash)) {
-                        Ok(artifact_ctx) => {
-                            self.sender
-                                .send(LazyArtifactContext::NotLoaded(artifact_ctx))
-                                .expect("Failed to send artifact context to parent thread");
-                        }
+                        Ok(artifact_ctx) => LazyArtifactContext::NotLoaded(artifact_ctx),
                         Err(err) => {
                             error!(
                                 "Failed to read contents of package artifact '{}': {}",
                                 entry.path().display(),
                                 err
                             );
+                            return WalkState::Continue;
                         }
                     }
+                };
+                if let Err(err) = artifact_consistency_check(entry.path(), artifact_ctx.id()) {
+                    warn!(
+                        "Skipping inconsistent package artifact '{}': {}",
+                        entry.path().display(),
+                        err
+                    );
+                    return WalkState::Continue;
                 }
+                self.sender
+                    .send((artifact_ctx, is_verified))
+                    .expect("Failed to send artifact context to parent thread");
             } else {
                 return WalkState::Continue;
             }
@@ -1477,6 +2146,55 @@ impl<'a> ParallelVisitor for ArtifactIndexer<'a> {
     }
 }
 
+/// Finds the absolute paths in the contents of a text file. Paths are only
+/// recognized at the start of a word, or after a '-L', '-I' or '-R' compiler
+/// flag, so paths nested within other paths and URLs are not reported.
+fn absolute_paths_find(data: &[u8]) -> BTreeSet<PathBuf> {
+    let mut paths = BTreeSet::new();
+    // Binary data in a file with a text extension is not worth scanning
+    if data.contains(&0) {
+        return paths;
+    }
+    let is_path_byte = |byte: u8| byte.is_ascii_alphanumeric() || b"/._-+@$%{}()".contains(&byte);
+    let mut index = 0;
+    while index < data.len() {
+        if data[index] != b'/' {
+            index += 1;
+            continue;
+        }
+        let is_word_start = index == 0
+            || !is_path_byte(data[index - 1])
+            || (index >= 2 && data[index - 2] == b'-' && b"LIR".contains(&data[index - 1]));
+        let end = data[index..]
+            .iter()
+            .position(|byte| !is_path_byte(*byte))
+            .map_or(data.len(), |length| index + length);
+        if is_word_start {
+            let path = String::from_utf8_lossy(&data[index..end]);
+            let path = path.trim_end_matches(['/', '.']);
+            if !path.is_empty() && !path.starts_with("//") {
+                paths.insert(PathBuf::from(path));
+            }
+        }
+        index = end.max(index + 1);
+    }
+    paths
+}
+
+/// Finds the absolute paths to host directories in the contents of a text file
+fn host_paths_find(data: &[u8]) -> BTreeSet<PathBuf> {
+    absolute_paths_find(data)
+        .into_iter()
+        .filter(|path| {
+            HOST_PATH_PREFIXES.iter().any(|prefix| {
+                path.to_str()
+                    .and_then(|path| path.strip_prefix(prefix))
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+        })
+        .collect()
+}
+
 pub(crate) enum Resource {
     Elf(ElfMetadata),
     MachO(MachOMetadata),
@@ -1513,73 +2231,14 @@ impl Resource {
                     is_executable: file_mode & 0o111 != 0,
                 }))
             }
-            FileKind::Elf | FileKind::MachBinary => {
+            FileKind::Elf => Ok(Resource::Elf(ElfMetadata::read(
+                path,
+                file_mode,
+                Cursor::new(data),
+            )?)),
+            FileKind::MachBinary => {
                 let object = Object::parse(&data)?;
-                // Determine the exact elf type, for more details check the following:
-                // ELF Header (Section 1-3): https://www.cs.cmu.edu/afs/cs/academic/class/15213-f00/docs/elf.pdf
-                // https://unix.stackexchange.com/questions/89211/how-to-test-whether-a-linux-binary-was-compiled-as-position-independent-code/435038#435038
                 match object {
-                    Object::Elf(object) => {
-                        let is_executable = file_mode & 0o111 != 0;
-                        let elf_type = if object.header.e_type == ET_DYN {
-                            if let Some(dynamic) = object.dynamic {
-                                if dynamic.info.flags_1 & DF_1_PIE == DF_1_PIE {
-                                    ElfType::PieExecutable
-                                } else {
-                                    ElfType::SharedLibrary
-                                }
-                            } else if is_executable {
-                                ElfType::Executable
-                            } else {
-                                ElfType::SharedLibrary
-                            }
-                        } else if object.header.e_type == ET_EXEC {
-                            ElfType::Executable
-                        } else {
-                            ElfType::Other
-                        };
-
-                        Ok(Resource::Elf(ElfMetadata {
-                            required_libraries: object
-                                .libraries
-                                .into_iter()
-                                .map(String::from)
-                                .collect(),
-                            rpath: object
-                                .rpaths
-                                .iter()
-                                .flat_map(|v| v.split(':'))
-                                .map(|v| {
-                                    if v.contains("$ORIGIN") {
-                                        PathBuf::from(v.replace(
-                                            "$ORIGIN",
-                                            path.as_ref().parent().unwrap().to_str().unwrap(),
-                                        ))
-                                    } else {
-                                        PathBuf::from(v)
-                                    }
-                                })
-                                .collect::<Vec<_>>(),
-                            runpath: object
-                                .runpaths
-                                .iter()
-                                .flat_map(|v| v.split(':'))
-                                .map(|v| {
-                                    if v.contains("$ORIGIN") {
-                                        PathBuf::from(v.replace(
-                                            "$ORIGIN",
-                                            path.as_ref().parent().unwrap().to_str().unwrap(),
-                                        ))
-                                    } else {
-                                        PathBuf::from(v)
-                                    }
-                                })
-                                .collect::<Vec<_>>(),
-                            interpreter: object.interpreter.map(PathBuf::from),
-                            elf_type,
-                            is_executable,
-                        }))
-                    }
                     Object::Mach(macho) => {
                         let mut metadata = MachOMetadata { archs: Vec::new() };
                         match macho {
@@ -1641,11 +2300,440 @@ impl Resource {
             }
         }
     }
+
+    /// Reads a resource from a temporary copy of a large binary, only the
+    /// headers and dynamic linking sections of ELF binaries are read. The
+    /// `skipped_ranges` of the binary are missing from the copy.
+    pub fn from_spooled_file(
+        path: impl AsRef<Path>,
+        file_mode: u32,
+        kind: FileKind,
+        spool_path: impl AsRef<Path>,
+        skipped_ranges: Vec<Range<u64>>,
+    ) -> Result<Resource> {
+        match kind {
+            FileKind::Elf => Ok(Resource::Elf(ElfMetadata::read(
+                path,
+                file_mode,
+                SpooledElfReader {
+                    reader: BufReader::new(File::open(spool_path.as_ref())?),
+                    skipped_ranges,
+                    position: 0,
+                },
+            )?)),
+            _ => Resource::from_data(path, file_mode, kind, std::fs::read(spool_path.as_ref())?),
+        }
+    }
+}
+
+impl ElfMetadata {
+    /// Reads the metadata of an ELF binary. Only its headers and the sections used
+    /// by the dynamic linker are read, so the code and data of large binaries are
+    /// never loaded into memory.
+    fn read(
+        path: impl AsRef<Path>,
+        file_mode: u32,
+        mut reader: impl Read + Seek,
+    ) -> Result<ElfMetadata> {
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        let header_data =
+            elf_region_read(&mut reader, file_size, 0, file_size.min(SIZEOF_EHDR as u64))?;
+        let header = Elf::parse_header(&header_data)?;
+        let ctx = Ctx::new(header.container()?, header.endianness()?);
+        let is_executable = file_mode & 0o111 != 0;
+
+        let program_headers_data = elf_region_read(
+            &mut reader,
+            file_size,
+            header.e_phoff,
+            header.e_phnum as u64 * ProgramHeader::size(ctx) as u64,
+        )?;
+        let mut program_headers =
+            ProgramHeader::parse(&program_headers_data, 0, header.e_phnum as usize, ctx)?;
+
+        let mut interpreter = None;
+        for program_header in program_headers
+            .iter()
+            .filter(|program_header| program_header.p_type == PT_INTERP)
+            .filter(|program_header| program_header.p_filesz != 0)
+        {
+            interpreter = elf_region_read(
+                &mut reader,
+                file_size,
+                program_header.p_offset,
+                program_header.p_filesz - 1,
+            )
+            .ok()
+            .and_then(|data| String::from_utf8(data).ok())
+            .map(PathBuf::from);
+        }
+
+        // The dynamic section is parsed on its own, the other program headers are
+        // still needed to translate the addresses it contains to file offsets
+        let mut dynamic_data = Vec::new();
+        if let Some(program_header) = program_headers
+            .iter_mut()
+            .find(|program_header| program_header.p_type == PT_DYNAMIC)
+        {
+            dynamic_data = elf_region_read(
+                &mut reader,
+                file_size,
+                program_header.p_offset,
+                program_header.p_filesz,
+            )?;
+            program_header.p_offset = 0;
+        }
+        let dynamic = Dynamic::parse(&dynamic_data, &program_headers, ctx)?;
+        let dynstrtab_data = match dynamic.as_ref() {
+            Some(dynamic) => elf_region_read(
+                &mut reader,
+                file_size,
+                dynamic.info.strtab as u64,
+                dynamic.info.strsz as u64,
+            )?,
+            None => Vec::new(),
+        };
+        let dynstrtab = Strtab::parse(&dynstrtab_data, 0, dynstrtab_data.len(), 0x0)?;
+
+        let section_header_size = SectionHeader::size(ctx) as u64;
+        let section_headers = if header.e_shoff == 0 {
+            Vec::new()
+        } else {
+            let mut count = header.e_shnum as u64;
+            if count == 0 {
+                // The number of sections is stored in the first section header when
+                // it is too large for the ELF header
+                let data =
+                    elf_region_read(&mut reader, file_size, header.e_shoff, section_header_size)?;
+                count = SectionHeader::parse_from(&data, 0, 1, ctx)?[0].sh_size;
+            }
+            let data = elf_region_read(
+                &mut reader,
+                file_size,
+                header.e_shoff,
+                count.saturating_mul(section_header_size),
+            )?;
+            SectionHeader::parse_from(&data, 0, count as usize, ctx)?
+        };
+        let mut shdr_strtab_index = header.e_shstrndx as usize;
+        if shdr_strtab_index == SHN_XINDEX as usize {
+            shdr_strtab_index = section_headers
+                .first()
+                .map_or(usize::MAX, |section_header| section_header.sh_link as usize);
+        }
+        let shdr_strtab_data = match section_headers.get(shdr_strtab_index) {
+            Some(section_header) => elf_section_read(&mut reader, file_size, section_header)?,
+            None => Vec::new(),
+        };
+        let shdr_strtab = Strtab::parse(&shdr_strtab_data, 0, shdr_strtab_data.len(), 0x0)?;
+
+        let verneed_section =
+            elf_section_find_read(&mut reader, file_size, &section_headers, SHT_GNU_VERNEED)?;
+        let verneed = match verneed_section.as_ref() {
+            Some((data, section_header)) => {
+                VerneedSection::parse(data, std::slice::from_ref(section_header), ctx)?
+            }
+            None => None,
+        };
+        let verdef_section =
+            elf_section_find_read(&mut reader, file_size, &section_headers, SHT_GNU_VERDEF)?;
+        let verdef = match verdef_section.as_ref() {
+            Some((data, section_header)) => {
+                VerdefSection::parse(data, std::slice::from_ref(section_header), ctx)?
+            }
+            None => None,
+        };
+
+        let debug_size = section_headers
+            .iter()
+            .filter(|section_header| {
+                shdr_strtab
+                    .get_at(section_header.sh_name)
+                    .is_some_and(|name| name.starts_with(".debug"))
+            })
+            .map(|section_header| section_header.sh_size)
+            .sum();
+        let mut required_symbol_versions: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        if let Some(verneed) = verneed.as_ref() {
+            for need in verneed.iter() {
+                if let Some(library) = dynstrtab.get_at(need.vn_file) {
+                    required_symbol_versions
+                        .entry(library.to_string())
+                        .or_default()
+                        .extend(
+                            need.iter()
+                                .filter_map(|aux| dynstrtab.get_at(aux.vna_name).map(String::from)),
+                        );
+                }
+            }
+        }
+        let mut provided_symbol_versions = BTreeSet::new();
+        if let Some(verdef) = verdef.as_ref() {
+            // The base definition only names the library itself
+            for def in verdef.iter().filter(|def| def.vd_flags & VER_FLG_BASE == 0) {
+                if let Some(version) = def
+                    .iter()
+                    .next()
+                    .and_then(|aux| dynstrtab.get_at(aux.vda_name))
+                {
+                    provided_symbol_versions.insert(version.to_string());
+                }
+            }
+        }
+
+        let mut required_libraries = Vec::new();
+        let mut rpath = Vec::new();
+        let mut runpath = Vec::new();
+        if let Some(dynamic) = dynamic.as_ref() {
+            if dynamic.info.needed_count > 0 {
+                required_libraries = dynamic
+                    .get_libraries(&dynstrtab)
+                    .into_iter()
+                    .map(String::from)
+                    .collect();
+            }
+            let search_paths = |tag: u64| {
+                dynamic
+                    .dyns
+                    .iter()
+                    .filter(move |entry| entry.d_tag == tag)
+                    .filter_map(|entry| dynstrtab.get_at(entry.d_val as usize))
+                    .flat_map(|v| v.split(':'))
+                    .map(|v| {
+                        if v.contains("$ORIGIN") {
+                            PathBuf::from(v.replace(
+                                "$ORIGIN",
+                                path.as_ref().parent().unwrap().to_str().unwrap(),
+                            ))
+                        } else {
+                            PathBuf::from(v)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            };
+            rpath = search_paths(DT_RPATH);
+            runpath = search_paths(DT_RUNPATH);
+        }
+
+        // Determine the exact elf type, for more details check the following:
+        // ELF Header (Section 1-3): https://www.cs.cmu.edu/afs/cs/academic/class/15213-f00/docs/elf.pdf
+        // https://unix.stackexchange.com/questions/89211/how-to-test-whether-a-linux-binary-was-compiled-as-position-independent-code/435038#435038
+        let elf_type = if header.e_type == ET_DYN {
+            if let Some(dynamic) = dynamic.as_ref() {
+                if dynamic.info.flags_1 & DF_1_PIE == DF_1_PIE {
+                    ElfType::PieExecutable
+                } else {
+                    ElfType::SharedLibrary
+                }
+            } else if is_executable {
+                ElfType::Executable
+            } else {
+                ElfType::SharedLibrary
+            }
+        } else if header.e_type == ET_EXEC {
+            ElfType::Executable
+        } else {
+            ElfType::Other
+        };
+
+        Ok(ElfMetadata {
+            required_libraries,
+            rpath,
+            runpath,
+            interpreter,
+            elf_type,
+            is_executable,
+            debug_size,
+            required_symbol_versions,
+            provided_symbol_versions,
+        })
+    }
+}
+
+/// Reads `len` bytes at `offset` of an ELF binary of `file_size` bytes
+fn elf_region_read(
+    reader: &mut (impl Read + Seek),
+    file_size: u64,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>> {
+    if offset.checked_add(len).is_none_or(|end| end > file_size) {
+        return Err(eyre!(
+            "ELF region at offset {:#x} with size {:#x} extends past the end of the file",
+            offset,
+            len
+        ));
+    }
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![0u8; len as usize];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Reads the contents of a section of an ELF binary
+fn elf_section_read(
+    reader: &mut (impl Read + Seek),
+    file_size: u64,
+    section_header: &SectionHeader,
+) -> Result<Vec<u8>> {
+    if section_header.sh_type == SHT_NOBITS {
+        Ok(Vec::new())
+    } else {
+        elf_region_read(
+            reader,
+            file_size,
+            section_header.sh_offset,
+            section_header.sh_size,
+        )
+    }
+}
+
+/// Reads the contents of the first section of an ELF binary of the given type,
+/// along with a copy of its header that locates it at the start of the contents
+fn elf_section_find_read(
+    reader: &mut (impl Read + Seek),
+    file_size: u64,
+    section_headers: &[SectionHeader],
+    section_type: u32,
+) -> Result<Option<(Vec<u8>, SectionHeader)>> {
+    let Some(section_header) = section_headers
+        .iter()
+        .find(|section_header| section_header.sh_type == section_type)
+    else {
+        return Ok(None);
+    };
+    let data = elf_section_read(reader, file_size, section_header)?;
+    let mut section_header = section_header.clone();
+    section_header.sh_offset = 0;
+    Ok(Some((data, section_header)))
+}
+
+/// Copies an ELF binary of `size` bytes to `spool_file`, `data` holds the bytes
+/// already read from `reader`. Returns the ranges of the binary that were left
+/// out of the copy, which are its code segments when the binary is laid out in a
+/// way that guarantees they do not hold any of the data read by `ElfMetadata::read`.
+fn elf_spool(
+    mut reader: impl Read,
+    mut data: Vec<u8>,
+    size: u64,
+    spool_file: &mut File,
+) -> Result<Vec<Range<u64>>> {
+    if (data.len() as u64) < ELF_HEADERS_MAX_SIZE {
+        (&mut reader)
+            .take(ELF_HEADERS_MAX_SIZE - data.len() as u64)
+            .read_to_end(&mut data)?;
+    }
+    let skipped_ranges = elf_code_ranges(&data, size).unwrap_or_default();
+    spool_file.write_all(&data)?;
+    let mut position = data.len() as u64;
+    for range in skipped_ranges.iter() {
+        std::io::copy(&mut (&mut reader).take(range.start - position), spool_file)?;
+        std::io::copy(
+            &mut (&mut reader).take(range.end - range.start),
+            &mut std::io::sink(),
+        )?;
+        spool_file.seek(SeekFrom::Current((range.end - range.start) as i64))?;
+        position = range.end;
+    }
+    std::io::copy(&mut reader, spool_file)?;
+    spool_file.set_len(size)?;
+    Ok(skipped_ranges)
+}
+
+/// Finds the ranges of the executable segments of an ELF binary from the program
+/// headers in `data`. Current linkers place the ELF headers and the dynamic linking
+/// sections in a read-only segment and the code in executable segments of its own,
+/// for any other layout nothing is returned.
+fn elf_code_ranges(data: &[u8], size: u64) -> Option<Vec<Range<u64>>> {
+    let header = Elf::parse_header(data).ok()?;
+    let ctx = Ctx::new(header.container().ok()?, header.endianness().ok()?);
+    let program_headers =
+        ProgramHeader::parse(data, header.e_phoff as usize, header.e_phnum as usize, ctx).ok()?;
+    let first_segment = program_headers
+        .iter()
+        .filter(|program_header| program_header.p_type == PT_LOAD)
+        .min_by_key(|program_header| program_header.p_offset)?;
+    if first_segment.p_offset != 0 || first_segment.is_executable() {
+        return None;
+    }
+    let required_ranges = program_headers
+        .iter()
+        .filter(|program_header| matches!(program_header.p_type, PT_DYNAMIC | PT_INTERP))
+        .map(|program_header| {
+            program_header.p_offset
+                ..program_header
+                    .p_offset
+                    .saturating_add(program_header.p_filesz)
+        })
+        .chain((header.e_shoff != 0).then_some(header.e_shoff..size))
+        .collect::<Vec<_>>();
+    let mut code_ranges = program_headers
+        .iter()
+        .filter(|program_header| program_header.p_type == PT_LOAD && program_header.is_executable())
+        .map(|program_header| {
+            program_header.p_offset.max(data.len() as u64)
+                ..program_header
+                    .p_offset
+                    .saturating_add(program_header.p_filesz)
+                    .min(size)
+        })
+        .filter(|range| {
+            !range.is_empty()
+                && !required_ranges.iter().any(|required_range| {
+                    required_range.start < range.end && range.start < required_range.end
+                })
+        })
+        .collect::<Vec<_>>();
+    code_ranges.sort_by_key(|range| range.start);
+    // Overlapping segments are not expected, but they would break the copy
+    if code_ranges
+        .windows(2)
+        .any(|ranges| ranges[0].end > ranges[1].start)
+    {
+        return None;
+    }
+    Some(code_ranges)
+}
+
+/// Reader of a spooled copy of an ELF binary, reading any of the ranges left out of
+/// the copy fails instead of returning the zeroes that take their place.
+struct SpooledElfReader<R> {
+    reader: R,
+    skipped_ranges: Vec<Range<u64>>,
+    position: u64,
+}
+
+impl<R: Read> Read for SpooledElfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut len = buf.len() as u64;
+        for range in self.skipped_ranges.iter() {
+            if range.contains(&self.position) {
+                return Err(std::io::Error::other(format!(
+                    "ELF region at offset {:#x} was not copied from the artifact",
+                    self.position
+                )));
+            }
+            if range.start > self.position {
+                len = len.min(range.start - self.position);
+            }
+        }
+        let n = self.reader.read(&mut buf[..len as usize])?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for SpooledElfReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = self.reader.seek(pos)?;
+        Ok(self.position)
+    }
 }
 
 pub(crate) struct ArtifactIndexerBuilder<'a> {
     store: &'a Store,
-    sender: Sender<LazyArtifactContext>,
+    key_cache_path: Option<&'a HabitatKeyCachePath>,
+    sender: Sender<(LazyArtifactContext, bool)>,
 }
 
 impl<'s, 'a> ParallelVisitorBuilder<'s> for ArtifactIndexerBuilder<'a>
@@ -1655,13 +2743,22 @@ where
     fn build(&mut self) -> Box<dyn ignore::ParallelVisitor + 's> {
         Box::new(ArtifactIndexer {
             store: self.store,
+            key_cache_path: self.key_cache_path,
             sender: self.sender.clone(),
         })
     }
 }
 
 impl<'a> ArtifactIndexerBuilder<'a> {
-    pub fn new(store: &'a Store, sender: Sender<LazyArtifactContext>) -> ArtifactIndexerBuilder {
-        ArtifactIndexerBuilder { store, sender }
+    pub fn new(
+        store: &'a Store,
+        key_cache_path: Option<&'a HabitatKeyCachePath>,
+        sender: Sender<(LazyArtifactContext, bool)>,
+    ) -> ArtifactIndexerBuilder<'a> {
+        ArtifactIndexerBuilder {
+            store,
+            key_cache_path,
+            sender,
+        }
     }
 }
@@ -0,0 +1,359 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::{artifact_archive_open, ArtifactContext, ArtifactFile, FSRootPath, PackagePath};
+
+/// Placeholder that replaces the release of an artifact in its file contents
+/// when checking if two builds are identical
+const RELEASE_PLACEHOLDER: &[u8] = b"@@RELEASE@@";
+
+/// Metadata files of an artifact that always differ between builds, they embed
+/// the build time or the hashes and signature of the other files
+const NONDETERMINISTIC_METADATA_FILES: [&str; 2] = ["MANIFEST", "FILES"];
+
+/// Semantic differences between a source artifact and a target artifact, usually
+/// two builds of the same plan. Files are relative to the package folder, so
+/// artifacts of different versions and releases can be compared.
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ArtifactDiff {
+    pub source: String,
+    pub target: String,
+    /// Installed size of the source artifact in bytes
+    pub source_installed_size: u64,
+    /// Installed size of the target artifact in bytes
+    pub target_installed_size: u64,
+    /// Size of the source .hart file in bytes
+    pub source_artifact_size: u64,
+    /// Size of the target .hart file in bytes
+    pub target_artifact_size: u64,
+    /// Files only found in the target artifact
+    pub added_files: Vec<PathBuf>,
+    /// Files only found in the source artifact
+    pub removed_files: Vec<PathBuf>,
+    /// ELF binaries found in both artifacts whose required shared libraries differ
+    pub elf_dependencies: Vec<ElfDependencyDiff>,
+    pub added_licenses: Vec<String>,
+    pub removed_licenses: Vec<String>,
+    /// Runtime path entries only found in the target artifact, entries pointing into
+    /// other packages are compared without their version and release
+    pub added_runtime_path: Vec<PathBuf>,
+    /// Runtime path entries only found in the source artifact
+    pub removed_runtime_path: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ElfDependencyDiff {
+    pub file: PathBuf,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ArtifactDiff {
+    /// Whether the artifacts only differ by their identifiers and sizes
+    pub fn is_empty(&self) -> bool {
+        self.added_files.is_empty()
+            && self.removed_files.is_empty()
+            && self.elf_dependencies.is_empty()
+            && self.added_licenses.is_empty()
+            && self.removed_licenses.is_empty()
+            && self.added_runtime_path.is_empty()
+            && self.removed_runtime_path.is_empty()
+    }
+}
+
+/// Compares the .hart files at `source_path` and `target_path`
+pub(crate) fn artifact_diff(
+    source_path: impl AsRef<Path>,
+    target_path: impl AsRef<Path>,
+) -> Result<ArtifactDiff> {
+    let source_path = source_path.as_ref();
+    let target_path = target_path.as_ref();
+    let source = ArtifactContext::read_from_disk(source_path, None)
+        .with_context(|| format!("Failed to read artifact '{}'", source_path.display()))?;
+    let target = ArtifactContext::read_from_disk(target_path, None)
+        .with_context(|| format!("Failed to read artifact '{}'", target_path.display()))?;
+    let source_files = artifact_files_list(source_path)?;
+    let target_files = artifact_files_list(target_path)?;
+
+    let source_elfs = artifact_elf_dependencies(&source);
+    let mut elf_dependencies = Vec::new();
+    for (file, target_libraries) in artifact_elf_dependencies(&target) {
+        if let Some(source_libraries) = source_elfs.get(&file) {
+            let added = target_libraries
+                .difference(source_libraries)
+                .cloned()
+                .collect::<Vec<_>>();
+            let removed = source_libraries
+                .difference(&target_libraries)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !added.is_empty() || !removed.is_empty() {
+                elf_dependencies.push(ElfDependencyDiff {
+                    file,
+                    added,
+                    removed,
+                });
+            }
+        }
+    }
+
+    let source_licenses = source.licenses.iter().collect::<BTreeSet<_>>();
+    let target_licenses = target.licenses.iter().collect::<BTreeSet<_>>();
+    let source_runtime_path = source
+        .runtime_path
+        .iter()
+        .map(runtime_path_entry_key)
+        .collect::<BTreeSet<_>>();
+    let target_runtime_path = target
+        .runtime_path
+        .iter()
+        .map(runtime_path_entry_key)
+        .collect::<BTreeSet<_>>();
+
+    Ok(ArtifactDiff {
+        source: source.id.to_string(),
+        target: target.id.to_string(),
+        source_installed_size: source.installed_size,
+        target_installed_size: target.installed_size,
+        source_artifact_size: std::fs::metadata(source_path)?.len(),
+        target_artifact_size: std::fs::metadata(target_path)?.len(),
+        added_files: target_files.difference(&source_files).cloned().collect(),
+        removed_files: source_files.difference(&target_files).cloned().collect(),
+        elf_dependencies,
+        added_licenses: target_licenses
+            .difference(&source_licenses)
+            .map(|license| license.to_string())
+            .collect(),
+        removed_licenses: source_licenses
+            .difference(&target_licenses)
+            .map(|license| license.to_string())
+            .collect(),
+        added_runtime_path: target
+            .runtime_path
+            .iter()
+            .filter(|entry| !source_runtime_path.contains(&runtime_path_entry_key(entry)))
+            .cloned()
+            .collect(),
+        removed_runtime_path: source
+            .runtime_path
+            .iter()
+            .filter(|entry| !target_runtime_path.contains(&runtime_path_entry_key(entry)))
+            .cloned()
+            .collect(),
+    })
+}
+
+/// Lists the files and links of an artifact, relative to its package folder
+fn artifact_files_list(artifact_path: &Path) -> Result<BTreeSet<PathBuf>> {
+    Ok(ArtifactFile::read_all(artifact_path)?
+        .into_iter()
+        .map(|file| file.path)
+        .collect())
+}
+
+/// Required shared libraries of each ELF binary of an artifact, by the path of the
+/// binary relative to the package folder
+fn artifact_elf_dependencies(artifact: &ArtifactContext) -> BTreeMap<PathBuf, BTreeSet<String>> {
+    artifact
+        .elfs
+        .iter()
+        .filter_map(|(path, metadata)| {
+            path.relative_package_path()
+                .map(|file| (file, metadata.required_libraries.iter().cloned().collect()))
+        })
+        .collect()
+}
+
+/// Identifies a runtime path entry regardless of the version and release of the
+/// package it points into
+fn runtime_path_entry_key(entry: &PathBuf) -> String {
+    match (entry.package_path(), entry.relative_package_path()) {
+        (Some(package_path), Some(relative_path)) => {
+            let mut components = package_path.components().skip(3);
+            let origin = components.next();
+            let name = components.next();
+            match (origin, name) {
+                (Some(origin), Some(name)) => format!(
+                    "{}/{}/{}",
+                    origin.as_os_str().to_string_lossy(),
+                    name.as_os_str().to_string_lossy(),
+                    relative_path.display()
+                ),
+                _ => entry.display().to_string(),
+            }
+        }
+        _ => entry.display().to_string(),
+    }
+}
+
+/// Files whose contents differ between two builds of the same plan, after the
+/// expected differences between builds are normalized away. Modification times
+/// are ignored and the releases of the artifacts are treated as equal wherever
+/// they appear in paths and file contents.
+#[derive(Debug)]
+pub(crate) struct ArtifactContentDiff {
+    /// Files only found in the target artifact
+    pub added_files: Vec<PathBuf>,
+    /// Files only found in the source artifact
+    pub removed_files: Vec<PathBuf>,
+    /// Files found in both artifacts with different contents, modes or link targets
+    pub changed_files: Vec<PathBuf>,
+}
+
+impl ArtifactContentDiff {
+    /// Whether the builds are identical once normalized
+    pub fn is_empty(&self) -> bool {
+        self.added_files.is_empty()
+            && self.removed_files.is_empty()
+            && self.changed_files.is_empty()
+    }
+}
+
+/// Compares the normalized contents of the .hart files at `source_path` and `target_path`
+pub(crate) fn artifact_content_diff(
+    source_path: impl AsRef<Path>,
+    target_path: impl AsRef<Path>,
+) -> Result<ArtifactContentDiff> {
+    let source_files = artifact_files_hash(source_path.as_ref())?;
+    let target_files = artifact_files_hash(target_path.as_ref())?;
+    Ok(ArtifactContentDiff {
+        added_files: target_files
+            .keys()
+            .filter(|file| !source_files.contains_key(*file))
+            .cloned()
+            .collect(),
+        removed_files: source_files
+            .keys()
+            .filter(|file| !target_files.contains_key(*file))
+            .cloned()
+            .collect(),
+        changed_files: source_files
+            .iter()
+            .filter(|(file, hash)| {
+                target_files
+                    .get(*file)
+                    .is_some_and(|target_hash| target_hash != *hash)
+            })
+            .map(|(file, _)| file.clone())
+            .collect(),
+    })
+}
+
+/// Hashes the files and links of an artifact with their release replaced by a
+/// placeholder, by their path relative to the package folder
+fn artifact_files_hash(artifact_path: &Path) -> Result<BTreeMap<PathBuf, blake3::Hash>> {
+    let mut archive = artifact_archive_open(artifact_path)?;
+    let mut release: Option<Vec<u8>> = None;
+    let mut files = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            continue;
+        }
+        let entry_install_path = FSRootPath::default().as_ref().join(entry.path()?);
+        let (Some(package_path), Some(file)) = (
+            entry_install_path.package_path(),
+            entry_install_path.relative_package_path(),
+        ) else {
+            continue;
+        };
+        if NONDETERMINISTIC_METADATA_FILES
+            .iter()
+            .any(|metadata_file| file.as_os_str() == *metadata_file)
+        {
+            continue;
+        }
+        let release = release.get_or_insert_with(|| {
+            package_path
+                .file_name()
+                .map(|release| release.as_encoded_bytes().to_vec())
+                .unwrap_or_default()
+        });
+        let mut hasher = ReleaseNormalizingHasher::new(release);
+        hasher.update(&entry.header().mode()?.to_le_bytes());
+        if let Some(link_name) = entry.link_name_bytes() {
+            hasher.update(&[entry_type.as_byte()]);
+            hasher.update(&link_name);
+        } else {
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                let bytes_read = entry.read(&mut buffer).with_context(|| {
+                    format!(
+                        "Failed to read '{}' from artifact '{}'",
+                        file.display(),
+                        artifact_path.display()
+                    )
+                })?;
+                if bytes_read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..bytes_read]);
+            }
+        }
+        files.insert(file, hasher.finalize());
+    }
+    if release.is_none() {
+        return Err(eyre!(
+            "The artifact '{}' does not contain any package files",
+            artifact_path.display()
+        ));
+    }
+    Ok(files)
+}
+
+/// Hasher that replaces every occurrence of a release with [`RELEASE_PLACEHOLDER`]
+/// before hashing, occurrences split across updates are handled by holding back
+/// the bytes that could be the start of one.
+struct ReleaseNormalizingHasher<'a> {
+    release: &'a [u8],
+    pending: Vec<u8>,
+    hasher: blake3::Hasher,
+}
+
+impl<'a> ReleaseNormalizingHasher<'a> {
+    fn new(release: &'a [u8]) -> Self {
+        Self {
+            release,
+            pending: Vec::new(),
+            hasher: blake3::Hasher::new(),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+        if self.release.is_empty() {
+            self.hasher.update(&self.pending);
+            self.pending.clear();
+            return;
+        }
+        let hold_back = self.release.len() - 1;
+        let mut start = 0;
+        let mut index = 0;
+        while index + self.release.len() <= self.pending.len() {
+            if self.pending[index..].starts_with(self.release) {
+                self.hasher.update(&self.pending[start..index]);
+                self.hasher.update(RELEASE_PLACEHOLDER);
+                index += self.release.len();
+                start = index;
+            } else {
+                index += 1;
+            }
+        }
+        let flushed = start.max(self.pending.len().saturating_sub(hold_back));
+        self.hasher.update(&self.pending[start..flushed]);
+        self.pending.drain(..flushed);
+    }
+
+    fn finalize(mut self) -> blake3::Hash {
+        self.hasher.update(&self.pending);
+        self.hasher.finalize()
+    }
+}
@@ -0,0 +1,644 @@
+use std::{
+    env,
+    fmt::Display,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use reqwest::{
+    blocking::{Client, ClientBuilder},
+    header, StatusCode,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::debug;
+
+use super::PackageSourceURL;
+
+/// Endpoint of the OSV API querying the vulnerabilities of a package version or commit
+const OSV_QUERY_URL: &str = "https://api.osv.dev/v1/query";
+
+/// Endpoint of the NVD API searching CVEs
+const NVD_CVES_URL: &str = "https://services.nvd.nist.gov/rest/json/cves/2.0";
+
+/// Largest number of CVEs the NVD API returns for a single request
+const NVD_PAGE_SIZE: usize = 2000;
+
+/// Time between requests to the NVD API, it allows 5 requests in a rolling 30 second
+/// window without an API key and 50 with one
+const NVD_REQUEST_INTERVAL: Duration = Duration::from_secs(6);
+const NVD_API_KEY_REQUEST_INTERVAL: Duration = Duration::from_millis(600);
+
+/// Number of times a request rejected by the NVD rate limit is retried
+const NVD_REQUEST_RETRIES: u32 = 4;
+
+/// Delay before retrying a request rejected by the NVD rate limit, doubled after each retry
+const NVD_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Configures how the known vulnerabilities of a plan are looked up, in the
+/// 'audit' table of the plan's `.hab-plan-config.toml`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub(crate) struct AuditConfig {
+    /// OSV ecosystem the plan's package is published in, such as 'PyPI' or 'npm'
+    pub ecosystem: Option<String>,
+    /// Name of the package in the OSV ecosystem, or of the product in the NVD,
+    /// defaults to the name of the plan
+    pub package: Option<String>,
+    /// Ids of the vulnerabilities that do not affect the plan
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+/// Where the known vulnerabilities of a plan are looked up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AuditQuery {
+    /// Vulnerabilities of a package version in an OSV ecosystem, git repositories
+    /// are in the 'GIT' ecosystem with their release tags as versions
+    OsvPackage {
+        ecosystem: String,
+        package: String,
+        version: String,
+    },
+    /// Vulnerabilities affecting a commit of a git repository in OSV
+    OsvCommit { commit: String },
+    /// CVEs of a product version in the NVD
+    Nvd { product: String, version: String },
+}
+
+impl Display for AuditQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditQuery::OsvPackage {
+                ecosystem,
+                package,
+                version,
+            } => write!(f, "OSV {} {}@{}", ecosystem, package, version),
+            AuditQuery::OsvCommit { commit } => write!(f, "OSV commit {}", commit),
+            AuditQuery::Nvd { product, version } => {
+                write!(f, "NVD cpe:2.3:a:*:{}:{}", product, version)
+            }
+        }
+    }
+}
+
+impl AuditQuery {
+    /// Picks where to look up the vulnerabilities of a plan. The plan's configuration
+    /// takes precedence over the git repository of its source, which takes precedence
+    /// over the NVD.
+    pub fn new(
+        name: &str,
+        version: &str,
+        source_url: Option<&PackageSourceURL>,
+        config: Option<&AuditConfig>,
+    ) -> AuditQuery {
+        let package = config
+            .and_then(|config| config.package.clone())
+            .unwrap_or_else(|| name.to_string());
+        if let Some(ecosystem) = config.and_then(|config| config.ecosystem.as_ref()) {
+            return AuditQuery::OsvPackage {
+                ecosystem: ecosystem.clone(),
+                package,
+                version: version.to_string(),
+            };
+        }
+        if let Some(git_source) = source_url.and_then(|source_url| source_url.git_source()) {
+            if git_source.revision.len() == 40
+                && git_source
+                    .revision
+                    .chars()
+                    .all(|char| char.is_ascii_hexdigit())
+            {
+                return AuditQuery::OsvCommit {
+                    commit: git_source.revision,
+                };
+            }
+            return AuditQuery::OsvPackage {
+                ecosystem: "GIT".to_string(),
+                package: git_source.repo_url.trim_end_matches(".git").to_string(),
+                version: git_source.revision,
+            };
+        }
+        if let Some((repo_url, tag)) = source_url.and_then(github_release_tag) {
+            return AuditQuery::OsvPackage {
+                ecosystem: "GIT".to_string(),
+                package: repo_url,
+                version: tag,
+            };
+        }
+        AuditQuery::Nvd {
+            product: package.to_lowercase(),
+            version: version.to_string(),
+        }
+    }
+}
+
+/// Finds the repository and release tag of a source archive downloaded from GitHub,
+/// like 'https://github.com/<owner>/<repo>/archive/refs/tags/<tag>.tar.gz' or
+/// 'https://github.com/<owner>/<repo>/releases/download/<tag>/<file>'
+fn github_release_tag(source_url: &PackageSourceURL) -> Option<(String, String)> {
+    let url = source_url.as_ref();
+    if url.host_str() != Some("github.com") {
+        return None;
+    }
+    let segments = url.path_segments()?.collect::<Vec<_>>();
+    let (owner, repo) = (segments.first()?, segments.get(1)?);
+    let tag = match &segments[2..] {
+        ["archive", "refs", "tags", archive] | ["archive", archive] => [".tar.gz", ".tgz", ".zip"]
+            .iter()
+            .find_map(|extension| archive.strip_suffix(extension))?,
+        ["releases", "download", tag, _] => tag,
+        _ => return None,
+    };
+    Some((
+        format!(
+            "https://github.com/{}/{}",
+            owner,
+            repo.trim_end_matches(".git")
+        ),
+        tag.to_string(),
+    ))
+}
+
+/// Severity of a vulnerability, from its CVSS score when it has one
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum VulnerabilitySeverity {
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Display for VulnerabilitySeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VulnerabilitySeverity::Unknown => write!(f, "unknown"),
+            VulnerabilitySeverity::Low => write!(f, "low"),
+            VulnerabilitySeverity::Medium => write!(f, "medium"),
+            VulnerabilitySeverity::High => write!(f, "high"),
+            VulnerabilitySeverity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+impl VulnerabilitySeverity {
+    fn parse(value: &str) -> VulnerabilitySeverity {
+        match value.to_uppercase().as_str() {
+            "LOW" => VulnerabilitySeverity::Low,
+            "MODERATE" | "MEDIUM" => VulnerabilitySeverity::Medium,
+            "HIGH" => VulnerabilitySeverity::High,
+            "CRITICAL" => VulnerabilitySeverity::Critical,
+            _ => VulnerabilitySeverity::Unknown,
+        }
+    }
+
+    fn from_score(score: f64) -> VulnerabilitySeverity {
+        if score >= 9.0 {
+            VulnerabilitySeverity::Critical
+        } else if score >= 7.0 {
+            VulnerabilitySeverity::High
+        } else if score >= 4.0 {
+            VulnerabilitySeverity::Medium
+        } else if score > 0.0 {
+            VulnerabilitySeverity::Low
+        } else {
+            VulnerabilitySeverity::Unknown
+        }
+    }
+}
+
+/// Known vulnerability affecting the version of a plan
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub(crate) struct Vulnerability {
+    /// Id of the vulnerability in the database it was found in
+    pub id: String,
+    /// Other ids of the vulnerability, such as its CVE id
+    pub aliases: Vec<String>,
+    pub summary: Option<String>,
+    pub severity: VulnerabilitySeverity,
+    /// CVSS base score of the vulnerability
+    pub score: Option<f64>,
+    pub url: String,
+}
+
+impl Vulnerability {
+    /// Whether the vulnerability is one of the given ids, by its id or one of its aliases
+    pub fn is_any_of(&self, ids: &[String]) -> bool {
+        ids.iter()
+            .any(|id| *id == self.id || self.aliases.contains(id))
+    }
+}
+
+/// Looks up the known vulnerabilities of plans in OSV and the NVD
+pub(crate) struct VulnerabilityChecker {
+    client: Client,
+    nvd_request_interval: Duration,
+    /// Time of the last request to the NVD API, locked while a request is made so
+    /// that requests are sent one at a time
+    nvd_last_request: Mutex<Option<Instant>>,
+}
+
+impl VulnerabilityChecker {
+    pub fn new() -> Result<VulnerabilityChecker> {
+        let mut headers = header::HeaderMap::new();
+        headers.append(header::USER_AGENT, "hab-auto-build".parse().unwrap());
+        // Requests to the NVD API without a key are heavily rate limited
+        let mut nvd_request_interval = NVD_REQUEST_INTERVAL;
+        if let Ok(api_key) = env::var("NVD_API_KEY") {
+            headers.append("apiKey", api_key.parse().context("Invalid NVD_API_KEY")?);
+            nvd_request_interval = NVD_API_KEY_REQUEST_INTERVAL;
+        }
+        Ok(VulnerabilityChecker {
+            client: ClientBuilder::new().default_headers(headers).build()?,
+            nvd_request_interval,
+            nvd_last_request: Mutex::new(None),
+        })
+    }
+
+    pub fn vulnerabilities(&self, query: &AuditQuery) -> Result<Vec<Vulnerability>> {
+        match query {
+            AuditQuery::OsvPackage {
+                ecosystem,
+                package,
+                version,
+            } => self.osv_vulnerabilities(json!({
+                "package": { "ecosystem": ecosystem, "name": package },
+                "version": version,
+            })),
+            AuditQuery::OsvCommit { commit } => {
+                self.osv_vulnerabilities(json!({ "commit": commit }))
+            }
+            AuditQuery::Nvd { product, version } => self.nvd_vulnerabilities(product, version),
+        }
+    }
+
+    fn osv_vulnerabilities(&self, mut body: serde_json::Value) -> Result<Vec<Vulnerability>> {
+        let mut vulnerabilities = Vec::new();
+        loop {
+            debug!("Querying OSV for vulnerabilities of {}", body);
+            let response = self
+                .client
+                .post(OSV_QUERY_URL)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+                .context("Failed to query OSV")?;
+            if !response.status().is_success() {
+                return Err(eyre!(
+                    "Failed to query OSV, the server responded with {}",
+                    response.status()
+                ));
+            }
+            let response: serde_json::Value = serde_json::from_str(
+                &response
+                    .text()
+                    .context("Failed to read response from OSV")?,
+            )
+            .context("Invalid response from the OSV API")?;
+            vulnerabilities.extend(
+                response["vulns"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(osv_vulnerability),
+            );
+            match response["next_page_token"].as_str() {
+                Some(page_token) if !page_token.is_empty() => {
+                    body["page_token"] = json!(page_token);
+                }
+                _ => break,
+            }
+        }
+        Ok(vulnerabilities)
+    }
+
+    fn nvd_vulnerabilities(&self, product: &str, version: &str) -> Result<Vec<Vulnerability>> {
+        let mut vulnerabilities = Vec::new();
+        let cpe = format!("cpe:2.3:a:*:{}:{}", product, version);
+        loop {
+            debug!("Querying the NVD for CVEs of {}", cpe);
+            let response = self.nvd_get(&[
+                ("virtualMatchString", cpe.as_str()),
+                ("resultsPerPage", NVD_PAGE_SIZE.to_string().as_str()),
+                ("startIndex", vulnerabilities.len().to_string().as_str()),
+            ])?;
+            let page = response["vulnerabilities"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            vulnerabilities.extend(page.iter().map(|item| nvd_vulnerability(&item["cve"])));
+            let total_results = response["totalResults"].as_u64().unwrap_or_default() as usize;
+            if page.is_empty() || vulnerabilities.len() >= total_results {
+                break;
+            }
+        }
+        Ok(vulnerabilities)
+    }
+
+    /// Sends a request to the NVD API, waiting between requests to stay under its
+    /// rate limit and backing off when the server rejects a request for exceeding it
+    fn nvd_get(&self, query: &[(&str, &str)]) -> Result<serde_json::Value> {
+        let mut retry_delay = NVD_RETRY_DELAY;
+        let mut retries = 0;
+        loop {
+            let mut last_request = self.nvd_last_request.lock().unwrap();
+            if let Some(elapsed) = last_request.map(|last_request| last_request.elapsed()) {
+                if elapsed < self.nvd_request_interval {
+                    std::thread::sleep(self.nvd_request_interval - elapsed);
+                }
+            }
+            let response = self.client.get(NVD_CVES_URL).query(query).send();
+            *last_request = Some(Instant::now());
+            drop(last_request);
+            let response = response.context("Failed to query the NVD")?;
+            if matches!(
+                response.status(),
+                StatusCode::FORBIDDEN
+                    | StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::SERVICE_UNAVAILABLE
+            ) {
+                if retries < NVD_REQUEST_RETRIES {
+                    debug!(
+                        "The NVD responded with {}, retrying in {}s",
+                        response.status(),
+                        retry_delay.as_secs()
+                    );
+                    std::thread::sleep(retry_delay);
+                    retry_delay *= 2;
+                    retries += 1;
+                    continue;
+                }
+                return Err(eyre!(
+                    "Failed to query the NVD, the server responded with {}",
+                    response.status()
+                ))
+                .with_suggestion(|| {
+                    "Set the NVD_API_KEY environment variable to an NVD API key to raise the rate limit"
+                });
+            }
+            if !response.status().is_success() {
+                return Err(eyre!(
+                    "Failed to query the NVD, the server responded with {}",
+                    response.status()
+                ));
+            }
+            return serde_json::from_str(
+                &response
+                    .text()
+                    .context("Failed to read response from the NVD")?,
+            )
+            .context("Invalid response from the NVD API");
+        }
+    }
+}
+
+fn osv_vulnerability(vuln: &serde_json::Value) -> Vulnerability {
+    let id = vuln["id"].as_str().unwrap_or_default().to_string();
+    let score = vuln["severity"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|severity| {
+            matches!(
+                severity["type"].as_str(),
+                Some("CVSS_V3") | Some("CVSS_V31")
+            )
+        })
+        .filter_map(|severity| severity["score"].as_str().and_then(cvss3_base_score))
+        .reduce(f64::max);
+    let severity = match (vuln["database_specific"]["severity"].as_str(), score) {
+        (Some(severity), _)
+            if VulnerabilitySeverity::parse(severity) != VulnerabilitySeverity::Unknown =>
+        {
+            VulnerabilitySeverity::parse(severity)
+        }
+        (_, Some(score)) => VulnerabilitySeverity::from_score(score),
+        _ => VulnerabilitySeverity::Unknown,
+    };
+    Vulnerability {
+        url: format!("https://osv.dev/vulnerability/{}", id),
+        id,
+        aliases: vuln["aliases"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|alias| alias.as_str().map(String::from))
+            .collect(),
+        summary: vuln["summary"].as_str().map(String::from),
+        severity,
+        score,
+    }
+}
+
+fn nvd_vulnerability(cve: &serde_json::Value) -> Vulnerability {
+    let id = cve["id"].as_str().unwrap_or_default().to_string();
+    // The most recent version of CVSS the CVE was scored with is used
+    let metric = ["cvssMetricV31", "cvssMetricV30", "cvssMetricV2"]
+        .iter()
+        .find_map(|version| cve["metrics"][version].as_array()?.first());
+    let score = metric.and_then(|metric| metric["cvssData"]["baseScore"].as_f64());
+    let severity = metric
+        .and_then(|metric| {
+            metric["cvssData"]["baseSeverity"]
+                .as_str()
+                .or(metric["baseSeverity"].as_str())
+        })
+        .map(VulnerabilitySeverity::parse)
+        .or(score.map(VulnerabilitySeverity::from_score))
+        .unwrap_or(VulnerabilitySeverity::Unknown);
+    Vulnerability {
+        url: format!("https://nvd.nist.gov/vuln/detail/{}", id),
+        id,
+        aliases: Vec::new(),
+        summary: cve["descriptions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|description| description["lang"].as_str() == Some("en"))
+            .and_then(|description| description["value"].as_str())
+            .map(String::from),
+        severity,
+        score,
+    }
+}
+
+/// Computes the base score of a CVSS v3 vector, like 'CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H'
+fn cvss3_base_score(vector: &str) -> Option<f64> {
+    let mut metrics = vector.split('/');
+    if !metrics.next()?.starts_with("CVSS:3") {
+        return None;
+    }
+    let metrics = metrics
+        .filter_map(|metric| metric.split_once(':'))
+        .collect::<Vec<_>>();
+    let metric = |name: &str| {
+        metrics
+            .iter()
+            .find(|(metric_name, _)| *metric_name == name)
+            .map(|(_, value)| *value)
+    };
+    let is_scope_changed = match metric("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return None,
+    };
+    let attack_vector: f64 = match metric("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let attack_complexity = match metric("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let privileges_required = match (metric("PR")?, is_scope_changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let user_interaction = match metric("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+    let impact_weight = |name: &str| -> Option<f64> {
+        match metric(name)? {
+            "H" => Some(0.56),
+            "L" => Some(0.22),
+            "N" => Some(0.0),
+            _ => None,
+        }
+    };
+    let impact_sub_score = 1.0
+        - (1.0 - impact_weight("C")?) * (1.0 - impact_weight("I")?) * (1.0 - impact_weight("A")?);
+    let impact = if is_scope_changed {
+        7.52 * (impact_sub_score - 0.029) - 3.25 * (impact_sub_score - 0.02).powi(15)
+    } else {
+        6.42 * impact_sub_score
+    };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability =
+        8.22 * attack_vector * attack_complexity * privileges_required * user_interaction;
+    let score = if is_scope_changed {
+        1.08 * (impact + exploitability)
+    } else {
+        impact + exploitability
+    };
+    Some(cvss_round_up(score.min(10.0)))
+}
+
+/// Rounds up to one decimal as defined by the CVSS v3.1 specification, avoiding
+/// floating point errors
+fn cvss_round_up(value: f64) -> f64 {
+    let value = (value * 100_000.0).round() as u64;
+    if value.is_multiple_of(10_000) {
+        value as f64 / 100_000.0
+    } else {
+        ((value / 10_000) + 1) as f64 / 10.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cvss3_base_score_reference_vectors() {
+        // Examples of the CVSS v3.1 specification
+        for (vector, score) in [
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N", 6.1),
+            ("CVSS:3.1/AV:N/AC:L/PR:L/UI:N/S:C/C:L/I:L/A:N", 6.4),
+            ("CVSS:3.1/AV:N/AC:H/PR:N/UI:R/S:U/C:L/I:N/A:N", 3.1),
+            ("CVSS:3.1/AV:N/AC:L/PR:L/UI:N/S:C/C:H/I:H/A:H", 9.9),
+            ("CVSS:3.1/AV:L/AC:L/PR:H/UI:N/S:U/C:L/I:L/A:L", 4.2),
+            ("CVSS:3.1/AV:N/AC:L/PR:L/UI:N/S:U/C:H/I:H/A:H", 8.8),
+            ("CVSS:3.1/AV:L/AC:L/PR:N/UI:R/S:U/C:H/I:H/A:H", 7.8),
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:N/A:N", 7.5),
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", 9.8),
+            ("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:C/C:N/I:H/A:N", 6.8),
+            ("CVSS:3.1/AV:P/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", 6.8),
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:L/I:N/A:N", 5.8),
+            ("CVSS:3.0/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", 9.8),
+        ] {
+            assert_eq!(cvss3_base_score(vector), Some(score), "{}", vector);
+        }
+    }
+
+    #[test]
+    fn cvss3_base_score_bounds() {
+        // Scores are capped at 10 and are 0 without any impact
+        assert_eq!(
+            cvss3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H"),
+            Some(10.0)
+        );
+        assert_eq!(
+            cvss3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:N/I:N/A:N"),
+            Some(0.0)
+        );
+        // Metrics may be given in any order and temporal metrics are ignored
+        assert_eq!(
+            cvss3_base_score("CVSS:3.1/S:U/AV:N/AC:L/PR:N/UI:N/C:H/I:H/A:H/E:P/RL:O"),
+            Some(9.8)
+        );
+    }
+
+    #[test]
+    fn cvss3_base_score_invalid_vectors() {
+        assert_eq!(
+            cvss3_base_score("AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"),
+            None
+        );
+        assert_eq!(
+            cvss3_base_score("CVSS:2.0/AV:N/AC:L/Au:N/C:P/I:P/A:P"),
+            None
+        );
+        assert_eq!(
+            cvss3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H"),
+            None
+        );
+        assert_eq!(
+            cvss3_base_score("CVSS:3.1/AV:X/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"),
+            None
+        );
+    }
+
+    #[test]
+    fn cvss_round_up_edge_cases() {
+        assert_eq!(cvss_round_up(4.0), 4.0);
+        assert_eq!(cvss_round_up(4.02), 4.1);
+        assert_eq!(cvss_round_up(4.00001), 4.1);
+        // Floating point errors below the rounding precision of the specification
+        // don't round up
+        assert_eq!(cvss_round_up(4.000000000000001), 4.0);
+        assert_eq!(cvss_round_up(4.000004), 4.0);
+        assert_eq!(cvss_round_up(0.0), 0.0);
+        assert_eq!(cvss_round_up(10.0), 10.0);
+    }
+}
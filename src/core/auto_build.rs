@@ -3,11 +3,16 @@ use std::{
     fmt::Display,
     fs::File,
     path::{Path, PathBuf},
-    sync::{mpsc::channel, Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, Mutex, OnceLock, RwLock,
+    },
     time::Instant,
 };
 
-use chrono::Duration;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::ValueEnum;
 use color_eyre::{
     eyre::{eyre, Context, Result},
     Help,
@@ -19,31 +24,45 @@ use diesel::{
 
 use ignore::WalkBuilder;
 use lazy_static::lazy_static;
+use owo_colors::OwoColorize;
 use path_absolutize::Absolutize;
-use petgraph::{algo, stable_graph::NodeIndex};
+use petgraph::{algo, stable_graph::NodeIndex, visit::EdgeRef, Direction};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{debug, error, info, trace};
 
 use crate::{
     check::{
-        ArtifactCheck, Checker, CheckerContext, LeveledArtifactCheckViolation,
-        LeveledSourceCheckViolation, PlanContextConfig, SourceCheck,
+        plan_source_check, removed_dependency_check, ArtifactCheck, Checker, CheckerContext,
+        LeveledArtifactCheckViolation, LeveledSourceCheckViolation, LicensePolicy, NewRulesPolicy,
+        PlanContextConfig, RuleProfile, SourceCheck, ViolationLevel, RULES_VERSION,
     },
     core::{
-        ArtifactCache, ArtifactCachePath, Dependency, DependencyDepth, DependencyDirection,
-        DependencyType, PackageSourceDownloadError, SourceContext,
+        license_scan_pool_configure, ArtifactCache, ArtifactCachePath, ArtifactContext,
+        ArtifactFile, Dependency, DependencyDepth, DependencyDirection, DependencyType,
+        HabitatRootPath, PackageSourceDownloadError, ShaSum, SourceContext,
+    },
+    store::{
+        self, BuildClaim, BuildClaimStatus, BuildSession, BuildSessionArtifact, BuildStepState,
+        BuildStepStatus, InvalidPackageSourceArchiveStorePath, RecordedArtifactSize, Store,
+        YankedArtifact, TIMESTAMP_FORMAT,
     },
-    store::{self, InvalidPackageSourceArchiveStorePath, Store},
 };
 
+#[cfg(not(target_os = "windows"))]
+use super::file_system_space;
 use super::{
-    habitat::{self, BuildError},
-    BuildOrder, ChangeDetectionMode, DepGraph, DepGraphData, DependencyChangeCause,
-    LazyArtifactContext, PackageBuildVersion, PackageDepGlob, PackageDepIdent, PackageIdent,
-    PackageName, PackageOrigin, PackageSha256Sum, PackageSource, PackageTarget, PlanContext,
-    PlanContextID, PlanContextPathGitSyncStatus, PlanScannerBuilder, RepoConfig, RepoContext,
-    RepoContextID,
+    disk_usage,
+    habitat::{self, BuildError, BuildExecutor, BuildSandbox, BuildSandboxCommand, PlanBuilder},
+    oci_image_export, store_archive_export, BuildHostMetadata, BuildOrder, BuilderClient,
+    ChangeDetectionMode, DepGraph, DepGraphData, DependencyChangeCause, DependencyCycle,
+    DependencyLock, Download, DownloadTracker, LazyArtifactContext, Metadata, NotifyConfig,
+    OciImageExport, PackageBuildVersion, PackageDepGlob, PackageDepIdent, PackageIdent,
+    PackageName, PackageOrigin, PackageRelease, PackageResolvedDepIdent, PackageSha256Sum,
+    PackageSource, PackageSourceURL, PackageTarget, PackageVersion, PlanContext, PlanContextID,
+    PlanContextPathGitSyncStatus, PlanScannerBuilder, RebuildPath, RepoConfig, RepoContext,
+    RepoContextID, RepoGitUpdate, SbomPackages, StoreArchiveSummary, CURRENT_BUILD_HOST,
 };
 
 lazy_static! {
@@ -54,6 +73,18 @@ lazy_static! {
     pub static ref DEFAULT_STORE_PATH: PathBuf = PathBuf::from(".hab-auto-build");
 }
 
+/// Settings given on the command line that take precedence over the configuration file
+#[derive(Debug, Default)]
+pub(crate) struct AutoBuildConfigOverrides {
+    pub store: Option<PathBuf>,
+    pub workspace: Option<String>,
+}
+
+static CONFIG_OVERRIDES: OnceLock<AutoBuildConfigOverrides> = OnceLock::new();
+
+/// Number of days the claims of a cooperative build are kept after they are released
+const BUILD_CLAIM_RETENTION_DAYS: i64 = 7;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BuildStudioConfig {
     pub standard: PackageDepIdent,
@@ -69,16 +100,181 @@ impl Default for BuildStudioConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BrokenPlanConfig {
+    /// Pattern matching the broken plans
+    pub package: PackageDepGlob,
+    /// Explanation of why the plans are broken
+    pub reason: String,
+    /// Person or team responsible for fixing the plans
+    pub owner: String,
+    /// Date after which the entry is no longer accepted
+    pub expires_on: NaiveDate,
+}
+
+/// Extra arguments to pass to the build command of a set of plans
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BuildArgsConfig {
+    /// Pattern matching the plans
+    pub package: PackageDepGlob,
+    /// Arguments added to the build command, before the plan context
+    pub args: Vec<String>,
+}
+
+impl BrokenPlanConfig {
+    pub fn is_expired(&self) -> bool {
+        Utc::now().date_naive() > self.expires_on
+    }
+}
+
+/// Determines when the studios created during a build session are removed
+/// once the session ends.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum StudioCleanupPolicy {
+    Always,
+    #[default]
+    OnSuccess,
+    Never,
+}
+
+impl StudioCleanupPolicy {
+    pub fn should_cleanup(&self, session_succeeded: bool) -> bool {
+        match self {
+            StudioCleanupPolicy::Always => true,
+            StudioCleanupPolicy::OnSuccess => session_succeeded,
+            StudioCleanupPolicy::Never => false,
+        }
+    }
+}
+
+/// Determines how successfully built artifacts are placed in the artifacts
+/// folder, if one is configured.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactsDirMode {
+    /// Copy the artifacts, keeping them in the hab-auto-build store as well
+    #[default]
+    Copy,
+    /// Move the artifacts out of the hab-auto-build store
+    Move,
+}
+
+/// Determines what happens when the disk space estimated to be needed by a build
+/// exceeds the space available.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiskSpaceCheckPolicy {
+    /// Warn about the missing space and build anyway
+    #[default]
+    Warn,
+    /// Refuse to start the build
+    Abort,
+    /// Skip the check
+    Ignore,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AutoBuildConfig {
     #[serde(default)]
     pub studios: BuildStudioConfig,
     #[serde(default)]
+    pub studio_cleanup: StudioCleanupPolicy,
+    pub artifacts_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub artifacts_dir_mode: ArtifactsDirMode,
+    /// Mirrors to try in order before downloading sources from their upstream url
+    #[serde(default)]
+    pub source_mirrors: Vec<PackageSourceURL>,
+    #[serde(default)]
+    pub broken_plans: Vec<BrokenPlanConfig>,
+    #[serde(default)]
+    pub build_args: Vec<BuildArgsConfig>,
+    /// Default number of seconds a build may run before it is killed, plans can
+    /// override it with 'timeout-secs' in their '.hab-plan-config.toml'
+    pub build_timeout_secs: Option<u64>,
+    /// Default number of times a build is retried after it fails or times out, plans
+    /// can override it with 'retries' in their '.hab-plan-config.toml'
+    #[serde(default)]
+    pub build_retries: u32,
+    /// Where bootstrap and standard plans are built, either 'local' or a worker
+    /// reached over SSH as 'ssh://[user@]host[:port][/path]'
+    #[serde(default)]
+    pub build_executor: BuildExecutor,
+    /// How builds on the current host are run as root, either 'sudo' or 'rootless'
+    #[serde(default)]
+    pub sandbox: BuildSandbox,
+    /// Command rootless builds are run with, defaults to running them in a user
+    /// namespace with 'unshare'
+    pub sandbox_command: Option<BuildSandboxCommand>,
+    /// Where standard plans are built on the current host, either 'studio' or
+    /// 'docker', plans can override it with 'builder' in their '.hab-plan-config.toml'
+    #[serde(default)]
+    pub builder: PlanBuilder,
+    /// Image standard plans are built in with the 'docker' builder, plans can
+    /// override it with 'docker-image' in their '.hab-plan-config.toml'
+    pub docker_image: Option<String>,
+    /// Number of threads used to scan source archives for licenses, defaults to half the available cores
+    pub license_scan_threads: Option<usize>,
+    /// Version of the rule set the repos were last checked against, rules added
+    /// after it are reported with 'new_rule_level' instead of their own level
+    pub rules_version: Option<u32>,
+    /// Level of the rules added after 'rules_version', defaults to warnings
+    pub new_rule_level: Option<ViolationLevel>,
+    /// Licenses the packages are allowed to have, enforced by the 'denied-license'
+    /// and 'unknown-license' rules
+    pub license_policy: Option<LicensePolicy>,
+    /// Rule profiles selectable with 'check --profile', in addition to or replacing
+    /// the built-in 'strict', 'default' and 'permissive' profiles
+    #[serde(default)]
+    pub profiles: BTreeMap<String, RuleProfile>,
+    #[serde(default)]
     pub ignore_cycles: bool,
+    #[serde(default)]
+    pub verify_artifacts: bool,
+    /// What to do when a build is estimated to need more disk space than available
+    #[serde(default)]
+    pub disk_space_check: DiskSpaceCheckPolicy,
+    /// Disk space in MB to keep free on top of the estimate, defaults to 2048
+    pub disk_space_headroom_mb: Option<u64>,
+    /// Notifications sent when a build session ends
+    pub notify: Option<NotifyConfig>,
     pub store: Option<PathBuf>,
+    /// Package target of the plans in the workspace, used by commands when no
+    /// '--target' is given, defaults to the target of the host
+    #[serde(default, with = "package_target_option")]
+    pub target: Option<PackageTarget>,
+    /// Name identifying this workspace in a store shared with other checkouts of
+    /// the plans, defaults to an identifier derived from the configuration path
+    pub workspace: Option<String>,
     pub repos: Vec<RepoConfig>,
 }
 
+/// Serializes the configured package target as a string such as 'x86_64-linux'
+mod package_target_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::core::PackageTarget;
+
+    pub fn serialize<S: Serializer>(
+        target: &Option<PackageTarget>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match target {
+            Some(target) => serializer.serialize_some(&target.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<PackageTarget>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|target| PackageTarget::parse(target).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
 impl AutoBuildConfig {
     pub fn new(config_path: impl AsRef<Path>) -> Result<AutoBuildConfig> {
         let config_path = config_path
@@ -98,7 +294,7 @@ impl AutoBuildConfig {
                 config_path.display()
             )
         })?;
-        let config = serde_json::from_reader(config_file)
+        let mut config: AutoBuildConfig = serde_json::from_reader(config_file)
             .with_context(|| {
                 eyre!(
                     "Failed to read configuration file '{}'",
@@ -112,8 +308,91 @@ impl AutoBuildConfig {
                 )
             })?;
         debug!("Configuration file '{}' loaded", config_path.display());
+        if let Some(overrides) = CONFIG_OVERRIDES.get() {
+            if let Some(store) = overrides.store.as_ref() {
+                config.store = Some(store.clone());
+            }
+            if let Some(workspace) = overrides.workspace.as_ref() {
+                config.workspace = Some(workspace.clone());
+            }
+        }
         Ok(config)
     }
+
+    /// Package target commands operate on unless another one is given, either the
+    /// configured target or the target of the host.
+    pub fn target(&self) -> PackageTarget {
+        self.target.unwrap_or_default()
+    }
+
+    /// Sets the settings that override those of every configuration file loaded
+    /// afterwards, they can only be set once.
+    pub fn overrides_set(overrides: AutoBuildConfigOverrides) {
+        if CONFIG_OVERRIDES.set(overrides).is_err() {
+            debug!("Configuration overrides were already set");
+        }
+    }
+
+    /// Identifier of the workspace in the store, either the configured name or one
+    /// derived from the name of the configuration folder and the configuration path.
+    pub fn workspace_id(&self, config_path: impl AsRef<Path>) -> Result<String> {
+        match self.workspace.as_ref() {
+            Some(workspace) if workspace.trim().is_empty() => {
+                Err(eyre!("The workspace name cannot be empty"))
+            }
+            Some(workspace) => Ok(workspace.trim().to_string()),
+            None => {
+                let config_path = config_path
+                    .as_ref()
+                    .canonicalize()
+                    .unwrap_or_else(|_| config_path.as_ref().to_path_buf());
+                let folder_name = config_path
+                    .parent()
+                    .and_then(|folder| folder.file_name())
+                    .map(|folder_name| folder_name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                Ok(format!(
+                    "{}-{}",
+                    folder_name,
+                    &blake3::hash(config_path.to_string_lossy().as_bytes()).to_hex()[..8]
+                ))
+            }
+        }
+    }
+
+    /// Absolute path to the store, relative store paths are resolved against the
+    /// folder containing the configuration file.
+    pub fn store_path(&self, config_path: impl AsRef<Path>) -> Result<PathBuf> {
+        let store_path = self.store.as_ref().unwrap_or(&DEFAULT_STORE_PATH);
+        if store_path.is_absolute() {
+            Ok(store_path.clone())
+        } else {
+            let config_dir = config_path.as_ref().parent().ok_or(eyre!(
+                "Failed to determine parent folder of hab-auto-build configuration file"
+            ))?;
+            Ok(config_dir.join(store_path).absolutize()?.to_path_buf())
+        }
+    }
+
+    /// Finds the rule profile with the given name, configured profiles take
+    /// precedence over the built-in ones.
+    pub fn rule_profile(&self, name: &str) -> Result<RuleProfile> {
+        self.profiles
+            .get(name)
+            .cloned()
+            .or_else(|| RuleProfile::built_in(name))
+            .ok_or_else(|| eyre!("Unknown rule profile '{}'", name))
+            .with_suggestion(|| {
+                let mut names = RuleProfile::BUILT_IN
+                    .iter()
+                    .map(|name| name.to_string())
+                    .chain(self.profiles.keys().cloned())
+                    .collect::<Vec<_>>();
+                names.sort();
+                names.dedup();
+                format!("Available profiles are: {}", names.join(", "))
+            })
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -140,6 +419,7 @@ pub enum AnalysisType {
     TransitiveBuildDependencies,
     ReverseDependencies,
     ReverseBuildDependencies,
+    BuildHosts,
 }
 impl Display for AnalysisType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -151,6 +431,7 @@ impl Display for AnalysisType {
             AnalysisType::TransitiveBuildDependencies => write!(f, "Transitive Build Dependencies"),
             AnalysisType::ReverseDependencies => write!(f, "Reverse Dependencies"),
             AnalysisType::ReverseBuildDependencies => write!(f, "Reverse Build Dependencies"),
+            AnalysisType::BuildHosts => write!(f, "Build Hosts"),
         }
     }
 }
@@ -160,12 +441,41 @@ pub(crate) struct AutoBuildContext {
     #[allow(dead_code)]
     path: AutoBuildContextPath,
     studios: BuildStudioConfig,
+    studio_cleanup: StudioCleanupPolicy,
+    artifacts_dir: Option<PathBuf>,
+    artifacts_dir_mode: ArtifactsDirMode,
+    source_mirrors: Vec<PackageSourceURL>,
+    /// Tracks the progress and limits the bandwidth of source downloads
+    download_tracker: DownloadTracker,
+    /// Identifier of the current session, used to record the artifacts it built
+    session_id: String,
+    /// Roots of the studios used by builds in the current session
+    session_studio_roots: Mutex<BTreeSet<PathBuf>>,
+    broken_plans: Vec<BrokenPlanConfig>,
+    build_args: Vec<BuildArgsConfig>,
+    build_timeout_secs: Option<u64>,
+    build_retries: u32,
+    build_executor: BuildExecutor,
+    build_sandbox: BuildSandbox,
+    build_sandbox_command: BuildSandboxCommand,
+    builder: PlanBuilder,
+    docker_image: Option<String>,
+    /// License scans of source archives done in the current session
+    license_scans: Mutex<Vec<LicenseScan>>,
     store: Store,
     repos: HashMap<RepoContextID, RepoContext>,
     dep_graph: DepGraph,
     artifact_cache: Arc<RwLock<ArtifactCache>>,
 }
 
+/// Outcome of scanning a source archive for license files
+#[derive(Debug, Clone)]
+pub(crate) struct LicenseScan {
+    pub plan_ctx_id: PlanContextID,
+    pub license_count: usize,
+    pub duration: std::time::Duration,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct PackageDiff {
     pub source: BTreeSet<PackageBuildVersion>,
@@ -175,9 +485,23 @@ pub(crate) struct PackageDiff {
 pub(crate) struct DependencyChange<'a> {
     pub plan_ctx: &'a PlanContext,
     pub causes: Vec<DependencyChangeCause>,
+    pub skipped: Option<BrokenPlanSkip<'a>>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Reason for skipping the build of a changed plan, the broken plan
+/// is either the skipped plan itself or one of its dependencies.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BrokenPlanSkip<'a> {
+    pub broken_plan: &'a PlanContextID,
+    pub config: &'a BrokenPlanConfig,
+}
+
+pub(crate) struct SkipStep<'a> {
+    pub plan_ctx: &'a PlanContext,
+    pub skip: BrokenPlanSkip<'a>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum BuildStepStudio {
     Native,
     Bootstrap,
@@ -202,7 +526,6 @@ pub(crate) struct CheckStep<'a> {
 
 #[derive(Debug)]
 pub(crate) struct BuildStep<'a> {
-    #[allow(dead_code)]
     pub index: NodeIndex,
     pub repo_ctx: &'a RepoContext,
     pub plan_ctx: &'a PlanContext,
@@ -215,12 +538,44 @@ pub(crate) struct BuildStep<'a> {
     pub remote_deps: Vec<&'a Dependency>,
     pub causes: Vec<DependencyChangeCause>,
     pub build_duration: Option<Duration>,
+    /// Extra arguments passed to the build command
+    pub build_args: Vec<String>,
+    /// Number of seconds the build may run before it is killed
+    pub timeout_secs: Option<u64>,
+    /// Number of times the build is retried after it fails or times out
+    pub retries: u32,
+    /// Image the plan is built in, if it is a standard plan built with Docker
+    pub docker_image: Option<String>,
+    /// Command the privileged commands of the build are run with
+    pub sandbox_command: &'a BuildSandboxCommand,
+    /// Stops the build of the step while it is running
+    pub cancellation: BuildCancellation,
+}
+
+/// Flag shared with a running build to stop it, a cancelled build is terminated
+/// and fails with a cancellation error.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BuildCancellation(Arc<AtomicBool>);
+
+impl BuildCancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct BuildStepResult {
     pub artifact_ident: PackageIdent,
     pub artifact_violations: Vec<LeveledArtifactCheckViolation>,
+    pub artifact_path: PathBuf,
     pub build_log: PathBuf,
 }
 
@@ -232,9 +587,83 @@ pub(crate) enum BuildStepError {
     Unexpected(#[from] color_eyre::eyre::Error),
 }
 
+/// Resolves the studio package a plan is built with. The studio packages of the
+/// plan's repo override the global ones, and the plan's configuration can pin the
+/// version of the studio package it would otherwise be built with.
+fn plan_studio_package<'a>(
+    plan_ctx: &'a PlanContext,
+    repo_ctx: &'a RepoContext,
+    studio: BuildStepStudio,
+    studio_package: Option<&'a PackageDepIdent>,
+) -> Result<Option<&'a PackageDepIdent>> {
+    let repo_studio_package = match studio {
+        BuildStepStudio::Standard => repo_ctx.studios.standard.as_ref().or(studio_package),
+        BuildStepStudio::Bootstrap => repo_ctx.studios.bootstrap.as_ref().or(studio_package),
+        BuildStepStudio::Native => None,
+    };
+    let Some(plan_studio_package) = plan_ctx
+        .plan_config
+        .as_ref()
+        .and_then(|config| config.studio.as_ref())
+    else {
+        return Ok(repo_studio_package);
+    };
+    match repo_studio_package {
+        Some(repo_studio_package)
+            if repo_studio_package.origin == plan_studio_package.origin
+                && repo_studio_package.name == plan_studio_package.name =>
+        {
+            Ok(Some(plan_studio_package))
+        }
+        Some(repo_studio_package) => Err(eyre!(
+            "The plan '{}' is built in the {} studio with {}, it cannot be built with {}",
+            plan_ctx.id,
+            studio,
+            repo_studio_package,
+            plan_studio_package
+        ))
+        .with_suggestion(|| {
+            format!(
+                "Set 'studio' in {} to a version of {}/{}",
+                plan_ctx.plan_path.plan_config_path().display(),
+                repo_studio_package.origin,
+                repo_studio_package.name
+            )
+        }),
+        None => Err(eyre!(
+            "The native plan '{}' is not built in a studio, it cannot be built with {}",
+            plan_ctx.id,
+            plan_studio_package
+        ))
+        .with_suggestion(|| {
+            format!(
+                "Remove 'studio' from {}",
+                plan_ctx.plan_path.plan_config_path().display()
+            )
+        }),
+    }
+}
+
 pub(crate) struct BuildPlan<'a> {
     pub check_steps: Vec<CheckStep<'a>>,
     pub build_steps: Vec<BuildStep<'a>>,
+    pub skip_steps: Vec<SkipStep<'a>>,
+}
+
+/// Narrows down the plans built by a build plan
+#[derive(Debug, Default)]
+pub(crate) struct BuildPlanScope {
+    /// Only build the selected packages, not the changed dependencies built along with them
+    pub skip_deps: bool,
+    /// Only build the plans that changed themselves, not the plans that are rebuilt
+    /// because one of their dependencies is rebuilt
+    pub only_changed: bool,
+    /// Packages at which the rebuild stops, they are built but the plans depending
+    /// on them are not
+    pub through: Vec<NodeIndex>,
+    /// Plans left to build by a resumed build session, they are built whether or
+    /// not they are still detected as changed
+    pub resume: Option<Vec<NodeIndex>>,
 }
 
 pub(crate) enum AddStatus {
@@ -248,6 +677,26 @@ pub(crate) enum AddError {
     UnexpectedError(#[from] color_eyre::eyre::Error),
 }
 
+/// Modification time recorded in the store for a file of a plan context by the
+/// 'add' and 'remove' commands. It is used for change detection in place of the
+/// real modification time of the file as long as the file is not modified again.
+#[derive(Debug, Clone)]
+pub(crate) struct PlanFileTimestamp {
+    pub path: PathBuf,
+    /// Real modification time of the file when the timestamp was recorded
+    pub recorded_modified_at: DateTime<Utc>,
+    pub alternate_modified_at: DateTime<Utc>,
+    /// Current real modification time of the file, if it still exists
+    pub real_modified_at: Option<DateTime<Utc>>,
+}
+
+impl PlanFileTimestamp {
+    /// Whether the alternate modification time is currently used for change detection
+    pub fn is_active(&self) -> bool {
+        self.real_modified_at == Some(self.recorded_modified_at)
+    }
+}
+
 pub(crate) enum RemoveStatus {
     Removed(PlanContextID),
     AlreadyRemoved(PlanContextID),
@@ -262,6 +711,110 @@ pub(crate) enum RemoveError {
     UnexpectedError(#[from] color_eyre::eyre::Error),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StoreCleanCategory {
+    Sources,
+    InvalidSources,
+    GitRepos,
+    BuildSuccessLogs,
+    BuildFailureLogs,
+    Temp,
+    Studio,
+}
+
+impl Display for StoreCleanCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreCleanCategory::Sources => write!(f, "source"),
+            StoreCleanCategory::InvalidSources => write!(f, "invalid source"),
+            StoreCleanCategory::GitRepos => write!(f, "git repository"),
+            StoreCleanCategory::BuildSuccessLogs => write!(f, "build success log"),
+            StoreCleanCategory::BuildFailureLogs => write!(f, "build failure log"),
+            StoreCleanCategory::Temp => write!(f, "temporary file"),
+            StoreCleanCategory::Studio => write!(f, "studio"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StoreCleanOptions {
+    pub sources: bool,
+    /// Remove the bare clones of the repositories of git sources
+    pub git_repos: bool,
+    pub logs: bool,
+    pub tmp: bool,
+    /// Remove the build studios left over from previous sessions, these live
+    /// outside the store in the habitat studios folder
+    pub studios: bool,
+    /// Only remove entries that were last modified before this duration
+    pub older_than: Option<Duration>,
+    /// Only remove entries that are not referenced by any plan or artifact
+    pub unreferenced: bool,
+}
+
+pub(crate) struct StoreCleanStatus {
+    pub category: StoreCleanCategory,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum StoreCleanError {
+    #[error("Encountered an unexpected error while trying to clean the hab-auto-build store")]
+    UnexpectedError(#[from] color_eyre::eyre::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BuildLogKind {
+    /// Log of a build that is still in progress
+    Running,
+    Success,
+    Failure,
+}
+
+impl Display for BuildLogKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildLogKind::Running => write!(f, "running build"),
+            BuildLogKind::Success => write!(f, "build success"),
+            BuildLogKind::Failure => write!(f, "build failure"),
+        }
+    }
+}
+
+pub(crate) struct BuildLog {
+    pub kind: BuildLogKind,
+    pub path: PathBuf,
+    pub modified_at: DateTime<Utc>,
+}
+
+pub(crate) enum SourceVerifyStatus {
+    /// The archive matches the shasum it is stored under
+    Valid,
+    /// The archive does not match the shasum it is stored under
+    Mismatch(PackageSha256Sum),
+    /// The source folder does not contain an archive
+    Missing,
+}
+
+pub(crate) struct SourceVerifyResult {
+    pub shasum: PackageSha256Sum,
+    pub path: PathBuf,
+    /// Plans whose 'pkg_shasum' refers to the source archive
+    pub plans: Vec<PlanContextID>,
+    pub status: SourceVerifyStatus,
+    pub quarantined: bool,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum SourceVerifyError {
+    #[error("Encountered an unexpected error while trying to verify the downloaded sources")]
+    UnexpectedError(#[from] color_eyre::eyre::Error),
+    #[error("Encountered an unexpected IO error while trying to verify the downloaded sources")]
+    UnexpectedIOError(#[from] std::io::Error),
+}
+
 pub(crate) struct PlanContextGitSyncStatus {
     pub id: PlanContextID,
     pub file_statuses: Vec<PlanContextPathGitSyncStatus>,
@@ -288,6 +841,7 @@ pub(crate) enum DownloadStatus {
         SourceContext,
         PlanContext,
         PackageSource,
+        PackageSourceURL,
         Duration,
         Vec<LeveledSourceCheckViolation>,
     ),
@@ -295,6 +849,7 @@ pub(crate) enum DownloadStatus {
         SourceContext,
         PlanContext,
         PackageSource,
+        PackageSourceURL,
         Vec<LeveledSourceCheckViolation>,
     ),
     MissingSource(PlanContext),
@@ -332,6 +887,195 @@ pub(crate) struct DependencyAnalysis<'a> {
     pub build_tdeps: Option<Vec<&'a Dependency>>,
     pub rdeps: Option<Vec<&'a Dependency>>,
     pub build_rdeps: Option<Vec<&'a Dependency>>,
+    pub build_hosts: Option<Vec<ArtifactBuildHost>>,
+}
+
+/// Host on which an artifact was built, artifacts that were not built
+/// by hab-auto-build do not have any recorded host.
+#[derive(Debug, Serialize)]
+pub(crate) struct ArtifactBuildHost {
+    pub artifact: PackageIdent,
+    pub host: Option<BuildHostMetadata>,
+}
+
+/// Artifact or source required by a build plan that is not available locally,
+/// which prevents the plan from being built offline.
+pub(crate) enum OfflineMissingItem {
+    /// Artifact of a dependency that is not in the artifact cache and is not built by the plan
+    Artifact {
+        plan: PlanContextID,
+        dependency: String,
+    },
+    /// Source archive of a plan that has not been downloaded to the store
+    Source {
+        plan: PlanContextID,
+        source: PackageSource,
+    },
+}
+
+/// Group of plans sharing a source in a way that usually indicates a copy-paste
+/// mistake in one of the plans.
+pub(crate) enum DuplicateSource<'a> {
+    /// Plans of different packages using the same source archive
+    SameArchive {
+        shasum: &'a PackageSha256Sum,
+        plans: Vec<&'a PlanContext>,
+    },
+    /// Plans using the same source url but pinning different shasums
+    ConflictingShasums {
+        url: &'a PackageSourceURL,
+        plans: Vec<&'a PlanContext>,
+    },
+}
+
+impl DuplicateSource<'_> {
+    pub fn plans(&self) -> &[&PlanContext] {
+        match self {
+            DuplicateSource::SameArchive { plans, .. }
+            | DuplicateSource::ConflictingShasums { plans, .. } => plans,
+        }
+    }
+}
+
+/// Bind of a service to the services exporting the configuration values it needs
+pub(crate) struct ServiceBind<'a> {
+    pub plan: &'a PlanContext,
+    pub name: &'a str,
+    /// Exports needed from the bound service
+    pub exports: &'a [String],
+    /// Whether the bind was declared with `pkg_binds_optional`
+    pub optional: bool,
+    /// Plans of other packages exporting everything the bind needs
+    pub providers: Vec<&'a PlanContext>,
+}
+
+/// Exports of a service that no bind of another service needs
+pub(crate) struct UnconsumedExports<'a> {
+    pub plan: &'a PlanContext,
+    pub exports: Vec<&'a str>,
+}
+
+/// Binds between the services of the plans of a target
+pub(crate) struct ServiceComposition<'a> {
+    pub binds: Vec<ServiceBind<'a>>,
+    pub unconsumed_exports: Vec<UnconsumedExports<'a>>,
+}
+
+/// Package of the plans in a dependency graph, regardless of their version
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct GraphPackage {
+    pub target: PackageTarget,
+    pub origin: PackageOrigin,
+    pub name: PackageName,
+}
+
+impl Display for GraphPackage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} ({})", self.origin, self.name, self.target)
+    }
+}
+
+/// Dependency of the plans of a package on another package in a dependency graph
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct GraphEdge {
+    pub package: GraphPackage,
+    pub dep_type: DependencyType,
+    pub dep: PackageDepIdent,
+}
+
+/// Package whose plans have different versions in two dependency graphs
+pub(crate) struct GraphVersionChange {
+    pub package: GraphPackage,
+    pub from: BTreeSet<PackageBuildVersion>,
+    pub to: BTreeSet<PackageBuildVersion>,
+}
+
+/// Differences between the dependency graphs of the plans of two workspaces
+#[derive(Default)]
+pub(crate) struct GraphDiff {
+    pub added_plans: Vec<GraphPackage>,
+    pub removed_plans: Vec<GraphPackage>,
+    pub version_changes: Vec<GraphVersionChange>,
+    pub added_edges: Vec<GraphEdge>,
+    pub removed_edges: Vec<GraphEdge>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_plans.is_empty()
+            && self.removed_plans.is_empty()
+            && self.version_changes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+/// Disk space needed by the builds of a build plan on a file system
+pub(crate) struct DiskSpaceRequirement {
+    /// Device of the file system
+    pub device: u64,
+    /// Folders written to by the builds on the file system
+    pub paths: Vec<PathBuf>,
+    /// Estimated space needed by the builds in bytes
+    pub required: u64,
+    /// Space available on the file system in bytes
+    pub available: u64,
+}
+
+/// Disk space needed by the builds of a build plan
+pub(crate) struct DiskSpaceEstimate {
+    pub requirements: Vec<DiskSpaceRequirement>,
+    /// Number of builds of plans that were never built before, they are left out
+    /// of the estimate
+    pub unestimated_steps: usize,
+}
+
+/// Package of a plan, as the disk space used by its builds is recorded in the store
+fn disk_usage_package(plan_ctx: &PlanContext) -> String {
+    let id = plan_ctx.id.as_ref();
+    format!("{}/{}/{}", id.origin, id.name, id.target)
+}
+
+/// Installed size of an artifact and of its transitive runtime dependencies
+pub(crate) struct ArtifactSize {
+    pub artifact: PackageIdent,
+    /// Installed size in bytes of the artifact
+    pub installed_size: u64,
+    /// Installed size in bytes of the artifact and its transitive runtime dependencies
+    pub closure_size: u64,
+    /// Installed sizes of the transitive runtime dependencies, largest first
+    pub tdeps: Vec<(PackageIdent, u64)>,
+    /// Transitive runtime dependencies whose artifacts are not in the artifact
+    /// cache, they are not counted in the closure size
+    pub missing_tdeps: Vec<PackageIdent>,
+    /// Sizes of the previous release of the package
+    pub previous: Option<RecordedArtifactSize>,
+}
+
+/// Overview of the repos and plans of a workspace
+pub(crate) struct WorkspaceSummary<'a> {
+    /// Repos with the number of plans they contain
+    pub repos: Vec<(&'a RepoContext, usize)>,
+    pub plans_by_studio: Vec<(BuildStepStudio, usize)>,
+    pub plans_by_origin: BTreeMap<&'a PackageOrigin, usize>,
+    /// Plans without any artifact in the artifact cache
+    pub plans_without_artifact: Vec<&'a PlanContext>,
+    /// Plans whose source archive has not been downloaded to the store
+    pub plans_missing_source: Vec<&'a PlanContext>,
+    /// Plans with a '.hab-plan-config.toml' file
+    pub plans_with_config: Vec<&'a PlanContext>,
+}
+
+impl WorkspaceSummary<'_> {
+    pub fn plan_count(&self) -> usize {
+        self.repos.iter().map(|(_, plan_count)| plan_count).sum()
+    }
+}
+
+pub(crate) struct BuildHostMismatch {
+    pub artifact: PackageIdent,
+    pub host: BuildHostMetadata,
+    pub differences: Vec<String>,
 }
 
 pub(crate) struct RepoChanges<'a> {
@@ -339,14 +1083,57 @@ pub(crate) struct RepoChanges<'a> {
     pub changes: Vec<DependencyChange<'a>>,
 }
 
+/// Plans of a repo whose files differ from the branch of the git remote the repo
+/// is tracking
+pub(crate) struct RepoUpstreamChanges<'a> {
+    pub repo: &'a RepoContext,
+    /// Plans with the files that differ, relative to the repo
+    pub plans: Vec<(&'a PlanContext, Vec<PathBuf>)>,
+    /// Files that differ but do not belong to any of the plans, relative to the repo
+    pub other_files: Vec<PathBuf>,
+}
+
 impl AutoBuildContext {
     pub fn new(
         config: &AutoBuildConfig,
         config_path: impl AsRef<Path>,
         change_detection_mode: ChangeDetectionMode,
+    ) -> Result<AutoBuildContext> {
+        AutoBuildContext::init(config, config_path, change_detection_mode, true)
+    }
+
+    /// Initializes a context without scanning the artifact cache. Every plan is
+    /// considered to have never been built, so the context is only suitable for
+    /// operations on the dependency graph of the plans.
+    pub fn new_without_artifact_cache(
+        config: &AutoBuildConfig,
+        config_path: impl AsRef<Path>,
+    ) -> Result<AutoBuildContext> {
+        AutoBuildContext::init(config, config_path, ChangeDetectionMode::Disk, false)
+    }
+
+    fn init(
+        config: &AutoBuildConfig,
+        config_path: impl AsRef<Path>,
+        change_detection_mode: ChangeDetectionMode,
+        scan_artifact_cache: bool,
     ) -> Result<AutoBuildContext> {
         let start = Instant::now();
 
+        if let Some(license_scan_threads) = config.license_scan_threads {
+            license_scan_pool_configure(license_scan_threads)
+                .with_context(|| eyre!("Invalid license scan thread count configured"))?;
+        }
+
+        for build_args in config.build_args.iter() {
+            habitat::build_args_validate(&build_args.args).with_context(|| {
+                eyre!(
+                    "Invalid build arguments configured for packages matching '{}'",
+                    build_args.package
+                )
+            })?;
+        }
+
         let mut repos = HashMap::new();
         let auto_build_ctx_path = AutoBuildContextPath::from(
             config_path
@@ -358,30 +1145,79 @@ impl AutoBuildContext {
                 .to_path_buf(),
         );
 
-        for repo_config in config.repos.iter() {
-            let repo_ctx = RepoContext::new(repo_config, &auto_build_ctx_path)?;
-            repos.insert(repo_ctx.id.clone(), repo_ctx);
-        }
-
-        let store_path = config.store.as_ref().unwrap_or(&DEFAULT_STORE_PATH);
-        let store_path = if store_path.is_absolute() {
-            store_path.clone()
-        } else {
-            auto_build_ctx_path
-                .as_ref()
-                .join(store_path)
-                .absolutize()?
-                .to_path_buf()
-        };
-        let store = Store::new(&store_path).with_context(|| {
+        let store_path = config.store_path(config_path.as_ref())?;
+        let workspace = config.workspace_id(config_path.as_ref())?;
+        let store = Store::new(&store_path, &workspace).with_context(|| {
             format!(
                 "Failed to initialize hab-auto-build store at {}",
                 store_path.display()
             )
         })?;
 
+        for repo_config in config.repos.iter() {
+            if let Some(RepoGitUpdate::Cloned) =
+                repo_config.clone_if_missing(&auto_build_ctx_path, &store)?
+            {
+                info!(target: "user-log",
+                    "Cloned repo '{}' into the hab-auto-build store",
+                    repo_config.id
+                );
+            }
+            let new_rules_policy = match repo_config.rules_version.or(config.rules_version) {
+                Some(rules_version) if rules_version > RULES_VERSION => {
+                    return Err(eyre!(
+                        "The repo '{}' is pinned to version {} of the rule set, but the latest version is {}",
+                        repo_config.id,
+                        rules_version,
+                        RULES_VERSION
+                    ))
+                    .with_suggestion(|| "Upgrade hab-auto-build or lower 'rules_version' in your configuration");
+                }
+                Some(rules_version) => Some(NewRulesPolicy {
+                    rules_version,
+                    level: repo_config
+                        .new_rule_level
+                        .or(config.new_rule_level)
+                        .unwrap_or(ViolationLevel::Warn),
+                }),
+                None => None,
+            };
+            let repo_ctx = RepoContext::new(
+                repo_config,
+                &auto_build_ctx_path,
+                &store,
+                new_rules_policy,
+                repo_config
+                    .license_policy
+                    .clone()
+                    .or(config.license_policy.clone()),
+            )?;
+            repos.insert(repo_ctx.id.clone(), repo_ctx);
+        }
+
+        let artifacts_dir = config
+            .artifacts_dir
+            .as_ref()
+            .map(|artifacts_dir| {
+                auto_build_ctx_path
+                    .as_ref()
+                    .join(artifacts_dir)
+                    .absolutize()
+                    .map(|path| path.to_path_buf())
+            })
+            .transpose()?;
+
         // Scan artifact cache
-        let artifact_cache = ArtifactCache::new(ArtifactCachePath::default(), &store)?;
+        let artifact_cache = if scan_artifact_cache {
+            ArtifactCache::new(
+                ArtifactCachePath::default(),
+                &store,
+                config.verify_artifacts,
+            )?
+        } else {
+            debug!("Skipping scan of artifact cache");
+            ArtifactCache::empty(ArtifactCachePath::default(), &store)?
+        };
 
         let mut dir_walk_builder: Option<WalkBuilder> = None;
         for repo_ctx in repos.values() {
@@ -406,12 +1242,16 @@ impl AutoBuildContext {
         };
         let mut plans: HashMap<PlanContextID, PlanContext> = HashMap::new();
         let modification_index = store.get_connection()?.transaction(|connection| {
-            store::files_alternate_modified_at_get_full_index(connection)
+            store::files_alternate_modified_at_get_full_index(connection, store.workspace())
         })?;
+        let plan_scan_index = store
+            .get_connection()?
+            .transaction(|connection| store::plan_scans_get_full_index(connection))?;
         let (sender, receiver) = channel();
         let mut dir_visitor_builder = PlanScannerBuilder::new(
             &repos,
             &modification_index,
+            &plan_scan_index,
             &artifact_cache,
             change_detection_mode,
             sender,
@@ -438,6 +1278,13 @@ impl AutoBuildContext {
                 .expect("Failed to join plan scanning directory walker thread");
             Ok(())
         })?;
+        let scanned_plan_count = store
+            .get_connection()?
+            .transaction(|connection| store::plan_scans_put(connection, &plan_scan_index))?;
+        debug!(
+            "Read {} plans from disk, the others were loaded from the plan scan cache",
+            scanned_plan_count
+        );
 
         info!(
             "Detected {} plans across {} repos in {}s",
@@ -451,6 +1298,26 @@ impl AutoBuildContext {
         Ok(AutoBuildContext {
             path: auto_build_ctx_path,
             studios: config.studios.clone(),
+            studio_cleanup: config.studio_cleanup,
+            artifacts_dir,
+            artifacts_dir_mode: config.artifacts_dir_mode,
+            source_mirrors: config.source_mirrors.clone(),
+            download_tracker: DownloadTracker::default(),
+            session_id: Utc::now().format(TIMESTAMP_FORMAT).to_string(),
+            session_studio_roots: Mutex::new(BTreeSet::new()),
+            broken_plans: config.broken_plans.clone(),
+            build_args: config.build_args.clone(),
+            build_timeout_secs: config.build_timeout_secs,
+            build_retries: config.build_retries,
+            build_executor: config.build_executor.clone(),
+            build_sandbox: config.sandbox,
+            build_sandbox_command: BuildSandboxCommand::new(
+                config.sandbox,
+                config.sandbox_command.as_ref(),
+            ),
+            builder: config.builder,
+            docker_image: config.docker_image.clone(),
+            license_scans: Mutex::new(Vec::new()),
             store,
             repos,
             dep_graph,
@@ -462,27 +1329,413 @@ impl AutoBuildContext {
         self.dep_graph.build_graph.node_count() == 0
     }
 
-    pub fn dep_graph_data(&self) -> DepGraphData {
-        DepGraphData::from(&self.dep_graph)
+    /// Counts the plans detected in each repo for the given target
+    pub fn repo_plan_counts(&self, target: PackageTarget) -> BTreeMap<&RepoContextID, usize> {
+        let mut plan_counts = self
+            .repos
+            .keys()
+            .map(|repo_id| (repo_id, 0))
+            .collect::<BTreeMap<_, _>>();
+        for node_index in self.dep_graph.build_graph.node_indices() {
+            if let Some(plan_ctx) = self.dep_graph.build_graph[node_index].plan_ctx() {
+                if plan_ctx.id.as_ref().target == target {
+                    if let Some(count) = plan_counts.get_mut(&plan_ctx.repo_id) {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+        plan_counts
     }
 
-    pub fn glob_deps(
-        &self,
-        globs: &[PackageDepGlob],
-        target: PackageTarget,
-    ) -> Result<Vec<NodeIndex>> {
-        let mut results = Vec::new();
-        for glob in globs {
-            let glob = glob.matcher();
-            results.extend(self.dep_graph.glob_deps(&glob, target));
-        }
-        Ok(results)
+    /// Lists the repos of the workspace, sorted by id
+    pub fn repos(&self) -> Vec<&RepoContext> {
+        let mut repos = self.repos.values().collect::<Vec<_>>();
+        repos.sort_by(|a, b| a.id.cmp(&b.id));
+        repos
     }
 
-    pub fn dep(&self, dep_node_index: NodeIndex) -> &Dependency {
+    /// Updates the clones of the repos in the hab-auto-build store to the latest
+    /// commit of the branch they are tracking, repos in a local folder are left
+    /// untouched.
+    pub fn repos_git_update(&self) -> Vec<(&RepoContext, Result<RepoGitUpdate>)> {
+        self.repos()
+            .into_iter()
+            .filter(|repo_ctx| repo_ctx.is_store_clone(&self.store))
+            .map(|repo_ctx| (repo_ctx, repo_ctx.git_update()))
+            .collect()
+    }
+
+    /// Finds the given plans of the repos with a git remote whose files differ from
+    /// the branch the repo is tracking
+    pub fn upstream_changes(
+        &self,
+        package_indices: &[NodeIndex],
+    ) -> Result<Vec<RepoUpstreamChanges<'_>>> {
+        let mut repo_changes = Vec::new();
+        for repo_ctx in self
+            .repos()
+            .into_iter()
+            .filter(|repo_ctx| repo_ctx.git.is_some())
+        {
+            let repo_plans = self
+                .dep_graph
+                .build_graph
+                .node_indices()
+                .filter(|node_index| package_indices.contains(node_index))
+                .filter_map(|node_index| self.dep_graph.build_graph[node_index].plan_ctx())
+                .filter(|plan_ctx| plan_ctx.repo_id == repo_ctx.id)
+                .collect::<Vec<_>>();
+            let mut plans: Vec<(&PlanContext, Vec<PathBuf>)> = Vec::new();
+            let mut other_files = Vec::new();
+            for file in repo_ctx.git_upstream_changed_files()? {
+                let path = repo_ctx.path.as_ref().join(&file);
+                let plan_ctx = repo_plans
+                    .iter()
+                    .filter(|plan_ctx| path.starts_with(plan_ctx.context_path.as_ref()))
+                    // Plans can be nested, the file belongs to the innermost one
+                    .max_by_key(|plan_ctx| plan_ctx.context_path.as_ref().components().count());
+                match plan_ctx {
+                    Some(plan_ctx) => match plans
+                        .iter_mut()
+                        .find(|(changed_plan_ctx, _)| changed_plan_ctx.id == plan_ctx.id)
+                    {
+                        Some((_, files)) => files.push(file),
+                        None => plans.push((plan_ctx, vec![file])),
+                    },
+                    None => other_files.push(file),
+                }
+            }
+            plans.sort_by(|(a, _), (b, _)| a.id.cmp(&b.id));
+            repo_changes.push(RepoUpstreamChanges {
+                repo: repo_ctx,
+                plans,
+                other_files,
+            });
+        }
+        Ok(repo_changes)
+    }
+
+    /// Finds the origins of the plans with the given name across all repos
+    pub fn plan_origins(&self, name: &PackageName) -> BTreeSet<&PackageOrigin> {
+        self.dep_graph
+            .build_graph
+            .node_indices()
+            .filter_map(|node_index| self.dep_graph.build_graph[node_index].plan_ctx())
+            .filter(|plan_ctx| &plan_ctx.id.as_ref().name == name)
+            .map(|plan_ctx| &plan_ctx.id.as_ref().origin)
+            .collect()
+    }
+
+    pub fn dep_graph_data(&self) -> DepGraphData {
+        DepGraphData::from(&self.dep_graph)
+    }
+
+    /// Node ids of the local plans in the data returned by `dep_graph_data`, indexed by plan id
+    pub fn dep_graph_plan_node_ids(&self) -> HashMap<String, u32> {
+        self.dep_graph
+            .build_graph
+            .node_indices()
+            .filter_map(|node_index| {
+                self.dep_graph.build_graph[node_index]
+                    .plan_ctx()
+                    .map(|plan_ctx| (plan_ctx.id.to_string(), node_index.index() as u32))
+            })
+            .collect()
+    }
+
+    pub fn glob_deps(
+        &self,
+        globs: &[PackageDepGlob],
+        target: PackageTarget,
+    ) -> Result<Vec<NodeIndex>> {
+        let mut results = Vec::new();
+        for glob in globs {
+            let glob = glob.matcher();
+            results.extend(self.dep_graph.glob_deps(&glob, target));
+        }
+        Ok(results)
+    }
+
+    pub fn dep(&self, dep_node_index: NodeIndex) -> &Dependency {
         self.dep_graph.dep(dep_node_index)
     }
 
+    /// Groups the plans for the given packages and their transitive dependencies
+    /// into layers, see [`DepGraph::layers`].
+    pub fn plan_layers(&self, dep_node_indices: &[NodeIndex]) -> Vec<Vec<&PlanContext>> {
+        self.dep_graph
+            .layers(dep_node_indices)
+            .into_iter()
+            .map(|layer| {
+                let mut plan_ctxs = layer
+                    .into_iter()
+                    .filter_map(|node_index| self.dep_graph.dep(node_index).plan_ctx())
+                    .collect::<Vec<_>>();
+                plan_ctxs.sort_by(|a, b| a.id.cmp(&b.id));
+                plan_ctxs
+            })
+            .collect()
+    }
+
+    /// Finds the given packages and their transitive dependencies of the given types,
+    /// see [`DepGraph::subgraph`].
+    pub fn dep_subgraph(
+        &self,
+        dep_node_indices: &[NodeIndex],
+        dep_types: HashSet<DependencyType>,
+    ) -> (Vec<NodeIndex>, Vec<(NodeIndex, NodeIndex, DependencyType)>) {
+        self.dep_graph.subgraph(dep_node_indices, dep_types)
+    }
+
+    /// Dependency cycles between plans, including the ones that are ignored
+    pub fn dep_cycles(&self) -> &[DependencyCycle] {
+        &self.dep_graph.cycles
+    }
+
+    /// Finds the plans for a target that share a source suspiciously, either
+    /// different packages built from the same archive or plans pinning different
+    /// shasums for the same source url.
+    pub fn duplicate_sources(&self, target: PackageTarget) -> Vec<DuplicateSource<'_>> {
+        let mut plans_by_shasum: BTreeMap<&str, Vec<&PlanContext>> = BTreeMap::new();
+        let mut plans_by_url: BTreeMap<String, Vec<&PlanContext>> = BTreeMap::new();
+        for node_index in self.dep_graph.build_graph.node_indices() {
+            let Some(plan_ctx) = self.dep_graph.build_graph[node_index].plan_ctx() else {
+                continue;
+            };
+            if plan_ctx.id.as_ref().target != target {
+                continue;
+            }
+            if let Some(source) = plan_ctx.source.as_ref() {
+                plans_by_shasum
+                    .entry(source.shasum.as_ref().as_ref())
+                    .or_default()
+                    .push(plan_ctx);
+                plans_by_url
+                    .entry(source.url.to_string())
+                    .or_default()
+                    .push(plan_ctx);
+            }
+        }
+        let mut duplicate_sources = Vec::new();
+        for mut plans in plans_by_shasum.into_values() {
+            let package_names = plans
+                .iter()
+                .map(|plan_ctx| (&plan_ctx.id.as_ref().origin, &plan_ctx.id.as_ref().name))
+                .collect::<HashSet<_>>();
+            if package_names.len() > 1 {
+                plans.sort_by_key(|plan_ctx| plan_ctx.id.to_string());
+                duplicate_sources.push(DuplicateSource::SameArchive {
+                    shasum: &plans[0].source.as_ref().unwrap().shasum,
+                    plans,
+                });
+            }
+        }
+        for mut plans in plans_by_url.into_values() {
+            let shasums = plans
+                .iter()
+                .map(|plan_ctx| &plan_ctx.source.as_ref().unwrap().shasum)
+                .collect::<HashSet<_>>();
+            if shasums.len() > 1 {
+                plans.sort_by_key(|plan_ctx| plan_ctx.id.to_string());
+                duplicate_sources.push(DuplicateSource::ConflictingShasums {
+                    url: &plans[0].source.as_ref().unwrap().url,
+                    plans,
+                });
+            }
+        }
+        duplicate_sources
+    }
+
+    /// Builds the service composition of the plans for a target. A bind can be
+    /// satisfied by any plan of another package exporting all the values it needs,
+    /// as the Supervisor binds services by their exports and not by their package.
+    pub fn service_composition(&self, target: PackageTarget) -> ServiceComposition<'_> {
+        let mut plans = self
+            .dep_graph
+            .build_graph
+            .node_indices()
+            .filter_map(|node_index| self.dep_graph.build_graph[node_index].plan_ctx())
+            .filter(|plan_ctx| plan_ctx.id.as_ref().target == target)
+            .collect::<Vec<_>>();
+        plans.sort_by_key(|plan_ctx| plan_ctx.id.to_string());
+        let is_same_package = |a: &PlanContext, b: &PlanContext| {
+            a.id.as_ref().origin == b.id.as_ref().origin && a.id.as_ref().name == b.id.as_ref().name
+        };
+
+        let mut binds = Vec::new();
+        for plan_ctx in plans.iter() {
+            for (optional, plan_binds) in
+                [(false, &plan_ctx.binds), (true, &plan_ctx.binds_optional)]
+            {
+                for (name, exports) in plan_binds.iter() {
+                    let providers = plans
+                        .iter()
+                        .filter(|provider| !is_same_package(plan_ctx, provider))
+                        .filter(|provider| {
+                            exports
+                                .iter()
+                                .all(|export| provider.exports.contains_key(export))
+                        })
+                        .copied()
+                        .collect();
+                    binds.push(ServiceBind {
+                        plan: plan_ctx,
+                        name,
+                        exports,
+                        optional,
+                        providers,
+                    });
+                }
+            }
+        }
+
+        let mut unconsumed_exports = Vec::new();
+        for plan_ctx in plans.iter() {
+            let exports = plan_ctx
+                .exports
+                .keys()
+                .filter(|export| {
+                    !binds.iter().any(|bind| {
+                        bind.exports.contains(export)
+                            && bind
+                                .providers
+                                .iter()
+                                .any(|provider| provider.id == plan_ctx.id)
+                    })
+                })
+                .map(|export| export.as_str())
+                .collect::<Vec<_>>();
+            if !exports.is_empty() {
+                unconsumed_exports.push(UnconsumedExports {
+                    plan: plan_ctx,
+                    exports,
+                });
+            }
+        }
+        ServiceComposition {
+            binds,
+            unconsumed_exports,
+        }
+    }
+
+    /// Summarizes the repos and plans of the workspace
+    pub fn workspace_summary(&self) -> WorkspaceSummary<'_> {
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        let mut plan_counts: HashMap<&RepoContextID, usize> = HashMap::new();
+        let mut studio_counts: HashMap<BuildStepStudio, usize> = HashMap::new();
+        let mut plans_by_origin = BTreeMap::new();
+        let mut plans_without_artifact = Vec::new();
+        let mut plans_missing_source = Vec::new();
+        let mut plans_with_config = Vec::new();
+        for node_index in self.dep_graph.build_graph.node_indices() {
+            let Some(plan_ctx) = self.dep_graph.build_graph[node_index].plan_ctx() else {
+                continue;
+            };
+            *plan_counts.entry(&plan_ctx.repo_id).or_default() += 1;
+            *studio_counts
+                .entry(self.node_studio(node_index).0)
+                .or_default() += 1;
+            *plans_by_origin
+                .entry(&plan_ctx.id.as_ref().origin)
+                .or_default() += 1;
+            if artifact_cache
+                .latest_plan_minimal_artifact(&plan_ctx.id)
+                .is_none()
+            {
+                plans_without_artifact.push(plan_ctx);
+            }
+            if let Some(source) = plan_ctx.source.as_ref() {
+                if !self
+                    .store
+                    .package_source_store_path(&source.shasum)
+                    .archive_data_path()
+                    .as_ref()
+                    .is_file()
+                {
+                    plans_missing_source.push(plan_ctx);
+                }
+            }
+            if plan_ctx.plan_config.is_some() {
+                plans_with_config.push(plan_ctx);
+            }
+        }
+        let mut repos = self
+            .repos
+            .values()
+            .map(|repo_ctx| {
+                (
+                    repo_ctx,
+                    plan_counts.get(&repo_ctx.id).copied().unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        repos.sort_by(|(a, _), (b, _)| a.id.cmp(&b.id));
+        for plans in [
+            &mut plans_without_artifact,
+            &mut plans_missing_source,
+            &mut plans_with_config,
+        ] {
+            plans.sort_by_key(|plan_ctx| plan_ctx.id.to_string());
+        }
+        WorkspaceSummary {
+            repos,
+            plans_by_studio: [
+                BuildStepStudio::Native,
+                BuildStepStudio::Bootstrap,
+                BuildStepStudio::Standard,
+            ]
+            .into_iter()
+            .map(|studio| {
+                (
+                    studio,
+                    studio_counts.get(&studio).copied().unwrap_or_default(),
+                )
+            })
+            .collect(),
+            plans_by_origin,
+            plans_without_artifact,
+            plans_missing_source,
+            plans_with_config,
+        }
+    }
+
+    /// Studio used to build the plan of a node, along with the studio package
+    fn node_studio(&self, node_index: NodeIndex) -> (BuildStepStudio, Option<&PackageDepIdent>) {
+        match self
+            .node_dep_analysis(node_index, AnalysisType::StudioDependency)
+            .unwrap()
+            .pop()
+        {
+            Some(package_dep) if package_dep.matches_dep_ident(&self.studios.bootstrap) => {
+                (BuildStepStudio::Bootstrap, Some(&self.studios.bootstrap))
+            }
+            Some(package_dep) if package_dep.matches_dep_ident(&self.studios.standard) => {
+                (BuildStepStudio::Standard, Some(&self.studios.standard))
+            }
+            None => (BuildStepStudio::Native, None),
+            Some(package_dep) => {
+                panic!("Invalid studio dependency {:?}", package_dep);
+            }
+        }
+    }
+
+    /// Paths along which changes to other plans cause the plan to be rebuilt
+    pub fn rebuild_paths(
+        &self,
+        package_index: NodeIndex,
+        change_detection_mode: ChangeDetectionMode,
+        build_order: BuildOrder,
+        build_target: PackageTarget,
+    ) -> (Vec<RebuildPath>, bool) {
+        self.dep_graph.rebuild_paths(
+            package_index,
+            change_detection_mode,
+            build_order,
+            build_target,
+        )
+    }
+
     pub fn dep_analysis<'a>(
         &'a self,
         dep_node_index: NodeIndex,
@@ -534,9 +1787,226 @@ impl AutoBuildContext {
                 .map(|t| self.node_dep_analysis(dep_node_index, *t))
                 .transpose()?
                 .map(|mut d| d.pop()),
+            build_hosts: if analysis_types.contains(&AnalysisType::BuildHosts) {
+                Some(self.artifact_build_hosts(dep_node_index)?)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Lists the hosts on which the latest artifact of a package and all of its
+    /// transitive dependencies were built.
+    fn artifact_build_hosts(&self, dep_node_index: NodeIndex) -> Result<Vec<ArtifactBuildHost>> {
+        let Some(artifact) = self.package_artifact(dep_node_index)? else {
+            return Ok(Vec::new());
+        };
+        let mut artifacts = artifact.tdeps.iter().cloned().collect::<Vec<_>>();
+        artifacts.sort();
+        artifacts.insert(0, artifact.id.clone());
+        self.store.get_connection()?.transaction(|connection| {
+            artifacts
+                .into_iter()
+                .map(|artifact| {
+                    let host = store::build_host_get(connection, &artifact)?;
+                    Ok(ArtifactBuildHost { artifact, host })
+                })
+                .collect()
         })
     }
 
+    /// Finds the artifacts used by a build plan that were built on a host
+    /// significantly different from the current one.
+    pub fn build_host_mismatches(&self, build_plan: &BuildPlan) -> Result<Vec<BuildHostMismatch>> {
+        let mut mismatches = Vec::new();
+        let mut connection = self.store.get_connection()?;
+        for check_step in build_plan.check_steps.iter() {
+            let Some(artifact) = self.package_artifact(check_step.index)? else {
+                continue;
+            };
+            if let Some(host) = store::build_host_get(&mut connection, &artifact.id)? {
+                let differences = CURRENT_BUILD_HOST.differences(&host);
+                if !differences.is_empty() {
+                    mismatches.push(BuildHostMismatch {
+                        artifact: artifact.id.clone(),
+                        host,
+                        differences,
+                    });
+                }
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// Finds the dependency artifacts and source archives needed by a build plan
+    /// that are missing from the artifact cache and the store.
+    pub fn offline_missing_items(&self, build_plan: &BuildPlan) -> Vec<OfflineMissingItem> {
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        let planned_builds = build_plan
+            .build_steps
+            .iter()
+            .map(|step| &step.plan_ctx.id)
+            .collect::<HashSet<_>>();
+        let mut missing_items = Vec::new();
+        for step in build_plan.build_steps.iter() {
+            if let Some(source) = &step.plan_ctx.source {
+                if !self
+                    .store
+                    .package_source_store_path(&source.shasum)
+                    .archive_data_path()
+                    .as_ref()
+                    .is_file()
+                {
+                    missing_items.push(OfflineMissingItem::Source {
+                        plan: step.plan_ctx.id.clone(),
+                        source: source.clone(),
+                    });
+                }
+            }
+            if let Some(studio_package) = step.studio_package {
+                let studio_planned = planned_builds.iter().any(|plan_id| {
+                    plan_id.as_ref().origin == studio_package.origin
+                        && plan_id.as_ref().name == studio_package.name
+                });
+                if !studio_planned
+                    && artifact_cache
+                        .latest_minimal_artifact(
+                            &studio_package.to_resolved_dep_ident(step.plan_ctx.id.as_ref().target),
+                        )
+                        .is_none()
+                {
+                    missing_items.push(OfflineMissingItem::Artifact {
+                        plan: step.plan_ctx.id.clone(),
+                        dependency: format!("{} (studio)", studio_package),
+                    });
+                }
+            }
+            for dep in step.deps_to_install.iter() {
+                if !planned_builds.contains(dep)
+                    && artifact_cache.latest_plan_minimal_artifact(dep).is_none()
+                {
+                    missing_items.push(OfflineMissingItem::Artifact {
+                        plan: step.plan_ctx.id.clone(),
+                        dependency: dep.to_string(),
+                    });
+                }
+            }
+            for dep in step.remote_deps.iter() {
+                let dependency = match dep {
+                    Dependency::ResolvedDep(ident) => artifact_cache
+                        .minimal_artifact(ident)
+                        .is_none()
+                        .then(|| ident.to_string()),
+                    Dependency::RemoteDep(resolved_dep_ident) => artifact_cache
+                        .latest_minimal_artifact(resolved_dep_ident)
+                        .is_none()
+                        .then(|| resolved_dep_ident.to_string()),
+                    Dependency::LocalPlan(_) => None,
+                };
+                if let Some(dependency) = dependency {
+                    missing_items.push(OfflineMissingItem::Artifact {
+                        plan: step.plan_ctx.id.clone(),
+                        dependency,
+                    });
+                }
+            }
+        }
+        missing_items
+    }
+
+    /// Verifies that the build steps that run on the current host can be run in
+    /// the configured sandbox
+    pub fn build_sandbox_verify(&self, build_steps: &[BuildStep]) -> Result<()> {
+        if build_steps
+            .iter()
+            .filter(|step| step.docker_image.is_none())
+            .any(|step| step.studio == BuildStepStudio::Native || self.build_executor.is_local())
+        {
+            habitat::build_sandbox_verify(self.build_sandbox, &self.build_sandbox_command)?;
+        }
+        Ok(())
+    }
+
+    /// Verifies that Docker is available to build the native plans that use a
+    /// docker image and the standard plans built with Docker, and that the images
+    /// match the architecture of the build target.
+    pub fn docker_images_verify(&self, build_steps: &[BuildStep]) -> Result<()> {
+        let mut docker_images: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for step in build_steps {
+            let docker_image = match (step.studio, &step.plan_ctx.plan_config) {
+                (
+                    BuildStepStudio::Native,
+                    Some(PlanContextConfig {
+                        docker_image: Some(docker_image),
+                        ..
+                    }),
+                ) => docker_image,
+                (BuildStepStudio::Standard, _) if self.build_executor.is_local() => {
+                    let Some(docker_image) = step.docker_image.as_ref() else {
+                        continue;
+                    };
+                    docker_image
+                }
+                _ => continue,
+            };
+            docker_images
+                .entry(docker_image.as_str())
+                .or_default()
+                .push(step.plan_ctx.id.to_string());
+        }
+        if docker_images.is_empty() {
+            return Ok(());
+        }
+        let plans = docker_images
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        let daemon_arch = habitat::docker_daemon_arch()
+            .with_context(|| format!("Docker is required to build the plans {}", plans))
+            .with_suggestion(|| {
+                "Install Docker and start its daemon, making sure the current user has access to it"
+            })?;
+        let target_arch = habitat::docker_arch(PackageTarget::default().arch);
+        if daemon_arch != target_arch {
+            return Err(eyre!(
+                "The Docker daemon runs on {} but the plans {} are built for {}",
+                daemon_arch,
+                plans,
+                target_arch
+            ))
+            .with_suggestion(|| format!("Use a Docker daemon running on {}", target_arch));
+        }
+        for (docker_image, plans) in docker_images.iter() {
+            match habitat::docker_image_arch(docker_image)? {
+                Some(image_arch) if image_arch != target_arch => {
+                    return Err(eyre!(
+                        "The Docker image '{}' used to build the plans {} is built for {} instead of {}",
+                        docker_image,
+                        plans.join(", "),
+                        image_arch,
+                        target_arch
+                    ))
+                    .with_suggestion(|| {
+                        format!(
+                            "Pull the image for the build target with 'docker pull --platform linux/{} {}'",
+                            target_arch, docker_image
+                        )
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    debug!(
+                        "Docker image '{}' is not available locally, it will be pulled during the build",
+                        docker_image
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn compare(
         &self,
         source: &AutoBuildContext,
@@ -582,6 +2052,98 @@ impl AutoBuildContext {
         diffs
     }
 
+    /// Versions of the plans of each package and the dependencies between packages
+    /// in the dependency graph
+    fn graph_snapshot(
+        &self,
+    ) -> (
+        BTreeMap<GraphPackage, BTreeSet<PackageBuildVersion>>,
+        BTreeSet<GraphEdge>,
+    ) {
+        let build_graph = &self.dep_graph.build_graph;
+        let mut packages: BTreeMap<GraphPackage, BTreeSet<PackageBuildVersion>> = BTreeMap::new();
+        let mut edges = BTreeSet::new();
+        for node_index in build_graph.node_indices() {
+            let Dependency::LocalPlan(plan_ctx) = &build_graph[node_index] else {
+                continue;
+            };
+            let id = plan_ctx.id.as_ref();
+            let package = GraphPackage {
+                target: id.target,
+                origin: id.origin.clone(),
+                name: id.name.clone(),
+            };
+            for edge in build_graph.edges_directed(node_index, Direction::Outgoing) {
+                let (origin, name) = match &build_graph[edge.target()] {
+                    Dependency::ResolvedDep(ident) => (&ident.origin, &ident.name),
+                    Dependency::RemoteDep(ident) => (&ident.origin, &ident.name),
+                    Dependency::LocalPlan(plan_ctx) => {
+                        (&plan_ctx.id.as_ref().origin, &plan_ctx.id.as_ref().name)
+                    }
+                };
+                edges.insert(GraphEdge {
+                    package: package.clone(),
+                    dep_type: *edge.weight(),
+                    dep: PackageDepIdent {
+                        origin: origin.clone(),
+                        name: name.clone(),
+                        version: PackageVersion::Unresolved,
+                        release: PackageRelease::Unresolved,
+                    },
+                });
+            }
+            packages
+                .entry(package)
+                .or_default()
+                .insert(id.version.clone());
+        }
+        (packages, edges)
+    }
+
+    /// Differences between the dependency graph of another context and this one
+    pub fn graph_diff(&self, from: &AutoBuildContext) -> GraphDiff {
+        let (from_packages, from_edges) = from.graph_snapshot();
+        let (to_packages, to_edges) = self.graph_snapshot();
+        let mut diff = GraphDiff::default();
+        for (package, to_versions) in to_packages.iter() {
+            match from_packages.get(package) {
+                Some(from_versions) if from_versions != to_versions => {
+                    diff.version_changes.push(GraphVersionChange {
+                        package: package.clone(),
+                        from: from_versions.clone(),
+                        to: to_versions.clone(),
+                    })
+                }
+                Some(_) => {}
+                None => diff.added_plans.push(package.clone()),
+            }
+        }
+        diff.removed_plans = from_packages
+            .keys()
+            .filter(|package| !to_packages.contains_key(package))
+            .cloned()
+            .collect();
+        // The dependencies of added and removed plans are left out, only the
+        // changes to the dependencies of plans present in both graphs are listed
+        diff.added_edges = to_edges
+            .difference(&from_edges)
+            .filter(|edge| from_packages.contains_key(&edge.package))
+            .cloned()
+            .collect();
+        diff.removed_edges = from_edges
+            .difference(&to_edges)
+            .filter(|edge| to_packages.contains_key(&edge.package))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// Sets the tracker used by source downloads, to report their progress
+    /// or limit their bandwidth.
+    pub fn download_tracker_set(&mut self, download_tracker: DownloadTracker) {
+        self.download_tracker = download_tracker;
+    }
+
     pub fn download_dep_source(
         &self,
         package_index: NodeIndex,
@@ -600,10 +2162,10 @@ impl AutoBuildContext {
         check_source: bool,
     ) -> Result<DownloadStatus, DownloadError> {
         if let Some(source) = &plan_ctx.source {
-            let source_store_path = self.store.package_source_store_path(source);
+            let source_store_path = self.store.package_source_store_path(&source.shasum);
             let source_archive_path = source_store_path.archive_data_path();
 
-            let invalid_source_store_path = self.store.invalid_source_store_path(source);
+            let invalid_source_store_path = self.store.invalid_source_store_path(&source.shasum);
             let invalid_source_archive_path = invalid_source_store_path.archive_data_path();
 
             if source_archive_path.as_ref().is_file() {
@@ -617,14 +2179,17 @@ impl AutoBuildContext {
                                 store::source_context_get(connection, &source.shasum)
                             })
                             .map_err(DownloadError::UnexpectedError)?;
-                        let source_ctx = if let Some(existing_source_ctx) = existing_source_ctx {
+                        let mut source_ctx = if let Some(existing_source_ctx) = existing_source_ctx
+                        {
                             existing_source_ctx
                         } else {
-                            let new_source_ctx = SourceContext::read_from_disk(
-                                source_archive_path,
-                                Some(source.shasum.clone()),
-                            )
-                            .map_err(DownloadError::UnexpectedError)?;
+                            let new_source_ctx = self
+                                .source_context_scan(
+                                    plan_ctx,
+                                    source_archive_path,
+                                    Some(source.shasum.clone()),
+                                )
+                                .map_err(DownloadError::UnexpectedError)?;
                             self.store
                                 .get_connection()
                                 .map_err(DownloadError::UnexpectedError)?
@@ -638,8 +2203,9 @@ impl AutoBuildContext {
                                 .map_err(DownloadError::UnexpectedError)?;
                             new_source_ctx
                         };
+                        self.source_licenses_rescan(plan_ctx, &mut source_ctx);
                         let source_violations = if check_source {
-                            let checker = Checker::new();
+                            let checker = Checker::new(plan_ctx.id.as_ref().target);
                             checker.source_context_check_with_plan(
                                 &plan_ctx.config(),
                                 plan_ctx,
@@ -648,10 +2214,20 @@ impl AutoBuildContext {
                         } else {
                             vec![]
                         };
+                        let download_url = self
+                            .store
+                            .get_connection()
+                            .map_err(DownloadError::UnexpectedError)?
+                            .transaction(|connection| {
+                                store::source_download_url_get(connection, &source.shasum)
+                            })
+                            .map_err(DownloadError::UnexpectedError)?
+                            .unwrap_or_else(|| source.url.clone());
                         return Ok(DownloadStatus::AlreadyDownloaded(
                             source_ctx,
                             plan_ctx.clone(),
                             source.clone(),
+                            download_url,
                             source_violations,
                         ));
                     }
@@ -672,26 +2248,46 @@ impl AutoBuildContext {
                 source.url,
                 temp_file_path.display()
             );
-            match source.download_and_verify_pkg_archive(temp_file_path.as_path()) {
-                Ok(download_duration) => {
+            let download_result = if source.url.is_git() {
+                source.fetch_and_verify_git_archive(
+                    temp_file_path.as_path(),
+                    self.store.git_repos_path(),
+                )
+            } else {
+                source.download_and_verify_pkg_archive(
+                    temp_file_path.as_path(),
+                    &self.source_mirrors,
+                    &self.download_tracker,
+                )
+            };
+            match download_result {
+                Ok((download_duration, download_url)) => {
                     std::fs::create_dir_all(source_store_path.as_ref())
                         .map_err(DownloadError::UnexpectedIOError)?;
                     std::fs::rename(temp_file_path.as_path(), source_archive_path.as_ref())
                         .map_err(DownloadError::UnexpectedIOError)?;
-                    let source_ctx = SourceContext::read_from_disk(
-                        source_archive_path,
-                        Some(source.shasum.clone()),
-                    )
-                    .map_err(DownloadError::UnexpectedError)?;
+                    let mut source_ctx = self
+                        .source_context_scan(
+                            plan_ctx,
+                            source_archive_path,
+                            Some(source.shasum.clone()),
+                        )
+                        .map_err(DownloadError::UnexpectedError)?;
                     self.store
                         .get_connection()
                         .map_err(DownloadError::UnexpectedError)?
                         .transaction(|connection| {
-                            store::source_context_put(connection, &source.shasum, &source_ctx)
+                            store::source_context_put(connection, &source.shasum, &source_ctx)?;
+                            store::source_download_url_put(
+                                connection,
+                                &source.shasum,
+                                &download_url,
+                            )
                         })
                         .map_err(DownloadError::UnexpectedError)?;
+                    self.source_licenses_rescan(plan_ctx, &mut source_ctx);
                     let source_violations = if check_source {
-                        let checker = Checker::new();
+                        let checker = Checker::new(plan_ctx.id.as_ref().target);
                         checker.source_context_check_with_plan(
                             &plan_ctx.config(),
                             plan_ctx,
@@ -704,6 +2300,7 @@ impl AutoBuildContext {
                         source_ctx,
                         plan_ctx.clone(),
                         source.clone(),
+                        download_url,
                         download_duration,
                         source_violations,
                     ))
@@ -730,6 +2327,122 @@ impl AutoBuildContext {
         }
     }
 
+    /// Downloads a source archive whose shasum is not known yet, such as the
+    /// source of a newer upstream version, into the store and returns its shasum
+    pub fn source_archive_add(&self, url: &PackageSourceURL) -> Result<PackageSha256Sum> {
+        if url.is_git() {
+            return Err(eyre!(
+                "Cannot download git source '{}' without its shasum",
+                url
+            ));
+        }
+        let tmp_dir = self.store.temp_dir("download")?;
+        let temp_file_path = tmp_dir.path().join("download.part");
+        info!(
+            "Downloading source archive from {} to {}",
+            url,
+            temp_file_path.display()
+        );
+        Download::new(url.as_ref(), temp_file_path.as_path())
+            .with_tracker(&self.download_tracker)
+            .execute()
+            .with_context(|| eyre!("Failed to download source archive from {}", url))?;
+        let shasum = PackageSha256Sum::from(String::from(ShaSum::from_path(&temp_file_path)?));
+        let source_store_path = self.store.package_source_store_path(&shasum);
+        std::fs::create_dir_all(source_store_path.as_ref())?;
+        std::fs::rename(
+            temp_file_path.as_path(),
+            source_store_path.archive_data_path().as_ref(),
+        )?;
+        self.store
+            .get_connection()?
+            .transaction(|connection| store::source_download_url_put(connection, &shasum, url))?;
+        Ok(shasum)
+    }
+
+    /// Reads a source archive and scans it for licenses, reporting the progress
+    /// of the scan since it can take a while for large archives.
+    fn source_context_scan(
+        &self,
+        plan_ctx: &PlanContext,
+        source_archive_path: impl AsRef<Path>,
+        source_shasum: Option<PackageSha256Sum>,
+    ) -> Result<SourceContext> {
+        info!(target: "user-ui", "{} licenses in sources of {}", "     Scanning".magenta().bold(), plan_ctx.id);
+        let start = Instant::now();
+        let source_ctx = SourceContext::read_from_disk(source_archive_path, source_shasum)
+            .with_context(|| eyre!("Failed to scan sources of {} for licenses", plan_ctx.id))?;
+        let duration = start.elapsed();
+        info!(target: "user-ui", "{} {} license files in sources of {} in {:.3}s", "      Scanned".green().bold(), source_ctx.licenses.len(), plan_ctx.id, duration.as_secs_f32());
+        self.license_scans.lock().unwrap().push(LicenseScan {
+            plan_ctx_id: plan_ctx.id.clone(),
+            license_count: source_ctx.licenses.len(),
+            duration,
+        });
+        Ok(source_ctx)
+    }
+
+    /// License scans of source archives done so far in the current session
+    pub fn license_scans(&self) -> Vec<LicenseScan> {
+        self.license_scans.lock().unwrap().clone()
+    }
+
+    /// Detects the licenses of a source again if the plan's repo has a custom
+    /// license scanner, the stored source context always uses the default one.
+    /// The licenses detected by each custom scanner are cached by source shasum.
+    fn source_licenses_rescan(&self, plan_ctx: &PlanContext, source_ctx: &mut SourceContext) {
+        let Some(license_scanner) = self
+            .repos
+            .get(&plan_ctx.repo_id)
+            .and_then(|repo_ctx| repo_ctx.license_scanner.as_ref())
+        else {
+            return;
+        };
+        let Some(source_shasum) = source_ctx.source_shasum.clone() else {
+            source_ctx.licenses_rescan(license_scanner.as_ref());
+            return;
+        };
+        let cached_licenses = self.store.get_connection().and_then(|mut connection| {
+            connection.transaction(|connection| {
+                store::source_license_scan_get(
+                    connection,
+                    &source_shasum,
+                    license_scanner.fingerprint(),
+                )
+            })
+        });
+        match cached_licenses {
+            Ok(Some(detected_licenses)) => {
+                debug!("Licenses of source {} loaded from cache", source_shasum);
+                source_ctx.detected_licenses_set(detected_licenses);
+                return;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                error!(
+                    "Failed to read cached licenses of source {}: {:#}",
+                    source_shasum, err
+                );
+            }
+        }
+        source_ctx.licenses_rescan(license_scanner.as_ref());
+        if let Err(err) = self.store.get_connection().and_then(|mut connection| {
+            connection.transaction(|connection| {
+                store::source_license_scan_put(
+                    connection,
+                    &source_shasum,
+                    license_scanner.fingerprint(),
+                    &source_ctx.detected_licenses(),
+                )
+            })
+        }) {
+            error!(
+                "Failed to cache licenses of source {}: {:#}",
+                source_shasum, err
+            );
+        }
+    }
+
     fn node_dep_analysis(
         &self,
         node_index: NodeIndex,
@@ -810,6 +2523,12 @@ impl AutoBuildContext {
                 false,
                 true,
             ),
+            AnalysisType::BuildHosts => {
+                return Err(eyre!(
+                    "{} is not a dependency analysis, use 'artifact_build_hosts' instead",
+                    analysis_type
+                ));
+            }
         };
         Ok(nodes
             .into_iter()
@@ -824,8 +2543,18 @@ impl AutoBuildContext {
         build_order: BuildOrder,
         build_target: PackageTarget,
     ) -> Vec<RepoChanges<'_>> {
-        self.dep_graph
-            .detect_changes_in_repos(change_detection_mode, build_order, build_target)
+        let repo_changes = self.dep_graph.detect_changes_in_repos(
+            change_detection_mode,
+            build_order,
+            build_target,
+        );
+        let skipped_plans = self.broken_plans_skipped(
+            &repo_changes
+                .values()
+                .flat_map(|changes| changes.keys().cloned())
+                .collect(),
+        );
+        repo_changes
             .into_iter()
             .map(|(repo_ctx_id, changes)| RepoChanges {
                 repo: self.repos.get(&repo_ctx_id).unwrap(),
@@ -835,9 +2564,11 @@ impl AutoBuildContext {
                         if package_indices.contains(&dep_index) {
                             match &self.dep_graph.build_graph[dep_index] {
                                 Dependency::ResolvedDep(_) | Dependency::RemoteDep(_) => None,
-                                Dependency::LocalPlan(plan_ctx) => {
-                                    Some(DependencyChange { plan_ctx, causes })
-                                }
+                                Dependency::LocalPlan(plan_ctx) => Some(DependencyChange {
+                                    plan_ctx,
+                                    causes,
+                                    skipped: skipped_plans.get(&dep_index).copied(),
+                                }),
                             }
                         } else {
                             None
@@ -848,6 +2579,136 @@ impl AutoBuildContext {
             .collect()
     }
 
+    /// Fails if any of the broken plan entries in the configuration has expired,
+    /// this ensures that broken plans are not forgotten about forever.
+    pub fn broken_plans_verify(&self) -> Result<()> {
+        let expired_broken_plans = self
+            .broken_plans
+            .iter()
+            .filter(|broken_plan| broken_plan.is_expired())
+            .collect::<Vec<_>>();
+        if expired_broken_plans.is_empty() {
+            return Ok(());
+        }
+        Err(eyre!(
+            "Found {} expired broken plan entries: {}",
+            expired_broken_plans.len(),
+            expired_broken_plans
+                .iter()
+                .map(|broken_plan| format!(
+                    "'{}' owned by {} expired on {} ({})",
+                    broken_plan.package,
+                    broken_plan.owner,
+                    broken_plan.expires_on,
+                    broken_plan.reason
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .with_suggestion(|| {
+            "Fix the plans and remove them from 'broken_plans' in the hab-auto-build configuration, or extend their 'expires_on' date"
+        }))
+    }
+
+    /// Collects the extra build arguments of all the entries matching a plan, in order
+    fn plan_build_args(&self, plan_ctx: &PlanContext) -> Vec<String> {
+        self.build_args
+            .iter()
+            .filter(|build_args| {
+                build_args
+                    .package
+                    .matcher()
+                    .matches_package_build_ident(plan_ctx.id.as_ref())
+            })
+            .flat_map(|build_args| build_args.args.iter().cloned())
+            .collect()
+    }
+
+    /// Determines the image a standard plan is built in when it is built with the
+    /// 'docker' builder, the plan's configuration overrides the global one.
+    fn plan_docker_image(
+        &self,
+        plan_ctx: &PlanContext,
+        studio: BuildStepStudio,
+    ) -> Result<Option<String>> {
+        let plan_config = plan_ctx.plan_config.as_ref();
+        let builder = plan_config
+            .and_then(|config| config.builder)
+            .unwrap_or(self.builder);
+        if studio != BuildStepStudio::Standard || builder != PlanBuilder::Docker {
+            return Ok(None);
+        }
+        plan_config
+            .and_then(|config| config.docker_image.clone())
+            .or_else(|| self.docker_image.clone())
+            .map(Some)
+            .ok_or_else(|| {
+                eyre!(
+                    "Plan {} is built with the 'docker' builder, but no docker image is configured",
+                    plan_ctx.id
+                )
+            })
+            .with_suggestion(|| {
+                "Set 'docker-image' in the plan's .hab-plan-config.toml file or 'docker_image' in the hab-auto-build configuration"
+            })
+    }
+
+    fn broken_plan(&self, plan_ctx: &PlanContext) -> Option<&BrokenPlanConfig> {
+        self.broken_plans.iter().find(|broken_plan| {
+            broken_plan
+                .package
+                .matcher()
+                .matches_package_build_ident(plan_ctx.id.as_ref())
+        })
+    }
+
+    /// Determines which of the changed plans must be skipped, either because they
+    /// are listed as broken or because they depend on a changed broken plan.
+    fn broken_plans_skipped(
+        &self,
+        changed_node_indices: &HashSet<NodeIndex>,
+    ) -> HashMap<NodeIndex, BrokenPlanSkip<'_>> {
+        let mut skipped_plans = HashMap::new();
+        let mut pending_node_indices = Vec::new();
+        for node_index in changed_node_indices {
+            if let Some(plan_ctx) = self.dep_graph.build_graph[*node_index].plan_ctx() {
+                if let Some(config) = self.broken_plan(plan_ctx) {
+                    skipped_plans.insert(
+                        *node_index,
+                        BrokenPlanSkip {
+                            broken_plan: &plan_ctx.id,
+                            config,
+                        },
+                    );
+                    pending_node_indices.push(*node_index);
+                }
+            }
+        }
+        while let Some(node_index) = pending_node_indices.pop() {
+            let skip = skipped_plans[&node_index];
+            for rev_dep_node_index in self
+                .dep_graph
+                .build_graph
+                .edges_directed(node_index, Direction::Incoming)
+                .filter(|e| {
+                    matches!(
+                        e.weight(),
+                        DependencyType::Build | DependencyType::Runtime | DependencyType::Studio
+                    )
+                })
+                .map(|e| e.source())
+            {
+                if changed_node_indices.contains(&rev_dep_node_index)
+                    && !skipped_plans.contains_key(&rev_dep_node_index)
+                {
+                    skipped_plans.insert(rev_dep_node_index, skip);
+                    pending_node_indices.push(rev_dep_node_index);
+                }
+            }
+        }
+        skipped_plans
+    }
+
     #[allow(dead_code)]
     pub fn get_plan_contexts(&self, package: &PackageDepIdent) -> Vec<&PlanContext> {
         self.dep_graph
@@ -888,10 +2749,11 @@ impl AutoBuildContext {
                         // Delete any modifications for the plan context that may be present
                         store::plan_context_alternate_modified_at_delete(
                             connection,
+                            self.store.workspace(),
                             &plan_ctx.context_path,
                         )?;
                         plan_ctx.determine_changes(
-                            Some(connection),
+                            Some((connection, self.store.workspace())),
                             None,
                             Some(&latest_plan_artifact),
                             ChangeDetectionMode::Disk,
@@ -901,13 +2763,14 @@ impl AutoBuildContext {
                                 latest_plan_artifact.created_at + Duration::seconds(1);
                             store::file_alternate_modified_at_put(
                                 connection,
+                                self.store.workspace(),
                                 &plan_ctx.context_path,
                                 plan_ctx.target_context_path.clone(),
                                 plan_ctx.target_context_last_modified_at,
                                 alternate_modified_at,
                             )?;
                             plan_ctx.determine_changes(
-                                Some(connection),
+                                Some((connection, self.store.workspace())),
                                 None,
                                 Some(&latest_plan_artifact),
                                 ChangeDetectionMode::Disk,
@@ -938,9 +2801,302 @@ impl AutoBuildContext {
         Ok(results)
     }
 
-    pub fn sync_plans_with_git(
-        &mut self,
-        connection: &mut SqliteConnection,
+    /// Finds the build logs of a plan in the store, most recently modified first.
+    /// Logs of finished builds are named '<origin>-<name>-<version>-<release>-<target>.log',
+    /// the logs of builds in progress are in the plan's temporary build folder.
+    pub fn build_logs(
+        &self,
+        package: &PackageDepIdent,
+        target: PackageTarget,
+    ) -> Result<Vec<BuildLog>> {
+        let prefix = format!("{}-{}-", package.origin, package.name);
+        let suffix = format!("-{}.log", target);
+        let is_plan_log = |path: &Path| {
+            let Some(version_release) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(prefix.as_str()))
+                .and_then(|name| name.strip_suffix(suffix.as_str()))
+            else {
+                return false;
+            };
+            // The name of the package may itself be the prefix of another package's name,
+            // so the remainder must be exactly a version and a release
+            let Some((version, release)) = version_release.rsplit_once('-') else {
+                return false;
+            };
+            if version.is_empty()
+                || version.contains('-')
+                || release.len() != 14
+                || !release.chars().all(|c| c.is_ascii_digit())
+            {
+                return false;
+            }
+            let version_matches = match &package.version {
+                PackageVersion::Resolved(package_version) => package_version.to_string() == version,
+                PackageVersion::Unresolved => true,
+            };
+            let release_matches = match &package.release {
+                PackageRelease::Resolved(package_release) => package_release.to_string() == release,
+                PackageRelease::Unresolved => true,
+            };
+            version_matches && release_matches
+        };
+
+        let mut candidates = Vec::new();
+        // The version and release of a build in progress are not known until it completes
+        if package.version == PackageVersion::Unresolved
+            && package.release == PackageRelease::Unresolved
+        {
+            for path in store::store_entries_get(self.store.temp_dir_path())? {
+                if habitat::build_temp_dir_is_for(&path, &package.origin, &package.name, target) {
+                    let build_log_path = path.join("build.log");
+                    if build_log_path.is_file() {
+                        candidates.push((BuildLogKind::Running, build_log_path));
+                    }
+                }
+            }
+        }
+        for path in store::store_entries_get(self.store.package_build_success_logs_path())? {
+            if is_plan_log(&path) {
+                candidates.push((BuildLogKind::Success, path));
+            }
+        }
+        for path in store::store_entries_get(self.store.package_build_failure_logs_path())? {
+            if is_plan_log(&path) {
+                candidates.push((BuildLogKind::Failure, path));
+            }
+        }
+
+        let mut build_logs = Vec::new();
+        for (kind, path) in candidates {
+            build_logs.push(BuildLog {
+                kind,
+                modified_at: path.last_modifed_at()?,
+                path,
+            });
+        }
+        build_logs.sort_by_key(|build_log| std::cmp::Reverse(build_log.modified_at));
+        Ok(build_logs)
+    }
+
+    pub fn store_clean(
+        &self,
+        connection: &mut SqliteConnection,
+        options: &StoreCleanOptions,
+        dry_run: bool,
+    ) -> Result<Vec<StoreCleanStatus>, StoreCleanError> {
+        let mut candidates = Vec::new();
+        if options.sources {
+            let referenced_sources = self
+                .dep_graph
+                .build_graph
+                .node_weights()
+                .filter_map(|dep| dep.plan_ctx())
+                .filter_map(|plan_ctx| plan_ctx.source.as_ref())
+                .map(|source| source.shasum.to_string())
+                .collect::<HashSet<_>>();
+            for path in store::store_entries_get(self.store.package_sources_path())? {
+                let is_referenced = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| referenced_sources.contains(name))
+                    .unwrap_or_default();
+                if options.unreferenced && is_referenced {
+                    continue;
+                }
+                candidates.push((StoreCleanCategory::Sources, path));
+            }
+            for path in store::store_entries_get(self.store.invalid_package_sources_path())? {
+                candidates.push((StoreCleanCategory::InvalidSources, path));
+            }
+        }
+        if options.git_repos {
+            let referenced_repos = self
+                .dep_graph
+                .build_graph
+                .node_weights()
+                .filter_map(|dep| dep.plan_ctx())
+                .filter_map(|plan_ctx| plan_ctx.source.as_ref())
+                .filter_map(|source| source.url.git_source())
+                .map(|git_source| git_source.repo_id())
+                .collect::<HashSet<_>>();
+            for path in store::store_entries_get(self.store.git_repos_path())? {
+                let is_referenced = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| referenced_repos.contains(name))
+                    .unwrap_or_default();
+                if options.unreferenced && is_referenced {
+                    continue;
+                }
+                candidates.push((StoreCleanCategory::GitRepos, path));
+            }
+        }
+        if options.logs {
+            // Build success logs are named after the artifact that was built, so
+            // they are referenced as long as the artifact is in the artifact cache
+            let artifact_cache_path = ArtifactCachePath::default();
+            for path in store::store_entries_get(self.store.package_build_success_logs_path())? {
+                let is_referenced = path
+                    .file_stem()
+                    .and_then(|name| name.to_str())
+                    .map(|name| {
+                        artifact_cache_path
+                            .as_ref()
+                            .join(format!("{}.hart", name))
+                            .is_file()
+                    })
+                    .unwrap_or_default();
+                if options.unreferenced && is_referenced {
+                    continue;
+                }
+                candidates.push((StoreCleanCategory::BuildSuccessLogs, path));
+            }
+            for path in store::store_entries_get(self.store.package_build_failure_logs_path())? {
+                candidates.push((StoreCleanCategory::BuildFailureLogs, path));
+            }
+        }
+        if options.tmp {
+            for path in store::store_entries_get(self.store.temp_dir_path())? {
+                candidates.push((StoreCleanCategory::Temp, path));
+            }
+        }
+        if options.studios {
+            for path in habitat::build_studio_roots()? {
+                candidates.push((StoreCleanCategory::Studio, path));
+            }
+        }
+
+        let now = Utc::now();
+        let mut results = Vec::new();
+        for (category, path) in candidates {
+            let modified_at = path.last_modifed_at()?;
+            if let Some(older_than) = options.older_than {
+                if now - modified_at < older_than {
+                    continue;
+                }
+            }
+            let size = if category == StoreCleanCategory::Studio {
+                // Studios may contain files that are only readable by root
+                store::store_entry_size(&path).unwrap_or_default()
+            } else {
+                store::store_entry_size(&path)?
+            };
+            if !dry_run {
+                if category == StoreCleanCategory::Sources {
+                    if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                        let hash = PackageSha256Sum::from(name.to_string());
+                        store::source_context_delete(connection, &hash)?;
+                        store::source_download_url_delete(connection, &hash)?;
+                    }
+                }
+                if category == StoreCleanCategory::Studio {
+                    habitat::studio_remove(&path, &self.build_sandbox_command)?;
+                } else {
+                    store::store_entry_delete(&path)?;
+                }
+                debug!("Removed {} '{}' from store", category, path.display());
+            }
+            results.push(StoreCleanStatus {
+                category,
+                path,
+                size,
+                modified_at,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Re-hashes every source archive in the store and compares it with the shasum
+    /// recorded by the plans referring to it. Corrupted archives are moved to the
+    /// invalid sources folder if `quarantine` is set.
+    pub fn sources_verify(
+        &self,
+        connection: &mut SqliteConnection,
+        quarantine: bool,
+    ) -> Result<Vec<SourceVerifyResult>, SourceVerifyError> {
+        let mut referencing_plans: HashMap<PackageSha256Sum, Vec<PlanContextID>> = HashMap::new();
+        for plan_ctx in self
+            .dep_graph
+            .build_graph
+            .node_weights()
+            .filter_map(|dep| dep.plan_ctx())
+        {
+            if let Some(source) = plan_ctx.source.as_ref() {
+                referencing_plans
+                    .entry(source.shasum.clone())
+                    .or_default()
+                    .push(plan_ctx.id.clone());
+            }
+        }
+        let mut results = store::store_entries_get(self.store.package_sources_path())?
+            .into_par_iter()
+            .filter_map(|path| {
+                let name = path.file_name().and_then(|name| name.to_str())?;
+                Some((PackageSha256Sum::from(name.to_string()), path))
+            })
+            .map(|(shasum, path)| {
+                let archive_path = self
+                    .store
+                    .package_source_store_path(&shasum)
+                    .archive_data_path();
+                let status = if archive_path.as_ref().is_file() {
+                    let actual = PackageSha256Sum::from(
+                        ShaSum::from_path(archive_path.as_ref())?.to_string(),
+                    );
+                    if actual == shasum {
+                        SourceVerifyStatus::Valid
+                    } else {
+                        SourceVerifyStatus::Mismatch(actual)
+                    }
+                } else {
+                    SourceVerifyStatus::Missing
+                };
+                let mut plans = referencing_plans.get(&shasum).cloned().unwrap_or_default();
+                plans.sort();
+                Ok(SourceVerifyResult {
+                    shasum,
+                    path,
+                    plans,
+                    status,
+                    quarantined: false,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        results.sort_by(|a, b| a.path.cmp(&b.path));
+
+        if quarantine {
+            for result in results.iter_mut() {
+                if matches!(result.status, SourceVerifyStatus::Valid) {
+                    continue;
+                }
+                if let SourceVerifyStatus::Mismatch(_) = result.status {
+                    let source_archive_path = self
+                        .store
+                        .package_source_store_path(&result.shasum)
+                        .archive_data_path();
+                    let invalid_source_store_path =
+                        self.store.invalid_source_store_path(&result.shasum);
+                    std::fs::create_dir_all(invalid_source_store_path.as_ref())?;
+                    std::fs::rename(
+                        source_archive_path.as_ref(),
+                        invalid_source_store_path.archive_data_path().as_ref(),
+                    )?;
+                }
+                store::store_entry_delete(&result.path)?;
+                store::source_context_delete(connection, &result.shasum)?;
+                store::source_download_url_delete(connection, &result.shasum)?;
+                debug!("Quarantined source '{}' from store", result.path.display());
+                result.quarantined = true;
+            }
+        }
+        Ok(results)
+    }
+
+    pub fn sync_plans_with_git(
+        &mut self,
+        connection: &mut SqliteConnection,
         plan_node_indices: &[NodeIndex],
         is_dry_run: bool,
     ) -> Result<BTreeMap<RepoContextID, Vec<PlanContextGitSyncStatus>>, GitSyncError> {
@@ -949,7 +3105,66 @@ impl AutoBuildContext {
             match self.dep_graph.dep_mut(*plan_node_index) {
                 Dependency::ResolvedDep(_) | Dependency::RemoteDep(_) => {}
                 Dependency::LocalPlan(ref mut plan_ctx) => {
+                    let previously_synced = store::plan_context_git_synced_from_get(
+                        connection,
+                        self.store.workspace(),
+                        &plan_ctx.context_path,
+                    )?;
                     let sync_results = plan_ctx.sync_changes_with_git(is_dry_run)?;
+                    if !is_dry_run {
+                        // Delete any modifications for the plan context that may be present
+                        store::plan_context_alternate_modified_at_delete(
+                            connection,
+                            self.store.workspace(),
+                            &plan_ctx.context_path,
+                        )?;
+                        // Keep the original modification times of files synced earlier
+                        // that have not changed since, so that they can still be reverted
+                        for (path, (synced_to_modified_at, synced_from_modified_at)) in
+                            previously_synced.iter()
+                        {
+                            if path.last_modifed_at().ok() == Some(*synced_to_modified_at) {
+                                store::file_git_synced_from_put(
+                                    connection,
+                                    self.store.workspace(),
+                                    &plan_ctx.context_path,
+                                    path,
+                                    *synced_from_modified_at,
+                                    *synced_to_modified_at,
+                                )?;
+                            }
+                        }
+                        for sync_result in sync_results.iter() {
+                            if let PlanContextPathGitSyncStatus::Synced(
+                                path,
+                                disk_modified_at,
+                                git_modified_at,
+                                _,
+                            ) = sync_result
+                            {
+                                let path = plan_ctx
+                                    .context_path
+                                    .as_ref()
+                                    .join(path.strip_prefix(".").unwrap_or(path));
+                                let synced_from_modified_at = previously_synced
+                                    .get(&path)
+                                    .filter(|(synced_to_modified_at, _)| {
+                                        synced_to_modified_at == disk_modified_at
+                                    })
+                                    .map_or(*disk_modified_at, |(_, synced_from_modified_at)| {
+                                        *synced_from_modified_at
+                                    });
+                                store::file_git_synced_from_put(
+                                    connection,
+                                    self.store.workspace(),
+                                    &plan_ctx.context_path,
+                                    path,
+                                    synced_from_modified_at,
+                                    *git_modified_at,
+                                )?;
+                            }
+                        }
+                    }
                     if !sync_results.is_empty() {
                         results.entry(plan_ctx.repo_id.clone()).or_default().push(
                             PlanContextGitSyncStatus {
@@ -958,19 +3173,126 @@ impl AutoBuildContext {
                             },
                         );
                     }
-                    if !is_dry_run {
-                        // Delete any modifications for the plan context that may be present
-                        store::plan_context_alternate_modified_at_delete(
-                            connection,
-                            &plan_ctx.context_path,
-                        )?;
-                    }
                 }
             }
         }
         Ok(results)
     }
 
+    /// Restores the modification times that files of the plans had before they were
+    /// synced with git, files modified since the sync are left untouched.
+    pub fn revert_plans_git_sync(
+        &mut self,
+        connection: &mut SqliteConnection,
+        plan_node_indices: &[NodeIndex],
+    ) -> Result<BTreeMap<RepoContextID, Vec<PlanContextGitSyncStatus>>, GitSyncError> {
+        let mut results: BTreeMap<RepoContextID, Vec<PlanContextGitSyncStatus>> = BTreeMap::new();
+        for plan_node_index in plan_node_indices {
+            let Some(plan_ctx) = self.dep_graph.dep(*plan_node_index).plan_ctx() else {
+                continue;
+            };
+            let synced_files = store::plan_context_git_synced_from_get(
+                connection,
+                self.store.workspace(),
+                &plan_ctx.context_path,
+            )?;
+            let mut revert_results = Vec::new();
+            for (path, (synced_to_modified_at, synced_from_modified_at)) in synced_files {
+                let relative_path = Path::new(".").join(
+                    path.strip_prefix(plan_ctx.context_path.as_ref())
+                        .unwrap_or(&path),
+                );
+                let Ok(disk_modified_at) = path.last_modifed_at() else {
+                    continue;
+                };
+                if disk_modified_at == synced_to_modified_at {
+                    path.set_last_modifed_at(synced_from_modified_at)?;
+                    revert_results.push(PlanContextPathGitSyncStatus::Reverted(
+                        relative_path,
+                        synced_to_modified_at,
+                        synced_from_modified_at,
+                    ));
+                } else {
+                    revert_results.push(PlanContextPathGitSyncStatus::ModifiedSinceSync(
+                        relative_path,
+                        disk_modified_at,
+                    ));
+                }
+            }
+            store::plan_context_git_synced_from_delete(
+                connection,
+                self.store.workspace(),
+                &plan_ctx.context_path,
+            )?;
+            if !revert_results.is_empty() {
+                revert_results.sort_by(|a, b| a.path().cmp(b.path()));
+                results.entry(plan_ctx.repo_id.clone()).or_default().push(
+                    PlanContextGitSyncStatus {
+                        id: plan_ctx.id.clone(),
+                        file_statuses: revert_results,
+                    },
+                );
+            }
+        }
+        Ok(results)
+    }
+
+    /// Returns the modification times recorded in the store for the files of a plan
+    pub fn plan_file_timestamps(
+        &self,
+        connection: &mut SqliteConnection,
+        plan_node_index: NodeIndex,
+    ) -> Result<Vec<PlanFileTimestamp>> {
+        let Some(plan_ctx) = self.dep_graph.dep(plan_node_index).plan_ctx() else {
+            return Ok(Vec::new());
+        };
+        let mut timestamps = store::plan_context_alternate_modified_at_get(
+            connection,
+            self.store.workspace(),
+            &plan_ctx.context_path,
+        )?
+        .into_iter()
+        .map(
+            |(path, (recorded_modified_at, alternate_modified_at))| PlanFileTimestamp {
+                real_modified_at: path.last_modifed_at().ok(),
+                path,
+                recorded_modified_at,
+                alternate_modified_at,
+            },
+        )
+        .collect::<Vec<_>>();
+        timestamps.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(timestamps)
+    }
+
+    /// Deletes the modification times recorded in the store for the files of a
+    /// plan and detects its changes again using the real modification times.
+    /// Returns the timestamps that were deleted.
+    pub fn plan_file_timestamps_reset(
+        &mut self,
+        connection: &mut SqliteConnection,
+        plan_node_index: NodeIndex,
+    ) -> Result<Vec<PlanFileTimestamp>> {
+        let timestamps = self.plan_file_timestamps(connection, plan_node_index)?;
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        if let Dependency::LocalPlan(ref mut plan_ctx) = self.dep_graph.dep_mut(plan_node_index) {
+            store::plan_context_alternate_modified_at_delete(
+                connection,
+                self.store.workspace(),
+                &plan_ctx.context_path,
+            )?;
+            plan_ctx.determine_changes(
+                Some((connection, self.store.workspace())),
+                None,
+                artifact_cache
+                    .latest_plan_minimal_artifact(&plan_ctx.id)
+                    .as_ref(),
+                ChangeDetectionMode::Disk,
+            )?;
+        }
+        Ok(timestamps)
+    }
+
     pub fn remove_plans_from_changes(
         &mut self,
         connection: &mut SqliteConnection,
@@ -1016,10 +3338,11 @@ impl AutoBuildContext {
                         // Delete any modifications for the plan context that may be present
                         store::plan_context_alternate_modified_at_delete(
                             connection,
+                            self.store.workspace(),
                             &plan_ctx.context_path,
                         )?;
                         plan_ctx.determine_changes(
-                            Some(connection),
+                            Some((connection, self.store.workspace())),
                             None,
                             artifact_cache
                                 .latest_plan_minimal_artifact(&plan_ctx.id)
@@ -1030,6 +3353,7 @@ impl AutoBuildContext {
                             for changed_file in plan_ctx.files_changed_on_disk.iter() {
                                 store::file_alternate_modified_at_put(
                                     connection,
+                                    self.store.workspace(),
                                     &plan_ctx.context_path,
                                     changed_file.path.clone(),
                                     changed_file.real_last_modified_at,
@@ -1037,7 +3361,7 @@ impl AutoBuildContext {
                                 )?;
                             }
                             plan_ctx.determine_changes(
-                                Some(connection),
+                                Some((connection, self.store.workspace())),
                                 None,
                                 artifact_cache
                                     .latest_plan_minimal_artifact(&plan_ctx.id)
@@ -1072,10 +3396,30 @@ impl AutoBuildContext {
         build_order: BuildOrder,
         build_target: PackageTarget,
         allow_remote: bool,
-    ) -> Result<BuildPlan> {
-        let base_changes_graph =
+        scope: &BuildPlanScope,
+    ) -> Result<BuildPlan<'_>> {
+        let mut base_changes_graph =
             self.dep_graph
                 .detect_changes(change_detection_mode, build_order, build_target);
+        if let Some(resume) = scope.resume.as_ref() {
+            // Plans that were rebuilt only because their dependencies were rebuilt are
+            // not detected as changed anymore once those dependencies are built
+            base_changes_graph = self.dep_graph.build_graph.filter_map(
+                |node_index, _node| {
+                    if resume.contains(&node_index) {
+                        Some(
+                            base_changes_graph
+                                .node_weight(node_index)
+                                .cloned()
+                                .unwrap_or_default(),
+                        )
+                    } else {
+                        None
+                    }
+                },
+                |_edge_index, edge| Some(*edge),
+            );
+        }
 
         let mut changes_graph = base_changes_graph.filter_map(
             |_node_index, node| Some(node),
@@ -1102,6 +3446,62 @@ impl AutoBuildContext {
                 |_edge_index, edge| Some(*edge),
             );
         }
+        if scope.skip_deps || scope.only_changed || !scope.through.is_empty() {
+            changes_graph = changes_graph.filter_map(
+                |node_index, node| {
+                    if scope.skip_deps && !package_indices.contains(&node_index) {
+                        return None;
+                    }
+                    if scope.only_changed
+                        && !node.iter().any(|cause| {
+                            matches!(
+                                cause,
+                                DependencyChangeCause::PlanContextChanged { .. }
+                                    | DependencyChangeCause::NoBuiltArtifact
+                            )
+                        })
+                    {
+                        return None;
+                    }
+                    for through_index in scope.through.iter() {
+                        if *through_index != node_index
+                            && changes_graph.contains_node(*through_index)
+                            && algo::has_path_connecting(
+                                &changes_graph,
+                                node_index,
+                                *through_index,
+                                None,
+                            )
+                        {
+                            return None;
+                        }
+                    }
+                    Some(*node)
+                },
+                |_edge_index, edge| Some(*edge),
+            );
+        }
+        let skipped_plans =
+            self.broken_plans_skipped(&changes_graph.node_indices().collect::<HashSet<_>>());
+        let changes_graph = changes_graph.filter_map(
+            |node_index, node| {
+                if skipped_plans.contains_key(&node_index) {
+                    None
+                } else {
+                    Some(*node)
+                }
+            },
+            |_edge_index, edge| Some(*edge),
+        );
+        let mut skip_steps = skipped_plans
+            .into_iter()
+            .filter_map(|(node_index, skip)| {
+                self.dep_graph.build_graph[node_index]
+                    .plan_ctx()
+                    .map(|plan_ctx| SkipStep { plan_ctx, skip })
+            })
+            .collect::<Vec<_>>();
+        skip_steps.sort_by(|a, b| a.plan_ctx.id.cmp(&b.plan_ctx.id));
         let node_indices = changes_graph.node_indices().collect::<Vec<_>>();
         let mut check_deps = self.dep_graph.get_deps(
             &node_indices,
@@ -1136,26 +3536,7 @@ impl AutoBuildContext {
                 build_steps: build_order
                     .into_iter()
                     .map(|node_index| {
-                        let (studio, studio_package) = match self
-                            .node_dep_analysis(node_index, AnalysisType::StudioDependency)
-                            .unwrap()
-                            .pop()
-                        {
-                            Some(package_dep)
-                                if package_dep.matches_dep_ident(&self.studios.bootstrap) =>
-                            {
-                                (BuildStepStudio::Bootstrap, Some(&self.studios.bootstrap))
-                            }
-                            Some(package_dep)
-                                if package_dep.matches_dep_ident(&self.studios.standard) =>
-                            {
-                                (BuildStepStudio::Standard, Some(&self.studios.standard))
-                            }
-                            None => (BuildStepStudio::Native, None),
-                            Some(package_dep) => {
-                                panic!("Invalid studio dependency {:?}", package_dep);
-                            }
-                        };
+                        let (studio, studio_package) = self.node_studio(node_index);
                         let deps_to_install = self
                             .dep_graph
                             .get_deps(
@@ -1201,9 +3582,14 @@ impl AutoBuildContext {
                             .repos
                             .get(&plan_ctx.repo_id)
                             .expect("Plan must belong to a repo");
-                        let build_duration =
-                            store::build_time_get(connection, plan_ctx.id.as_ref())?
-                                .map(|value| Duration::seconds(value.duration_in_secs as i64));
+                        let studio_package =
+                            plan_studio_package(plan_ctx, repo_ctx, studio, studio_package)?;
+                        let build_duration = store::build_time_get(
+                            connection,
+                            self.store.workspace(),
+                            plan_ctx.id.as_ref(),
+                        )?
+                        .map(|value| Duration::seconds(value.duration_in_secs as i64));
                         let remote_deps = self
                             .dep_graph
                             .get_deps(
@@ -1236,14 +3622,422 @@ impl AutoBuildContext {
                             remote_deps,
                             causes: changes_graph[node_index].clone(),
                             build_duration,
+                            build_args: self.plan_build_args(plan_ctx),
+                            timeout_secs: plan_ctx
+                                .plan_config
+                                .as_ref()
+                                .and_then(|config| config.timeout_secs)
+                                .or(self.build_timeout_secs),
+                            retries: plan_ctx
+                                .plan_config
+                                .as_ref()
+                                .and_then(|config| config.retries)
+                                .unwrap_or(self.build_retries),
+                            docker_image: self.plan_docker_image(plan_ctx, studio)?,
+                            sandbox_command: &self.build_sandbox_command,
+                            cancellation: BuildCancellation::default(),
                         })
                     })
                     .collect::<Result<Vec<_>>>()?,
+                skip_steps,
             })
         })
     }
 
-    pub fn package_check(&self, package_index: NodeIndex) -> Result<PlanCheckStatus> {
+    /// Finds the artifact for a package, for local plans this is the latest artifact
+    /// built from the plan.
+    pub fn package_artifact(&self, package_index: NodeIndex) -> Result<Option<ArtifactContext>> {
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        match &self.dep_graph.build_graph[package_index] {
+            Dependency::ResolvedDep(ident) => artifact_cache.artifact(ident),
+            Dependency::RemoteDep(resolved_dep_ident) => {
+                artifact_cache.latest_artifact(resolved_dep_ident)
+            }
+            Dependency::LocalPlan(plan_ctx) => artifact_cache.latest_plan_artifact(&plan_ctx.id),
+        }
+    }
+
+    /// Finds the .hart file of the latest artifact built from a plan, looking in the
+    /// artifact cache, the hab-auto-build store and the artifacts folder.
+    pub fn latest_plan_artifact_path(&self, plan_ctx: &PlanContext) -> Option<PathBuf> {
+        let artifact_ident = self
+            .artifact_cache
+            .read()
+            .unwrap()
+            .latest_plan_minimal_artifact(&plan_ctx.id)?
+            .id
+            .clone();
+        self.artifact_path(&artifact_ident)
+    }
+
+    /// Finds the .hart file of an artifact, looking in the artifact cache, the
+    /// hab-auto-build store and the artifacts folder.
+    pub fn artifact_path(&self, artifact_ident: &PackageIdent) -> Option<PathBuf> {
+        let artifact_name = artifact_ident.artifact_name();
+        [
+            Some(
+                self.artifact_cache
+                    .read()
+                    .unwrap()
+                    .path
+                    .artifact_path(artifact_ident)
+                    .as_ref()
+                    .to_path_buf(),
+            ),
+            Some(
+                self.store
+                    .package_build_artifacts_path()
+                    .as_ref()
+                    .join(&artifact_name),
+            ),
+            self.artifacts_dir
+                .as_ref()
+                .map(|artifacts_dir| artifacts_dir.join(&artifact_name)),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|artifact_path| artifact_path.is_file())
+    }
+
+    /// Lists the files and links of an artifact. Files are recorded in the store when
+    /// the artifact is indexed, artifacts indexed by older versions of hab-auto-build
+    /// have their files read from the .hart file and recorded on first use.
+    pub fn artifact_files(&self, artifact: &ArtifactContext) -> Result<Vec<ArtifactFile>> {
+        if !artifact.files.is_empty() {
+            return Ok(artifact.files.clone());
+        }
+        let mut connection = self.store.get_connection()?;
+        if let Some(files) = store::artifact_files_get(&mut connection, &artifact.hash)? {
+            return Ok(files);
+        }
+        let artifact_name = artifact.id.artifact_name();
+        let artifact_path = [
+            self.artifact_cache
+                .read()
+                .unwrap()
+                .path
+                .artifact_path(&artifact.id)
+                .as_ref()
+                .to_path_buf(),
+            self.store
+                .package_build_artifacts_path()
+                .as_ref()
+                .join(&artifact_name),
+        ]
+        .into_iter()
+        .find(|artifact_path| artifact_path.is_file())
+        .ok_or_else(|| eyre!("Failed to find the .hart file of artifact {}", artifact.id))?;
+        debug!(
+            "Recording files of artifact {} from {}",
+            artifact.id,
+            artifact_path.display()
+        );
+        let files = ArtifactFile::read_all(&artifact_path).with_context(|| {
+            format!(
+                "Failed to read files of artifact '{}'",
+                artifact_path.display()
+            )
+        })?;
+        store::artifact_files_put(&mut connection, &artifact.hash, &files)?;
+        Ok(files)
+    }
+
+    /// Computes the installed size of artifacts and of their transitive runtime
+    /// dependencies. The sizes of complete closures are recorded in the store, so
+    /// that each artifact can be compared with the previous release of its package
+    /// even once that release has been removed from the artifact cache.
+    pub fn artifact_sizes(&self, artifacts: &[ArtifactContext]) -> Result<Vec<ArtifactSize>> {
+        let mut recorded_sizes = self
+            .store
+            .get_connection()?
+            .transaction(|connection| store::artifact_sizes_get(connection))?;
+        let mut artifact_sizes = Vec::new();
+        for artifact in artifacts {
+            let mut artifact_size = self.artifact_closure_size(artifact)?;
+            if artifact_size.missing_tdeps.is_empty() {
+                let recorded_size = RecordedArtifactSize {
+                    artifact: artifact.id.clone(),
+                    installed_size: artifact_size.installed_size,
+                    closure_size: artifact_size.closure_size,
+                };
+                self.store
+                    .get_connection()?
+                    .immediate_transaction(|connection| {
+                        store::artifact_size_put(connection, &recorded_size)
+                    })?;
+                recorded_sizes.push(recorded_size);
+            }
+            artifact_size.previous = recorded_sizes
+                .iter()
+                .filter(|recorded_size| {
+                    recorded_size.artifact.origin == artifact.id.origin
+                        && recorded_size.artifact.name == artifact.id.name
+                        && recorded_size.artifact.target == artifact.id.target
+                        && recorded_size.artifact.release < artifact.id.release
+                })
+                .max_by(|a, b| a.artifact.release.cmp(&b.artifact.release))
+                .cloned();
+            // Releases built before sizes were recorded are looked up in the artifact cache
+            if artifact_size.previous.is_none() {
+                let previous_artifact = self
+                    .artifact_cache
+                    .read()
+                    .unwrap()
+                    .previous_artifact(&artifact.id)?;
+                if let Some(previous_artifact) = previous_artifact {
+                    let previous_size = self.artifact_closure_size(&previous_artifact)?;
+                    if previous_size.missing_tdeps.is_empty() {
+                        let recorded_size = RecordedArtifactSize {
+                            artifact: previous_artifact.id.clone(),
+                            installed_size: previous_size.installed_size,
+                            closure_size: previous_size.closure_size,
+                        };
+                        self.store
+                            .get_connection()?
+                            .immediate_transaction(|connection| {
+                                store::artifact_size_put(connection, &recorded_size)
+                            })?;
+                        recorded_sizes.push(recorded_size.clone());
+                        artifact_size.previous = Some(recorded_size);
+                    }
+                }
+            }
+            artifact_sizes.push(artifact_size);
+        }
+        Ok(artifact_sizes)
+    }
+
+    fn artifact_closure_size(&self, artifact: &ArtifactContext) -> Result<ArtifactSize> {
+        let installed_size = artifact.installed_size;
+        let mut tdeps = Vec::new();
+        let mut missing_tdeps = Vec::new();
+        for tdep in artifact.tdeps.iter() {
+            let tdep_artifact = self.artifact_cache.read().unwrap().artifact(tdep)?;
+            match tdep_artifact {
+                Some(tdep_artifact) => tdeps.push((tdep.clone(), tdep_artifact.installed_size)),
+                None => missing_tdeps.push(tdep.clone()),
+            }
+        }
+        tdeps.sort_by(|(a_ident, a_size), (b_ident, b_size)| {
+            b_size.cmp(a_size).then_with(|| a_ident.cmp(b_ident))
+        });
+        missing_tdeps.sort();
+        Ok(ArtifactSize {
+            artifact: artifact.id.clone(),
+            installed_size,
+            closure_size: installed_size + tdeps.iter().map(|(_, size)| size).sum::<u64>(),
+            tdeps,
+            missing_tdeps,
+            previous: None,
+        })
+    }
+
+    /// Resolves every remote dependency of the build graph to the latest matching
+    /// artifact in the artifact cache. Dependencies for which no artifact is available
+    /// are returned separately.
+    pub fn dependency_lock(&self) -> (DependencyLock, Vec<String>) {
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        let mut dependency_lock = DependencyLock::default();
+        let mut unresolved = Vec::new();
+        for node_index in self.dep_graph.build_graph.node_indices() {
+            let (dependency, artifact_ident) = match &self.dep_graph.build_graph[node_index] {
+                Dependency::ResolvedDep(ident) => (ident.to_string(), Some(ident.clone())),
+                Dependency::RemoteDep(resolved_dep_ident) => (
+                    resolved_dep_ident.to_string(),
+                    artifact_cache
+                        .latest_minimal_artifact(resolved_dep_ident)
+                        .map(|artifact| artifact.id.clone()),
+                ),
+                Dependency::LocalPlan(_) => continue,
+            };
+            match artifact_ident {
+                Some(artifact_ident) => {
+                    dependency_lock.dependencies.insert(
+                        dependency,
+                        format!(
+                            "{}/{}/{}/{}",
+                            artifact_ident.origin,
+                            artifact_ident.name,
+                            artifact_ident.version,
+                            artifact_ident.release
+                        ),
+                    );
+                }
+                None => unresolved.push(dependency),
+            }
+        }
+        unresolved.sort();
+        unresolved.dedup();
+        (dependency_lock, unresolved)
+    }
+
+    /// Resolves the remote dependencies of the build graph for a target to the latest
+    /// packages in the Builder channel, replacing them with the resolved packages.
+    /// Dependencies that cannot be resolved are left as is and returned along with the
+    /// reason.
+    pub fn remote_deps_resolve(
+        &mut self,
+        builder: &BuilderClient,
+        target: PackageTarget,
+    ) -> Vec<(PackageResolvedDepIdent, String)> {
+        let remote_deps = self
+            .dep_graph
+            .build_graph
+            .node_indices()
+            .filter_map(|node_index| match &self.dep_graph.build_graph[node_index] {
+                Dependency::RemoteDep(resolved_dep_ident)
+                    if resolved_dep_ident.target == target =>
+                {
+                    Some((node_index, resolved_dep_ident.clone()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let resolutions = remote_deps
+            .into_par_iter()
+            .map(|(node_index, resolved_dep_ident)| {
+                let resolution = builder.latest_package(&resolved_dep_ident);
+                (node_index, resolved_dep_ident, resolution)
+            })
+            .collect::<Vec<_>>();
+        let mut unresolved = Vec::new();
+        for (node_index, resolved_dep_ident, resolution) in resolutions {
+            match resolution {
+                Ok(Some(ident)) => {
+                    debug!("Resolved {} to {}", resolved_dep_ident, ident);
+                    self.dep_graph.build_graph[node_index] = Dependency::ResolvedDep(ident);
+                }
+                Ok(None) => unresolved.push((
+                    resolved_dep_ident,
+                    format!("not found in channel '{}'", builder.channel()),
+                )),
+                Err(err) => unresolved.push((resolved_dep_ident, format!("{:#}", err))),
+            }
+        }
+        unresolved.sort_by(|a, b| a.0.cmp(&b.0));
+        unresolved
+    }
+
+    /// Collects the given artifacts along with all their transitive runtime dependencies
+    /// for inclusion in a software bill of materials.
+    pub fn sbom_packages(&self, artifacts: &[ArtifactContext]) -> Result<SbomPackages> {
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        let mut sbom_packages = SbomPackages::default();
+        for artifact in artifacts {
+            sbom_packages.roots.push(artifact.id.clone());
+            sbom_packages
+                .packages
+                .insert(artifact.id.clone(), Some(artifact.clone()));
+        }
+        for artifact in artifacts {
+            for tdep in artifact.tdeps.iter() {
+                if !sbom_packages.packages.contains_key(tdep) {
+                    sbom_packages
+                        .packages
+                        .insert(tdep.clone(), artifact_cache.artifact(tdep)?);
+                }
+            }
+        }
+        Ok(sbom_packages)
+    }
+
+    /// Exports an artifact along with its transitive runtime dependencies from the
+    /// artifact cache as an OCI image archive.
+    pub fn oci_image_export(
+        &self,
+        artifact: &ArtifactContext,
+        tag: &str,
+        output_path: &Path,
+    ) -> Result<OciImageExport> {
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        let artifact_path = |artifact_ident: &PackageIdent| {
+            let artifact_path = artifact_cache
+                .path
+                .as_ref()
+                .join(artifact_ident.artifact_name());
+            if artifact_path.is_file() {
+                Ok(artifact_path)
+            } else {
+                Err(eyre!(
+                    "No artifact found for {} in the artifact cache at '{}'",
+                    artifact_ident,
+                    artifact_cache.path.as_ref().display()
+                ))
+            }
+        };
+        let tdeps = artifact
+            .tdeps
+            .iter()
+            .map(|tdep| Ok((tdep.clone(), artifact_path(tdep)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let tmp_dir = self.store.temp_dir("oci-export")?;
+        oci_image_export(
+            artifact,
+            &artifact_path(&artifact.id)?,
+            &tdeps,
+            tag,
+            tmp_dir.path(),
+            output_path,
+        )
+    }
+
+    /// Exports the store database along with the source archives referenced by the
+    /// plans and, if requested, the latest artifacts built from them to a tar archive.
+    pub fn store_export(
+        &self,
+        include_sources: bool,
+        include_artifacts: bool,
+        output_path: &Path,
+    ) -> Result<StoreArchiveSummary> {
+        let plan_ctxs = self
+            .dep_graph
+            .build_graph
+            .node_weights()
+            .filter_map(|dep| dep.plan_ctx())
+            .collect::<Vec<_>>();
+        let source_paths = if include_sources {
+            plan_ctxs
+                .iter()
+                .filter_map(|plan_ctx| plan_ctx.source.as_ref())
+                .map(|source| {
+                    self.store
+                        .package_source_store_path(&source.shasum)
+                        .as_ref()
+                        .to_path_buf()
+                })
+                .filter(|source_path| source_path.is_dir())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+        let artifact_paths = if include_artifacts {
+            let artifact_cache = self.artifact_cache.read().unwrap();
+            plan_ctxs
+                .iter()
+                .filter_map(|plan_ctx| artifact_cache.latest_plan_minimal_artifact(&plan_ctx.id))
+                .map(|artifact| {
+                    artifact_cache
+                        .path
+                        .as_ref()
+                        .join(artifact.id.artifact_name())
+                })
+                .filter(|artifact_path| artifact_path.is_file())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+        store_archive_export(&self.store, &source_paths, &artifact_paths, output_path)
+    }
+
+    pub fn package_check(
+        &self,
+        package_index: NodeIndex,
+        rule_profile: Option<&RuleProfile>,
+    ) -> Result<PlanCheckStatus> {
         let mut artifact_cache = self.artifact_cache.write().unwrap();
         let (plan_config_path, plan_config, artifact) = {
             match &self.dep_graph.build_graph[package_index] {
@@ -1264,11 +4058,15 @@ impl AutoBuildContext {
                 ),
             }
         };
+        let plan_config = match rule_profile {
+            Some(rule_profile) => plan_config.with_rule_profile(rule_profile),
+            None => plan_config,
+        };
         let source_violations = match self.download_dep_source(package_index, true)? {
-            DownloadStatus::Downloaded(_source_ctx, _plan_ctx, _, _, source_violations) => {
+            DownloadStatus::Downloaded(_source_ctx, _plan_ctx, _, _, _, source_violations) => {
                 Some(source_violations)
             }
-            DownloadStatus::AlreadyDownloaded(_source_ctx, _plan_ctx, _, source_violations) => {
+            DownloadStatus::AlreadyDownloaded(_source_ctx, _plan_ctx, _, _, source_violations) => {
                 Some(source_violations)
             }
             DownloadStatus::MissingSource(_) | DownloadStatus::InvalidArchive(_, _, _, _) => None,
@@ -1277,15 +4075,19 @@ impl AutoBuildContext {
             }
         };
         let artifact_violations = if let Some(artifact) = artifact {
-            let checker = Checker::new();
+            let checker = Checker::new(artifact.target);
             let mut checker_context = CheckerContext::default();
-            Some(checker.artifact_context_check(
+            let mut artifact_violations = checker.artifact_context_check(
                 &self.store,
                 &plan_config,
                 &mut checker_context,
                 &mut artifact_cache,
                 &artifact,
-            ))
+            );
+            if let Dependency::LocalPlan(plan_ctx) = &self.dep_graph.build_graph[package_index] {
+                artifact_violations.extend(plan_source_check(&plan_config, plan_ctx, &artifact));
+            }
+            Some(artifact_violations)
         } else {
             None
         };
@@ -1296,23 +4098,276 @@ impl AutoBuildContext {
         ))
     }
 
+    /// Checks a plan before it is built. The sources are checked as usual, while
+    /// the artifact violations are predicted from the latest artifact built for
+    /// the plan, using the dependencies currently declared by the plan.
+    pub fn package_pre_build_check(
+        &self,
+        package_index: NodeIndex,
+        rule_profile: Option<&RuleProfile>,
+    ) -> Result<PlanCheckStatus> {
+        let Dependency::LocalPlan(plan_ctx) = &self.dep_graph.build_graph[package_index] else {
+            return Err(eyre!(
+                "Cannot pre-build check dependencies that are not plans"
+            ));
+        };
+        let artifact = self
+            .artifact_cache
+            .write()
+            .unwrap()
+            .latest_plan_artifact(&plan_ctx.id)?;
+        let source_violations = match self.download_dep_source(package_index, true)? {
+            DownloadStatus::Downloaded(_source_ctx, _plan_ctx, _, _, _, source_violations) => {
+                source_violations
+            }
+            DownloadStatus::AlreadyDownloaded(_source_ctx, _plan_ctx, _, _, source_violations) => {
+                source_violations
+            }
+            DownloadStatus::MissingSource(_)
+            | DownloadStatus::InvalidArchive(_, _, _, _)
+            | DownloadStatus::NoSource => Vec::new(),
+        };
+        let plan_config = match rule_profile {
+            Some(rule_profile) => plan_ctx.config().with_rule_profile(rule_profile),
+            None => plan_ctx.config(),
+        };
+        let artifact_violations = artifact
+            .map(|artifact| removed_dependency_check(&plan_config, &artifact, &plan_ctx.deps))
+            .unwrap_or_default();
+        Ok(PlanCheckStatus::CheckSucceeded(
+            Some(plan_ctx.plan_path.plan_config_path()),
+            source_violations,
+            artifact_violations,
+        ))
+    }
+
+    /// Finds the plans among the given packages whose latest artifact was built from
+    /// a plan source that differs from the plan file in the repo.
+    pub fn plan_source_divergences(
+        &self,
+        package_indices: &[NodeIndex],
+    ) -> Result<Vec<(&PlanContext, PackageIdent)>> {
+        let artifact_cache = self.artifact_cache.write().unwrap();
+        let mut divergences = Vec::new();
+        for package_index in package_indices {
+            let Some(plan_ctx) = self.dep_graph.dep(*package_index).plan_ctx() else {
+                continue;
+            };
+            let Some(artifact) = artifact_cache.latest_plan_artifact(&plan_ctx.id)? else {
+                continue;
+            };
+            if let Some(plan_source) = artifact.plan_source.as_ref() {
+                if !plan_ctx.plan_source_matches(plan_source)? {
+                    divergences.push((plan_ctx, artifact.id.clone()));
+                }
+            }
+        }
+        Ok(divergences)
+    }
+
+    /// Returns the artifacts built in the last build session, along with the
+    /// number of error-level violations found when checking each of them.
+    pub fn last_build_session(&self) -> Result<Vec<(PackageIdent, usize)>> {
+        let mut connection = self.store.get_connection()?;
+        store::build_session_last_get(&mut connection, self.store.workspace())
+    }
+
+    /// Records all the steps of the current session as pending
+    pub fn build_steps_pending_put(&self, build_steps: &[BuildStep<'_>]) -> Result<()> {
+        let now = Utc::now();
+        self.store.get_connection()?.transaction(|connection| {
+            for build_step in build_steps {
+                store::build_step_state_put(
+                    connection,
+                    self.store.workspace(),
+                    &self.session_id,
+                    &build_step.plan_ctx.id.to_string(),
+                    BuildStepStatus::Pending,
+                    now,
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Records the progress of a step of the current session, so other processes
+    /// sharing the store can follow the build.
+    pub fn build_step_status_put(
+        &self,
+        build_step: &BuildStep<'_>,
+        status: BuildStepStatus,
+    ) -> Result<()> {
+        let mut connection = self.store.get_connection()?;
+        store::build_step_state_put(
+            &mut connection,
+            self.store.workspace(),
+            &self.session_id,
+            &build_step.plan_ctx.id.to_string(),
+            status,
+            Utc::now(),
+        )
+    }
+
+    /// Returns the id and step states of the most recent build session
+    pub fn last_build_session_step_states(&self) -> Result<Option<(String, Vec<BuildStepState>)>> {
+        let mut connection = self.store.get_connection()?;
+        store::build_step_states_last_get(&mut connection, self.store.workspace())
+    }
+
+    /// Returns the plans that the most recent build session did not build, either
+    /// because they failed or because the session was interrupted, along with the
+    /// ones that are not part of the workspace anymore. Returns nothing if no
+    /// session was recorded.
+    pub fn build_session_resume_plans(&self) -> Result<Option<(Vec<NodeIndex>, Vec<String>)>> {
+        let Some((_, step_states)) = self.last_build_session_step_states()? else {
+            return Ok(None);
+        };
+        let plan_node_ids = self.dep_graph_plan_node_ids();
+        let mut plans = Vec::new();
+        let mut missing_plans = Vec::new();
+        for step_state in step_states {
+            if step_state.status == BuildStepStatus::Built {
+                continue;
+            }
+            match plan_node_ids.get(&step_state.plan) {
+                Some(node_id) => plans.push(NodeIndex::new(*node_id as usize)),
+                None => missing_plans.push(step_state.plan),
+            }
+        }
+        Ok(Some((plans, missing_plans)))
+    }
+
+    /// Records the start of the current build session, so it shows up in the build history
+    pub fn build_session_start(&self, build_steps: &[BuildStep<'_>]) -> Result<()> {
+        let mut connection = self.store.get_connection()?;
+        store::build_session_start(
+            &mut connection,
+            self.store.workspace(),
+            &self.session_id,
+            Utc::now(),
+            build_steps.len(),
+        )
+    }
+
+    /// Records the end of the current build session along with the outcome of its steps
+    pub fn build_session_end(&self) -> Result<()> {
+        self.store.get_connection()?.transaction(|connection| {
+            store::build_session_end(
+                connection,
+                self.store.workspace(),
+                &self.session_id,
+                Utc::now(),
+            )
+        })
+    }
+
+    /// Returns the duration in seconds of the most recent build of every plan that was built
+    pub fn build_times(&self) -> Result<Vec<(String, u64)>> {
+        let mut connection = self.store.get_connection()?;
+        Ok(
+            store::build_times_get_all(&mut connection, self.store.workspace())?
+                .into_iter()
+                .map(|build_time| (build_time.build_ident, build_time.duration_in_secs as u64))
+                .collect(),
+        )
+    }
+
+    /// Returns the recorded build sessions, most recent first
+    pub fn build_sessions(&self, limit: Option<usize>) -> Result<Vec<BuildSession>> {
+        let mut connection = self.store.get_connection()?;
+        store::build_sessions_get(&mut connection, self.store.workspace(), limit)
+    }
+
+    pub fn build_session(&self, id: i32) -> Result<Option<BuildSession>> {
+        let mut connection = self.store.get_connection()?;
+        store::build_session_get(&mut connection, self.store.workspace(), id)
+    }
+
+    /// Returns the state of the steps of a recorded build session
+    pub fn build_session_step_states(
+        &self,
+        build_session: &BuildSession,
+    ) -> Result<Vec<BuildStepState>> {
+        let mut connection = self.store.get_connection()?;
+        store::build_step_states_get(
+            &mut connection,
+            self.store.workspace(),
+            &build_session.session_id,
+        )
+    }
+
+    /// Returns the artifacts built by a recorded build session
+    pub fn build_session_artifacts(
+        &self,
+        build_session: &BuildSession,
+    ) -> Result<Vec<BuildSessionArtifact>> {
+        let mut connection = self.store.get_connection()?;
+        store::build_session_artifacts_get(
+            &mut connection,
+            self.store.workspace(),
+            &build_session.session_id,
+        )
+    }
+
+    pub fn studio_cleanup_policy(&self) -> StudioCleanupPolicy {
+        self.studio_cleanup
+    }
+
+    /// Removes the studios used by builds in the current session, returns the
+    /// roots of the studios that were removed.
+    pub fn session_studios_cleanup(&self) -> Result<Vec<PathBuf>> {
+        let mut session_studio_roots = self.session_studio_roots.lock().unwrap();
+        let mut removed_studio_roots = Vec::new();
+        while let Some(studio_root) = session_studio_roots.pop_first() {
+            if studio_root.is_dir() {
+                habitat::studio_remove(&studio_root, &self.build_sandbox_command)?;
+                removed_studio_roots.push(studio_root);
+            }
+        }
+        Ok(removed_studio_roots)
+    }
+
     pub fn build_step_execute(
         &self,
         build_step: &BuildStep<'_>,
     ) -> Result<BuildStepResult, BuildStepError> {
         let mut artifact_cache = self.artifact_cache.write().unwrap();
-        let start = Instant::now();
-        let build_output = {
-            match build_step.studio {
-                BuildStepStudio::Native => {
-                    habitat::native_package_build(build_step, &artifact_cache, &self.store)?
-                }
-                BuildStepStudio::Bootstrap => {
-                    habitat::bootstrap_package_build(build_step, &artifact_cache, &self.store, 1)?
-                }
-                BuildStepStudio::Standard => {
-                    habitat::standard_package_build(build_step, &artifact_cache, &self.store, 1)?
+        let mut start = Instant::now();
+        // The plan context is hashed before building, so files created during the build are not included
+        let plan_context_hash = build_step.plan_ctx.context_hash()?;
+        let mut attempt = 0;
+        let build_output = loop {
+            if build_step.studio != BuildStepStudio::Native
+                && build_step.docker_image.is_none()
+                && self.build_executor.is_local()
+            {
+                self.session_studio_roots
+                    .lock()
+                    .unwrap()
+                    .insert(habitat::build_studio_root(1).as_ref().to_path_buf());
+            }
+            let build_result =
+                self.build_executor
+                    .package_build(build_step, &artifact_cache, &self.store, 1);
+            match build_result {
+                Ok(build_output) => break build_output,
+                Err(
+                    err @ (BuildError::Native(..)
+                    | BuildError::Bootstrap(..)
+                    | BuildError::Standard(..)
+                    | BuildError::TimedOut(..)),
+                ) if attempt < build_step.retries => {
+                    attempt += 1;
+                    info!(target: "user-log",
+                        "{}: {}, retrying ({} of {})",
+                        "warning".bold().yellow(),
+                        err,
+                        attempt,
+                        build_step.retries
+                    );
+                    start = Instant::now();
                 }
+                Err(err) => return Err(err.into()),
             }
         };
         // Add the artifact to the cache
@@ -1321,8 +4376,9 @@ impl AutoBuildContext {
             LazyArtifactContext::Loaded(build_output.artifact),
         )?;
         let artifact_ctx = artifact_cache.artifact(&artifact_ident)?.unwrap();
+        let artifact_path = self.artifact_export(&artifact_ident)?;
         // Check the artifact for violations
-        let checker = Checker::new();
+        let checker = Checker::new(artifact_ctx.target);
         let mut checker_context = CheckerContext::default();
         let artifact_violations = checker.artifact_context_check(
             &self.store,
@@ -1332,18 +4388,452 @@ impl AutoBuildContext {
             &artifact_ctx,
         );
         let elapsed_duration_in_secs = start.elapsed().as_secs() as i32;
+        let build_disk_usage = self.build_disk_usage(build_step);
         self.store.get_connection()?.transaction(|connection| {
             store::build_time_put(
                 connection,
+                self.store.workspace(),
                 build_step.plan_ctx.id.as_ref(),
                 elapsed_duration_in_secs,
+            )?;
+            if let Some(build_disk_usage) = build_disk_usage {
+                store::build_disk_usage_put(
+                    connection,
+                    self.store.workspace(),
+                    &disk_usage_package(build_step.plan_ctx),
+                    build_disk_usage,
+                )?;
+            }
+            // Artifacts built on a remote worker are not tied to the current host
+            if build_step.studio == BuildStepStudio::Native || self.build_executor.is_local() {
+                store::build_host_put(connection, &artifact_ident, &CURRENT_BUILD_HOST)?;
+            }
+            store::plan_context_hash_put(connection, &artifact_ident, &plan_context_hash)?;
+            store::build_session_artifact_put(
+                connection,
+                self.store.workspace(),
+                &self.session_id,
+                &artifact_ident,
+                &artifact_path,
+                artifact_violations
+                    .iter()
+                    .filter(|violation| violation.level == ViolationLevel::Error)
+                    .count(),
+                artifact_violations
+                    .iter()
+                    .filter(|violation| violation.level == ViolationLevel::Warn)
+                    .count(),
             )
         })?;
 
         Ok(BuildStepResult {
             artifact_ident,
             artifact_violations,
+            artifact_path,
             build_log: build_output.build_log,
         })
     }
+
+    /// Disk space left behind by a build on the current host: the studio of
+    /// bootstrap and standard builds, or the source folder of native builds.
+    /// Builds in Docker or on a remote worker are not measured.
+    fn build_disk_usage(&self, build_step: &BuildStep) -> Option<u64> {
+        let build_path = match build_step.studio {
+            BuildStepStudio::Native => {
+                if build_step
+                    .plan_ctx
+                    .plan_config
+                    .as_ref()
+                    .is_some_and(|config| config.docker_image.is_some())
+                {
+                    return None;
+                }
+                let id = build_step.plan_ctx.id.as_ref();
+                HabitatRootPath::default()
+                    .source_cache()
+                    .as_ref()
+                    .join(format!("{}-{}", id.name, id.version))
+            }
+            BuildStepStudio::Bootstrap | BuildStepStudio::Standard
+                if build_step.docker_image.is_none() && self.build_executor.is_local() =>
+            {
+                habitat::build_studio_root(1).as_ref().to_path_buf()
+            }
+            BuildStepStudio::Bootstrap | BuildStepStudio::Standard => return None,
+        };
+        Some(disk_usage(build_path)).filter(|disk_usage| *disk_usage > 0)
+    }
+
+    /// Estimates the disk space needed by the builds of a build plan on each file
+    /// system they write to, from the sizes of the previous artifacts of the plans
+    /// and the disk space used by their previous builds
+    #[cfg(not(target_os = "windows"))]
+    pub fn disk_space_estimate(&self, build_steps: &[BuildStep]) -> Result<DiskSpaceEstimate> {
+        let mut connection = self.store.get_connection()?;
+        let build_disk_usages =
+            store::build_disk_usages_get_all(&mut connection, self.store.workspace())?;
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        let mut artifacts_size = 0;
+        let mut installed_size = 0;
+        let mut native_build_size = 0;
+        let mut studio_build_size = 0;
+        let mut unestimated_steps = 0;
+        for build_step in build_steps {
+            let artifact = artifact_cache
+                .latest_plan_minimal_artifact(&build_step.plan_ctx.id)
+                .map(|artifact| artifact_cache.artifact(&artifact.id))
+                .transpose()?
+                .flatten();
+            let build_disk_usage = build_disk_usages.get(&disk_usage_package(build_step.plan_ctx));
+            if artifact.is_none() && build_disk_usage.is_none() {
+                unestimated_steps += 1;
+                continue;
+            }
+            if let Some(artifact) = artifact {
+                artifacts_size += disk_usage(
+                    artifact_cache
+                        .path
+                        .as_ref()
+                        .join(artifact.id.artifact_name()),
+                );
+                installed_size += artifact.installed_size;
+            }
+            // Studios are reused from one build to the next, while native builds
+            // leave their sources behind
+            match build_step.studio {
+                BuildStepStudio::Native => {
+                    native_build_size += build_disk_usage.copied().unwrap_or_default()
+                }
+                BuildStepStudio::Bootstrap | BuildStepStudio::Standard => {
+                    studio_build_size =
+                        studio_build_size.max(build_disk_usage.copied().unwrap_or_default())
+                }
+            }
+        }
+
+        // Built artifacts are written to the store, then added to the artifact
+        // cache and installed in the Habitat root along with the build folders
+        let habitat_root = HabitatRootPath::default();
+        let mut requirements: Vec<DiskSpaceRequirement> = Vec::new();
+        for (path, required) in [
+            (
+                self.store
+                    .package_build_artifacts_path()
+                    .as_ref()
+                    .to_path_buf(),
+                artifacts_size,
+            ),
+            (
+                habitat_root.as_ref().to_path_buf(),
+                artifacts_size + installed_size + native_build_size + studio_build_size,
+            ),
+        ] {
+            let (device, available) = file_system_space(&path)?;
+            match requirements
+                .iter_mut()
+                .find(|requirement| requirement.device == device)
+            {
+                Some(requirement) => {
+                    requirement.paths.push(path);
+                    requirement.required += required;
+                }
+                None => requirements.push(DiskSpaceRequirement {
+                    device,
+                    paths: vec![path],
+                    required,
+                    available,
+                }),
+            }
+        }
+        Ok(DiskSpaceEstimate {
+            requirements,
+            unestimated_steps,
+        })
+    }
+
+    /// Identifies a build plan, workers that generate the same build plan for a
+    /// cooperative build share their claims on its steps. The content of the
+    /// plans is part of the id, so editing a plan starts a new build.
+    pub fn build_plan_id(&self, build_steps: &[BuildStep<'_>]) -> Result<String> {
+        let mut hasher = blake3::Hasher::new();
+        for step in build_steps {
+            hasher.update(step.plan_ctx.id.to_string().as_bytes());
+            hasher.update(&[0]);
+            hasher.update(step.plan_ctx.context_hash()?.as_ref().as_bytes());
+            hasher.update(b"\n");
+        }
+        Ok(hasher.finalize().to_hex()[..16].to_string())
+    }
+
+    /// Returns the positions of the build steps that must be completed before
+    /// each build step can start.
+    pub fn build_step_dependencies(&self, build_steps: &[BuildStep<'_>]) -> Vec<Vec<usize>> {
+        let step_positions = build_steps
+            .iter()
+            .enumerate()
+            .map(|(position, step)| (step.index, position))
+            .collect::<HashMap<_, _>>();
+        build_steps
+            .iter()
+            .map(|step| {
+                self.dep_graph
+                    .get_deps(
+                        Some(step.index).iter(),
+                        [
+                            DependencyType::Build,
+                            DependencyType::Runtime,
+                            DependencyType::Studio,
+                        ]
+                        .into_iter()
+                        .collect(),
+                        DependencyDepth::Transitive,
+                        DependencyDirection::Forward,
+                        false,
+                        false,
+                    )
+                    .into_iter()
+                    .filter_map(|node_index| step_positions.get(&node_index).copied())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Groups the positions of the build steps into stages of steps that can be
+    /// built in parallel, each step is placed in the stage following the last stage
+    /// of the steps it depends on. Dependencies on later steps only come from ignored
+    /// dependency cycles and are left out, like in the build order.
+    pub fn build_step_stages(&self, build_steps: &[BuildStep<'_>]) -> Vec<Vec<usize>> {
+        let step_dependencies = self.build_step_dependencies(build_steps);
+        let mut step_stages: Vec<usize> = Vec::with_capacity(build_steps.len());
+        let mut stages: Vec<Vec<usize>> = Vec::new();
+        for (position, dependencies) in step_dependencies.iter().enumerate() {
+            let stage = dependencies
+                .iter()
+                .filter(|dependency| **dependency < position)
+                .map(|dependency| step_stages[*dependency] + 1)
+                .max()
+                .unwrap_or_default();
+            step_stages.push(stage);
+            if stages.len() <= stage {
+                stages.resize_with(stage + 1, Vec::new);
+            }
+            stages[stage].push(position);
+        }
+        stages
+    }
+
+    /// Prepares the claims of a cooperative build for a worker joining it. The
+    /// failed claims of the build are removed so a rerun retries their steps, and
+    /// the claims of builds that ended long ago are pruned. Returns the number of
+    /// failed claims removed.
+    pub fn build_claims_join(&self, build_id: &str) -> Result<usize> {
+        self.store
+            .get_connection()?
+            .immediate_transaction(|connection| {
+                store::build_claims_prune(
+                    connection,
+                    Utc::now() - Duration::days(BUILD_CLAIM_RETENTION_DAYS),
+                )?;
+                store::build_claims_failed_delete(connection, build_id)
+            })
+    }
+
+    /// Returns the claims on the steps of a cooperative build, by plan
+    pub fn build_claims(&self, build_id: &str) -> Result<HashMap<String, BuildClaim>> {
+        Ok(self
+            .store
+            .get_connection()?
+            .transaction(|connection| store::build_claims_get(connection, build_id))?
+            .into_iter()
+            .map(|claim| (claim.plan.clone(), claim))
+            .collect())
+    }
+
+    /// Atomically claims a step of a cooperative build for a worker, returns
+    /// whether the claim succeeded.
+    pub fn build_step_claim(
+        &self,
+        build_id: &str,
+        worker: &str,
+        build_step: &BuildStep<'_>,
+        lease_timeout: Duration,
+    ) -> Result<bool> {
+        let now = Utc::now();
+        self.store
+            .get_connection()?
+            .immediate_transaction(|connection| {
+                store::build_claim_put(
+                    connection,
+                    build_id,
+                    &build_step.plan_ctx.id.to_string(),
+                    worker,
+                    now,
+                    now + lease_timeout,
+                )
+            })
+    }
+
+    /// Extends the lease on a claimed step, returns false if the claim has been
+    /// taken over by another worker.
+    pub fn build_step_claim_renew(
+        &self,
+        build_id: &str,
+        worker: &str,
+        build_step: &BuildStep<'_>,
+        lease_timeout: Duration,
+    ) -> Result<bool> {
+        self.store
+            .get_connection()?
+            .immediate_transaction(|connection| {
+                store::build_claim_renew(
+                    connection,
+                    build_id,
+                    &build_step.plan_ctx.id.to_string(),
+                    worker,
+                    Utc::now() + lease_timeout,
+                )
+            })
+    }
+
+    pub fn build_step_claim_release(
+        &self,
+        build_id: &str,
+        worker: &str,
+        build_step: &BuildStep<'_>,
+        status: &BuildClaimStatus,
+    ) -> Result<()> {
+        self.store
+            .get_connection()?
+            .immediate_transaction(|connection| {
+                store::build_claim_release(
+                    connection,
+                    build_id,
+                    &build_step.plan_ctx.id.to_string(),
+                    worker,
+                    status,
+                    Utc::now(),
+                )
+            })
+    }
+
+    /// Adds artifacts built by other workers in the shared store to the local
+    /// artifact cache, returns the artifacts that were imported.
+    pub fn artifacts_import(&self, artifact_idents: &[PackageIdent]) -> Result<Vec<PackageIdent>> {
+        let artifact_cache = self.artifact_cache.read().unwrap();
+        let mut imported_artifacts = Vec::new();
+        for artifact_ident in artifact_idents {
+            if artifact_cache.minimal_artifact(artifact_ident).is_some() {
+                continue;
+            }
+            let artifact_path = artifact_cache.path.artifact_path(artifact_ident);
+            if !artifact_path.as_ref().is_file() {
+                let shared_artifact_path = self
+                    .store
+                    .package_build_artifacts_path()
+                    .as_ref()
+                    .join(artifact_ident.artifact_name());
+                debug!(
+                    "Importing artifact {} from {}",
+                    artifact_ident,
+                    shared_artifact_path.display()
+                );
+                std::fs::copy(&shared_artifact_path, artifact_path.as_ref()).with_context(
+                    || {
+                        format!(
+                            "Failed to copy artifact {} from the hab-auto-build store to {}",
+                            shared_artifact_path.display(),
+                            artifact_path.as_ref().display()
+                        )
+                    },
+                )?;
+            }
+            let artifact_ctx = ArtifactContext::read_from_disk(artifact_path.as_ref(), None)?;
+            artifact_cache.artifact_add(&self.store, LazyArtifactContext::Loaded(artifact_ctx))?;
+            imported_artifacts.push(artifact_ident.clone());
+        }
+        Ok(imported_artifacts)
+    }
+
+    pub fn yanked_artifacts(&self) -> Result<Vec<YankedArtifact>> {
+        self.store
+            .get_connection()?
+            .transaction(|connection| store::yanked_artifacts_get(connection))
+    }
+
+    /// Excludes an artifact in the artifact cache from dependency resolution
+    /// without removing it, the artifact is excluded in future runs as well.
+    pub fn artifact_yank(&self, artifact_ident: &PackageIdent, reason: Option<&str>) -> Result<()> {
+        let mut artifact_cache = self.artifact_cache.write().unwrap();
+        if artifact_cache.minimal_artifact(artifact_ident).is_none() {
+            return Err(eyre!(
+                "No artifact found for {} in the artifact cache",
+                artifact_ident
+            ));
+        }
+        self.store
+            .get_connection()?
+            .immediate_transaction(|connection| {
+                store::yanked_artifact_put(connection, artifact_ident, reason, Utc::now())
+            })?;
+        artifact_cache.artifact_yank(artifact_ident);
+        Ok(())
+    }
+
+    /// Makes a yanked artifact available for dependency resolution again,
+    /// returns whether the artifact was yanked.
+    pub fn artifact_unyank(&self, artifact_ident: &PackageIdent) -> Result<bool> {
+        let mut artifact_cache = self.artifact_cache.write().unwrap();
+        let was_yanked = self
+            .store
+            .get_connection()?
+            .immediate_transaction(|connection| {
+                store::yanked_artifact_delete(connection, artifact_ident)
+            })?;
+        artifact_cache.artifact_unyank(artifact_ident);
+        Ok(was_yanked)
+    }
+
+    /// Places a newly built artifact in the artifacts folder if one is configured,
+    /// returns the final location of the artifact.
+    fn artifact_export(&self, artifact_ident: &PackageIdent) -> Result<PathBuf> {
+        let artifact_path = self
+            .store
+            .package_build_artifacts_path()
+            .as_ref()
+            .join(artifact_ident.artifact_name());
+        let Some(artifacts_dir) = self.artifacts_dir.as_ref() else {
+            return Ok(artifact_path);
+        };
+        std::fs::create_dir_all(artifacts_dir).with_context(|| {
+            format!(
+                "Failed to create artifacts directory at '{}'",
+                artifacts_dir.display()
+            )
+        })?;
+        let final_artifact_path = artifacts_dir.join(artifact_ident.artifact_name());
+        debug!(
+            "Placing build artifact {} in {} ({:?})",
+            artifact_path.display(),
+            final_artifact_path.display(),
+            self.artifacts_dir_mode
+        );
+        // The artifacts folder may be on a different filesystem than the store, so
+        // the artifact is always copied instead of being renamed
+        std::fs::copy(&artifact_path, &final_artifact_path).with_context(|| {
+            format!(
+                "Failed to copy build artifact from {} to {}",
+                artifact_path.display(),
+                final_artifact_path.display()
+            )
+        })?;
+        if self.artifacts_dir_mode == ArtifactsDirMode::Move {
+            std::fs::remove_file(&artifact_path).with_context(|| {
+                format!(
+                    "Failed to remove build artifact {} from hab-auto-build store",
+                    artifact_path.display()
+                )
+            })?;
+        }
+        Ok(final_artifact_path)
+    }
 }
@@ -0,0 +1,246 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{HabitatRootPath, PlanContext};
+
+/// Maximum number of hints reported for a single build failure
+const BUILD_FAILURE_MAX_HINTS: usize = 10;
+
+lazy_static! {
+    static ref ANSI_ESCAPE_SEQUENCE: Regex = Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap();
+    static ref MISSING_HEADER: Regex =
+        Regex::new(r"fatal error: ([\w./+-]+\.(?:h|hh|hpp|hxx)): No such file or directory")
+            .unwrap();
+    static ref MISSING_LIBRARY: Regex = Regex::new(r"cannot find -l([\w.+-]+)").unwrap();
+    static ref MISSING_COMMAND: Regex =
+        Regex::new(r"(?:^|[\s:])([\w.+-]+): (?:command )?not found$").unwrap();
+    static ref SHASUM_MISMATCH: Regex = Regex::new(
+        r"(?i)checksum (?:error|invalid|mismatch)|(?:shasum|sha256sum).*(?:mismatch|did not match|failed)"
+    )
+    .unwrap();
+    static ref NETWORK_UNAVAILABLE: Regex = Regex::new(
+        r"(?i)could not resolve host|temporary failure in name resolution|network is unreachable|failed to connect to|name or service not known"
+    )
+    .unwrap();
+    static ref DISK_FULL: Regex = Regex::new(r"(?i)no space left on device").unwrap();
+}
+
+/// Known cause of a build failure, recognized from the build log
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum BuildFailureKind {
+    /// A header included by the sources is not available
+    MissingHeader(String),
+    /// The linker cannot find a library
+    MissingLibrary(String),
+    /// A command run by the build is not available
+    MissingCommand(String),
+    /// The downloaded source does not match the plan's shasum
+    ShasumMismatch,
+    /// The build tried to reach the network
+    NetworkUnavailable,
+    /// The disk ran out of space
+    DiskFull,
+}
+
+/// Likely cause of a build failure along with what can be done about it
+#[derive(Debug, Clone)]
+pub(crate) struct BuildFailureHint {
+    /// Line of the build log the failure was recognized from, starting at 1
+    pub line_number: usize,
+    pub line: String,
+    pub hint: String,
+}
+
+/// Scans the build log of a failed build for known causes of failure, every cause
+/// is reported once, at the first line it is recognized from.
+pub(crate) fn build_failure_analyze(
+    plan_ctx: &PlanContext,
+    build_log: impl AsRef<Path>,
+) -> Result<Vec<BuildFailureHint>> {
+    let build_log = build_log.as_ref();
+    let file = File::open(build_log)
+        .with_context(|| eyre!("Failed to open build log '{}'", build_log.display()))?;
+    let mut kinds = BTreeSet::new();
+    let mut hints = Vec::new();
+    for (index, line) in BufReader::new(file).split(b'\n').enumerate() {
+        let line =
+            line.with_context(|| eyre!("Failed to read build log '{}'", build_log.display()))?;
+        let line = String::from_utf8_lossy(&line);
+        let line = ANSI_ESCAPE_SEQUENCE.replace_all(line.trim_end(), "");
+        let Some(kind) = build_failure_kind(&line) else {
+            continue;
+        };
+        if !kinds.insert(kind.clone()) {
+            continue;
+        }
+        let dependencies = installed_packages_providing(&build_failure_paths(&kind));
+        hints.push(BuildFailureHint {
+            hint: build_failure_hint(plan_ctx, &kind, &dependencies),
+            line_number: index + 1,
+            line: line.trim().to_string(),
+        });
+        if hints.len() == BUILD_FAILURE_MAX_HINTS {
+            break;
+        }
+    }
+    Ok(hints)
+}
+
+fn build_failure_kind(line: &str) -> Option<BuildFailureKind> {
+    if let Some(captures) = MISSING_HEADER.captures(line) {
+        return Some(BuildFailureKind::MissingHeader(captures[1].to_string()));
+    }
+    if let Some(captures) = MISSING_LIBRARY.captures(line) {
+        return Some(BuildFailureKind::MissingLibrary(captures[1].to_string()));
+    }
+    if let Some(captures) = MISSING_COMMAND.captures(line) {
+        return Some(BuildFailureKind::MissingCommand(captures[1].to_string()));
+    }
+    if SHASUM_MISMATCH.is_match(line) {
+        return Some(BuildFailureKind::ShasumMismatch);
+    }
+    if NETWORK_UNAVAILABLE.is_match(line) {
+        return Some(BuildFailureKind::NetworkUnavailable);
+    }
+    if DISK_FULL.is_match(line) {
+        return Some(BuildFailureKind::DiskFull);
+    }
+    None
+}
+
+/// Paths, relative to a package's install folder, at which a package would
+/// provide what is missing
+fn build_failure_paths(kind: &BuildFailureKind) -> Vec<PathBuf> {
+    match kind {
+        BuildFailureKind::MissingHeader(header) => vec![PathBuf::from("include").join(header)],
+        BuildFailureKind::MissingLibrary(library) => ["lib", "lib64"]
+            .iter()
+            .flat_map(|lib_dir| {
+                [
+                    PathBuf::from(lib_dir).join(format!("lib{}.so", library)),
+                    PathBuf::from(lib_dir).join(format!("lib{}.a", library)),
+                ]
+            })
+            .collect(),
+        BuildFailureKind::MissingCommand(command) => vec![
+            PathBuf::from("bin").join(command),
+            PathBuf::from("sbin").join(command),
+        ],
+        BuildFailureKind::ShasumMismatch
+        | BuildFailureKind::NetworkUnavailable
+        | BuildFailureKind::DiskFull => Vec::new(),
+    }
+}
+
+/// Finds the packages installed under '/hab/pkgs' that contain any of the paths
+fn installed_packages_providing(paths: &[PathBuf]) -> Vec<String> {
+    let mut packages = BTreeSet::new();
+    if paths.is_empty() {
+        return Vec::new();
+    }
+    let pkgs_path = HabitatRootPath::default().as_ref().join("pkgs");
+    for release_path in sub_dirs(&pkgs_path)
+        .into_iter()
+        .flat_map(|origin_path| sub_dirs(&origin_path))
+        .flat_map(|name_path| sub_dirs(&name_path))
+        .flat_map(|version_path| sub_dirs(&version_path))
+    {
+        if paths.iter().any(|path| release_path.join(path).exists()) {
+            if let Ok(package) = release_path.strip_prefix(&pkgs_path) {
+                let mut components = package.components();
+                if let (Some(origin), Some(name)) = (components.next(), components.next()) {
+                    packages.insert(format!(
+                        "{}/{}",
+                        origin.as_os_str().to_string_lossy(),
+                        name.as_os_str().to_string_lossy()
+                    ));
+                }
+            }
+        }
+    }
+    packages.into_iter().collect()
+}
+
+fn sub_dirs(path: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn build_failure_hint(
+    plan_ctx: &PlanContext,
+    kind: &BuildFailureKind,
+    dependencies: &[String],
+) -> String {
+    let (missing, deps_var) = match kind {
+        BuildFailureKind::MissingHeader(header) => (
+            format!("The header '{}' was not found", header),
+            "'pkg_deps' or 'pkg_build_deps'",
+        ),
+        BuildFailureKind::MissingLibrary(library) => (
+            format!("The linker could not find the library 'lib{}'", library),
+            "'pkg_deps'",
+        ),
+        BuildFailureKind::MissingCommand(command) => (
+            format!("The command '{}' was not found", command),
+            "'pkg_build_deps'",
+        ),
+        BuildFailureKind::ShasumMismatch => {
+            return "The downloaded source does not match the plan's 'pkg_shasum', update 'pkg_shasum' if the upstream source changed or remove the cached source to download it again".to_string();
+        }
+        BuildFailureKind::NetworkUnavailable => {
+            return "The build tried to access the network, which is not available in the build studio. Add everything the build downloads to the plan's sources".to_string();
+        }
+        BuildFailureKind::DiskFull => {
+            return "The disk ran out of space, free up space by removing old build studios and artifacts with 'hab-auto-build clean'".to_string();
+        }
+    };
+    let declared_dependencies = dependencies
+        .iter()
+        .filter(|dependency| {
+            plan_ctx
+                .deps
+                .iter()
+                .chain(plan_ctx.build_deps.iter())
+                .any(|dep| format!("{}/{}", dep.origin, dep.name) == **dependency)
+        })
+        .collect::<Vec<_>>();
+    if !declared_dependencies.is_empty() {
+        format!(
+            "{}, even though the plan depends on {} which provides it. Make sure the build looks for it in the dependency's install folder",
+            missing,
+            declared_dependencies
+                .iter()
+                .map(|dependency| format!("'{}'", dependency))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    } else if !dependencies.is_empty() {
+        format!(
+            "{}, add the package providing it to {}: {}",
+            missing,
+            deps_var,
+            dependencies
+                .iter()
+                .map(|dependency| format!("'{}'", dependency))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )
+    } else {
+        format!("{}, add the package providing it to {}", missing, deps_var)
+    }
+}
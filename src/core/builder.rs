@@ -0,0 +1,323 @@
+use std::{
+    env,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    time::Duration,
+};
+
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use color_eyre::eyre::{eyre, Context, Result};
+use reqwest::{
+    blocking::{Body, Client, ClientBuilder},
+    header, StatusCode, Url,
+};
+use serde::Deserialize;
+use tracing::debug;
+
+use super::{
+    PackageIdent, PackageName, PackageOrigin, PackageRelease, PackageResolvedDepIdent,
+    PackageResolvedRelease, PackageResolvedVersion, PackageVersion,
+};
+
+/// Builder instance used when HAB_BLDR_URL is not set
+const BUILDER_DEFAULT_URL: &str = "https://bldr.habitat.sh";
+/// Builder channel used when HAB_BLDR_CHANNEL is not set
+const BUILDER_DEFAULT_CHANNEL: &str = "stable";
+/// Size of the chunks in which artifacts are streamed to Builder
+const UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+/// Time an artifact upload may take before it is abandoned and retried
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+/// Delay before the first retry of a failed upload, doubled after each retry
+const UPLOAD_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct BuilderPackage {
+    ident: BuilderPackageIdent,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuilderPackageIdent {
+    origin: String,
+    name: String,
+    version: String,
+    release: String,
+}
+
+/// Looks up the packages published to a channel of a Habitat Builder instance,
+/// the instance and channel are the ones used by the hab cli.
+pub(crate) struct BuilderClient {
+    client: Client,
+    url: Url,
+    channel: String,
+    has_auth_token: bool,
+}
+
+/// Outcome of an artifact upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArtifactUploadStatus {
+    Uploaded,
+    /// Builder already had the artifact, from an earlier upload that may have been interrupted
+    AlreadyUploaded,
+}
+
+/// Streams an artifact in chunks, reporting the number of bytes sent so far
+struct UploadProgressReader<R, F> {
+    inner: R,
+    sent: u64,
+    on_progress: F,
+}
+
+impl<R: Read, F: FnMut(u64)> Read for UploadProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.sent += read as u64;
+        (self.on_progress)(self.sent);
+        Ok(read)
+    }
+}
+
+impl BuilderClient {
+    pub fn new() -> Result<BuilderClient> {
+        let mut url = env::var("HAB_BLDR_URL").unwrap_or(BUILDER_DEFAULT_URL.to_string());
+        // API paths are joined to the instance url, which must be a directory
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        let url = Url::parse(&url).with_context(|| eyre!("Invalid HAB_BLDR_URL '{}'", url))?;
+        let channel = env::var("HAB_BLDR_CHANNEL").unwrap_or(BUILDER_DEFAULT_CHANNEL.to_string());
+        let mut headers = header::HeaderMap::new();
+        headers.append(header::USER_AGENT, "hab-auto-build".parse().unwrap());
+        // Packages of private origins are only visible to authenticated requests
+        if let Ok(token) = env::var("HAB_AUTH_TOKEN") {
+            headers.append(
+                header::AUTHORIZATION,
+                format!("Bearer {}", token)
+                    .parse()
+                    .context("Invalid HAB_AUTH_TOKEN")?,
+            );
+        }
+        Ok(BuilderClient {
+            client: ClientBuilder::new().default_headers(headers).build()?,
+            url,
+            channel,
+            has_auth_token: env::var("HAB_AUTH_TOKEN").is_ok(),
+        })
+    }
+
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Finds the latest package in the channel that satisfies the dependency, returns
+    /// nothing if the channel has no such package for the dependency's target.
+    pub fn latest_package(
+        &self,
+        dep_ident: &PackageResolvedDepIdent,
+    ) -> Result<Option<PackageIdent>> {
+        let mut path = format!(
+            "v1/depot/channels/{}/{}/pkgs/{}",
+            dep_ident.origin, self.channel, dep_ident.name
+        );
+        match (&dep_ident.version, &dep_ident.release) {
+            (PackageVersion::Resolved(version), PackageRelease::Resolved(release)) => {
+                path.push_str(&format!("/{}/{}", version, release));
+            }
+            (PackageVersion::Resolved(version), PackageRelease::Unresolved) => {
+                path.push_str(&format!("/{}/latest", version));
+            }
+            (PackageVersion::Unresolved, _) => path.push_str("/latest"),
+        }
+        let mut url = self
+            .url
+            .join(&path)
+            .with_context(|| eyre!("Invalid Builder API path '{}'", path))?;
+        url.query_pairs_mut()
+            .append_pair("target", &dep_ident.target.to_string());
+        debug!("Resolving {} from {}", dep_ident, url);
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .with_context(|| eyre!("Failed to fetch '{}'", url))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(eyre!(
+                "Failed to fetch '{}', the server responded with {}",
+                url,
+                response.status()
+            ));
+        }
+        let response = response
+            .text()
+            .with_context(|| eyre!("Failed to read the response from '{}'", url))?;
+        let package: BuilderPackage = serde_json::from_str(&response)
+            .with_context(|| eyre!("Invalid response from the Builder API for '{}'", url))?;
+        Ok(Some(PackageIdent {
+            origin: PackageOrigin::parse(package.ident.origin)?,
+            name: PackageName::parse(package.ident.name)?,
+            version: PackageResolvedVersion::parse(package.ident.version)?,
+            release: PackageResolvedRelease::parse(package.ident.release)?,
+            target: dep_ident.target,
+        }))
+    }
+
+    fn package_path(artifact_ident: &PackageIdent) -> String {
+        format!(
+            "v1/depot/pkgs/{}/{}/{}/{}",
+            artifact_ident.origin,
+            artifact_ident.name,
+            artifact_ident.version,
+            artifact_ident.release
+        )
+    }
+
+    /// Checks whether Builder already has an artifact, in any channel
+    pub fn package_exists(&self, artifact_ident: &PackageIdent) -> Result<bool> {
+        let path = Self::package_path(artifact_ident);
+        let mut url = self
+            .url
+            .join(&path)
+            .with_context(|| eyre!("Invalid Builder API path '{}'", path))?;
+        url.query_pairs_mut()
+            .append_pair("target", &artifact_ident.target.to_string());
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .with_context(|| eyre!("Failed to fetch '{}'", url))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(eyre!(
+                "Failed to fetch '{}', the server responded with {}",
+                url,
+                status
+            )),
+        }
+    }
+
+    /// Uploads an artifact to Builder, streaming the .hart file in chunks. Builder
+    /// only accepts whole artifacts, so an interrupted upload is retried from the
+    /// start up to the given number of times, waiting longer after each failure.
+    /// Artifacts Builder already has are not uploaded again, which lets an
+    /// interrupted batch of uploads resume where it stopped.
+    pub fn artifact_upload(
+        &self,
+        artifact_ident: &PackageIdent,
+        artifact_path: &Path,
+        retries: u32,
+        on_progress: impl Fn(u64, u64) + Clone + Send + 'static,
+    ) -> Result<ArtifactUploadStatus> {
+        if !self.has_auth_token {
+            return Err(eyre!(
+                "Uploading {} to Builder requires HAB_AUTH_TOKEN to be set",
+                artifact_ident
+            ));
+        }
+        if self.package_exists(artifact_ident)? {
+            return Ok(ArtifactUploadStatus::AlreadyUploaded);
+        }
+        let checksum = artifact_checksum(artifact_path)?;
+        let size = std::fs::metadata(artifact_path)
+            .with_context(|| eyre!("Failed to read artifact '{}'", artifact_path.display()))?
+            .len();
+        let path = Self::package_path(artifact_ident);
+        let mut url = self
+            .url
+            .join(&path)
+            .with_context(|| eyre!("Invalid Builder API path '{}'", path))?;
+        url.query_pairs_mut()
+            .append_pair("checksum", &checksum)
+            .append_pair("target", &artifact_ident.target.to_string());
+
+        let mut attempt = 0;
+        let mut retry_delay = UPLOAD_RETRY_DELAY;
+        loop {
+            let file = File::open(artifact_path)
+                .with_context(|| eyre!("Failed to open artifact '{}'", artifact_path.display()))?;
+            let on_progress = on_progress.clone();
+            let reader = UploadProgressReader {
+                inner: BufReader::with_capacity(UPLOAD_CHUNK_SIZE, file),
+                sent: 0,
+                on_progress: move |sent| on_progress(sent, size),
+            };
+            debug!(
+                "Uploading {} to {} (attempt {} of {})",
+                artifact_ident,
+                url,
+                attempt + 1,
+                retries + 1
+            );
+            let error = match self
+                .client
+                .post(url.clone())
+                .header(header::CONTENT_TYPE, "application/octet-stream")
+                .timeout(UPLOAD_TIMEOUT)
+                .body(Body::sized(reader, size))
+                .send()
+            {
+                Ok(response) if response.status().is_success() => {
+                    return Ok(ArtifactUploadStatus::Uploaded)
+                }
+                // Another upload of the same artifact completed in the meantime
+                Ok(response) if response.status() == StatusCode::CONFLICT => {
+                    return Ok(ArtifactUploadStatus::AlreadyUploaded)
+                }
+                Ok(response) if response.status().is_client_error() => {
+                    let status = response.status();
+                    let body = response.text().unwrap_or_default();
+                    return Err(eyre!(
+                        "Builder refused the upload of {} with {}: {}",
+                        artifact_ident,
+                        status,
+                        body.trim()
+                    ));
+                }
+                Ok(response) => eyre!(
+                    "Failed to upload {}, the server responded with {}",
+                    artifact_ident,
+                    response.status()
+                ),
+                Err(err) => eyre!(err).wrap_err(format!("Failed to upload {}", artifact_ident)),
+            };
+            if attempt >= retries {
+                return Err(error);
+            }
+            attempt += 1;
+            debug!(
+                "{:#}, retrying in {}s ({} of {})",
+                error,
+                retry_delay.as_secs(),
+                attempt,
+                retries
+            );
+            std::thread::sleep(retry_delay);
+            retry_delay *= 2;
+        }
+    }
+}
+
+/// Computes the BLAKE2b checksum Builder uses to verify uploaded artifacts
+fn artifact_checksum(artifact_path: &Path) -> Result<String> {
+    let mut reader = BufReader::new(
+        File::open(artifact_path)
+            .with_context(|| eyre!("Failed to open artifact '{}'", artifact_path.display()))?,
+    );
+    let mut hasher = Blake2b::<U32>::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
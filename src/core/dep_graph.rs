@@ -16,11 +16,13 @@ use color_eyre::{
 use emoji_printer::print_emojis;
 
 use petgraph::{
-    algo::{self, greedy_feedback_arc_set},
+    algo::{self, greedy_feedback_arc_set, is_cyclic_directed, tarjan_scc},
+    graph::DiGraph,
     stable_graph::{NodeIndex, StableGraph},
-    visit::{EdgeRef, IntoNodeReferences},
+    visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences},
     Directed, Direction,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
@@ -30,7 +32,9 @@ use std::{
 };
 use tracing::{error, info, warn};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, PartialOrd, Ord, Serialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, PartialOrd, Ord, Serialize, JsonSchema,
+)]
 pub(crate) enum DependencyType {
     #[serde(rename = "studio")]
     Studio,
@@ -211,9 +215,158 @@ impl From<&DepGraph> for DepGraphData {
     }
 }
 
+/// Maximum number of candidate edge sets tried when searching for the smallest
+/// set of build dependencies that breaks a dependency cycle
+const CYCLE_BREAK_SEARCH_LIMIT: usize = 100_000;
+
+/// Maximum number of paths listed when explaining why a plan is rebuilt, the
+/// number of paths grows exponentially with the number of shared dependencies
+const REBUILD_PATH_LIMIT: usize = 1000;
+
+/// Chain of dependencies along which a change to a plan causes another plan to be rebuilt
+#[derive(Debug, Clone)]
+pub(crate) struct RebuildPath {
+    /// Plans from the changed plan to the rebuilt plan
+    pub nodes: Vec<NodeIndex>,
+    /// Type of the dependency each plan of the path has on the previous one
+    pub dep_types: Vec<DependencyType>,
+    /// Changes to the first plan of the path that cause it to be rebuilt
+    pub causes: Vec<DependencyChangeCause>,
+}
+
+/// Group of plans that depend on each other, forming one or more dependency cycles
+#[derive(Debug, Clone)]
+pub(crate) struct DependencyCycle {
+    pub nodes: Vec<NodeIndex>,
+    /// Dependencies between the plans of the cycle
+    pub edges: Vec<(NodeIndex, NodeIndex, DependencyType)>,
+    /// Smallest set of build dependencies to remove to break the cycle, empty
+    /// if the cycle is only made of runtime dependencies
+    pub suggested_breaks: Vec<(NodeIndex, NodeIndex)>,
+}
+
+impl DependencyCycle {
+    /// Finds the dependency cycles formed by the strongly connected components of the graph
+    fn detect(build_graph: &StableGraph<Dependency, DependencyType, Directed>) -> Vec<Self> {
+        // Plan ids give the cycles a stable order across runs
+        let node_key = |node_index: &NodeIndex| {
+            build_graph[*node_index]
+                .plan_ctx()
+                .map(|plan_ctx| plan_ctx.id.clone())
+        };
+        let mut cycles = tarjan_scc(build_graph)
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || build_graph.contains_edge(component[0], component[0])
+            })
+            .map(|mut component| {
+                component.sort_by_key(node_key);
+                let mut edges = build_graph
+                    .edge_references()
+                    .filter(|edge| {
+                        component.contains(&edge.source()) && component.contains(&edge.target())
+                    })
+                    .map(|edge| (edge.source(), edge.target(), *edge.weight()))
+                    .collect::<Vec<_>>();
+                edges.sort_by_key(|(source, target, dep_type)| {
+                    (node_key(source), node_key(target), *dep_type)
+                });
+                let suggested_breaks = DependencyCycle::suggested_breaks(&component, &edges);
+                DependencyCycle {
+                    nodes: component,
+                    edges,
+                    suggested_breaks,
+                }
+            })
+            .collect::<Vec<_>>();
+        cycles.sort_by_key(|cycle| node_key(&cycle.nodes[0]));
+        cycles
+    }
+
+    /// Searches for the smallest set of build dependencies whose removal leaves
+    /// the component without cycles. Components with too many build dependencies
+    /// for an exhaustive search get a set from which no edge can be dropped instead.
+    fn suggested_breaks(
+        nodes: &[NodeIndex],
+        edges: &[(NodeIndex, NodeIndex, DependencyType)],
+    ) -> Vec<(NodeIndex, NodeIndex)> {
+        let build_edges = (0..edges.len())
+            .filter(|index| edges[*index].2 == DependencyType::Build)
+            .collect::<Vec<_>>();
+        let is_acyclic_without = |removed: &[usize]| {
+            let mut graph = DiGraph::<(), ()>::new();
+            let graph_nodes = nodes
+                .iter()
+                .map(|node| (*node, graph.add_node(())))
+                .collect::<HashMap<_, _>>();
+            for (index, (source, target, _)) in edges.iter().enumerate() {
+                if !removed.contains(&index) {
+                    graph.add_edge(graph_nodes[source], graph_nodes[target], ());
+                }
+            }
+            !is_cyclic_directed(&graph)
+        };
+        if !is_acyclic_without(&build_edges) {
+            return Vec::new();
+        }
+        let to_node_pairs = |removed: Vec<usize>| {
+            removed
+                .into_iter()
+                .map(|index| (edges[index].0, edges[index].1))
+                .collect::<Vec<_>>()
+        };
+        let mut attempts = 0;
+        for size in 1..build_edges.len() {
+            let mut combination = (0..size).collect::<Vec<_>>();
+            loop {
+                attempts += 1;
+                if attempts > CYCLE_BREAK_SEARCH_LIMIT {
+                    break;
+                }
+                let removed = combination
+                    .iter()
+                    .map(|position| build_edges[*position])
+                    .collect::<Vec<_>>();
+                if is_acyclic_without(&removed) {
+                    return to_node_pairs(removed);
+                }
+                // Advance to the next combination in lexicographic order
+                let Some(position) = (0..size)
+                    .rev()
+                    .find(|position| combination[*position] < build_edges.len() - size + position)
+                else {
+                    break;
+                };
+                combination[position] += 1;
+                for next_position in position + 1..size {
+                    combination[next_position] = combination[next_position - 1] + 1;
+                }
+            }
+            if attempts > CYCLE_BREAK_SEARCH_LIMIT {
+                break;
+            }
+        }
+        // Drop every edge that is not required to break the cycle
+        let mut removed = build_edges;
+        let mut position = 0;
+        while position < removed.len() {
+            let mut candidate = removed.clone();
+            candidate.remove(position);
+            if is_acyclic_without(&candidate) {
+                removed = candidate;
+            } else {
+                position += 1;
+            }
+        }
+        to_node_pairs(removed)
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct DepGraph {
     pub build_graph: StableGraph<Dependency, DependencyType, Directed>,
+    /// Dependency cycles found between plans, before any cycles were ignored
+    pub cycles: Vec<DependencyCycle>,
     #[allow(dead_code)]
     pub known_versions: PackageVersionList,
 }
@@ -222,6 +375,9 @@ pub(crate) struct DepGraph {
 pub(crate) enum ChangeDetectionMode {
     Git,
     Disk,
+    /// Changes on disk are only considered if the content of the plan context differs
+    /// from the one the latest artifact was built from
+    Hash,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
@@ -335,6 +491,7 @@ impl DepGraph {
             }
         }
 
+        let cycles = DependencyCycle::detect(&build_graph);
         let feedback_edges = greedy_feedback_arc_set(&build_graph)
             .map(|e| e.id())
             .collect::<Vec<_>>();
@@ -355,8 +512,15 @@ impl DepGraph {
             build_graph.edge_count(),
             start.elapsed().as_secs_f32()
         );
+        if !cycles.is_empty() && !ignore_cycles {
+            error!(target: "user-log",
+                "Found {} dependency cycles between plans, run 'hab-auto-build analyze --cycles' for the dependencies to remove to break them",
+                cycles.len()
+            );
+        }
         let mut dep_graph = DepGraph {
             build_graph,
+            cycles,
             known_versions,
         };
 
@@ -672,7 +836,7 @@ impl DepGraph {
                                 });
                             }
                         }
-                        ChangeDetectionMode::Disk => {
+                        ChangeDetectionMode::Disk | ChangeDetectionMode::Hash => {
                             if !files_changed_on_disk.is_empty() {
                                 causes.push(DependencyChangeCause::PlanContextChanged {
                                     latest_plan_artifact: latest_artifact.clone(),
@@ -805,6 +969,169 @@ impl DepGraph {
         )
     }
 
+    /// Finds every path from a changed plan to the given plan through the plans that
+    /// are rebuilt. The returned flag is set if the paths were truncated to `REBUILD_PATH_LIMIT`.
+    pub fn rebuild_paths(
+        &self,
+        node_index: NodeIndex,
+        change_detection_mode: ChangeDetectionMode,
+        build_order: BuildOrder,
+        build_target: PackageTarget,
+    ) -> (Vec<RebuildPath>, bool) {
+        let changes_graph = self.detect_changes(change_detection_mode, build_order, build_target);
+        let mut paths = Vec::new();
+        if !changes_graph.contains_node(node_index) {
+            return (paths, false);
+        }
+        // Walk the dependencies of the plan depth first, the stack holds the path
+        // to the current plan along with the dependencies left to visit at each step
+        let mut nodes = vec![node_index];
+        let mut dep_types = Vec::new();
+        let mut stack = vec![changes_graph
+            .edges_directed(node_index, Direction::Outgoing)
+            .map(|edge| (edge.target(), *edge.weight()))
+            .collect::<Vec<_>>()];
+        let rebuild_path = |nodes: &[NodeIndex], dep_types: &[DependencyType]| {
+            let causes = changes_graph[*nodes.last().unwrap()]
+                .iter()
+                .filter(|cause| {
+                    !matches!(
+                        cause,
+                        DependencyChangeCause::DependencyPlansNeedRebuild { .. }
+                            | DependencyChangeCause::DependencyStudioNeedRebuild { .. }
+                    )
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if causes.is_empty() {
+                None
+            } else {
+                Some(RebuildPath {
+                    nodes: nodes.iter().rev().cloned().collect(),
+                    dep_types: dep_types.iter().rev().cloned().collect(),
+                    causes,
+                })
+            }
+        };
+        paths.extend(rebuild_path(&nodes, &dep_types));
+        while let Some(deps) = stack.last_mut() {
+            if paths.len() >= REBUILD_PATH_LIMIT {
+                return (paths, true);
+            }
+            let Some((dep_node_index, dep_type)) = deps.pop() else {
+                stack.pop();
+                nodes.pop();
+                dep_types.pop();
+                continue;
+            };
+            if nodes.contains(&dep_node_index) {
+                continue;
+            }
+            nodes.push(dep_node_index);
+            dep_types.push(dep_type);
+            paths.extend(rebuild_path(&nodes, &dep_types));
+            stack.push(
+                changes_graph
+                    .edges_directed(dep_node_index, Direction::Outgoing)
+                    .map(|edge| (edge.target(), *edge.weight()))
+                    .collect(),
+            );
+        }
+        (paths, false)
+    }
+
+    /// Finds the given nodes and their transitive dependencies of the given types,
+    /// along with the dependencies of those types between them.
+    pub fn subgraph<'a>(
+        &self,
+        nodes: impl IntoIterator<Item = &'a NodeIndex>,
+        dep_types: HashSet<DependencyType>,
+    ) -> (Vec<NodeIndex>, Vec<(NodeIndex, NodeIndex, DependencyType)>) {
+        let node_indices = self.get_deps(
+            nodes,
+            dep_types.clone(),
+            DependencyDepth::Transitive,
+            DependencyDirection::Forward,
+            true,
+            false,
+        );
+        let node_set = node_indices.iter().collect::<HashSet<_>>();
+        let mut edges = Vec::new();
+        for node_index in node_indices.iter() {
+            for edge in self.build_graph.edges(*node_index) {
+                if dep_types.contains(edge.weight()) && node_set.contains(&edge.target()) {
+                    edges.push((*node_index, edge.target(), *edge.weight()));
+                }
+            }
+        }
+        (node_indices, edges)
+    }
+
+    /// Groups the local plans among the given nodes and their transitive dependencies
+    /// into layers. Plans in layer 0 have no local dependencies, plans in layer N only
+    /// depend on local plans in layers below N. Plans that are part of, or depend on,
+    /// a dependency cycle cannot be layered and are placed together in a final layer.
+    pub fn layers<'a>(
+        &self,
+        nodes: impl IntoIterator<Item = &'a NodeIndex>,
+    ) -> Vec<Vec<NodeIndex>> {
+        let plan_nodes = self
+            .get_deps(
+                nodes,
+                [
+                    DependencyType::Build,
+                    DependencyType::Runtime,
+                    DependencyType::Studio,
+                ]
+                .into_iter()
+                .collect(),
+                DependencyDepth::Transitive,
+                DependencyDirection::Forward,
+                true,
+                false,
+            )
+            .into_iter()
+            .filter(|node_index| self.build_graph[*node_index].plan_ctx().is_some())
+            .collect::<HashSet<_>>();
+        let mut layered_nodes = HashSet::new();
+        let mut layers: Vec<Vec<NodeIndex>> = Vec::new();
+        loop {
+            let mut layer = Vec::new();
+            for node_index in plan_nodes.iter() {
+                if layered_nodes.contains(node_index) {
+                    continue;
+                }
+                if self
+                    .build_graph
+                    .neighbors_directed(*node_index, Direction::Outgoing)
+                    .filter(|dep_node_index| {
+                        dep_node_index != node_index && plan_nodes.contains(dep_node_index)
+                    })
+                    .all(|dep_node_index| layered_nodes.contains(&dep_node_index))
+                {
+                    layer.push(*node_index);
+                }
+            }
+            if layer.is_empty() {
+                break;
+            }
+            layered_nodes.extend(layer.iter().cloned());
+            layers.push(layer);
+        }
+        let cyclic_nodes = plan_nodes
+            .into_iter()
+            .filter(|node_index| !layered_nodes.contains(node_index))
+            .collect::<Vec<_>>();
+        if !cyclic_nodes.is_empty() {
+            warn!(
+                "{} plans are part of or depend on dependency cycles and cannot be layered",
+                cyclic_nodes.len()
+            );
+            layers.push(cyclic_nodes);
+        }
+        layers
+    }
+
     pub fn get_deps<'a>(
         &self,
         nodes: impl IntoIterator<Item = &'a NodeIndex>,
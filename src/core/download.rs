@@ -7,10 +7,15 @@ use reqwest::{
     Method, StatusCode, Url,
 };
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::{Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
-    time::Instant,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 use suppaftp::FtpStream;
 use tracing::{debug, log::error};
@@ -20,9 +25,91 @@ lazy_static! {
     static ref DOWNLOAD_MEMORY_BUFFER: u64 = 1024 * 256;
 }
 
+/// Progress of a download in progress
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    /// Name of the downloaded file
+    pub name: String,
+    pub downloaded: u64,
+    /// Size of the downloaded file, if known
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct DownloadTrackerState {
+    downloads: Mutex<BTreeMap<PathBuf, DownloadProgress>>,
+    downloaded: AtomicU64,
+    /// Maximum combined download rate in bytes per second
+    rate_limit: Option<u64>,
+    /// Time at which the bytes downloaded so far are allowed by the rate limit
+    rate_limit_until: Mutex<Option<Instant>>,
+}
+
+/// Tracks the progress of downloads and limits their combined bandwidth, it is
+/// shared by all the downloads of a command.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadTracker(Arc<DownloadTrackerState>);
+
+impl DownloadTracker {
+    pub fn new(rate_limit: Option<u64>) -> DownloadTracker {
+        DownloadTracker(Arc::new(DownloadTrackerState {
+            rate_limit,
+            ..Default::default()
+        }))
+    }
+
+    /// Progress of the downloads in progress
+    pub fn downloads(&self) -> Vec<DownloadProgress> {
+        self.0.downloads.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Total number of bytes downloaded
+    pub fn downloaded(&self) -> u64 {
+        self.0.downloaded.load(Ordering::Relaxed)
+    }
+
+    fn start(&self, filename: &Path, name: &str, total: Option<u64>) {
+        self.0.downloads.lock().unwrap().insert(
+            filename.to_path_buf(),
+            DownloadProgress {
+                name: name.to_string(),
+                downloaded: 0,
+                total,
+            },
+        );
+    }
+
+    fn finish(&self, filename: &Path) {
+        self.0.downloads.lock().unwrap().remove(filename);
+    }
+
+    /// Records downloaded bytes, waiting as long as needed to stay within the rate limit
+    fn transferred(&self, filename: &Path, bytes: u64) {
+        if let Some(download) = self.0.downloads.lock().unwrap().get_mut(filename) {
+            download.downloaded += bytes;
+        }
+        self.0.downloaded.fetch_add(bytes, Ordering::Relaxed);
+        if let Some(rate_limit) = self.0.rate_limit {
+            let wait_until = {
+                let mut rate_limit_until = self.0.rate_limit_until.lock().unwrap();
+                let now = Instant::now();
+                let start = rate_limit_until.filter(|until| *until > now).unwrap_or(now);
+                let until = start + Duration::from_secs_f64(bytes as f64 / rate_limit as f64);
+                *rate_limit_until = Some(until);
+                until
+            };
+            let now = Instant::now();
+            if wait_until > now {
+                std::thread::sleep(wait_until - now);
+            }
+        }
+    }
+}
+
 pub struct Download {
     pub url: Url,
     pub filename: PathBuf,
+    tracker: DownloadTracker,
 }
 
 impl Download {
@@ -30,10 +117,43 @@ impl Download {
         Download {
             url: url.clone(),
             filename: filename.as_ref().to_path_buf(),
+            tracker: DownloadTracker::default(),
         }
     }
 
-    fn calculate_ranges(content_length: u64) -> Vec<(u64, u64, u64, u64)> {
+    pub fn with_tracker(mut self, tracker: &DownloadTracker) -> Download {
+        self.tracker = tracker.clone();
+        self
+    }
+
+    /// Name of the downloaded file, as shown in progress reports
+    fn name(&self) -> String {
+        self.url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .unwrap_or_else(|| self.url.to_string())
+    }
+
+    /// Copies the data from the reader to the file, recording the progress
+    /// of the download.
+    fn copy_tracked(&self, reader: &mut impl Read, file: &mut File) -> std::io::Result<()> {
+        let mut buffer = vec![0u8; *DOWNLOAD_MEMORY_BUFFER as usize];
+        loop {
+            let bytes_read = match reader.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(bytes_read) => bytes_read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            file.write_all(&buffer[..bytes_read])?;
+            self.tracker
+                .transferred(self.filename.as_path(), bytes_read as u64);
+        }
+    }
+
+    fn calculate_ranges(content_length: u64) -> Vec<(u64, u64)> {
         let mut range_start = 0;
         let mut ranges = vec![];
         let chunk_size = content_length / *DOWNLOAD_THREAD_COUNT - 1;
@@ -44,22 +164,22 @@ impl Download {
                 range_end = content_length
             }
 
-            let range_to_process: u64 = range_end - range_start;
-            let buffer_chunks: u64 = range_to_process / *DOWNLOAD_MEMORY_BUFFER;
-            let chunk_remainder: u64 = range_to_process % *DOWNLOAD_MEMORY_BUFFER;
-
-            ranges.push((range_start, range_end, buffer_chunks, chunk_remainder));
+            ranges.push((range_start, range_end));
             range_start = range_start + chunk_size + 1;
         }
         ranges
     }
 
     pub fn execute(self) -> Result<()> {
-        match self.url.scheme() {
+        let filename = self.filename.clone();
+        let tracker = self.tracker.clone();
+        let result = match self.url.scheme() {
             "http" | "https" => self.execute_http(),
             "ftp" => self.execute_ftp(),
             _ => Err(eyre!("Unsupported download protocol")),
-        }
+        };
+        tracker.finish(filename.as_path());
+        result
     }
     fn execute_ftp(self) -> Result<()> {
         let host = &self
@@ -95,24 +215,10 @@ impl Download {
         );
         let mut stream = ftp_stream.retr_as_stream(remote_file_name)?;
         let mut file = File::create(self.filename.as_path())?;
-        let buffer_chunks: u64 = file_size / *DOWNLOAD_MEMORY_BUFFER;
-        let chunk_remainder: u64 = file_size % *DOWNLOAD_MEMORY_BUFFER;
-        for _ in 0..buffer_chunks {
-            let mut buffer = vec![0u8; *DOWNLOAD_MEMORY_BUFFER as usize];
-            stream
-                .read_exact(&mut buffer)
-                .expect("Failed to read ftp stream into buffer");
-            file.write_all(&buffer)
-                .expect("Failed to write buffered data to file");
-        }
-        if chunk_remainder != 0 {
-            let mut buffer = Vec::with_capacity(*DOWNLOAD_MEMORY_BUFFER as usize);
-            let bytes_read = stream
-                .read_to_end(&mut buffer)
-                .expect("Failed to read ftp stream into buffer");
-            file.write_all(&buffer[..bytes_read])
-                .expect("Failed to write buffered data to file");
-        }
+        self.tracker
+            .start(self.filename.as_path(), &self.name(), Some(file_size));
+        self.copy_tracked(&mut stream, &mut file)
+            .expect("Failed to copy ftp stream to file");
         file.sync_all().expect("Failed to sync file data");
 
         Ok(())
@@ -135,12 +241,8 @@ impl Download {
         let mut additional_headers = reqwest::header::HeaderMap::new();
         while final_response.is_none() {
             let mut request = reqwest::blocking::Request::new(Method::GET, url.clone());
-            request
-                .headers_mut()
-                .extend(base_headers.clone().into_iter());
-            request
-                .headers_mut()
-                .extend(additional_headers.clone().into_iter());
+            request.headers_mut().extend(base_headers.clone());
+            request.headers_mut().extend(additional_headers.clone());
             additional_headers.clear();
 
             let response = Download::execute_request(&client, request)?;
@@ -193,11 +295,17 @@ impl Download {
             Some(file_content_length) => {
                 let start = Instant::now();
                 debug!("Starting multi-threaded download of file from {}", url);
+                self.tracker.start(
+                    self.filename.as_path(),
+                    &self.name(),
+                    Some(file_content_length),
+                );
                 let ranges = Download::calculate_ranges(file_content_length);
                 std::thread::scope(|scope| {
                     let mut children = vec![];
-                    for (range_start, range_end, buffer_chunks, chunk_remainder) in ranges {
+                    for (range_start, range_end) in ranges {
                         children.push(scope.spawn({
+                            let download = &self;
                             let filename = self.filename.as_path();
                             let client = &client;
                             let base_headers = base_headers.clone();
@@ -209,7 +317,7 @@ impl Download {
                                     .expect("Failed to seek range in file");
 
                                 let mut request = reqwest::blocking::Request::new(Method::GET, url);
-                                request.headers_mut().extend(base_headers.into_iter());
+                                request.headers_mut().extend(base_headers);
                                 request.headers_mut().insert(
                                     header::RANGE,
                                     format!("bytes={}-{}", range_start, range_end)
@@ -218,22 +326,10 @@ impl Download {
                                 );
                                 let mut file_range_res = Download::execute_request(client, request)
                                     .expect("Failed to send request to download file");
-                                for _ in 0..buffer_chunks {
-                                    let mut buffer = vec![0u8; *DOWNLOAD_MEMORY_BUFFER as usize];
-                                    let range = file_range_res.by_ref();
-                                    range
-                                        .read_exact(&mut buffer)
-                                        .expect("Failed to read reponse data into buffer");
-                                    file.write_all(&buffer)
-                                        .expect("Failed to write buffered data to file");
-                                }
+                                download
+                                    .copy_tracked(&mut file_range_res, &mut file)
+                                    .expect("Failed to copy reponse data to file");
                                 file.sync_all().expect("Failed to sync file data");
-
-                                if chunk_remainder != 0 {
-                                    file_range_res
-                                        .copy_to(&mut file)
-                                        .expect("Failed to copy remaining reponse data to file");
-                                }
                             }
                         }));
                     }
@@ -254,9 +350,14 @@ impl Download {
                 debug!("Starting single-threaded download of file from {}", url);
                 let mut request = reqwest::blocking::Request::new(Method::GET, url.clone());
                 request.headers_mut().extend(base_headers);
-                let response = Download::execute_request(&client, request)?;
-                let mut file = File::create(self.filename)?;
-                file.write_all(&response.bytes()?)?;
+                let mut response = Download::execute_request(&client, request)?;
+                let mut file = File::create(self.filename.as_path())?;
+                self.tracker.start(
+                    self.filename.as_path(),
+                    &self.name(),
+                    response.content_length(),
+                );
+                self.copy_tracked(&mut response, &mut file)?;
                 file.sync_all()?;
                 debug!(
                     "Finished single-threaded download of file from {} in {}s",
@@ -83,35 +83,25 @@ impl FileKind {
         Ok(FileKind::detect_from_reader(file))
     }
 
-    pub fn maybe_read_file(
+    /// Detects the kind of the file read from `reader` if it is one of
+    /// `accepted_file_kinds`, returning it along with the bytes read to detect it.
+    /// The rest of the file can then be read from `reader` as needed.
+    pub fn maybe_detect_from_reader(
         mut reader: impl Read,
         accepted_file_kinds: &[FileKind],
     ) -> Option<(FileKind, Vec<u8>)> {
         let mut buffer = [0u8; 1024];
         let mut data = Vec::new();
-        let mut file_type = None;
-        while let Ok(n) = reader.read(&mut buffer) {
-            if n == 0 {
-                if file_type.is_none() {
-                    file_type = Some(FileKind::detect(&data));
-                }
-                break;
-            }
-            if file_type.is_none() && data.len() > 512 {
-                let detected_file_type = FileKind::detect(&data);
-                if !accepted_file_kinds.contains(&detected_file_type) {
-                    return None;
-                }
-                file_type = Some(detected_file_type);
+        while data.len() <= 512 {
+            match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => data.extend_from_slice(&buffer[..n]),
+                Err(_) => return None,
             }
-            data.extend_from_slice(&buffer[..n]);
         }
-        if let Some(file_type) = file_type {
-            if accepted_file_kinds.contains(&file_type) {
-                Some((file_type, data))
-            } else {
-                None
-            }
+        let file_type = FileKind::detect(&data);
+        if accepted_file_kinds.contains(&file_type) {
+            Some((file_type, data))
         } else {
             None
         }
@@ -154,6 +144,21 @@ impl AsRef<Path> for HabitatSourceCachePath {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub(crate) struct HabitatKeyCachePath(PathBuf);
+
+impl HabitatKeyCachePath {
+    pub fn public_key_path(&self, key_name_with_rev: &str) -> PathBuf {
+        self.0.join(format!("{}.pub", key_name_with_rev))
+    }
+}
+
+impl AsRef<Path> for HabitatKeyCachePath {
+    fn as_ref(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub(crate) struct HabitatStudioRootPath(PathBuf);
 
@@ -182,6 +187,9 @@ impl HabitatRootPath {
     pub fn source_cache(&self) -> HabitatSourceCachePath {
         HabitatSourceCachePath(self.0.join("cache").join("src"))
     }
+    pub fn key_cache(&self) -> HabitatKeyCachePath {
+        HabitatKeyCachePath(self.0.join("cache").join("keys"))
+    }
 }
 
 impl AsRef<Path> for HabitatRootPath {
@@ -259,3 +267,52 @@ where
         Ok(())
     }
 }
+
+/// Total size of the files in a folder, without following symlinks. Entries that
+/// cannot be read, such as the files of a studio owned by root, are skipped.
+pub(crate) fn disk_usage(path: impl AsRef<Path>) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path.as_ref()) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path.as_ref())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| disk_usage(entry.path()))
+                .sum()
+        })
+        .unwrap_or_default()
+}
+
+/// File system containing a path, along with the space available on it to
+/// unprivileged users. Paths that do not exist yet are resolved to the closest
+/// existing folder containing them.
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn file_system_space(path: impl AsRef<Path>) -> Result<(u64, u64)> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt, os::unix::fs::MetadataExt};
+
+    let existing_path = path
+        .as_ref()
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or(eyre!(
+            "Failed to find the file system of '{}'",
+            path.as_ref().display()
+        ))?;
+    let device = existing_path.metadata()?.dev();
+    let c_path = CString::new(existing_path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| {
+            format!(
+                "Failed to read the free space of the file system of '{}'",
+                existing_path.display()
+            )
+        });
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Ok((device, stat.f_bavail as u64 * stat.f_frsize as u64))
+}
@@ -1,29 +1,39 @@
-#[cfg(not(target_os = "windows"))]
-use super::PackageIdent;
 use super::{
-    ArtifactCache, ArtifactCachePath, ArtifactContext, BuildStep, FSRootPath, HabitatRootPath,
-    HabitatSourceCachePath, PlanContextID,
+    ArtifactCache, ArtifactCachePath, ArtifactContext, BuildStep, BuildStepStudio, FSRootPath,
+    HabitatRootPath, HabitatSourceCachePath, HabitatStudioRootPath, PlanContextID,
 };
-use crate::store::Store;
+use super::{PackageArch, PackageIdent, PackageName, PackageOrigin, PackageTarget};
 #[cfg(not(target_os = "windows"))]
-use crate::{check::PlanContextConfig, core::PackageTarget};
+use crate::check::PlanContextConfig;
+use crate::store::Store;
 use chrono::Utc;
-use color_eyre::eyre::{eyre, Context, Result};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+#[cfg(target_os = "macos")]
 use goblin::{
     mach::{Mach, SingleArch},
     Object,
 };
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 #[cfg(not(target_os = "windows"))]
 use std::env;
 #[cfg(not(target_os = "windows"))]
 use std::process::Stdio;
+#[cfg(target_os = "macos")]
 use std::{
     collections::{BTreeSet, VecDeque},
     fmt::Write,
+};
+use std::{
+    fmt::Display,
     path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
 };
-use subprocess::{Exec, NullFile, Redirection};
+use subprocess::{Exec, ExitStatus, NullFile, Popen, Redirection};
 use tempdir::TempDir;
 use thiserror::Error;
 use tracing::{debug, error, trace};
@@ -68,18 +78,228 @@ lazy_static! {
         which("hab").expect("Failed to find hab binary in environment");
 }
 
-#[allow(dead_code)]
-const MACOS_CPU_TYPE: u32 = 16777228;
-#[allow(dead_code)]
-const MACOS_CPU_SUBTYPE: u32 = 2;
-#[allow(dead_code)]
+/// Build arguments that hab-auto-build sets itself and that cannot be overridden
+const RESERVED_BUILD_ARGS: &[&str] = &[
+    "-D",
+    "--docker",
+    "-N",
+    "--native-package",
+    "-R",
+    "--reuse",
+    "-r",
+    "--root",
+    "-s",
+    "--src",
+    "-k",
+    "--keys",
+];
+
+/// CPU type of the Mach-O slices that are loaded on the current host
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+const MACOS_CPU_TYPE: u32 = goblin::mach::cputype::CPU_TYPE_ARM64;
+#[cfg(all(target_os = "macos", not(target_arch = "aarch64")))]
+const MACOS_CPU_TYPE: u32 = goblin::mach::cputype::CPU_TYPE_X86_64;
+/// Binaries that native builds run from outside of '/hab'
+#[cfg(target_os = "macos")]
+const SANDBOX_RUNTIME_BINARIES: &[&str] = &["hab", "bash", "env", "basename", "dirname"];
+#[cfg(target_os = "macos")]
 const SANDBOX_DEFAULTS: &str = include_str!("../scripts/sandbox-defaults.sb");
 
+/// Validates extra arguments for the build command of a plan
+pub(crate) fn build_args_validate(args: &[String]) -> Result<()> {
+    for arg in args {
+        if arg.is_empty() || arg.contains(['\0', '\n']) {
+            return Err(eyre!("Invalid build argument {:?}", arg));
+        }
+        let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+        if RESERVED_BUILD_ARGS.contains(&flag) {
+            return Err(eyre!(
+                "The build argument '{}' is managed by hab-auto-build and cannot be specified",
+                flag
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn artifact_promote(artifact_ident: &PackageIdent, channel: &str) -> Result<()> {
+    debug!("Promoting {} to channel '{}'", artifact_ident, channel);
+    let capture = Exec::cmd(HAB_BINARY.as_path())
+        .arg("pkg")
+        .arg("promote")
+        .arg(format!(
+            "{}/{}/{}/{}",
+            artifact_ident.origin,
+            artifact_ident.name,
+            artifact_ident.version,
+            artifact_ident.release
+        ))
+        .arg(channel)
+        .arg(artifact_ident.target.to_string())
+        .env("HAB_LICENSE", "accept-no-persist")
+        .stdin(NullFile)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Merge)
+        .capture()?;
+    if !capture.success() {
+        return Err(eyre!(
+            "Failed to promote {} to channel '{}': {}",
+            artifact_ident,
+            channel,
+            capture.stdout_str().trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Name of the architecture of a package target in Docker images and daemons
+pub(crate) fn docker_arch(arch: PackageArch) -> &'static str {
+    match arch {
+        PackageArch::X86_64 => "amd64",
+        PackageArch::Aarch64 => "arm64",
+    }
+}
+
+/// Verifies that the Docker daemon is reachable and returns its architecture
+pub(crate) fn docker_daemon_arch() -> Result<String> {
+    let docker_binary =
+        which("docker").map_err(|_| eyre!("Failed to find docker binary in environment"))?;
+    let capture = Exec::cmd(docker_binary)
+        .arg("version")
+        .arg("--format")
+        .arg("{{.Server.Arch}}")
+        .stdin(NullFile)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Merge)
+        .capture()?;
+    if !capture.success() {
+        return Err(eyre!(
+            "Failed to connect to the Docker daemon: {}",
+            capture.stdout_str().trim()
+        ));
+    }
+    Ok(capture.stdout_str().trim().to_string())
+}
+
+/// Returns the architecture of a Docker image, or `None` if the image has not
+/// been pulled yet.
+pub(crate) fn docker_image_arch(docker_image: &str) -> Result<Option<String>> {
+    let capture = Exec::cmd("docker")
+        .arg("image")
+        .arg("inspect")
+        .arg("--format")
+        .arg("{{.Architecture}}")
+        .arg(docker_image)
+        .stdin(NullFile)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe)
+        .capture()?;
+    if !capture.success() {
+        debug!(
+            "Failed to inspect Docker image '{}': {}",
+            docker_image,
+            capture.stderr_str().trim()
+        );
+        return Ok(None);
+    }
+    Ok(Some(capture.stdout_str().trim().to_string()))
+}
+
+/// Prefix of the names of the studios created by hab-auto-build for builds
+const BUILD_STUDIO_NAME_PREFIX: &str = "hab-auto-build-";
+
+pub(crate) fn build_studio_root(id: u64) -> HabitatStudioRootPath {
+    HabitatRootPath::default().studio_root(format!("{}{}", BUILD_STUDIO_NAME_PREFIX, id).as_str())
+}
+
+/// Lists the roots of all the build studios created by hab-auto-build, including
+/// the ones left over from previous sessions.
+pub(crate) fn build_studio_roots() -> Result<Vec<PathBuf>> {
+    let studios_path = HabitatRootPath::default().as_ref().join("studios");
+    if !studios_path.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut studio_roots = Vec::new();
+    for entry in std::fs::read_dir(&studios_path)
+        .with_context(|| format!("Failed to read studios folder '{}'", studios_path.display()))?
+    {
+        let entry = entry?;
+        if entry
+            .file_name()
+            .to_str()
+            .map(|name| name.starts_with(BUILD_STUDIO_NAME_PREFIX))
+            .unwrap_or_default()
+            && entry.file_type()?.is_dir()
+        {
+            studio_roots.push(entry.path());
+        }
+    }
+    studio_roots.sort();
+    Ok(studio_roots)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn studio_remove(
+    studio_root: &Path,
+    sandbox_command: &BuildSandboxCommand,
+) -> Result<()> {
+    debug!("Removing studio at '{}'", studio_root.display());
+    let capture = sandbox_command
+        .exec(HAB_BINARY.as_path())
+        .arg("studio")
+        .arg("-r")
+        .arg(studio_root)
+        .arg("rm")
+        .env("HAB_LICENSE", "accept-no-persist")
+        .stdin(NullFile)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Merge)
+        .capture()?;
+    if !capture.success() {
+        return Err(eyre!(
+            "Failed to remove studio at '{}': {}",
+            studio_root.display(),
+            capture.stdout_str().trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn studio_remove(
+    studio_root: &Path,
+    _sandbox_command: &BuildSandboxCommand,
+) -> Result<()> {
+    debug!("Removing studio at '{}'", studio_root.display());
+    let capture = Exec::cmd("powershell")
+        .arg("-Command")
+        .arg("hab")
+        .arg("studio")
+        .arg("rm")
+        .env("HAB_LICENSE", "accept-no-persist")
+        .env("HAB_STUDIO_ROOT", get_normalized_path(studio_root))
+        .stdin(NullFile)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Merge)
+        .capture()?;
+    if !capture.success() {
+        return Err(eyre!(
+            "Failed to remove studio at '{}': {}",
+            studio_root.display(),
+            capture.stdout_str().trim()
+        ));
+    }
+    Ok(())
+}
+
 #[cfg(not(target_os = "windows"))]
-pub(crate) fn install_artifact_offline(package_ident: &PackageIdent) -> Result<()> {
+pub(crate) fn install_artifact_offline(
+    package_ident: &PackageIdent,
+    sandbox_command: &BuildSandboxCommand,
+) -> Result<()> {
     debug!("Installing habitat package {}", package_ident);
-    let exit_status = std::process::Command::new("sudo")
-        .arg("-E")
+    let exit_status = std::process::Command::new(sandbox_command.program())
+        .args(&sandbox_command.0[1..])
         .arg(HAB_BINARY.as_path())
         .arg("pkg")
         .arg("install")
@@ -107,6 +327,38 @@ pub(crate) fn install_artifact_offline(package_ident: &PackageIdent) -> Result<(
     }
 }
 
+#[cfg(target_os = "windows")]
+pub(crate) fn install_artifact_offline(
+    package_ident: &PackageIdent,
+    _sandbox_command: &BuildSandboxCommand,
+) -> Result<()> {
+    debug!("Installing habitat package {}", package_ident);
+    let capture = Exec::cmd("powershell")
+        .arg("-Command")
+        .arg("hab")
+        .arg("pkg")
+        .arg("install")
+        .arg(get_normalized_path(
+            ArtifactCachePath::default()
+                .artifact_path(package_ident)
+                .as_ref(),
+        ))
+        .env("HAB_LICENSE", "accept-no-persist")
+        .stdin(NullFile)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Merge)
+        .capture()?;
+    if capture.success() {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "Failed to install package {}: {}",
+            package_ident,
+            capture.stdout_str().trim()
+        ))
+    }
+}
+
 fn copy_source_to_cache(
     build_step: &BuildStep,
     store: &Store,
@@ -119,7 +371,9 @@ fn copy_source_to_cache(
                 source_cache_folder.as_ref().display()
             )
         })?;
-        let store_archive = store.package_source_store_path(source).archive_data_path();
+        let store_archive = store
+            .package_source_store_path(&source.shasum)
+            .archive_data_path();
         let source_cache_path = source_cache_folder.as_ref().join(source.url.filename()?);
         if !source_cache_path.exists() {
             trace!(
@@ -151,97 +405,57 @@ fn copy_source_to_cache(
     Ok(())
 }
 
+/// Extension of the files describing a build that the plan build script writes
+/// to the build output folder
 #[cfg(target_os = "windows")]
-fn copy_build_success_output(
-    store: &Store,
-    _build_step: &BuildStep,
-    build_log_path: impl AsRef<Path>,
+const BUILD_ENV_FILE_EXTENSION: &str = "ps1";
+#[cfg(not(target_os = "windows"))]
+const BUILD_ENV_FILE_EXTENSION: &str = "env";
+
+/// Reads a variable from a file describing a build in the build output folder,
+/// like 'last_build.env'. Variables are written as `name=value` by the plan build
+/// script on Linux and macOS, and as `$name="value"` on Windows.
+fn build_env_file_var(
     build_output_path: impl AsRef<Path>,
-) -> Result<(PathBuf, PathBuf)> {
-    let last_build_path = build_output_path.as_ref().join("last_build.ps1");
-    let last_build = std::fs::read_to_string(&last_build_path).with_context(|| {
+    file_stem: &str,
+    name: &str,
+) -> Result<String> {
+    let build_env_path = build_output_path
+        .as_ref()
+        .join(format!("{}.{}", file_stem, BUILD_ENV_FILE_EXTENSION));
+    let build_env = std::fs::read_to_string(&build_env_path).with_context(|| {
         format!(
-            "Failed to read last build file at '{}'",
-            last_build_path.display()
+            "Failed to read {} file at '{}'",
+            file_stem.replace('_', " "),
+            build_env_path.display()
         )
     })?;
-    let artifact_name = last_build
+    build_env
         .lines()
-        .filter_map(|l| l.strip_prefix("$pkg_artifact="))
-        .next()
-        .unwrap()
-        .trim()
-        .trim_matches('"'); // Trims double quotes
-    let final_build_artifacts_dir_path = store.package_build_artifacts_path();
-    std::fs::create_dir_all(final_build_artifacts_dir_path.as_ref()).with_context(|| {
-        format!(
-            "Failed to create build artifact directory at '{}'",
-            final_build_artifacts_dir_path.as_ref().display()
-        )
-    })?;
-    let artifact_path = build_output_path.as_ref().join(artifact_name);
-    let final_artifact_path = final_build_artifacts_dir_path.as_ref().join(artifact_name);
-
-    let final_build_log_dir_path = store.package_build_success_logs_path();
-    std::fs::create_dir_all(final_build_log_dir_path.as_ref()).with_context(|| {
-        format!(
-            "Failed to create build log directory at '{}'",
-            final_build_log_dir_path.as_ref().display()
-        )
-    })?;
-    let final_build_log_path = final_build_log_dir_path.as_ref().join(format!(
-        "{}.log",
-        artifact_name.strip_suffix(".hart").unwrap()
-    ));
-    debug!(
-        "Moving build log from {} to {}",
-        build_log_path.as_ref().display(),
-        final_build_log_path.display()
-    );
-    std::fs::rename(build_log_path.as_ref(), final_build_log_path.as_path()).with_context(
-        || {
-            format!(
-                "Failed to move build log from {} to {}",
-                build_log_path.as_ref().display(),
-                final_build_log_path.display()
+        .find_map(|line| {
+            line.trim()
+                .trim_start_matches('$')
+                .strip_prefix(name)?
+                .strip_prefix('=')
+                .map(|value| value.trim().trim_matches('"').to_string())
+        })
+        .ok_or_else(|| {
+            eyre!(
+                "Failed to find '{}' in {} file at '{}'",
+                name,
+                file_stem.replace('_', " "),
+                build_env_path.display()
             )
-        },
-    )?;
-    debug!(
-        "Moving build artifact from {} to {}",
-        artifact_path.display(),
-        final_artifact_path.display()
-    );
-    std::fs::rename(artifact_path.as_path(), final_artifact_path.as_path()).with_context(|| {
-        format!(
-            "Failed to move build artifact from {} to {}",
-            artifact_path.display(),
-            final_artifact_path.display()
-        )
-    })?;
-    Ok((final_artifact_path, final_build_log_path))
+        })
 }
 
-#[cfg(not(target_os = "windows"))]
 fn copy_build_success_output(
     store: &Store,
     _build_step: &BuildStep,
     build_log_path: impl AsRef<Path>,
     build_output_path: impl AsRef<Path>,
-) -> Result<(PathBuf, PathBuf)> {
-    let last_build_path = build_output_path.as_ref().join("last_build.env");
-    let last_build = std::fs::read_to_string(&last_build_path).with_context(|| {
-        format!(
-            "Failed to read last build file at '{}'",
-            last_build_path.display()
-        )
-    })?;
-    let artifact_name = last_build
-        .lines()
-        .filter_map(|l| l.strip_prefix("pkg_artifact="))
-        .next()
-        .unwrap()
-        .trim();
+) -> Result<(ArtifactContext, PathBuf)> {
+    let artifact_name = build_env_file_var(&build_output_path, "last_build", "pkg_artifact")?;
     let final_build_artifacts_dir_path = store.package_build_artifacts_path();
     std::fs::create_dir_all(final_build_artifacts_dir_path.as_ref()).with_context(|| {
         format!(
@@ -249,8 +463,8 @@ fn copy_build_success_output(
             final_build_artifacts_dir_path.as_ref().display()
         )
     })?;
-    let artifact_path = build_output_path.as_ref().join(artifact_name);
-    let final_artifact_path = final_build_artifacts_dir_path.as_ref().join(artifact_name);
+    let artifact_path = build_output_path.as_ref().join(&artifact_name);
+    let final_artifact_path = final_build_artifacts_dir_path.as_ref().join(&artifact_name);
 
     let final_build_log_dir_path = store.package_build_success_logs_path();
     std::fs::create_dir_all(final_build_log_dir_path.as_ref()).with_context(|| {
@@ -278,18 +492,52 @@ fn copy_build_success_output(
         },
     )?;
     debug!(
-        "Moving build artifact from {} to {}",
+        "Moving and indexing build artifact from {} to {}",
         artifact_path.display(),
         final_artifact_path.display()
     );
-    std::fs::rename(artifact_path.as_path(), final_artifact_path.as_path()).with_context(|| {
-        format!(
-            "Failed to move build artifact from {} to {}",
-            artifact_path.display(),
-            final_artifact_path.display()
-        )
-    })?;
-    Ok((final_artifact_path, final_build_log_path))
+    let artifact =
+        ArtifactContext::move_and_read(artifact_path.as_path(), final_artifact_path.as_path())
+            .with_context(|| {
+                format!(
+                    "Failed to move and index build artifact from {} to {}",
+                    artifact_path.display(),
+                    final_artifact_path.display()
+                )
+            })?;
+    Ok((artifact, final_build_log_path))
+}
+
+/// Prefix of the temporary folder a plan is built in, it names the plan so that
+/// the log of a build in progress can be found. Origins and names cannot contain
+/// a '.', so the parts of the prefix can be split apart again.
+fn build_temp_dir_prefix(kind: &str, build_step: &BuildStep) -> String {
+    let build_id = build_step.plan_ctx.id.as_ref();
+    format!(
+        "{}.{}.{}.{}",
+        kind, build_id.origin, build_id.name, build_id.target
+    )
+}
+
+/// Checks if a temporary folder in the store is the build folder of the given plan
+pub(crate) fn build_temp_dir_is_for(
+    path: impl AsRef<Path>,
+    origin: &PackageOrigin,
+    name: &PackageName,
+    target: PackageTarget,
+) -> bool {
+    let Some(dir_name) = path.as_ref().file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let parts = dir_name.split('.').collect::<Vec<_>>();
+    matches!(
+        parts.as_slice(),
+        [kind, dir_origin, dir_name, dir_target, _]
+            if kind.ends_with("-build")
+                && *dir_origin == origin.to_string()
+                && *dir_name == name.to_string()
+                && *dir_target == target.to_string()
+    )
 }
 
 fn copy_build_failure_output(
@@ -298,20 +546,8 @@ fn copy_build_failure_output(
     build_log_path: impl AsRef<Path>,
     build_output_path: impl AsRef<Path>,
 ) -> Result<PathBuf> {
-    let pre_build_path = build_output_path.as_ref().join("pre_build.env");
-    let pkg_ident = match std::fs::read_to_string(&pre_build_path).with_context(|| {
-        format!(
-            "Failed to read pre build file at '{}'",
-            pre_build_path.display()
-        )
-    }) {
-        Ok(pre_build) => pre_build
-            .lines()
-            .filter_map(|l| l.strip_prefix("pkg_ident="))
-            .next()
-            .unwrap()
-            .trim()
-            .replace('/', "-"),
+    let pkg_ident = match build_env_file_var(&build_output_path, "pre_build", "pkg_ident") {
+        Ok(pkg_ident) => pkg_ident.replace('/', "-"),
         Err(err) => {
             debug!("Failed to find pre_build file: {:#}", err);
             let build_id = build_step.plan_ctx.id.as_ref();
@@ -353,6 +589,102 @@ fn copy_build_failure_output(
     Ok(final_build_log_path)
 }
 
+/// Time given to a timed out build to exit after it is asked to terminate,
+/// before it is killed
+const BUILD_TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Interval at which a running build checks whether its build step was cancelled
+const BUILD_CANCELLATION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How a build process stopped being waited on
+enum BuildProcessWait {
+    Exited(ExitStatus),
+    TimedOut,
+    Cancelled,
+}
+
+/// Waits for a build process to exit, until the timeout passes or the build step
+/// is cancelled. The process is left running in the last two cases.
+fn build_process_wait(
+    process: &mut Popen,
+    build_step: &BuildStep,
+    timeout: Option<Duration>,
+) -> Result<BuildProcessWait, BuildError> {
+    let started_at = Instant::now();
+    loop {
+        let poll_interval = match timeout {
+            Some(timeout) => match timeout.checked_sub(started_at.elapsed()) {
+                Some(remaining) if !remaining.is_zero() => {
+                    remaining.min(BUILD_CANCELLATION_POLL_INTERVAL)
+                }
+                _ => return Ok(BuildProcessWait::TimedOut),
+            },
+            None => BUILD_CANCELLATION_POLL_INTERVAL,
+        };
+        if let Some(exit_status) = process.wait_timeout(poll_interval)? {
+            return Ok(BuildProcessWait::Exited(exit_status));
+        }
+        if build_step.cancellation.is_cancelled() {
+            return Ok(BuildProcessWait::Cancelled);
+        }
+    }
+}
+
+/// Asks a build process to terminate and kills it if it does not exit in time
+fn build_process_stop(process: &mut Popen) -> Result<ExitStatus, BuildError> {
+    process.terminate()?;
+    if let Some(exit_status) = process.wait_timeout(BUILD_TERMINATE_GRACE_PERIOD)? {
+        return Ok(exit_status);
+    }
+    process.kill()?;
+    Ok(process.wait()?)
+}
+
+/// Runs a build command to completion. If the build step has a timeout and the
+/// build exceeds it, or the build step is cancelled, the build is terminated and
+/// its log is preserved like the log of a failed build.
+fn build_cmd_join(
+    cmd: Exec,
+    store: &Store,
+    build_step: &BuildStep,
+    build_log_path: impl AsRef<Path>,
+    build_output_path: impl AsRef<Path>,
+) -> Result<ExitStatus, BuildError> {
+    let mut process = cmd.popen()?;
+    let timeout = build_step.timeout_secs.map(Duration::from_secs);
+    match build_process_wait(&mut process, build_step, timeout)? {
+        BuildProcessWait::Exited(exit_status) => Ok(exit_status),
+        BuildProcessWait::TimedOut => {
+            let timeout_secs = build_step.timeout_secs.unwrap_or_default();
+            debug!(
+                "Build of {} exceeded timeout of {}s, terminating it",
+                build_step.plan_ctx.id, timeout_secs
+            );
+            build_process_stop(&mut process)?;
+            let build_log_path =
+                copy_build_failure_output(store, build_step, build_log_path, build_output_path)?;
+            Err(BuildError::TimedOut(
+                build_step.plan_ctx.id.clone(),
+                build_log_path,
+                timeout_secs,
+            ))
+        }
+        BuildProcessWait::Cancelled => {
+            debug!(
+                "Build of {} was cancelled, terminating it",
+                build_step.plan_ctx.id
+            );
+            build_process_stop(&mut process)?;
+            let build_log_path =
+                copy_build_failure_output(store, build_step, build_log_path, build_output_path)?;
+            Err(BuildError::Cancelled(
+                build_step.plan_ctx.id.clone(),
+                build_log_path,
+            ))
+        }
+    }
+}
+
 pub(crate) struct BuildOutput {
     pub artifact: ArtifactContext,
     pub build_log: PathBuf,
@@ -368,6 +700,10 @@ pub(crate) enum BuildError {
     #[error("Failed to build standard package {0}, you can find the build log at {1}")]
     #[allow(dead_code)]
     Standard(PlanContextID, PathBuf),
+    #[error("Build of package {0} timed out after {2}s, you can find the build log at {1}")]
+    TimedOut(PlanContextID, PathBuf, u64),
+    #[error("Build of package {0} was cancelled, you can find the build log at {1}")]
+    Cancelled(PlanContextID, PathBuf),
     #[error("Failed due to unexpected IO error")]
     IO(#[from] std::io::Error),
     #[error("Failed due to unexpected sub process error")]
@@ -384,7 +720,11 @@ pub(crate) fn native_package_build(
 ) -> Result<BuildOutput, BuildError> {
     let tmp_path = store.temp_dir_path();
     std::fs::create_dir_all(tmp_path.as_ref())?;
-    let tmp_dir = TempDir::new_in(tmp_path.as_ref(), "native-build").with_context(|| {
+    let tmp_dir = TempDir::new_in(
+        tmp_path.as_ref(),
+        &build_temp_dir_prefix("native-build", build_step),
+    )
+    .with_context(|| {
         format!(
             "Failed to create temporary directory in hab-auto-build store at '{}'",
             tmp_path.as_ref().display()
@@ -419,97 +759,36 @@ pub(crate) fn native_package_build(
             docker_image,
             build_log_path.display()
         );
-        let deps_to_install = build_step
-            .deps_to_install
-            .iter()
-            .filter_map(|dep| artifact_cache.latest_plan_minimal_artifact(dep))
-            .map(|artifact| {
-                format!(
-                    "{}",
-                    ArtifactCachePath::new(HabitatRootPath::default())
-                        .as_ref()
-                        .join(artifact.id.artifact_name())
-                        .display(),
-                )
-            })
-            .collect::<Vec<String>>()
-            .join(":");
         let container_name = "hab-auto-build-native";
-        let container_id_output = std::process::Command::new("docker")
-            .args(["ps", "-aqf", &format!("name={}", container_name)])
-            .output()?;
-        let container_id = String::from_utf8_lossy(&container_id_output.stdout);
-        let container_id = container_id.trim();
-        if !container_id.is_empty() {
-            let exit_status = Exec::cmd("docker").arg("rm").arg(container_name).join()?;
-            if !exit_status.success() {
-                return Err(BuildError::Unexpected(eyre!(
-                    "Failed to remove Docker container '{}'",
-                    container_name
-                )));
-            }
-        }
-
-        cmd = Exec::cmd("docker")
-            .arg("run")
-            .arg("-it")
-            .arg("--name")
-            .arg(container_name)
-            .arg("-v")
-            .arg(format!(
-                "{}:/src",
-                build_step.repo_ctx.path.as_ref().display()
-            ));
-        if let Some(source) = &build_step.plan_ctx.source {
-            let source_cache_folder = HabitatRootPath::default().source_cache();
-            let store_archive = store.package_source_store_path(source).archive_data_path();
-            let source_cache_path = source_cache_folder.as_ref().join(source.url.filename()?);
-            cmd = cmd.arg("-v").arg(format!(
-                "{}:{}",
-                store_archive.as_ref().display(),
-                source_cache_path.display()
-            ));
-        }
-        if !build_step.allow_remote {
-            cmd = cmd.arg("-e").arg("HAB_BLDR_URL=https://non-existent");
-        }
-        cmd = cmd
-            .arg("-v")
-            .arg(format!("{}:/bin/hab", HAB_BINARY.display()))
-            .arg("-v")
-            .arg(format!("{}:/output", build_output_dir.display()))
-            .arg("-v")
-            .arg("/hab/cache/artifacts:/hab/cache/artifacts")
-            .arg("-v")
-            .arg("/hab/cache/keys:/hab/cache/keys")
-            .arg("--workdir")
-            .arg("/src")
-            .arg("-e")
-            .arg(format!("HAB_STUDIO_INSTALL_PKGS={}", deps_to_install))
-            .arg("-e")
-            .arg("NO_INSTALL_DEPS=1")
-            .arg("-e")
-            .arg("HAB_LICENSE=accept")
-            .arg("-e")
-            .arg("HAB_FEAT_NATIVE_PACKAGE_SUPPORT=1")
-            .arg("-e")
-            .arg("HAB_OUTPUT_PATH=/output")
-            .arg("-e")
-            .arg(format!(
-                "HAB_ORIGIN={}",
-                build_step.plan_ctx.id.as_ref().origin
-            ))
-            .arg("-e")
-            .arg(format!("BUILD_PKG_TARGET={}", PackageTarget::default()))
-            .arg(docker_image)
-            .arg("build")
-            .arg(relative_plan_context)
-            .cwd(build_step.repo_ctx.path.as_ref())
-            .stdin(Redirection::None)
-            .stdout(Redirection::File(build_log))
-            .stderr(Redirection::Merge);
+        cmd = docker_build_exec(
+            build_step,
+            artifact_cache,
+            store,
+            container_name,
+            build_output_dir,
+        )?
+        .arg("-it")
+        .arg("-e")
+        .arg("HAB_FEAT_NATIVE_PACKAGE_SUPPORT=1")
+        .arg("-e")
+        .arg(format!("BUILD_PKG_TARGET={}", PackageTarget::default()))
+        .arg(docker_image)
+        .arg("build")
+        .args(&build_step.build_args)
+        .arg(relative_plan_context)
+        .cwd(build_step.repo_ctx.path.as_ref())
+        .stdin(Redirection::None)
+        .stdout(Redirection::File(build_log))
+        .stderr(Redirection::Merge);
         trace!("Executing command: {:?}", cmd);
-        exit_status = cmd.join()?;
+        exit_status = docker_build_join(
+            cmd,
+            store,
+            build_step,
+            &build_log_path,
+            build_output_dir,
+            container_name,
+        )?;
     } else {
         debug!(
             "Starting build of native package {}, logging output to {}",
@@ -521,14 +800,15 @@ pub(crate) fn native_package_build(
             store,
             &HabitatRootPath::default().source_cache(),
         )?;
-        cmd = Exec::cmd("sudo")
-            .arg("-E")
-            .arg("env")
+        cmd = build_step
+            .sandbox_command
+            .exec("env")
             .arg(format!("PATH={}", env::var("PATH").unwrap_or_default()))
             .arg(HAB_BINARY.as_path())
             .arg("pkg")
             .arg("build")
             .arg("-N")
+            .args(&build_step.build_args)
             .arg(relative_plan_context)
             .env("HAB_FEAT_NATIVE_PACKAGE_SUPPORT", "1")
             .env("HAB_OUTPUT_PATH", tmp_dir.path())
@@ -545,21 +825,14 @@ pub(crate) fn native_package_build(
             cmd = cmd.env("HAB_BLDR_URL", "https://non-existent");
         }
         trace!("Executing command: {:?}", cmd);
-        exit_status = cmd.join()?;
+        exit_status = build_cmd_join(cmd, store, build_step, &build_log_path, build_output_dir)?;
     }
 
     if exit_status.success() {
-        let (artifact_path, build_log_path) =
+        let (artifact, build_log_path) =
             copy_build_success_output(store, build_step, &build_log_path, build_output_dir)?;
         Ok(BuildOutput {
-            artifact: ArtifactContext::read_from_disk(artifact_path.as_path(), None).with_context(
-                || {
-                    format!(
-                        "Failed to index built artifact: {}",
-                        artifact_path.display()
-                    )
-                },
-            )?,
+            artifact,
             build_log: build_log_path,
         })
     } else {
@@ -572,93 +845,359 @@ pub(crate) fn native_package_build(
     }
 }
 
-#[allow(dead_code)]
+/// Prepares the command that builds a plan in a Docker container. Any container
+/// left over with the same name is removed first. The container is given the
+/// plan's repo, its source, the artifact and key caches, and the build output
+/// folder, the image, its arguments and any extra options are left to the caller.
+#[cfg(target_os = "linux")]
+fn docker_build_exec(
+    build_step: &BuildStep,
+    artifact_cache: &ArtifactCache,
+    store: &Store,
+    container_name: &str,
+    build_output_dir: &Path,
+) -> Result<Exec, BuildError> {
+    let deps_to_install = build_step
+        .deps_to_install
+        .iter()
+        .filter_map(|dep| artifact_cache.latest_plan_minimal_artifact(dep))
+        .map(|artifact| {
+            format!(
+                "{}",
+                ArtifactCachePath::new(HabitatRootPath::default())
+                    .as_ref()
+                    .join(artifact.id.artifact_name())
+                    .display(),
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(":");
+    let container_id_output = std::process::Command::new("docker")
+        .args(["ps", "-aqf", &format!("name={}", container_name)])
+        .output()?;
+    let container_id = String::from_utf8_lossy(&container_id_output.stdout);
+    let container_id = container_id.trim();
+    if !container_id.is_empty() {
+        let exit_status = Exec::cmd("docker").arg("rm").arg(container_name).join()?;
+        if !exit_status.success() {
+            return Err(BuildError::Unexpected(eyre!(
+                "Failed to remove Docker container '{}'",
+                container_name
+            )));
+        }
+    }
+
+    let mut cmd = Exec::cmd("docker")
+        .arg("run")
+        .arg("--name")
+        .arg(container_name)
+        .arg("-v")
+        .arg(format!(
+            "{}:/src",
+            build_step.repo_ctx.path.as_ref().display()
+        ));
+    if let Some(source) = &build_step.plan_ctx.source {
+        let source_cache_folder = HabitatRootPath::default().source_cache();
+        let store_archive = store
+            .package_source_store_path(&source.shasum)
+            .archive_data_path();
+        let source_cache_path = source_cache_folder.as_ref().join(source.url.filename()?);
+        cmd = cmd.arg("-v").arg(format!(
+            "{}:{}",
+            store_archive.as_ref().display(),
+            source_cache_path.display()
+        ));
+    }
+    if !build_step.allow_remote {
+        cmd = cmd.arg("-e").arg("HAB_BLDR_URL=https://non-existent");
+    }
+    Ok(cmd
+        .arg("-v")
+        .arg(format!("{}:/bin/hab", HAB_BINARY.display()))
+        .arg("-v")
+        .arg(format!("{}:/output", build_output_dir.display()))
+        .arg("-v")
+        .arg("/hab/cache/artifacts:/hab/cache/artifacts")
+        .arg("-v")
+        .arg("/hab/cache/keys:/hab/cache/keys")
+        .arg("--workdir")
+        .arg("/src")
+        .arg("-e")
+        .arg(format!("HAB_STUDIO_INSTALL_PKGS={}", deps_to_install))
+        .arg("-e")
+        .arg("NO_INSTALL_DEPS=1")
+        .arg("-e")
+        .arg("HAB_LICENSE=accept")
+        .arg("-e")
+        .arg("HAB_OUTPUT_PATH=/output")
+        .arg("-e")
+        .arg(format!(
+            "HAB_ORIGIN={}",
+            build_step.plan_ctx.id.as_ref().origin
+        )))
+}
+
+/// Runs a build in a Docker container to completion, like `build_cmd_join`. The
+/// container keeps running after the docker client is terminated, so it is killed
+/// when the build times out or is cancelled.
+#[cfg(target_os = "linux")]
+fn docker_build_join(
+    cmd: Exec,
+    store: &Store,
+    build_step: &BuildStep,
+    build_log_path: impl AsRef<Path>,
+    build_output_path: impl AsRef<Path>,
+    container_name: &str,
+) -> Result<ExitStatus, BuildError> {
+    match build_cmd_join(cmd, store, build_step, build_log_path, build_output_path) {
+        Err(err @ (BuildError::TimedOut(..) | BuildError::Cancelled(..))) => {
+            let exit_status = Exec::cmd("docker").arg("kill").arg(container_name).join()?;
+            if !exit_status.success() {
+                error!("Failed to kill Docker container '{}'", container_name);
+            }
+            Err(err)
+        }
+        result => result,
+    }
+}
+
+/// Builds a standard plan in a Docker container instead of a studio on the host,
+/// which does not require the studio or root access to be set up on the host.
+#[cfg(target_os = "linux")]
+pub(crate) fn docker_package_build(
+    build_step: &BuildStep,
+    docker_image: &str,
+    artifact_cache: &ArtifactCache,
+    store: &Store,
+    id: u64,
+) -> Result<BuildOutput, BuildError> {
+    let tmp_path = store.temp_dir_path();
+    std::fs::create_dir_all(tmp_path.as_ref())?;
+    let tmp_dir = TempDir::new_in(
+        tmp_path.as_ref(),
+        &build_temp_dir_prefix("docker-build", build_step),
+    )
+    .with_context(|| {
+        format!(
+            "Failed to create temporary directory in hab-auto-build store at '{}'",
+            tmp_path.as_ref().display()
+        )
+    })?;
+    let build_log_path = tmp_dir.path().join("build.log");
+    let build_log = std::fs::File::create(&build_log_path).with_context(|| {
+        format!(
+            "Failed to create build log at '{}'",
+            build_log_path.display()
+        )
+    })?;
+    let build_output_dir = tmp_dir.path();
+    let relative_plan_context =
+        if build_step.plan_ctx.context_path.as_ref() == build_step.repo_ctx.path.as_ref() {
+            PathBuf::from(".")
+        } else {
+            build_step
+                .plan_ctx
+                .context_path
+                .as_ref()
+                .strip_prefix(&build_step.repo_ctx.path)
+                .unwrap()
+                .to_path_buf()
+        };
+    debug!(
+        "Starting build of standard package {} with image {}, logging output to {}",
+        relative_plan_context.display(),
+        docker_image,
+        build_log_path.display()
+    );
+
+    let container_name = format!("{}{}", BUILD_STUDIO_NAME_PREFIX, id);
+    let cmd = docker_build_exec(
+        build_step,
+        artifact_cache,
+        store,
+        &container_name,
+        build_output_dir,
+    )?
+    .arg("--rm")
+    .arg(docker_image)
+    .arg("build")
+    .args(&build_step.build_args)
+    .arg(relative_plan_context)
+    .cwd(build_step.repo_ctx.path.as_ref())
+    .stdin(NullFile)
+    .stdout(Redirection::File(build_log))
+    .stderr(Redirection::Merge);
+    trace!("Executing command: {:?}", cmd);
+    let exit_status = docker_build_join(
+        cmd,
+        store,
+        build_step,
+        &build_log_path,
+        build_output_dir,
+        &container_name,
+    )?;
+
+    if exit_status.success() {
+        let (artifact, build_log_path) =
+            copy_build_success_output(store, build_step, &build_log_path, build_output_dir)?;
+        Ok(BuildOutput {
+            artifact,
+            build_log: build_log_path,
+        })
+    } else {
+        let build_log_path =
+            copy_build_failure_output(store, build_step, &build_log_path, build_output_dir)?;
+        Err(BuildError::Standard(
+            build_step.plan_ctx.id.clone(),
+            build_log_path,
+        ))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn docker_package_build(
+    _build_step: &BuildStep,
+    _docker_image: &str,
+    _artifact_cache: &ArtifactCache,
+    _store: &Store,
+    _id: u64,
+) -> Result<BuildOutput, BuildError> {
+    Err(BuildError::Unexpected(eyre!(
+        "Building standard packages with Docker is only supported on Linux"
+    )))
+}
+
+/// Finds the binary along with every library it loads, directly or through
+/// other libraries, by absolute path
+#[cfg(target_os = "macos")]
 fn compute_binary_impurities(binary_path: impl AsRef<Path>) -> Result<BTreeSet<PathBuf>> {
     let mut impure_paths = BTreeSet::new();
     let mut unvisted_paths = VecDeque::new();
     impure_paths.insert(binary_path.as_ref().to_path_buf());
     unvisted_paths.push_back(binary_path.as_ref().to_path_buf());
-    while !unvisted_paths.is_empty() {
-        if let Some(current_object_path) = unvisted_paths.pop_front() {
-            let data = std::fs::read(current_object_path)?;
-            let macho = Object::parse(&data)?;
-            if let Object::Mach(macho) = macho {
-                match macho {
-                    Mach::Fat(archs) => {
-                        for index in 0..archs.narches {
-                            let arch = archs.get(index)?;
-                            match arch {
-                                SingleArch::MachO(arch) => {
-                                    if arch.header.cputype == MACOS_CPU_TYPE
-                                        && arch.header.cpusubtype == MACOS_CPU_SUBTYPE
-                                    {
-                                        for lib in arch.libs {
-                                            let library_path = PathBuf::from(lib);
-                                            if library_path.is_absolute() {
-                                                // if MACOS_SYSTEM_LIBS.contains(&lib) {
-                                                //     continue;
-                                                // }
-                                                impure_paths.insert(library_path.clone());
-                                                if !impure_paths.contains(&library_path) {
-                                                    unvisted_paths.push_back(library_path.clone());
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                SingleArch::Archive(_) => {}
-                            }
-                        }
-                    }
-                    Mach::Binary(arch) => {
-                        for lib in arch.libs {
-                            let library_path = PathBuf::from(lib);
-                            if library_path.is_absolute() {
-                                // if MACOS_SYSTEM_LIBS.contains(&lib) {
-                                //     continue;
-                                // }
-                                impure_paths.insert(library_path.clone());
-                                if !impure_paths.contains(&library_path) {
-                                    unvisted_paths.push_back(library_path.clone());
-                                }
-                            }
+    while let Some(current_object_path) = unvisted_paths.pop_front() {
+        // Libraries in the dyld shared cache do not exist on disk and are
+        // already allowed by the sandbox defaults
+        let Ok(data) = std::fs::read(&current_object_path) else {
+            continue;
+        };
+        let libs = match Object::parse(&data)? {
+            Object::Mach(Mach::Fat(archs)) => {
+                let mut libs = Vec::new();
+                for index in 0..archs.narches {
+                    if let SingleArch::MachO(arch) = archs.get(index)? {
+                        if arch.header.cputype == MACOS_CPU_TYPE {
+                            libs = arch.libs.iter().map(PathBuf::from).collect();
+                            break;
                         }
                     }
                 }
-            } else {
-                return Err(eyre!("Binary is not a valid Mach-O executable"));
+                libs
+            }
+            Object::Mach(Mach::Binary(arch)) => arch.libs.iter().map(PathBuf::from).collect(),
+            _ => {
+                return Err(eyre!(
+                    "Binary '{}' is not a valid Mach-O executable",
+                    current_object_path.display()
+                ))
+            }
+        };
+        for library_path in libs {
+            if library_path.is_absolute() && impure_paths.insert(library_path.clone()) {
+                unvisted_paths.push_back(library_path);
             }
         }
     }
     Ok(impure_paths)
 }
 
-#[allow(dead_code)]
-fn build_sandbox_profile(tmp_dir: impl AsRef<Path>) -> Result<PathBuf> {
+/// Quotes a path as a string literal of the sandbox profile language
+#[cfg(target_os = "macos")]
+fn sandbox_profile_literal(path: &Path) -> String {
+    format!(
+        "\"{}\"",
+        path.to_string_lossy()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+    )
+}
+
+/// Generates the sandbox profile of a native build in the build's temporary
+/// directory, on top of the defaults it allows access to the binaries run by the
+/// build, the plan context and the exceptions configured by the plan.
+#[cfg(target_os = "macos")]
+fn build_sandbox_profile(build_step: &BuildStep, tmp_dir: impl AsRef<Path>) -> Result<PathBuf> {
     let sandbox_profile_path = tmp_dir.as_ref().join("sandbox-profile.sb");
-    let mut sandbox_profile = String::new();
-    let mut impure_dirs = BTreeSet::new();
-    let runtime_binaries = &["hab", "bash", "env", "basename", "dirname"];
-    for runtime_binary in runtime_binaries {
-        let binary_path = which(runtime_binary)?;
-        impure_dirs.append(&mut compute_binary_impurities(binary_path)?);
-    }
-    println!("IMPURE DIRS: {:?}", impure_dirs);
-    writeln!(&mut sandbox_profile, "(version 1)")?;
-    writeln!(&mut sandbox_profile, "(import \"dyld-support.sb\")")?;
-    let impure_dirs = impure_dirs
-        .into_iter()
-        .map(|dir| format!("(literal \"{}\")", dir.display()))
-        .collect::<Vec<String>>()
-        .join("\n");
+    let mut sandbox_profile = String::from(SANDBOX_DEFAULTS);
+    let mut impure_paths = BTreeSet::new();
+    for runtime_binary in SANDBOX_RUNTIME_BINARIES {
+        let binary_path = which(runtime_binary)
+            .with_context(|| eyre!("Failed to find '{}' binary in environment", runtime_binary))?;
+        impure_paths.append(&mut compute_binary_impurities(binary_path)?);
+    }
+    debug!(
+        "Allowing native build of {} to access impure paths: {:?}",
+        build_step.plan_ctx.id, impure_paths
+    );
+    writeln!(
+        &mut sandbox_profile,
+        "\n; Binaries run by the build and the libraries they load"
+    )?;
     writeln!(
         &mut sandbox_profile,
         "(allow file-read* process-exec {})",
-        impure_dirs
+        impure_paths
+            .iter()
+            .map(|path| format!("(literal {})", sandbox_profile_literal(path)))
+            .collect::<Vec<_>>()
+            .join(" ")
     )?;
-    // Compute directories in chroot
-    write!(&mut sandbox_profile, "{}", SANDBOX_DEFAULTS)?;
-    std::fs::write(&sandbox_profile_path, sandbox_profile.as_bytes())?;
+    writeln!(&mut sandbox_profile, "\n; Plan context of the build")?;
+    writeln!(
+        &mut sandbox_profile,
+        "(allow file-read* (subpath {}))",
+        sandbox_profile_literal(build_step.repo_ctx.path.as_ref())
+    )?;
+    let plan_config = build_step.plan_ctx.plan_config.as_ref();
+    let allow_paths = plan_config
+        .map(|config| config.sandbox_allow_paths.as_slice())
+        .unwrap_or_default();
+    if !allow_paths.is_empty() {
+        writeln!(
+            &mut sandbox_profile,
+            "\n; Paths allowed by the plan's 'sandbox-allow-paths'"
+        )?;
+        writeln!(
+            &mut sandbox_profile,
+            "(allow file* process-exec {})",
+            allow_paths
+                .iter()
+                .map(|path| format!("(subpath {})", sandbox_profile_literal(path)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )?;
+    }
+    if plan_config.and_then(|config| config.allow_networking) == Some(true) {
+        writeln!(
+            &mut sandbox_profile,
+            "\n; Networking allowed by the plan's 'allow-networking'"
+        )?;
+        writeln!(&mut sandbox_profile, "(allow network*)")?;
+        writeln!(
+            &mut sandbox_profile,
+            "(allow mach-lookup (global-name \"com.apple.SystemConfiguration.DNSConfiguration\") (global-name \"com.apple.SystemConfiguration.configd\"))"
+        )?;
+        writeln!(
+            &mut sandbox_profile,
+            "(allow file-read* (subpath \"/private/etc/ssl\") (literal \"/private/etc/services\"))"
+        )?;
+    }
+    std::fs::write(&sandbox_profile_path, sandbox_profile.as_bytes()).with_context(|| {
+        eyre!(
+            "Failed to write sandbox profile at '{}'",
+            sandbox_profile_path.display()
+        )
+    })?;
     Ok(sandbox_profile_path)
 }
 
@@ -670,7 +1209,11 @@ pub(crate) fn native_package_build(
 ) -> Result<BuildOutput, BuildError> {
     let tmp_path = store.temp_dir_path();
     std::fs::create_dir_all(tmp_path.as_ref())?;
-    let tmp_dir = TempDir::new_in(tmp_path.as_ref(), "native-build").with_context(|| {
+    let tmp_dir = TempDir::new_in(
+        tmp_path.as_ref(),
+        &build_temp_dir_prefix("native-build", build_step),
+    )
+    .with_context(|| {
         format!(
             "Failed to create temporary directory in hab-auto-build store at '{}'",
             tmp_path.as_ref().display()
@@ -707,20 +1250,20 @@ pub(crate) fn native_package_build(
 
     cmd = Exec::cmd("sudo").arg("-E");
 
-    if let Some(PlanContextConfig {
-        sandbox: Some(true),
-        ..
-    }) = &build_step.plan_ctx.plan_config
-    {
-        let sandbox_profile = build_sandbox_profile(tmp_path.as_ref())?;
+    // Native builds are sandboxed unless the plan opts out
+    let plan_config = build_step.plan_ctx.plan_config.as_ref();
+    if plan_config.and_then(|config| config.sandbox) != Some(false) {
+        let sandbox_profile = build_sandbox_profile(build_step, tmp_dir.path())?;
         cmd = cmd
             .arg("sandbox-exec")
             .arg("-f")
             .arg(sandbox_profile)
             .arg("-D")
-            .arg(format!("BUILD_DIR={}", "/hab/cache/"))
-            .arg("-D")
-            .arg(format!("ALLOW_NETWORKING={}", "1"));
+            .arg(format!("BUILD_DIR={}", tmp_dir.path().display()))
+            .env("TMPDIR", tmp_dir.path());
+        if plan_config.and_then(|config| config.allow_networking) == Some(true) {
+            cmd = cmd.arg("-D").arg("ALLOW_LOCAL_NETWORKING=1");
+        }
     }
 
     cmd = cmd
@@ -730,6 +1273,7 @@ pub(crate) fn native_package_build(
         .arg("pkg")
         .arg("build")
         .arg("-N")
+        .args(&build_step.build_args)
         .arg(relative_plan_context)
         .env("HAB_LICENSE", "accept-no-persist")
         .env("HAB_FEAT_NATIVE_PACKAGE_SUPPORT", "1")
@@ -743,20 +1287,13 @@ pub(crate) fn native_package_build(
         cmd = cmd.env("HAB_BLDR_URL", "https://non-existent");
     }
     trace!("Executing command: {:?}", cmd);
-    let exit_status = cmd.join()?;
+    let exit_status = build_cmd_join(cmd, store, build_step, &build_log_path, build_output_dir)?;
 
     if exit_status.success() {
-        let (artifact_path, build_log_path) =
+        let (artifact, build_log_path) =
             copy_build_success_output(store, build_step, &build_log_path, build_output_dir)?;
         Ok(BuildOutput {
-            artifact: ArtifactContext::read_from_disk(artifact_path.as_path(), None).with_context(
-                || {
-                    format!(
-                        "Failed to index built artifact: {}",
-                        artifact_path.display()
-                    )
-                },
-            )?,
+            artifact,
             build_log: build_log_path,
         })
     } else {
@@ -790,7 +1327,11 @@ pub(crate) fn bootstrap_package_build(
 ) -> Result<BuildOutput, BuildError> {
     let tmp_path = store.temp_dir_path();
     std::fs::create_dir_all(tmp_path.as_ref())?;
-    let tmp_dir = TempDir::new_in(tmp_path.as_ref(), "bootstrap-build").with_context(|| {
+    let tmp_dir = TempDir::new_in(
+        tmp_path.as_ref(),
+        &build_temp_dir_prefix("bootstrap-build", build_step),
+    )
+    .with_context(|| {
         format!(
             "Failed to create temporary directory in hab-auto-build store at '{}'",
             tmp_path.as_ref().display()
@@ -803,8 +1344,7 @@ pub(crate) fn bootstrap_package_build(
             build_log_path.display()
         )
     })?;
-    let studio_root = HabitatRootPath::new(FSRootPath::default())
-        .studio_root(format!("hab-auto-build-{}", id).as_str());
+    let studio_root = build_studio_root(id);
     let build_output_dir = studio_root.as_ref().join("output");
     let deps_to_install = build_step
         .deps_to_install
@@ -857,11 +1397,12 @@ pub(crate) fn bootstrap_package_build(
             )
             .unwrap()
             .id,
+        build_step.sandbox_command,
     )?;
 
-    let exit_status = Exec::cmd("sudo")
-        .arg("-E")
-        .arg(HAB_BINARY.as_path())
+    let exit_status = build_step
+        .sandbox_command
+        .exec(HAB_BINARY.as_path())
         .arg("pkg")
         .arg("exec")
         .arg(build_step.studio_package.unwrap().to_string())
@@ -903,9 +1444,9 @@ pub(crate) fn bootstrap_package_build(
         &HabitatRootPath::new(FSRootPath::from(studio_root.clone())).source_cache(),
     )?;
 
-    let mut cmd = Exec::cmd("sudo")
-        .arg("-E")
-        .arg(HAB_BINARY.as_path())
+    let mut cmd = build_step
+        .sandbox_command
+        .exec(HAB_BINARY.as_path())
         .arg("pkg")
         .arg("exec")
         .arg(build_step.studio_package.unwrap().to_string())
@@ -917,6 +1458,7 @@ pub(crate) fn bootstrap_package_build(
         .arg(studio_root.as_ref())
         .arg("build")
         .arg("-R")
+        .args(&build_step.build_args)
         .arg(relative_plan_context)
         .env("HAB_ORIGIN_KEYS", origin_keys)
         .env(
@@ -937,19 +1479,12 @@ pub(crate) fn bootstrap_package_build(
         cmd = cmd.env("HAB_BLDR_URL", "https://non-existent");
     }
     trace!("Executing command: {:?}", cmd);
-    let exit_status = cmd.join()?;
+    let exit_status = build_cmd_join(cmd, store, build_step, &build_log_path, &build_output_dir)?;
     if exit_status.success() {
-        let (artifact_path, build_log_path) =
+        let (artifact, build_log_path) =
             copy_build_success_output(store, build_step, &build_log_path, &build_output_dir)?;
         Ok(BuildOutput {
-            artifact: ArtifactContext::read_from_disk(artifact_path.as_path(), None).with_context(
-                || {
-                    format!(
-                        "Failed to index built artifact: {}",
-                        artifact_path.display()
-                    )
-                },
-            )?,
+            artifact,
             build_log: build_log_path,
         })
     } else {
@@ -971,7 +1506,11 @@ pub(crate) fn bootstrap_package_build(
 ) -> Result<BuildOutput, BuildError> {
     let tmp_path = store.temp_dir_path();
     std::fs::create_dir_all(tmp_path.as_ref())?;
-    let tmp_dir = TempDir::new_in(tmp_path.as_ref(), "bootstrap-build").with_context(|| {
+    let tmp_dir = TempDir::new_in(
+        tmp_path.as_ref(),
+        &build_temp_dir_prefix("bootstrap-build", build_step),
+    )
+    .with_context(|| {
         format!(
             "Failed to create temporary directory in hab-auto-build store at '{}'",
             tmp_path.as_ref().display()
@@ -984,8 +1523,7 @@ pub(crate) fn bootstrap_package_build(
             build_log_path.display()
         )
     })?;
-    let studio_root = HabitatRootPath::new(FSRootPath::default())
-        .studio_root(format!("hab-auto-build-{}", id).as_str());
+    let studio_root = build_studio_root(id);
 
     let build_output_dir = tmp_dir.path();
     let deps_to_install = build_step
@@ -1039,6 +1577,7 @@ pub(crate) fn bootstrap_package_build(
             )
             .unwrap()
             .id,
+        build_step.sandbox_command,
     )?;
 
     let build_log = std::fs::File::options()
@@ -1070,6 +1609,7 @@ pub(crate) fn bootstrap_package_build(
         .arg("-r")
         .arg(studio_root.as_ref())
         .arg("build")
+        .args(&build_step.build_args)
         .arg(relative_plan_context)
         .env("CERT_PATH", "/hab/cache/ssl")
         .env(
@@ -1089,19 +1629,12 @@ pub(crate) fn bootstrap_package_build(
         cmd = cmd.env("HAB_BLDR_URL", "https://non-existent");
     }
     trace!("Executing command: {:?}", cmd);
-    let exit_status = cmd.join()?;
+    let exit_status = build_cmd_join(cmd, store, build_step, &build_log_path, build_output_dir)?;
     if exit_status.success() {
-        let (artifact_path, build_log_path) =
+        let (artifact, build_log_path) =
             copy_build_success_output(store, build_step, &build_log_path, build_output_dir)?;
         Ok(BuildOutput {
-            artifact: ArtifactContext::read_from_disk(artifact_path.as_path(), None).with_context(
-                || {
-                    format!(
-                        "Failed to index built artifact: {}",
-                        artifact_path.display()
-                    )
-                },
-            )?,
+            artifact,
             build_log: build_log_path,
         })
     } else {
@@ -1136,7 +1669,11 @@ pub(crate) fn standard_package_build(
 ) -> Result<BuildOutput, BuildError> {
     let tmp_path = store.temp_dir_path();
     std::fs::create_dir_all(tmp_path.as_ref())?;
-    let tmp_dir = TempDir::new_in(tmp_path.as_ref(), "standard-build").with_context(|| {
+    let tmp_dir = TempDir::new_in(
+        tmp_path.as_ref(),
+        &build_temp_dir_prefix("standard-build", build_step),
+    )
+    .with_context(|| {
         format!(
             "Failed to create temporary directory in hab-auto-build store at '{}'",
             tmp_path.as_ref().display()
@@ -1149,8 +1686,7 @@ pub(crate) fn standard_package_build(
             build_log_path.display()
         )
     })?;
-    let studio_root = HabitatRootPath::new(FSRootPath::default())
-        .studio_root(format!("hab-auto-build-{}", id).as_str());
+    let studio_root = build_studio_root(id);
     let build_output_dir = studio_root.as_ref().join("output");
     let deps_to_install = build_step
         .deps_to_install
@@ -1203,11 +1739,12 @@ pub(crate) fn standard_package_build(
             )
             .unwrap()
             .id,
+        build_step.sandbox_command,
     )?;
 
-    let cmd = Exec::cmd("sudo")
-        .arg("-E")
-        .arg(HAB_BINARY.as_path())
+    let cmd = build_step
+        .sandbox_command
+        .exec(HAB_BINARY.as_path())
         .arg("pkg")
         .arg("exec")
         .arg(build_step.studio_package.unwrap().to_string())
@@ -1249,9 +1786,9 @@ pub(crate) fn standard_package_build(
         store,
         &HabitatRootPath::new(FSRootPath::from(studio_root.clone())).source_cache(),
     )?;
-    let mut cmd = Exec::cmd("sudo")
-        .arg("-E")
-        .arg(HAB_BINARY.as_path())
+    let mut cmd = build_step
+        .sandbox_command
+        .exec(HAB_BINARY.as_path())
         .arg("pkg")
         .arg("exec")
         .arg(build_step.studio_package.unwrap().to_string())
@@ -1261,6 +1798,7 @@ pub(crate) fn standard_package_build(
         .arg(studio_root.as_ref())
         .arg("build")
         .arg("-R")
+        .args(&build_step.build_args)
         .arg(relative_plan_context)
         .env("HAB_ORIGIN_KEYS", origin_keys)
         .env(
@@ -1281,20 +1819,13 @@ pub(crate) fn standard_package_build(
         cmd = cmd.env("HAB_BLDR_URL", "https://non-existent");
     }
     trace!("Executing command: {:?}", cmd);
-    let exit_status = cmd.join()?;
+    let exit_status = build_cmd_join(cmd, store, build_step, &build_log_path, &build_output_dir)?;
 
     if exit_status.success() {
-        let (artifact_path, build_log_path) =
+        let (artifact, build_log_path) =
             copy_build_success_output(store, build_step, &build_log_path, &build_output_dir)?;
         Ok(BuildOutput {
-            artifact: ArtifactContext::read_from_disk(artifact_path.as_path(), None).with_context(
-                || {
-                    format!(
-                        "Failed to index built artifact: {}",
-                        artifact_path.display()
-                    )
-                },
-            )?,
+            artifact,
             build_log: build_log_path,
         })
     } else {
@@ -1316,7 +1847,11 @@ pub(crate) fn standard_package_build(
 ) -> Result<BuildOutput, BuildError> {
     let tmp_path = store.temp_dir_path();
     std::fs::create_dir_all(tmp_path.as_ref())?;
-    let tmp_dir = TempDir::new_in(tmp_path.as_ref(), "standard-build").with_context(|| {
+    let tmp_dir = TempDir::new_in(
+        tmp_path.as_ref(),
+        &build_temp_dir_prefix("standard-build", build_step),
+    )
+    .with_context(|| {
         format!(
             "Failed to create temporary directory in hab-auto-build store at '{}'",
             tmp_path.as_ref().display()
@@ -1329,8 +1864,7 @@ pub(crate) fn standard_package_build(
             build_log_path.display()
         )
     })?;
-    let studio_root = HabitatRootPath::new(FSRootPath::default())
-        .studio_root(format!("hab-auto-build-{}", id).as_str());
+    let studio_root = build_studio_root(id);
 
     let build_output_dir = tmp_dir.path();
     let deps_to_install = build_step
@@ -1384,6 +1918,7 @@ pub(crate) fn standard_package_build(
             )
             .unwrap()
             .id,
+        build_step.sandbox_command,
     )?;
 
     let build_log = std::fs::File::options()
@@ -1413,6 +1948,7 @@ pub(crate) fn standard_package_build(
         .arg("-r")
         .arg(studio_root.as_ref())
         .arg("build")
+        .args(&build_step.build_args)
         .arg(relative_plan_context)
         .env("CERT_PATH", "/hab/cache/ssl")
         .env(
@@ -1432,19 +1968,12 @@ pub(crate) fn standard_package_build(
         cmd = cmd.env("HAB_BLDR_URL", "https://non-existent");
     }
     trace!("Executing command: {:?}", cmd);
-    let exit_status = cmd.join()?;
+    let exit_status = build_cmd_join(cmd, store, build_step, &build_log_path, build_output_dir)?;
     if exit_status.success() {
-        let (artifact_path, build_log_path) =
+        let (artifact, build_log_path) =
             copy_build_success_output(store, build_step, &build_log_path, build_output_dir)?;
         Ok(BuildOutput {
-            artifact: ArtifactContext::read_from_disk(artifact_path.as_path(), None).with_context(
-                || {
-                    format!(
-                        "Failed to index built artifact: {}",
-                        artifact_path.display()
-                    )
-                },
-            )?,
+            artifact,
             build_log: build_log_path,
         })
     } else {
@@ -1466,22 +1995,25 @@ pub(crate) fn standard_package_build(
 ) -> Result<BuildOutput, BuildError> {
     let tmp_path = store.temp_dir_path();
     std::fs::create_dir_all(tmp_path.as_ref())?;
-    let tmp_dir = TempDir::new_in(tmp_path.as_ref(), "standard-build").with_context(|| {
+    let tmp_dir = TempDir::new_in(
+        tmp_path.as_ref(),
+        &build_temp_dir_prefix("standard-build", build_step),
+    )
+    .with_context(|| {
         format!(
             "Failed to create temporary directory in hab-auto-build store at '{}'",
             tmp_path.as_ref().display()
         )
     })?;
     let build_log_path = tmp_dir.path().join("build.log");
-    let _build_log = std::fs::File::create(&build_log_path).with_context(|| {
+    let build_log = std::fs::File::create(&build_log_path).with_context(|| {
         format!(
             "Failed to create build log at '{}'",
             build_log_path.display()
         )
     })?;
 
-    let studio_root = HabitatRootPath::new(FSRootPath::default())
-        .studio_root(format!("hab-auto-build-{}", id).as_str());
+    let studio_root = build_studio_root(id);
 
     let build_output_dir = build_step.repo_ctx.path.as_ref().join("results");
     let deps_to_install = build_step
@@ -1526,27 +2058,23 @@ pub(crate) fn standard_package_build(
         build_log_path.display()
     );
 
-    // install_artifact_offline(
-    //     &artifact_cache
-    //         .latest_minimal_artifact(
-    //             &build_step
-    //                 .studio_package
-    //                 .unwrap()
-    //                 .to_resolved_dep_ident(PackageTarget::default()),
-    //         )
-    //         .unwrap()
-    //         .id,
-    // )?;
-
-    let build_log = std::fs::File::options()
-        .append(true)
-        .open(&build_log_path)
-        .with_context(|| {
-            format!(
-                "Failed to append to build log at '{}'",
-                build_log_path.display()
+    install_artifact_offline(
+        &artifact_cache
+            .latest_minimal_artifact(
+                &build_step
+                    .studio_package
+                    .unwrap()
+                    .to_resolved_dep_ident(PackageTarget::default()),
             )
-        })?;
+            .unwrap()
+            .id,
+        build_step.sandbox_command,
+    )?;
+
+    // Remove the studio left over from a previous build with the same id
+    if studio_root.as_ref().exists() {
+        studio_remove(studio_root.as_ref(), build_step.sandbox_command)?;
+    }
 
     copy_source_to_cache(
         build_step,
@@ -1554,56 +2082,6 @@ pub(crate) fn standard_package_build(
         &HabitatRootPath::new(FSRootPath::from(studio_root.clone())).source_cache(),
     )?;
 
-    // let bldr_url = if build_step.allow_remote {
-    //     "https://bldr.habitat.sh"
-    // } else {
-    //     "https://non-existent"
-    // };
-
-    // TODO commenting docker as we require some changes
-    // let mut cmd = Exec::cmd("powershell")
-    //     .arg("-Command")
-    //     .arg("docker")
-    //     .arg("run")
-    //     .arg("--rm")
-    //     // .arg("--tty")
-    //     .arg("--interactive")
-    //     .env("HAB_LICENSE", "accept-no-persist")
-    //     .env("HAB_STUDIO_SECRET_HAB_STUDIO_HOST_ARCH", "x86_64-windows")
-    //     // .env("HAB_STUDIO_INSTALL_PKGS", deps_to_install)
-    //     .arg("-e")
-    //     .arg(format!(
-    //         "HAB_ORIGIN={}",
-    //         build_step.plan_ctx.id.as_ref().origin
-    //     ))
-    //     .env("HAB_ORIGIN_KEYS", origin_keys)
-    //     .arg("-e")
-    //     .arg(format!("HAB_BLDR_URL={}", bldr_url))
-    //     .arg("-e")
-    //     .arg(format!("HAB_STUDIO_INSTALL_PKGS={}", deps_to_install))
-    //     .arg("--volume")
-    //     .arg(format!(
-    //         "{}:{}",
-    //         build_step.repo_ctx.path.as_ref().display(),
-    //         "c:/src"
-    //     ))
-    //     .arg("--volume")
-    //     .arg("c:/hab/cache/keys:c:/hab/cache/keys")
-    //     .arg("--volume")
-    //     .arg("c:/hab/cache/artifacts:c:/hab/cache/artifacts")
-    //     .arg("--volume")
-    //     .arg("c:/hab/cache/ssl:c:/hab/cache/ssl")
-    //     .arg("habitat/win-studio-x86_64-windows:ltsc2019-1.6.1041")
-    //     .arg("build")
-    //     .arg(relative_plan_context)
-    //     .arg("-n")
-    //     .arg("-o")
-    //     .arg("c:/")
-    //     .cwd(build_step.repo_ctx.path.as_ref())
-    //     .stdin(NullFile)
-    //     .stdout(Redirection::File(build_log))
-    //     .stderr(Redirection::Merge);
-
     let mut cmd = Exec::cmd("powershell")
         .arg("-Command")
         .arg("hab")
@@ -1619,6 +2097,7 @@ pub(crate) fn standard_package_build(
         .env("HAB_STUDIO_ROOT", get_normalized_path(studio_root.as_ref()))
         .arg("build")
         .arg("-R")
+        .args(&build_step.build_args)
         .arg(relative_plan_context)
         .cwd(get_normalized_path(build_step.repo_ctx.path.as_ref()))
         .stdin(NullFile)
@@ -1629,25 +2108,18 @@ pub(crate) fn standard_package_build(
     }
 
     trace!("Executing command: {:?}", cmd);
-    let exit_status = cmd.join()?;
+    let exit_status = build_cmd_join(cmd, store, build_step, &build_log_path, &build_output_dir)?;
     if exit_status.success() {
-        let (artifact_path, build_log_path) =
-            copy_build_success_output(store, build_step, &build_log_path, build_output_dir)?;
+        let (artifact, build_log_path) =
+            copy_build_success_output(store, build_step, &build_log_path, &build_output_dir)?;
         Ok(BuildOutput {
-            artifact: ArtifactContext::read_from_disk(artifact_path.as_path(), None).with_context(
-                || {
-                    format!(
-                        "Failed to index built artifact: {}",
-                        artifact_path.display()
-                    )
-                },
-            )?,
+            artifact,
             build_log: build_log_path,
         })
     } else {
         let build_log_path =
-            copy_build_failure_output(store, build_step, &build_log_path, build_output_dir)?;
-        Err(BuildError::Bootstrap(
+            copy_build_failure_output(store, build_step, &build_log_path, &build_output_dir)?;
+        Err(BuildError::Standard(
             build_step.plan_ctx.id.clone(),
             build_log_path,
         ))
@@ -1667,3 +2139,671 @@ fn get_normalized_path(path: &Path) -> String {
         normalized_path
     }
 }
+
+/// Folder on remote workers in which builds are staged, unless the worker url has a path
+const SSH_WORKER_DEFAULT_DIR: &str = "/var/tmp/hab-auto-build";
+
+/// Exit code of the 'timeout' command when the command it runs times out
+const TIMEOUT_EXIT_CODE: u32 = 124;
+
+/// Command rootless builds are run with by default, it runs them in a user namespace
+/// in which the current user is mapped to root
+const ROOTLESS_SANDBOX_DEFAULT_COMMAND: [&str; 5] =
+    ["unshare", "--map-root-user", "--mount", "--pid", "--fork"];
+
+/// Determines how the builds on the current host are run as root
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum BuildSandbox {
+    /// Run the builds with sudo
+    #[default]
+    Sudo,
+    /// Run the builds as root in a user namespace, without sudo
+    Rootless,
+}
+
+/// Determines where standard plans are built on the current host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum PlanBuilder {
+    /// Build the plans in a studio
+    #[default]
+    Studio,
+    /// Build the plans in a Docker container
+    Docker,
+}
+
+/// Command the privileged commands of the builds on the current host are run with,
+/// the program is followed by its arguments and is never empty
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub(crate) struct BuildSandboxCommand(Vec<String>);
+
+impl BuildSandboxCommand {
+    pub fn parse(command: Vec<String>) -> Result<BuildSandboxCommand> {
+        match command.first() {
+            Some(program) if !program.trim().is_empty() => Ok(BuildSandboxCommand(command)),
+            _ => Err(eyre!(
+                "Invalid sandbox command, 'sandbox_command' must start with the program to run"
+            )),
+        }
+    }
+
+    /// Command for the sandbox, rootless builds are run with the given command or
+    /// with 'unshare' if there is none.
+    pub fn new(
+        sandbox: BuildSandbox,
+        command: Option<&BuildSandboxCommand>,
+    ) -> BuildSandboxCommand {
+        match sandbox {
+            BuildSandbox::Sudo => BuildSandboxCommand(vec!["sudo".to_string(), "-E".to_string()]),
+            BuildSandbox::Rootless => command.cloned().unwrap_or_else(|| {
+                BuildSandboxCommand(
+                    ROOTLESS_SANDBOX_DEFAULT_COMMAND
+                        .iter()
+                        .map(|arg| arg.to_string())
+                        .collect(),
+                )
+            }),
+        }
+    }
+
+    pub fn program(&self) -> &str {
+        &self.0[0]
+    }
+
+    /// Command that runs a program as root, keeping the environment of the current process
+    #[cfg(not(target_os = "windows"))]
+    fn exec(&self, program: impl AsRef<std::ffi::OsStr>) -> Exec {
+        Exec::cmd(&self.0[0]).args(&self.0[1..]).arg(program)
+    }
+}
+
+impl TryFrom<Vec<String>> for BuildSandboxCommand {
+    type Error = color_eyre::eyre::Error;
+
+    fn try_from(value: Vec<String>) -> Result<Self, Self::Error> {
+        BuildSandboxCommand::parse(value)
+    }
+}
+
+impl From<BuildSandboxCommand> for Vec<String> {
+    fn from(value: BuildSandboxCommand) -> Self {
+        value.0
+    }
+}
+
+/// Verifies that the builds on the current host can be run in the sandbox
+pub(crate) fn build_sandbox_verify(
+    sandbox: BuildSandbox,
+    sandbox_command: &BuildSandboxCommand,
+) -> Result<()> {
+    if sandbox != BuildSandbox::Rootless {
+        return Ok(());
+    }
+    if !cfg!(target_os = "linux") {
+        return Err(eyre!("Rootless builds are only supported on Linux"))
+            .with_suggestion(|| "Set 'sandbox' to 'sudo' in the hab-auto-build configuration");
+    }
+    let program = sandbox_command.program();
+    which(program)
+        .with_context(|| eyre!("Failed to find '{}' to run rootless builds", program))
+        .with_suggestion(|| {
+            "Install 'unshare' from util-linux, or set 'sandbox_command' to a wrapper like bubblewrap"
+        })?;
+    // Files owned by other users cannot be modified from the user namespace
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let hab_root = HabitatRootPath::default();
+        if let Ok(metadata) = std::fs::metadata(hab_root.as_ref()) {
+            if metadata.uid() != unsafe { libc::getuid() } {
+                return Err(eyre!(
+                    "The Habitat root '{}' must be owned by the current user for rootless builds",
+                    hab_root.as_ref().display()
+                ))
+                .with_suggestion(|| {
+                    format!(
+                        "Change the owner of '{}' with 'sudo chown -R $(id -u):$(id -g) {}'",
+                        hab_root.as_ref().display(),
+                        hab_root.as_ref().display()
+                    )
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Determines where the build steps of a session are executed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) enum BuildExecutor {
+    /// Builds the plans on the current host
+    #[default]
+    Local,
+    /// Builds the plans on a remote worker over SSH
+    Ssh(SshWorker),
+}
+
+/// Remote worker reached over SSH, it needs 'hab', 'sudo', 'tar' and 'timeout' along
+/// with the signing keys of the origins it builds. Logins must not be interactive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SshWorker {
+    /// SSH destination, as '[user@]host'
+    pub destination: String,
+    pub port: Option<u16>,
+    /// Folder on the worker in which builds are staged
+    pub work_dir: String,
+}
+
+impl BuildExecutor {
+    /// Parses an executor from 'local' or a worker url like 'ssh://user@host:22/var/tmp/builds'
+    pub fn parse(value: impl AsRef<str>) -> Result<BuildExecutor> {
+        let value = value.as_ref();
+        if value == "local" {
+            return Ok(BuildExecutor::Local);
+        }
+        let url = reqwest::Url::parse(value)
+            .with_context(|| eyre!("Invalid build executor '{}'", value))?;
+        if url.scheme() != "ssh" {
+            return Err(eyre!(
+                "Invalid build executor '{}', expected 'local' or an 'ssh://' url",
+                value
+            ));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| eyre!("Invalid build executor '{}', the url has no host", value))?;
+        Ok(BuildExecutor::Ssh(SshWorker {
+            destination: if url.username().is_empty() {
+                host.to_string()
+            } else {
+                format!("{}@{}", url.username(), host)
+            },
+            port: url.port(),
+            work_dir: match url.path().trim_end_matches('/') {
+                "" => SSH_WORKER_DEFAULT_DIR.to_string(),
+                path => path.to_string(),
+            },
+        }))
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self, BuildExecutor::Local)
+    }
+
+    /// Builds the plan of a build step, native plans are always built on the
+    /// current host as they are built against the host itself.
+    pub fn package_build(
+        &self,
+        build_step: &BuildStep,
+        artifact_cache: &ArtifactCache,
+        store: &Store,
+        id: u64,
+    ) -> Result<BuildOutput, BuildError> {
+        match (self, build_step.studio) {
+            (_, BuildStepStudio::Native) => native_package_build(build_step, artifact_cache, store),
+            (BuildExecutor::Local, BuildStepStudio::Bootstrap) => {
+                bootstrap_package_build(build_step, artifact_cache, store, id)
+            }
+            (BuildExecutor::Local, BuildStepStudio::Standard) => match &build_step.docker_image {
+                Some(docker_image) => {
+                    docker_package_build(build_step, docker_image, artifact_cache, store, id)
+                }
+                None => standard_package_build(build_step, artifact_cache, store, id),
+            },
+            (BuildExecutor::Ssh(worker), _) => {
+                worker.package_build(build_step, artifact_cache, store)
+            }
+        }
+    }
+}
+
+impl Display for BuildExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildExecutor::Local => write!(f, "local"),
+            BuildExecutor::Ssh(worker) => {
+                write!(f, "ssh://{}", worker.destination)?;
+                if let Some(port) = worker.port {
+                    write!(f, ":{}", port)?;
+                }
+                write!(f, "{}", worker.work_dir)
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for BuildExecutor {
+    type Error = color_eyre::eyre::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        BuildExecutor::parse(value)
+    }
+}
+
+impl From<BuildExecutor> for String {
+    fn from(value: BuildExecutor) -> Self {
+        value.to_string()
+    }
+}
+
+impl FromStr for BuildExecutor {
+    type Err = color_eyre::eyre::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        BuildExecutor::parse(value)
+    }
+}
+
+/// Quotes a value so that it is passed as a single word to a remote shell
+fn shell_quote(value: impl AsRef<str>) -> String {
+    format!("'{}'", value.as_ref().replace('\'', r"'\''"))
+}
+
+impl SshWorker {
+    fn ssh(&self, remote_command: impl AsRef<str>) -> Exec {
+        let mut cmd = Exec::cmd("ssh").arg("-o").arg("BatchMode=yes");
+        if let Some(port) = self.port {
+            cmd = cmd.arg("-p").arg(port.to_string());
+        }
+        trace!(
+            "Executing command on {}: {}",
+            self.destination,
+            remote_command.as_ref()
+        );
+        cmd.arg(&self.destination).arg(remote_command.as_ref())
+    }
+
+    fn ssh_run(&self, remote_command: impl AsRef<str>, build_log_path: &Path) -> Result<()> {
+        let build_log = std::fs::File::options()
+            .append(true)
+            .open(build_log_path)
+            .with_context(|| {
+                format!(
+                    "Failed to append to build log at '{}'",
+                    build_log_path.display()
+                )
+            })?;
+        let exit_status = self
+            .ssh(remote_command.as_ref())
+            .stdin(NullFile)
+            .stdout(Redirection::File(build_log))
+            .stderr(Redirection::Merge)
+            .join()?;
+        if exit_status.success() {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "Command on build worker {} failed with {:?}: {}",
+                self.destination,
+                exit_status,
+                remote_command.as_ref()
+            ))
+        }
+    }
+
+    /// Copies the entries of a local folder into a folder on the worker
+    fn upload(
+        &self,
+        local_dir: &Path,
+        entries: &[String],
+        excludes: &[&str],
+        remote_dir: &str,
+    ) -> Result<()> {
+        debug!(
+            "Uploading {} entries of {} to {}:{}",
+            entries.len(),
+            local_dir.display(),
+            self.destination,
+            remote_dir
+        );
+        let exit_status = (Exec::cmd("tar")
+            .args(
+                &excludes
+                    .iter()
+                    .map(|exclude| format!("--exclude={}", exclude))
+                    .collect::<Vec<_>>(),
+            )
+            .arg("-C")
+            .arg(local_dir)
+            .arg("-cf")
+            .arg("-")
+            .args(entries)
+            | self.ssh(format!(
+                "mkdir -p {0} && tar -C {0} -xf -",
+                shell_quote(remote_dir)
+            )))
+        .join()?;
+        if exit_status.success() {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "Failed to upload '{}' to build worker {}",
+                local_dir.display(),
+                self.destination
+            ))
+        }
+    }
+
+    /// Copies a local file into a folder on the worker
+    fn upload_file(&self, local_path: &Path, remote_dir: &str, remote_name: &str) -> Result<()> {
+        let local_file = std::fs::File::open(local_path)
+            .with_context(|| format!("Failed to open '{}'", local_path.display()))?;
+        let exit_status = self
+            .ssh(format!(
+                "mkdir -p {0} && cat > {0}/{1}",
+                shell_quote(remote_dir),
+                shell_quote(remote_name)
+            ))
+            .stdin(local_file)
+            .join()?;
+        if exit_status.success() {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "Failed to upload '{}' to build worker {}",
+                local_path.display(),
+                self.destination
+            ))
+        }
+    }
+
+    /// Copies the contents of a folder on the worker into a local folder
+    fn download(&self, remote_dir: &str, local_dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(local_dir)?;
+        let exit_status = (self.ssh(format!("sudo tar -C {} -cf - .", shell_quote(remote_dir)))
+            | Exec::cmd("tar")
+                .arg("-C")
+                .arg(local_dir)
+                .arg("-xf")
+                .arg("-"))
+        .join()?;
+        if exit_status.success() {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "Failed to download '{}' from build worker {}",
+                remote_dir,
+                self.destination
+            ))
+        }
+    }
+
+    /// Builds a plan in a studio on the worker. The repo of the plan, the artifacts
+    /// of its dependencies and its source are copied to the worker, and the artifact
+    /// and the build outputs are copied back once the build completes.
+    fn package_build(
+        &self,
+        build_step: &BuildStep,
+        artifact_cache: &ArtifactCache,
+        store: &Store,
+    ) -> Result<BuildOutput, BuildError> {
+        let (kind, studio_args) = match build_step.studio {
+            BuildStepStudio::Bootstrap => ("bootstrap-build", vec!["-t", "bootstrap"]),
+            _ => ("standard-build", vec![]),
+        };
+        let tmp_path = store.temp_dir_path();
+        std::fs::create_dir_all(tmp_path.as_ref())?;
+        let tmp_dir = TempDir::new_in(tmp_path.as_ref(), &build_temp_dir_prefix(kind, build_step))
+            .with_context(|| {
+                format!(
+                    "Failed to create temporary directory in hab-auto-build store at '{}'",
+                    tmp_path.as_ref().display()
+                )
+            })?;
+        let build_log_path = tmp_dir.path().join("build.log");
+        std::fs::File::create(&build_log_path).with_context(|| {
+            format!(
+                "Failed to create build log at '{}'",
+                build_log_path.display()
+            )
+        })?;
+        let build_output_dir = tmp_dir.path().join("output");
+        let remote_dir = format!(
+            "{}/{}",
+            self.work_dir,
+            tmp_dir.path().file_name().unwrap().to_string_lossy()
+        );
+        let remote_repo_dir = format!("{}/repo", remote_dir);
+        let remote_artifacts_dir = format!("{}/artifacts", remote_dir);
+        let remote_studio_root = format!("{}/studio", remote_dir);
+
+        let result = self.package_build_run(
+            build_step,
+            artifact_cache,
+            store,
+            &studio_args,
+            &build_log_path,
+            &remote_repo_dir,
+            &remote_artifacts_dir,
+            &remote_studio_root,
+        );
+        let exit_status = match result {
+            Ok(exit_status) => exit_status,
+            Err(err) => {
+                let _ = self.ssh_run(
+                    format!("sudo rm -rf {}", shell_quote(&remote_dir)),
+                    &build_log_path,
+                );
+                let build_log_path = copy_build_failure_output(
+                    store,
+                    build_step,
+                    &build_log_path,
+                    &build_output_dir,
+                )?;
+                return Err(eyre!(
+                    "{:#}, you can find the build log at {}",
+                    err,
+                    build_log_path.display()
+                )
+                .into());
+            }
+        };
+        if build_step.cancellation.is_cancelled() {
+            if let Err(err) = self.ssh_run(
+                format!("sudo rm -rf {}", shell_quote(&remote_dir)),
+                &build_log_path,
+            ) {
+                debug!("Failed to remove build folder from worker: {:#}", err);
+            }
+            let build_log_path =
+                copy_build_failure_output(store, build_step, &build_log_path, &build_output_dir)?;
+            return Err(BuildError::Cancelled(
+                build_step.plan_ctx.id.clone(),
+                build_log_path,
+            ));
+        }
+        let download_result =
+            self.download(&format!("{}/output", remote_studio_root), &build_output_dir);
+        if let Err(err) = self.ssh_run(
+            format!("sudo rm -rf {}", shell_quote(&remote_dir)),
+            &build_log_path,
+        ) {
+            debug!("Failed to remove build folder from worker: {:#}", err);
+        }
+        if exit_status.success() {
+            download_result?;
+            let (artifact, build_log_path) =
+                copy_build_success_output(store, build_step, &build_log_path, &build_output_dir)?;
+            return Ok(BuildOutput {
+                artifact,
+                build_log: build_log_path,
+            });
+        }
+        let build_log_path =
+            copy_build_failure_output(store, build_step, &build_log_path, &build_output_dir)?;
+        match (exit_status, build_step.timeout_secs) {
+            (ExitStatus::Exited(TIMEOUT_EXIT_CODE), Some(timeout_secs)) => Err(
+                BuildError::TimedOut(build_step.plan_ctx.id.clone(), build_log_path, timeout_secs),
+            ),
+            _ if build_step.studio == BuildStepStudio::Bootstrap => Err(BuildError::Bootstrap(
+                build_step.plan_ctx.id.clone(),
+                build_log_path,
+            )),
+            _ => Err(BuildError::Standard(
+                build_step.plan_ctx.id.clone(),
+                build_log_path,
+            )),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn package_build_run(
+        &self,
+        build_step: &BuildStep,
+        artifact_cache: &ArtifactCache,
+        store: &Store,
+        studio_args: &[&str],
+        build_log_path: &Path,
+        remote_repo_dir: &str,
+        remote_artifacts_dir: &str,
+        remote_studio_root: &str,
+    ) -> Result<ExitStatus> {
+        let studio_package = build_step.studio_package.unwrap();
+        let studio_artifact = artifact_cache
+            .latest_minimal_artifact(
+                &studio_package.to_resolved_dep_ident(PackageTarget::default()),
+            )
+            .ok_or_else(|| eyre!("No artifact found for studio package {}", studio_package))?;
+        let dep_artifacts = build_step
+            .deps_to_install
+            .iter()
+            .filter_map(|dep| artifact_cache.latest_plan_minimal_artifact(dep))
+            .map(|artifact| artifact.id.artifact_name())
+            .collect::<Vec<_>>();
+        let relative_plan_context = build_step
+            .plan_ctx
+            .context_path
+            .as_ref()
+            .strip_prefix(&build_step.repo_ctx.path)
+            .unwrap()
+            .to_path_buf();
+        let relative_plan_context = if relative_plan_context.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            relative_plan_context
+        };
+
+        debug!(
+            "Starting build of {} on worker {} with studio package {}, logging output to {}",
+            relative_plan_context.display(),
+            self.destination,
+            studio_package,
+            build_log_path.display()
+        );
+        self.upload(
+            build_step.repo_ctx.path.as_ref(),
+            &[".".to_string()],
+            &[".git"],
+            remote_repo_dir,
+        )?;
+        let mut artifact_names = dep_artifacts.clone();
+        artifact_names.push(studio_artifact.id.artifact_name());
+        self.upload(
+            ArtifactCachePath::default().as_ref(),
+            &artifact_names,
+            &[],
+            remote_artifacts_dir,
+        )?;
+
+        let hab_studio = format!(
+            "hab pkg exec {} hab-studio -- {} -r {}",
+            shell_quote(studio_package.to_string()),
+            studio_args.join(" "),
+            shell_quote(remote_studio_root)
+        );
+        // Installing an already installed package exits with 1, like 'install_artifact_offline'
+        self.ssh_run(
+            format!(
+                "export HAB_LICENSE=accept-no-persist; sudo -E hab pkg install {}/{} >/dev/null; [ $? -le 1 ] && sudo -E {} rm",
+                shell_quote(remote_artifacts_dir),
+                shell_quote(studio_artifact.id.artifact_name()),
+                hab_studio
+            ),
+            build_log_path,
+        )?;
+
+        if let Some(source) = &build_step.plan_ctx.source {
+            self.upload_file(
+                store
+                    .package_source_store_path(&source.shasum)
+                    .archive_data_path()
+                    .as_ref(),
+                &format!("{}/hab/cache/src", remote_studio_root),
+                &source.url.filename()?.to_string_lossy(),
+            )?;
+        }
+
+        let mut env = vec![
+            (
+                "HAB_ORIGIN_KEYS",
+                build_step
+                    .origins
+                    .iter()
+                    .map(|origin| origin.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            (
+                "HAB_ORIGIN",
+                build_step.plan_ctx.id.as_ref().origin.to_string(),
+            ),
+            ("HAB_LICENSE", "accept-no-persist".to_string()),
+            (
+                "HAB_STUDIO_INSTALL_PKGS",
+                dep_artifacts
+                    .iter()
+                    .map(|artifact_name| format!("{}/{}", remote_artifacts_dir, artifact_name))
+                    .collect::<Vec<String>>()
+                    .join(":"),
+            ),
+            ("HAB_STUDIO_SUP", "false".to_string()),
+            ("HAB_STUDIO_SECRET_STUDIO_ENTER", "1".to_string()),
+            ("HAB_STUDIO_SECRET_HAB_OUTPUT_PATH", "/output".to_string()),
+            ("HAB_STUDIO_SECRET_NO_INSTALL_DEPS", "1".to_string()),
+        ];
+        if !build_step.allow_remote {
+            env.push(("HAB_BLDR_URL", "https://non-existent".to_string()));
+        }
+        let build_command = format!(
+            "cd {} && {} sudo -E {}{} build -R {} {}",
+            shell_quote(remote_repo_dir),
+            env.iter()
+                .map(|(name, value)| format!("{}={}", name, shell_quote(value)))
+                .collect::<Vec<_>>()
+                .join(" "),
+            build_step
+                .timeout_secs
+                .map(|timeout_secs| format!("timeout {} ", timeout_secs))
+                .unwrap_or_default(),
+            hab_studio,
+            build_step
+                .build_args
+                .iter()
+                .map(shell_quote)
+                .collect::<Vec<_>>()
+                .join(" "),
+            shell_quote(relative_plan_context.to_string_lossy())
+        );
+        let build_log = std::fs::File::options().append(true).open(build_log_path)?;
+        let mut process = self
+            .ssh(build_command)
+            .stdin(NullFile)
+            .stdout(Redirection::File(build_log))
+            .stderr(Redirection::Merge)
+            .popen()?;
+        // The build is limited by 'timeout' on the worker, only cancellation is handled here
+        match build_process_wait(&mut process, build_step, None)? {
+            BuildProcessWait::Exited(exit_status) => Ok(exit_status),
+            BuildProcessWait::TimedOut | BuildProcessWait::Cancelled => {
+                debug!(
+                    "Build of {} on worker {} was cancelled, terminating it",
+                    build_step.plan_ctx.id, self.destination
+                );
+                Ok(build_process_stop(&mut process)?)
+            }
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::check::{AssignmentValue, PlanFile, Word};
+
+/// Names of the lifecycle hooks run by the Habitat Supervisor
+pub(crate) const HOOK_NAMES: &[&str] = &[
+    "file-updated",
+    "health-check",
+    "init",
+    "install",
+    "post-run",
+    "post-stop",
+    "reconfigure",
+    "reload",
+    "run",
+    "suitability",
+    "uninstall",
+];
+
+/// Folder of a plan or a package containing its hooks
+pub(crate) const HOOKS_DIRECTORY: &str = "hooks";
+
+/// Maximum size of a hook that is read for checks
+pub(crate) const HOOK_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Shells whose hooks are analyzed for the commands they run
+const HOOK_SHELLS: &[&str] = &["sh", "ash", "bash", "dash", "ksh", "zsh"];
+
+/// Shell builtins and keywords, which are never looked up in the runtime path
+const SHELL_BUILTINS: &[&str] = &[
+    "!",
+    ".",
+    ":",
+    "[",
+    "[[",
+    "]]",
+    "{",
+    "}",
+    "alias",
+    "bg",
+    "bind",
+    "break",
+    "builtin",
+    "caller",
+    "case",
+    "cd",
+    "command",
+    "compgen",
+    "complete",
+    "continue",
+    "declare",
+    "dirs",
+    "disown",
+    "do",
+    "done",
+    "echo",
+    "elif",
+    "else",
+    "enable",
+    "esac",
+    "eval",
+    "exec",
+    "exit",
+    "export",
+    "false",
+    "fc",
+    "fg",
+    "fi",
+    "for",
+    "function",
+    "getopts",
+    "hash",
+    "help",
+    "history",
+    "if",
+    "in",
+    "jobs",
+    "kill",
+    "let",
+    "local",
+    "logout",
+    "mapfile",
+    "popd",
+    "printf",
+    "pushd",
+    "pwd",
+    "read",
+    "readarray",
+    "readonly",
+    "return",
+    "select",
+    "set",
+    "shift",
+    "shopt",
+    "source",
+    "suspend",
+    "test",
+    "then",
+    "time",
+    "times",
+    "trap",
+    "true",
+    "type",
+    "typeset",
+    "ulimit",
+    "umask",
+    "unalias",
+    "unset",
+    "until",
+    "wait",
+    "while",
+];
+
+/// Builtins that run the command given as their argument
+const SHELL_COMMAND_RUNNERS: &[&str] = &["builtin", "command", "exec"];
+
+/// Word that template expressions are replaced with when a hook is analyzed as a
+/// shell script
+const HOOK_TEMPLATE_PLACEHOLDER: &str = "__hab_template__";
+
+lazy_static! {
+    pub(crate) static ref HOOK_TEMPLATE_EXPRESSION: Regex =
+        Regex::new(r"\{\{\s*([^}]*?)\s*\}\}").unwrap();
+    pub(crate) static ref HOOK_PKG_PATH_FOR: Regex =
+        Regex::new(r#"^pkgPathFor\s+"([^/"]+)/([^/"]+)"$"#).unwrap();
+    static ref HOOK_INTERPRETER_PKG_PATH_FOR: Regex =
+        Regex::new(r#"^\{\{~?\s*pkgPathFor\s+"([^/"]+)/([^/"]+)"\s*~?\}\}(\S*)"#).unwrap();
+    static ref HOOK_INTERPRETER_PKG_PATH: Regex =
+        Regex::new(r"^\{\{~?\s*pkg\.path\s*~?\}\}(\S*)").unwrap();
+    static ref SHELL_COMMAND_NAME: Regex = Regex::new(r"^[A-Za-z_][A-Za-z0-9_.+-]*$").unwrap();
+}
+
+/// Interpreter of a hook, as declared on its first line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HookInterpreter {
+    /// Interpreter inside a dependency, such as `{{pkgPathFor "core/bash"}}/bin/bash`,
+    /// with the path relative to the dependency's package folder
+    Dependency {
+        origin: String,
+        name: String,
+        path: PathBuf,
+    },
+    /// Interpreter inside the package itself, such as `{{pkg.path}}/bin/server`,
+    /// with the path relative to the package folder
+    Package(PathBuf),
+    /// Interpreter found in the runtime path by `env`, such as `/usr/bin/env bash`
+    Env(String),
+    /// Absolute path to an interpreter
+    Path(PathBuf),
+}
+
+impl HookInterpreter {
+    /// Name of the interpreter's executable, used to select the shell dialect
+    pub fn command_name(&self) -> Option<&str> {
+        match self {
+            HookInterpreter::Dependency { path, .. }
+            | HookInterpreter::Package(path)
+            | HookInterpreter::Path(path) => path.file_name().and_then(|name| name.to_str()),
+            HookInterpreter::Env(command) => Some(command.as_str()),
+        }
+    }
+
+    /// Whether the interpreter is a POSIX compatible shell
+    pub fn is_shell(&self) -> bool {
+        self.command_name()
+            .is_some_and(|name| HOOK_SHELLS.contains(&name))
+    }
+}
+
+/// Lifecycle hook of a plan or a package
+#[derive(Debug, Clone)]
+pub(crate) struct Hook {
+    pub name: String,
+    pub content: String,
+}
+
+impl Hook {
+    /// Reads the hooks in the `hooks` folder of a plan, if there is one
+    pub fn read_all(plan_dir: impl AsRef<Path>) -> Result<Vec<Hook>> {
+        let hooks_dir = plan_dir.as_ref().join(HOOKS_DIRECTORY);
+        if !hooks_dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut hooks = Vec::new();
+        for entry in std::fs::read_dir(&hooks_dir)
+            .with_context(|| format!("Failed to read hooks folder '{}'", hooks_dir.display()))?
+        {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() || metadata.len() > HOOK_MAX_SIZE {
+                continue;
+            }
+            let content = std::fs::read(entry.path())
+                .with_context(|| format!("Failed to read hook '{}'", entry.path().display()))?;
+            hooks.push(Hook {
+                name: entry.file_name().to_string_lossy().to_string(),
+                content: String::from_utf8_lossy(&content).to_string(),
+            });
+        }
+        hooks.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(hooks)
+    }
+
+    /// Whether the Supervisor runs a hook with this name
+    pub fn is_known(&self) -> bool {
+        HOOK_NAMES.contains(&self.name.as_str())
+    }
+
+    /// Path of the hook relative to the plan or package folder
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(HOOKS_DIRECTORY).join(&self.name)
+    }
+
+    /// Interpreter line of the hook, without the leading `#!`
+    pub fn interpreter_line(&self) -> Option<&str> {
+        self.content
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("#!"))
+            .map(|line| line.trim())
+    }
+
+    /// Interpreter of the hook, `None` if the hook does not start with `#!`
+    pub fn interpreter(&self) -> Option<HookInterpreter> {
+        let line = self.interpreter_line()?;
+        if let Some(captures) = HOOK_INTERPRETER_PKG_PATH_FOR.captures(line) {
+            return Some(HookInterpreter::Dependency {
+                origin: captures[1].to_string(),
+                name: captures[2].to_string(),
+                path: PathBuf::from(captures[3].trim_start_matches('/')),
+            });
+        }
+        if let Some(captures) = HOOK_INTERPRETER_PKG_PATH.captures(line) {
+            return Some(HookInterpreter::Package(PathBuf::from(
+                captures[1].trim_start_matches('/'),
+            )));
+        }
+        let mut words = line.split_whitespace();
+        let command = PathBuf::from(words.next()?);
+        if command.file_name().and_then(|name| name.to_str()) == Some("env") {
+            if let Some(argument) = words.find(|word| !word.starts_with('-')) {
+                return Some(HookInterpreter::Env(argument.to_string()));
+            }
+        }
+        Some(HookInterpreter::Path(command))
+    }
+
+    /// Dependencies referenced with `pkgPathFor`, as origin and name
+    pub fn pkg_path_for_references(&self) -> BTreeSet<(String, String)> {
+        HOOK_TEMPLATE_EXPRESSION
+            .captures_iter(&self.content)
+            .filter_map(|captures| {
+                HOOK_PKG_PATH_FOR
+                    .captures(&captures[1])
+                    .map(|dep| (dep[1].to_string(), dep[2].to_string()))
+            })
+            .collect()
+    }
+
+    /// Content of the hook with its template expressions replaced, so that it can be
+    /// analyzed as a shell script. Line numbers are preserved.
+    pub fn shell_source(&self, shell: &str) -> String {
+        let mut lines = self.content.lines();
+        let mut source = String::new();
+        if self.interpreter_line().is_some() {
+            lines.next();
+            source.push_str(&format!("#!/bin/{}\n", shell));
+        }
+        for line in lines {
+            let line = HOOK_TEMPLATE_EXPRESSION.replace_all(line, |captures: &regex::Captures| {
+                let expression = captures[1].trim_matches('~').trim();
+                if expression.starts_with(['#', '/', '!', '~']) || expression == "else" {
+                    String::new()
+                } else {
+                    String::from(HOOK_TEMPLATE_PLACEHOLDER)
+                }
+            });
+            source.push_str(&line);
+            source.push('\n');
+        }
+        source
+    }
+
+    /// Names of the external commands run by the hook, including the commands of its
+    /// command substitutions. Shell builtins, functions defined by the hook, commands
+    /// given by path or built from expansions and template expressions are left out.
+    /// Hooks that are not valid shell scripts have no commands.
+    pub fn commands(&self) -> BTreeSet<String> {
+        let mut functions = BTreeSet::new();
+        let mut names = Vec::new();
+        let mut sources = vec![self.shell_source("sh")];
+        while let Some(source) = sources.pop() {
+            let Ok(hook_file) = PlanFile::parse(&source) else {
+                continue;
+            };
+            functions.extend(
+                hook_file
+                    .functions
+                    .into_iter()
+                    .map(|function| function.name),
+            );
+            let assignment_words =
+                hook_file
+                    .assignments
+                    .iter()
+                    .flat_map(|assignment| match &assignment.value {
+                        AssignmentValue::Scalar(word) => std::slice::from_ref(word),
+                        AssignmentValue::Array(words) => words.as_slice(),
+                    });
+            for word in hook_file
+                .commands
+                .iter()
+                .flat_map(|command| command.words.iter())
+                .chain(assignment_words)
+            {
+                sources.extend(word.command_substitutions());
+            }
+            for command in hook_file.commands.iter() {
+                let name = command
+                    .words
+                    .iter()
+                    .map(Word::literal)
+                    .skip_while(|word| {
+                        word.as_deref()
+                            .is_some_and(|word| SHELL_COMMAND_RUNNERS.contains(&word))
+                    })
+                    .find(|word| !word.as_deref().is_some_and(|word| word.starts_with('-')))
+                    .flatten();
+                names.extend(name);
+            }
+        }
+        names
+            .into_iter()
+            .filter(|name| {
+                SHELL_COMMAND_NAME.is_match(name)
+                    && !name.contains(HOOK_TEMPLATE_PLACEHOLDER)
+                    && !SHELL_BUILTINS.contains(&name.as_str())
+                    && !functions.contains(name)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook_commands(content: &str) -> Vec<String> {
+        Hook {
+            name: String::from("run"),
+            content: content.to_string(),
+        }
+        .commands()
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn hook_commands_detection() {
+        assert_eq!(
+            hook_commands(
+                r#"#!{{pkgPathFor "core/bash"}}/bin/bash
+set -e
+start() {
+  exec nginx -c "{{pkg.svc_config_path}}/nginx.conf"
+}
+PORT={{cfg.port}} env | grep -q PORT && mkdir -p /tmp/x; start
+if [[ -f a && -f b ]]; then chown -R hab {{pkg.svc_data_path}}; fi
+for file in a b; do cp "$file" /tmp; done
+"$HOOK_CMD" arg
+{{pkgPathFor "core/curl"}}/bin/curl localhost
+{{cfg.command}} --flag
+command -v sed > /dev/null 2>&1
+(( retries++ ))
+"#
+            ),
+            vec!["chown", "cp", "env", "grep", "mkdir", "nginx", "sed"]
+        );
+    }
+
+    #[test]
+    fn hook_commands_quoting() {
+        assert_eq!(
+            hook_commands(
+                r#"#!/bin/sh
+echo "quoted; not-run && not-run" 'single | not-run' $'ansi\'; not-run'
+printf '%s\n' "a # not a comment" # not-run
+"ls" -la
+"#
+            ),
+            vec!["ls"]
+        );
+    }
+
+    #[test]
+    fn hook_commands_heredocs() {
+        assert_eq!(
+            hook_commands(
+                "#!/bin/bash\ncat > config <<CONFIG\nnot-run --flag\nserver { listen 80; }\nCONFIG\ntee config.toml <<-'TOML' | sort\n\tnot-run\n\tTOML\nuname -a\n"
+            ),
+            vec!["cat", "sort", "tee", "uname"]
+        );
+    }
+
+    #[test]
+    fn hook_commands_multi_line_strings() {
+        assert_eq!(
+            hook_commands(
+                r#"#!/bin/bash
+message="first line
+not-run; second line"
+awk '
+  { print $1 }
+  END { system("not-run") }
+' input \
+  | head -n 1
+"#
+            ),
+            vec!["awk", "head"]
+        );
+    }
+
+    #[test]
+    fn hook_commands_substitutions_and_cases() {
+        assert_eq!(
+            hook_commands(
+                r#"#!/bin/bash
+host="$(hostname -f)"
+ips=(`ip addr | awk '{ print $2 }'`)
+total=$((count + 1))
+case "$(uname -s)" in
+  Linux|linux) sysctl -n kernel.hostname ;;
+  (Darwin) launchctl limit ;;
+  *) ;;
+esac
+"#
+            ),
+            vec!["awk", "hostname", "ip", "launchctl", "sysctl", "uname"]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::{fmt::Display, process::Command};
+
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+lazy_static! {
+    pub(crate) static ref CURRENT_BUILD_HOST: BuildHostMetadata = BuildHostMetadata::detect();
+}
+
+/// Information about the host on which an artifact was built. Artifacts built
+/// from the same plan on hosts with different kernels, C libraries or toolchains
+/// can behave differently despite having identical idents.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub(crate) struct BuildHostMetadata {
+    pub os: String,
+    pub arch: String,
+    pub os_version: Option<String>,
+    pub kernel_version: Option<String>,
+    pub libc_version: Option<String>,
+    pub xcode_version: Option<String>,
+}
+
+impl BuildHostMetadata {
+    pub fn detect() -> BuildHostMetadata {
+        let metadata = if cfg!(target_os = "macos") {
+            BuildHostMetadata {
+                os: std::env::consts::OS.to_string(),
+                arch: std::env::consts::ARCH.to_string(),
+                os_version: command_output("sw_vers", &["-productVersion"]),
+                kernel_version: command_output("uname", &["-r"]),
+                libc_version: None,
+                xcode_version: command_output("xcodebuild", &["-version"])
+                    .and_then(|output| output.lines().next().map(str::to_string))
+                    .and_then(|line| line.strip_prefix("Xcode ").map(str::to_string)),
+            }
+        } else if cfg!(target_os = "windows") {
+            BuildHostMetadata {
+                os: std::env::consts::OS.to_string(),
+                arch: std::env::consts::ARCH.to_string(),
+                os_version: command_output("cmd", &["/C", "ver"]),
+                kernel_version: None,
+                libc_version: None,
+                xcode_version: None,
+            }
+        } else {
+            BuildHostMetadata {
+                os: std::env::consts::OS.to_string(),
+                arch: std::env::consts::ARCH.to_string(),
+                os_version: std::fs::read_to_string("/etc/os-release")
+                    .ok()
+                    .and_then(|data| {
+                        data.lines()
+                            .filter_map(|line| line.strip_prefix("PRETTY_NAME="))
+                            .map(|value| value.trim_matches('"').to_string())
+                            .next()
+                    }),
+                kernel_version: command_output("uname", &["-r"]),
+                libc_version: command_output("getconf", &["GNU_LIBC_VERSION"])
+                    .map(|output| output.trim_start_matches("glibc ").to_string()),
+                xcode_version: None,
+            }
+        };
+        debug!("Detected build host: {}", metadata);
+        metadata
+    }
+
+    /// Lists the significant differences between two hosts, minor and patch level
+    /// version differences are not considered significant.
+    pub fn differences(&self, other: &BuildHostMetadata) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.os != other.os || self.arch != other.arch {
+            differences.push(format!(
+                "platform {}-{} != {}-{}",
+                self.arch, self.os, other.arch, other.os
+            ));
+        }
+        for (name, value, other_value, significant_parts) in [
+            ("kernel", &self.kernel_version, &other.kernel_version, 2),
+            ("libc", &self.libc_version, &other.libc_version, 2),
+            ("xcode", &self.xcode_version, &other.xcode_version, 1),
+        ] {
+            if let (Some(value), Some(other_value)) = (value, other_value) {
+                if version_prefix(value, significant_parts)
+                    != version_prefix(other_value, significant_parts)
+                {
+                    differences.push(format!("{} {} != {}", name, value, other_value));
+                }
+            }
+        }
+        differences
+    }
+}
+
+impl Display for BuildHostMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.arch, self.os)?;
+        if let Some(os_version) = self.os_version.as_ref() {
+            write!(f, ", {}", os_version)?;
+        }
+        if let Some(kernel_version) = self.kernel_version.as_ref() {
+            write!(f, ", kernel {}", kernel_version)?;
+        }
+        if let Some(libc_version) = self.libc_version.as_ref() {
+            write!(f, ", glibc {}", libc_version)?;
+        }
+        if let Some(xcode_version) = self.xcode_version.as_ref() {
+            write!(f, ", xcode {}", xcode_version)?;
+        }
+        Ok(())
+    }
+}
+
+/// Name of the current host, used to identify the workers of cooperative builds
+pub(crate) fn host_name() -> Option<String> {
+    command_output("hostname", &[])
+}
+
+/// Checks whether a process with the given id is running on the current host
+pub(crate) fn process_is_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        std::path::Path::new("/proc").join(pid.to_string()).exists()
+    } else if cfg!(target_os = "windows") {
+        command_output("tasklist", &["/FI", &format!("PID eq {}", pid), "/NH"])
+            .map(|output| {
+                output
+                    .split_whitespace()
+                    .any(|field| field == pid.to_string())
+            })
+            .unwrap_or(true)
+    } else {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(true)
+    }
+}
+
+fn version_prefix(version: &str, parts: usize) -> Vec<&str> {
+    version
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .take(parts)
+        .collect()
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+    let output = output.trim();
+    if output.is_empty() {
+        None
+    } else {
+        Some(output.to_string())
+    }
+}
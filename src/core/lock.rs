@@ -0,0 +1,121 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Version of the lockfile format
+const DEPENDENCY_LOCK_VERSION: u32 = 1;
+
+/// Pins every remote dependency of the build graph to the artifact it resolves to.
+/// Dependencies are keyed by the identifier used to depend on them, along with their
+/// target, and pinned to the 'origin/name/version/release' of the artifact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct DependencyLock {
+    pub version: u32,
+    pub dependencies: BTreeMap<String, String>,
+}
+
+/// Difference between the resolution of a dependency recorded in the lockfile
+/// and its current resolution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DependencyLockDifference {
+    /// Dependency that resolves to a different artifact
+    Changed {
+        dependency: String,
+        locked: String,
+        current: String,
+    },
+    /// Dependency that is not pinned by the lockfile
+    Added {
+        dependency: String,
+        current: Option<String>,
+    },
+    /// Pinned dependency that is no longer used by the build graph
+    Removed { dependency: String, locked: String },
+    /// Pinned dependency for which no artifact is available anymore
+    Unresolved { dependency: String, locked: String },
+}
+
+impl Default for DependencyLock {
+    fn default() -> Self {
+        DependencyLock {
+            version: DEPENDENCY_LOCK_VERSION,
+            dependencies: BTreeMap::new(),
+        }
+    }
+}
+
+impl DependencyLock {
+    pub fn read(path: impl AsRef<Path>) -> Result<DependencyLock> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lockfile '{}'", path.display()))?;
+        serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse lockfile '{}'", path.display()))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(
+            path,
+            serde_json::to_string_pretty(self).context("Failed to serialize lockfile into JSON")?,
+        )
+        .with_context(|| format!("Failed to write lockfile '{}'", path.display()))
+    }
+
+    /// Compares the dependencies pinned by this lockfile with their current
+    /// resolution, dependencies that cannot be resolved currently are given
+    /// separately.
+    pub fn differences(
+        &self,
+        current: &DependencyLock,
+        unresolved: &[String],
+    ) -> Vec<DependencyLockDifference> {
+        let mut differences = Vec::new();
+        for (dependency, locked) in self.dependencies.iter() {
+            match current.dependencies.get(dependency) {
+                Some(current) if current == locked => {}
+                Some(current) => differences.push(DependencyLockDifference::Changed {
+                    dependency: dependency.clone(),
+                    locked: locked.clone(),
+                    current: current.clone(),
+                }),
+                None if unresolved.contains(dependency) => {
+                    differences.push(DependencyLockDifference::Unresolved {
+                        dependency: dependency.clone(),
+                        locked: locked.clone(),
+                    })
+                }
+                None => differences.push(DependencyLockDifference::Removed {
+                    dependency: dependency.clone(),
+                    locked: locked.clone(),
+                }),
+            }
+        }
+        for (dependency, current) in current.dependencies.iter() {
+            if !self.dependencies.contains_key(dependency) {
+                differences.push(DependencyLockDifference::Added {
+                    dependency: dependency.clone(),
+                    current: Some(current.clone()),
+                });
+            }
+        }
+        for dependency in unresolved.iter() {
+            if !self.dependencies.contains_key(dependency) {
+                differences.push(DependencyLockDifference::Added {
+                    dependency: dependency.clone(),
+                    current: None,
+                });
+            }
+        }
+        differences
+    }
+}
+
+/// The lockfile is kept next to the hab-auto-build configuration
+pub(crate) fn dependency_lock_path(config_path: impl AsRef<Path>) -> PathBuf {
+    config_path.as_ref().with_extension("lock")
+}
@@ -1,31 +1,76 @@
 mod archive;
 mod artifact;
+mod artifact_diff;
+mod audit;
 mod auto_build;
+mod build_failure;
+mod builder;
 mod crypto_hash;
 mod dep_graph;
 mod download;
 mod fs;
 pub mod habitat;
+mod hook;
+mod host;
+mod lock;
+mod notify;
+mod oci_image;
 mod package;
 mod package_source;
 mod plan;
 mod repo;
+mod sbom;
+mod service_config;
+mod signature;
 mod source;
+mod store_archive;
+mod store_repair;
+mod update;
 
 #[allow(unused_imports)]
 pub use archive::*;
 #[allow(unused_imports)]
 pub use artifact::*;
+#[allow(unused_imports)]
+pub use artifact_diff::*;
+#[allow(unused_imports)]
+pub use audit::*;
 pub use auto_build::*;
+#[allow(unused_imports)]
+pub use build_failure::*;
+#[allow(unused_imports)]
+pub use builder::*;
 pub use crypto_hash::*;
 #[allow(unused_imports)]
 pub use dep_graph::*;
 pub use download::*;
 pub use fs::*;
+#[allow(unused_imports)]
+pub use hook::*;
+#[allow(unused_imports)]
+pub use host::*;
+#[allow(unused_imports)]
+pub use lock::*;
+#[allow(unused_imports)]
+pub use notify::*;
+#[allow(unused_imports)]
+pub use oci_image::*;
 pub use package::*;
 pub use package_source::*;
 #[allow(unused_imports)]
 pub use plan::*;
 pub use repo::*;
 #[allow(unused_imports)]
+pub use sbom::*;
+#[allow(unused_imports)]
+pub use service_config::*;
+#[allow(unused_imports)]
+pub use signature::*;
+#[allow(unused_imports)]
 pub use source::*;
+#[allow(unused_imports)]
+pub use store_archive::*;
+#[allow(unused_imports)]
+pub use store_repair::*;
+#[allow(unused_imports)]
+pub use update::*;
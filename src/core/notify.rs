@@ -0,0 +1,158 @@
+use std::process::Command;
+
+use chrono::Duration;
+use chrono_humanize::{Accuracy, HumanTime, Tense};
+use color_eyre::eyre::{eyre, Context, Report, Result};
+use reqwest::{blocking::ClientBuilder, header};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use which::which;
+
+/// Outcome of a build session that can trigger a notification
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotifyEvent {
+    Success,
+    Failure,
+}
+
+/// Notifications sent when a build session ends
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotifyConfig {
+    /// Slack compatible incoming webhook the summary of the build session is posted to
+    pub webhook: Option<String>,
+    /// Outcomes of build sessions to notify about, defaults to all of them
+    #[serde(default = "NotifyConfig::default_on")]
+    pub on: Vec<NotifyEvent>,
+    /// Show a desktop notification as well
+    #[serde(default)]
+    pub desktop: bool,
+}
+
+impl NotifyConfig {
+    fn default_on() -> Vec<NotifyEvent> {
+        vec![NotifyEvent::Success, NotifyEvent::Failure]
+    }
+
+    /// Sends the notifications configured for the outcome of the build session,
+    /// every notification is attempted even if an earlier one fails.
+    pub fn notify(&self, summary: &BuildSessionSummary) -> Vec<Report> {
+        let event = if summary.succeeded {
+            NotifyEvent::Success
+        } else {
+            NotifyEvent::Failure
+        };
+        if !self.on.contains(&event) {
+            return Vec::new();
+        }
+        let mut errors = Vec::new();
+        if let Some(webhook) = self.webhook.as_ref() {
+            if let Err(err) = webhook_notify(webhook, summary) {
+                errors.push(err);
+            }
+        }
+        if self.desktop {
+            if let Err(err) = desktop_notify(summary) {
+                errors.push(err);
+            }
+        }
+        errors
+    }
+}
+
+/// Summary of a finished build session, as sent in notifications
+#[derive(Debug, Clone)]
+pub(crate) struct BuildSessionSummary {
+    pub workspace: String,
+    pub succeeded: bool,
+    pub planned: usize,
+    pub built: usize,
+    pub failed_plans: Vec<String>,
+    pub duration: Option<Duration>,
+}
+
+impl BuildSessionSummary {
+    pub fn title(&self) -> String {
+        format!(
+            "hab-auto-build {} in '{}'",
+            if self.succeeded {
+                "build succeeded"
+            } else {
+                "build failed"
+            },
+            self.workspace
+        )
+    }
+
+    pub fn message(&self) -> String {
+        let mut message = format!("Built {}/{} packages", self.built, self.planned);
+        if let Some(duration) = self.duration {
+            message.push_str(&format!(
+                " in {}",
+                HumanTime::from(duration).to_text_en(Accuracy::Rough, Tense::Present)
+            ));
+        }
+        if !self.failed_plans.is_empty() {
+            message.push_str(&format!(
+                ", {} failed: {}",
+                self.failed_plans.len(),
+                self.failed_plans.join(", ")
+            ));
+        }
+        message
+    }
+}
+
+fn webhook_notify(webhook: &str, summary: &BuildSessionSummary) -> Result<()> {
+    debug!("Posting build session summary to '{}'", webhook);
+    let body = serde_json::json!({
+        "text": format!("*{}*\n{}", summary.title(), summary.message())
+    });
+    let response = ClientBuilder::new()
+        .build()?
+        .post(webhook)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::USER_AGENT, "hab-auto-build")
+        .body(body.to_string())
+        .send()
+        .with_context(|| eyre!("Failed to post the build summary to '{}'", webhook))?;
+    if !response.status().is_success() {
+        return Err(eyre!(
+            "Failed to post the build summary to '{}', the server responded with {}",
+            webhook,
+            response.status()
+        ));
+    }
+    Ok(())
+}
+
+fn desktop_notify(summary: &BuildSessionSummary) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {:?} with title {:?}",
+            summary.message(),
+            summary.title()
+        ));
+        command
+    } else {
+        let notify_send = which("notify-send")
+            .map_err(|_| eyre!("Failed to find 'notify-send' to show desktop notifications"))?;
+        let mut command = Command::new(notify_send);
+        command
+            .arg("--app-name=hab-auto-build")
+            .arg(summary.title())
+            .arg(summary.message());
+        command
+    };
+    let status = command
+        .status()
+        .context("Failed to show desktop notification")?;
+    if !status.success() {
+        return Err(eyre!(
+            "Failed to show desktop notification, the command exited with {}",
+            status
+        ));
+    }
+    Ok(())
+}
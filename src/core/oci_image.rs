@@ -0,0 +1,401 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{SecondsFormat, Utc};
+use color_eyre::eyre::{eyre, Context, Result};
+use flate2::{write::GzEncoder, Compression};
+use regex::Captures;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tar::{Archive, Builder, EntryType, Header};
+use tracing::debug;
+use xz2::bufread::XzDecoder;
+
+use super::{
+    habitat, ArtifactContext, PackageIdent, PackageOS, HOOK_PKG_PATH_FOR, HOOK_TEMPLATE_EXPRESSION,
+};
+
+const OCI_IMAGE_LAYOUT_VERSION: &str = "1.0.0";
+const OCI_MEDIA_TYPE_INDEX: &str = "application/vnd.oci.image.index.v1+json";
+const OCI_MEDIA_TYPE_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+const OCI_MEDIA_TYPE_CONFIG: &str = "application/vnd.oci.image.config.v1+json";
+const OCI_MEDIA_TYPE_LAYER: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+
+/// Summary of a container image exported from a package
+#[derive(Debug)]
+pub(crate) struct OciImageExport {
+    pub tag: String,
+    pub digest: String,
+    /// Number of packages installed in the image, including the exported package
+    pub package_count: usize,
+    /// Whether the package's run hook is the entrypoint of the image
+    pub has_entrypoint: bool,
+}
+
+/// Writer that computes the digest and size of the data written through it
+struct DigestWriter<W: Write> {
+    writer: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> DigestWriter<W> {
+    fn new(writer: W) -> Self {
+        DigestWriter {
+            writer,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    fn finish(self) -> (W, String, u64) {
+        (
+            self.writer,
+            format!("sha256:{:x}", self.hasher.finalize()),
+            self.size,
+        )
+    }
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Describes a blob of the image, as referenced by the image manifest and index
+struct OciBlob {
+    media_type: &'static str,
+    digest: String,
+    size: u64,
+}
+
+impl OciBlob {
+    fn from_data(media_type: &'static str, data: &[u8]) -> OciBlob {
+        OciBlob {
+            media_type,
+            digest: format!("sha256:{:x}", Sha256::digest(data)),
+            size: data.len() as u64,
+        }
+    }
+
+    fn descriptor(&self) -> Value {
+        json!({
+            "mediaType": self.media_type,
+            "digest": self.digest,
+            "size": self.size,
+        })
+    }
+
+    fn path(&self) -> String {
+        format!("blobs/{}", self.digest.replacen(':', "/", 1))
+    }
+}
+
+/// Opens the compressed tarball of a .hart file, skipping its signature header
+pub(crate) fn artifact_archive_open(
+    artifact_path: &Path,
+) -> Result<Archive<XzDecoder<BufReader<File>>>> {
+    let mut reader = BufReader::new(
+        File::open(artifact_path)
+            .with_context(|| format!("Failed to open artifact '{}'", artifact_path.display()))?,
+    );
+    let mut line = String::new();
+    for _ in 0..5 {
+        if reader.read_line(&mut line)? == 0 {
+            return Err(eyre!(
+                "The file '{}' is not a valid .hart file",
+                artifact_path.display()
+            ));
+        }
+    }
+    Ok(Archive::new(XzDecoder::new(reader)))
+}
+
+/// Renders the template expressions of a run hook that do not depend on the
+/// Supervisor, any other expression is reported as an error.
+fn run_hook_render(
+    hook: &str,
+    artifact: &ArtifactContext,
+    tdeps: &BTreeMap<PackageIdent, PathBuf>,
+) -> Result<String> {
+    let pkg_path = |ident: &PackageIdent| {
+        format!(
+            "/hab/pkgs/{}/{}/{}/{}",
+            ident.origin, ident.name, ident.version, ident.release
+        )
+    };
+    let mut unsupported = Vec::new();
+    let rendered = HOOK_TEMPLATE_EXPRESSION.replace_all(hook, |captures: &Captures| {
+        let expression = &captures[1];
+        let id = &artifact.id;
+        match expression {
+            "pkg.ident" => format!("{}/{}/{}/{}", id.origin, id.name, id.version, id.release),
+            "pkg.origin" => id.origin.to_string(),
+            "pkg.name" => id.name.to_string(),
+            "pkg.version" => id.version.to_string(),
+            "pkg.release" => id.release.to_string(),
+            "pkg.path" => pkg_path(id),
+            _ => {
+                let tdep = HOOK_PKG_PATH_FOR.captures(expression).and_then(|dep| {
+                    tdeps.keys().find(|tdep| {
+                        tdep.origin.to_string() == dep[1] && tdep.name.to_string() == dep[2]
+                    })
+                });
+                match tdep {
+                    Some(tdep) => pkg_path(tdep),
+                    None => {
+                        unsupported.push(expression.to_string());
+                        captures[0].to_string()
+                    }
+                }
+            }
+        }
+    });
+    if !unsupported.is_empty() {
+        return Err(eyre!(
+            "The run hook of {} uses template expressions that require the Habitat Supervisor: {}",
+            artifact.id,
+            unsupported.join(", ")
+        ));
+    }
+    Ok(rendered.into_owned())
+}
+
+/// Adds the contents of an artifact to an image layer, returns the contents of
+/// the artifact's run hook if it has one.
+fn artifact_layer_append<W: Write>(
+    builder: &mut Builder<W>,
+    artifact_ident: &PackageIdent,
+    artifact_path: &Path,
+) -> Result<Option<String>> {
+    let run_hook_path = PathBuf::from(format!(
+        "hab/pkgs/{}/{}/{}/{}/hooks/run",
+        artifact_ident.origin, artifact_ident.name, artifact_ident.version, artifact_ident.release
+    ));
+    let mut run_hook = None;
+    let mut archive = artifact_archive_open(artifact_path)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let mut header = entry.header().clone();
+        let path = entry.path()?.into_owned();
+        match header.entry_type() {
+            EntryType::Symlink | EntryType::Link => {
+                let link_name = entry
+                    .link_name()?
+                    .ok_or_else(|| {
+                        eyre!(
+                            "Link '{}' in artifact '{}' has no target",
+                            path.display(),
+                            artifact_path.display()
+                        )
+                    })?
+                    .into_owned();
+                builder.append_link(&mut header, &path, &link_name)?;
+            }
+            EntryType::Regular if path == run_hook_path => {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                builder.append_data(&mut header, &path, data.as_slice())?;
+                run_hook = Some(String::from_utf8_lossy(&data).into_owned());
+            }
+            _ => {
+                builder.append_data(&mut header, &path, &mut entry)?;
+            }
+        }
+    }
+    Ok(run_hook)
+}
+
+fn layer_directory_append<W: Write>(builder: &mut Builder<W>, path: &str, mode: u32) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Directory);
+    header.set_mode(mode);
+    header.set_size(0);
+    header.set_mtime(0);
+    builder.append_data(&mut header, path, std::io::empty())?;
+    Ok(())
+}
+
+fn archive_data_append<W: Write>(builder: &mut Builder<W>, path: &str, data: &[u8]) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_size(data.len() as u64);
+    header.set_mtime(0);
+    builder.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+/// Exports a package and its transitive dependencies as an OCI image archive with
+/// a single layer holding their '/hab/pkgs' folders. The run hook of the package,
+/// if any, becomes the entrypoint of the image.
+pub(crate) fn oci_image_export(
+    artifact: &ArtifactContext,
+    artifact_path: &Path,
+    tdeps: &BTreeMap<PackageIdent, PathBuf>,
+    tag: &str,
+    tmp_dir: &Path,
+    output_path: &Path,
+) -> Result<OciImageExport> {
+    if artifact.id.target.os != PackageOS::Linux {
+        return Err(eyre!(
+            "Container images can only be exported for Linux packages, {} is a {} package",
+            artifact.id,
+            artifact.id.target
+        ));
+    }
+
+    // Build the layer, it is hashed both before and after compression
+    let layer_path = tmp_dir.join("layer.tar.gz");
+    let layer_file = File::create(&layer_path)
+        .with_context(|| format!("Failed to create image layer '{}'", layer_path.display()))?;
+    let mut builder = Builder::new(DigestWriter::new(GzEncoder::new(
+        DigestWriter::new(BufWriter::new(layer_file)),
+        Compression::default(),
+    )));
+    for (path, mode) in [("hab", 0o755), ("hab/pkgs", 0o755), ("tmp", 0o1777)] {
+        layer_directory_append(&mut builder, path, mode)?;
+    }
+    for (tdep, tdep_path) in tdeps.iter() {
+        debug!(
+            "Adding {} to image layer from {}",
+            tdep,
+            tdep_path.display()
+        );
+        artifact_layer_append(&mut builder, tdep, tdep_path)?;
+    }
+    debug!(
+        "Adding {} to image layer from {}",
+        artifact.id,
+        artifact_path.display()
+    );
+    let entrypoint = match artifact_layer_append(&mut builder, &artifact.id, artifact_path)? {
+        Some(run_hook) => {
+            let run_hook = run_hook_render(&run_hook, artifact, tdeps)?;
+            let run_hook_path = format!("hab/svc/{}/hooks/run", artifact.id.name);
+            for path in [
+                "hab/svc".to_string(),
+                format!("hab/svc/{}", artifact.id.name),
+                format!("hab/svc/{}/hooks", artifact.id.name),
+            ] {
+                layer_directory_append(&mut builder, &path, 0o755)?;
+            }
+            let mut header = Header::new_gnu();
+            header.set_mode(0o755);
+            header.set_size(run_hook.len() as u64);
+            header.set_mtime(0);
+            builder.append_data(&mut header, &run_hook_path, run_hook.as_bytes())?;
+            Some(format!("/{}", run_hook_path))
+        }
+        None => None,
+    };
+    let (gz_encoder, diff_id, _) = builder.into_inner()?.finish();
+    let (mut layer_writer, layer_digest, layer_size) = gz_encoder.finish()?.finish();
+    layer_writer.flush()?;
+    drop(layer_writer);
+    let layer = OciBlob {
+        media_type: OCI_MEDIA_TYPE_LAYER,
+        digest: layer_digest,
+        size: layer_size,
+    };
+
+    let created = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let mut config = json!({
+        "Env": [format!(
+            "PATH={}",
+            artifact
+                .runtime_path
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(":")
+        )],
+        "WorkingDir": "/",
+        "Labels": {
+            "org.opencontainers.image.title": artifact.id.name.to_string(),
+            "org.opencontainers.image.version": format!("{}-{}", artifact.id.version, artifact.id.release),
+            "sh.habitat.package.ident": format!(
+                "{}/{}/{}/{}",
+                artifact.id.origin, artifact.id.name, artifact.id.version, artifact.id.release
+            ),
+        },
+    });
+    if let Some(entrypoint) = entrypoint.as_ref() {
+        config["Entrypoint"] = json!([entrypoint]);
+    }
+    let config_data = serde_json::to_vec(&json!({
+        "created": created,
+        "architecture": habitat::docker_arch(artifact.id.target.arch),
+        "os": "linux",
+        "config": config,
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": [diff_id],
+        },
+        "history": [{
+            "created": created,
+            "created_by": format!("{} export docker", env!("CARGO_PKG_NAME")),
+        }],
+    }))?;
+    let config_blob = OciBlob::from_data(OCI_MEDIA_TYPE_CONFIG, &config_data);
+    let manifest_data = serde_json::to_vec(&json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_MEDIA_TYPE_MANIFEST,
+        "config": config_blob.descriptor(),
+        "layers": [layer.descriptor()],
+    }))?;
+    let manifest_blob = OciBlob::from_data(OCI_MEDIA_TYPE_MANIFEST, &manifest_data);
+    let mut manifest_descriptor = manifest_blob.descriptor();
+    manifest_descriptor["annotations"] = json!({ "org.opencontainers.image.ref.name": tag });
+    let index_data = serde_json::to_vec(&json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_MEDIA_TYPE_INDEX,
+        "manifests": [manifest_descriptor],
+    }))?;
+    // Lets 'docker load' import the archive on versions without OCI layout support
+    let docker_manifest_data = serde_json::to_vec(&json!([{
+        "Config": config_blob.path(),
+        "RepoTags": [tag],
+        "Layers": [layer.path()],
+    }]))?;
+
+    // Write the image layout archive
+    let output_file = File::create(output_path)
+        .with_context(|| format!("Failed to create image archive '{}'", output_path.display()))?;
+    let mut builder = Builder::new(BufWriter::new(output_file));
+    archive_data_append(
+        &mut builder,
+        "oci-layout",
+        json!({ "imageLayoutVersion": OCI_IMAGE_LAYOUT_VERSION })
+            .to_string()
+            .as_bytes(),
+    )?;
+    archive_data_append(&mut builder, "index.json", &index_data)?;
+    archive_data_append(&mut builder, "manifest.json", &docker_manifest_data)?;
+    archive_data_append(&mut builder, &config_blob.path(), &config_data)?;
+    archive_data_append(&mut builder, &manifest_blob.path(), &manifest_data)?;
+    let mut header = Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_size(layer.size);
+    header.set_mtime(0);
+    builder.append_data(&mut header, layer.path(), File::open(&layer_path)?)?;
+    builder.into_inner()?.flush()?;
+
+    Ok(OciImageExport {
+        tag: tag.to_string(),
+        digest: manifest_blob.digest,
+        package_count: tdeps.len() + 1,
+        has_entrypoint: entrypoint.is_some(),
+    })
+}
@@ -253,6 +253,14 @@ impl PackageTarget {
     }
 }
 
+impl FromStr for PackageTarget {
+    type Err = color_eyre::eyre::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        PackageTarget::parse(value)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum PackageArch {
     X86_64,
@@ -669,6 +677,25 @@ impl Display for PackageDepIdent {
 }
 
 impl PackageDepIdent {
+    /// Whether the dependency refers to the other dependency, leaving out the
+    /// version and release when it does not specify them
+    pub fn includes(&self, dep_ident: &PackageDepIdent) -> bool {
+        self.origin == dep_ident.origin
+            && self.name == dep_ident.name
+            && match &self.version {
+                resolved_version @ PackageVersion::Resolved(_) => {
+                    resolved_version == &dep_ident.version
+                }
+                PackageVersion::Unresolved => true,
+            }
+            && match &self.release {
+                resolved_release @ PackageRelease::Resolved(_) => {
+                    resolved_release == &dep_ident.release
+                }
+                PackageRelease::Unresolved => true,
+            }
+    }
+
     pub fn to_resolved_dep_ident(&self, target: PackageTarget) -> PackageResolvedDepIdent {
         PackageResolvedDepIdent {
             name: self.name.to_owned(),
@@ -1016,11 +1043,11 @@ mod tests {
 
         for dep_ident in satisfed_dep_idents {
             let dep_ident = PackageDepIdent::parse(dep_ident).unwrap();
-            assert_eq!(dynamic_ident.satisfies_dependency(&dep_ident), true);
+            assert!(dynamic_ident.satisfies_dependency(&dep_ident));
         }
         for dep_ident in unsatisfied_dep_idents {
             let dep_ident = PackageDepIdent::parse(dep_ident).unwrap();
-            assert_eq!(dynamic_ident.satisfies_dependency(&dep_ident), false);
+            assert!(!dynamic_ident.satisfies_dependency(&dep_ident));
         }
     }
 
@@ -1044,11 +1071,11 @@ mod tests {
 
         for dep_glob in satisfed_dep_globs {
             let dep_glob = PackageDepGlob::parse(dep_glob).unwrap().matcher();
-            assert_eq!(dep_glob.matches_package_build_ident(&dynamic_ident), true);
+            assert!(dep_glob.matches_package_build_ident(&dynamic_ident));
         }
         for dep_glob in unsatisfied_dep_globs {
             let dep_glob = PackageDepGlob::parse(dep_glob).unwrap().matcher();
-            assert_eq!(dep_glob.matches_package_build_ident(&dynamic_ident), false);
+            assert!(!dep_glob.matches_package_build_ident(&dynamic_ident));
         }
     }
 }
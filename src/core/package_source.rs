@@ -4,17 +4,18 @@ use std::{
     time::Instant,
 };
 
-use chrono::Duration;
+use chrono::{Duration, Utc};
 use color_eyre::{
     eyre::{eyre, Context, Result},
     Help,
 };
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
+use subprocess::{Exec, NullFile, Redirection};
 use thiserror::Error;
-use tracing::debug;
+use tracing::{debug, warn};
 
-use super::{Download, ShaSum};
+use super::{Download, DownloadTracker, Metadata, ShaSum};
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct PackageSource {
@@ -33,11 +34,71 @@ pub enum PackageSourceDownloadError {
 }
 
 impl PackageSource {
+    /// Downloads the source archive and verifies its shasum. Each mirror is tried in
+    /// order before falling back to the upstream url, returns the url from which the
+    /// archive was downloaded.
     pub fn download_and_verify_pkg_archive(
         &self,
         dest: impl AsRef<Path>,
-    ) -> Result<Duration, PackageSourceDownloadError> {
+        mirrors: &[PackageSourceURL],
+        tracker: &DownloadTracker,
+    ) -> Result<(Duration, PackageSourceURL), PackageSourceDownloadError> {
         let start = Instant::now();
+        let filename = if mirrors.is_empty() {
+            None
+        } else {
+            match self.url.filename() {
+                Ok(filename) => Some(filename),
+                Err(err) => {
+                    debug!(
+                        "Skipping mirrors for package source {}, the archive name could not be determined: {:#}",
+                        self.url, err
+                    );
+                    None
+                }
+            }
+        };
+        if let Some(filename) = filename {
+            for mirror in mirrors {
+                let mirror_url = match mirror.mirror_url(&filename) {
+                    Ok(mirror_url) => mirror_url,
+                    Err(err) => {
+                        debug!(
+                            "Skipping mirror {} for package source {}: {:#}",
+                            mirror, self.url, err
+                        );
+                        continue;
+                    }
+                };
+                debug!(
+                    "Downloading package source from mirror {} to {}",
+                    mirror_url,
+                    dest.as_ref().display()
+                );
+                match Download::new(&mirror_url.0, dest.as_ref())
+                    .with_tracker(tracker)
+                    .execute()
+                {
+                    Ok(_) => match self.verify_pkg_archive(dest.as_ref()) {
+                        Ok(_) => {
+                            return Ok((Duration::from_std(start.elapsed()).unwrap(), mirror_url))
+                        }
+                        Err(err) => {
+                            warn!(
+                                "Package source from mirror {} is invalid: {}",
+                                mirror_url, err
+                            );
+                        }
+                    },
+                    Err(err) => {
+                        debug!(
+                            "Failed to download package source from mirror {}: {:#}",
+                            mirror_url, err
+                        );
+                    }
+                }
+            }
+        }
         debug!(
             "Downloading package source from {} to {}",
             self.url,
@@ -45,7 +106,7 @@ impl PackageSource {
         );
         let mut download_attempts = 3;
         while download_attempts > 0 {
-            match self.download_pkg_source(dest.as_ref()) {
+            match self.download_pkg_source(dest.as_ref(), tracker) {
                 Ok(_) => {
                     break;
                 }
@@ -56,7 +117,144 @@ impl PackageSource {
             }
         }
         self.verify_pkg_archive(dest.as_ref())?;
-        Ok(Duration::from_std(start.elapsed()).unwrap())
+        Ok((
+            Duration::from_std(start.elapsed()).unwrap(),
+            self.url.clone(),
+        ))
+    }
+
+    /// Fetches the revision of a git source into a bare clone of its repository,
+    /// archives it to a deterministic tarball and verifies the tarball's shasum.
+    pub fn fetch_and_verify_git_archive(
+        &self,
+        dest: impl AsRef<Path>,
+        git_repos_path: impl AsRef<Path>,
+    ) -> Result<(Duration, PackageSourceURL), PackageSourceDownloadError> {
+        let start = Instant::now();
+        let git_source = self
+            .url
+            .git_source()
+            .ok_or_else(|| {
+                eyre!(
+                    "Git source url '{}' must specify a revision, like 'git+https://example.com/repo.git#v1.0.0'",
+                    self.url
+                )
+            })?;
+        let repo_path = git_repos_path.as_ref().join(git_source.repo_id());
+        if repo_path.is_dir() {
+            debug!(
+                "Fetching git repository {} into {}",
+                git_source.repo_url,
+                repo_path.display()
+            );
+            git_run(
+                Exec::cmd("git")
+                    .arg("-C")
+                    .arg(&repo_path)
+                    .arg("fetch")
+                    .arg("--quiet")
+                    .arg("--tags")
+                    .arg("--force")
+                    .arg(&git_source.repo_url)
+                    .arg("+refs/heads/*:refs/heads/*"),
+            )
+            .with_context(|| format!("Failed to fetch git repository {}", git_source.repo_url))?;
+            // Fetching does not always touch the clone's folder, its modification time
+            // tells the clean command when the clone was last used
+            repo_path.set_last_modifed_at(Utc::now())?;
+        } else {
+            debug!(
+                "Cloning git repository {} into {}",
+                git_source.repo_url,
+                repo_path.display()
+            );
+            std::fs::create_dir_all(git_repos_path.as_ref())?;
+            if let Err(err) = git_run(
+                Exec::cmd("git")
+                    .arg("clone")
+                    .arg("--quiet")
+                    .arg("--bare")
+                    .arg(&git_source.repo_url)
+                    .arg(&repo_path),
+            ) {
+                if repo_path.is_dir() {
+                    std::fs::remove_dir_all(&repo_path)?;
+                }
+                return Err(err
+                    .wrap_err(format!(
+                        "Failed to clone git repository {}",
+                        git_source.repo_url
+                    ))
+                    .into());
+            }
+        }
+        let revision = format!("{}^{{commit}}", git_source.revision);
+        let commit = match git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(&repo_path)
+                .arg("rev-parse")
+                .arg("--verify")
+                .arg(&revision),
+        ) {
+            Ok(commit) => commit,
+            Err(_) => {
+                // Commits that are not reachable from a branch or tag must be fetched explicitly
+                git_run(
+                    Exec::cmd("git")
+                        .arg("-C")
+                        .arg(&repo_path)
+                        .arg("fetch")
+                        .arg("--quiet")
+                        .arg(&git_source.repo_url)
+                        .arg(&git_source.revision),
+                )
+                .with_context(|| {
+                    format!(
+                        "Failed to find revision '{}' in git repository {}",
+                        git_source.revision, git_source.repo_url
+                    )
+                })?;
+                git_run(
+                    Exec::cmd("git")
+                        .arg("-C")
+                        .arg(&repo_path)
+                        .arg("rev-parse")
+                        .arg("--verify")
+                        .arg(&revision),
+                )?
+            }
+        };
+        debug!(
+            "Archiving commit {} of git repository {} to {}",
+            commit,
+            git_source.repo_url,
+            dest.as_ref().display()
+        );
+        // The tar format of git archive is deterministic, unlike its compressed formats
+        // which depend on the version of git
+        git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(&repo_path)
+                .arg("archive")
+                .arg("--format=tar")
+                .arg(format!("--prefix={}/", git_source.archive_prefix()))
+                .arg("-o")
+                .arg(dest.as_ref())
+                .arg(&commit),
+        )
+        .with_context(|| {
+            format!(
+                "Failed to archive revision '{}' of git repository {}",
+                git_source.revision, git_source.repo_url
+            )
+        })?;
+        self.verify_pkg_archive(dest.as_ref())?;
+        Ok((
+            Duration::from_std(start.elapsed()).unwrap(),
+            self.url.clone(),
+        ))
     }
 
     pub fn verify_pkg_archive(
@@ -79,8 +277,58 @@ impl PackageSource {
         }
     }
 
-    fn download_pkg_source(&self, dest: impl AsRef<Path>) -> Result<()> {
-        Download::new(&self.url.0, dest).execute()
+    fn download_pkg_source(&self, dest: impl AsRef<Path>, tracker: &DownloadTracker) -> Result<()> {
+        Download::new(&self.url.0, dest)
+            .with_tracker(tracker)
+            .execute()
+    }
+}
+
+/// Runs a git command and returns its trimmed output
+pub(crate) fn git_run(cmd: Exec) -> Result<String> {
+    let capture = cmd
+        .stdin(NullFile)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .capture()?;
+    if !capture.success() {
+        return Err(eyre!("{}", capture.stderr_str().trim()));
+    }
+    Ok(capture.stdout_str().trim().to_string())
+}
+
+/// Repository and revision of a package source written as `git+<url>#<revision>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    pub repo_url: String,
+    pub revision: String,
+}
+
+impl GitSource {
+    /// Name of the repository, taken from the last segment of its url
+    pub fn repo_name(&self) -> &str {
+        let repo_url = self.repo_url.trim_end_matches('/');
+        let name = repo_url.rsplit('/').next().unwrap_or(repo_url);
+        name.strip_suffix(".git").unwrap_or(name)
+    }
+
+    /// Identifies the repository in the store's git repositories folder
+    pub fn repo_id(&self) -> String {
+        format!(
+            "{}-{}",
+            self.repo_name(),
+            &blake3::hash(self.repo_url.as_bytes()).to_hex()[..16]
+        )
+    }
+
+    /// Folder containing the files of the revision in its archive
+    fn archive_prefix(&self) -> String {
+        format!(
+            "{}-{}",
+            self.repo_name(),
+            self.revision.replace(['/', '\\'], "-")
+        )
     }
 }
 
@@ -92,12 +340,47 @@ impl PackageSourceURL {
     pub fn parse(value: impl AsRef<str>) -> Result<PackageSourceURL> {
         Ok(PackageSourceURL(Url::parse(value.as_ref()).with_context(|| format!("Failed to parse package source url: {}", value.as_ref())).with_suggestion(|| "Please ensure your 'pkg_source' parameter contains a valid absolute URL like 'https://example.com'")?))
     }
+    /// Returns the url of a file with the given name on this mirror
+    pub fn mirror_url(&self, filename: impl AsRef<Path>) -> Result<PackageSourceURL> {
+        let mut base_url = self.0.clone();
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(format!("{}/", base_url.path()).as_str());
+        }
+        let filename = filename.as_ref().to_string_lossy();
+        Ok(PackageSourceURL(base_url.join(&filename).with_context(
+            || {
+                format!(
+                    "Failed to build url for '{}' on mirror '{}'",
+                    filename, self.0
+                )
+            },
+        )?))
+    }
+    /// Whether the source is a git repository rather than an archive
+    pub fn is_git(&self) -> bool {
+        self.0.scheme().starts_with("git+")
+    }
+    /// Returns the repository and revision of the source if it is a git source
+    pub fn git_source(&self) -> Option<GitSource> {
+        let repo_scheme = self.0.scheme().strip_prefix("git+")?;
+        let revision = self.0.fragment().filter(|revision| !revision.is_empty())?;
+        let mut repo_url = self.0.clone();
+        repo_url.set_fragment(None);
+        let repo_url = repo_url.to_string();
+        Some(GitSource {
+            repo_url: format!("{}{}", repo_scheme, &repo_url[repo_url.find(':')?..]),
+            revision: revision.to_string(),
+        })
+    }
     pub fn filename(&self) -> Result<PathBuf> {
+        if let Some(git_source) = self.git_source() {
+            return Ok(format!("{}.tar", git_source.archive_prefix()).into());
+        }
         Ok(self
             .0
             .path()
             .split('/')
-            .last()
+            .next_back()
             .ok_or_else(|| {
                 eyre!(
                     "Package source url '{}' does not seem to refer to a file",
@@ -122,6 +405,12 @@ impl TryFrom<String> for PackageSourceURL {
     }
 }
 
+impl AsRef<Url> for PackageSourceURL {
+    fn as_ref(&self) -> &Url {
+        &self.0
+    }
+}
+
 impl From<Url> for PackageSourceURL {
     fn from(value: Url) -> Self {
         PackageSourceURL(value)
@@ -134,7 +423,7 @@ impl From<PackageSourceURL> for String {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 
 pub struct PackageSha256Sum(ShaSum);
 
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
     io::Write,
     path::{Path, PathBuf},
@@ -8,9 +8,6 @@ use std::{
     time::Instant,
 };
 
-#[cfg(not(target_os = "windows"))]
-use std::io::Read;
-
 #[cfg(windows)]
 use std::fs::{self, File};
 
@@ -23,7 +20,8 @@ use color_eyre::{
     Help, SectionExt,
 };
 use diesel::SqliteConnection;
-use ignore::{ParallelVisitor, ParallelVisitorBuilder, WalkBuilder, WalkState};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::{ParallelVisitor, ParallelVisitorBuilder, Walk, WalkBuilder, WalkState};
 
 use lazy_static::lazy_static;
 
@@ -35,17 +33,23 @@ use sha2::{Digest, Sha256};
 
 use tracing::{debug, error, info, trace};
 
+#[cfg(not(target_os = "windows"))]
+use crate::check::PlanFile;
 use crate::{
-    check::PlanContextConfig,
-    store::{self, ModificationIndex},
+    check::{LicensePolicy, NewRulesPolicy, PlanContextConfig},
+    store::{self, ModificationIndex, PlanScanIndex},
 };
 
 use super::{
-    ArtifactCache, ChangeDetectionMode, Metadata, MinimalArtifactContext, PackageBuildIdent,
-    PackageBuildVersion, PackageDepIdent, PackageIdent, PackageName, PackageOrigin,
-    PackageResolvedDepIdent, PackageSource, PackageTarget, RepoContext, RepoContextID,
+    ArtifactCache, Blake3, ChangeDetectionMode, Metadata, MinimalArtifactContext,
+    PackageBuildIdent, PackageBuildVersion, PackageDepIdent, PackageIdent, PackageName,
+    PackageOrigin, PackageResolvedDepIdent, PackageSource, PackageTarget, RepoContext,
+    RepoContextID,
 };
 
+/// Paths of the plan files of a plan context, relative to the plan context, that are
+/// built for the current platform. A plan context has at most one plan per target, the
+/// target specific plans come first as they take precedence over the generic ones.
 fn get_platform_specific_paths() -> Vec<(PathBuf, PackageTarget)> {
     let mut paths = Vec::new();
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
@@ -96,14 +100,14 @@ fn get_platform_specific_paths() -> Vec<(PathBuf, PackageTarget)> {
         ));
     }
 
-    #[cfg(any(target_os = "windows", target_arch = "x86_64"))]
+    #[cfg(all(target_os = "windows", target_arch = "x86_64"))]
     {
         paths.push((
             vec!["x86_64-windows", "plan.ps1"],
             PackageTarget::parse("x86_64-windows").unwrap(),
         ));
         paths.push((
-            vec!["habitat", "x86_64-windows", "plan.sh"],
+            vec!["habitat", "x86_64-windows", "plan.ps1"],
             PackageTarget::parse("x86_64-windows").unwrap(),
         ));
     }
@@ -126,9 +130,30 @@ fn get_platform_specific_paths() -> Vec<(PathBuf, PackageTarget)> {
         .collect()
 }
 
+/// Paths generated by builds and tooling that are never considered part of a
+/// plan context, relative to the plan context or repo
+const DEFAULT_EXCLUDED_PATHS: &[&str] = &["results", "**/node_modules", "**/*.hart"];
+
 lazy_static! {
     static ref RELATIVE_PLAN_FILE_PATHS: Vec<(PathBuf, PackageTarget)> =
         get_platform_specific_paths();
+    static ref DEFAULT_EXCLUDED_PATHS_GLOBSET: GlobSet =
+        excluded_paths_globset(&[]).expect("Default excluded paths must be valid globs");
+}
+
+fn excluded_paths_globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in DEFAULT_EXCLUDED_PATHS
+        .iter()
+        .copied()
+        .chain(patterns.iter().map(String::as_str))
+    {
+        builder.add(
+            Glob::new(pattern)
+                .with_context(|| format!("Invalid excluded path pattern '{}'", pattern))?,
+        );
+    }
+    Ok(builder.build()?)
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -189,6 +214,198 @@ pub(crate) struct RawPlanData {
     pub deps: Vec<PackageDepIdent>,
     pub build_deps: Vec<PackageDepIdent>,
     pub scaffolding_dep: Option<PackageDepIdent>,
+    #[serde(default)]
+    pub exports: BTreeMap<String, String>,
+    #[serde(default)]
+    pub binds: BTreeMap<String, String>,
+    #[serde(default)]
+    pub binds_optional: BTreeMap<String, String>,
+}
+
+impl RawPlanData {
+    /// Runs the plan in a bash shell to extract its metadata
+    #[cfg(not(target_os = "windows"))]
+    fn read_from_plan(
+        plan_ctx_path: &PlanContextPath,
+        plan_target_ctx_path: &PlanTargetContextPath,
+        plan_path: &PlanFilePath,
+    ) -> Result<RawPlanData> {
+        let mut child =  Command::new("bash")
+            .arg("-s")
+            .arg("-")
+            .arg(plan_path.as_ref())
+            .arg(plan_ctx_path.as_ref())
+            .arg(plan_target_ctx_path.as_ref())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .current_dir(plan_target_ctx_path.as_ref())
+            .spawn()
+            .context("Failed to execute bash shell")
+            .with_suggestion(|| "Make sure you have bash installed on your system, and that it's location is included in your PATH")?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .expect("Failed to acquire stdin to bash process");
+        stdin.write_all(PLAN_DATA_EXTRACT_SCRIPT)?;
+        stdin.flush()?;
+        drop(stdin);
+        let output = child.wait_with_output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        if output.status.success() {
+            serde_json::from_str(&stdout)
+                .with_context(|| {
+                    format!(
+                        "Failed to read extracted JSON data from plan file at '{}'",
+                        plan_path.as_ref().display()
+                    )
+                })
+                .with_section(move || stdout.header("stdout: "))
+                .with_section(move || stderr.header("stderr: "))
+                .with_suggestion(|| "Ensure your plan file does not generate output outside the standard functions like 'do_begin', 'do_prepare', 'do_build', 'do_check' and 'do_install'")
+        } else {
+            Err(eyre!(
+                "Failed to extract plan data from {}, bash process exited with code: {}",
+                plan_path.as_ref().display(),
+                output.status,
+            )
+            .with_section(move || stdout.header("stdout: "))
+            .with_section(move || stderr.header("stderr: ")))
+        }
+    }
+}
+
+/// Hashes the extraction script along with the relative paths and contents of the
+/// shell scripts in a plan context and the files sourced by the plan. Returns `None`
+/// when the files read by the plan can't be determined, so that it is rescanned.
+#[cfg(not(target_os = "windows"))]
+fn plan_scan_hash(
+    plan_ctx_path: &PlanContextPath,
+    plan_target_ctx_path: &PlanTargetContextPath,
+    plan_path: &PlanFilePath,
+) -> Result<Option<String>> {
+    let Some(sourced_paths) = plan_sourced_paths(plan_ctx_path, plan_target_ctx_path, plan_path)
+    else {
+        trace!(
+            "Plan {} sources files that can't be determined, skipping scan cache",
+            plan_path.as_ref().display()
+        );
+        return Ok(None);
+    };
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(PLAN_DATA_EXTRACT_SCRIPT);
+    for entry in WalkBuilder::new(plan_ctx_path.as_ref())
+        .standard_filters(false)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build()
+    {
+        let entry = entry?;
+        if entry.path().extension().and_then(|ext| ext.to_str()) != Some("sh")
+            || !entry.path().is_file()
+        {
+            continue;
+        }
+        hasher.update(
+            entry
+                .path()
+                .strip_prefix(plan_ctx_path.as_ref())?
+                .to_string_lossy()
+                .as_bytes(),
+        );
+        hasher.update(&[0]);
+        hasher.update(&std::fs::read(entry.path())?);
+        hasher.update(&[0]);
+    }
+    for path in sourced_paths {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update(&[0]);
+        // Missing files are hashed too, the plan may source them once they exist
+        match std::fs::read(&path) {
+            Ok(data) => {
+                hasher.update(&[1]);
+                hasher.update(&data);
+            }
+            Err(_) => {
+                hasher.update(&[0]);
+            }
+        }
+        hasher.update(&[0]);
+    }
+    Ok(Some(hasher.finalize().to_hex().to_string()))
+}
+
+/// Finds the paths of the files sourced by a plan, following the files they source
+/// in turn. Paths may only refer to the plan locations set by the extraction script,
+/// returns `None` if a path that is sourced while scanning the plan depends on
+/// anything else or if one of the files can't be parsed.
+#[cfg(not(target_os = "windows"))]
+fn plan_sourced_paths(
+    plan_ctx_path: &PlanContextPath,
+    plan_target_ctx_path: &PlanTargetContextPath,
+    plan_path: &PlanFilePath,
+) -> Option<Vec<PathBuf>> {
+    let plan_target_ctx_dir = plan_target_ctx_path.as_ref().to_string_lossy();
+    let plan_ctx_dir = plan_ctx_path.as_ref().to_string_lossy();
+    let variables = [
+        ("PLAN_CONTEXT", plan_target_ctx_dir.as_ref()),
+        ("SRC_PATH", plan_ctx_dir.as_ref()),
+    ];
+    let mut paths = vec![plan_path.as_ref().to_path_buf()];
+    let mut visited = paths.iter().cloned().collect::<HashSet<_>>();
+    // Functions called by each function, top level calls are made by `None`
+    let mut calls: Vec<(Option<String>, String)> = Vec::new();
+    // Functions that source a path which can't be determined
+    let mut unresolved: Vec<Option<String>> = Vec::new();
+    let mut index = 0;
+    while let Some(path) = paths.get(index).cloned() {
+        index += 1;
+        let Ok(source) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let plan_file = PlanFile::parse(&source).ok()?;
+        for command in plan_file.commands {
+            let Some(name) = command.name() else {
+                continue;
+            };
+            if name == "source" || name == "." {
+                // Relative paths are resolved from the working directory of the scan
+                match command
+                    .words
+                    .get(1)
+                    .and_then(|word| word.expand(&variables))
+                {
+                    Some(sourced_path) => {
+                        let sourced_path = plan_target_ctx_path.as_ref().join(sourced_path);
+                        if visited.insert(sourced_path.clone()) {
+                            paths.push(sourced_path);
+                        }
+                    }
+                    None => unresolved.push(command.function.clone()),
+                }
+            }
+            calls.push((command.function, name));
+        }
+    }
+    // Functions defined by the plan only run during the scan if they are called
+    // from the top level, directly or through other functions
+    let mut reachable = HashSet::new();
+    let mut pending = vec![None];
+    while let Some(caller) = pending.pop() {
+        for (_, callee) in calls.iter().filter(|(function, _)| *function == caller) {
+            if reachable.insert(callee.clone()) {
+                pending.push(Some(callee.clone()));
+            }
+        }
+    }
+    if unresolved.iter().any(|function| match function {
+        Some(function) => reachable.contains(function),
+        None => true,
+    }) {
+        return None;
+    }
+    Some(paths.split_off(1))
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize, PartialOrd, Ord)]
@@ -235,17 +452,141 @@ pub(crate) struct PlanContext {
     pub files_changed_on_git: Vec<PlanContextFileChangeOnGit>,
     pub is_native: bool,
     pub plan_config: Option<PlanContextConfig>,
+    #[serde(default)]
+    pub new_rules_policy: Option<NewRulesPolicy>,
+    /// Change ignore patterns of the plan's repo, the plan's own patterns are added to these
+    #[serde(default)]
+    pub repo_change_ignore: Vec<String>,
+    /// License policy of the plan's repo
+    #[serde(default)]
+    pub license_policy: Option<LicensePolicy>,
+    /// Configuration values exported by the service, by export name, from `pkg_exports`
+    #[serde(default)]
+    pub exports: BTreeMap<String, String>,
+    /// Services the service must be bound to, with the exports it needs from each,
+    /// from `pkg_binds`
+    #[serde(default)]
+    pub binds: BTreeMap<String, Vec<String>>,
+    /// Services the service can optionally be bound to, with the exports it needs
+    /// from each, from `pkg_binds_optional`
+    #[serde(default)]
+    pub binds_optional: BTreeMap<String, Vec<String>>,
+}
+
+/// Splits the space separated exports needed by each bind of a plan
+fn bind_exports(binds: BTreeMap<String, String>) -> BTreeMap<String, Vec<String>> {
+    binds
+        .into_iter()
+        .map(|(bind, exports)| (bind, exports.split_whitespace().map(String::from).collect()))
+        .collect()
+}
+
+/// Drops the removed dependencies and appends the extra dependencies declared
+/// for the target in the plan config
+fn deps_with_changes(
+    deps: impl IntoIterator<Item = PackageDepIdent>,
+    extra_deps: &[PackageDepIdent],
+    removed_deps: &[PackageDepIdent],
+) -> Vec<PackageDepIdent> {
+    let mut deps = deps
+        .into_iter()
+        .filter(|dep| !removed_deps.iter().any(|removed| removed.includes(dep)))
+        .collect::<Vec<_>>();
+    for extra_dep in extra_deps {
+        if !deps.contains(extra_dep) {
+            deps.push(extra_dep.clone());
+        }
+    }
+    deps
+}
+
+/// Reads the plan config next to a plan file, a plan config that is not valid is
+/// reported and ignored
+fn plan_config_read(
+    repo_ctx: &RepoContext,
+    plan_path: &PlanFilePath,
+    target: PackageTarget,
+) -> Result<Option<PlanContextConfig>> {
+    let plan_config_path = plan_path.plan_config_path();
+    if !plan_config_path.is_file() {
+        return Ok(None);
+    }
+    let data = std::fs::read_to_string(plan_config_path.as_path())?;
+    match PlanContextConfig::from_str(data.as_str(), target)
+        .with_section(move || data.header(format!("{}:", "File Contents".bright_cyan())))
+        .with_suggestion(|| "Ensure your .hab-plan-config.toml file contains valid rules")
+    {
+        Ok(plan_rules) => Ok(Some(plan_rules)),
+        Err(err) => {
+            info!(target: "user-ui", "{} Failed to read plan config from {}: {:?}", "error:".bold().red(), plan_config_path.strip_prefix(repo_ctx.path.as_ref()).unwrap().display(), err);
+            Ok(None)
+        }
+    }
+}
+
+/// Runtime and build dependencies of a plan for the target, with the dependency
+/// changes of its plan config applied
+fn plan_deps(
+    deps: Vec<PackageDepIdent>,
+    build_deps: impl IntoIterator<Item = PackageDepIdent>,
+    plan_config: Option<&PlanContextConfig>,
+    target: PackageTarget,
+) -> (Vec<PackageResolvedDepIdent>, Vec<PackageResolvedDepIdent>) {
+    let deps = deps_with_changes(
+        deps,
+        plan_config.map_or(&[], |config| &config.extra_deps),
+        plan_config.map_or(&[], |config| &config.removed_deps),
+    );
+    let build_deps = deps_with_changes(
+        build_deps,
+        plan_config.map_or(&[], |config| &config.extra_build_deps),
+        plan_config.map_or(&[], |config| &config.removed_build_deps),
+    );
+    (
+        deps.iter()
+            .map(|dep| dep.to_resolved_dep_ident(target))
+            .collect(),
+        build_deps
+            .iter()
+            .map(|dep| dep.to_resolved_dep_ident(target))
+            .collect(),
+    )
 }
 
 impl PlanContext {
     pub fn config(&self) -> PlanContextConfig {
-        let context_rules = PlanContextConfig::default();
+        let mut context_rules = match self.new_rules_policy.as_ref() {
+            Some(policy) => PlanContextConfig::default().with_new_rules_policy(policy),
+            None => PlanContextConfig::default(),
+        };
+        context_rules.change_ignore = self.repo_change_ignore.clone();
+        context_rules.license_policy = self.license_policy.clone();
         if let Some(rules) = self.plan_config.as_ref() {
             context_rules.merge(rules)
         } else {
             context_rules
         }
     }
+
+    /// Walks over the files of the plan context in order, skipping the default
+    /// and configured excluded paths.
+    fn files_walker(&self) -> Result<Walk> {
+        let excluded_paths = excluded_paths_globset(&self.config().exclude)?;
+        let context_path = self.context_path.as_ref().to_path_buf();
+        Ok(WalkBuilder::new(self.context_path.as_ref())
+            .standard_filters(false)
+            .sort_by_file_path(|a, b| a.cmp(b))
+            .filter_entry(move |entry| {
+                entry
+                    .path()
+                    .strip_prefix(&context_path)
+                    .map_or(true, |relative_path| {
+                        relative_path.as_os_str().is_empty()
+                            || !excluded_paths.is_match(relative_path)
+                    })
+            })
+            .build())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -268,15 +609,33 @@ pub(crate) struct PlanContextFileChangeOnGit {
 }
 
 pub(crate) enum PlanContextPathGitSyncStatus {
-    Synced(PathBuf, DateTime<Utc>, DateTime<Utc>),
+    /// The modification time of the file was synced from its time on disk to the
+    /// timestamp of the last commit that changed it
+    Synced(PathBuf, DateTime<Utc>, DateTime<Utc>, String),
     LocallyModified(PathBuf, DateTime<Utc>),
+    /// The modification time of the file was restored from the time it was synced
+    /// to, to the time it had before the sync
+    Reverted(PathBuf, DateTime<Utc>, DateTime<Utc>),
+    /// The file was modified after it was synced, so its modification time is left as is
+    ModifiedSinceSync(PathBuf, DateTime<Utc>),
+}
+
+impl PlanContextPathGitSyncStatus {
+    pub fn path(&self) -> &Path {
+        match self {
+            PlanContextPathGitSyncStatus::Synced(path, ..)
+            | PlanContextPathGitSyncStatus::LocallyModified(path, ..)
+            | PlanContextPathGitSyncStatus::Reverted(path, ..)
+            | PlanContextPathGitSyncStatus::ModifiedSinceSync(path, ..) => path,
+        }
+    }
 }
 
 impl PlanContext {
     #[allow(clippy::too_many_arguments)]
     #[cfg(not(target_os = "windows"))]
     pub fn read_from_disk(
-        connection: Option<&mut SqliteConnection>,
+        connection: Option<(&mut SqliteConnection, &str)>,
         modification_index: Option<&ModificationIndex>,
         repo_ctx: &RepoContext,
         artifact_cache: &ArtifactCache,
@@ -284,126 +643,98 @@ impl PlanContext {
         plan_target_ctx_path: &PlanTargetContextPath,
         plan_path: &PlanFilePath,
         target: PackageTarget,
+        plan_scan_index: Option<&PlanScanIndex>,
         change_detection_mode: ChangeDetectionMode,
     ) -> Result<PlanContext> {
         let start = Instant::now();
-        let mut child =  Command::new("bash")
-            .arg("-s")
-            .arg("-")
-            .arg(plan_path.as_ref())
-            .arg(plan_ctx_path.as_ref())
-            .arg(plan_target_ctx_path.as_ref())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .current_dir(plan_target_ctx_path.as_ref())
-            .spawn()
-            .context("Failed to execute bash shell")
-            .with_suggestion(|| "Make sure you have bash installed on your system, and that it's location is included in your PATH")?;
-        let mut stdin = child
-            .stdin
-            .take()
-            .expect("Failed to acquire stdin to bash process");
-        stdin.write_all(PLAN_DATA_EXTRACT_SCRIPT)?;
-        stdin.flush()?;
-        drop(stdin);
-        let output = child.wait_with_output()?;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-        if output.status.success() {
-            let raw_data: RawPlanData = serde_json::from_str(&stdout)
-                .with_context(|| {
-                    format!(
-                        "Failed to read extracted JSON data from plan file at '{}'",
-                        plan_path.as_ref().display()
-                    )
-                })
-                .with_section(move || stdout.header("stdout: "))
-                .with_section(move || stderr.header("stderr: "))
-                .with_suggestion(|| "Ensure your plan file does not generate output outside the standard functions like 'do_begin', 'do_prepare', 'do_build', 'do_check' and 'do_install'")?;
-            let id = PlanContextID(PackageBuildIdent {
-                origin: raw_data.origin,
-                name: raw_data.name,
-                version: raw_data.version,
-                target: target.to_owned(),
-            });
-            let plan_config_path = plan_path.plan_config_path();
-            let plan_config = if let Ok(mut file) = std::fs::File::open(plan_config_path.as_path())
-            {
-                let mut data = String::new();
-                file.read_to_string(&mut data)?;
-                match PlanContextConfig::from_str(data.as_str(), target)
-                    .with_section(move || {
-                        data.header(format!("{}:", "File Contents".bright_cyan()))
-                    })
-                    .with_suggestion(|| {
-                        "Ensure your .hab-plan-config.toml file contains valid rules"
-                    }) {
-                    Ok(plan_rules) => Some(plan_rules),
-                    Err(err) => {
-                        info!(target: "user-ui", "{} Failed to read plan config from {}: {:?}", "error:".bold().red(), plan_config_path.strip_prefix(repo_ctx.path.as_ref()).unwrap().display(), err);
-                        None
-                    }
+        let plan_scan_hash = match plan_scan_index {
+            Some(_) => plan_scan_hash(plan_ctx_path, plan_target_ctx_path, plan_path)?,
+            None => None,
+        };
+        let cached_raw_data = plan_scan_index
+            .zip(plan_scan_hash.as_ref())
+            .and_then(|(plan_scan_index, hash)| plan_scan_index.plan_scan_get(plan_path, hash))
+            .and_then(|data| serde_json::from_str::<RawPlanData>(data).ok());
+        let raw_data = match cached_raw_data {
+            Some(raw_data) => {
+                trace!(
+                    "Plan data of {} loaded from scan cache",
+                    plan_path.as_ref().display()
+                );
+                raw_data
+            }
+            None => {
+                let raw_data =
+                    RawPlanData::read_from_plan(plan_ctx_path, plan_target_ctx_path, plan_path)?;
+                if let (Some(plan_scan_index), Some(hash)) = (plan_scan_index, plan_scan_hash) {
+                    plan_scan_index.plan_scan_put(
+                        plan_path,
+                        hash,
+                        serde_json::to_string(&raw_data)?,
+                    );
                 }
-            } else {
-                None
-            };
+                raw_data
+            }
+        };
+        let id = PlanContextID(PackageBuildIdent {
+            origin: raw_data.origin,
+            name: raw_data.name,
+            version: raw_data.version,
+            target: target.to_owned(),
+        });
+        let plan_config = plan_config_read(repo_ctx, plan_path, target)?;
+        let (deps, build_deps) = plan_deps(
+            raw_data.deps,
+            raw_data
+                .build_deps
+                .into_iter()
+                .chain(raw_data.scaffolding_dep),
+            plan_config.as_ref(),
+            target,
+        );
 
-            let mut plan_ctx = PlanContext {
-                id,
-                repo_id: repo_ctx.id.clone(),
-                is_native: repo_ctx.is_native_plan(plan_ctx_path),
-                context_path: plan_ctx_path.clone(),
-                target_context_last_modified_at: plan_target_ctx_path.last_modifed_at()?,
-                target_context_path: plan_target_ctx_path.clone(),
-                plan_path: plan_path.clone(),
-                source: raw_data.source,
-                licenses: raw_data.licenses,
-                deps: raw_data
-                    .deps
-                    .into_iter()
-                    .map(|d| d.to_resolved_dep_ident(target.to_owned()))
-                    .collect(),
-                build_deps: raw_data
-                    .build_deps
-                    .into_iter()
-                    .chain(raw_data.scaffolding_dep)
-                    .map(|d| d.to_resolved_dep_ident(target.to_owned()))
-                    .collect(),
-                latest_artifact: None,
-                files_changed_on_disk: Vec::new(),
-                files_changed_on_git: Vec::new(),
-                plan_config,
-            };
-            let latest_artifact = artifact_cache.latest_plan_minimal_artifact(&plan_ctx.id);
-            plan_ctx.determine_changes(
-                connection,
-                modification_index,
-                latest_artifact.as_ref(),
-                change_detection_mode,
-            )?;
-            trace!(
-                "Read plan context {} from disk in {}s",
-                plan_ctx.context_path.as_ref().display(),
-                start.elapsed().as_secs_f32()
-            );
-            Ok(plan_ctx)
-        } else {
-            Err(eyre!(
-                "Failed to extract plan data from {}, bash process exited with code: {}",
-                plan_path.as_ref().display(),
-                output.status,
-            )
-            .with_section(move || stdout.header("stdout: "))
-            .with_section(move || stderr.header("stderr: ")))
-        }
+        let mut plan_ctx = PlanContext {
+            id,
+            repo_id: repo_ctx.id.clone(),
+            is_native: repo_ctx.is_native_plan(plan_ctx_path),
+            context_path: plan_ctx_path.clone(),
+            target_context_last_modified_at: plan_target_ctx_path.last_modifed_at()?,
+            target_context_path: plan_target_ctx_path.clone(),
+            plan_path: plan_path.clone(),
+            source: raw_data.source,
+            licenses: raw_data.licenses,
+            deps,
+            build_deps,
+            latest_artifact: None,
+            files_changed_on_disk: Vec::new(),
+            files_changed_on_git: Vec::new(),
+            plan_config,
+            new_rules_policy: repo_ctx.new_rules_policy,
+            repo_change_ignore: repo_ctx.change_ignore.clone(),
+            license_policy: repo_ctx.license_policy.clone(),
+            exports: raw_data.exports,
+            binds: bind_exports(raw_data.binds),
+            binds_optional: bind_exports(raw_data.binds_optional),
+        };
+        let latest_artifact = artifact_cache.latest_plan_minimal_artifact(&plan_ctx.id);
+        plan_ctx.determine_changes(
+            connection,
+            modification_index,
+            latest_artifact.as_ref(),
+            change_detection_mode,
+        )?;
+        trace!(
+            "Read plan context {} from disk in {}s",
+            plan_ctx.context_path.as_ref().display(),
+            start.elapsed().as_secs_f32()
+        );
+        Ok(plan_ctx)
     }
 
     #[allow(clippy::too_many_arguments)]
     #[cfg(target_os = "windows")]
     pub fn read_from_disk(
-        connection: Option<&mut SqliteConnection>,
+        connection: Option<(&mut SqliteConnection, &str)>,
         modification_index: Option<&ModificationIndex>,
         repo_ctx: &RepoContext,
         artifact_cache: &ArtifactCache,
@@ -411,6 +742,7 @@ impl PlanContext {
         plan_target_ctx_path: &PlanTargetContextPath,
         plan_path: &PlanFilePath,
         target: PackageTarget,
+        _plan_scan_index: Option<&PlanScanIndex>,
         change_detection_mode: ChangeDetectionMode,
     ) -> Result<PlanContext> {
         let start = Instant::now();
@@ -481,7 +813,16 @@ impl PlanContext {
                 target: target.to_owned(),
             });
             // For Windows, suppress it for now until we establish some validation rules.
-            // let plan_config_path = plan_path.plan_config_path();
+            // Only the dependency changes of the plan config are applied.
+            let (deps, build_deps) = plan_deps(
+                raw_data.deps,
+                raw_data
+                    .build_deps
+                    .into_iter()
+                    .chain(raw_data.scaffolding_dep),
+                plan_config_read(repo_ctx, plan_path, target)?.as_ref(),
+                target,
+            );
             let plan_config = None;
 
             let mut plan_ctx = PlanContext {
@@ -494,21 +835,18 @@ impl PlanContext {
                 plan_path: plan_path.clone(),
                 source: raw_data.source,
                 licenses: raw_data.licenses,
-                deps: raw_data
-                    .deps
-                    .into_iter()
-                    .map(|d| d.to_resolved_dep_ident(target.to_owned()))
-                    .collect(),
-                build_deps: raw_data
-                    .build_deps
-                    .into_iter()
-                    .chain(raw_data.scaffolding_dep)
-                    .map(|d| d.to_resolved_dep_ident(target.to_owned()))
-                    .collect(),
+                deps,
+                build_deps,
                 latest_artifact: None,
                 files_changed_on_disk: Vec::new(),
                 files_changed_on_git: Vec::new(),
                 plan_config,
+                new_rules_policy: repo_ctx.new_rules_policy,
+                repo_change_ignore: repo_ctx.change_ignore.clone(),
+                license_policy: repo_ctx.license_policy.clone(),
+                exports: raw_data.exports,
+                binds: bind_exports(raw_data.binds),
+                binds_optional: bind_exports(raw_data.binds_optional),
             };
             let latest_artifact = artifact_cache.latest_plan_minimal_artifact(&plan_ctx.id);
             plan_ctx.determine_changes(
@@ -536,56 +874,22 @@ impl PlanContext {
 
     pub fn determine_changes(
         &mut self,
-        mut connection: Option<&mut SqliteConnection>,
+        mut connection: Option<(&mut SqliteConnection, &str)>,
         modification_index: Option<&ModificationIndex>,
         artifact_ctx: Option<&MinimalArtifactContext>,
         change_detection_mode: ChangeDetectionMode,
     ) -> Result<()> {
-        let plan_ctx_walker = WalkBuilder::new(self.context_path.as_ref())
-            .standard_filters(false)
-            .sort_by_file_path(|a, b| a.cmp(b))
-            .build();
+        let plan_ctx_walker = self.files_walker()?;
+        let change_ignored_paths = self.change_ignored_paths()?;
         self.files_changed_on_disk = Vec::new();
         self.latest_artifact = artifact_ctx.map(|artifact_ctx| PlanContextLatestArtifact {
             created_at: artifact_ctx.created_at,
             ident: artifact_ctx.id.clone(),
         });
-        // Is the plan a top level plan in the same folder as the plan context?
-        let is_in_top_level_dir = self.target_context_path.as_ref() == self.context_path.as_ref();
-
         for entry in plan_ctx_walker {
             match entry {
                 Ok(entry) => {
-                    // Is this inside the plan's target folder
-                    let is_in_target_dir = entry
-                        .path()
-                        .strip_prefix(self.target_context_path.as_ref())
-                        .is_ok();
-                    // Is this inside a habitat or platform folder ?
-                    let is_in_habitat_dir = entry
-                        .path()
-                        .strip_prefix(self.context_path.as_ref())
-                        .ok()
-                        .and_then(|p| p.components().next())
-                        .and_then(|p| p.as_os_str().to_str())
-                        .map_or(false, |p| p == "habitat" || PackageTarget::parse(p).is_ok());
-                    let is_plan_config = if let Some(file_name) = entry.path().file_name() {
-                        file_name == PLAN_CONFIG_FILE
-                    } else {
-                        false
-                    };
-                    if !is_in_top_level_dir && is_in_habitat_dir && !is_in_target_dir {
-                        continue;
-                    }
-                    if is_in_target_dir && is_plan_config {
-                        continue;
-                    }
-
-                    // Why do we care about the directory timestamp? If the build system writes temporary files
-                    // in that directory, its timestamp is modified, causing it to be rebuilt even when nothing
-                    // has changed according to the plan. For now, this is handled only for Windows, but we could explore
-                    // a better way to track plan-related files instead of scanning the entire directory for changes.
-                    if cfg!(target_os = "windows") && entry.path().is_dir() {
+                    if !self.is_tracked_path(entry.path(), &change_ignored_paths) {
                         continue;
                     }
 
@@ -595,9 +899,10 @@ impl PlanContext {
                                 self.target_context_last_modified_at = real_last_modified_at;
                             }
                             let alternate_modified_at =
-                                if let Some(connection) = connection.as_mut() {
+                                if let Some((connection, workspace)) = connection.as_mut() {
                                     store::file_alternate_modified_at_get(
                                         connection,
+                                        workspace,
                                         &self.context_path,
                                         entry.path(),
                                         real_last_modified_at,
@@ -680,18 +985,163 @@ impl PlanContext {
                 }
             }
         }
+        if change_detection_mode == ChangeDetectionMode::Hash
+            && !self.files_changed_on_disk.is_empty()
+        {
+            let recorded_hash = if let Some(artifact_ctx) = artifact_ctx {
+                if let Some((connection, _)) = connection.as_mut() {
+                    store::plan_context_hash_get(connection, &artifact_ctx.id)?
+                } else if let Some(modification_index) = modification_index {
+                    modification_index.plan_context_hash_get(&artifact_ctx.id)
+                } else {
+                    panic!("No modification source provided")
+                }
+            } else {
+                None
+            };
+            // Files that were modified without changing the plan context are ignored, unless
+            // they were explicitly marked as changed with a recorded timestamp
+            if recorded_hash.is_some_and(|recorded_hash| {
+                self.context_hash()
+                    .is_ok_and(|context_hash| context_hash == recorded_hash)
+            }) {
+                self.files_changed_on_disk.retain(|file_change| {
+                    file_change.last_modified_at != file_change.real_last_modified_at
+                });
+            }
+        }
         Ok(())
     }
 
+    /// Compares the plan file with the plan source embedded in an artifact, ignoring
+    /// differences in line endings, trailing whitespace and surrounding blank lines.
+    pub fn plan_source_matches(&self, plan_source: &str) -> Result<bool> {
+        let current_plan_source =
+            std::fs::read_to_string(self.plan_path.as_ref()).with_context(|| {
+                format!(
+                    "Failed to read plan file '{}'",
+                    self.plan_path.as_ref().display()
+                )
+            })?;
+        let normalize = |source: &str| {
+            source
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim_matches('\n')
+                .to_string()
+        };
+        Ok(normalize(&current_plan_source) == normalize(plan_source))
+    }
+
+    /// Hashes the path and content of every tracked file in the plan context, artifacts
+    /// built from plan contexts with the same hash were built from the same files.
+    pub fn context_hash(&self) -> Result<Blake3> {
+        let mut hasher = blake3::Hasher::new();
+        let change_ignored_paths = self.change_ignored_paths()?;
+        for entry in self.files_walker()? {
+            let entry = entry?;
+            if !entry.path().is_file() || !self.is_tracked_path(entry.path(), &change_ignored_paths)
+            {
+                continue;
+            }
+            let relative_path = entry.path().strip_prefix(self.context_path.as_ref())?;
+            hasher.update(relative_path.to_string_lossy().as_bytes());
+            hasher.update(&[0]);
+            hasher.update(Blake3::from_path(entry.path())?.as_ref().as_bytes());
+        }
+        Ok(Blake3::from(hasher.finalize().to_string()))
+    }
+
+    /// Builds the glob set of the change ignore patterns of the plan and its repo,
+    /// relative to the plan context
+    fn change_ignored_paths(&self) -> Result<GlobSet> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in self.config().change_ignore.iter() {
+            builder.add(
+                Glob::new(pattern)
+                    .with_context(|| format!("Invalid change ignore pattern '{}'", pattern))?,
+            );
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Determines whether changes to a path in the plan context affect the plan
+    fn is_tracked_path(&self, path: &Path, change_ignored_paths: &GlobSet) -> bool {
+        // Does the path or one of the folders containing it match a change ignore pattern?
+        let is_change_ignored =
+            path.strip_prefix(self.context_path.as_ref())
+                .is_ok_and(|relative_path| {
+                    relative_path
+                        .ancestors()
+                        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                        .any(|ancestor| change_ignored_paths.is_match(ancestor))
+                });
+        if is_change_ignored {
+            return false;
+        }
+        // Is the plan a top level plan in the same folder as the plan context?
+        let is_in_top_level_dir = self.target_context_path.as_ref() == self.context_path.as_ref();
+        // Is this inside the plan's target folder
+        let is_in_target_dir = path.strip_prefix(self.target_context_path.as_ref()).is_ok();
+        // Is this inside the folder of a plan for another target?
+        let is_in_other_target_dir = path
+            .strip_prefix(self.context_path.as_ref())
+            .ok()
+            .and_then(|relative_path| {
+                let mut target_dir = self.context_path.as_ref().to_path_buf();
+                for component in relative_path.components().take(2) {
+                    let component = component.as_os_str().to_str()?;
+                    target_dir.push(component);
+                    if PackageTarget::parse(component).is_ok() {
+                        return Some(target_dir);
+                    }
+                    if component != "habitat" {
+                        return None;
+                    }
+                }
+                None
+            })
+            .is_some_and(|target_dir| !self.target_context_path.as_ref().starts_with(target_dir));
+        if is_in_other_target_dir {
+            return false;
+        }
+        // Is this inside a habitat or platform folder ?
+        let is_in_habitat_dir = path
+            .strip_prefix(self.context_path.as_ref())
+            .ok()
+            .and_then(|p| p.components().next())
+            .and_then(|p| p.as_os_str().to_str())
+            .is_some_and(|p| p == "habitat" || PackageTarget::parse(p).is_ok());
+        let is_plan_config = if let Some(file_name) = path.file_name() {
+            file_name == PLAN_CONFIG_FILE
+        } else {
+            false
+        };
+        if !is_in_top_level_dir && is_in_habitat_dir && !is_in_target_dir {
+            return false;
+        }
+        if is_in_target_dir && is_plan_config {
+            return false;
+        }
+
+        // Why do we care about the directory timestamp? If the build system writes temporary files
+        // in that directory, its timestamp is modified, causing it to be rebuilt even when nothing
+        // has changed according to the plan. For now, this is handled only for Windows, but we could explore
+        // a better way to track plan-related files instead of scanning the entire directory for changes.
+        if cfg!(target_os = "windows") && path.is_dir() {
+            return false;
+        }
+        true
+    }
+
     pub fn sync_changes_with_git(
         &mut self,
         is_dry_run: bool,
     ) -> Result<Vec<PlanContextPathGitSyncStatus>> {
         let mut results = Vec::new();
-        let plan_ctx_walker = WalkBuilder::new(self.context_path.as_ref())
-            .standard_filters(false)
-            .sort_by_file_path(|a, b| a.cmp(b))
-            .build();
+        let plan_ctx_walker = self.files_walker()?;
         for entry in plan_ctx_walker {
             match entry {
                 Ok(entry) => {
@@ -711,11 +1161,11 @@ impl PlanContext {
                         !exit_status.success()
                     };
                     if !is_locally_modified {
-                        let git_modified_at: Option<DateTime<Utc>> = {
+                        let git_last_commit: Option<(String, DateTime<Utc>)> = {
                             let child = std::process::Command::new("git")
                                 .arg("log")
                                 .arg("-1")
-                                .arg("--pretty=%ci")
+                                .arg("--pretty=%h %ci")
                                 .arg(entry.path())
                                 .stdin(Stdio::null())
                                 .stdout(Stdio::piped())
@@ -724,13 +1174,24 @@ impl PlanContext {
                                 .spawn()?;
                             let output = child.wait_with_output()?;
                             let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                            DateTime::parse_from_str(stdout.trim(), "%Y-%m-%d %H:%M:%S %z")
-                                .ok()
-                                .map(|value| {
-                                    DateTime::from_naive_utc_and_offset(value.naive_utc(), Utc)
+                            stdout
+                                .trim()
+                                .split_once(' ')
+                                .and_then(|(commit, timestamp)| {
+                                    DateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S %z")
+                                        .ok()
+                                        .map(|value| {
+                                            (
+                                                commit.to_string(),
+                                                DateTime::from_naive_utc_and_offset(
+                                                    value.naive_utc(),
+                                                    Utc,
+                                                ),
+                                            )
+                                        })
                                 })
                         };
-                        if let Some(git_modified_at) = git_modified_at {
+                        if let Some((commit, git_modified_at)) = git_last_commit {
                             if git_modified_at != disk_modified_at {
                                 if !is_dry_run {
                                     entry.path().set_last_modifed_at(git_modified_at)?;
@@ -740,6 +1201,7 @@ impl PlanContext {
                                         .join(entry.path().strip_prefix(&self.context_path)?),
                                     disk_modified_at,
                                     git_modified_at,
+                                    commit,
                                 ));
                             }
                         }
@@ -762,6 +1224,7 @@ impl PlanContext {
 pub(crate) struct PlanScanner<'a> {
     repos: &'a HashMap<RepoContextID, RepoContext>,
     modification_index: &'a ModificationIndex,
+    plan_scan_index: &'a PlanScanIndex,
     artifact_cache: &'a ArtifactCache,
     change_detection_mode: ChangeDetectionMode,
     sender: Sender<PlanContext>,
@@ -777,12 +1240,31 @@ impl<'a> ParallelVisitor for PlanScanner<'a> {
             if !base_dir.is_dir() {
                 return WalkState::Continue;
             }
+            // Do not look for plans in generated folders
+            if self.repos.values().any(|repo_ctx| {
+                base_dir
+                    .strip_prefix(repo_ctx.path.as_ref())
+                    .is_ok_and(|relative_path| {
+                        !relative_path.as_os_str().is_empty()
+                            && DEFAULT_EXCLUDED_PATHS_GLOBSET.is_match(relative_path)
+                    })
+            }) {
+                return WalkState::Skip;
+            }
             let mut is_plan_ctx = false;
+            let mut plan_targets = HashSet::new();
             for (plan_rel_path, plan_target) in RELATIVE_PLAN_FILE_PATHS.iter() {
-                // println!("Plan rel path {:?} and target {:?}", plan_rel_path, plan_target);
                 let plan_path = base_dir.join(plan_rel_path);
                 if plan_path.is_file() {
                     is_plan_ctx = true;
+                    if !plan_targets.insert(*plan_target) {
+                        debug!(
+                            "Skipping plan at {}, the plan context already has a plan for {}",
+                            plan_path.display(),
+                            plan_target
+                        );
+                        continue;
+                    }
                     let (_, repo_ctx) = self
                         .repos
                         .iter()
@@ -813,6 +1295,7 @@ impl<'a> ParallelVisitor for PlanScanner<'a> {
                         &plan_target_ctx_path,
                         &plan_path,
                         plan_target.to_owned(),
+                        Some(self.plan_scan_index),
                         self.change_detection_mode,
                     ) {
                         Ok(plan_ctx) => {
@@ -840,6 +1323,7 @@ impl<'a> ParallelVisitor for PlanScanner<'a> {
 pub(crate) struct PlanScannerBuilder<'a> {
     repos: &'a HashMap<RepoContextID, RepoContext>,
     modification_index: &'a ModificationIndex,
+    plan_scan_index: &'a PlanScanIndex,
     artifact_cache: &'a ArtifactCache,
     change_detection_mode: ChangeDetectionMode,
     sender: Sender<PlanContext>,
@@ -853,6 +1337,7 @@ where
         Box::new(PlanScanner {
             repos: self.repos,
             modification_index: self.modification_index,
+            plan_scan_index: self.plan_scan_index,
             artifact_cache: self.artifact_cache,
             change_detection_mode: self.change_detection_mode,
             sender: self.sender.clone(),
@@ -864,6 +1349,7 @@ impl<'a> PlanScannerBuilder<'a> {
     pub fn new(
         repos: &'a HashMap<RepoContextID, RepoContext>,
         modification_index: &'a ModificationIndex,
+        plan_scan_index: &'a PlanScanIndex,
         artifact_cache: &'a ArtifactCache,
         change_detection_mode: ChangeDetectionMode,
         sender: Sender<PlanContext>,
@@ -871,9 +1357,39 @@ impl<'a> PlanScannerBuilder<'a> {
         PlanScannerBuilder {
             repos,
             modification_index,
+            plan_scan_index,
             artifact_cache,
             change_detection_mode,
             sender,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deps(idents: &[&str]) -> Vec<PackageDepIdent> {
+        idents
+            .iter()
+            .map(|ident| PackageDepIdent::parse(ident).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn deps_with_changes_applied() {
+        assert_eq!(
+            deps_with_changes(
+                deps(&["core/glibc", "core/zlib/1.3", "core/xz/5.4.2", "core/bzip2"]),
+                &deps(&["core/libatomic", "core/bzip2"]),
+                &deps(&["core/zlib", "core/xz/5.2.0"]),
+            ),
+            deps(&[
+                "core/glibc",
+                "core/xz/5.4.2",
+                "core/bzip2",
+                "core/libatomic"
+            ])
+        );
+    }
+}
@@ -1,20 +1,146 @@
-use color_eyre::eyre::{eyre, Context, Result};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::Display,
     path::{Path, PathBuf},
+    sync::Arc,
 };
+use subprocess::Exec;
+use tracing::debug;
 
-use super::{AutoBuildContextPath, GlobSetExpression, PlanContextPath};
+use crate::{
+    check::{LicensePolicy, NewRulesPolicy, ViolationLevel},
+    store::Store,
+};
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::{
+    git_run, AskalonoLicenseScanner, AutoBuildContextPath, GlobSetExpression, LicenseScanConfig,
+    LicenseScanner, PackageDepIdent, PlanContextPath,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RepoConfig {
     pub id: String,
-    pub source: PathBuf,
+    /// Folder containing the repo, it is cloned into the hab-auto-build store from
+    /// its git remote if it is not set
+    pub source: Option<PathBuf>,
+    /// Git remote and branch the repo is tracking
+    pub git: Option<RepoGitConfig>,
     #[serde(default)]
     pub native_packages: GlobSetExpression,
     #[serde(default)]
     pub ignored_packages: GlobSetExpression,
+    #[serde(default)]
+    pub license_scan: LicenseScanConfig,
+    /// Version of the rule set the repo was last checked against, overrides the global one
+    pub rules_version: Option<u32>,
+    /// Level of the rules added after 'rules_version', overrides the global one
+    pub new_rule_level: Option<ViolationLevel>,
+    /// Studio packages used to build the repo's plans, override the global ones
+    #[serde(default)]
+    pub studios: RepoStudioConfig,
+    /// Glob patterns of files, relative to each plan context, whose changes do not
+    /// cause the repo's plans to be rebuilt
+    #[serde(default)]
+    pub change_ignore: Vec<String>,
+    /// Licenses the repo's packages are allowed to have, overrides the global policy
+    pub license_policy: Option<LicensePolicy>,
+}
+
+/// Studio packages of a repo, the global studio package is used for any studio
+/// that is not set.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RepoStudioConfig {
+    pub standard: Option<PackageDepIdent>,
+    pub bootstrap: Option<PackageDepIdent>,
+}
+
+/// Git remote of a repo
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RepoGitConfig {
+    /// Url of the remote repository
+    pub url: String,
+    /// Branch of the remote repository, defaults to 'main'
+    #[serde(default = "RepoGitConfig::default_branch")]
+    pub branch: String,
+}
+
+impl RepoGitConfig {
+    fn default_branch() -> String {
+        String::from("main")
+    }
+}
+
+/// Outcome of updating the clone of a repo from its git remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RepoGitUpdate {
+    Cloned,
+    Updated { from: String, to: String },
+    UpToDate,
+}
+
+/// Commit of a repo checked out into a git worktree in the hab-auto-build store,
+/// the worktree is removed when this is dropped
+#[derive(Debug)]
+pub(crate) struct RepoWorktree {
+    /// Root of the git repository the worktree belongs to
+    git_root: PathBuf,
+    /// Root of the worktree
+    path: PathBuf,
+    /// Folder of the repo inside the worktree
+    pub source: PathBuf,
+    /// Commit checked out in the worktree
+    pub commit: String,
+}
+
+impl RepoWorktree {
+    fn remove(git_root: &Path, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        debug!("Removing git worktree {}", path.display());
+        let result = git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(git_root)
+                .arg("worktree")
+                .arg("remove")
+                .arg("--force")
+                .arg(path),
+        );
+        // The worktree may not be registered anymore, in which case its folder
+        // is left behind
+        if path.exists() {
+            std::fs::remove_dir_all(path).with_context(|| {
+                format!("Failed to remove git worktree at '{}'", path.display())
+            })?;
+            git_run(
+                Exec::cmd("git")
+                    .arg("-C")
+                    .arg(git_root)
+                    .arg("worktree")
+                    .arg("prune"),
+            )?;
+        } else {
+            result?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RepoWorktree {
+    fn drop(&mut self) {
+        if let Err(err) = RepoWorktree::remove(&self.git_root, &self.path) {
+            debug!(
+                "Failed to remove git worktree {}: {:?}",
+                self.path.display(),
+                err
+            );
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
@@ -61,28 +187,350 @@ pub(crate) struct RepoContext {
     pub ignore_globset: GlobSetExpression,
     #[serde(skip)]
     pub native_globset: GlobSetExpression,
+    /// License scanner for the sources of the repo's plans, if it differs
+    /// from the default one
+    #[serde(skip)]
+    pub license_scanner: Option<Arc<dyn LicenseScanner>>,
+    /// Level of the rules added since the repo was last checked, if it is pinned
+    /// to a version of the rule set
+    #[serde(default)]
+    pub new_rules_policy: Option<NewRulesPolicy>,
+    /// Studio packages overriding the global ones for the repo's plans
+    #[serde(default)]
+    pub studios: RepoStudioConfig,
+    /// Git remote and branch the repo is tracking
+    #[serde(default)]
+    pub git: Option<RepoGitConfig>,
+    /// Glob patterns of files whose changes do not cause the repo's plans to be rebuilt
+    #[serde(default)]
+    pub change_ignore: Vec<String>,
+    /// Licenses the repo's packages are allowed to have
+    #[serde(default)]
+    pub license_policy: Option<LicensePolicy>,
+}
+
+impl RepoConfig {
+    /// Folder containing the repo, either its configured source or its clone in
+    /// the hab-auto-build store
+    pub fn path(
+        &self,
+        auto_build_ctx_path: &AutoBuildContextPath,
+        store: &Store,
+    ) -> Result<PathBuf> {
+        match (&self.source, &self.git) {
+            (Some(source), _) if source.is_absolute() => Ok(source.clone()),
+            (Some(source), _) => Ok(auto_build_ctx_path.as_ref().join(source)),
+            (None, Some(_)) => Ok(store.repo_clones_path().as_ref().join(&self.id)),
+            (None, None) => Err(eyre!(
+                "The repo '{}' must have either a 'source' folder or a 'git' remote",
+                self.id
+            )),
+        }
+    }
+
+    /// Clones the repo into the hab-auto-build store if it is only configured
+    /// with a git remote and was not cloned yet
+    pub fn clone_if_missing(
+        &self,
+        auto_build_ctx_path: &AutoBuildContextPath,
+        store: &Store,
+    ) -> Result<Option<RepoGitUpdate>> {
+        let (None, Some(git)) = (&self.source, &self.git) else {
+            return Ok(None);
+        };
+        let path = self.path(auto_build_ctx_path, store)?;
+        if path.is_dir() {
+            return Ok(None);
+        }
+        debug!(
+            "Cloning branch '{}' of git repository {} into {}",
+            git.branch,
+            git.url,
+            path.display()
+        );
+        std::fs::create_dir_all(store.repo_clones_path().as_ref())?;
+        if let Err(err) = git_run(
+            Exec::cmd("git")
+                .arg("clone")
+                .arg("--quiet")
+                .arg("--branch")
+                .arg(&git.branch)
+                .arg(&git.url)
+                .arg(&path),
+        ) {
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path)?;
+            }
+            return Err(err.wrap_err(format!(
+                "Failed to clone branch '{}' of git repository {} for repo '{}'",
+                git.branch, git.url, self.id
+            )));
+        }
+        Ok(Some(RepoGitUpdate::Cloned))
+    }
+
+    /// Checks out a git ref of the repo into a worktree named `name` in the
+    /// hab-auto-build store, replacing any previous worktree with the same name
+    pub fn git_worktree_add(
+        &self,
+        auto_build_ctx_path: &AutoBuildContextPath,
+        store: &Store,
+        name: &str,
+        git_ref: &str,
+    ) -> Result<RepoWorktree> {
+        self.clone_if_missing(auto_build_ctx_path, store)?;
+        let repo_path = self.path(auto_build_ctx_path, store)?;
+        let git_root = PathBuf::from(
+            git_run(
+                Exec::cmd("git")
+                    .arg("-C")
+                    .arg(&repo_path)
+                    .arg("rev-parse")
+                    .arg("--show-toplevel"),
+            )
+            .with_context(|| format!("The repo '{}' is not a git repository", self.id))?,
+        );
+        let git_root = git_root.canonicalize().unwrap_or(git_root);
+        let relative_path = repo_path
+            .canonicalize()
+            .unwrap_or(repo_path)
+            .strip_prefix(&git_root)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let commit = git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(&git_root)
+                .arg("rev-parse")
+                .arg("--verify")
+                .arg("--quiet")
+                .arg(format!("{}^{{commit}}", git_ref)),
+        )
+        .map_err(|_| eyre!("Unknown git ref '{}' in repo '{}'", git_ref, self.id))
+        .with_suggestion(|| "Fetch the ref into the repo, or give a branch, tag or commit")?;
+
+        let path = store
+            .repo_worktrees_path()
+            .as_ref()
+            .join(name)
+            .join(&self.id);
+        RepoWorktree::remove(&git_root, &path)?;
+        std::fs::create_dir_all(store.repo_worktrees_path().as_ref().join(name))?;
+        debug!(
+            "Checking out commit {} of repo '{}' into {}",
+            commit,
+            self.id,
+            path.display()
+        );
+        git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(&git_root)
+                .arg("worktree")
+                .arg("add")
+                .arg("--quiet")
+                .arg("--detach")
+                .arg("--force")
+                .arg(&path)
+                .arg(&commit),
+        )
+        .with_context(|| {
+            format!(
+                "Failed to check out git ref '{}' of repo '{}'",
+                git_ref, self.id
+            )
+        })?;
+        Ok(RepoWorktree {
+            git_root,
+            source: path.join(relative_path),
+            path,
+            commit,
+        })
+    }
 }
 
 impl RepoContext {
     pub fn new(
         config: &RepoConfig,
         auto_build_ctx_path: &AutoBuildContextPath,
+        store: &Store,
+        new_rules_policy: Option<NewRulesPolicy>,
+        license_policy: Option<LicensePolicy>,
     ) -> Result<RepoContext> {
+        let path: RepoContextPath = config.path(auto_build_ctx_path, store)?.try_into()?;
+        let license_scanner: Option<Arc<dyn LicenseScanner>> =
+            if config.license_scan == LicenseScanConfig::default() {
+                None
+            } else {
+                Some(Arc::new(
+                    AskalonoLicenseScanner::new(&config.license_scan, path.as_ref()).with_context(
+                        || {
+                            eyre!(
+                                "Failed to configure license scanner for repo '{}'",
+                                config.id
+                            )
+                        },
+                    )?,
+                ))
+            };
+        for pattern in config.change_ignore.iter() {
+            globset::Glob::new(pattern).with_context(|| {
+                eyre!(
+                    "Invalid glob pattern '{}' in 'change_ignore' of repo '{}'",
+                    pattern,
+                    config.id
+                )
+            })?;
+        }
         Ok(RepoContext {
             id: RepoContextID(config.id.clone()),
-            path: if config.source.is_absolute() {
-                config.source.clone().try_into()?
-            } else {
-                auto_build_ctx_path
-                    .as_ref()
-                    .join(config.source.as_path())
-                    .try_into()?
-            },
+            path,
             ignore_globset: config.ignored_packages.clone(),
             native_globset: config.native_packages.clone(),
+            license_scanner,
+            new_rules_policy,
+            studios: config.studios.clone(),
+            git: config.git.clone(),
+            change_ignore: config.change_ignore.clone(),
+            license_policy,
         })
     }
 
+    /// Whether the repo is a clone in the hab-auto-build store that is kept up
+    /// to date with its git remote
+    pub fn is_store_clone(&self, store: &Store) -> bool {
+        self.git.is_some()
+            && self
+                .path
+                .as_ref()
+                .starts_with(store.repo_clones_path().as_ref())
+    }
+
+    /// Fast-forwards the clone of the repo in the hab-auto-build store to the
+    /// latest commit of the branch it is tracking
+    pub fn git_update(&self) -> Result<RepoGitUpdate> {
+        let git = self
+            .git
+            .as_ref()
+            .ok_or_else(|| eyre!("The repo '{}' has no git remote", self.id))?;
+        let head = |path: &Path| {
+            git_run(
+                Exec::cmd("git")
+                    .arg("-C")
+                    .arg(path)
+                    .arg("rev-parse")
+                    .arg("HEAD"),
+            )
+        };
+        let from = head(self.path.as_ref())?;
+        debug!(
+            "Fetching branch '{}' of git repository {} into {}",
+            git.branch,
+            git.url,
+            self.path.as_ref().display()
+        );
+        git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(self.path.as_ref())
+                .arg("fetch")
+                .arg("--quiet")
+                .arg(&git.url)
+                .arg(&git.branch),
+        )
+        .with_context(|| {
+            format!(
+                "Failed to fetch branch '{}' of git repository {}",
+                git.branch, git.url
+            )
+        })?;
+        git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(self.path.as_ref())
+                .arg("merge")
+                .arg("--quiet")
+                .arg("--ff-only")
+                .arg("FETCH_HEAD"),
+        )
+        .with_context(|| {
+            format!(
+                "Failed to update repo '{}' to the latest commit of branch '{}'",
+                self.id, git.branch
+            )
+        })
+        .with_suggestion(|| {
+            format!(
+                "Discard the local changes to the clone of the repo at '{}'",
+                self.path.as_ref().display()
+            )
+        })?;
+        let to = head(self.path.as_ref())?;
+        if from == to {
+            Ok(RepoGitUpdate::UpToDate)
+        } else {
+            Ok(RepoGitUpdate::Updated { from, to })
+        }
+    }
+
+    /// Lists the files of the repo's working tree that differ from the latest
+    /// commit of the branch it is tracking, relative to the repo. This includes
+    /// uncommitted changes, local commits and commits on the remote branch that
+    /// are missing locally.
+    pub fn git_upstream_changed_files(&self) -> Result<Vec<PathBuf>> {
+        let git = self
+            .git
+            .as_ref()
+            .ok_or_else(|| eyre!("The repo '{}' has no git remote", self.id))?;
+        git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(self.path.as_ref())
+                .arg("fetch")
+                .arg("--quiet")
+                .arg(&git.url)
+                .arg(&git.branch),
+        )
+        .with_context(|| {
+            format!(
+                "Failed to fetch branch '{}' of git repository {}",
+                git.branch, git.url
+            )
+        })?;
+        let repo_root = PathBuf::from(git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(self.path.as_ref())
+                .arg("rev-parse")
+                .arg("--show-toplevel"),
+        )?);
+        let changed_files = git_run(
+            Exec::cmd("git")
+                .arg("-C")
+                .arg(self.path.as_ref())
+                .arg("diff")
+                .arg("--name-only")
+                .arg("FETCH_HEAD"),
+        )
+        .with_context(|| {
+            format!(
+                "Failed to compare repo '{}' with branch '{}' of git repository {}",
+                self.id, git.branch, git.url
+            )
+        })?;
+        let repo_root = repo_root.canonicalize().unwrap_or(repo_root);
+        Ok(changed_files
+            .lines()
+            .filter_map(|file| {
+                repo_root
+                    .join(file)
+                    .strip_prefix(self.path.as_ref())
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .collect())
+    }
+
     pub fn is_ignored_plan(&self, plan_ctx_path: &PlanContextPath) -> bool {
         let relative_path = plan_ctx_path
             .as_ref()
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Value};
+
+use super::{ArtifactContext, PackageIdent};
+
+const SBOM_TOOL_NAME: &str = env!("CARGO_PKG_NAME");
+const SBOM_TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+const SPDX_NO_ASSERTION: &str = "NOASSERTION";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SbomFormat {
+    Spdx,
+    CycloneDx,
+}
+
+impl SbomFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SbomFormat::Spdx => "spdx.json",
+            SbomFormat::CycloneDx => "cdx.json",
+        }
+    }
+}
+
+/// The set of packages described by a software bill of materials. The root packages
+/// are the ones the document is about, the other packages are their transitive
+/// runtime dependencies. Packages without an artifact are still listed, but without
+/// licensing, source or dependency information.
+#[derive(Debug, Default)]
+pub(crate) struct SbomPackages {
+    pub roots: Vec<PackageIdent>,
+    pub packages: BTreeMap<PackageIdent, Option<ArtifactContext>>,
+}
+
+pub(crate) fn sbom_generate(format: SbomFormat, name: &str, packages: &SbomPackages) -> Value {
+    match format {
+        SbomFormat::Spdx => sbom_spdx_generate(name, packages),
+        SbomFormat::CycloneDx => sbom_cyclonedx_generate(name, packages),
+    }
+}
+
+fn sbom_spdx_generate(name: &str, packages: &SbomPackages) -> Value {
+    let created_at = Utc::now();
+    let mut spdx_packages = Vec::new();
+    let mut relationships = Vec::new();
+    for root in packages.roots.iter() {
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": spdx_id(root),
+        }));
+    }
+    for (ident, artifact) in packages.packages.iter() {
+        let mut spdx_package = json!({
+            "SPDXID": spdx_id(ident),
+            "name": format!("{}/{}", ident.origin, ident.name),
+            "versionInfo": format!("{}-{}", ident.version, ident.release),
+            "supplier": format!("Organization: {}", ident.origin),
+            "downloadLocation": SPDX_NO_ASSERTION,
+            "filesAnalyzed": false,
+            "licenseConcluded": SPDX_NO_ASSERTION,
+            "licenseDeclared": SPDX_NO_ASSERTION,
+            "copyrightText": SPDX_NO_ASSERTION,
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": purl(ident),
+            }],
+        });
+        if let Some(artifact) = artifact {
+            spdx_package["checksums"] = json!([{
+                "algorithm": "BLAKE3",
+                "checksumValue": artifact.hash.to_string(),
+            }]);
+            if let Some(source) = artifact.source.as_ref() {
+                spdx_package["downloadLocation"] = json!(source.url.to_string());
+                spdx_package["sourceInfo"] = json!(format!(
+                    "Built from source archive with sha256 checksum {}",
+                    source.shasum
+                ));
+            }
+            if !artifact.licenses.is_empty() {
+                if artifact
+                    .licenses
+                    .iter()
+                    .all(|license| spdx::Expression::parse(license).is_ok())
+                {
+                    spdx_package["licenseDeclared"] = json!(artifact
+                        .licenses
+                        .iter()
+                        .map(
+                            |license| if artifact.licenses.len() > 1 && license.contains(' ') {
+                                format!("({})", license)
+                            } else {
+                                license.clone()
+                            }
+                        )
+                        .collect::<Vec<_>>()
+                        .join(" AND "));
+                } else {
+                    spdx_package["licenseComments"] = json!(format!(
+                        "Declared licenses are not valid SPDX expressions: {}",
+                        artifact.licenses.join(", ")
+                    ));
+                }
+            }
+            let mut deps = artifact.deps.iter().collect::<Vec<_>>();
+            deps.sort();
+            for dep in deps {
+                relationships.push(json!({
+                    "spdxElementId": spdx_id(ident),
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": spdx_id(dep),
+                }));
+            }
+        }
+        spdx_packages.push(spdx_package);
+    }
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": format!(
+            "https://habitat.sh/spdxdocs/{}-{}",
+            name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-', "-"),
+            created_at.timestamp_nanos_opt().unwrap_or_default()
+        ),
+        "creationInfo": {
+            "created": created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "creators": [format!("Tool: {}-{}", SBOM_TOOL_NAME, SBOM_TOOL_VERSION)],
+        },
+        "packages": spdx_packages,
+        "relationships": relationships,
+    })
+}
+
+fn sbom_cyclonedx_generate(name: &str, packages: &SbomPackages) -> Value {
+    let mut components = Vec::new();
+    let mut dependencies = Vec::new();
+    for (ident, artifact) in packages.packages.iter() {
+        let mut component = json!({
+            "type": "application",
+            "bom-ref": purl(ident),
+            "supplier": { "name": ident.origin.to_string() },
+            "group": ident.origin.to_string(),
+            "name": ident.name.to_string(),
+            "version": format!("{}-{}", ident.version, ident.release),
+            "purl": purl(ident),
+        });
+        if let Some(artifact) = artifact {
+            component["hashes"] = json!([{
+                "alg": "BLAKE3",
+                "content": artifact.hash.to_string(),
+            }]);
+            if !artifact.licenses.is_empty() {
+                component["licenses"] = json!(artifact
+                    .licenses
+                    .iter()
+                    .map(|license| if spdx::license_id(license).is_some() {
+                        json!({ "license": { "id": license } })
+                    } else {
+                        json!({ "license": { "name": license } })
+                    })
+                    .collect::<Vec<_>>());
+            }
+            if let Some(source) = artifact.source.as_ref() {
+                component["externalReferences"] = json!([{
+                    "type": "distribution",
+                    "url": source.url.to_string(),
+                    "hashes": [{
+                        "alg": "SHA-256",
+                        "content": source.shasum.to_string(),
+                    }],
+                }]);
+            }
+            let mut deps = artifact.deps.iter().map(purl).collect::<Vec<_>>();
+            deps.sort();
+            dependencies.push(json!({
+                "ref": purl(ident),
+                "dependsOn": deps,
+            }));
+        }
+        components.push(component);
+    }
+    let mut metadata = json!({
+        "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        "tools": {
+            "components": [{
+                "type": "application",
+                "name": SBOM_TOOL_NAME,
+                "version": SBOM_TOOL_VERSION,
+            }],
+        },
+    });
+    // A document describing a single package uses it as the subject of the
+    // bill of materials, otherwise all packages are listed as components.
+    if let [root] = packages.roots.as_slice() {
+        if let Some(index) = components
+            .iter()
+            .position(|component| component["bom-ref"] == json!(purl(root)))
+        {
+            metadata["component"] = components.remove(index);
+        }
+    } else {
+        metadata["component"] = json!({
+            "type": "application",
+            "bom-ref": name,
+            "name": name,
+        });
+        dependencies.push(json!({
+            "ref": name,
+            "dependsOn": packages.roots.iter().map(purl).collect::<Vec<_>>(),
+        }));
+    }
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": metadata,
+        "components": components,
+        "dependencies": dependencies,
+    })
+}
+
+fn spdx_id(ident: &PackageIdent) -> String {
+    format!(
+        "SPDXRef-Package-{}-{}-{}-{}",
+        ident.origin, ident.name, ident.version, ident.release
+    )
+    .replace(
+        |c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-',
+        "-",
+    )
+}
+
+fn purl(ident: &PackageIdent) -> String {
+    format!(
+        "pkg:generic/{}/{}@{}-{}?target={}",
+        ident.origin, ident.name, ident.version, ident.release, ident.target
+    )
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use toml_edit::{DocumentMut, TableLike};
+
+use super::HOOK_TEMPLATE_EXPRESSION;
+
+/// File of a plan containing the default configuration of the service
+pub(crate) const DEFAULT_CONFIG_FILE: &str = "default.toml";
+
+/// Folders of a plan containing the configuration templates of the service
+pub(crate) const CONFIG_TEMPLATE_DIRECTORIES: &[&str] = &["config", "config_install"];
+
+/// Maximum size of a configuration template that is read for checks
+const CONFIG_TEMPLATE_MAX_SIZE: u64 = 1024 * 1024;
+
+lazy_static! {
+    /// Reference to a configuration value in a template expression. References
+    /// to the configuration of bound services, such as 'bind.db.first.cfg.port',
+    /// are left out by requiring that 'cfg' is not preceded by a '.'
+    static ref CONFIG_VALUE_REFERENCE: Regex =
+        Regex::new(r"(?:^|[^.\w])cfg((?:\.(?:[A-Za-z0-9_-]+|\[[^\]]*\]))*)").unwrap();
+}
+
+/// Configuration template of a plan
+#[derive(Debug, Clone)]
+pub(crate) struct ConfigTemplate {
+    /// Path of the template relative to the plan folder
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl ConfigTemplate {
+    /// Reads the templates in the config folders of a plan, if there are any
+    pub fn read_all(plan_dir: impl AsRef<Path>) -> Result<Vec<ConfigTemplate>> {
+        let mut templates = Vec::new();
+        for directory in CONFIG_TEMPLATE_DIRECTORIES {
+            let mut pending_dirs = vec![PathBuf::from(directory)];
+            while let Some(relative_dir) = pending_dirs.pop() {
+                let dir = plan_dir.as_ref().join(&relative_dir);
+                if !dir.is_dir() {
+                    continue;
+                }
+                for entry in std::fs::read_dir(&dir)
+                    .with_context(|| format!("Failed to read config folder '{}'", dir.display()))?
+                {
+                    let entry = entry?;
+                    let metadata = entry.metadata()?;
+                    let relative_path = relative_dir.join(entry.file_name());
+                    if metadata.is_dir() {
+                        pending_dirs.push(relative_path);
+                        continue;
+                    }
+                    if !metadata.is_file() || metadata.len() > CONFIG_TEMPLATE_MAX_SIZE {
+                        continue;
+                    }
+                    let content = std::fs::read(entry.path()).with_context(|| {
+                        format!(
+                            "Failed to read config template '{}'",
+                            entry.path().display()
+                        )
+                    })?;
+                    templates.push(ConfigTemplate {
+                        path: relative_path,
+                        content: String::from_utf8_lossy(&content).to_string(),
+                    });
+                }
+            }
+        }
+        templates.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(templates)
+    }
+}
+
+/// Default configuration of a service, read from the `default.toml` of its plan
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DefaultConfig {
+    document: DocumentMut,
+}
+
+impl DefaultConfig {
+    /// Reads the default configuration of a plan, `None` if the plan has none
+    pub fn read(plan_dir: impl AsRef<Path>) -> Result<Option<DefaultConfig>> {
+        let path = plan_dir.as_ref().join(DEFAULT_CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read default config '{}'", path.display()))?;
+        let document = content
+            .parse::<DocumentMut>()
+            .with_context(|| format!("Invalid TOML in default config '{}'", path.display()))?;
+        Ok(Some(DefaultConfig { document }))
+    }
+
+    /// Dotted paths of all the keys of the configuration, including tables
+    pub fn keys(&self) -> BTreeSet<String> {
+        let mut keys = BTreeSet::new();
+        config_table_keys(self.document.as_table(), "", &mut keys);
+        keys
+    }
+
+    /// Whether the configuration has a value for the given dotted path. Values
+    /// inside arrays and scalars cannot be checked, so paths going through them
+    /// are considered to be defined.
+    pub fn is_defined(&self, key: &str) -> bool {
+        let mut item = self.document.as_item();
+        for segment in key.split('.').filter(|segment| !segment.is_empty()) {
+            if !item.is_table_like() {
+                return true;
+            }
+            match item.get(segment) {
+                Some(value) => item = value,
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+fn config_table_keys(table: &dyn TableLike, prefix: &str, keys: &mut BTreeSet<String>) {
+    for (key, item) in table.iter() {
+        let path = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        if let Some(table) = item.as_table_like() {
+            config_table_keys(table, &path, keys);
+        }
+        keys.insert(path);
+    }
+}
+
+/// Configuration values referenced by the template expressions of a template or a
+/// hook, as dotted paths. Array indices end a path, and a reference to the whole
+/// configuration is returned as an empty path.
+pub(crate) fn config_value_references(content: &str) -> BTreeSet<String> {
+    let mut references = BTreeSet::new();
+    for expression in HOOK_TEMPLATE_EXPRESSION.captures_iter(content) {
+        for reference in CONFIG_VALUE_REFERENCE.captures_iter(&expression[1]) {
+            let mut segments = Vec::new();
+            for segment in reference[1]
+                .split('.')
+                .filter(|segment| !segment.is_empty())
+            {
+                let segment = segment.trim_start_matches('[').trim_end_matches(']');
+                if segment.chars().all(|c| c.is_ascii_digit()) {
+                    break;
+                }
+                segments.push(segment);
+            }
+            references.insert(segments.join("."));
+        }
+    }
+    references
+}
@@ -0,0 +1,148 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use thiserror::Error;
+
+use super::HabitatKeyCachePath;
+
+const HART_FORMAT_VERSION: &str = "HART-1";
+const PUBLIC_KEY_FORMAT_VERSION: &str = "SIG-PUB-1";
+const SIGNATURE_HASH_TYPE: &str = "BLAKE2b";
+
+type Blake2b256 = Blake2b<U32>;
+
+#[derive(Debug, Error)]
+pub(crate) enum ArtifactSignatureError {
+    #[error("Artifact header is malformed: {0}")]
+    MalformedHeader(String),
+    #[error("Unsupported artifact format version '{0}'")]
+    UnsupportedFormatVersion(String),
+    #[error("Unsupported artifact signature hash type '{0}'")]
+    UnsupportedHashType(String),
+    #[error("Public origin key '{0}' not found at '{1}'")]
+    MissingPublicKey(String, PathBuf),
+    #[error("Public origin key '{0}' is invalid: {1}")]
+    InvalidPublicKey(String, String),
+    #[error("Artifact signature could not be verified with the public origin key '{0}'")]
+    InvalidSignature(String),
+    #[error("Artifact contents do not match the signed checksum")]
+    ChecksumMismatch,
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+/// Verifies the signature in the header of a `.hart` file against the public
+/// origin key found in the habitat key cache.
+pub(crate) fn artifact_signature_verify(
+    artifact_path: impl AsRef<Path>,
+    key_cache_path: &HabitatKeyCachePath,
+) -> Result<(), ArtifactSignatureError> {
+    let mut reader = BufReader::new(File::open(artifact_path.as_ref())?);
+
+    let format_version = header_line_read(&mut reader, "format version")?;
+    if format_version != HART_FORMAT_VERSION {
+        return Err(ArtifactSignatureError::UnsupportedFormatVersion(
+            format_version,
+        ));
+    }
+    let key_name_with_rev = header_line_read(&mut reader, "key name")?;
+    let hash_type = header_line_read(&mut reader, "hash type")?;
+    if hash_type != SIGNATURE_HASH_TYPE {
+        return Err(ArtifactSignatureError::UnsupportedHashType(hash_type));
+    }
+    let signed_checksum = BASE64
+        .decode(header_line_read(&mut reader, "signature")?)
+        .map_err(|err| {
+            ArtifactSignatureError::MalformedHeader(format!("Invalid signature encoding: {}", err))
+        })?;
+    if !header_line_read(&mut reader, "separator")?.is_empty() {
+        return Err(ArtifactSignatureError::MalformedHeader(
+            "Missing empty line after signature".to_string(),
+        ));
+    }
+
+    // The signature is stored in the 'combined' format, which is the signature
+    // followed by the signed message. The message is the hex encoded BLAKE2b
+    // checksum of the rest of the artifact.
+    if signed_checksum.len() <= SIGNATURE_LENGTH {
+        return Err(ArtifactSignatureError::MalformedHeader(
+            "Signature is too short".to_string(),
+        ));
+    }
+    let (signature, checksum) = signed_checksum.split_at(SIGNATURE_LENGTH);
+    let signature = Signature::from_slice(signature)
+        .map_err(|_| ArtifactSignatureError::InvalidSignature(key_name_with_rev.clone()))?;
+    let public_key = public_key_read(key_cache_path, &key_name_with_rev)?;
+    public_key
+        .verify(checksum, &signature)
+        .map_err(|_| ArtifactSignatureError::InvalidSignature(key_name_with_rev.clone()))?;
+
+    let mut hasher = Blake2b256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    let actual_checksum = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    if actual_checksum.as_bytes() != checksum {
+        return Err(ArtifactSignatureError::ChecksumMismatch);
+    }
+    Ok(())
+}
+
+fn header_line_read(
+    reader: &mut impl BufRead,
+    field: &str,
+) -> Result<String, ArtifactSignatureError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(ArtifactSignatureError::MalformedHeader(format!(
+            "Missing {}",
+            field
+        )));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn public_key_read(
+    key_cache_path: &HabitatKeyCachePath,
+    key_name_with_rev: &str,
+) -> Result<VerifyingKey, ArtifactSignatureError> {
+    let public_key_path = key_cache_path.public_key_path(key_name_with_rev);
+    let content = std::fs::read_to_string(&public_key_path).map_err(|_| {
+        ArtifactSignatureError::MissingPublicKey(key_name_with_rev.to_string(), public_key_path)
+    })?;
+    let invalid_key = |reason: &str| {
+        ArtifactSignatureError::InvalidPublicKey(key_name_with_rev.to_string(), reason.to_string())
+    };
+    let mut lines = content.lines();
+    if lines.next() != Some(PUBLIC_KEY_FORMAT_VERSION) {
+        return Err(invalid_key("Unsupported key format version"));
+    }
+    if lines.next() != Some(key_name_with_rev) {
+        return Err(invalid_key("Key name does not match"));
+    }
+    let key = lines
+        .find(|line| !line.trim().is_empty())
+        .ok_or_else(|| invalid_key("Missing key data"))?;
+    let key = BASE64
+        .decode(key.trim())
+        .map_err(|_| invalid_key("Invalid key encoding"))?;
+    let key: [u8; PUBLIC_KEY_LENGTH] = key
+        .try_into()
+        .map_err(|_| invalid_key("Invalid key length"))?;
+    VerifyingKey::from_bytes(&key).map_err(|_| invalid_key("Invalid key data"))
+}
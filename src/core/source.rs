@@ -1,18 +1,20 @@
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
     fs::File,
     io::{BufReader, Read},
     path::{Path, PathBuf},
+    sync::OnceLock,
     time::Instant,
 };
 
 use askalono::{ScanMode, ScanStrategy, Store, TextData};
 use bzip2::read::BzDecoder;
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Context, Result};
 use flate2::bufread::GzDecoder;
 use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use lazy_static::lazy_static;
-use rayon::prelude::*;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
 use serde::{Deserialize, Serialize};
 use tar::Archive;
 use tracing::{debug, error, trace};
@@ -20,6 +22,11 @@ use xz2::bufread::XzDecoder;
 
 use super::{FileKind, PackageSha256Sum};
 
+/// Pool of threads on which license files are scanned, it is kept separate from
+/// the global pool and smaller than the number of cores so that scanning the
+/// sources of many large packages does not starve the rest of the host.
+static LICENSE_SCAN_POOL: OnceLock<ThreadPool> = OnceLock::new();
+
 const LICENSE_GLOBS: &[&str] = &[
     // General
     "COPYING",
@@ -59,6 +66,7 @@ const LICENSE_GLOBS: &[&str] = &[
     "OFL-*[0-9]*",
 ];
 const LICENSE_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/license-cache.bin.gz"));
+const DEFAULT_LICENSE_CONFIDENCE_THRESHOLD: f32 = 0.8;
 
 lazy_static! {
     static ref LICENSE_STORE: Store = Store::from_cache(LICENSE_DATA).unwrap();
@@ -89,6 +97,168 @@ lazy_static! {
     };
 }
 
+/// Configures how licenses are detected in the license files of a repo's sources
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LicenseScanConfig {
+    /// Minimum score for a license text to be considered a match
+    #[serde(default = "default_license_confidence_threshold")]
+    pub confidence_threshold: f32,
+    /// Additional license texts, relative to the repo. The license name is
+    /// the file name without the extension.
+    #[serde(default)]
+    pub license_texts: Vec<PathBuf>,
+}
+
+fn default_license_confidence_threshold() -> f32 {
+    DEFAULT_LICENSE_CONFIDENCE_THRESHOLD
+}
+
+impl Default for LicenseScanConfig {
+    fn default() -> Self {
+        LicenseScanConfig {
+            confidence_threshold: DEFAULT_LICENSE_CONFIDENCE_THRESHOLD,
+            license_texts: Vec::new(),
+        }
+    }
+}
+
+/// Detects the licenses contained in the text of a license file
+pub(crate) trait LicenseScanner: Debug + Send + Sync {
+    fn scan(&self, text: &str) -> BTreeSet<String>;
+    /// Identifies the configuration of the scanner, scanners with the same
+    /// fingerprint detect the same licenses in a text
+    fn fingerprint(&self) -> &str;
+}
+
+/// License scanner based on askalono, using the SPDX license list bundled at
+/// build time and any additional license texts.
+pub(crate) struct AskalonoLicenseScanner {
+    store: Option<Store>,
+    confidence_threshold: f32,
+    fingerprint: String,
+}
+
+impl AskalonoLicenseScanner {
+    pub fn new(
+        config: &LicenseScanConfig,
+        base_path: impl AsRef<Path>,
+    ) -> Result<AskalonoLicenseScanner> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&config.confidence_threshold.to_le_bytes());
+        let store = if config.license_texts.is_empty() {
+            None
+        } else {
+            let mut store = Store::from_cache(LICENSE_DATA)
+                .map_err(|err| eyre!("Failed to load license data: {}", err))?;
+            for license_text_path in config.license_texts.iter() {
+                let license_text_path = base_path.as_ref().join(license_text_path);
+                let name = license_text_path
+                    .file_stem()
+                    .and_then(|name| name.to_str())
+                    .ok_or(eyre!(
+                        "Invalid license text file name '{}'",
+                        license_text_path.display()
+                    ))?
+                    .to_string();
+                let text = std::fs::read_to_string(&license_text_path).with_context(|| {
+                    format!(
+                        "Failed to read license text '{}'",
+                        license_text_path.display()
+                    )
+                })?;
+                hasher.update(name.as_bytes());
+                hasher.update(&[0]);
+                hasher.update(text.as_bytes());
+                hasher.update(&[0]);
+                store.add_license(name, TextData::new(&text));
+            }
+            Some(store)
+        };
+        Ok(AskalonoLicenseScanner {
+            store,
+            confidence_threshold: config.confidence_threshold,
+            fingerprint: hasher.finalize().to_hex().to_string(),
+        })
+    }
+}
+
+impl Default for AskalonoLicenseScanner {
+    fn default() -> Self {
+        AskalonoLicenseScanner {
+            store: None,
+            confidence_threshold: DEFAULT_LICENSE_CONFIDENCE_THRESHOLD,
+            fingerprint: String::from("default"),
+        }
+    }
+}
+
+impl Debug for AskalonoLicenseScanner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AskalonoLicenseScanner")
+            .field("custom_store", &self.store.is_some())
+            .field("confidence_threshold", &self.confidence_threshold)
+            .finish()
+    }
+}
+
+impl LicenseScanner for AskalonoLicenseScanner {
+    fn scan(&self, text: &str) -> BTreeSet<String> {
+        let strategy = ScanStrategy::new(self.store.as_ref().unwrap_or(&LICENSE_STORE))
+            .confidence_threshold(self.confidence_threshold)
+            .mode(ScanMode::TopDown)
+            .shallow_limit(0.98)
+            .max_passes(50)
+            .optimize(true);
+        let mut detected_licenses = BTreeSet::new();
+        if let Ok(results) = strategy.scan(&TextData::new(text)) {
+            for item in results.containing {
+                detected_licenses.insert(item.license.name.to_string());
+            }
+        }
+        detected_licenses
+    }
+
+    fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+}
+
+/// Sets the number of threads used to scan license files, this has no effect
+/// once a scan has started.
+pub(crate) fn license_scan_pool_configure(threads: usize) -> Result<()> {
+    if threads == 0 {
+        return Err(eyre!(
+            "The number of license scan threads must be at least 1"
+        ));
+    }
+    if LICENSE_SCAN_POOL.get().is_some() {
+        debug!(
+            "License scan pool already initialized, ignoring thread count {}",
+            threads
+        );
+        return Ok(());
+    }
+    let pool = license_scan_pool_build(threads)?;
+    let _ = LICENSE_SCAN_POOL.set(pool);
+    Ok(())
+}
+
+fn license_scan_pool() -> &'static ThreadPool {
+    LICENSE_SCAN_POOL.get_or_init(|| {
+        license_scan_pool_build((num_cpus::get() / 2).max(1))
+            .expect("Failed to create license scan thread pool")
+    })
+}
+
+fn license_scan_pool_build(threads: usize) -> Result<ThreadPool> {
+    debug!("Creating license scan pool with {} threads", threads);
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("license-scan-{}", index))
+        .build()
+        .context("Failed to create license scan thread pool")
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct SourceContext {
     pub format: (FileKind, Option<FileKind>),
@@ -186,12 +356,7 @@ impl SourceContext {
         R: Read,
     {
         let start = Instant::now();
-        let strategy = ScanStrategy::new(&LICENSE_STORE)
-            .confidence_threshold(0.8)
-            .mode(ScanMode::TopDown)
-            .shallow_limit(0.98)
-            .max_passes(50)
-            .optimize(true);
+        let scanner = AskalonoLicenseScanner::default();
         let mut license_files = Vec::new();
         for entry in tar.entries()? {
             let mut entry = entry?;
@@ -205,35 +370,77 @@ impl SourceContext {
                 }
             }
         }
-        // Detect licenses in parallel
-        let licenses = license_files
-            .into_par_iter()
-            .map(|(path, text)| {
-                trace!("Scanning file {} for licenses", path.display());
-                let mut detected_licenses = BTreeSet::new();
-                if let Ok(results) = strategy.scan(&TextData::new(&text)) {
-                    for item in results.containing {
-                        detected_licenses.insert(item.license.name.to_string());
+        // Detect licenses in parallel on the dedicated license scan pool
+        let licenses = license_scan_pool().install(|| {
+            license_files
+                .into_par_iter()
+                .map(|(path, text)| {
+                    trace!("Scanning file {} for licenses", path.display());
+                    let detected_licenses = scanner.scan(&text);
+                    trace!(
+                        "Including license file {} in source context, with detected licenses: {:?}",
+                        path.display(),
+                        detected_licenses
+                    );
+                    SourceLicenseContext {
+                        path,
+                        text,
+                        detected_licenses,
                     }
-                }
-                trace!(
-                    "Including license file {} in source context, with detected licenses: {:?}",
-                    path.display(),
-                    detected_licenses
-                );
-                SourceLicenseContext {
-                    path,
-                    text,
-                    detected_licenses,
-                }
-            })
-            .collect();
+                })
+                .collect()
+        });
         debug!(
             "Completed scanning for licenses in archive in {}s",
             start.elapsed().as_secs_f32()
         );
         Ok(licenses)
     }
+
+    /// Licenses detected in each license file, keyed by the path of the file
+    pub fn detected_licenses(&self) -> BTreeMap<PathBuf, BTreeSet<String>> {
+        self.licenses
+            .iter()
+            .map(|license_ctx| {
+                (
+                    license_ctx.path.clone(),
+                    license_ctx.detected_licenses.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Replaces the licenses detected in the license files with those of an
+    /// earlier scan, license files missing from the scan are left unchanged.
+    pub fn detected_licenses_set(
+        &mut self,
+        mut detected_licenses: BTreeMap<PathBuf, BTreeSet<String>>,
+    ) {
+        self.licenses = std::mem::take(&mut self.licenses)
+            .into_iter()
+            .map(|mut license_ctx| {
+                if let Some(detected) = detected_licenses.remove(&license_ctx.path) {
+                    license_ctx.detected_licenses = detected;
+                }
+                license_ctx
+            })
+            .collect();
+    }
+
+    /// Detects the licenses in the license files again with a different scanner,
+    /// the license files are kept so the archive does not have to be read again.
+    pub fn licenses_rescan(&mut self, scanner: &dyn LicenseScanner) {
+        let licenses = std::mem::take(&mut self.licenses);
+        self.licenses = license_scan_pool().install(|| {
+            licenses
+                .into_par_iter()
+                .map(|mut license_ctx| {
+                    license_ctx.detected_licenses = scanner.scan(&license_ctx.text);
+                    license_ctx
+                })
+                .collect()
+        });
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
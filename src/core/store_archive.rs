@@ -0,0 +1,204 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use tar::{Archive, Builder};
+use tracing::debug;
+
+use crate::store::{self, Store};
+
+use super::ArtifactCachePath;
+
+const STORE_ARCHIVE_SOURCES_DIR: &str = "sources";
+const STORE_ARCHIVE_ARTIFACTS_DIR: &str = "artifacts";
+
+/// Summary of the entries written to or read from a store archive
+#[derive(Debug, Default)]
+pub(crate) struct StoreArchiveSummary {
+    /// Number of source archive folders in the archive
+    pub sources: usize,
+    /// Number of artifacts in the archive
+    pub artifacts: usize,
+    /// Number of artifacts that were already present in the artifact cache
+    /// and left untouched during an import
+    pub artifacts_skipped: usize,
+}
+
+/// Writes the database of the store along with the given source store folders
+/// and artifacts to a tar archive at `output_path`.
+pub(crate) fn store_archive_export(
+    store: &Store,
+    source_paths: &[PathBuf],
+    artifact_paths: &[PathBuf],
+    output_path: impl AsRef<Path>,
+) -> Result<StoreArchiveSummary> {
+    let output_path = output_path.as_ref();
+    let tmp_dir = store.temp_dir("store-export")?;
+    let database_snapshot_path = tmp_dir.path().join(store::DATABASE_FILE_NAME);
+    store.database_snapshot(&database_snapshot_path)?;
+
+    let output_file = File::create(output_path).with_context(|| {
+        format!(
+            "Failed to create store archive at '{}'",
+            output_path.display()
+        )
+    })?;
+    let mut builder = Builder::new(BufWriter::new(output_file));
+    builder.follow_symlinks(false);
+    builder.append_path_with_name(&database_snapshot_path, store::DATABASE_FILE_NAME)?;
+
+    let mut summary = StoreArchiveSummary::default();
+    for source_path in source_paths {
+        let name = source_path
+            .file_name()
+            .ok_or_else(|| eyre!("Invalid source store path '{}'", source_path.display()))?;
+        builder
+            .append_dir_all(Path::new(STORE_ARCHIVE_SOURCES_DIR).join(name), source_path)
+            .with_context(|| {
+                format!(
+                    "Failed to add source store folder '{}' to store archive",
+                    source_path.display()
+                )
+            })?;
+        summary.sources += 1;
+    }
+    for artifact_path in artifact_paths {
+        let name = artifact_path
+            .file_name()
+            .ok_or_else(|| eyre!("Invalid artifact path '{}'", artifact_path.display()))?;
+        builder
+            .append_path_with_name(
+                artifact_path,
+                Path::new(STORE_ARCHIVE_ARTIFACTS_DIR).join(name),
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to add artifact '{}' to store archive",
+                    artifact_path.display()
+                )
+            })?;
+        summary.artifacts += 1;
+    }
+    builder.into_inner()?.flush()?;
+    debug!(
+        "Exported store with {} sources and {} artifacts to '{}'",
+        summary.sources,
+        summary.artifacts,
+        output_path.display()
+    );
+    Ok(summary)
+}
+
+/// Unpacks a store archive created by `store_archive_export` into the store at
+/// `store_path` and the artifact cache. The store must not have a database yet
+/// unless `force` is set, in which case the existing database is replaced.
+pub(crate) fn store_archive_import(
+    store_path: impl AsRef<Path>,
+    workspace: &str,
+    archive_path: impl AsRef<Path>,
+    force: bool,
+) -> Result<StoreArchiveSummary> {
+    let store_path = store_path.as_ref();
+    let archive_path = archive_path.as_ref();
+    let database_path = store_path.join(store::DATABASE_FILE_NAME);
+    if database_path.exists() && !force {
+        return Err(eyre!(
+            "The hab-auto-build store at '{}' already has a database",
+            store_path.display()
+        ))
+        .with_suggestion(|| "Use the --force option to replace the existing database");
+    }
+    let artifact_cache_path = ArtifactCachePath::default();
+    std::fs::create_dir_all(store_path)?;
+
+    let archive_file = File::open(archive_path).with_context(|| {
+        format!(
+            "Failed to open store archive at '{}'",
+            archive_path.display()
+        )
+    })?;
+    let mut archive = Archive::new(BufReader::new(archive_file));
+    let mut summary = StoreArchiveSummary::default();
+    let mut has_database = false;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_path_buf();
+        let components = entry_path
+            .components()
+            .map(|component| match component {
+                Component::Normal(component) => Ok(component.to_string_lossy().to_string()),
+                _ => Err(eyre!(
+                    "Invalid entry '{}' in store archive",
+                    entry_path.display()
+                )),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        match components
+            .iter()
+            .map(|component| component.as_str())
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [store::DATABASE_FILE_NAME] => {
+                // Remove any leftover journal files that belong to the database being replaced
+                for suffix in ["-wal", "-shm", "-journal"] {
+                    let journal_path =
+                        store_path.join(format!("{}{}", store::DATABASE_FILE_NAME, suffix));
+                    if journal_path.exists() {
+                        std::fs::remove_file(&journal_path)?;
+                    }
+                }
+                entry.unpack(&database_path).with_context(|| {
+                    format!("Failed to unpack database to '{}'", database_path.display())
+                })?;
+                has_database = true;
+            }
+            [STORE_ARCHIVE_SOURCES_DIR, name, ..] => {
+                if components.len() == 2 && entry.header().entry_type().is_dir() {
+                    debug!("Importing source store folder '{}'", name);
+                    summary.sources += 1;
+                }
+                entry.unpack_in(store_path)?;
+            }
+            [STORE_ARCHIVE_ARTIFACTS_DIR, name] => {
+                let artifact_path = artifact_cache_path.as_ref().join(name);
+                if artifact_path.exists() {
+                    summary.artifacts_skipped += 1;
+                    continue;
+                }
+                std::fs::create_dir_all(artifact_cache_path.as_ref())?;
+                entry.unpack(&artifact_path).with_context(|| {
+                    format!("Failed to unpack artifact to '{}'", artifact_path.display())
+                })?;
+                summary.artifacts += 1;
+            }
+            _ => {
+                return Err(eyre!(
+                    "Unexpected entry '{}' in store archive",
+                    entry_path.display()
+                ))
+            }
+        }
+    }
+    if !has_database {
+        return Err(eyre!(
+            "The store archive at '{}' does not contain a hab-auto-build database",
+            archive_path.display()
+        ));
+    }
+    // Opening the store brings the imported database up to date with the current
+    // migrations and assigns its records to the workspace
+    Store::new(store_path, workspace).with_context(|| {
+        format!(
+            "Failed to initialize hab-auto-build store at {}",
+            store_path.display()
+        )
+    })?;
+    Ok(summary)
+}
@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use tracing::{debug, warn};
+
+use crate::store::{self, DatabaseCheckError, Store};
+
+use super::{ArtifactCachePath, ArtifactContext, PackageSha256Sum, SourceContext};
+
+/// Summary of a rebuild of the store database
+#[derive(Debug, Default)]
+pub(crate) struct StoreRepairSummary {
+    /// Path the previous database was moved to, if there was one
+    pub backup_path: Option<PathBuf>,
+    /// Number of source archives indexed in the new database
+    pub sources: usize,
+    /// Number of artifacts indexed in the new database
+    pub artifacts: usize,
+    /// Number of source archives and artifacts that could not be read
+    pub failures: usize,
+}
+
+/// Rebuilds the database of the store at `store_path` if it is corrupted, or
+/// unconditionally if `force` is set. The previous database is kept next to the new
+/// one, which is populated by reading the downloaded sources and the artifact cache.
+/// Returns `None` if the database is healthy and `force` is not set.
+pub(crate) fn store_repair(
+    store_path: impl AsRef<Path>,
+    workspace: &str,
+    force: bool,
+) -> Result<Option<StoreRepairSummary>> {
+    let store_path = store_path.as_ref();
+    let database_path = store_path.join(store::DATABASE_FILE_NAME);
+    let mut summary = StoreRepairSummary::default();
+
+    if database_path.exists() {
+        match store::database_check(&database_path.to_string_lossy()) {
+            Ok(()) if !force => return Ok(None),
+            Ok(()) => {}
+            Err(DatabaseCheckError::Corrupted(_, problems)) => {
+                debug!("Repairing corrupted database: {}", problems);
+            }
+            Err(err @ DatabaseCheckError::Locked(_)) => {
+                return Err(eyre!(err)).with_suggestion(|| {
+                    "Make sure no other hab-auto-build process is using the store before repairing it"
+                })
+            }
+            Err(DatabaseCheckError::UnexpectedError(err)) => return Err(err),
+        }
+        let backup_path = store_path.join(format!(
+            "{}.{}.bak",
+            store::DATABASE_FILE_NAME,
+            Utc::now().format("%Y%m%d%H%M%S")
+        ));
+        // The write-ahead log and shared memory files belong to the previous database
+        // and must move along with it
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            let path = store_path.join(format!("{}{}", store::DATABASE_FILE_NAME, suffix));
+            if path.exists() {
+                let moved_path = PathBuf::from(format!("{}{}", backup_path.display(), suffix));
+                std::fs::rename(&path, &moved_path).with_context(|| {
+                    format!(
+                        "Failed to move '{}' to '{}'",
+                        path.display(),
+                        moved_path.display()
+                    )
+                })?;
+            }
+        }
+        summary.backup_path = Some(backup_path);
+    }
+
+    let store = Store::new(store_path, workspace).with_context(|| {
+        format!(
+            "Failed to initialize hab-auto-build store at {}",
+            store_path.display()
+        )
+    })?;
+    let mut connection = store.get_connection()?;
+
+    for source_path in store::store_entries_get(store.package_sources_path())? {
+        let Some(name) = source_path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let shasum = PackageSha256Sum::from(name.to_string());
+        let source_archive_path = store.package_source_store_path(&shasum).archive_data_path();
+        if !source_archive_path.as_ref().is_file() {
+            continue;
+        }
+        match SourceContext::read_from_disk(source_archive_path.as_ref(), Some(shasum.clone())) {
+            Ok(source_ctx) => {
+                store::source_context_put(&mut connection, &shasum, &source_ctx)?;
+                summary.sources += 1;
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to read source archive '{}': {:?}",
+                    source_archive_path.as_ref().display(),
+                    err
+                );
+                summary.failures += 1;
+            }
+        }
+    }
+
+    for artifact_path in store::store_entries_get(ArtifactCachePath::default())? {
+        if artifact_path.extension().and_then(|ext| ext.to_str()) != Some("hart") {
+            continue;
+        }
+        match ArtifactContext::read_from_disk(&artifact_path, None) {
+            Ok(artifact_ctx) => {
+                store::artifact_context_put(&mut connection, &artifact_ctx.hash, &artifact_ctx)?;
+                store::artifact_files_put(
+                    &mut connection,
+                    &artifact_ctx.hash,
+                    &artifact_ctx.files,
+                )?;
+                summary.artifacts += 1;
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to read artifact '{}': {:?}",
+                    artifact_path.display(),
+                    err
+                );
+                summary.failures += 1;
+            }
+        }
+    }
+    Ok(Some(summary))
+}
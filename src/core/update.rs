@@ -0,0 +1,288 @@
+use std::{cmp::Ordering, collections::BTreeSet, env, fmt::Display};
+
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::{
+    blocking::{Client, ClientBuilder},
+    header, Url,
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::PackageSourceURL;
+
+lazy_static! {
+    /// Stable version at the end of a release tag, like 'v1.2.3' or 'release-1.2'
+    static ref TAG_VERSION_REGEX: Regex = Regex::new(r"^\D*(\d+(?:\.\d+)*)$").unwrap();
+}
+
+/// Pattern matching stable versions in the files of a directory listing
+const VERSION_PATTERN: &str = r"(\d+(?:\.\d+)*)";
+
+/// Number of releases requested from each page of the GitHub API
+const GITHUB_PAGE_SIZE: usize = 100;
+
+/// Configures where newer upstream versions of a plan's source are looked for,
+/// in the 'update' table of the plan's `.hab-plan-config.toml`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub(crate) struct UpdateCheckConfig {
+    /// Page listing the available versions, defaults to the folder of the source url
+    pub url: Option<String>,
+    /// Pattern matching the versions on the page, the first capture group is the version
+    pub regex: Option<String>,
+}
+
+/// How the upstream versions of a plan's source are found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum UpdateCheckMethod {
+    /// Releases of a GitHub repository, or its tags if it has no releases
+    GitHubReleases { owner: String, repo: String },
+    /// Versions matched by a pattern in a page, like the directory listing
+    /// of the source archive's folder
+    Page { url: Url, regex: String },
+}
+
+impl Display for UpdateCheckMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateCheckMethod::GitHubReleases { owner, repo } => {
+                write!(f, "GitHub releases of {}/{}", owner, repo)
+            }
+            UpdateCheckMethod::Page { url, .. } => write!(f, "{}", url),
+        }
+    }
+}
+
+impl UpdateCheckMethod {
+    /// Picks how to look for newer versions of a source, the plan's configuration
+    /// takes precedence over GitHub releases, which take precedence over the
+    /// directory listing of the source's folder.
+    pub fn new(
+        source_url: &PackageSourceURL,
+        version: &str,
+        config: Option<&UpdateCheckConfig>,
+    ) -> Result<UpdateCheckMethod> {
+        let source_url = match source_url.git_source() {
+            Some(git_source) => Url::parse(&git_source.repo_url)?,
+            None => source_url.as_ref().clone(),
+        };
+        if let Some(regex) = config.and_then(|config| config.regex.as_ref()) {
+            Regex::new(regex).with_context(|| eyre!("Invalid update check regex '{}'", regex))?;
+            let url = match config.and_then(|config| config.url.as_ref()) {
+                Some(url) => {
+                    Url::parse(url).with_context(|| eyre!("Invalid update check url '{}'", url))?
+                }
+                None => source_url.join(".")?,
+            };
+            return Ok(UpdateCheckMethod::Page {
+                url,
+                regex: regex.clone(),
+            });
+        }
+        if source_url.host_str() == Some("github.com") {
+            let mut segments = source_url
+                .path_segments()
+                .into_iter()
+                .flatten()
+                .filter(|segment| !segment.is_empty());
+            if let (Some(owner), Some(repo)) = (segments.next(), segments.next()) {
+                return Ok(UpdateCheckMethod::GitHubReleases {
+                    owner: owner.to_string(),
+                    repo: repo.trim_end_matches(".git").to_string(),
+                });
+            }
+        }
+        if !matches!(source_url.scheme(), "http" | "https") {
+            return Err(eyre!(
+                "Cannot list the versions available at '{}'",
+                source_url
+            ))
+            .with_suggestion(|| {
+                "Set the 'url' and 'regex' of the 'update' table in the plan's .hab-plan-config.toml"
+            });
+        }
+        let filename = source_url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or_default();
+        let escaped_version = regex::escape(version);
+        let escaped_filename = regex::escape(filename);
+        if version.is_empty() || !escaped_filename.contains(&escaped_version) {
+            return Err(eyre!(
+                "The source archive name '{}' does not contain the version '{}'",
+                filename,
+                version
+            ))
+            .with_suggestion(|| {
+                "Set the 'url' and 'regex' of the 'update' table in the plan's .hab-plan-config.toml"
+            });
+        }
+        Ok(UpdateCheckMethod::Page {
+            url: source_url.join(".")?,
+            regex: escaped_filename.replace(&escaped_version, VERSION_PATTERN),
+        })
+    }
+}
+
+/// Looks for the versions of sources published upstream
+pub(crate) struct UpdateChecker {
+    client: Client,
+}
+
+impl UpdateChecker {
+    pub fn new() -> Result<UpdateChecker> {
+        let mut headers = header::HeaderMap::new();
+        headers.append(header::USER_AGENT, "hab-auto-build".parse().unwrap());
+        // Unauthenticated requests to the GitHub API are heavily rate limited
+        if let Ok(token) = env::var("GITHUB_TOKEN") {
+            headers.append(
+                header::AUTHORIZATION,
+                format!("Bearer {}", token)
+                    .parse()
+                    .context("Invalid GITHUB_TOKEN")?,
+            );
+        }
+        Ok(UpdateChecker {
+            client: ClientBuilder::new().default_headers(headers).build()?,
+        })
+    }
+
+    /// Lists the stable versions published upstream
+    pub fn versions(&self, method: &UpdateCheckMethod) -> Result<BTreeSet<String>> {
+        match method {
+            UpdateCheckMethod::GitHubReleases { owner, repo } => {
+                let releases = self.github_get(&format!(
+                    "repos/{}/{}/releases?per_page={}",
+                    owner, repo, GITHUB_PAGE_SIZE
+                ))?;
+                let mut tag_names = releases
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter(|release| {
+                        !release["draft"].as_bool().unwrap_or_default()
+                            && !release["prerelease"].as_bool().unwrap_or_default()
+                    })
+                    .filter_map(|release| release["tag_name"].as_str().map(String::from))
+                    .collect::<Vec<_>>();
+                if tag_names.is_empty() {
+                    let tags = self.github_get(&format!(
+                        "repos/{}/{}/tags?per_page={}",
+                        owner, repo, GITHUB_PAGE_SIZE
+                    ))?;
+                    tag_names = tags
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|tag| tag["name"].as_str().map(String::from))
+                        .collect();
+                }
+                Ok(tag_names
+                    .iter()
+                    .filter_map(|tag_name| TAG_VERSION_REGEX.captures(tag_name))
+                    .map(|captures| captures[1].to_string())
+                    .collect())
+            }
+            UpdateCheckMethod::Page { url, regex } => {
+                let regex = Regex::new(regex)?;
+                let page = self.get(url.as_str())?;
+                Ok(regex
+                    .captures_iter(&page)
+                    .filter_map(|captures| captures.get(1))
+                    .map(|version| version.as_str().to_string())
+                    .collect())
+            }
+        }
+    }
+
+    fn github_get(&self, path: &str) -> Result<serde_json::Value> {
+        let response = self.get(&format!("https://api.github.com/{}", path))?;
+        serde_json::from_str(&response)
+            .with_context(|| eyre!("Invalid response from the GitHub API for '{}'", path))
+    }
+
+    fn get(&self, url: &str) -> Result<String> {
+        debug!("Fetching upstream versions from {}", url);
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .with_context(|| eyre!("Failed to fetch '{}'", url))?;
+        if !response.status().is_success() {
+            return Err(eyre!(
+                "Failed to fetch '{}', the server responded with {}",
+                url,
+                response.status()
+            ));
+        }
+        response
+            .text()
+            .with_context(|| eyre!("Failed to read the response from '{}'", url))
+    }
+}
+
+/// Finds the most recent version that is newer than the current one
+pub(crate) fn latest_version<'a>(
+    current_version: &str,
+    versions: impl IntoIterator<Item = &'a String>,
+) -> Option<&'a String> {
+    versions
+        .into_iter()
+        .filter(|version| version_cmp(version, current_version) == Ordering::Greater)
+        .max_by(|a, b| version_cmp(a, b))
+}
+
+/// Compares versions by their numeric and alphabetic parts, numbers are compared
+/// by value so that '1.10' is newer than '1.9'.
+pub(crate) fn version_cmp(a: &str, b: &str) -> Ordering {
+    fn parts(version: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let mut start = None;
+        let mut is_numeric = false;
+        for (index, char) in version.char_indices() {
+            if !char.is_ascii_alphanumeric() {
+                if let Some(start) = start.take() {
+                    parts.push(&version[start..index]);
+                }
+                continue;
+            }
+            match start {
+                Some(part_start) if char.is_ascii_digit() != is_numeric => {
+                    parts.push(&version[part_start..index]);
+                    start = Some(index);
+                }
+                Some(_) => {}
+                None => start = Some(index),
+            }
+            is_numeric = char.is_ascii_digit();
+        }
+        if let Some(start) = start {
+            parts.push(&version[start..]);
+        }
+        parts
+    }
+    let (a_parts, b_parts) = (parts(a), parts(b));
+    for (a_part, b_part) in a_parts.iter().zip(b_parts.iter()) {
+        let ordering = match (a_part.parse::<u64>(), b_part.parse::<u64>()) {
+            (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number),
+            // Numbers are newer than letters, so that '1.0' is newer than '1.0rc1'
+            (Ok(_), Err(_)) => Ordering::Greater,
+            (Err(_), Ok(_)) => Ordering::Less,
+            (Err(_), Err(_)) => a_part.cmp(b_part),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    let common_len = a_parts.len().min(b_parts.len());
+    match (a_parts.get(common_len), b_parts.get(common_len)) {
+        // A version followed by letters is a pre-release of it
+        (Some(a_part), None) if a_part.parse::<u64>().is_err() => Ordering::Less,
+        (None, Some(b_part)) if b_part.parse::<u64>().is_err() => Ordering::Greater,
+        _ => a_parts.len().cmp(&b_parts.len()),
+    }
+}
@@ -3,7 +3,7 @@ mod cli;
 mod core;
 mod store;
 
-use cli::Cli;
+use cli::{Cli, UserOutput};
 use color_eyre::eyre::Result;
 use tracing::Level;
 use tracing_subscriber::{
@@ -18,6 +18,7 @@ fn main() -> Result<()> {
             metadata.target() != "user-ui" && metadata.target() != "user-log"
         }));
     let user_ui_layer = fmt::layer()
+        .with_writer(UserOutput)
         .with_target(false)
         .with_level(false)
         .without_time()
@@ -25,6 +26,7 @@ fn main() -> Result<()> {
             metadata.target() == "user-ui" && *metadata.level() == Level::INFO
         }));
     let user_log_layer = fmt::layer()
+        .with_writer(UserOutput)
         .with_target(false)
         .with_level(true)
         .without_time()
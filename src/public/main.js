@@ -43,6 +43,7 @@ const fn = (async () => {
                 default:
                     break;
             }
+            node.status = null;
             nodes.push(node);
             nodeById.set(parseInt(node_index), node);
         }
@@ -87,9 +88,15 @@ const fn = (async () => {
         }
         console.log(nodes);
         console.log(links);
-        return { nodes, links };
+        return { nodes, links, nodeById };
     })();
 
+    const statusColors = {
+        "pending": "#999",
+        "building": "#e69500",
+        "failed": "#d62728",
+        "built": "#2ca02c"
+    };
     const color = d3.scaleOrdinal(graph.nodes.map(d => d.degree).sort(d3.ascending), d3.schemeCategory10);
     const y = d3.scalePoint(graph.nodes.map(d => d.id).sort(d3.ascending), [margin.top, height - margin.bottom]).domain(graph.nodes.sort((a, b) => a.degree - b.degree).map(d => d.id));
     const chart = (() => {
@@ -148,7 +155,9 @@ const fn = (async () => {
                 .text(d => `${d.id}-${d.degree}`))
             .call(g => g.append("circle")
                 .attr("r", 3)
-                .attr("fill", d => color(d.degree)));
+                .attr("fill", d => color(d.degree)))
+            .call(g => g.append("title")
+                .text(d => d.id));
 
         const path = svg.insert("g", "*")
             .attr("fill", "none")
@@ -197,6 +206,29 @@ const fn = (async () => {
                 // feedbackPath.classed("primary-feedback-source", false).order();
                 // feedbackPath.classed("primary-feedback-target", false).order();
             });
+        // Follow the progress of the current build session
+        const liveUrl = `${window.location.protocol === "https:" ? "wss:" : "ws:"}//${window.location.host}/api/graph/live`;
+        const socket = new WebSocket(liveUrl);
+        socket.onmessage = (event) => {
+            const message = JSON.parse(event.data);
+            if (message.reset) {
+                for (const node of graph.nodes) {
+                    node.status = null;
+                }
+            }
+            for (const node_index in message.nodes) {
+                const node = graph.nodeById.get(parseInt(node_index));
+                if (node) {
+                    node.status = message.nodes[node_index];
+                }
+            }
+            label.select("circle")
+                .attr("r", d => d.status == null ? 3 : 4)
+                .attr("fill", d => d.status == null ? color(d.degree) : statusColors[d.status]);
+            label.select("title")
+                .text(d => d.status == null ? d.id : `${d.id} (${d.status})`);
+        };
+
         return svg.node();
     })();
 })
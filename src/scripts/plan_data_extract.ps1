@@ -66,6 +66,15 @@ $build_deps = ConvertTo-Array $pkg_build_deps
 $licensesJson = if ($licenses.Count -gt 0) { "[" + '"{0}"' -f ($licenses -join '", "') + "]"  } else { "[]" }
 $depsJson = if ($deps.Count -gt 0) { "[" + '"{0}"' -f ($deps -join '", "') + "]" } else { "[]" }
 $buildDepsJson = if ($build_deps.Count -gt 0) { "[" + '"{0}"' -f ($build_deps -join '", "') + "]" } else { "[]" }
+function ConvertTo-JsonObject($var) {
+    if ($var -is [hashtable] -and $var.Count -gt 0) {
+        return "{" + (($var.GetEnumerator() | ForEach-Object { '"{0}": "{1}"' -f $_.Key, $_.Value }) -join ", ") + "}"
+    }
+    return "{}"
+}
+$exportsJson = ConvertTo-JsonObject $pkg_exports
+$bindsJson = ConvertTo-JsonObject $pkg_binds
+$bindsOptionalJson = ConvertTo-JsonObject $pkg_binds_optional
 
 # Construct the source block conditionally based on whether $pkg_shasum is empty or not
 if (-not [string]::IsNullOrWhiteSpace($pkg_shasum)) {
@@ -94,6 +103,9 @@ $jsonOutput = @"
     "version": "$_version",$sourceJson
     "licenses": $licensesJson,
     "scaffolding_dep": null,
+    "exports": $exportsJson,
+    "binds": $bindsJson,
+    "binds_optional": $bindsOptionalJson,
     "deps": $depsJson,
     "build_deps": $buildDepsJson
 }
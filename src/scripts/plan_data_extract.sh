@@ -28,6 +28,21 @@ json_array() {
   echo ']'
 }
 
+# Prints the associative array with the given name as a JSON object
+json_object() {
+  local name="$1" i key value keys=()
+  eval "keys=(\"\${!${name}[@]}\")"
+  echo -n '{'
+  for ((i = 0; i < ${#keys[@]}; i++)); do
+    key=${keys[$i]//\\/\\\\}
+    eval "value=\"\${${name}[\${keys[\$i]}]}\""
+    value=${value//\\/\\\\}
+    echo -n \""${key//\"/\\\"}"\": \""${value//\"/\\\"}"\"
+    [ $i -lt $((${#keys[@]} - 1)) ] && echo -n ', '
+  done
+  echo '}'
+}
+
 # Output data as json
 echo "{ \
 \"origin\": \"${pkg_origin}\", \
@@ -53,6 +68,9 @@ else
   echo "\"scaffolding_dep\": null,"
 fi
 
-echo "\"deps\": $(json_array "${pkg_deps[@]}"), \
+echo "\"exports\": $(json_object pkg_exports), \
+\"binds\": $(json_object pkg_binds), \
+\"binds_optional\": $(json_object pkg_binds_optional), \
+\"deps\": $(json_array "${pkg_deps[@]}"), \
 \"build_deps\": $(json_array "${pkg_build_deps[@]}")\
 }"
@@ -0,0 +1,155 @@
+use std::{
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::core::{host_name, process_is_running};
+
+const STORE_LOCK_FILE_NAME: &str = "hab-auto-build.lock";
+const STORE_LOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Number of polls after which a lock file that still cannot be read is considered
+/// to have been left behind by a process that crashed while writing it
+const STORE_LOCK_UNREADABLE_POLL_LIMIT: usize = 5;
+
+/// Owner of a store lock, as recorded in the lock file
+#[derive(Debug, Serialize, Deserialize)]
+struct StoreLockOwner {
+    pid: u32,
+    host: String,
+    command: String,
+    acquired_at: DateTime<Utc>,
+}
+
+impl StoreLockOwner {
+    fn current(command: &str) -> StoreLockOwner {
+        StoreLockOwner {
+            pid: std::process::id(),
+            host: host_name().unwrap_or_else(|| "localhost".to_string()),
+            command: command.to_string(),
+            acquired_at: Utc::now(),
+        }
+    }
+
+    /// A lock is stale if the process that acquired it is no longer running, this
+    /// can only be determined for locks acquired on the current host.
+    fn is_stale(&self) -> bool {
+        self.host == host_name().unwrap_or_else(|| "localhost".to_string())
+            && !process_is_running(self.pid)
+    }
+}
+
+/// Advisory lock that prevents hab-auto-build processes from modifying the same
+/// store at the same time. The lock is released when it is dropped.
+#[derive(Debug)]
+pub(crate) struct StoreLock {
+    path: PathBuf,
+}
+
+impl StoreLock {
+    /// Acquires the lock of the store at `store_path` for `command`. If another process
+    /// holds the lock, this waits for it to be released if `wait` is set, or fails.
+    pub fn acquire(store_path: impl AsRef<Path>, command: &str, wait: bool) -> Result<StoreLock> {
+        let store_path = store_path.as_ref();
+        std::fs::create_dir_all(store_path)?;
+        let path = store_path.join(STORE_LOCK_FILE_NAME);
+        let mut is_waiting = false;
+        let mut unreadable_polls = 0;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    serde_json::to_writer(&mut file, &StoreLockOwner::current(command))?;
+                    file.flush()?;
+                    debug!("Acquired store lock '{}'", path.display());
+                    return Ok(StoreLock { path });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+                Err(err) => {
+                    return Err(eyre!(err)).with_context(|| {
+                        format!("Failed to create store lock '{}'", path.display())
+                    })
+                }
+            }
+            // The lock file may be empty or partially written if its owner is still
+            // writing it, in which case we try again later
+            let owner = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|data| serde_json::from_str::<StoreLockOwner>(&data).ok());
+            let is_stale = match owner.as_ref() {
+                Some(owner) => owner.is_stale(),
+                None => {
+                    unreadable_polls += 1;
+                    unreadable_polls > STORE_LOCK_UNREADABLE_POLL_LIMIT
+                }
+            };
+            if is_stale {
+                warn!(
+                    "Removing stale store lock '{}' left by a process which is no longer running",
+                    path.display()
+                );
+                if let Err(err) = std::fs::remove_file(&path) {
+                    if err.kind() != ErrorKind::NotFound {
+                        return Err(eyre!(err)).with_context(|| {
+                            format!("Failed to remove stale store lock '{}'", path.display())
+                        });
+                    }
+                }
+                unreadable_polls = 0;
+                continue;
+            }
+            if let Some(owner) = owner {
+                unreadable_polls = 0;
+                if !wait {
+                    return Err(eyre!(
+                        "The hab-auto-build store at '{}' is in use by '{}' (process {} on {}) since {}",
+                        store_path.display(),
+                        owner.command,
+                        owner.pid,
+                        owner.host,
+                        owner.acquired_at.to_rfc3339()
+                    ))
+                    .with_suggestion(|| {
+                        format!(
+                            "Use '--wait' to wait for it to finish, or remove '{}' if that process is no longer running",
+                            path.display()
+                        )
+                    });
+                }
+                if !is_waiting {
+                    info!(target: "user-log",
+                        "Waiting for '{}' (process {} on {}) to release the hab-auto-build store",
+                        owner.command.white(),
+                        owner.pid,
+                        owner.host
+                    );
+                    is_waiting = true;
+                }
+            }
+            std::thread::sleep(STORE_LOCK_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Failed to release store lock '{}': {}",
+                self.path.display(),
+                err
+            );
+        } else {
+            debug!("Released store lock '{}'", self.path.display());
+        }
+    }
+}
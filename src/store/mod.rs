@@ -1,36 +1,61 @@
+mod lock;
 pub mod model;
 pub mod schema;
 
+pub(crate) use lock::StoreLock;
+
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
 };
 
 use crate::{
     core::{
-        ArtifactContext, Blake3, InnerArtifactContext, PackageBuildIdent, PackageSha256Sum,
-        PackageSource, PlanContextPath, SourceContext,
+        ArtifactContext, ArtifactFile, Blake3, BuildHostMetadata, InnerArtifactContext,
+        PackageBuildIdent, PackageIdent, PackageSha256Sum, PackageSourceURL, PlanContextPath,
+        SourceContext, ARTIFACT_CONTEXT_VERSION,
     },
     store::model::SourceContextRecord,
 };
 
-use self::model::{ArtifactContextRecord, BuildTimeRecord, FileModificationRecord};
+use self::model::{
+    ArtifactContextRecord, BuildClaimRecord, BuildHostRecord, BuildSessionArtifactRecord,
+    BuildSessionRecord, BuildStepStateRecord, BuildTimeRecord, FileModificationRecord,
+    IntegrityCheckRecord, PlanContextHashRecord, PlanScanRecord, SourceDownloadUrlRecord,
+    YankedArtifactRecord,
+};
 use chrono::{DateTime, NaiveDateTime, Utc};
-use color_eyre::eyre::{Context, Result};
+use color_eyre::{
+    eyre::{eyre, Context, Result},
+    Help,
+};
 
 use diesel::{
+    connection::SimpleConnection,
     delete, insert_into,
     prelude::*,
-    r2d2::{ConnectionManager, Pool, PooledConnection},
-    update,
+    r2d2::{ConnectionManager, CustomizeConnection, Pool, PooledConnection},
+    replace_into, update,
 };
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use lazy_static::__Deref;
+use serde::{Deserialize, Serialize};
 use tempdir::TempDir;
-use tracing::trace;
+use thiserror::Error;
+use tracing::{debug, trace, warn};
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.9f";
+pub const DATABASE_FILE_NAME: &str = "hab-auto-build.sqlite";
+/// Time in milliseconds a connection waits for another hab-auto-build process to
+/// release its lock on the database before giving up
+const DATABASE_BUSY_TIMEOUT_MS: u32 = 30_000;
+/// Maximum number of problems reported by the integrity check of the database
+const DATABASE_INTEGRITY_CHECK_MAX_ERRORS: u32 = 10;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub(crate) struct StorePath(PathBuf);
@@ -92,6 +117,51 @@ impl AsRef<Path> for PackageBuildFailureLogsStorePath {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub(crate) struct PackageSourcesStorePath(PathBuf);
+
+impl AsRef<Path> for PackageSourcesStorePath {
+    fn as_ref(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub(crate) struct GitReposStorePath(PathBuf);
+
+impl AsRef<Path> for GitReposStorePath {
+    fn as_ref(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub(crate) struct RepoClonesStorePath(PathBuf);
+
+impl AsRef<Path> for RepoClonesStorePath {
+    fn as_ref(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub(crate) struct RepoWorktreesStorePath(PathBuf);
+
+impl AsRef<Path> for RepoWorktreesStorePath {
+    fn as_ref(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub(crate) struct InvalidPackageSourcesStorePath(PathBuf);
+
+impl AsRef<Path> for InvalidPackageSourcesStorePath {
+    fn as_ref(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub(crate) struct PackageSourceStorePath(PathBuf);
 
@@ -138,36 +208,85 @@ impl AsRef<Path> for PackageSourceLicenseStorePath {
 #[derive(Debug, Clone)]
 pub(crate) struct Store {
     path: StorePath,
+    /// Identifier of the workspace using the store, records that are specific to a
+    /// checkout of the plans are kept separate for each workspace sharing the store
+    workspace: String,
     pool: Pool<ConnectionManager<SqliteConnection>>,
 }
 
 impl Store {
-    pub fn new(path: impl AsRef<Path>) -> Result<Store> {
+    pub fn new(path: impl AsRef<Path>, workspace: &str) -> Result<Store> {
         std::fs::create_dir_all(path.as_ref())?;
         let db_url = path
             .as_ref()
-            .join("hab-auto-build.sqlite")
+            .join(DATABASE_FILE_NAME)
             .to_str()
             .unwrap()
             .to_string();
 
+        match database_check(&db_url) {
+            Ok(()) => {}
+            Err(err @ DatabaseCheckError::Locked(_)) => {
+                return Err(eyre!(err)).with_suggestion(|| {
+                    "Make sure no other hab-auto-build process is using the store, or wait for it to finish"
+                })
+            }
+            Err(err @ DatabaseCheckError::Corrupted(..)) => {
+                return Err(eyre!(err)).with_suggestion(|| {
+                    "Run 'hab-auto-build store repair' to rebuild the database from the artifact cache and the downloaded sources"
+                })
+            }
+            Err(DatabaseCheckError::UnexpectedError(err)) => return Err(err),
+        }
+
         let manager = ConnectionManager::<SqliteConnection>::new(db_url);
         // Refer to the `r2d2` documentation for more methods to use
         // when building a connection pool
         let pool = Pool::builder()
             .max_size(1)
             .test_on_check_out(true)
+            .connection_customizer(Box::new(ConnectionOptions))
             .build(manager)?;
         let mut connection = pool.get()?;
         connection
             .run_pending_migrations(MIGRATIONS)
             .expect("Failed to run migration");
+        connection.transaction(|connection| workspace_records_adopt(connection, workspace))?;
         Ok(Store {
             path: StorePath(path.as_ref().to_path_buf()),
+            workspace: workspace.to_string(),
             pool,
         })
     }
 
+    pub fn workspace(&self) -> &str {
+        &self.workspace
+    }
+
+    /// Writes a consistent copy of the database to `snapshot_path` that only contains
+    /// the records of the current workspace, detached so that they are adopted by the
+    /// first workspace that opens a store built from the snapshot.
+    pub fn database_snapshot(&self, snapshot_path: impl AsRef<Path>) -> Result<()> {
+        let snapshot_path = snapshot_path.as_ref();
+        let snapshot_url = snapshot_path.to_str().ok_or_else(|| {
+            eyre!(
+                "Invalid database snapshot path '{}'",
+                snapshot_path.display()
+            )
+        })?;
+        diesel::sql_query("VACUUM INTO ?")
+            .bind::<diesel::sql_types::Text, _>(snapshot_url)
+            .execute(&mut self.get_connection()?)
+            .with_context(|| {
+                format!(
+                    "Failed to write snapshot of hab-auto-build database to '{}'",
+                    snapshot_path.display()
+                )
+            })?;
+        let mut connection = SqliteConnection::establish(snapshot_url)?;
+        connection.transaction(|connection| workspace_records_detach(connection, &self.workspace))
+    }
+
     pub fn temp_dir_path(&self) -> TempDirStorePath {
         TempDirStorePath(self.path.as_ref().join("tmp"))
     }
@@ -198,30 +317,236 @@ impl Store {
         PackageBuildFailureLogsStorePath(self.path.as_ref().join("build-failure-logs"))
     }
 
-    pub fn package_source_store_path(&self, source: &PackageSource) -> PackageSourceStorePath {
-        PackageSourceStorePath(
+    pub fn package_sources_path(&self) -> PackageSourcesStorePath {
+        PackageSourcesStorePath(self.path.as_ref().join("sources"))
+    }
+    /// Folder containing bare clones of the repositories of git sources
+    pub fn git_repos_path(&self) -> GitReposStorePath {
+        GitReposStorePath(self.path.as_ref().join("git-repos"))
+    }
+    /// Folder containing the clones of the workspace's repos that are configured
+    /// with a git remote instead of a local folder
+    pub fn repo_clones_path(&self) -> RepoClonesStorePath {
+        RepoClonesStorePath(
             self.path
                 .as_ref()
-                .join("sources")
-                .join(source.shasum.to_string()),
+                .join("repos")
+                .join(self.workspace.as_str()),
+        )
+    }
+    /// Folder containing the git worktrees in which other commits of the
+    /// workspace's repos are checked out
+    pub fn repo_worktrees_path(&self) -> RepoWorktreesStorePath {
+        RepoWorktreesStorePath(
+            self.path
+                .as_ref()
+                .join("worktrees")
+                .join(self.workspace.as_str()),
+        )
+    }
+    pub fn invalid_package_sources_path(&self) -> InvalidPackageSourcesStorePath {
+        InvalidPackageSourcesStorePath(self.path.as_ref().join("invalid-sources"))
+    }
+
+    pub fn package_source_store_path(&self, shasum: &PackageSha256Sum) -> PackageSourceStorePath {
+        PackageSourceStorePath(
+            self.package_sources_path()
+                .as_ref()
+                .join(shasum.to_string()),
         )
     }
     pub fn invalid_source_store_path(
         &self,
-        source: &PackageSource,
+        shasum: &PackageSha256Sum,
     ) -> InvalidPackageSourceStorePath {
         InvalidPackageSourceStorePath(
-            self.path
+            self.invalid_package_sources_path()
                 .as_ref()
-                .join("invalid-sources")
-                .join(source.shasum.to_string()),
+                .join(shasum.to_string()),
+        )
+    }
+}
+
+/// Settings applied to every connection to the database. The write-ahead log lets
+/// other hab-auto-build processes read the database while one of them writes to it,
+/// and the busy timeout makes them wait for each other instead of failing.
+#[derive(Debug)]
+struct ConnectionOptions;
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        connection
+            .batch_execute(&format!(
+                "PRAGMA busy_timeout = {}; PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;",
+                DATABASE_BUSY_TIMEOUT_MS
+            ))
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum DatabaseCheckError {
+    #[error("The hab-auto-build database at '{0}' is locked by another process")]
+    Locked(String),
+    #[error("The hab-auto-build database at '{0}' is corrupted: {1}")]
+    Corrupted(String, String),
+    #[error("Encountered an unexpected error while checking the hab-auto-build database")]
+    UnexpectedError(#[from] color_eyre::eyre::Error),
+}
+
+/// Opens the database at `db_url` on its own and checks its integrity, waiting for
+/// other processes to release their locks on it first.
+pub(crate) fn database_check(db_url: &str) -> Result<(), DatabaseCheckError> {
+    let database_error = |err: diesel::result::Error| match err {
+        diesel::result::Error::DatabaseError(_, ref info)
+            if info.message().contains("locked") || info.message().contains("busy") =>
+        {
+            DatabaseCheckError::Locked(db_url.to_string())
+        }
+        diesel::result::Error::DatabaseError(_, info) => {
+            DatabaseCheckError::Corrupted(db_url.to_string(), info.message().to_string())
+        }
+        err => DatabaseCheckError::UnexpectedError(eyre!(err)),
+    };
+    let mut connection = SqliteConnection::establish(db_url).map_err(|err| {
+        DatabaseCheckError::UnexpectedError(eyre!(err).wrap_err(format!(
+            "Failed to open hab-auto-build database at '{}'",
+            db_url
+        )))
+    })?;
+    connection
+        .batch_execute(&format!(
+            "PRAGMA busy_timeout = {};",
+            DATABASE_BUSY_TIMEOUT_MS
+        ))
+        .map_err(database_error)?;
+    let problems = diesel::sql_query(format!(
+        "PRAGMA integrity_check({})",
+        DATABASE_INTEGRITY_CHECK_MAX_ERRORS
+    ))
+    .load::<IntegrityCheckRecord>(&mut connection)
+    .map_err(database_error)?
+    .into_iter()
+    .map(|record| record.integrity_check)
+    .filter(|result| result != "ok")
+    .collect::<Vec<_>>();
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(DatabaseCheckError::Corrupted(
+            db_url.to_string(),
+            problems.join(", "),
+        ))
+    }
+}
+
+/// Lists the immediate children of a folder in the store, returning an empty
+/// list if the folder does not exist yet.
+pub(crate) fn store_entries_get(store_path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let store_path = store_path.as_ref();
+    if !store_path.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(store_path)
+        .with_context(|| format!("Failed to read store folder '{}'", store_path.display()))?
+    {
+        entries.push(entry?.path());
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Computes the total size of a file or folder in the store, without following symlinks.
+pub(crate) fn store_entry_size(store_entry_path: impl AsRef<Path>) -> Result<u64> {
+    let metadata = std::fs::symlink_metadata(store_entry_path.as_ref())?;
+    if metadata.is_dir() {
+        let mut size = 0;
+        for entry in std::fs::read_dir(store_entry_path.as_ref())? {
+            size += store_entry_size(entry?.path())?;
+        }
+        Ok(size)
+    } else {
+        Ok(metadata.len())
+    }
+}
+
+/// Removes a file or folder from the store, symlinks are removed without touching their targets.
+pub(crate) fn store_entry_delete(store_entry_path: impl AsRef<Path>) -> Result<()> {
+    let store_entry_path = store_entry_path.as_ref();
+    let metadata = std::fs::symlink_metadata(store_entry_path)?;
+    if metadata.is_dir() {
+        std::fs::remove_dir_all(store_entry_path)
+    } else {
+        std::fs::remove_file(store_entry_path)
+    }
+    .with_context(|| {
+        format!(
+            "Failed to remove '{}' from hab-auto-build store",
+            store_entry_path.display()
         )
+    })
+}
+
+/// Assigns the records created before stores were shared between workspaces to
+/// the first workspace that uses the store.
+fn workspace_records_adopt(connection: &mut SqliteConnection, workspace_value: &str) -> Result<()> {
+    macro_rules! adopt {
+        ($table:ident) => {{
+            use crate::store::schema::$table::dsl::*;
+            update($table.filter(workspace.eq("")))
+                .set(workspace.eq(workspace_value))
+                .execute(connection)?
+        }};
+    }
+    let adopted = adopt!(file_modifications)
+        + adopt!(build_times)
+        + adopt!(build_disk_usages)
+        + adopt!(build_sessions)
+        + adopt!(build_session_artifacts)
+        + adopt!(build_step_states);
+    if adopted > 0 {
+        debug!(
+            "Assigned {} records without a workspace to workspace '{}'",
+            adopted, workspace_value
+        );
+    }
+    Ok(())
+}
+
+/// Removes the records of other workspaces and the current build claims, and clears
+/// the workspace of the remaining records so that they can be adopted by another one.
+fn workspace_records_detach(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+) -> Result<()> {
+    macro_rules! detach {
+        ($table:ident) => {{
+            use crate::store::schema::$table::dsl::*;
+            delete($table.filter(workspace.ne(workspace_value))).execute(connection)?;
+            update($table).set(workspace.eq("")).execute(connection)?
+        }};
     }
+    let detached = detach!(file_modifications)
+        + detach!(build_times)
+        + detach!(build_disk_usages)
+        + detach!(build_sessions)
+        + detach!(build_session_artifacts)
+        + detach!(build_step_states);
+    delete(crate::store::schema::build_claims::table).execute(connection)?;
+    debug!(
+        "Detached {} records from workspace '{}'",
+        detached, workspace_value
+    );
+    Ok(())
 }
 
 type PathMap = HashMap<PathBuf, (DateTime<Utc>, DateTime<Utc>)>;
 
-pub(crate) struct ModificationIndex(HashMap<PathBuf, PathMap>);
+pub(crate) struct ModificationIndex {
+    files: HashMap<PathBuf, PathMap>,
+    plan_context_hashes: HashMap<String, Blake3>,
+}
 
 impl ModificationIndex {
     pub(crate) fn file_alternate_modified_at_get(
@@ -230,7 +555,7 @@ impl ModificationIndex {
         file_path_value: impl AsRef<Path>,
         real_modified_at_value: DateTime<Utc>,
     ) -> Option<DateTime<Utc>> {
-        self.0
+        self.files
             .get(plan_context_path_value.as_ref())
             .and_then(|m| m.get(file_path_value.as_ref()))
             .and_then(|(real_modified_at, alternate_modified_at)| {
@@ -241,14 +566,26 @@ impl ModificationIndex {
                 }
             })
     }
+
+    pub(crate) fn plan_context_hash_get(
+        &self,
+        artifact_ident_value: &PackageIdent,
+    ) -> Option<Blake3> {
+        self.plan_context_hashes
+            .get(&artifact_ident_value.to_string())
+            .cloned()
+    }
 }
 
 pub(crate) fn files_alternate_modified_at_get_full_index(
     connection: &mut SqliteConnection,
+    workspace_value: &str,
 ) -> Result<ModificationIndex> {
     use crate::store::schema::file_modifications::dsl::*;
     let mut results: HashMap<PathBuf, PathMap> = HashMap::new();
-    let rows = file_modifications.load::<FileModificationRecord>(connection)?;
+    let rows = file_modifications
+        .filter(workspace.eq(workspace_value))
+        .load::<FileModificationRecord>(connection)?;
     for row in rows {
         results
             .entry(PathBuf::from(row.plan_context_path))
@@ -266,182 +603,1297 @@ pub(crate) fn files_alternate_modified_at_get_full_index(
                 ),
             ));
     }
-    Ok(ModificationIndex(results))
+    let plan_context_hashes = {
+        use crate::store::schema::plan_context_hashes::dsl::*;
+        plan_context_hashes
+            .load::<PlanContextHashRecord>(connection)?
+            .into_iter()
+            .map(|row| (row.artifact_ident, Blake3::from(row.hash)))
+            .collect()
+    };
+    Ok(ModificationIndex {
+        files: results,
+        plan_context_hashes,
+    })
+}
+
+/// Plan data extracted by earlier scans, indexed by plan file path along with the
+/// hash of the plan files it was extracted from. Plan scanners record the data of
+/// the plans they had to read again, which is saved once the scan is complete.
+pub(crate) struct PlanScanIndex {
+    scans: HashMap<PathBuf, (String, String)>,
+    updates: Mutex<Vec<(PathBuf, String, String)>>,
+}
+
+impl PlanScanIndex {
+    pub(crate) fn plan_scan_get(&self, plan_path: impl AsRef<Path>, hash: &str) -> Option<&str> {
+        self.scans
+            .get(plan_path.as_ref())
+            .filter(|(scan_hash, _)| scan_hash == hash)
+            .map(|(_, data)| data.as_str())
+    }
+
+    pub(crate) fn plan_scan_put(&self, plan_path: impl AsRef<Path>, hash: String, data: String) {
+        self.updates
+            .lock()
+            .unwrap()
+            .push((plan_path.as_ref().to_path_buf(), hash, data));
+    }
+}
+
+pub(crate) fn plan_scans_get_full_index(
+    connection: &mut SqliteConnection,
+) -> Result<PlanScanIndex> {
+    use crate::store::schema::plan_scans::dsl::*;
+    let scans = plan_scans
+        .load::<PlanScanRecord>(connection)?
+        .into_iter()
+        .map(|row| (PathBuf::from(row.plan_path), (row.hash, row.data)))
+        .collect();
+    Ok(PlanScanIndex {
+        scans,
+        updates: Mutex::new(Vec::new()),
+    })
+}
+
+/// Saves the plan data recorded in the index since it was loaded, returning the
+/// number of plans that were saved.
+pub(crate) fn plan_scans_put(
+    connection: &mut SqliteConnection,
+    plan_scan_index: &PlanScanIndex,
+) -> Result<usize> {
+    use crate::store::schema::plan_scans::dsl::*;
+    let updates = std::mem::take(&mut *plan_scan_index.updates.lock().unwrap());
+    for (plan_path_value, hash_value, data_value) in updates.iter() {
+        replace_into(plan_scans)
+            .values((
+                plan_path.eq(plan_path_value.to_string_lossy().to_string()),
+                hash.eq(hash_value),
+                data.eq(data_value),
+            ))
+            .execute(connection)?;
+    }
+    Ok(updates.len())
 }
 
 pub(crate) fn build_time_get(
     connection: &mut SqliteConnection,
+    workspace_value: &str,
     build_ident_value: &PackageBuildIdent,
 ) -> Result<Option<BuildTimeRecord>> {
     use crate::store::schema::build_times::dsl::*;
     Ok(build_times
+        .filter(workspace.eq(workspace_value))
         .filter(build_ident.eq(build_ident_value.to_string()))
         .load::<BuildTimeRecord>(connection)?
         .pop())
 }
 
+/// Returns the most recent build time of every plan that was built
+pub(crate) fn build_times_get_all(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+) -> Result<Vec<BuildTimeRecord>> {
+    use crate::store::schema::build_times::dsl::*;
+    Ok(build_times
+        .filter(workspace.eq(workspace_value))
+        .load::<BuildTimeRecord>(connection)?)
+}
+
 pub(crate) fn build_time_put(
     connection: &mut SqliteConnection,
+    workspace_value: &str,
     build_ident_value: &PackageBuildIdent,
     build_duration_in_secs_value: i32,
 ) -> Result<()> {
     use crate::store::schema::build_times::dsl::*;
     if build_times
+        .filter(workspace.eq(workspace_value))
         .filter(build_ident.eq(build_ident_value.to_string()))
         .load::<BuildTimeRecord>(connection)?
-        .first()
-        .is_none()
+        .is_empty()
     {
         insert_into(build_times)
             .values((
+                workspace.eq(workspace_value),
                 build_ident.eq(build_ident_value.to_string()),
                 duration_in_secs.eq(build_duration_in_secs_value),
             ))
             .execute(connection)?;
     } else {
-        update(build_times.filter(build_ident.eq(build_ident_value.to_string())))
-            .set(duration_in_secs.eq(build_duration_in_secs_value))
-            .execute(connection)?;
+        update(
+            build_times
+                .filter(workspace.eq(workspace_value))
+                .filter(build_ident.eq(build_ident_value.to_string())),
+        )
+        .set(duration_in_secs.eq(build_duration_in_secs_value))
+        .execute(connection)?;
     }
     Ok(())
 }
 
-pub(crate) fn source_context_get(
+/// Returns the disk space used by the latest build of every package that was
+/// built, keyed by the package as 'origin/name/target'
+pub(crate) fn build_disk_usages_get_all(
     connection: &mut SqliteConnection,
-    hash_value: &PackageSha256Sum,
-) -> Result<Option<SourceContext>> {
-    use crate::store::schema::source_contexts::dsl::*;
-    if let Some(row) = source_contexts
-        .filter(hash.eq(hash_value.to_string()))
-        .load::<SourceContextRecord>(connection)?
+    workspace_value: &str,
+) -> Result<HashMap<String, u64>> {
+    use crate::store::schema::build_disk_usages::dsl::*;
+    Ok(build_disk_usages
+        .filter(workspace.eq(workspace_value))
+        .select((package, disk_usage))
+        .load::<(String, i64)>(connection)?
+        .into_iter()
+        .map(|(package_value, disk_usage_value)| (package_value, disk_usage_value as u64))
+        .collect())
+}
+
+pub(crate) fn build_disk_usage_put(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    package_value: &str,
+    disk_usage_value: u64,
+) -> Result<()> {
+    use crate::store::schema::build_disk_usages::dsl::*;
+    replace_into(build_disk_usages)
+        .values((
+            workspace.eq(workspace_value),
+            package.eq(package_value),
+            disk_usage.eq(disk_usage_value as i64),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+pub(crate) fn build_host_get(
+    connection: &mut SqliteConnection,
+    artifact_ident_value: &PackageIdent,
+) -> Result<Option<BuildHostMetadata>> {
+    use crate::store::schema::build_hosts::dsl::*;
+    if let Some(row) = build_hosts
+        .filter(artifact_ident.eq(artifact_ident_value.to_string()))
+        .load::<BuildHostRecord>(connection)?
         .first()
     {
-        Ok(Some(serde_json::from_str(&row.context)?))
+        Ok(Some(serde_json::from_str(&row.host)?))
     } else {
         Ok(None)
     }
 }
 
-pub(crate) fn source_context_put(
+pub(crate) fn build_host_put(
     connection: &mut SqliteConnection,
-    hash_value: &PackageSha256Sum,
-    source_context_value: &SourceContext,
+    artifact_ident_value: &PackageIdent,
+    host_value: &BuildHostMetadata,
 ) -> Result<()> {
-    use crate::store::schema::source_contexts::dsl::*;
-    if source_contexts
-        .filter(hash.eq(hash_value.to_string()))
-        .load::<SourceContextRecord>(connection)?
-        .first()
-        .is_none()
+    use crate::store::schema::build_hosts::dsl::*;
+    if build_hosts
+        .filter(artifact_ident.eq(artifact_ident_value.to_string()))
+        .load::<BuildHostRecord>(connection)?
+        .is_empty()
     {
-        insert_into(source_contexts)
+        insert_into(build_hosts)
             .values((
-                hash.eq(hash_value.to_string()),
-                context.eq(serde_json::to_string(source_context_value)?),
+                artifact_ident.eq(artifact_ident_value.to_string()),
+                host.eq(serde_json::to_string(host_value)?),
             ))
             .execute(connection)?;
+    } else {
+        update(build_hosts.filter(artifact_ident.eq(artifact_ident_value.to_string())))
+            .set(host.eq(serde_json::to_string(host_value)?))
+            .execute(connection)?;
     }
     Ok(())
 }
 
-pub(crate) fn artifact_context_get(
+/// Returns the hash of the plan context an artifact was built from, if it was recorded
+pub(crate) fn plan_context_hash_get(
     connection: &mut SqliteConnection,
-    hash_value: &Blake3,
-) -> Result<Option<ArtifactContext>> {
-    use crate::store::schema::artifact_contexts::dsl::*;
-    if let Some(row) = artifact_contexts
-        .filter(hash.eq(hash_value.to_string()))
-        .load::<ArtifactContextRecord>(connection)?
+    artifact_ident_value: &PackageIdent,
+) -> Result<Option<Blake3>> {
+    use crate::store::schema::plan_context_hashes::dsl::*;
+    if let Some(row) = plan_context_hashes
+        .filter(artifact_ident.eq(artifact_ident_value.to_string()))
+        .load::<PlanContextHashRecord>(connection)?
         .first()
     {
-        Ok(Some(
-            serde_json::from_str::<InnerArtifactContext>(&row.context)?.into(),
-        ))
+        Ok(Some(Blake3::from(row.hash.clone())))
     } else {
         Ok(None)
     }
 }
 
-pub(crate) fn artifact_context_put(
+pub(crate) fn plan_context_hash_put(
     connection: &mut SqliteConnection,
+    artifact_ident_value: &PackageIdent,
     hash_value: &Blake3,
-    artifact_context_value: &ArtifactContext,
 ) -> Result<()> {
-    use crate::store::schema::artifact_contexts::dsl::*;
-    insert_into(artifact_contexts)
-        .values((
-            hash.eq(hash_value.to_string()),
-            context.eq(serde_json::to_string(artifact_context_value.deref())?),
+    use crate::store::schema::plan_context_hashes::dsl::*;
+    if plan_context_hashes
+        .filter(artifact_ident.eq(artifact_ident_value.to_string()))
+        .load::<PlanContextHashRecord>(connection)?
+        .is_empty()
+    {
+        insert_into(plan_context_hashes)
+            .values((
+                artifact_ident.eq(artifact_ident_value.to_string()),
+                hash.eq(hash_value.to_string()),
+            ))
+            .execute(connection)?;
+    } else {
+        update(plan_context_hashes.filter(artifact_ident.eq(artifact_ident_value.to_string())))
+            .set(hash.eq(hash_value.to_string()))
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// Returns the artifacts built in the most recent build session along with the
+/// number of error-level violations found when checking them.
+pub(crate) fn build_session_last_get(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+) -> Result<Vec<(PackageIdent, usize)>> {
+    use crate::store::schema::build_session_artifacts::dsl::*;
+    // Session ids are timestamps, so the most recent session has the greatest id
+    let last_session_id = build_session_artifacts
+        .filter(workspace.eq(workspace_value))
+        .select(session_id)
+        .order(session_id.desc())
+        .first::<String>(connection)
+        .optional()?;
+    let Some(last_session_id) = last_session_id else {
+        return Ok(Vec::new());
+    };
+    let mut results = Vec::new();
+    for row in build_session_artifacts
+        .filter(workspace.eq(workspace_value))
+        .filter(session_id.eq(last_session_id))
+        .load::<BuildSessionArtifactRecord>(connection)?
+    {
+        results.push((
+            serde_json::from_str(&row.artifact)?,
+            row.error_count as usize,
+        ));
+    }
+    results.sort();
+    Ok(results)
+}
+
+pub(crate) fn build_session_artifact_put(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    session_id_value: &str,
+    artifact_value: &PackageIdent,
+    artifact_path_value: &Path,
+    error_count_value: usize,
+    warning_count_value: usize,
+) -> Result<()> {
+    use crate::store::schema::build_session_artifacts::dsl::*;
+    let artifact_value = serde_json::to_string(artifact_value)?;
+    if build_session_artifacts
+        .filter(workspace.eq(workspace_value))
+        .filter(session_id.eq(session_id_value))
+        .filter(artifact.eq(&artifact_value))
+        .load::<BuildSessionArtifactRecord>(connection)?
+        .is_empty()
+    {
+        insert_into(build_session_artifacts)
+            .values((
+                workspace.eq(workspace_value),
+                session_id.eq(session_id_value),
+                artifact.eq(&artifact_value),
+                error_count.eq(error_count_value as i32),
+                warning_count.eq(warning_count_value as i32),
+                artifact_path.eq(artifact_path_value.to_string_lossy()),
+            ))
+            .execute(connection)?;
+    } else {
+        update(
+            build_session_artifacts
+                .filter(workspace.eq(workspace_value))
+                .filter(session_id.eq(session_id_value))
+                .filter(artifact.eq(&artifact_value)),
+        )
+        .set((
+            error_count.eq(error_count_value as i32),
+            warning_count.eq(warning_count_value as i32),
+            artifact_path.eq(artifact_path_value.to_string_lossy()),
         ))
         .execute(connection)?;
+    }
     Ok(())
 }
 
-pub(crate) fn file_alternate_modified_at_get(
+/// Artifact built in a build session, with the number of violations found when checking it
+#[derive(Debug, Clone)]
+pub(crate) struct BuildSessionArtifact {
+    pub artifact: PackageIdent,
+    pub error_count: usize,
+    pub warning_count: usize,
+}
+
+pub(crate) fn build_session_artifacts_get(
     connection: &mut SqliteConnection,
-    plan_context_path_value: &PlanContextPath,
-    file_path_value: impl AsRef<Path>,
-    real_modified_at_value: DateTime<Utc>,
-) -> Result<Option<DateTime<Utc>>> {
-    use crate::store::schema::file_modifications::dsl::*;
-    if let Some(row) = file_modifications
-        .filter(plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap()))
-        .filter(file_path.eq(file_path_value.as_ref().to_str().unwrap()))
-        .filter(
-            real_modified_at.eq(real_modified_at_value
-                .naive_utc()
-                .format(TIMESTAMP_FORMAT)
-                .to_string()
-                .as_str()),
-        )
-        .limit(1)
-        .load::<FileModificationRecord>(connection)?
-        .first()
+    workspace_value: &str,
+    session_id_value: &str,
+) -> Result<Vec<BuildSessionArtifact>> {
+    use crate::store::schema::build_session_artifacts::dsl::*;
+    let mut results = Vec::new();
+    for row in build_session_artifacts
+        .filter(workspace.eq(workspace_value))
+        .filter(session_id.eq(session_id_value))
+        .load::<BuildSessionArtifactRecord>(connection)?
     {
-        Ok(Some(DateTime::<Utc>::from_naive_utc_and_offset(
-            NaiveDateTime::parse_from_str(&row.alternate_modified_at, TIMESTAMP_FORMAT).unwrap(),
-            Utc,
-        )))
-    } else {
-        Ok(None)
+        results.push(BuildSessionArtifact {
+            artifact: serde_json::from_str(&row.artifact)?,
+            error_count: row.error_count as usize,
+            warning_count: row.warning_count as usize,
+        });
     }
+    results.sort_by(|a, b| a.artifact.cmp(&b.artifact));
+    Ok(results)
 }
 
-pub(crate) fn file_alternate_modified_at_put(
+/// Record of an invocation of the build command, the counts are filled in
+/// when the session ends
+#[derive(Debug, Clone)]
+pub(crate) struct BuildSession {
+    pub id: i32,
+    pub session_id: String,
+    pub started_at: DateTime<Utc>,
+    /// Not set if the session is still running or was interrupted
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Number of packages the session planned to build
+    pub planned: usize,
+    pub attempted: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub error_count: usize,
+    pub warning_count: usize,
+}
+
+impl TryFrom<BuildSessionRecord> for BuildSession {
+    type Error = color_eyre::eyre::Error;
+
+    fn try_from(row: BuildSessionRecord) -> Result<Self> {
+        Ok(BuildSession {
+            id: row.id,
+            session_id: row.session_id,
+            started_at: DateTime::<Utc>::from_naive_utc_and_offset(
+                NaiveDateTime::parse_from_str(&row.started_at, TIMESTAMP_FORMAT)?,
+                Utc,
+            ),
+            ended_at: match row.ended_at {
+                Some(ended_at) => Some(DateTime::<Utc>::from_naive_utc_and_offset(
+                    NaiveDateTime::parse_from_str(&ended_at, TIMESTAMP_FORMAT)?,
+                    Utc,
+                )),
+                None => None,
+            },
+            planned: row.planned as usize,
+            attempted: row.attempted as usize,
+            succeeded: row.succeeded as usize,
+            failed: row.failed as usize,
+            error_count: row.error_count as usize,
+            warning_count: row.warning_count as usize,
+        })
+    }
+}
+
+pub(crate) fn build_session_start(
     connection: &mut SqliteConnection,
-    plan_context_path_value: &PlanContextPath,
-    file_path_value: impl AsRef<Path>,
-    real_modified_at_value: DateTime<Utc>,
-    alternate_modified_at_value: DateTime<Utc>,
+    workspace_value: &str,
+    session_id_value: &str,
+    started_at_value: DateTime<Utc>,
+    planned_value: usize,
 ) -> Result<()> {
-    use crate::store::schema::file_modifications::dsl::*;
-    insert_into(file_modifications)
+    use crate::store::schema::build_sessions::dsl::*;
+    insert_into(build_sessions)
         .values((
-            plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap()),
-            file_path.eq(file_path_value.as_ref().to_str().unwrap()),
-            real_modified_at.eq(&real_modified_at_value
-                .naive_utc()
-                .format(TIMESTAMP_FORMAT)
-                .to_string()),
-            alternate_modified_at.eq(&alternate_modified_at_value
+            workspace.eq(workspace_value),
+            session_id.eq(session_id_value),
+            started_at.eq(started_at_value
                 .naive_utc()
                 .format(TIMESTAMP_FORMAT)
                 .to_string()),
+            planned.eq(planned_value as i32),
         ))
         .execute(connection)?;
     Ok(())
 }
 
-pub(crate) fn plan_context_alternate_modified_at_delete(
+/// Marks a build session as ended, counting the outcome of its steps and the
+/// violations found in the artifacts it built.
+pub(crate) fn build_session_end(
     connection: &mut SqliteConnection,
-    plan_context_path_value: &PlanContextPath,
-) -> Result<Option<PathMap>> {
+    workspace_value: &str,
+    session_id_value: &str,
+    ended_at_value: DateTime<Utc>,
+) -> Result<()> {
+    use crate::store::schema::build_sessions::dsl::*;
+    let step_states = build_step_states_get(connection, workspace_value, session_id_value)?;
+    let count = |status_value: BuildStepStatus| {
+        step_states
+            .iter()
+            .filter(|step_state| step_state.status == status_value)
+            .count()
+    };
+    let (succeeded_value, failed_value) = (
+        count(BuildStepStatus::Built),
+        count(BuildStepStatus::Failed),
+    );
+    let attempted_value = succeeded_value + failed_value + count(BuildStepStatus::Building);
+    let artifacts = build_session_artifacts_get(connection, workspace_value, session_id_value)?;
+    update(
+        build_sessions
+            .filter(workspace.eq(workspace_value))
+            .filter(session_id.eq(session_id_value)),
+    )
+    .set((
+        ended_at.eq(ended_at_value
+            .naive_utc()
+            .format(TIMESTAMP_FORMAT)
+            .to_string()),
+        attempted.eq(attempted_value as i32),
+        succeeded.eq(succeeded_value as i32),
+        failed.eq(failed_value as i32),
+        error_count.eq(artifacts
+            .iter()
+            .map(|artifact| artifact.error_count)
+            .sum::<usize>() as i32),
+        warning_count.eq(artifacts
+            .iter()
+            .map(|artifact| artifact.warning_count)
+            .sum::<usize>() as i32),
+    ))
+    .execute(connection)?;
+    Ok(())
+}
+
+/// Returns the recorded build sessions, most recent first
+pub(crate) fn build_sessions_get(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    limit: Option<usize>,
+) -> Result<Vec<BuildSession>> {
+    use crate::store::schema::build_sessions::dsl::*;
+    let mut query = build_sessions
+        .filter(workspace.eq(workspace_value))
+        .order(id.desc())
+        .into_boxed();
+    if let Some(limit) = limit {
+        query = query.limit(limit as i64);
+    }
+    query
+        .load::<BuildSessionRecord>(connection)?
+        .into_iter()
+        .map(BuildSession::try_from)
+        .collect()
+}
+
+pub(crate) fn build_session_get(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    id_value: i32,
+) -> Result<Option<BuildSession>> {
+    use crate::store::schema::build_sessions::dsl::*;
+    build_sessions
+        .filter(workspace.eq(workspace_value))
+        .filter(id.eq(id_value))
+        .first::<BuildSessionRecord>(connection)
+        .optional()?
+        .map(BuildSession::try_from)
+        .transpose()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BuildClaimStatus {
+    Claimed,
+    Completed(PackageIdent),
+    Failed,
+}
+
+/// Claim of a worker on a step of a cooperative build
+#[derive(Debug, Clone)]
+pub(crate) struct BuildClaim {
+    pub plan: String,
+    pub worker: String,
+    pub status: BuildClaimStatus,
+    pub lease_expires_at: DateTime<Utc>,
+}
+
+impl BuildClaim {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.status == BuildClaimStatus::Claimed && self.lease_expires_at < now
+    }
+}
+
+impl TryFrom<BuildClaimRecord> for BuildClaim {
+    type Error = color_eyre::eyre::Error;
+
+    fn try_from(row: BuildClaimRecord) -> Result<Self> {
+        Ok(BuildClaim {
+            status: match (row.status.as_str(), row.artifact) {
+                ("claimed", _) => BuildClaimStatus::Claimed,
+                ("completed", Some(artifact_value)) => {
+                    BuildClaimStatus::Completed(serde_json::from_str(&artifact_value)?)
+                }
+                ("failed", _) => BuildClaimStatus::Failed,
+                (status_value, _) => {
+                    return Err(eyre!("Invalid build claim status '{}'", status_value))
+                }
+            },
+            plan: row.plan,
+            worker: row.worker,
+            lease_expires_at: DateTime::<Utc>::from_naive_utc_and_offset(
+                NaiveDateTime::parse_from_str(&row.lease_expires_at, TIMESTAMP_FORMAT)?,
+                Utc,
+            ),
+        })
+    }
+}
+
+pub(crate) fn build_claims_get(
+    connection: &mut SqliteConnection,
+    build_id_value: &str,
+) -> Result<Vec<BuildClaim>> {
+    use crate::store::schema::build_claims::dsl::*;
+    build_claims
+        .filter(build_id.eq(build_id_value))
+        .load::<BuildClaimRecord>(connection)?
+        .into_iter()
+        .map(BuildClaim::try_from)
+        .collect()
+}
+
+/// Claims a build step for a worker, a step can only be claimed if no other
+/// worker has claimed it or if the lease of the previous claim has expired.
+/// Returns whether the claim succeeded.
+pub(crate) fn build_claim_put(
+    connection: &mut SqliteConnection,
+    build_id_value: &str,
+    plan_value: &str,
+    worker_value: &str,
+    now: DateTime<Utc>,
+    lease_expires_at_value: DateTime<Utc>,
+) -> Result<bool> {
+    use crate::store::schema::build_claims::dsl::*;
+    let lease_expires_at_value = lease_expires_at_value
+        .naive_utc()
+        .format(TIMESTAMP_FORMAT)
+        .to_string();
+    if let Some(row) = build_claims
+        .filter(build_id.eq(build_id_value))
+        .filter(plan.eq(plan_value))
+        .load::<BuildClaimRecord>(connection)?
+        .pop()
+    {
+        if !BuildClaim::try_from(row)?.is_expired(now) {
+            return Ok(false);
+        }
+        update(
+            build_claims
+                .filter(build_id.eq(build_id_value))
+                .filter(plan.eq(plan_value)),
+        )
+        .set((
+            worker.eq(worker_value),
+            lease_expires_at.eq(lease_expires_at_value),
+        ))
+        .execute(connection)?;
+    } else {
+        insert_into(build_claims)
+            .values((
+                build_id.eq(build_id_value),
+                plan.eq(plan_value),
+                worker.eq(worker_value),
+                status.eq("claimed"),
+                lease_expires_at.eq(lease_expires_at_value),
+            ))
+            .execute(connection)?;
+    }
+    Ok(true)
+}
+
+/// Extends the lease of a worker's claim on a build step, returns false if
+/// the claim was taken over by another worker in the meantime.
+pub(crate) fn build_claim_renew(
+    connection: &mut SqliteConnection,
+    build_id_value: &str,
+    plan_value: &str,
+    worker_value: &str,
+    lease_expires_at_value: DateTime<Utc>,
+) -> Result<bool> {
+    use crate::store::schema::build_claims::dsl::*;
+    let updated_rows = update(
+        build_claims
+            .filter(build_id.eq(build_id_value))
+            .filter(plan.eq(plan_value))
+            .filter(worker.eq(worker_value))
+            .filter(status.eq("claimed")),
+    )
+    .set(
+        lease_expires_at.eq(lease_expires_at_value
+            .naive_utc()
+            .format(TIMESTAMP_FORMAT)
+            .to_string()),
+    )
+    .execute(connection)?;
+    Ok(updated_rows > 0)
+}
+
+/// Records the outcome of a worker's claim on a build step. Fails if the worker
+/// no longer holds the claim, as the outcome would be lost.
+pub(crate) fn build_claim_release(
+    connection: &mut SqliteConnection,
+    build_id_value: &str,
+    plan_value: &str,
+    worker_value: &str,
+    status_value: &BuildClaimStatus,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    use crate::store::schema::build_claims::dsl::*;
+    let (status_value, artifact_value) = match status_value {
+        BuildClaimStatus::Claimed => ("claimed", None),
+        BuildClaimStatus::Completed(artifact_value) => {
+            ("completed", Some(serde_json::to_string(artifact_value)?))
+        }
+        BuildClaimStatus::Failed => ("failed", None),
+    };
+    let updated_rows = update(
+        build_claims
+            .filter(build_id.eq(build_id_value))
+            .filter(plan.eq(plan_value))
+            .filter(worker.eq(worker_value))
+            .filter(status.eq("claimed")),
+    )
+    .set((
+        status.eq(status_value),
+        artifact.eq(artifact_value),
+        released_at.eq(now.naive_utc().format(TIMESTAMP_FORMAT).to_string()),
+    ))
+    .execute(connection)?;
+    if updated_rows == 0 {
+        return Err(eyre!(
+            "Worker {} no longer holds the claim on {} in build {}",
+            worker_value,
+            plan_value,
+            build_id_value
+        ));
+    }
+    Ok(())
+}
+
+/// Removes the failed claims of a build so their steps can be claimed again,
+/// returns the number of claims removed.
+pub(crate) fn build_claims_failed_delete(
+    connection: &mut SqliteConnection,
+    build_id_value: &str,
+) -> Result<usize> {
+    use crate::store::schema::build_claims::dsl::*;
+    Ok(delete(
+        build_claims
+            .filter(build_id.eq(build_id_value))
+            .filter(status.eq("failed")),
+    )
+    .execute(connection)?)
+}
+
+/// Removes the claims of every build that were released, or whose lease expired,
+/// before the given time. Returns the number of claims removed.
+pub(crate) fn build_claims_prune(
+    connection: &mut SqliteConnection,
+    before: DateTime<Utc>,
+) -> Result<usize> {
+    use crate::store::schema::build_claims::dsl::*;
+    let before = before.naive_utc().format(TIMESTAMP_FORMAT).to_string();
+    Ok(delete(
+        build_claims.filter(
+            status
+                .eq("claimed")
+                .and(lease_expires_at.lt(&before))
+                .or(status
+                    .ne("claimed")
+                    .and(released_at.is_null().or(released_at.lt(&before)))),
+        ),
+    )
+    .execute(connection)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum BuildStepStatus {
+    Pending,
+    Building,
+    Failed,
+    Built,
+}
+
+impl BuildStepStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildStepStatus::Pending => "pending",
+            BuildStepStatus::Building => "building",
+            BuildStepStatus::Failed => "failed",
+            BuildStepStatus::Built => "built",
+        }
+    }
+}
+
+/// Progress of a step of a build session, recorded so that other processes
+/// sharing the store can follow a running build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct BuildStepState {
+    pub plan: String,
+    pub status: BuildStepStatus,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<BuildStepStateRecord> for BuildStepState {
+    type Error = color_eyre::eyre::Error;
+
+    fn try_from(row: BuildStepStateRecord) -> Result<Self> {
+        Ok(BuildStepState {
+            status: match row.status.as_str() {
+                "pending" => BuildStepStatus::Pending,
+                "building" => BuildStepStatus::Building,
+                "failed" => BuildStepStatus::Failed,
+                "built" => BuildStepStatus::Built,
+                status_value => return Err(eyre!("Invalid build step status '{}'", status_value)),
+            },
+            plan: row.plan,
+            updated_at: DateTime::<Utc>::from_naive_utc_and_offset(
+                NaiveDateTime::parse_from_str(&row.updated_at, TIMESTAMP_FORMAT)?,
+                Utc,
+            ),
+        })
+    }
+}
+
+/// Returns the id and step states of the most recent build session that
+/// recorded its progress.
+pub(crate) fn build_step_states_last_get(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+) -> Result<Option<(String, Vec<BuildStepState>)>> {
+    use crate::store::schema::build_step_states::dsl::*;
+    // Session ids are timestamps, so the most recent session has the greatest id
+    let last_session_id = build_step_states
+        .filter(workspace.eq(workspace_value))
+        .select(session_id)
+        .order(session_id.desc())
+        .first::<String>(connection)
+        .optional()?;
+    let Some(last_session_id) = last_session_id else {
+        return Ok(None);
+    };
+    let states = build_step_states_get(connection, workspace_value, &last_session_id)?;
+    Ok(Some((last_session_id, states)))
+}
+
+pub(crate) fn build_step_states_get(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    session_id_value: &str,
+) -> Result<Vec<BuildStepState>> {
+    use crate::store::schema::build_step_states::dsl::*;
+    build_step_states
+        .filter(workspace.eq(workspace_value))
+        .filter(session_id.eq(session_id_value))
+        .load::<BuildStepStateRecord>(connection)?
+        .into_iter()
+        .map(BuildStepState::try_from)
+        .collect()
+}
+
+pub(crate) fn build_step_state_put(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    session_id_value: &str,
+    plan_value: &str,
+    status_value: BuildStepStatus,
+    updated_at_value: DateTime<Utc>,
+) -> Result<()> {
+    use crate::store::schema::build_step_states::dsl::*;
+    let updated_at_value = updated_at_value
+        .naive_utc()
+        .format(TIMESTAMP_FORMAT)
+        .to_string();
+    if build_step_states
+        .filter(workspace.eq(workspace_value))
+        .filter(session_id.eq(session_id_value))
+        .filter(plan.eq(plan_value))
+        .load::<BuildStepStateRecord>(connection)?
+        .is_empty()
+    {
+        insert_into(build_step_states)
+            .values((
+                workspace.eq(workspace_value),
+                session_id.eq(session_id_value),
+                plan.eq(plan_value),
+                status.eq(status_value.as_str()),
+                updated_at.eq(updated_at_value),
+            ))
+            .execute(connection)?;
+    } else {
+        update(
+            build_step_states
+                .filter(workspace.eq(workspace_value))
+                .filter(session_id.eq(session_id_value))
+                .filter(plan.eq(plan_value)),
+        )
+        .set((
+            status.eq(status_value.as_str()),
+            updated_at.eq(updated_at_value),
+        ))
+        .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// Sizes of an artifact recorded by the size report
+#[derive(Debug, Clone)]
+pub(crate) struct RecordedArtifactSize {
+    pub artifact: PackageIdent,
+    /// Installed size in bytes of the artifact
+    pub installed_size: u64,
+    /// Installed size in bytes of the artifact and its transitive runtime dependencies
+    pub closure_size: u64,
+}
+
+pub(crate) fn artifact_sizes_get(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<RecordedArtifactSize>> {
+    use crate::store::schema::artifact_sizes::dsl::*;
+    let mut results = Vec::new();
+    for (artifact_value, installed_size_value, closure_size_value) in artifact_sizes
+        .select((artifact, installed_size, closure_size))
+        .load::<(String, i64, i64)>(connection)?
+    {
+        results.push(RecordedArtifactSize {
+            artifact: serde_json::from_str(&artifact_value)?,
+            installed_size: installed_size_value as u64,
+            closure_size: closure_size_value as u64,
+        });
+    }
+    Ok(results)
+}
+
+pub(crate) fn artifact_size_put(
+    connection: &mut SqliteConnection,
+    recorded_artifact_size: &RecordedArtifactSize,
+) -> Result<()> {
+    use crate::store::schema::artifact_sizes::dsl::*;
+    replace_into(artifact_sizes)
+        .values((
+            artifact.eq(serde_json::to_string(&recorded_artifact_size.artifact)?),
+            installed_size.eq(recorded_artifact_size.installed_size as i64),
+            closure_size.eq(recorded_artifact_size.closure_size as i64),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Artifact excluded from dependency resolution without being removed from the artifact cache
+#[derive(Debug, Clone)]
+pub(crate) struct YankedArtifact {
+    pub artifact: PackageIdent,
+    pub reason: Option<String>,
+    pub yanked_at: DateTime<Utc>,
+}
+
+pub(crate) fn yanked_artifacts_get(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<YankedArtifact>> {
+    use crate::store::schema::yanked_artifacts::dsl::*;
+    let mut results = Vec::new();
+    for row in yanked_artifacts.load::<YankedArtifactRecord>(connection)? {
+        results.push(YankedArtifact {
+            artifact: serde_json::from_str(&row.artifact)?,
+            reason: row.reason,
+            yanked_at: DateTime::<Utc>::from_naive_utc_and_offset(
+                NaiveDateTime::parse_from_str(&row.yanked_at, TIMESTAMP_FORMAT)?,
+                Utc,
+            ),
+        });
+    }
+    results.sort_by(|a, b| a.artifact.cmp(&b.artifact));
+    Ok(results)
+}
+
+pub(crate) fn yanked_artifact_put(
+    connection: &mut SqliteConnection,
+    artifact_value: &PackageIdent,
+    reason_value: Option<&str>,
+    yanked_at_value: DateTime<Utc>,
+) -> Result<()> {
+    use crate::store::schema::yanked_artifacts::dsl::*;
+    let artifact_value = serde_json::to_string(artifact_value)?;
+    let yanked_at_value = yanked_at_value
+        .naive_utc()
+        .format(TIMESTAMP_FORMAT)
+        .to_string();
+    if yanked_artifacts
+        .filter(artifact.eq(&artifact_value))
+        .load::<YankedArtifactRecord>(connection)?
+        .is_empty()
+    {
+        insert_into(yanked_artifacts)
+            .values((
+                artifact.eq(&artifact_value),
+                reason.eq(reason_value),
+                yanked_at.eq(yanked_at_value),
+            ))
+            .execute(connection)?;
+    } else {
+        update(yanked_artifacts.filter(artifact.eq(&artifact_value)))
+            .set((reason.eq(reason_value), yanked_at.eq(yanked_at_value)))
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// Removes an artifact from the yanked artifacts, returns whether it was yanked
+pub(crate) fn yanked_artifact_delete(
+    connection: &mut SqliteConnection,
+    artifact_value: &PackageIdent,
+) -> Result<bool> {
+    use crate::store::schema::yanked_artifacts::dsl::*;
+    let deleted_rows =
+        delete(yanked_artifacts.filter(artifact.eq(serde_json::to_string(artifact_value)?)))
+            .execute(connection)?;
+    Ok(deleted_rows > 0)
+}
+
+/// Counts the lookups of a kind of context cached in the store made by this process
+#[derive(Debug)]
+pub(crate) struct ContextCacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ContextCacheStats {
+    const fn new() -> ContextCacheStats {
+        ContextCacheStats {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, is_hit: bool) {
+        if is_hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+pub(crate) static SOURCE_CONTEXT_CACHE_STATS: ContextCacheStats = ContextCacheStats::new();
+pub(crate) static ARTIFACT_CONTEXT_CACHE_STATS: ContextCacheStats = ContextCacheStats::new();
+
+pub(crate) fn source_context_get(
+    connection: &mut SqliteConnection,
+    hash_value: &PackageSha256Sum,
+) -> Result<Option<SourceContext>> {
+    use crate::store::schema::source_contexts::dsl::*;
+    let row = source_contexts
+        .filter(hash.eq(hash_value.to_string()))
+        .load::<SourceContextRecord>(connection)?
+        .pop();
+    SOURCE_CONTEXT_CACHE_STATS.record(row.is_some());
+    if let Some(row) = row {
+        Ok(Some(serde_json::from_str(&row.context)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub(crate) fn source_context_put(
+    connection: &mut SqliteConnection,
+    hash_value: &PackageSha256Sum,
+    source_context_value: &SourceContext,
+) -> Result<()> {
+    use crate::store::schema::source_contexts::dsl::*;
+    if source_contexts
+        .filter(hash.eq(hash_value.to_string()))
+        .load::<SourceContextRecord>(connection)?
+        .is_empty()
+    {
+        insert_into(source_contexts)
+            .values((
+                hash.eq(hash_value.to_string()),
+                context.eq(serde_json::to_string(source_context_value)?),
+            ))
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn source_context_delete(
+    connection: &mut SqliteConnection,
+    hash_value: &PackageSha256Sum,
+) -> Result<()> {
+    use crate::store::schema::source_contexts::dsl::*;
+    delete(source_contexts.filter(hash.eq(hash_value.to_string()))).execute(connection)?;
+    Ok(())
+}
+
+/// Returns the licenses detected in the license files of a source archive by a
+/// license scanner, keyed by the path of the license file.
+pub(crate) fn source_license_scan_get(
+    connection: &mut SqliteConnection,
+    hash_value: &PackageSha256Sum,
+    scanner_value: &str,
+) -> Result<Option<BTreeMap<PathBuf, BTreeSet<String>>>> {
+    use crate::store::schema::source_license_scans::dsl::*;
+    if let Some(row) = source_license_scans
+        .filter(hash.eq(hash_value.to_string()))
+        .filter(scanner.eq(scanner_value))
+        .select(licenses)
+        .load::<String>(connection)?
+        .pop()
+    {
+        Ok(Some(serde_json::from_str(&row)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub(crate) fn source_license_scan_put(
+    connection: &mut SqliteConnection,
+    hash_value: &PackageSha256Sum,
+    scanner_value: &str,
+    licenses_value: &BTreeMap<PathBuf, BTreeSet<String>>,
+) -> Result<()> {
+    use crate::store::schema::source_license_scans::dsl::*;
+    replace_into(source_license_scans)
+        .values((
+            hash.eq(hash_value.to_string()),
+            scanner.eq(scanner_value),
+            licenses.eq(serde_json::to_string(licenses_value)?),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Returns the url from which a source archive was downloaded, which is either
+/// one of the configured source mirrors or the upstream url.
+pub(crate) fn source_download_url_get(
+    connection: &mut SqliteConnection,
+    hash_value: &PackageSha256Sum,
+) -> Result<Option<PackageSourceURL>> {
+    use crate::store::schema::source_download_urls::dsl::*;
+    if let Some(row) = source_download_urls
+        .filter(hash.eq(hash_value.to_string()))
+        .load::<SourceDownloadUrlRecord>(connection)?
+        .first()
+    {
+        Ok(Some(PackageSourceURL::parse(&row.url)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub(crate) fn source_download_url_put(
+    connection: &mut SqliteConnection,
+    hash_value: &PackageSha256Sum,
+    url_value: &PackageSourceURL,
+) -> Result<()> {
+    use crate::store::schema::source_download_urls::dsl::*;
+    if source_download_urls
+        .filter(hash.eq(hash_value.to_string()))
+        .load::<SourceDownloadUrlRecord>(connection)?
+        .is_empty()
+    {
+        insert_into(source_download_urls)
+            .values((
+                hash.eq(hash_value.to_string()),
+                url.eq(url_value.to_string()),
+            ))
+            .execute(connection)?;
+    } else {
+        update(source_download_urls.filter(hash.eq(hash_value.to_string())))
+            .set(url.eq(url_value.to_string()))
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn source_download_url_delete(
+    connection: &mut SqliteConnection,
+    hash_value: &PackageSha256Sum,
+) -> Result<()> {
+    use crate::store::schema::source_download_urls::dsl::*;
+    delete(source_download_urls.filter(hash.eq(hash_value.to_string()))).execute(connection)?;
+    Ok(())
+}
+
+pub(crate) fn artifact_context_get(
+    connection: &mut SqliteConnection,
+    hash_value: &Blake3,
+) -> Result<Option<ArtifactContext>> {
+    use crate::store::schema::artifact_contexts::dsl::*;
+    let row = artifact_contexts
+        .filter(hash.eq(hash_value.to_string()))
+        .load::<ArtifactContextRecord>(connection)?
+        .pop();
+    // Contexts stored with another version of the schema are treated as missing,
+    // they are read again from the artifact and replace the stale record
+    let artifact_ctx = match row {
+        Some(row) if row.version != ARTIFACT_CONTEXT_VERSION => {
+            debug!(
+                "Ignoring artifact context {} stored with schema version {}, current version is {}",
+                hash_value, row.version, ARTIFACT_CONTEXT_VERSION
+            );
+            None
+        }
+        Some(row) => match serde_json::from_str::<InnerArtifactContext>(&row.context) {
+            Ok(artifact_ctx) => Some(ArtifactContext::from(artifact_ctx)),
+            Err(err) => {
+                warn!(
+                    "Ignoring unreadable artifact context {} stored with schema version {}: {}",
+                    hash_value, row.version, err
+                );
+                None
+            }
+        },
+        None => None,
+    };
+    ARTIFACT_CONTEXT_CACHE_STATS.record(artifact_ctx.is_some());
+    Ok(artifact_ctx)
+}
+
+pub(crate) fn artifact_context_put(
+    connection: &mut SqliteConnection,
+    hash_value: &Blake3,
+    artifact_context_value: &ArtifactContext,
+) -> Result<()> {
+    use crate::store::schema::artifact_contexts::dsl::*;
+    replace_into(artifact_contexts)
+        .values((
+            hash.eq(hash_value.to_string()),
+            context.eq(serde_json::to_string(artifact_context_value.deref())?),
+            version.eq(ARTIFACT_CONTEXT_VERSION),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Gets the files recorded for an artifact, artifacts indexed before files were
+/// recorded have none.
+pub(crate) fn artifact_files_get(
+    connection: &mut SqliteConnection,
+    hash_value: &Blake3,
+) -> Result<Option<Vec<ArtifactFile>>> {
+    use crate::store::schema::artifact_files::dsl::*;
+    artifact_files
+        .filter(hash.eq(hash_value.to_string()))
+        .select(files)
+        .load::<String>(connection)?
+        .pop()
+        .map(|files_value| {
+            serde_json::from_str(&files_value)
+                .with_context(|| format!("Failed to read files of artifact {}", hash_value))
+        })
+        .transpose()
+}
+
+pub(crate) fn artifact_files_put(
+    connection: &mut SqliteConnection,
+    hash_value: &Blake3,
+    files_value: &[ArtifactFile],
+) -> Result<()> {
+    use crate::store::schema::artifact_files::dsl::*;
+    replace_into(artifact_files)
+        .values((
+            hash.eq(hash_value.to_string()),
+            files.eq(serde_json::to_string(files_value)?),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+pub(crate) fn file_alternate_modified_at_get(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    plan_context_path_value: &PlanContextPath,
+    file_path_value: impl AsRef<Path>,
+    real_modified_at_value: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>> {
     use crate::store::schema::file_modifications::dsl::*;
-    let existing_file_modifications = file_modifications
+    if let Some(row) = file_modifications
+        .filter(workspace.eq(workspace_value))
         .filter(plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap()))
+        .filter(file_path.eq(file_path_value.as_ref().to_str().unwrap()))
+        .filter(
+            real_modified_at.eq(real_modified_at_value
+                .naive_utc()
+                .format(TIMESTAMP_FORMAT)
+                .to_string()
+                .as_str()),
+        )
         .limit(1)
+        .load::<FileModificationRecord>(connection)?
+        .first()
+    {
+        Ok(Some(DateTime::<Utc>::from_naive_utc_and_offset(
+            NaiveDateTime::parse_from_str(&row.alternate_modified_at, TIMESTAMP_FORMAT).unwrap(),
+            Utc,
+        )))
+    } else {
+        Ok(None)
+    }
+}
+
+pub(crate) fn file_alternate_modified_at_put(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    plan_context_path_value: &PlanContextPath,
+    file_path_value: impl AsRef<Path>,
+    real_modified_at_value: DateTime<Utc>,
+    alternate_modified_at_value: DateTime<Utc>,
+) -> Result<()> {
+    use crate::store::schema::file_modifications::dsl::*;
+    insert_into(file_modifications)
+        .values((
+            workspace.eq(workspace_value),
+            plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap()),
+            file_path.eq(file_path_value.as_ref().to_str().unwrap()),
+            real_modified_at.eq(&real_modified_at_value
+                .naive_utc()
+                .format(TIMESTAMP_FORMAT)
+                .to_string()),
+            alternate_modified_at.eq(&alternate_modified_at_value
+                .naive_utc()
+                .format(TIMESTAMP_FORMAT)
+                .to_string()),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Returns the real and alternate modification times recorded for the files of a plan context
+pub(crate) fn plan_context_alternate_modified_at_get(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    plan_context_path_value: &PlanContextPath,
+) -> Result<PathMap> {
+    use crate::store::schema::file_modifications::dsl::*;
+    let existing_file_modifications = file_modifications
+        .filter(workspace.eq(workspace_value))
+        .filter(plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap()))
         .load::<FileModificationRecord>(connection)?;
-    let results: HashMap<PathBuf, (DateTime<Utc>, DateTime<Utc>)> = existing_file_modifications
+    Ok(existing_file_modifications
         .into_iter()
         .map(|row| {
             (
@@ -460,9 +1912,23 @@ pub(crate) fn plan_context_alternate_modified_at_delete(
                 ),
             )
         })
-        .collect();
+        .collect())
+}
+
+pub(crate) fn plan_context_alternate_modified_at_delete(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    plan_context_path_value: &PlanContextPath,
+) -> Result<Option<PathMap>> {
+    use crate::store::schema::file_modifications::dsl::*;
+    let results = plan_context_alternate_modified_at_get(
+        connection,
+        workspace_value,
+        plan_context_path_value,
+    )?;
     delete(
         file_modifications
+            .filter(workspace.eq(workspace_value))
             .filter(plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap())),
     )
     .execute(connection)?;
@@ -472,3 +1938,363 @@ pub(crate) fn plan_context_alternate_modified_at_delete(
         Ok(Some(results))
     }
 }
+
+/// Records that the modification time of a file was rewritten by a git sync, the time
+/// it was synced to is recorded as both its real and alternate modification time so
+/// that change detection is unaffected.
+pub(crate) fn file_git_synced_from_put(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    plan_context_path_value: &PlanContextPath,
+    file_path_value: impl AsRef<Path>,
+    synced_from_modified_at_value: DateTime<Utc>,
+    synced_to_modified_at_value: DateTime<Utc>,
+) -> Result<()> {
+    use crate::store::schema::file_modifications::dsl::*;
+    let synced_to_modified_at_value = synced_to_modified_at_value
+        .naive_utc()
+        .format(TIMESTAMP_FORMAT)
+        .to_string();
+    replace_into(file_modifications)
+        .values((
+            workspace.eq(workspace_value),
+            plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap()),
+            file_path.eq(file_path_value.as_ref().to_str().unwrap()),
+            real_modified_at.eq(&synced_to_modified_at_value),
+            alternate_modified_at.eq(&synced_to_modified_at_value),
+            synced_from_modified_at.eq(&synced_from_modified_at_value
+                .naive_utc()
+                .format(TIMESTAMP_FORMAT)
+                .to_string()),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Returns the time each file of a plan context was synced to by a git sync and
+/// the modification time it had before
+pub(crate) fn plan_context_git_synced_from_get(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    plan_context_path_value: &PlanContextPath,
+) -> Result<PathMap> {
+    use crate::store::schema::file_modifications::dsl::*;
+    let rows = file_modifications
+        .filter(workspace.eq(workspace_value))
+        .filter(plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap()))
+        .filter(synced_from_modified_at.is_not_null())
+        .load::<FileModificationRecord>(connection)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let synced_from_modified_at_value = row.synced_from_modified_at?;
+            Some((
+                PathBuf::from(row.file_path),
+                (
+                    DateTime::<Utc>::from_naive_utc_and_offset(
+                        NaiveDateTime::parse_from_str(&row.real_modified_at, TIMESTAMP_FORMAT)
+                            .unwrap(),
+                        Utc,
+                    ),
+                    DateTime::<Utc>::from_naive_utc_and_offset(
+                        NaiveDateTime::parse_from_str(
+                            &synced_from_modified_at_value,
+                            TIMESTAMP_FORMAT,
+                        )
+                        .unwrap(),
+                        Utc,
+                    ),
+                ),
+            ))
+        })
+        .collect())
+}
+
+pub(crate) fn plan_context_git_synced_from_delete(
+    connection: &mut SqliteConnection,
+    workspace_value: &str,
+    plan_context_path_value: &PlanContextPath,
+) -> Result<()> {
+    use crate::store::schema::file_modifications::dsl::*;
+    delete(
+        file_modifications
+            .filter(workspace.eq(workspace_value))
+            .filter(plan_context_path.eq(plan_context_path_value.as_ref().to_str().unwrap()))
+            .filter(synced_from_modified_at.is_not_null()),
+    )
+    .execute(connection)?;
+    Ok(())
+}
+
+/// Gets the vulnerabilities found by a query to a vulnerability database, if
+/// they were fetched after the given time.
+pub(crate) fn vulnerability_query_get(
+    connection: &mut SqliteConnection,
+    query_value: &str,
+    fetched_after: DateTime<Utc>,
+) -> Result<Option<String>> {
+    use crate::store::schema::vulnerability_queries::dsl::*;
+    Ok(vulnerability_queries
+        .filter(query.eq(query_value))
+        .filter(
+            fetched_at.gt(fetched_after
+                .naive_utc()
+                .format(TIMESTAMP_FORMAT)
+                .to_string()),
+        )
+        .select(vulnerabilities)
+        .load::<String>(connection)?
+        .pop())
+}
+
+pub(crate) fn vulnerability_query_put(
+    connection: &mut SqliteConnection,
+    query_value: &str,
+    vulnerabilities_value: &str,
+) -> Result<()> {
+    use crate::store::schema::vulnerability_queries::dsl::*;
+    replace_into(vulnerability_queries)
+        .values((
+            query.eq(query_value),
+            vulnerabilities.eq(vulnerabilities_value),
+            fetched_at.eq(Utc::now().naive_utc().format(TIMESTAMP_FORMAT).to_string()),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        PackageName, PackageOrigin, PackageResolvedRelease, PackageResolvedVersion, PackageTarget,
+    };
+    use chrono::Duration;
+
+    fn test_artifact_ident() -> PackageIdent {
+        PackageIdent {
+            name: PackageName::parse("a").unwrap(),
+            origin: PackageOrigin::parse("core").unwrap(),
+            version: PackageResolvedVersion::parse("1.0").unwrap(),
+            release: PackageResolvedRelease::parse("20240101000000").unwrap(),
+            target: PackageTarget::default(),
+        }
+    }
+
+    fn test_connection() -> SqliteConnection {
+        let mut connection = SqliteConnection::establish(":memory:").unwrap();
+        connection.run_pending_migrations(MIGRATIONS).unwrap();
+        connection
+    }
+
+    fn claim_status(connection: &mut SqliteConnection, plan_value: &str) -> BuildClaimStatus {
+        build_claims_get(connection, "build")
+            .unwrap()
+            .into_iter()
+            .find(|claim| claim.plan == plan_value)
+            .unwrap()
+            .status
+    }
+
+    #[test]
+    fn build_claim_is_exclusive_until_its_lease_expires() {
+        let mut connection = test_connection();
+        let now = Utc::now();
+        let lease = Duration::seconds(60);
+        assert!(
+            build_claim_put(&mut connection, "build", "core/a", "w1", now, now + lease).unwrap()
+        );
+        assert!(
+            !build_claim_put(&mut connection, "build", "core/a", "w2", now, now + lease).unwrap()
+        );
+        let later = now + lease + Duration::seconds(1);
+        assert!(build_claim_put(
+            &mut connection,
+            "build",
+            "core/a",
+            "w2",
+            later,
+            later + lease
+        )
+        .unwrap());
+        // The first worker lost the claim, it can neither renew nor release it
+        assert!(
+            !build_claim_renew(&mut connection, "build", "core/a", "w1", later + lease).unwrap()
+        );
+        assert!(build_claim_release(
+            &mut connection,
+            "build",
+            "core/a",
+            "w1",
+            &BuildClaimStatus::Failed,
+            later
+        )
+        .is_err());
+        assert!(
+            build_claim_renew(&mut connection, "build", "core/a", "w2", later + lease).unwrap()
+        );
+        build_claim_release(
+            &mut connection,
+            "build",
+            "core/a",
+            "w2",
+            &BuildClaimStatus::Failed,
+            later,
+        )
+        .unwrap();
+        assert_eq!(
+            claim_status(&mut connection, "core/a"),
+            BuildClaimStatus::Failed
+        );
+    }
+
+    #[test]
+    fn build_claim_release_requires_an_active_claim() {
+        let mut connection = test_connection();
+        let now = Utc::now();
+        let lease = Duration::seconds(60);
+        let artifact_ident = test_artifact_ident();
+        assert!(build_claim_release(
+            &mut connection,
+            "build",
+            "core/a",
+            "w1",
+            &BuildClaimStatus::Failed,
+            now
+        )
+        .is_err());
+        build_claim_put(&mut connection, "build", "core/a", "w1", now, now + lease).unwrap();
+        build_claim_release(
+            &mut connection,
+            "build",
+            "core/a",
+            "w1",
+            &BuildClaimStatus::Completed(artifact_ident.clone()),
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            claim_status(&mut connection, "core/a"),
+            BuildClaimStatus::Completed(artifact_ident)
+        );
+        // A released claim can not be released a second time
+        assert!(build_claim_release(
+            &mut connection,
+            "build",
+            "core/a",
+            "w1",
+            &BuildClaimStatus::Failed,
+            now
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn build_claims_failed_delete_only_removes_failed_claims() {
+        let mut connection = test_connection();
+        let now = Utc::now();
+        let lease = Duration::seconds(60);
+        let artifact_ident = test_artifact_ident();
+        for plan_value in ["core/a", "core/b", "core/c"] {
+            build_claim_put(&mut connection, "build", plan_value, "w1", now, now + lease).unwrap();
+        }
+        build_claim_put(&mut connection, "other", "core/b", "w1", now, now + lease).unwrap();
+        build_claim_release(
+            &mut connection,
+            "build",
+            "core/a",
+            "w1",
+            &BuildClaimStatus::Completed(artifact_ident),
+            now,
+        )
+        .unwrap();
+        for build_id_value in ["build", "other"] {
+            build_claim_release(
+                &mut connection,
+                build_id_value,
+                "core/b",
+                "w1",
+                &BuildClaimStatus::Failed,
+                now,
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            build_claims_failed_delete(&mut connection, "build").unwrap(),
+            1
+        );
+        let mut plans = build_claims_get(&mut connection, "build")
+            .unwrap()
+            .into_iter()
+            .map(|claim| claim.plan)
+            .collect::<Vec<_>>();
+        plans.sort();
+        assert_eq!(plans, vec!["core/a", "core/c"]);
+        assert_eq!(build_claims_get(&mut connection, "other").unwrap().len(), 1);
+        // The step of the failed claim can be claimed again
+        assert!(
+            build_claim_put(&mut connection, "build", "core/b", "w2", now, now + lease).unwrap()
+        );
+    }
+
+    #[test]
+    fn build_claims_prune_removes_old_released_and_expired_claims() {
+        let mut connection = test_connection();
+        let now = Utc::now();
+        let lease = Duration::seconds(60);
+        let long_ago = now - Duration::days(30);
+        // Released long ago
+        build_claim_put(
+            &mut connection,
+            "build",
+            "core/a",
+            "w1",
+            long_ago,
+            long_ago + lease,
+        )
+        .unwrap();
+        build_claim_release(
+            &mut connection,
+            "build",
+            "core/a",
+            "w1",
+            &BuildClaimStatus::Failed,
+            long_ago,
+        )
+        .unwrap();
+        // Lease expired long ago
+        build_claim_put(
+            &mut connection,
+            "build",
+            "core/b",
+            "w1",
+            long_ago,
+            long_ago + lease,
+        )
+        .unwrap();
+        // Released recently
+        build_claim_put(&mut connection, "build", "core/c", "w1", now, now + lease).unwrap();
+        build_claim_release(
+            &mut connection,
+            "build",
+            "core/c",
+            "w1",
+            &BuildClaimStatus::Failed,
+            now,
+        )
+        .unwrap();
+        // Still active
+        build_claim_put(&mut connection, "build", "core/d", "w1", now, now + lease).unwrap();
+        assert_eq!(
+            build_claims_prune(&mut connection, now - Duration::days(7)).unwrap(),
+            2
+        );
+        let mut plans = build_claims_get(&mut connection, "build")
+            .unwrap()
+            .into_iter()
+            .map(|claim| claim.plan)
+            .collect::<Vec<_>>();
+        plans.sort();
+        assert_eq!(plans, vec!["core/c", "core/d"]);
+    }
+}
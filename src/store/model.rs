@@ -1,16 +1,20 @@
-use diesel::Queryable;
+use diesel::{Queryable, QueryableByName};
 
 #[derive(Debug, Queryable)]
 pub struct FileModificationRecord {
+    #[allow(dead_code)]
+    pub workspace: String,
     pub plan_context_path: String,
     pub file_path: String,
     pub real_modified_at: String,
     pub alternate_modified_at: String,
+    pub synced_from_modified_at: Option<String>,
 }
 
 #[derive(Debug, Queryable)]
 pub struct BuildTimeRecord {
     #[allow(dead_code)]
+    pub workspace: String,
     pub build_ident: String,
     pub duration_in_secs: i32,
 }
@@ -20,6 +24,7 @@ pub struct ArtifactContextRecord {
     #[allow(dead_code)]
     pub hash: String,
     pub context: String,
+    pub version: i32,
 }
 
 #[derive(Debug, Queryable)]
@@ -28,3 +33,96 @@ pub struct SourceContextRecord {
     pub hash: String,
     pub context: String,
 }
+
+#[derive(Debug, Queryable)]
+pub struct BuildHostRecord {
+    #[allow(dead_code)]
+    pub artifact_ident: String,
+    pub host: String,
+}
+
+#[derive(Debug, Queryable)]
+pub struct BuildSessionArtifactRecord {
+    #[allow(dead_code)]
+    pub workspace: String,
+    #[allow(dead_code)]
+    pub session_id: String,
+    pub artifact: String,
+    pub error_count: i32,
+    #[allow(dead_code)]
+    pub artifact_path: Option<String>,
+    pub warning_count: i32,
+}
+
+#[derive(Debug, Queryable)]
+pub struct BuildSessionRecord {
+    pub id: i32,
+    #[allow(dead_code)]
+    pub workspace: String,
+    pub session_id: String,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub planned: i32,
+    pub attempted: i32,
+    pub succeeded: i32,
+    pub failed: i32,
+    pub error_count: i32,
+    pub warning_count: i32,
+}
+
+#[derive(Debug, Queryable)]
+pub struct SourceDownloadUrlRecord {
+    #[allow(dead_code)]
+    pub hash: String,
+    pub url: String,
+}
+
+#[derive(Debug, Queryable)]
+pub struct BuildClaimRecord {
+    #[allow(dead_code)]
+    pub build_id: String,
+    pub plan: String,
+    pub worker: String,
+    pub status: String,
+    pub artifact: Option<String>,
+    pub lease_expires_at: String,
+    #[allow(dead_code)]
+    pub released_at: Option<String>,
+}
+
+#[derive(Debug, Queryable)]
+pub struct BuildStepStateRecord {
+    #[allow(dead_code)]
+    pub workspace: String,
+    #[allow(dead_code)]
+    pub session_id: String,
+    pub plan: String,
+    pub status: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Queryable)]
+pub struct PlanContextHashRecord {
+    pub artifact_ident: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Queryable)]
+pub struct PlanScanRecord {
+    pub plan_path: String,
+    pub hash: String,
+    pub data: String,
+}
+
+#[derive(Debug, Queryable)]
+pub struct YankedArtifactRecord {
+    pub artifact: String,
+    pub reason: Option<String>,
+    pub yanked_at: String,
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct IntegrityCheckRecord {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub integrity_check: String,
+}
@@ -4,25 +4,138 @@ diesel::table! {
     artifact_contexts (hash) {
         hash -> Text,
         context -> Text,
+        version -> Integer,
     }
 }
 
 diesel::table! {
-    file_modifications (plan_context_path, file_path) {
+    artifact_files (hash) {
+        hash -> Text,
+        files -> Text,
+    }
+}
+
+diesel::table! {
+    artifact_sizes (artifact) {
+        artifact -> Text,
+        installed_size -> BigInt,
+        closure_size -> BigInt,
+    }
+}
+
+diesel::table! {
+    file_modifications (workspace, plan_context_path, file_path) {
+        workspace -> Text,
         plan_context_path -> Text,
         file_path -> Text,
         real_modified_at -> Text,
         alternate_modified_at -> Text,
+        synced_from_modified_at -> Nullable<Text>,
     }
 }
 
 diesel::table! {
-    build_times (build_ident) {
+    build_times (workspace, build_ident) {
+        workspace -> Text,
         build_ident -> Text,
         duration_in_secs -> Integer,
     }
 }
 
+diesel::table! {
+    build_disk_usages (workspace, package) {
+        workspace -> Text,
+        package -> Text,
+        disk_usage -> BigInt,
+    }
+}
+
+diesel::table! {
+    build_hosts (artifact_ident) {
+        artifact_ident -> Text,
+        host -> Text,
+    }
+}
+
+diesel::table! {
+    build_session_artifacts (workspace, session_id, artifact) {
+        workspace -> Text,
+        session_id -> Text,
+        artifact -> Text,
+        error_count -> Integer,
+        artifact_path -> Nullable<Text>,
+        warning_count -> Integer,
+    }
+}
+
+diesel::table! {
+    build_sessions (id) {
+        id -> Integer,
+        workspace -> Text,
+        session_id -> Text,
+        started_at -> Text,
+        ended_at -> Nullable<Text>,
+        planned -> Integer,
+        attempted -> Integer,
+        succeeded -> Integer,
+        failed -> Integer,
+        error_count -> Integer,
+        warning_count -> Integer,
+    }
+}
+
+diesel::table! {
+    build_claims (build_id, plan) {
+        build_id -> Text,
+        plan -> Text,
+        worker -> Text,
+        status -> Text,
+        artifact -> Nullable<Text>,
+        lease_expires_at -> Text,
+        released_at -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    build_step_states (workspace, session_id, plan) {
+        workspace -> Text,
+        session_id -> Text,
+        plan -> Text,
+        status -> Text,
+        updated_at -> Text,
+    }
+}
+
+diesel::table! {
+    plan_context_hashes (artifact_ident) {
+        artifact_ident -> Text,
+        hash -> Text,
+    }
+}
+
+diesel::table! {
+    plan_scans (plan_path) {
+        plan_path -> Text,
+        hash -> Text,
+        data -> Text,
+    }
+}
+
+diesel::table! {
+    source_download_urls (hash) {
+        hash -> Text,
+        url -> Text,
+    }
+}
+
+diesel::table! {
+    source_license_scans (hash, scanner) {
+        hash -> Text,
+        scanner -> Text,
+        licenses -> Text,
+    }
+}
+
 diesel::table! {
     source_contexts (hash) {
         hash -> Text,
@@ -30,8 +143,25 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    vulnerability_queries (query) {
+        query -> Text,
+        vulnerabilities -> Text,
+        fetched_at -> Text,
+    }
+}
+
+diesel::table! {
+    yanked_artifacts (artifact) {
+        artifact -> Text,
+        reason -> Nullable<Text>,
+        yanked_at -> Text,
+    }
+}
+
 diesel::allow_tables_to_appear_in_same_query!(
     artifact_contexts,
     file_modifications,
     source_contexts,
+    source_download_urls,
 );